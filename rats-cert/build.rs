@@ -46,6 +46,16 @@ fn main() {
 
         strip_inner_attribute(&aa_dir.join("attestation_agent.rs"));
         strip_inner_attribute(&aa_dir.join("attestation_agent_ttrpc.rs"));
+
+        // Build for connecting AA with Grpc
+        let aa_grpc_dir = out_dir.join("attestation-agent").join("grpc_protocol");
+        let _ = std::fs::create_dir_all(&aa_grpc_dir); // This will panic below if the directory failed to create
+        tonic_prost_build::configure()
+            .out_dir(aa_grpc_dir)
+            .build_server(false)
+            .build_client(true)
+            .compile_protos(&protos, &["src/tee/coco/protos"])
+            .expect("Generate grpc protocol code failed.");
     }
 
     #[cfg(feature = "verifier-coco")]
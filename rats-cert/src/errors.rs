@@ -41,6 +41,12 @@ pub enum Error {
     #[error("Policy evaluation failed for policy_id `{policy_id}`")]
     PolicyEvaluationFailed { policy_id: String },
 
+    #[error("Claim requirement not met")]
+    ClaimRequirementNotMet(#[source] anyhow::Error),
+
+    #[error("Evidence is too old: issued {age} seconds ago, exceeding the maximum age of {max_age} seconds")]
+    EvidenceTooOld { age: u64, max_age: u64 },
+
     // Remote AS related
     #[error("Remote AS gRPC is not supported")]
     RemoteAsGrpcNotSupported,
@@ -81,6 +87,31 @@ pub enum Error {
     #[error("Failed to connect to Attestation Agent ttrpc endpoint")]
     ConnectAttestationAgentTtrpcFailed(#[source] ttrpc::Error),
 
+    // AA gRPC related errors
+    #[cfg(feature = "attester-coco")]
+    #[error("Failed to create gRPC endpoint for AA address `{aa_addr}`")]
+    AttestationAgentGrpcEndpointCreateFailed {
+        aa_addr: String,
+        #[source]
+        source: tonic::transport::Error,
+    },
+
+    #[cfg(feature = "attester-coco")]
+    #[error("Failed to connect to gRPC AA address `{aa_addr}`")]
+    ConnectAttestationAgentGrpcFailed {
+        aa_addr: String,
+        #[source]
+        source: tonic::transport::Error,
+    },
+
+    #[cfg(feature = "attester-coco")]
+    #[error("Failed to get evidence from Attestation Agent via gRPC")]
+    GetEvidenceFromAAGrpcFailed(#[source] tonic::Status),
+
+    #[cfg(feature = "attester-coco")]
+    #[error("Failed to get TEE type from Attestation Agent via gRPC")]
+    GetTeeTypeFromAAGrpcFailed(#[source] tonic::Status),
+
     #[error("Coco token verifier error")]
     CocoTokenVerifierError(#[source] anyhow::Error),
 
@@ -410,3 +441,25 @@ pub enum Error {
         response_body: String,
     },
 }
+
+impl Error {
+    /// Whether a request to the attestation service failed without an answer from it, i.e. the
+    /// attestation service could not be connected to, or did not answer in time.
+    pub fn is_attestation_service_unreachable(&self) -> bool {
+        match self {
+            Error::AttestationServiceChallengeHttpRequestSendFailed(_, _)
+            | Error::AttestationServiceAttestationHttpRequestSendFailed(_, _)
+            | Error::AttestationServiceChallengeHttpResponseReadFailed(_, _)
+            | Error::AttestationServiceAttestationHttpResponseReadFailed(_, _) => true,
+            #[cfg(not(wasm))]
+            Error::GrpcConnectFailed { .. } => true,
+            Error::AttestationServiceGrpcAttestationEvaluateFailed(_, status) => matches!(
+                status.code(),
+                tonic::Code::Unavailable | tonic::Code::DeadlineExceeded
+            ),
+            #[cfg(any(feature = "attester-ita", feature = "verifier-ita"))]
+            Error::ItaHttpRequestFailed { .. } => true,
+            _ => false,
+        }
+    }
+}
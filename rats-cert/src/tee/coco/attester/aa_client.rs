@@ -1,3 +1,7 @@
+use std::time::Duration;
+
+use super::grpc_protocol;
+use super::grpc_protocol::attestation_agent_service_client::AttestationAgentServiceClient as AttestationAgentServiceGrpcClient;
 use super::ttrpc_protocol::attestation_agent::{
     GetAdditionalEvidenceRequest, GetEvidenceRequest, GetTeeTypeRequest,
 };
@@ -5,53 +9,124 @@ use super::ttrpc_protocol::attestation_agent_ttrpc::AttestationAgentServiceClien
 use crate::errors::*;
 use crate::tee::coco::TTRPC_DEFAULT_TIMEOUT_NANO;
 
-/// Shared low-level client for the CoCo Attestation Agent ttrpc service.
+/// Shared low-level client for the CoCo Attestation Agent service.
 ///
 /// Both `CocoAttester` and `ItaAttester` talk to the same AA daemon; this
 /// struct encapsulates the connection and raw RPC calls so the attesters
 /// only need to implement their provider-specific REPORTDATA derivation
 /// and evidence construction.
+///
+/// The flavor of the AA API is selected by the scheme of the AA address:
+/// `http://` addresses (e.g. `http://127.0.0.1:50002`) are served over gRPC,
+/// and any other address (e.g. `unix:///run/.../attestation-agent.sock`) over ttrpc.
 pub(crate) struct AaClient {
-    client: AttestationAgentServiceClient,
+    transport: AaTransport,
     timeout_nano: i64,
 }
 
+enum AaTransport {
+    Ttrpc(AttestationAgentServiceClient),
+    /// The gRPC connection is established for each request, as the AA is only
+    /// queried when new evidence is needed.
+    Grpc {
+        aa_addr: String,
+        endpoint: tonic::transport::Endpoint,
+    },
+}
+
 impl AaClient {
     pub fn new(aa_addr: &str) -> Result<Self> {
         Self::new_with_timeout(aa_addr, TTRPC_DEFAULT_TIMEOUT_NANO)
     }
 
     pub fn new_with_timeout(aa_addr: &str, timeout_nano: i64) -> Result<Self> {
-        let inner =
-            ttrpc::Client::connect(aa_addr).map_err(Error::ConnectAttestationAgentTtrpcFailed)?;
-        let client = AttestationAgentServiceClient::new(inner);
+        let transport = if aa_addr.starts_with("http://") {
+            let timeout = Duration::from_nanos(timeout_nano.max(0) as u64);
+            let endpoint = tonic::transport::Endpoint::new(aa_addr.to_string())
+                .map_err(|e| Error::AttestationAgentGrpcEndpointCreateFailed {
+                    aa_addr: aa_addr.to_string(),
+                    source: e,
+                })?
+                .connect_timeout(timeout)
+                .timeout(timeout);
+            AaTransport::Grpc {
+                aa_addr: aa_addr.to_string(),
+                endpoint,
+            }
+        } else {
+            let inner = ttrpc::Client::connect(aa_addr)
+                .map_err(Error::ConnectAttestationAgentTtrpcFailed)?;
+            AaTransport::Ttrpc(AttestationAgentServiceClient::new(inner))
+        };
         Ok(Self {
-            client,
+            transport,
             timeout_nano,
         })
     }
 
+    async fn grpc_client(
+        aa_addr: &str,
+        endpoint: &tonic::transport::Endpoint,
+    ) -> Result<AttestationAgentServiceGrpcClient<tonic::transport::Channel>> {
+        let channel =
+            endpoint
+                .connect()
+                .await
+                .map_err(|e| Error::ConnectAttestationAgentGrpcFailed {
+                    aa_addr: aa_addr.to_string(),
+                    source: e,
+                })?;
+        Ok(AttestationAgentServiceGrpcClient::new(channel))
+    }
+
     /// Request a TEE evidence quote from the AA with the given runtime_data_hash_value bytes.
-    pub fn get_evidence(&self, runtime_data_hash_value: Vec<u8>) -> Result<Vec<u8>> {
-        let req = GetEvidenceRequest {
-            RuntimeData: runtime_data_hash_value,
-            ..Default::default()
-        };
-        let res = self
-            .client
-            .get_evidence(ttrpc::context::with_timeout(self.timeout_nano), &req)
-            .map_err(Error::GetEvidenceFromAAFailed)?;
-        Ok(res.Evidence)
+    pub async fn get_evidence(&self, runtime_data_hash_value: Vec<u8>) -> Result<Vec<u8>> {
+        match &self.transport {
+            AaTransport::Ttrpc(client) => {
+                let req = GetEvidenceRequest {
+                    RuntimeData: runtime_data_hash_value,
+                    ..Default::default()
+                };
+                let res = client
+                    .get_evidence(ttrpc::context::with_timeout(self.timeout_nano), &req)
+                    .map_err(Error::GetEvidenceFromAAFailed)?;
+                Ok(res.Evidence)
+            }
+            AaTransport::Grpc { aa_addr, endpoint } => {
+                let req = grpc_protocol::GetEvidenceRequest {
+                    runtime_data: runtime_data_hash_value,
+                };
+                let res = Self::grpc_client(aa_addr, endpoint)
+                    .await?
+                    .get_evidence(req)
+                    .await
+                    .map_err(Error::GetEvidenceFromAAGrpcFailed)?
+                    .into_inner();
+                Ok(res.evidence)
+            }
+        }
     }
 
     /// Query the TEE type string from the AA (e.g. "tdx", "snp").
-    pub fn get_tee_type(&self) -> Result<String> {
-        let req = GetTeeTypeRequest::default();
-        let res = self
-            .client
-            .get_tee_type(ttrpc::context::with_timeout(self.timeout_nano), &req)
-            .map_err(Error::GetTeeTypeFromAAFailed)?;
-        Ok(res.tee)
+    pub async fn get_tee_type(&self) -> Result<String> {
+        match &self.transport {
+            AaTransport::Ttrpc(client) => {
+                let req = GetTeeTypeRequest::default();
+                let res = client
+                    .get_tee_type(ttrpc::context::with_timeout(self.timeout_nano), &req)
+                    .map_err(Error::GetTeeTypeFromAAFailed)?;
+                Ok(res.tee)
+            }
+            AaTransport::Grpc { aa_addr, endpoint } => {
+                let res = Self::grpc_client(aa_addr, endpoint)
+                    .await?
+                    .get_tee_type(grpc_protocol::GetTeeTypeRequest {})
+                    .await
+                    .map_err(Error::GetTeeTypeFromAAGrpcFailed)?
+                    .into_inner();
+                Ok(res.tee)
+            }
+        }
     }
 
     /// Request additional device evidence (e.g. GPU attestation) from the AA.
@@ -59,16 +134,37 @@ impl AaClient {
     /// Returns `Ok(None)` when the AA does not support additional evidence or
     /// when the response is empty. Never fails the caller — unsupported RPCs
     /// are logged and swallowed.
-    pub fn get_additional_evidence(&self, runtime_data_hash_value: Vec<u8>) -> Option<Vec<u8>> {
-        let req = GetAdditionalEvidenceRequest {
-            RuntimeData: runtime_data_hash_value,
-            ..Default::default()
+    pub async fn get_additional_evidence(
+        &self,
+        runtime_data_hash_value: Vec<u8>,
+    ) -> Option<Vec<u8>> {
+        let result = match &self.transport {
+            AaTransport::Ttrpc(client) => {
+                let req = GetAdditionalEvidenceRequest {
+                    RuntimeData: runtime_data_hash_value,
+                    ..Default::default()
+                };
+                client
+                    .get_additional_evidence(ttrpc::context::with_timeout(self.timeout_nano), &req)
+                    .map(|res| res.Evidence)
+                    .map_err(anyhow::Error::from)
+            }
+            AaTransport::Grpc { aa_addr, endpoint } => {
+                let req = grpc_protocol::GetAdditionalEvidenceRequest {
+                    runtime_data: runtime_data_hash_value,
+                };
+                match Self::grpc_client(aa_addr, endpoint).await {
+                    Ok(mut client) => client
+                        .get_additional_evidence(req)
+                        .await
+                        .map(|res| res.into_inner().evidence)
+                        .map_err(anyhow::Error::from),
+                    Err(error) => Err(error.into()),
+                }
+            }
         };
-        match self
-            .client
-            .get_additional_evidence(ttrpc::context::with_timeout(self.timeout_nano), &req)
-        {
-            Ok(res) if !res.Evidence.is_empty() => Some(res.Evidence),
+        match result {
+            Ok(evidence) if !evidence.is_empty() => Some(evidence),
             Ok(_) => None,
             Err(error) => {
                 tracing::warn!(
@@ -80,3 +176,32 @@ impl AaClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aa_client_grpc_addr() -> Result<()> {
+        // No connection is made until the first request over gRPC
+        let aa = AaClient::new("http://127.0.0.1:50002")?;
+        assert!(matches!(aa.transport, AaTransport::Grpc { .. }));
+
+        assert!(matches!(
+            AaClient::new("http://[invalid"),
+            Err(Error::AttestationAgentGrpcEndpointCreateFailed { .. })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_aa_client_grpc_unreachable() -> Result<()> {
+        let aa = AaClient::new_with_timeout("http://127.0.0.1:1", 1000 * 1000 * 1000)?;
+        assert!(matches!(
+            aa.get_evidence(vec![0; 48]).await,
+            Err(Error::ConnectAttestationAgentGrpcFailed { .. })
+        ));
+        assert_eq!(aa.get_additional_evidence(vec![]).await, None);
+        Ok(())
+    }
+}
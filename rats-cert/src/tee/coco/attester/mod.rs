@@ -8,6 +8,13 @@ use crate::tee::{
 pub(crate) mod aa_client;
 mod ttrpc_protocol;
 
+mod grpc_protocol {
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/attestation-agent/grpc_protocol/attestation_agent.rs"
+    ));
+}
+
 pub(crate) use aa_client::AaClient;
 
 pub struct CocoAttester {
@@ -42,13 +49,13 @@ impl GenericAttester for CocoAttester {
         let aa_runtime_data_hash_value =
             DefaultCrypto::hash(aa_runtime_data_hash_algo, &aa_runtime_data_bytes);
 
-        let evidence = self.aa.get_evidence(aa_runtime_data_hash_value)?;
+        let evidence = self.aa.get_evidence(aa_runtime_data_hash_value).await?;
 
-        let tee_type_str = self.aa.get_tee_type()?;
+        let tee_type_str = self.aa.get_tee_type().await?;
         let tee_type = tee_from_str(&tee_type_str)?;
 
         // Attempt to get additional evidence from AA, but don't fail if not supported
-        let additional_evidence = self.aa.get_additional_evidence(Vec::new());
+        let additional_evidence = self.aa.get_additional_evidence(Vec::new()).await;
 
         Ok(CocoEvidence::new(
             tee_type,
@@ -9,7 +9,7 @@ use super::super::evidence::{
     tee_to_string, AttestationServiceHashAlgo, CocoAsToken, CocoEvidence,
 };
 use crate::errors::*;
-use crate::tee::coco::converter::{convert_additional_evidence, CoCoNonce};
+use crate::tee::coco::converter::{convert_additional_evidence, AsRequestConfig, CoCoNonce};
 use crate::tee::GenericConverter;
 
 mod as_api {
@@ -38,6 +38,7 @@ pub struct CocoGrpcConverter {
     as_addr: String,
     policy_ids: Vec<String>,
     request_metadata: tonic::metadata::MetadataMap,
+    request_config: AsRequestConfig,
 }
 
 impl CocoGrpcConverter {
@@ -45,6 +46,7 @@ impl CocoGrpcConverter {
         as_addr: &str,
         policy_ids: &Vec<String>,
         as_headers: &HashMap<String, String>,
+        request_config: &AsRequestConfig,
     ) -> Result<Self> {
         let mut request_metadata = tonic::metadata::MetadataMap::new();
         for (key, value) in as_headers {
@@ -60,6 +62,7 @@ impl CocoGrpcConverter {
             as_addr: as_addr.to_string(),
             policy_ids: policy_ids.to_owned(),
             request_metadata,
+            request_config: request_config.clone(),
         })
     }
 
@@ -67,6 +70,18 @@ impl CocoGrpcConverter {
     pub fn as_addr(&self) -> &str {
         &self.as_addr
     }
+
+    fn new_request<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::from_parts(
+            self.request_metadata.clone(),
+            tonic::Extensions::new(),
+            message,
+        );
+        if let Some(timeout) = self.request_config.timeout {
+            request.set_timeout(timeout);
+        }
+        request
+    }
 }
 
 #[cfg_attr(wasm, async_trait::async_trait(?Send))]
@@ -82,13 +97,17 @@ impl GenericConverter for CocoGrpcConverter {
             self.policy_ids
         );
 
-        match self.convert_v1_6_0(in_evidence).await {
-            Ok(v) => Ok(v),
-            Err(error) => {
-                tracing::warn!(?error, "Failed to convert CoCo evidence to CoCo AS token via grpc-as, try to convert with old grpc-as version");
-                self.convert_v1_5_2(in_evidence).await
-            }
-        }
+        self.request_config
+            .send("AttestationEvaluate", || async {
+                match self.convert_v1_6_0(in_evidence).await {
+                    Ok(v) => Ok(v),
+                    Err(error) => {
+                        tracing::warn!(?error, "Failed to convert CoCo evidence to CoCo AS token via grpc-as, try to convert with old grpc-as version");
+                        self.convert_v1_5_2(in_evidence).await
+                    }
+                }
+            })
+            .await
     }
 
     async fn get_nonce(&self) -> Result<Self::Nonce> {
@@ -107,9 +126,7 @@ impl CocoGrpcConverter {
         let runtime_data_hash_algorithm =
             AttestationServiceHashAlgo::from(in_evidence.get_aa_runtime_data_hash_algo()).str_id();
 
-        let request = tonic::Request::from_parts(
-            self.request_metadata.clone(),
-            tonic::Extensions::new(),
+        let request = self.new_request(
     as_api::v1_6_0::AttestationRequest {
             verification_requests: std::iter::once(Ok(as_api::v1_6_0::IndividualAttestationRequest {
                 tee: tee_to_string(*in_evidence.get_tee_type())?,
@@ -147,6 +164,10 @@ impl CocoGrpcConverter {
                             as_addr: self.as_addr.clone(),
                             source: e,
                         })?;
+                    let endpoint = match self.request_config.timeout {
+                        Some(timeout) => endpoint.connect_timeout(timeout),
+                        None => endpoint,
+                    };
                     as_api::v1_6_0::attestation_service_client::AttestationServiceClient::new(
                         endpoint
                             .connect()
@@ -186,23 +207,19 @@ impl CocoGrpcConverter {
         let runtime_data_hash_algorithm =
             AttestationServiceHashAlgo::from(in_evidence.get_aa_runtime_data_hash_algo()).str_id();
 
-        let request = tonic::Request::from_parts(
-            self.request_metadata.clone(),
-            tonic::Extensions::new(),
-            as_api::v1_5_2::AttestationRequest {
-                tee: tee_to_string(*in_evidence.get_tee_type())?,
-                evidence: URL_SAFE_NO_PAD.encode(in_evidence.aa_evidence_ref()),
-                init_data: None, // TODO: add support for init_data when support on AA is ready
-                init_data_hash_algorithm: "".into(),
-                policy_ids: self.policy_ids.clone(),
-                runtime_data: Some(
-                    as_api::v1_5_2::attestation_request::RuntimeData::StructuredRuntimeData(
-                        in_evidence.aa_runtime_data_ref().into(),
-                    ),
+        let request = self.new_request(as_api::v1_5_2::AttestationRequest {
+            tee: tee_to_string(*in_evidence.get_tee_type())?,
+            evidence: URL_SAFE_NO_PAD.encode(in_evidence.aa_evidence_ref()),
+            init_data: None, // TODO: add support for init_data when support on AA is ready
+            init_data_hash_algorithm: "".into(),
+            policy_ids: self.policy_ids.clone(),
+            runtime_data: Some(
+                as_api::v1_5_2::attestation_request::RuntimeData::StructuredRuntimeData(
+                    in_evidence.aa_runtime_data_ref().into(),
                 ),
-                runtime_data_hash_algorithm: runtime_data_hash_algorithm.into(),
-            },
-        );
+            ),
+            runtime_data_hash_algorithm: runtime_data_hash_algorithm.into(),
+        });
 
         let mut client =
             {
@@ -213,6 +230,10 @@ impl CocoGrpcConverter {
                             as_addr: self.as_addr.clone(),
                             source: e,
                         })?;
+                    let endpoint = match self.request_config.timeout {
+                        Some(timeout) => endpoint.connect_timeout(timeout),
+                        None => endpoint,
+                    };
                     as_api::v1_5_2::attestation_service_client::AttestationServiceClient::new(
                         endpoint
                             .connect()
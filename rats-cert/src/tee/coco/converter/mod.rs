@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
 use grpc::CocoGrpcConverter;
 use kbs_types::Tee;
@@ -8,6 +9,7 @@ use super::evidence::{AttestationServiceHashAlgo, CocoAsToken, CocoEvidence};
 use crate::errors::*;
 #[cfg(feature = "__builtin-as")]
 use crate::tee::coco::converter::builtin::BuiltinCocoConverter;
+use crate::tee::retry::RetryPolicy;
 use crate::tee::GenericConverter;
 
 #[cfg(feature = "__builtin-as")]
@@ -34,6 +36,81 @@ impl CocoConverter {
     }
 }
 
+/// How the requests to a remote attestation service are sent.
+#[derive(Debug, Clone)]
+pub struct AsRequestConfig {
+    /// The timeout of each request. No timeout is applied if `None`.
+    pub timeout: Option<Duration>,
+    /// The maximum number of retries after the first attempt, when the request fails with a
+    /// transient error.
+    pub max_retries: usize,
+    /// The delay before the first retry. It is doubled after each retry.
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for AsRequestConfig {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl AsRequestConfig {
+    pub(crate) fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::exponential(self.initial_backoff)
+            .with_max_delay(self.max_backoff)
+            .with_max_retries(self.max_retries)
+    }
+
+    /// Sends a request to the attestation service with `task`, retrying it on transient errors.
+    pub(crate) async fn send<F, Fut, T>(&self, label: &str, task: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        self.retry_policy()
+            .retry_if(task, |error| {
+                let transient = is_transient_as_error(error);
+                if transient {
+                    tracing::warn!(
+                        ?error,
+                        "{label} request to attestation service failed (retrying)"
+                    );
+                }
+                transient
+            })
+            .await
+    }
+}
+
+/// Whether the error of a request to the attestation service is likely to go away if the request
+/// is retried, i.e. the attestation service is unreachable, too slow, or failed internally.
+fn is_transient_as_error(error: &Error) -> bool {
+    match error {
+        Error::AttestationServiceChallengeHttpRequestSendFailed(_, _)
+        | Error::AttestationServiceAttestationHttpRequestSendFailed(_, _)
+        | Error::AttestationServiceChallengeHttpResponseReadFailed(_, _)
+        | Error::AttestationServiceAttestationHttpResponseReadFailed(_, _) => true,
+        Error::AttestationServiceChallengeHttpResponseError { status_code, .. }
+        | Error::AttestationServiceAttestationHttpResponseError { status_code, .. } => {
+            *status_code >= 500
+        }
+        #[cfg(not(wasm))]
+        Error::GrpcConnectFailed { .. } => true,
+        Error::AttestationServiceGrpcAttestationEvaluateFailed(_, status) => matches!(
+            status.code(),
+            tonic::Code::Unavailable | tonic::Code::DeadlineExceeded
+        ),
+        _ => false,
+    }
+}
+
 pub enum CoCoNonce {
     Jwt(String),
 }
@@ -15,6 +15,7 @@ use serde_json::Value;
 use super::super::evidence::{AttestationServiceHashAlgo, CocoAsToken, CocoEvidence};
 use crate::errors::*;
 use crate::tee::coco::converter::convert_additional_evidence;
+use crate::tee::coco::converter::AsRequestConfig;
 use crate::tee::coco::converter::CoCoNonce;
 use crate::tee::GenericConverter;
 
@@ -31,6 +32,7 @@ pub struct CocoRestfulConverter {
     as_addr: String,
     policy_ids: Vec<String>,
     client: Client,
+    request_config: AsRequestConfig,
 }
 
 impl CocoRestfulConverter {
@@ -38,6 +40,7 @@ impl CocoRestfulConverter {
         as_addr: &str,
         policy_ids: &Vec<String>,
         as_headers: &HashMap<String, String>,
+        request_config: &AsRequestConfig,
     ) -> Result<Self> {
         let mut headers = reqwest::header::HeaderMap::new();
         for (k, v) in as_headers {
@@ -54,8 +57,14 @@ impl CocoRestfulConverter {
                 .user_agent(format!("rats-rs/{}", env!("CARGO_PKG_VERSION")));
             builder = builder.default_headers(headers);
             #[cfg(unix)]
-            let builder =
-                builder.connect_timeout(Duration::from_secs(RESTFUL_AS_CONNECT_TIMEOUT_DEFAULT));
+            {
+                builder = builder
+                    .connect_timeout(Duration::from_secs(RESTFUL_AS_CONNECT_TIMEOUT_DEFAULT));
+            }
+            #[cfg(not(wasm))]
+            if let Some(timeout) = request_config.timeout {
+                builder = builder.timeout(timeout);
+            }
             builder
                 .build()
                 .map_err(Error::AttestationServiceHttpClientBuildFailed)?
@@ -65,6 +74,7 @@ impl CocoRestfulConverter {
             as_addr: as_addr.trim_end_matches('/').to_owned(),
             client,
             policy_ids: policy_ids.to_owned(),
+            request_config: request_config.clone(),
         })
     }
 
@@ -146,10 +156,22 @@ impl GenericConverter for CocoRestfulConverter {
             self.policy_ids
         );
 
-        self.convert_v1_6_0_or_fallback(in_evidence).await
+        self.request_config
+            .send("/attestation", || {
+                self.convert_v1_6_0_or_fallback(in_evidence)
+            })
+            .await
     }
 
     async fn get_nonce(&self) -> Result<Self::Nonce> {
+        self.request_config
+            .send("/challenge", || self.get_nonce_v1_6_0())
+            .await
+    }
+}
+
+impl CocoRestfulConverter {
+    async fn get_nonce_v1_6_0(&self) -> Result<CoCoNonce> {
         tracing::debug!("Connect to restful-as with protobuf version 1.6.0");
 
         let url = format!("{}/challenge", self.as_addr);
@@ -195,9 +217,7 @@ impl GenericConverter for CocoRestfulConverter {
 
         Ok(CoCoNonce::Jwt(challenge_response.extra_params.jwt))
     }
-}
 
-impl CocoRestfulConverter {
     async fn convert_v1_6_0_or_fallback(&self, in_evidence: &CocoEvidence) -> Result<CocoAsToken> {
         tracing::debug!("Connect to restful-as with protobuf version 1.6.0");
 
@@ -17,6 +17,7 @@ mod tests {
     use crate::cert::verify::AttestationServiceAddrArgs;
     use crate::tee::coco::attester::CocoAttester;
     use crate::tee::coco::converter::restful::CocoRestfulConverter;
+    use crate::tee::coco::converter::AsRequestConfig;
     use crate::tee::coco::converter::CocoConverter;
     use crate::tee::coco::verifier::remote::CocoRemoteVerifier;
     use crate::tee::coco::verifier::CocoVerifier;
@@ -49,9 +50,13 @@ mod tests {
         let attester = CocoAttester::new(TEST_AA_ADDR).expect("Failed to create attester");
 
         // Create converter (sends evidence to remote AS for verification)
-        let converter =
-            CocoRestfulConverter::new(TEST_AS_ADDR, &vec!["default".to_string()], &HashMap::new())
-                .expect("Failed to create converter");
+        let converter = CocoRestfulConverter::new(
+            TEST_AS_ADDR,
+            &vec!["default".to_string()],
+            &HashMap::new(),
+            &AsRequestConfig::default(),
+        )
+        .expect("Failed to create converter");
 
         // Create verifier (validates AS-issued token)
         let verifier = CocoRemoteVerifier::new(
@@ -91,9 +96,13 @@ mod tests {
         let attester = CocoAttester::new(TEST_AA_ADDR).expect("Failed to create attester");
 
         // Create converter (attester-side, converts evidence to token via AS)
-        let converter =
-            CocoRestfulConverter::new(TEST_AS_ADDR, &vec!["default".to_string()], &HashMap::new())
-                .expect("Failed to create converter");
+        let converter = CocoRestfulConverter::new(
+            TEST_AS_ADDR,
+            &vec!["default".to_string()],
+            &HashMap::new(),
+            &AsRequestConfig::default(),
+        )
+        .expect("Failed to create converter");
 
         // Get evidence
         let report_data = ReportData::Claims(serde_json::Map::new());
@@ -144,9 +153,13 @@ mod tests {
             .expect("Failed to create ASR attester");
 
         // Create converter (sends evidence to remote AS for verification)
-        let converter =
-            CocoRestfulConverter::new(TEST_AS_ADDR, &vec!["default".to_string()], &HashMap::new())
-                .expect("Failed to create converter");
+        let converter = CocoRestfulConverter::new(
+            TEST_AS_ADDR,
+            &vec!["default".to_string()],
+            &HashMap::new(),
+            &AsRequestConfig::default(),
+        )
+        .expect("Failed to create converter");
 
         // Create verifier (validates AS-issued token)
         let verifier = CocoRemoteVerifier::new(
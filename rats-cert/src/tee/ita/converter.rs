@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use super::retry::RetryPolicy;
+use crate::tee::retry::RetryPolicy;
 use base64::engine::general_purpose::STANDARD as BASE64;
 use base64::Engine as _;
 use reqwest::Client;
@@ -11,8 +11,6 @@ mod attester;
 mod attester_common;
 #[cfg(any(feature = "attester-ita", feature = "verifier-ita"))]
 pub mod converter;
-#[cfg(any(feature = "attester-ita", feature = "verifier-ita"))]
-mod retry;
 #[cfg(feature = "verifier-ita")]
 pub mod verifier;
 
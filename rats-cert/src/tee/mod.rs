@@ -17,6 +17,13 @@ pub mod coco;
 #[cfg(any(feature = "attester-ita", feature = "verifier-ita"))]
 pub mod ita;
 
+#[cfg(any(
+    feature = "verifier-coco",
+    feature = "attester-ita",
+    feature = "verifier-ita"
+))]
+pub(crate) mod retry;
+
 pub enum DiceParseEvidenceOutput<T> {
     NotMatch,
     MatchButInvalid(Error),
@@ -39,7 +39,21 @@ impl RetryPolicy {
     }
 
     /// Retry an async closure with exponential backoff.
-    pub async fn retry<F, Fut, T, E>(&self, mut task: F) -> Result<T, E>
+    pub async fn retry<F, Fut, T, E>(&self, task: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.retry_if(task, |_| true).await
+    }
+
+    /// Retry an async closure with exponential backoff, as long as `should_retry` accepts the
+    /// error. The other errors are returned immediately.
+    pub async fn retry_if<F, Fut, T, E>(
+        &self,
+        mut task: F,
+        mut should_retry: impl FnMut(&E) -> bool,
+    ) -> Result<T, E>
     where
         F: FnMut() -> Fut,
         Fut: Future<Output = Result<T, E>>,
@@ -48,7 +62,7 @@ impl RetryPolicy {
         for attempt in 0..=self.max_retries {
             match task().await {
                 Ok(val) => return Ok(val),
-                Err(err) if attempt == self.max_retries => return Err(err),
+                Err(err) if attempt == self.max_retries || !should_retry(&err) => return Err(err),
                 Err(_) => {
                     sleep(delay).await;
                     delay = (delay * 2).min(self.max_delay);
@@ -107,4 +121,21 @@ mod tests {
         assert_eq!(r.unwrap_err(), "e2");
         assert_eq!(calls.get(), 3);
     }
+
+    #[tokio::test]
+    async fn stops_on_non_retryable_error() {
+        let calls = Cell::new(0);
+        let r: Result<(), _> = policy()
+            .retry_if(
+                || {
+                    let n = calls.get();
+                    calls.set(n + 1);
+                    async move { Err(format!("e{n}")) }
+                },
+                |err| err != "e1",
+            )
+            .await;
+        assert_eq!(r.unwrap_err(), "e1");
+        assert_eq!(calls.get(), 2);
+    }
 }
@@ -0,0 +1,63 @@
+#!/bin/bash
+
+# Runs a command, e.g. `cargo test -p tng-testsuite --test no_ra`, as the root user of a new user
+# namespace with its own network and mount namespaces, so that the bin tests can create their
+# bridge network, network namespaces and iptables rules without sudo. Nothing is changed on the
+# host network, and the namespaces are gone once the command exits.
+#
+# Limitations: the host network is not reachable from inside (use `MockAsTask` instead of an
+# external attestation service at 192.168.1.254:8080), and the tests depending on cgroups or on
+# the podman runtime are not supported.
+
+set -euo pipefail
+
+if [[ $# -eq 0 ]]; then
+    echo "Usage: $0 <command> [args...]" >&2
+    exit 1
+fi
+
+if [[ "${TNG_TESTSUITE_ROOTLESS_INNER:-}" != "1" ]]; then
+    if [[ $(id -u) -eq 0 ]]; then
+        # Already root, nothing to do
+        exec "$@"
+    fi
+
+    if ! command -v unshare &>/dev/null; then
+        echo "unshare (util-linux) is required to run the tests without root" >&2
+        exit 1
+    fi
+
+    export TNG_TESTSUITE_ROOTLESS_INNER=1
+    exec unshare --user --map-root-user --net --mount --fork "$0" "$@"
+fi
+
+# Now in the new namespaces. The loopback interface of a new network namespace is down.
+ip link set lo up
+
+# Named network namespaces are bind-mounted under /run/netns, which the host root owns. Mount a
+# private tmpfs there, which is only visible in the new mount namespace.
+if [[ -d /run/netns ]]; then
+    mount -t tmpfs tmpfs /run/netns
+else
+    # /run/netns can't be created in /run, so replace /run with a tmpfs, and bind the original
+    # entries back (e.g. the attestation-agent socket under /run/confidential-containers).
+    orig_run=$(mktemp -d)
+    mount --rbind /run "$orig_run"
+    mount -t tmpfs tmpfs /run
+    for entry in "$orig_run"/* "$orig_run"/.[!.]*; do
+        [[ -e "$entry" || -L "$entry" ]] || continue
+        target="/run/$(basename "$entry")"
+        if [[ -L "$entry" ]]; then
+            cp -P "$entry" "$target"
+        elif [[ -d "$entry" ]]; then
+            mkdir "$target"
+            mount --rbind "$entry" "$target"
+        else
+            touch "$target"
+            mount --bind "$entry" "$target"
+        fi
+    done
+    mkdir /run/netns
+fi
+
+exec "$@"
@@ -15,6 +15,7 @@ catch() {
 ENABLE_COVERAGE=false
 INSTALL_TOOL=true
 GENERATE_REPORT=true
+RUNNER=()
 
 # Parse arguments
 while [[ $# -gt 0 ]]; do
@@ -31,6 +32,11 @@ while [[ $# -gt 0 ]]; do
             GENERATE_REPORT=false
             shift
             ;;
+        --rootless)
+            # Run the tests in user namespaces, see rootless.sh
+            RUNNER=("$(dirname "$(realpath "$0")")/rootless.sh")
+            shift
+            ;;
         -*)
             echo "Unknown option: $1" >&2
             exit 1
@@ -98,7 +104,7 @@ run_tests() {
     if $ENABLE_COVERAGE; then
         echo "cargo llvm-cov ${args[*]}"
         # Run tests first, capture output and exit code
-        cargo llvm-cov "${args[@]}" 2>&1 | tee /tmp/llvm-cov-output.log
+        "${RUNNER[@]}" cargo llvm-cov "${args[@]}" 2>&1 | tee /tmp/llvm-cov-output.log
         local test_exit=${PIPESTATUS[0]}
         # Filter out known spurious warnings that cause non-zero exit
         if [ "$test_exit" -ne 0 ]; then
@@ -112,7 +118,7 @@ run_tests() {
         return 0
     else
         echo "cargo test ${args[*]#--no-report}"
-        cargo test "${args[@]#--no-report}"
+        "${RUNNER[@]}" cargo test "${args[@]#--no-report}"
     fi
 }
 
@@ -21,8 +21,9 @@ static BIN_TEST_LOG_RELOAD_HANDLE: OnceCell<
 > = OnceCell::const_new();
 
 /// This is a common function to run bin tests. For each test, it will create many virtual nodes under
-/// a bridge network (192.168.1.0/24), at least there will be one node act as the server side, the other act as
-/// the client side. And the attestation service will be at `http://192.168.1.254:8080`.
+/// a bridge network (192.168.1.0/24, and fd00:192:168:1::/64 if IPv6 is available), at least there
+/// will be one node act as the server side, the other act as the client side. And the attestation service will be at `http://192.168.1.254:8080`, which is
+/// either an external one, or the one served by a [`task::mock_as::MockAsTask`] of the test.
 /// And all the test will be run in those two virtual nodes one by one.
 ///
 /// The `name` parameter identifies this test for structured logging (e.g. `function_name!()` or a
@@ -74,8 +75,23 @@ pub async fn run_test(name: &str, tasks: Vec<Box<dyn Task>>) -> Result<()> {
             let task_refs: Vec<&dyn Task> = tasks.iter().map(|t| t.as_ref()).collect();
             test_context::log_test_start(name, &task_refs);
 
+            if !nix::unistd::geteuid().is_root() {
+                bail!("The bin tests need root to create the network namespaces, run them with sudo or in user namespaces with `tng-testsuite/rootless.sh`");
+            }
+
             // Create a virtual network with two nodes connected to a bridge
-            let network = BridgeNetwork::new("192.168.1.254", 24).await?;
+            let mut network = BridgeNetwork::new("192.168.1.254", 24).await?;
+            // The nodes also get IPv6 addresses when the host supports it, see NodeType::ipv6()
+            let ipv6_enabled = match network.enable_ipv6("fd00:192:168:1::254", 64).await {
+                Ok(()) => true,
+                Err(error) => {
+                    tracing::warn!(
+                        ?error,
+                        "Failed to enable IPv6 on the bridge, the nodes only get IPv4 addresses"
+                    );
+                    false
+                }
+            };
 
             // Create required Node for each task
             let mut ip_to_nodes = HashMap::new();
@@ -83,7 +99,14 @@ pub async fn run_test(name: &str, tasks: Vec<Box<dyn Task>>) -> Result<()> {
             for task in tasks {
                 let ip = task.node_type().ip();
                 if !ip_to_nodes.contains_key(&ip) {
-                    let new_node = Arc::new(network.new_node(&ip).await?);
+                    let new_node = if ipv6_enabled {
+                        network
+                            .new_dual_stack_node(&ip, &task.node_type().ipv6())
+                            .await?
+                    } else {
+                        network.new_node(&ip).await?
+                    };
+                    let new_node = Arc::new(new_node);
                     ip_to_nodes.insert(ip.clone(), Arc::clone(&new_node));
                 }
                 let node = ip_to_nodes.get(&ip).unwrap();
@@ -1,4 +1,9 @@
-use std::{future::Future, net::Ipv4Addr, os::fd::AsRawFd as _};
+use std::{
+    future::Future,
+    net::{Ipv4Addr, Ipv6Addr},
+    os::fd::AsRawFd as _,
+    time::Duration,
+};
 
 use anyhow::{bail, Context, Result};
 use futures::TryStreamExt as _;
@@ -22,6 +27,8 @@ struct Bridge {
     pub bridge_idx: u32,
     pub bridge_addr: Option<Ipv4Addr>,
     pub prefix_len: Option<u8>,
+    /// The IPv6 address and prefix length of the bridge, if IPv6 is enabled.
+    pub ipv6: Option<(Ipv6Addr, u8)>,
 }
 
 impl Bridge {
@@ -58,6 +65,7 @@ impl Bridge {
             bridge_idx,
             bridge_addr: None,
             prefix_len: None,
+            ipv6: None,
         })
     }
 
@@ -126,6 +134,40 @@ impl Bridge {
 
         Ok(())
     }
+
+    async fn init_bridge_ipv6(&mut self, bridge_ipv6: &str, prefix_len: u8) -> Result<()> {
+        let bridge_addr = bridge_ipv6.parse::<Ipv6Addr>()?;
+
+        // Skip the duplicate address detection, so that the address can be used right away.
+        run_command(tokio::process::Command::new("sh").args([
+            "-c",
+            &format!(
+                "
+                set -x ; set -e ;
+                exec 2>&1 ;
+
+                echo 0 > /proc/sys/net/ipv6/conf/{bridge_name}/disable_ipv6
+                echo 1 > /proc/sys/net/ipv6/conf/all/forwarding
+                ip -6 addr add {bridge_addr}/{prefix_len} dev {bridge_name} nodad
+
+                ip6tables -t filter -D FORWARD -j TNG_TEST_NETNS_FORWARD 2>/dev/null || true
+                ip6tables -t filter -F TNG_TEST_NETNS_FORWARD 2>/dev/null || true
+                ip6tables -t filter -X TNG_TEST_NETNS_FORWARD 2>/dev/null || true
+
+                ip6tables -t filter -N TNG_TEST_NETNS_FORWARD
+                ip6tables -t filter -A TNG_TEST_NETNS_FORWARD -i {bridge_name} -o {bridge_name} -j ACCEPT
+                ip6tables -t filter -A FORWARD -j TNG_TEST_NETNS_FORWARD
+                ",
+                bridge_name = self.bridge_name,
+            ),
+        ]))
+        .await
+        .context("add ipv6 address to bridge failed")?;
+
+        self.ipv6 = Some((bridge_addr, prefix_len));
+
+        Ok(())
+    }
 }
 
 impl Drop for Bridge {
@@ -170,6 +212,18 @@ impl Drop for Bridge {
                     }
                 }
 
+                if self.ipv6.is_some() {
+                    run_command(tokio::process::Command::new("sh").args([
+                        "-c",
+                        "
+                            ip6tables -t filter -D FORWARD -j TNG_TEST_NETNS_FORWARD 2>/dev/null || true
+                            ip6tables -t filter -F TNG_TEST_NETNS_FORWARD 2>/dev/null || true
+                            ip6tables -t filter -X TNG_TEST_NETNS_FORWARD 2>/dev/null || true
+                            ",
+                    ]))
+                    .await?;
+                }
+
                 self.handle
                     .link()
                     .del(self.bridge_idx)
@@ -280,7 +334,29 @@ impl BridgeNetwork {
         Ok(Self { handle, bridge })
     }
 
+    /// Assigns an IPv6 address to the bridge, so that the nodes created with
+    /// [`BridgeNetwork::new_dual_stack_node`] can reach each other and the bridge over IPv6. Only
+    /// the subnet is routed, the nodes have no IPv6 access to the internet.
+    pub async fn enable_ipv6(&mut self, bridge_ipv6: &str, prefix_len: u8) -> Result<()> {
+        self.bridge.init_bridge_ipv6(bridge_ipv6, prefix_len).await
+    }
+
     pub async fn new_node(&self, node_ip: &str) -> Result<Node> {
+        self.new_node_inner(node_ip, None).await
+    }
+
+    /// Creates a node with both an IPv4 address and an IPv6 address, see
+    /// [`BridgeNetwork::enable_ipv6`].
+    pub async fn new_dual_stack_node(&self, node_ip: &str, node_ipv6: &str) -> Result<Node> {
+        let (_, prefix_len) = self.bridge.ipv6.context(
+            "IPv6 is not enabled on the bridge, call BridgeNetwork::enable_ipv6() first",
+        )?;
+        let node_ipv6 = node_ipv6.parse::<Ipv6Addr>()?;
+        self.new_node_inner(node_ip, Some((node_ipv6, prefix_len)))
+            .await
+    }
+
+    async fn new_node_inner(&self, node_ip: &str, ipv6: Option<(Ipv6Addr, u8)>) -> Result<Node> {
         let veth_pair = VethPair::create_veth_pair(&self.handle).await?;
         veth_pair
             .add_one_side_to_bridge(self.bridge.bridge_idx)
@@ -298,6 +374,9 @@ impl BridgeNetwork {
             )?,
         )
         .await?;
+        if let Some((node_ipv6, prefix_len)) = ipv6 {
+            node.init_node_ipv6(node_ipv6, prefix_len).await?;
+        }
         Ok(node)
     }
 }
@@ -307,6 +386,7 @@ struct VethPair {
     handle: Handle,
     veth_idx: u32,
     veth_2_idx: u32,
+    veth_2_name: String,
 }
 
 impl VethPair {
@@ -355,6 +435,7 @@ impl VethPair {
             handle: handle.clone(),
             veth_idx,
             veth_2_idx,
+            veth_2_name: veth_2,
         })
     }
 
@@ -535,6 +616,29 @@ impl Node {
         Ok(())
     }
 
+    async fn init_node_ipv6(&self, node_ipv6: Ipv6Addr, prefix_len: u8) -> Result<()> {
+        let veth_2_name = self.veth_pair.veth_2_name.clone();
+        self.run(async move {
+            // Skip the duplicate address detection, so that the address can be used right away.
+            run_command(tokio::process::Command::new("sh").args([
+                "-c",
+                &format!(
+                    "
+                    set -x ; set -e ;
+                    exec 2>&1 ;
+
+                    echo 0 > /proc/sys/net/ipv6/conf/all/disable_ipv6
+                    echo 0 > /proc/sys/net/ipv6/conf/{veth_2_name}/disable_ipv6
+                    ip -6 addr add {node_ipv6}/{prefix_len} dev {veth_2_name} nodad
+                    "
+                ),
+            ]))
+            .await
+            .with_context(|| format!("add ipv6 address to veth {veth_2_name} failed"))
+        })
+        .await?
+    }
+
     /// Create a multi thread runtime to run the future in the network namespace. This function will
     /// wait for the future to complete and return the output of the future. If the future spawns
     /// other tasks, they will be spawned in the same network namespace, and will continue to run in
@@ -569,6 +673,155 @@ impl Node {
             )
         })
     }
+
+    /// Applies `netem` to the interface of the node connected to the bridge, so that all the
+    /// traffic leaving the node is impaired. See [`Netem`].
+    pub async fn apply_netem(&self, netem: Netem) -> Result<()> {
+        self.run(async move { netem.apply().await }).await?
+    }
+
+    /// Removes the impairments applied with [`Node::apply_netem`].
+    pub async fn clear_netem(&self) -> Result<()> {
+        self.run(Netem::clear()).await?
+    }
+}
+
+/// The impairments emulated by the `netem` queueing discipline of `tc` on the egress of an
+/// interface, to test the timeout and retry behaviors under bad networks.
+///
+/// Since netem only delays the packets leaving an interface, applying it to one node delays the
+/// traffic in one direction only, e.g. a `delay` of 100ms adds 100ms to the round trip time.
+#[derive(Debug, Clone, Default)]
+pub struct Netem {
+    delay: Option<Duration>,
+    jitter: Option<Duration>,
+    loss_percent: Option<f64>,
+    rate_kbit: Option<u64>,
+}
+
+impl Netem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delays each packet by `delay`.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Varies the delay of each packet randomly by up to `jitter`. Only applies with a `delay`.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Drops each packet randomly with a probability of `percent`%.
+    pub fn loss(mut self, percent: f64) -> Self {
+        self.loss_percent = Some(percent);
+        self
+    }
+
+    /// Caps the bandwidth to `kbit` kilobits per second.
+    pub fn rate(mut self, kbit: u64) -> Self {
+        self.rate_kbit = Some(kbit);
+        self
+    }
+
+    fn tc_args(&self) -> Result<Vec<String>> {
+        let mut args = vec![];
+        match (self.delay, self.jitter) {
+            (Some(delay), jitter) => {
+                args.push("delay".to_owned());
+                args.push(format!("{}us", delay.as_micros()));
+                if let Some(jitter) = jitter {
+                    args.push(format!("{}us", jitter.as_micros()));
+                }
+            }
+            (None, Some(_)) => bail!("the jitter of netem requires a delay"),
+            (None, None) => {}
+        }
+        if let Some(loss_percent) = self.loss_percent {
+            if !(0.0..=100.0).contains(&loss_percent) {
+                bail!("the loss of netem must be between 0 and 100 percent, got {loss_percent}");
+            }
+            args.push("loss".to_owned());
+            args.push(format!("{loss_percent}%"));
+        }
+        if let Some(rate_kbit) = self.rate_kbit {
+            args.push("rate".to_owned());
+            args.push(format!("{rate_kbit}kbit"));
+        }
+        Ok(args)
+    }
+
+    /// Applies the impairments to the interface of the default route of the current network
+    /// namespace, i.e. to the interface connected to the bridge when called in [`Node::run`],
+    /// replacing the ones applied before.
+    pub async fn apply(&self) -> Result<()> {
+        let tc_args = self.tc_args()?;
+        let ifname = default_route_interface().await?;
+
+        tracing::info!(%ifname, netem = ?tc_args, "apply netem");
+        run_command(
+            tokio::process::Command::new("tc")
+                .args(["qdisc", "replace", "dev", &ifname, "root", "netem"])
+                .args(tc_args),
+        )
+        .await
+        .with_context(|| format!("failed to apply netem to interface {ifname}"))
+    }
+
+    /// Removes the impairments from the interface of the default route of the current network
+    /// namespace, if any.
+    pub async fn clear() -> Result<()> {
+        let ifname = default_route_interface().await?;
+
+        let output = tokio::process::Command::new("tc")
+            .args(["qdisc", "show", "dev", &ifname, "root"])
+            .output()
+            .await?;
+        if !String::from_utf8_lossy(&output.stdout).contains("netem") {
+            return Ok(());
+        }
+
+        tracing::info!(%ifname, "clear netem");
+        run_command(
+            tokio::process::Command::new("tc").args(["qdisc", "del", "dev", &ifname, "root"]),
+        )
+        .await
+        .with_context(|| format!("failed to clear netem of interface {ifname}"))
+    }
+}
+
+/// Returns the name of the interface of the default route in the current network namespace.
+async fn default_route_interface() -> Result<String> {
+    let output = tokio::process::Command::new("ip")
+        .args(["route", "show", "default"])
+        .output()
+        .await?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .split_whitespace()
+        .skip_while(|word| *word != "dev")
+        .nth(1)
+        .map(ToOwned::to_owned)
+        .with_context(|| format!("no default route found, `ip route show default`: {stdout}"))
+}
+
+async fn run_command(command: &mut tokio::process::Command) -> Result<()> {
+    let output = command.output().await?;
+
+    if !output.status.success() {
+        bail!(
+            "exit code: {:?}\nstdout: {}\nstderr: {}",
+            output.status.code(),
+            &String::from_utf8_lossy(&output.stdout),
+            &String::from_utf8_lossy(&output.stderr)
+        )
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -630,4 +883,106 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+    async fn test_dual_stack_bridge_network() -> Result<()> {
+        let mut network = BridgeNetwork::new("192.168.1.254", 24).await?;
+        network.enable_ipv6("fd00:192:168:1::254", 64).await?;
+        let node1 = network
+            .new_dual_stack_node("192.168.1.1", "fd00:192:168:1::1")
+            .await?;
+        let node2 = network
+            .new_dual_stack_node("192.168.1.253", "fd00:192:168:1::253")
+            .await?;
+
+        for node in [&node1, &node2] {
+            node.run(async move {
+                let output = tokio::process::Command::new("sh")
+                    .args([
+                        "-c",
+                        "
+                        set -x ; set -e ;
+                        exec 2>&1 ;
+
+                        ip -6 a | grep fd00:192:168:1:: ;
+                        ping -6 fd00:192:168:1::1 -c 1 -W 5 ;
+                        ping -6 fd00:192:168:1::253 -c 1 -W 5 ;
+                        ping -6 fd00:192:168:1::254 -c 1 -W 5 ;
+                        ping 192.168.1.254 -c 1 -W 5 ;
+                        ",
+                    ])
+                    .output()
+                    .await?;
+
+                if !output.status.success() {
+                    bail!(
+                        "network test failed with exit code: {:?}\nstdout: {}\nstderr: {}",
+                        output.status.code(),
+                        &String::from_utf8_lossy(&output.stdout),
+                        &String::from_utf8_lossy(&output.stderr)
+                    )
+                }
+                Ok(())
+            })
+            .await??;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_netem_tc_args() -> Result<()> {
+        let netem = Netem::new()
+            .delay(Duration::from_millis(100))
+            .jitter(Duration::from_millis(10))
+            .loss(1.5)
+            .rate(1024);
+        assert_eq!(
+            netem.tc_args()?,
+            ["delay", "100000us", "10000us", "loss", "1.5%", "rate", "1024kbit"]
+        );
+
+        assert!(Netem::new()
+            .jitter(Duration::from_millis(10))
+            .tc_args()
+            .is_err());
+        assert!(Netem::new().loss(101.0).tc_args().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+    async fn test_node_netem() -> Result<()> {
+        let network = BridgeNetwork::new("192.168.1.254", 24).await?;
+        let node = network.new_node("192.168.1.1").await?;
+
+        let ping = || {
+            node.run(async move {
+                let start = tokio::time::Instant::now();
+                let output = tokio::process::Command::new("ping")
+                    .args(["192.168.1.254", "-c", "1", "-W", "5"])
+                    .output()
+                    .await?;
+                if !output.status.success() {
+                    bail!(
+                        "ping failed with exit code: {:?}\nstdout: {}",
+                        output.status.code(),
+                        &String::from_utf8_lossy(&output.stdout),
+                    )
+                }
+                Ok(start.elapsed())
+            })
+        };
+
+        node.apply_netem(Netem::new().delay(Duration::from_millis(200)))
+            .await?;
+        let elapsed = ping().await??;
+        assert!(elapsed >= Duration::from_millis(200), "{elapsed:?}");
+
+        node.clear_netem().await?;
+        let elapsed = ping().await??;
+        assert!(elapsed < Duration::from_millis(200), "{elapsed:?}");
+
+        Ok(())
+    }
 }
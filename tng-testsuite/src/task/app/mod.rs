@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use http_client::HttpClientMode;
@@ -6,6 +8,8 @@ use tokio_util::sync::CancellationToken;
 
 use super::{NodeType, Task};
 
+pub use throughput_client::ThroughputThresholds;
+
 #[cfg(feature = "js-sdk")]
 mod browser_client;
 mod http_client;
@@ -13,6 +17,7 @@ mod http_server;
 mod load_balancer;
 mod tcp_client;
 mod tcp_server;
+mod throughput_client;
 mod tls_tcp_proxy;
 mod udp_client;
 mod udp_server;
@@ -67,10 +72,38 @@ pub enum AppType {
         port: u16,
         http_proxy: Option<HttpProxy>,
     },
+    /// A TCP client which keeps exchanging the payload for `rounds` rounds, and tolerates the
+    /// failures of each round for up to `recover_within`, e.g. while a TNG instance is restarted.
+    #[allow(dead_code)]
+    TcpClientWithRetry {
+        host: &'static str,
+        port: u16,
+        rounds: u32,
+        interval: Duration,
+        recover_within: Duration,
+    },
+    /// A TCP client measuring the latency and the throughput through the tunnel against
+    /// `TcpServer`, which fails if they are worse than the `thresholds`.
+    #[allow(dead_code)]
+    TcpThroughputClient {
+        host: &'static str,
+        port: u16,
+        total_bytes: u64,
+        thresholds: ThroughputThresholds,
+    },
     #[allow(dead_code)]
     UdpServer { port: u16 },
     #[allow(dead_code)]
     UdpClient { host: &'static str, port: u16 },
+    /// Sends `count` datagrams at once and checks that all of them are echoed back by a
+    /// `UdpServer`, in order if `ordered` is set.
+    #[allow(dead_code)]
+    UdpBurstClient {
+        host: &'static str,
+        port: u16,
+        count: u32,
+        ordered: bool,
+    },
 }
 
 #[async_trait]
@@ -78,10 +111,14 @@ impl Task for AppType {
     fn name(&self) -> String {
         match self {
             AppType::HttpServer { .. } | AppType::TcpServer { .. } => "app_server",
-            AppType::UdpServer { .. } | AppType::UdpClient { .. } => "app_udp",
+            AppType::UdpServer { .. }
+            | AppType::UdpClient { .. }
+            | AppType::UdpBurstClient { .. } => "app_udp",
             AppType::HttpClient { .. }
             | AppType::HttpClientWithReverseProxy { .. }
-            | AppType::TcpClient { .. } => "app_client",
+            | AppType::TcpClient { .. }
+            | AppType::TcpClientWithRetry { .. }
+            | AppType::TcpThroughputClient { .. } => "app_client",
             AppType::LoadBalancer { .. } => "load_balancer",
             AppType::TlsTcpProxy { .. } => "tls_tcp_proxy",
             #[cfg(feature = "js-sdk")]
@@ -96,8 +133,10 @@ impl Task for AppType {
             AppType::UdpServer { .. } => NodeType::Server,
             AppType::HttpClient { .. }
             | AppType::HttpClientWithReverseProxy { .. }
-            | AppType::TcpClient { .. } => NodeType::Client,
-            AppType::UdpClient { .. } => NodeType::Client,
+            | AppType::TcpClient { .. }
+            | AppType::TcpClientWithRetry { .. }
+            | AppType::TcpThroughputClient { .. } => NodeType::Client,
+            AppType::UdpClient { .. } | AppType::UdpBurstClient { .. } => NodeType::Client,
             AppType::LoadBalancer { .. } => NodeType::Middleware,
             AppType::TlsTcpProxy { .. } => NodeType::Middleware,
             #[cfg(feature = "js-sdk")]
@@ -158,10 +197,48 @@ impl Task for AppType {
                 port,
                 http_proxy,
             } => tcp_client::launch_tcp_client(token, host, *port, *http_proxy).await,
+            AppType::TcpClientWithRetry {
+                host,
+                port,
+                rounds,
+                interval,
+                recover_within,
+            } => {
+                tcp_client::launch_tcp_client_with_retry(
+                    token,
+                    host,
+                    *port,
+                    *rounds,
+                    *interval,
+                    *recover_within,
+                )
+                .await
+            }
+            AppType::TcpThroughputClient {
+                host,
+                port,
+                total_bytes,
+                thresholds,
+            } => {
+                throughput_client::launch_tcp_throughput_client(
+                    token,
+                    host,
+                    *port,
+                    *total_bytes,
+                    *thresholds,
+                )
+                .await
+            }
             AppType::UdpServer { port } => udp_server::launch_udp_server(token, *port).await,
             AppType::UdpClient { host, port } => {
                 udp_client::launch_udp_client(token, host, *port).await
             }
+            AppType::UdpBurstClient {
+                host,
+                port,
+                count,
+                ordered,
+            } => udp_client::launch_udp_burst_client(token, host, *port, *count, *ordered).await,
             AppType::LoadBalancer {
                 listen_port,
                 upstream_servers,
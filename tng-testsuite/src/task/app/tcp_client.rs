@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use anyhow::{anyhow, bail, Context as _, Result};
 use async_http_proxy::http_connect_tokio;
 use tokio::{
@@ -32,50 +34,57 @@ pub async fn launch_tcp_client(
                     port
                 );
 
-                let connect_task = async {
-                    Ok(match &http_proxy {
-                        Some(http_proxy) => {
-                            let mut stream = TcpStream::connect(format!(
-                                "{}:{}",
-                                http_proxy.host, http_proxy.port
-                            ))
-                            .await
-                            .context("Failed to connect to http proxy server")?;
-                            http_connect_tokio(&mut stream, &host, port)
-                                .await
-                                .context("Failed to connect to app server via http proxy server")?;
-                            stream
-                        }
-                        None => TcpStream::connect(format!("{}:{}", host, port))
-                            .await
-                            .context("Failed to connect to app server")?,
-                    })
-                };
-
-                let mut stream = tokio::select! {
-                    _ = tokio::time::sleep(std::time::Duration::from_secs(10)) => {
-                        Err(anyhow!("The TCP client task timed out"))
-                    }
-                    result = connect_task => result,
-                }?;
+                exchange_payload(&host, port, http_proxy).await?;
+            }
 
-                tracing::info!("Connected to the server");
+            tracing::info!("The TCP client task normally exited");
+            Ok(())
+        }
+        .instrument(parent_span),
+    ))
+}
 
-                let message = TCP_PAYLOAD.as_bytes();
-                stream.write_all(message).await?;
-                stream.shutdown().await?;
+/// Exchanges the payload `rounds` times, one every `interval`, tolerating the failures of each
+/// round for up to `recover_within`, e.g. while a TNG instance on the path is restarted.
+pub async fn launch_tcp_client_with_retry(
+    token: CancellationToken,
+    host: &str,
+    port: u16,
+    rounds: u32,
+    interval: Duration,
+    recover_within: Duration,
+) -> Result<JoinHandle<Result<()>>> {
+    let host = host.to_owned();
+    let parent_span = tracing::Span::current();
+    Ok(tokio::task::spawn(
+        async move {
+            let _drop_guard = token.drop_guard();
 
-                let mut response = Vec::new();
-                stream.read_to_end(&mut response).await?;
+            for i in 1..=rounds {
+                tracing::info!("TCP client round {i}/{rounds}, connecting to TCP server at {host}:{port}");
 
-                if response != message {
-                    bail!(
-                        "The response body should be `{TCP_PAYLOAD}`, but got `{}`",
-                        String::from_utf8_lossy(&response)
-                    )
-                } else {
-                    tracing::info!("Success! The response matchs expected value");
+                let deadline = tokio::time::Instant::now() + recover_within;
+                let mut failures = 0;
+                loop {
+                    match exchange_payload(&host, port, None).await {
+                        Ok(()) => break,
+                        Err(error) if tokio::time::Instant::now() < deadline => {
+                            failures += 1;
+                            tracing::warn!(?error, failures, "TCP client round {i} failed, retrying");
+                            tokio::time::sleep(Duration::from_millis(200)).await;
+                        }
+                        Err(error) => {
+                            return Err(error.context(format!(
+                                "The TCP client did not recover within {recover_within:?} in round {i}, after {failures} failures"
+                            )));
+                        }
+                    }
                 }
+                if failures > 0 {
+                    tracing::info!(failures, "TCP client recovered in round {i}");
+                }
+
+                tokio::time::sleep(interval).await;
             }
 
             tracing::info!("The TCP client task normally exited");
@@ -84,3 +93,51 @@ pub async fn launch_tcp_client(
         .instrument(parent_span),
     ))
 }
+
+/// Sends the payload to the TCP server and checks that it is echoed back.
+async fn exchange_payload(host: &str, port: u16, http_proxy: Option<HttpProxy>) -> Result<()> {
+    let connect_task = async {
+        Ok(match &http_proxy {
+            Some(http_proxy) => {
+                let mut stream =
+                    TcpStream::connect(format!("{}:{}", http_proxy.host, http_proxy.port))
+                        .await
+                        .context("Failed to connect to http proxy server")?;
+                http_connect_tokio(&mut stream, host, port)
+                    .await
+                    .context("Failed to connect to app server via http proxy server")?;
+                stream
+            }
+            None => TcpStream::connect(format!("{}:{}", host, port))
+                .await
+                .context("Failed to connect to app server")?,
+        })
+    };
+
+    let mut stream = tokio::select! {
+        _ = tokio::time::sleep(std::time::Duration::from_secs(10)) => {
+            Err(anyhow!("The TCP client task timed out"))
+        }
+        result = connect_task => result,
+    }?;
+
+    tracing::info!("Connected to the server");
+
+    let message = TCP_PAYLOAD.as_bytes();
+    stream.write_all(message).await?;
+    stream.shutdown().await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+
+    if response != message {
+        bail!(
+            "The response body should be `{TCP_PAYLOAD}`, but got `{}`",
+            String::from_utf8_lossy(&response)
+        )
+    } else {
+        tracing::info!("Success! The response matchs expected value");
+    }
+
+    Ok(())
+}
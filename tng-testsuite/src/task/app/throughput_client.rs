@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context as _, Result};
+use tokio::{
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    net::TcpStream,
+    task::JoinHandle,
+    time::Instant,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+/// The number of round trips the latency is measured over.
+const LATENCY_SAMPLES: usize = 20;
+
+/// The size of each write of the throughput measurement.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The thresholds a [`launch_tcp_throughput_client`] fails the test below.
+#[derive(Debug, Clone, Copy)]
+pub struct ThroughputThresholds {
+    /// The minimum throughput of the echoed data, in Mbit/s.
+    pub min_throughput_mbps: f64,
+    /// The maximum median latency of a round trip of a small message.
+    pub max_latency: Duration,
+}
+
+/// Measures the round trip latency and the throughput through the tunnel against a TCP server
+/// echoing the data back, i.e. `AppType::TcpServer`, and fails if they are worse than the
+/// `thresholds`.
+///
+/// The latency is the median of [`LATENCY_SAMPLES`] round trips of a small message over one
+/// connection. The throughput is that of `total_bytes` being sent on another connection and
+/// echoed back, measured until the last byte is received.
+pub async fn launch_tcp_throughput_client(
+    token: CancellationToken,
+    host: &str,
+    port: u16,
+    total_bytes: u64,
+    thresholds: ThroughputThresholds,
+) -> Result<JoinHandle<Result<()>>> {
+    let host = host.to_owned();
+    let parent_span = tracing::Span::current();
+    Ok(tokio::task::spawn(
+        async move {
+            let _drop_guard = token.drop_guard();
+
+            let latency = measure_latency(&host, port).await?;
+            let throughput_mbps = measure_throughput(&host, port, total_bytes).await?;
+            tracing::info!(
+                ?latency,
+                throughput_mbps,
+                ?thresholds,
+                "Measured the performance through the tunnel"
+            );
+
+            if latency > thresholds.max_latency {
+                bail!(
+                    "The median latency {latency:?} is above the threshold {:?}",
+                    thresholds.max_latency
+                );
+            }
+            if throughput_mbps < thresholds.min_throughput_mbps {
+                bail!(
+                    "The throughput {throughput_mbps:.2} Mbit/s is below the threshold {} Mbit/s",
+                    thresholds.min_throughput_mbps
+                );
+            }
+
+            tracing::info!("The TCP throughput client task normally exited");
+            Ok(())
+        }
+        .instrument(parent_span),
+    ))
+}
+
+async fn connect(host: &str, port: u16) -> Result<TcpStream> {
+    let stream = tokio::time::timeout(
+        Duration::from_secs(10),
+        TcpStream::connect(format!("{host}:{port}")),
+    )
+    .await
+    .context("Timeout connecting to app server")?
+    .context("Failed to connect to app server")?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+async fn measure_latency(host: &str, port: u16) -> Result<Duration> {
+    let mut stream = connect(host, port).await?;
+
+    let message = [0x5a; 64];
+    let mut response = [0; 64];
+    let mut samples = Vec::with_capacity(LATENCY_SAMPLES);
+    for _ in 0..LATENCY_SAMPLES {
+        let start = Instant::now();
+        stream.write_all(&message).await?;
+        stream
+            .read_exact(&mut response)
+            .await
+            .context("Failed to read the echoed message")?;
+        samples.push(start.elapsed());
+
+        if response != message {
+            bail!("The echoed message does not match the one sent");
+        }
+    }
+
+    samples.sort();
+    Ok(samples[samples.len() / 2])
+}
+
+/// Returns the throughput in Mbit/s.
+async fn measure_throughput(host: &str, port: u16, total_bytes: u64) -> Result<f64> {
+    let stream = connect(host, port).await?;
+    let (mut reader, mut writer) = stream.into_split();
+
+    // A pattern not aligned with the chunks, so that misordered data is detected.
+    let pattern = |offset: u64| (offset % 251) as u8;
+
+    let start = Instant::now();
+    let write_task = tokio::task::spawn(
+        async move {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let mut offset = 0;
+            while offset < total_bytes {
+                let len = (total_bytes - offset).min(CHUNK_SIZE as u64) as usize;
+                for (i, byte) in chunk[..len].iter_mut().enumerate() {
+                    *byte = pattern(offset + i as u64);
+                }
+                writer.write_all(&chunk[..len]).await?;
+                offset += len as u64;
+            }
+            writer.shutdown().await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .instrument(tracing::Span::current()),
+    );
+
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut received = 0;
+    loop {
+        let size = reader.read(&mut buffer).await?;
+        if size == 0 {
+            break;
+        }
+        if let Some(i) = (0..size).find(|i| buffer[*i] != pattern(received + *i as u64)) {
+            bail!(
+                "The echoed data is corrupted at offset {}",
+                received + i as u64
+            );
+        }
+        received += size as u64;
+    }
+    let elapsed = start.elapsed();
+    write_task.await??;
+
+    if received != total_bytes {
+        bail!("{received} bytes are echoed back, but {total_bytes} bytes are sent");
+    }
+
+    Ok(total_bytes as f64 * 8.0 / 1_000_000.0 / elapsed.as_secs_f64())
+}
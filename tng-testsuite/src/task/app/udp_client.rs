@@ -54,3 +54,74 @@ pub async fn launch_udp_client(
         Ok(())
     }))
 }
+
+/// Sends `count` numbered datagrams over a single socket without waiting for the responses, and
+/// asserts that each of them is echoed back exactly once, and in the order they were sent if
+/// `ordered` is set.
+pub async fn launch_udp_burst_client(
+    token: CancellationToken,
+    host: &str,
+    port: u16,
+    count: u32,
+    ordered: bool,
+) -> Result<JoinHandle<Result<()>>> {
+    let host = host.to_owned();
+    Ok(tokio::task::spawn(async move {
+        let _drop_guard = token.drop_guard();
+
+        tracing::info!(
+            count,
+            ordered,
+            "UDP client sending a burst of datagrams to UDP server at {host}:{port}"
+        );
+
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket
+            .connect(format!("{host}:{port}"))
+            .await
+            .context("Failed to connect to UDP server")?;
+
+        for seq in 0..count {
+            socket
+                .send(format!("{seq}:{UDP_PAYLOAD}").as_bytes())
+                .await?;
+        }
+
+        let mut received = vec![false; count as usize];
+        let mut last_seq = None;
+        let mut response = vec![0u8; 65535];
+        for _ in 0..count {
+            let n = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                socket.recv(&mut response),
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "UDP client timed out waiting for a response, {} of {count} datagrams are lost",
+                    received.iter().filter(|received| !**received).count()
+                )
+            })?
+            .context("Failed to receive response")?;
+
+            let response = String::from_utf8_lossy(&response[..n]);
+            let seq = response
+                .split_once(':')
+                .filter(|(_, payload)| *payload == UDP_PAYLOAD)
+                .and_then(|(seq, _)| seq.parse::<u32>().ok())
+                .filter(|seq| *seq < count)
+                .with_context(|| format!("Got an unexpected response `{response}`"))?;
+
+            if std::mem::replace(&mut received[seq as usize], true) {
+                bail!("The datagram {seq} is received twice");
+            }
+            if ordered && last_seq.is_some_and(|last_seq| seq < last_seq) {
+                bail!("The datagram {seq} is received after the datagram {last_seq:?}");
+            }
+            last_seq = Some(seq);
+        }
+
+        tracing::info!("Success! All the {count} datagrams are echoed back");
+        Ok(())
+    }))
+}
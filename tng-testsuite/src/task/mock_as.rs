@@ -0,0 +1,250 @@
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use axum::{extract::State, routing::get, routing::post, Json, Router};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use http::StatusCode;
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, KeyAlgorithm,
+    },
+    Algorithm, EncodingKey, Header,
+};
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+    KeyUsagePurpose, PKCS_ECDSA_P256_SHA256,
+};
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use super::{NodeType, Task};
+
+/// The address of the attestation service used by the bin tests, i.e. the address of the bridge.
+const MOCK_AS_ADDR: &str = "192.168.1.254:8080";
+
+/// How long the tokens issued by the mock attestation service are valid.
+const TOKEN_VALIDITY_SECS: u64 = 300;
+
+/// The verdict of the mock attestation service on every evidence it gets.
+#[derive(Debug, Clone, Copy)]
+pub enum MockAsVerdict {
+    /// Issue a token accepted by the policies the evidence is checked against.
+    Pass,
+    /// Reject the evidence, like the policies of a real attestation service would.
+    Fail,
+    /// Issue a token accepted by the policies, after the given delay.
+    Delay(Duration),
+}
+
+/// A fake restful attestation service of CoCo (Trustee), serving the `/challenge`,
+/// `/attestation` and `/certificate` APIs at `http://192.168.1.254:8080`, so that the bin tests
+/// don't depend on an external attestation service container.
+///
+/// The evidence is not appraised at all: the issued tokens only carry the runtime data of the
+/// evidence and the requested policy ids, and are signed with a certificate issued by a CA which
+/// is served on `/certificate`. The listener is bound on the bridge, in the network namespace of
+/// the test process, whichever node the task is launched in.
+pub struct MockAsTask {
+    pub node_type: NodeType,
+    pub verdict: MockAsVerdict,
+}
+
+struct MockAs {
+    verdict: MockAsVerdict,
+    /// The PEM of the CA, served on `/certificate`.
+    ca_cert_pem: String,
+    /// The DER of the certificate signing the tokens, which is carried in their `x5c`.
+    signer_cert_der: Vec<u8>,
+    signer_key_pair: KeyPair,
+}
+
+impl MockAs {
+    fn new(verdict: MockAsVerdict) -> Result<Self> {
+        let ca_key_pair = KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256)?;
+        let mut ca_params = CertificateParams::default();
+        ca_params
+            .distinguished_name
+            .push(DnType::OrganizationName, "TNG Testsuite Mock AS CA");
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params.key_usages = vec![
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+            KeyUsagePurpose::DigitalSignature,
+        ];
+        let ca_cert = ca_params.self_signed(&ca_key_pair)?;
+
+        let signer_key_pair = KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256)?;
+        let mut signer_params = CertificateParams::default();
+        signer_params
+            .distinguished_name
+            .push(DnType::CommonName, "TNG Testsuite Mock AS");
+        signer_params.is_ca = IsCa::NoCa;
+        signer_params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        signer_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let signer_cert = signer_params.signed_by(&signer_key_pair, &ca_cert, &ca_key_pair)?;
+
+        Ok(Self {
+            verdict,
+            ca_cert_pem: ca_cert.pem(),
+            signer_cert_der: signer_cert.der().to_vec(),
+            signer_key_pair,
+        })
+    }
+
+    /// Issues a token in the format of the CoCo AS, with the claims checked by the verifiers.
+    fn issue_token(&self, request: &Value) -> Result<String> {
+        let verification_request = request
+            .pointer("/verification_requests/0")
+            .context("no verification request")?;
+        let runtime_data = verification_request
+            .pointer("/runtime_data/structured")
+            .cloned()
+            .context("no structured runtime data in the verification request")?;
+        let policy_ids = match request.get("policy_ids").and_then(Value::as_array) {
+            Some(policy_ids) if !policy_ids.is_empty() => policy_ids.clone(),
+            _ => vec![json!("default")],
+        };
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let claims = json!({
+            "iss": "tng-testsuite-mock-as",
+            "iat": now,
+            "exp": now + TOKEN_VALIDITY_SECS,
+            "tee": verification_request.get("tee"),
+            "evaluation-reports": policy_ids
+                .iter()
+                .map(|policy_id| json!({ "policy-id": policy_id }))
+                .collect::<Vec<_>>(),
+            "customized_claims": {
+                "runtime_data": runtime_data,
+            },
+        });
+
+        // The public key is an uncompressed point of P-256, i.e. `0x04 || x || y`.
+        let public_key = self.signer_key_pair.public_key_raw();
+        let (x, y) = public_key[1..].split_at(32);
+        let mut header = Header::new(Algorithm::ES256);
+        header.jwk = Some(Jwk {
+            common: CommonParameters {
+                key_algorithm: Some(KeyAlgorithm::ES256),
+                x509_chain: Some(vec![URL_SAFE_NO_PAD.encode(&self.signer_cert_der)]),
+                ..Default::default()
+            },
+            algorithm: AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: EllipticCurveKeyType::EC,
+                curve: EllipticCurve::P256,
+                x: URL_SAFE_NO_PAD.encode(x),
+                y: URL_SAFE_NO_PAD.encode(y),
+            }),
+        });
+
+        Ok(jsonwebtoken::encode(
+            &header,
+            &claims,
+            &EncodingKey::from_ec_der(&self.signer_key_pair.serialize_der()),
+        )?)
+    }
+}
+
+/// Binds `addr` in the network namespace of the test process, where the bridge is.
+fn bind_in_test_process_netns(addr: SocketAddr) -> Result<std::net::TcpListener> {
+    // The network namespace is a property of each thread, so switch in a dedicated one.
+    std::thread::spawn(move || {
+        let netns = netns_rs::NetNs::get_from("/proc/self/ns/net")?;
+        let listener = netns.run(|_netns| std::net::TcpListener::bind(addr))??;
+        listener.set_nonblocking(true)?;
+        Ok::<_, anyhow::Error>(listener)
+    })
+    .join()
+    .map_err(|_| anyhow::anyhow!("the thread binding {addr} panicked"))?
+}
+
+#[async_trait]
+impl Task for MockAsTask {
+    fn name(&self) -> String {
+        "mock_as".to_string()
+    }
+
+    fn node_type(&self) -> NodeType {
+        self.node_type
+    }
+
+    async fn launch(&self, token: CancellationToken) -> Result<JoinHandle<Result<()>>> {
+        let mock_as = Arc::new(MockAs::new(self.verdict)?);
+
+        let addr = MOCK_AS_ADDR.parse::<SocketAddr>()?;
+        let listener = tokio::net::TcpListener::from_std(
+            bind_in_test_process_netns(addr)
+                .with_context(|| format!("failed to listen on {addr}"))?,
+        )?;
+        tracing::info!(verdict = ?self.verdict, "Mock attestation service listening on {addr}");
+
+        let app = Router::new()
+            .route(
+                "/challenge",
+                post(|| async {
+                    Json(json!({
+                        "nonce": "mock-nonce",
+                        "extra_params": { "jwt": "mock-challenge" },
+                    }))
+                }),
+            )
+            .route(
+                "/attestation",
+                post(
+                    |State(mock_as): State<Arc<MockAs>>, Json(request): Json<Value>| async move {
+                        match mock_as.verdict {
+                            MockAsVerdict::Pass => {}
+                            MockAsVerdict::Fail => {
+                                tracing::info!("Rejecting the evidence");
+                                return (
+                                    StatusCode::UNAUTHORIZED,
+                                    "Attestation failed: the policy rejected the evidence"
+                                        .to_owned(),
+                                );
+                            }
+                            MockAsVerdict::Delay(delay) => tokio::time::sleep(delay).await,
+                        }
+
+                        match mock_as.issue_token(&request) {
+                            Ok(token) => {
+                                tracing::info!("Issued an attestation token");
+                                (StatusCode::OK, token)
+                            }
+                            Err(error) => (StatusCode::BAD_REQUEST, format!("{error:?}")),
+                        }
+                    },
+                ),
+            )
+            .route(
+                "/certificate",
+                get(
+                    |State(mock_as): State<Arc<MockAs>>| async move { mock_as.ca_cert_pem.clone() },
+                ),
+            )
+            .with_state(mock_as);
+
+        Ok(tokio::task::spawn(
+            async move {
+                tokio::select! {
+                    _ = token.cancelled() => {}
+                    res = axum::serve(listener, app) => {
+                        res?;
+                    }
+                }
+
+                tracing::info!("The mock attestation service task normally exited");
+                Ok(())
+            }
+            .instrument(tracing::Span::current()),
+        ))
+    }
+}
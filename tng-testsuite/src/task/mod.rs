@@ -5,6 +5,9 @@ use tokio_util::sync::CancellationToken;
 
 pub mod app;
 pub mod function;
+pub mod mock_as;
+pub mod netem;
+pub mod packet_capture;
 pub mod shell;
 pub mod tagged_spawn;
 pub mod tng;
@@ -31,6 +34,14 @@ impl NodeType {
             NodeType::Middleware => "192.168.1.252".into(),
         }
     }
+
+    /// The IPv6 address of the node, in the `fd00:192:168:1::/64` subnet of the bridge, with the
+    /// same host number as its IPv4 address, e.g. `fd00:192:168:1::253` for the client.
+    pub fn ipv6(&self) -> String {
+        let ip = self.ip();
+        let host_num = ip.rsplit('.').next().unwrap_or_default();
+        format!("fd00:192:168:1::{host_num}")
+    }
 }
 
 impl std::fmt::Display for NodeType {
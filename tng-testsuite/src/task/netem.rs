@@ -0,0 +1,35 @@
+use super::{NodeType, Task};
+use crate::netns::Netem;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Applies the network impairments of `netem` to the node, before the tasks following it are
+/// launched, and removes them once the test is cancelled. Put it before the tasks which should
+/// run under the impaired network.
+pub struct NetemTask {
+    pub node_type: NodeType,
+    pub netem: Netem,
+}
+
+#[async_trait]
+impl Task for NetemTask {
+    fn name(&self) -> String {
+        "netem".to_string()
+    }
+
+    fn node_type(&self) -> NodeType {
+        self.node_type
+    }
+
+    async fn launch(&self, token: CancellationToken) -> Result<JoinHandle<Result<()>>> {
+        self.netem.apply().await?;
+
+        Ok(tokio::task::spawn(async move {
+            token.cancelled().await;
+            Netem::clear().await
+        }))
+    }
+}
@@ -0,0 +1,222 @@
+use std::{path::Path, process::Stdio, time::Duration};
+
+use anyhow::{bail, Context as _, Result};
+use async_trait::async_trait;
+use rand::{distr::Alphanumeric, Rng as _};
+use tokio::{
+    io::{AsyncBufReadExt as _, BufReader},
+    process::Command,
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use super::{NodeType, Task};
+
+/// An assertion on the packets captured by a [`PacketCaptureTask`], checked once the test is
+/// over.
+#[derive(Debug, Clone)]
+pub enum CaptureAssertion {
+    /// None of the captured packets contains the bytes, e.g. a plaintext payload which must only
+    /// be sent encrypted.
+    NotContains(Vec<u8>),
+    /// At least one of the captured packets contains the bytes.
+    Contains(Vec<u8>),
+    /// At least the given number of packets are captured, so that the other assertions are not
+    /// met trivially by a capture seeing nothing.
+    MinPackets(usize),
+}
+
+/// Captures the packets matching `filter` (a pcap filter expression, e.g. `tcp port 20001`) on
+/// an interface of the node with `tcpdump`, for the duration of the test, and checks the
+/// `assertions` on them once the test is cancelled.
+pub struct PacketCaptureTask {
+    pub node_type: NodeType,
+    /// The interface to capture on, `any` if not set.
+    pub interface: Option<String>,
+    pub filter: String,
+    pub assertions: Vec<CaptureAssertion>,
+}
+
+#[async_trait]
+impl Task for PacketCaptureTask {
+    fn name(&self) -> String {
+        "packet_capture".to_string()
+    }
+
+    fn node_type(&self) -> NodeType {
+        self.node_type
+    }
+
+    async fn launch(&self, token: CancellationToken) -> Result<JoinHandle<Result<()>>> {
+        let random_part: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect();
+        let pcap_path = std::env::temp_dir().join(format!("tng-testsuite-{random_part}.pcap"));
+        let interface = self.interface.as_deref().unwrap_or("any");
+
+        // `-U` flushes each packet to the file as soon as it is captured.
+        let mut child = Command::new("tcpdump")
+            .args(["-i", interface, "-n", "-U", "-s", "0", "-w"])
+            .arg(&pcap_path)
+            .arg(&self.filter)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to run tcpdump, is it installed?")?;
+
+        // Wait until tcpdump is capturing, so that no packet of the following tasks is missed.
+        let mut stderr =
+            BufReader::new(child.stderr.take().context("no stderr of tcpdump")?).lines();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while let Some(line) = stderr.next_line().await? {
+                tracing::info!(target: "spawn", "{}", line);
+                if line.contains("listening on") {
+                    return Ok(());
+                }
+            }
+            bail!("tcpdump exited before capturing: {:?}", child.wait().await?)
+        })
+        .await
+        .context("Timeout waiting for tcpdump to start capturing")??;
+        tracing::info!(interface, filter = %self.filter, "Capturing packets");
+        tokio::spawn(
+            async move {
+                while let Ok(Some(line)) = stderr.next_line().await {
+                    tracing::info!(target: "spawn", "{}", line);
+                }
+            }
+            .instrument(tracing::Span::current()),
+        );
+
+        let assertions = self.assertions.clone();
+        Ok(tokio::task::spawn(
+            async move {
+                token.cancelled().await;
+
+                // Stop tcpdump gracefully, so that it flushes the capture file.
+                if let Some(pid) = child.id() {
+                    nix::sys::signal::kill(
+                        nix::unistd::Pid::from_raw(pid as i32),
+                        nix::sys::signal::Signal::SIGTERM,
+                    )?;
+                }
+                child.wait().await?;
+
+                let result = check_capture(&pcap_path, &assertions).await;
+                if let Err(error) = tokio::fs::remove_file(&pcap_path).await {
+                    tracing::warn!(?error, ?pcap_path, "Failed to remove the capture file");
+                }
+                result
+            }
+            .instrument(tracing::Span::current()),
+        ))
+    }
+}
+
+async fn check_capture(pcap_path: &Path, assertions: &[CaptureAssertion]) -> Result<()> {
+    let pcap = tokio::fs::read(pcap_path)
+        .await
+        .with_context(|| format!("Failed to read the capture file {pcap_path:?}"))?;
+    let packets = parse_pcap(&pcap)?;
+    tracing::info!("Captured {} packets", packets.len());
+
+    let contains = |needle: &[u8]| {
+        needle.is_empty()
+            || packets
+                .iter()
+                .any(|packet| packet.windows(needle.len()).any(|window| window == needle))
+    };
+    for assertion in assertions {
+        match assertion {
+            CaptureAssertion::NotContains(needle) => {
+                if contains(needle) {
+                    bail!(
+                        "Found `{}` in the captured packets",
+                        String::from_utf8_lossy(needle)
+                    );
+                }
+            }
+            CaptureAssertion::Contains(needle) => {
+                if !contains(needle) {
+                    bail!(
+                        "`{}` is not found in the captured packets",
+                        String::from_utf8_lossy(needle)
+                    );
+                }
+            }
+            CaptureAssertion::MinPackets(min_packets) => {
+                if packets.len() < *min_packets {
+                    bail!(
+                        "Captured {} packets, but at least {min_packets} are expected",
+                        packets.len()
+                    );
+                }
+            }
+        }
+    }
+
+    tracing::info!("All the assertions on the captured packets passed");
+    Ok(())
+}
+
+/// Splits a file in the classic pcap format into the data of its packets.
+fn parse_pcap(pcap: &[u8]) -> Result<Vec<&[u8]>> {
+    const GLOBAL_HEADER_LEN: usize = 24;
+    const RECORD_HEADER_LEN: usize = 16;
+
+    let Some(magic) = pcap.get(..4) else {
+        bail!("The capture file is truncated");
+    };
+    let read_u32: fn([u8; 4]) -> u32 = match magic {
+        // Both the microsecond and the nanosecond variants
+        [0xd4, 0xc3, 0xb2, 0xa1] | [0x4d, 0x3c, 0xb2, 0xa1] => u32::from_le_bytes,
+        [0xa1, 0xb2, 0xc3, 0xd4] | [0xa1, 0xb2, 0x3c, 0x4d] => u32::from_be_bytes,
+        _ => bail!("Not a pcap file, magic: {magic:02x?}"),
+    };
+
+    let mut packets = vec![];
+    let mut offset = GLOBAL_HEADER_LEN;
+    while offset < pcap.len() {
+        let captured_len = pcap
+            .get(offset + 8..offset + 12)
+            .and_then(|len| len.try_into().ok())
+            .map(read_u32)
+            .context("The capture file is truncated")? as usize;
+        let start = offset + RECORD_HEADER_LEN;
+        let packet = pcap
+            .get(start..start + captured_len)
+            .context("The capture file is truncated")?;
+        packets.push(packet);
+        offset = start + captured_len;
+    }
+
+    Ok(packets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pcap() -> Result<()> {
+        let mut pcap = vec![0xd4, 0xc3, 0xb2, 0xa1];
+        pcap.extend_from_slice(&[0; 20]);
+        for packet in [&b"hello"[..], &b"world!"[..]] {
+            pcap.extend_from_slice(&[0; 8]);
+            pcap.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            pcap.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            pcap.extend_from_slice(packet);
+        }
+
+        assert_eq!(parse_pcap(&pcap)?, [&b"hello"[..], &b"world!"[..]]);
+        assert!(parse_pcap(&pcap[..pcap.len() - 1]).is_err());
+        assert!(parse_pcap(b"not a pcap file").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use super::TngInstance;
+use crate::task::{NodeType, Task};
+
+/// How long to keep trying to relaunch the instance, e.g. while the listening ports of the
+/// stopped one are still being released.
+const RELAUNCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs a TNG instance like [`TngInstance`] does, but stops it `kill_after` after it is launched
+/// and launches it again `down_for` later, `restarts` times. Put a client which tolerates the
+/// downtime after it, e.g. `AppType::TcpClientWithRetry`, to check that the traffic through the
+/// instance recovers once it is back, including the sessions pooled by its peer.
+pub struct TngChaosTask {
+    pub instance: TngInstance,
+    pub kill_after: Duration,
+    pub down_for: Duration,
+    pub restarts: u32,
+}
+
+#[async_trait]
+impl Task for TngChaosTask {
+    fn name(&self) -> String {
+        format!("{}_chaos", self.instance.name())
+    }
+
+    fn node_type(&self) -> NodeType {
+        self.instance.node_type()
+    }
+
+    async fn launch(&self, token: CancellationToken) -> Result<JoinHandle<Result<()>>> {
+        let instance = self.instance;
+        let kill_after = self.kill_after;
+        let down_for = self.down_for;
+        let restarts = self.restarts;
+
+        let mut instance_token = token.child_token();
+        let mut join_handle = instance.launch_inner(instance_token.clone()).await?;
+
+        Ok(tokio::task::spawn(
+            async move {
+                for i in 1..=restarts {
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        _ = tokio::time::sleep(kill_after) => {}
+                    }
+
+                    tracing::info!("Stopping the TNG instance ({i}/{restarts})");
+                    instance_token.cancel();
+                    join_handle
+                        .await?
+                        .context("The TNG instance failed before it was stopped")?;
+
+                    tokio::select! {
+                        _ = token.cancelled() => return Ok(()),
+                        _ = tokio::time::sleep(down_for) => {}
+                    }
+
+                    tracing::info!("Launching the TNG instance again ({i}/{restarts})");
+                    instance_token = token.child_token();
+                    join_handle = relaunch(instance, &instance_token).await?;
+                }
+
+                join_handle.await??;
+
+                tracing::info!("The TNG chaos task normally exited");
+                Ok(())
+            }
+            .instrument(tracing::Span::current()),
+        ))
+    }
+}
+
+async fn relaunch(
+    instance: TngInstance,
+    token: &CancellationToken,
+) -> Result<JoinHandle<Result<()>>> {
+    let deadline = tokio::time::Instant::now() + RELAUNCH_TIMEOUT;
+    loop {
+        match instance.launch_inner(token.clone()).await {
+            Ok(join_handle) => return Ok(join_handle),
+            Err(error) if tokio::time::Instant::now() < deadline => {
+                tracing::warn!(?error, "Failed to launch the TNG instance again, retrying");
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            Err(error) => return Err(error.context("Failed to launch the TNG instance again")),
+        }
+    }
+}
@@ -15,6 +15,8 @@ mod source_code;
 mod external;
 
 pub mod binary_locator;
+mod chaos;
+pub use chaos::TngChaosTask;
 mod exec;
 pub use exec::TngExecTask;
 mod readyz;
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use tng_testsuite::{
+    run_test,
+    task::{
+        app::AppType,
+        mock_as::{MockAsTask, MockAsVerdict},
+        tng::TngInstance,
+        NodeType, Task as _,
+    },
+};
+
+/// tng client as verifier and tng server as attester, with the built-in mock attestation service
+#[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+async fn test() -> Result<()> {
+    run_test!(
+        vec![
+            MockAsTask {
+                node_type: NodeType::Server,
+                verdict: MockAsVerdict::Pass,
+            }
+            .boxed(),
+            TngInstance::TngServer(
+                r#"
+                {
+                    "add_egress": [
+                        {
+                            "mapping": {
+                                "in": {
+                                    "host": "0.0.0.0",
+                                    "port": 20001
+                                },
+                                "out": {
+                                    "host": "127.0.0.1",
+                                    "port": 30001
+                                }
+                            },
+                            "attest": {
+                                "aa_addr": "unix:///run/confidential-containers/attestation-agent/attestation-agent.sock"
+                            }
+                        }
+                    ]
+                }
+                "#
+            ).boxed(),
+            TngInstance::TngClient(
+                r#"
+                {
+                    "add_ingress": [
+                        {
+                            "mapping": {
+                                "in": {
+                                    "port": 10001
+                                },
+                                "out": {
+                                    "host": "192.168.1.1",
+                                    "port": 20001
+                                }
+                            },
+                            "verify": {
+                                "as_addr": "http://192.168.1.254:8080/",
+                                "policy_ids": [
+                                    "default"
+                                ]
+                            }
+                        }
+                    ]
+                }
+                "#
+            ).boxed(),
+            AppType::TcpServer { port: 30001 }.boxed(),
+            AppType::TcpClient {
+                host: "127.0.0.1",
+                port: 10001,
+                http_proxy: None,
+            }.boxed(),
+        ]
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use tng_testsuite::{
+    run_test,
+    task::{
+        app::AppType,
+        packet_capture::{CaptureAssertion, PacketCaptureTask},
+        tng::TngInstance,
+        NodeType, Task as _,
+    },
+};
+
+/// The payload of the app is never seen in plaintext on the wire between the tng client and the
+/// tng server, while it is seen between the tng server and the app server.
+#[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+async fn test() -> Result<()> {
+    run_test!(vec![
+        PacketCaptureTask {
+            node_type: NodeType::Server,
+            interface: None,
+            filter: "tcp port 20001".to_owned(),
+            assertions: vec![
+                CaptureAssertion::MinPackets(1),
+                CaptureAssertion::NotContains(b"Hello World TCP!".to_vec()),
+            ],
+        }
+        .boxed(),
+        PacketCaptureTask {
+            node_type: NodeType::Server,
+            interface: Some("lo".to_owned()),
+            filter: "tcp port 30001".to_owned(),
+            assertions: vec![CaptureAssertion::Contains(b"Hello World TCP!".to_vec())],
+        }
+        .boxed(),
+        TngInstance::TngServer(
+            r#"
+        {
+            "add_egress": [
+                {
+                    "mapping": {
+                        "in": {
+                            "host": "0.0.0.0",
+                            "port": 20001
+                        },
+                        "out": {
+                            "host": "127.0.0.1",
+                            "port": 30001
+                        }
+                    },
+                    "no_ra": true
+                }
+            ]
+        }
+        "#,
+        )
+        .boxed(),
+        TngInstance::TngClient(
+            r#"
+        {
+            "add_ingress": [
+                {
+                    "mapping": {
+                        "in": {
+                            "port": 10001
+                        },
+                        "out": {
+                            "host": "192.168.1.1",
+                            "port": 20001
+                        }
+                    },
+                    "no_ra": true
+                }
+            ]
+        }
+        "#,
+        )
+        .boxed(),
+        AppType::TcpServer { port: 30001 }.boxed(),
+        AppType::TcpClient {
+            host: "127.0.0.1",
+            port: 10001,
+            http_proxy: None,
+        }
+        .boxed(),
+    ])
+    .await?;
+
+    Ok(())
+}
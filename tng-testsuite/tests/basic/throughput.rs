@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use tng_testsuite::{
+    run_test,
+    task::{
+        app::{AppType, ThroughputThresholds},
+        tng::TngInstance,
+        Task as _,
+    },
+};
+
+/// the throughput and the latency through the tunnel between the tng client and the tng server
+/// are above the thresholds
+#[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+async fn test() -> Result<()> {
+    run_test!(vec![
+        TngInstance::TngServer(
+            r#"
+        {
+            "add_egress": [
+                {
+                    "mapping": {
+                        "in": {
+                            "host": "0.0.0.0",
+                            "port": 20001
+                        },
+                        "out": {
+                            "host": "127.0.0.1",
+                            "port": 30001
+                        }
+                    },
+                    "no_ra": true
+                }
+            ]
+        }
+        "#,
+        )
+        .boxed(),
+        TngInstance::TngClient(
+            r#"
+        {
+            "add_ingress": [
+                {
+                    "mapping": {
+                        "in": {
+                            "port": 10001
+                        },
+                        "out": {
+                            "host": "192.168.1.1",
+                            "port": 20001
+                        }
+                    },
+                    "no_ra": true
+                }
+            ]
+        }
+        "#,
+        )
+        .boxed(),
+        AppType::TcpServer { port: 30001 }.boxed(),
+        AppType::TcpThroughputClient {
+            host: "127.0.0.1",
+            port: 10001,
+            total_bytes: 16 * 1024 * 1024,
+            // Loose enough for the CI runners, but a stall or a per-packet slowdown in the tunnel
+            // still fails it.
+            thresholds: ThroughputThresholds {
+                min_throughput_mbps: 10.0,
+                max_latency: Duration::from_millis(50),
+            },
+        }
+        .boxed(),
+    ])
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use tng_testsuite::{
+    run_test,
+    task::{
+        app::AppType,
+        tng::{TngChaosTask, TngInstance},
+        Task as _,
+    },
+};
+
+/// the tng server is restarted twice while the tcp client keeps sending traffic through the tng
+/// client, which should reconnect instead of reusing the broken pooled sessions
+#[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+async fn test() -> Result<()> {
+    run_test!(vec![
+        TngChaosTask {
+            instance: TngInstance::TngServer(
+                r#"
+            {
+                "add_egress": [
+                    {
+                        "mapping": {
+                            "in": {
+                                "host": "0.0.0.0",
+                                "port": 20001
+                            },
+                            "out": {
+                                "host": "127.0.0.1",
+                                "port": 30001
+                            }
+                        },
+                        "no_ra": true
+                    }
+                ]
+            }
+            "#,
+            ),
+            kill_after: Duration::from_secs(3),
+            down_for: Duration::from_secs(2),
+            restarts: 2,
+        }
+        .boxed(),
+        TngInstance::TngClient(
+            r#"
+        {
+            "add_ingress": [
+                {
+                    "mapping": {
+                        "in": {
+                            "port": 10001
+                        },
+                        "out": {
+                            "host": "192.168.1.1",
+                            "port": 20001
+                        }
+                    },
+                    "no_ra": true
+                }
+            ]
+        }
+        "#,
+        )
+        .boxed(),
+        AppType::TcpServer { port: 30001 }.boxed(),
+        AppType::TcpClientWithRetry {
+            host: "127.0.0.1",
+            port: 10001,
+            rounds: 30,
+            interval: Duration::from_millis(500),
+            recover_within: Duration::from_secs(20),
+        }
+        .boxed(),
+    ])
+    .await?;
+
+    Ok(())
+}
@@ -604,3 +604,72 @@ async fn test_mapping_udp_short_idle_timeout() -> Result<()> {
 
     Ok(())
 }
+
+/// A burst of UDP datagrams is delivered through the tunnel without loss or duplication
+#[serial]
+#[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+async fn test_mapping_udp_burst() -> Result<()> {
+    run_test!(vec![
+        TngInstance::TngServer(
+            r#"
+        {
+            "add_egress": [
+                {
+                    "mapping_udp": {
+                        "in": {
+                            "host": "0.0.0.0",
+                            "port": 20001
+                        },
+                        "out": {
+                            "host": "127.0.0.1",
+                            "port": 30001
+                        }
+                    },
+                    "quic": {
+                        "max_datagram_size": 1200
+                    },
+                    "no_ra": true
+                }
+            ]
+        }
+        "#,
+        )
+        .boxed(),
+        TngInstance::TngClient(
+            r#"
+        {
+            "add_ingress": [
+                {
+                    "mapping_udp": {
+                        "in": {
+                            "port": 10001
+                        },
+                        "out": {
+                            "host": "192.168.1.1",
+                            "port": 20001
+                        },
+                        "idle_timeout_secs": 30
+                    },
+                    "quic": {
+                        "max_datagram_size": 1200
+                    },
+                    "no_ra": true
+                }
+            ]
+        }
+        "#,
+        )
+        .boxed(),
+        AppType::UdpServer { port: 30001 }.boxed(),
+        AppType::UdpBurstClient {
+            host: "127.0.0.1",
+            port: 10001,
+            count: 50,
+            ordered: false,
+        }
+        .boxed(),
+    ])
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use anyhow::{bail, Result};
+use serial_test::serial;
+use tng_testsuite::{
+    run_test,
+    task::{function::FunctionTask, tng::TngInstance, NodeType, Task as _},
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// With `keys_endpoint` configured, a plain `GET` (without the `x-tng-ohttp-api`
+/// header) to that path returns the key configs in the RFC 9458
+/// `application/ohttp-keys` format.
+#[serial]
+#[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+async fn test_ohttp_keys_endpoint() -> Result<()> {
+    run_test!(vec![
+        TngInstance::TngServer(
+            r#"
+            {
+                "add_egress": [
+                    {
+                        "mapping": {
+                            "in": { "host": "0.0.0.0", "port": 20001 },
+                            "out": { "host": "127.0.0.1", "port": 30001 }
+                        },
+                        "ohttp": {
+                            "keys_endpoint": "/.well-known/ohttp-keys"
+                        },
+                        "no_ra": true
+                    }
+                ]
+            }
+            "#,
+        )
+        .boxed(),
+        FunctionTask {
+            name: "ohttp_keys_client".to_owned(),
+            node_type: NodeType::Client,
+            func: Box::new(fetch_ohttp_keys),
+        }
+        .boxed(),
+    ])
+    .await?;
+
+    Ok(())
+}
+
+fn fetch_ohttp_keys(token: CancellationToken) -> Result<JoinHandle<Result<()>>> {
+    Ok(tokio::spawn(async move {
+        let _drop_guard = token.drop_guard();
+
+        // Wait for the tng server to be ready
+        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+
+        let response = reqwest::get("http://192.168.1.1:20001/.well-known/ohttp-keys").await?;
+        if response.status() != reqwest::StatusCode::OK {
+            bail!("unexpected status code: {}", response.status());
+        }
+        let content_type = response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(ToOwned::to_owned);
+        if content_type.as_deref() != Some("application/ohttp-keys") {
+            bail!("unexpected content type: {content_type:?}");
+        }
+
+        // Each key config is prefixed with its 2-byte length
+        let body = response.bytes().await?;
+        if body.len() < 2 {
+            bail!("response body is too short: {} bytes", body.len());
+        }
+        let key_config_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+        if key_config_len == 0 || key_config_len + 2 != body.len() {
+            bail!(
+                "malformed key config list: declared length {key_config_len}, body length {}",
+                body.len()
+            );
+        }
+
+        tracing::info!("Got the OHTTP key config list from the keys endpoint");
+        Ok(())
+    }))
+}
@@ -1,10 +1,15 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
 use anyhow::{Context as _, Result};
 use gloo::utils::format::JsValueSerdeExt;
 use serde::Serialize;
+use serde_json::{Map, Value};
 use tng::{
     config::ra::{
         CocoConverterArgs, CocoVerifierArgs, ConverterArgs, RaArgs, VerifierArgs, VerifyArgs,
     },
+    tunnel::endpoint::TngEndpoint,
     AttestationResult,
 };
 use wasm_bindgen::prelude::*;
@@ -26,19 +31,54 @@ struct AttestationInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     ita_jwks_addr: Option<String>,
     attestation_result: AttestationResult,
+    /// The claims of `attestation_result`, flattened with `.` as separator.
+    claims: Map<String, Value>,
+    /// The `ear.status` of the token, for the EAR tokens of the CoCo AS. It is always
+    /// `affirming`, since the other ones fail the verification.
+    policy_status: Option<String>,
+    /// The `iat` and `exp` claims of the token, in seconds since the Unix epoch.
+    issued_at: Option<u64>,
+    expires_at: Option<u64>,
+    /// When the attestation result was received, in milliseconds since the Unix epoch.
+    verified_at: f64,
+}
+
+thread_local! {
+    /// The `attest_info` of the last completed fetch to each upstream endpoint.
+    static LAST_ATTEST_INFO: RefCell<HashMap<TngEndpoint, JsValue>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the `attest_info` of the last completed fetch to `endpoint`, or `undefined` if there
+/// is none yet.
+pub(super) fn last_attest_info(endpoint: &TngEndpoint) -> JsValue {
+    LAST_ATTEST_INFO.with_borrow(|infos| infos.get(endpoint).cloned().unwrap_or(JsValue::UNDEFINED))
 }
 
 pub(super) fn attach_attestation_info(
     web_response: web_sys::Response,
     attestation_result: AttestationResult,
+    endpoint: &TngEndpoint,
     ra_args: &RaArgs,
 ) -> Result<web_sys::Response, JsValue> {
+    let claims = attestation_result.claims().unwrap_or_else(|error| {
+        tracing::warn!(?error, "Failed to get the claims of the attestation result");
+        Map::new()
+    });
     let mut attest_info = AttestationInfo {
         as_addr: None,
         policy_ids: None,
         as_provider: None,
         ita_jwks_addr: None,
         attestation_result,
+        policy_status: claims
+            .iter()
+            .find(|(name, _)| name.ends_with("ear.status"))
+            .and_then(|(_, status)| status.as_str())
+            .map(str::to_owned),
+        issued_at: claims.get("iat").and_then(Value::as_u64),
+        expires_at: claims.get("exp").and_then(Value::as_u64),
+        claims,
+        verified_at: js_sys::Date::now(),
     };
 
     let verify_args = match ra_args {
@@ -50,7 +90,7 @@ pub(super) fn attach_attestation_info(
 
     if let Some(verify_args) = verify_args {
         match verify_args {
-            VerifyArgs::Passport { verifier } => match verifier {
+            VerifyArgs::Passport { verifier, .. } => match verifier {
                 VerifierArgs::Coco(coco) => match coco {
                     CocoVerifierArgs::Restful {
                         as_addr,
@@ -76,6 +116,7 @@ pub(super) fn attach_attestation_info(
             VerifyArgs::BackgroundCheck {
                 converter,
                 verifier,
+                ..
             } => match converter {
                 ConverterArgs::Coco(coco) => match coco {
                     CocoConverterArgs::Restful {
@@ -116,6 +157,7 @@ pub(super) fn attach_attestation_info(
         &JsValue::from_str("attest_info"),
         &attest_info_obj,
     )?;
+    LAST_ATTEST_INFO.with_borrow_mut(|infos| infos.insert(endpoint.clone(), attest_info_obj));
 
     Ok(web_response)
 }
@@ -0,0 +1,84 @@
+use anyhow::{anyhow, bail, Context as _, Result};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use tng::config::{
+    ingress::{OHttpArgs, RatsTlsArgs},
+    ra::RaArgsUnchecked,
+};
+use wasm_bindgen::{JsCast as _, JsValue};
+
+/// The fields accepted in the config of `fetch`.
+const FIELDS: &[&str] = &["no_ra", "verify", "ohttp", "rats_tls", "session_ttl_secs"];
+
+/// The configuration of `fetch`, which is the subset of the fields of an ingress that are
+/// meaningful in a browser: how the upstream is verified, and the protocol carrying the requests.
+#[derive(Debug)]
+pub(super) struct FetchConfig {
+    pub ra_args: RaArgsUnchecked,
+    pub ohttp: Option<OHttpArgs>,
+    pub rats_tls: Option<RatsTlsArgs>,
+    /// How long the rats-TLS sessions are reused by the following fetches, see
+    /// [`super::session`].
+    pub session_ttl_secs: Option<u64>,
+}
+
+impl FetchConfig {
+    /// Parses the config object passed to `fetch`. The fields are parsed one by one, so that the
+    /// errors name the field they come from.
+    pub(super) fn from_js(config: JsValue) -> Result<Self> {
+        let config = config
+            .dyn_into::<js_sys::Object>()
+            .map_err(|_| anyhow!("The config must be an object"))?;
+
+        let mut no_ra = None;
+        let mut verify = None;
+        let mut ohttp = None;
+        let mut rats_tls = None;
+        let mut session_ttl_secs = None;
+        for key in js_sys::Object::keys(&config) {
+            let Some(name) = key.as_string() else {
+                continue;
+            };
+            let value = js_sys::Reflect::get(&config, &key)
+                .map_err(|error| anyhow!("Failed to read the field `{name}`: {error:?}"))?;
+            if value.is_undefined() {
+                continue;
+            }
+
+            match name.as_str() {
+                "no_ra" => no_ra = Some(parse_field::<bool>(&name, value)?),
+                "verify" => verify = Some(parse_field::<serde_json::Value>(&name, value)?),
+                "ohttp" => ohttp = Some(parse_field(&name, value)?),
+                "rats_tls" => rats_tls = Some(parse_field(&name, value)?),
+                "session_ttl_secs" => session_ttl_secs = Some(parse_field(&name, value)?),
+                _ => bail!(
+                    "Unknown field `{name}` in the config, expected one of {}",
+                    FIELDS
+                        .iter()
+                        .map(|field| format!("`{field}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+        }
+
+        // Go through the parsing of the ingresses, which fills in the defaults of `verify`
+        let ra_args = serde_json::from_value(json!({
+            "no_ra": no_ra.unwrap_or_default(),
+            "verify": verify,
+        }))
+        .context("Invalid field `verify`")?;
+
+        Ok(Self {
+            ra_args,
+            ohttp,
+            rats_tls,
+            session_ttl_secs,
+        })
+    }
+}
+
+fn parse_field<T: DeserializeOwned>(name: &str, value: JsValue) -> Result<T> {
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|error| anyhow!("Invalid field `{name}`: {error}"))
+}
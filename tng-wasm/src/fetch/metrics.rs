@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+
+use gloo::utils::format::JsValueSerdeExt;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// The counters of the fetches since the module was loaded, which are passed to the callback
+/// registered with `set_metrics_callback` on each change. The native metric exporters are not
+/// available in a browser, so the page forwards them to its own telemetry instead.
+#[derive(Debug, Default, Clone, Serialize)]
+struct Metrics {
+    /// The calls to `fetch`.
+    requests: u64,
+    /// The rats-TLS sessions and OHTTP layers created, each of which attests the upstream again.
+    handshakes: u64,
+    /// The calls to `fetch` which failed before a response was returned.
+    failures: u64,
+    /// The bytes of the request bodies sent to the upstreams.
+    bytes_sent: u64,
+    /// The bytes of the response bodies received from the upstreams.
+    bytes_received: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Counter {
+    Requests,
+    Handshakes,
+    Failures,
+    BytesSent,
+    BytesReceived,
+}
+
+thread_local! {
+    static METRICS: RefCell<Metrics> = RefCell::new(Metrics::default());
+    static METRICS_CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+/// Replaces the callback receiving the counters, or removes it if `callback` is `None`.
+pub(super) fn set_callback(callback: Option<js_sys::Function>) {
+    METRICS_CALLBACK.set(callback)
+}
+
+/// Adds `value` to `counter`, and reports the counters to the callback, if any.
+pub(super) fn add(counter: Counter, value: u64) {
+    let metrics = METRICS.with_borrow_mut(|metrics| {
+        let field = match counter {
+            Counter::Requests => &mut metrics.requests,
+            Counter::Handshakes => &mut metrics.handshakes,
+            Counter::Failures => &mut metrics.failures,
+            Counter::BytesSent => &mut metrics.bytes_sent,
+            Counter::BytesReceived => &mut metrics.bytes_received,
+        };
+        *field += value;
+        metrics.clone()
+    });
+
+    // Clone the callback out first, so that the callback may replace itself.
+    let Some(callback) = METRICS_CALLBACK.with_borrow(Clone::clone) else {
+        return;
+    };
+    let metrics = match JsValue::from_serde(&metrics) {
+        Ok(metrics) => metrics,
+        Err(error) => {
+            tracing::warn!(?error, "Failed to convert the metrics");
+            return;
+        }
+    };
+    // A failing callback must not fail the fetch it is reported from.
+    if let Err(error) = callback.call1(&JsValue::NULL, &metrics) {
+        tracing::warn!(?error, "The metrics callback failed");
+    }
+}
@@ -1,23 +1,36 @@
+use std::rc::Rc;
 use std::sync::Arc;
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{bail, Context as _, Result};
 use tng::{
     config::{
-        ingress::{self, OHttpArgs},
+        ingress::{OHttpArgs, RatsTlsArgs},
         ra::RaArgs,
+        websocket::WebSocketArgs,
+    },
+    tunnel::{
+        endpoint::TngEndpoint,
+        ingress::protocol::{
+            ohttp::security::OHttpSecurityLayer, websocket::WebSocketSecurityLayer,
+        },
     },
-    tunnel::{endpoint::TngEndpoint, ingress::protocol::ohttp::security::OHttpSecurityLayer},
     AttestationResult, RaContext, TokioRuntime,
 };
 use wasm_bindgen::prelude::*;
 
 mod attestation;
+mod config;
+mod metrics;
 mod request;
 mod response;
+mod session;
 
-use self::attestation::attach_attestation_info;
+use self::attestation::{attach_attestation_info, last_attest_info};
+use self::config::FetchConfig;
+use self::metrics::Counter;
 use self::request::{build_http_request, parse_request_uri, upstream_endpoint};
 use self::response::convert_to_web_response;
+use self::session::{CachedSession, SessionKey, DEFAULT_SESSION_TTL_SECS};
 
 /// Map an error into a JS-side error, preserving its Debug representation.
 ///
@@ -29,41 +42,144 @@ pub(super) fn to_js_error<E: std::fmt::Debug>(error: E) -> JsValue {
     JsError::new(&format!("{error:?}")).into()
 }
 
+/// The protocol carrying the requests to the upstream.
+enum Transport {
+    OHttp(OHttpArgs),
+    /// A rats-TLS session inside a WebSocket connection, which is reused by the following
+    /// requests for `session_ttl_secs`.
+    WebSocket {
+        websocket: WebSocketArgs,
+        session_ttl_secs: u64,
+    },
+}
+
+impl Transport {
+    fn from_config(
+        ohttp: Option<OHttpArgs>,
+        rats_tls: Option<RatsTlsArgs>,
+        session_ttl_secs: Option<u64>,
+    ) -> Result<Self> {
+        match (ohttp, rats_tls) {
+            (Some(_), Some(_)) => {
+                bail!("Cannot specify both `ohttp` and `rats_tls` — they are mutually exclusive")
+            }
+            (ohttp, None) => {
+                if session_ttl_secs.is_some() {
+                    bail!("The `session_ttl_secs` field is only supported with `rats_tls`");
+                }
+                Ok(Self::OHttp(ohttp.unwrap_or_default()))
+            }
+            (None, Some(rats_tls)) => {
+                let Some(websocket) = rats_tls.websocket else {
+                    bail!("The `rats_tls` field is only supported with `rats_tls.websocket`");
+                };
+                if rats_tls.multiplex {
+                    bail!("`rats_tls.websocket` requires `rats_tls.multiplex` to be disabled");
+                }
+                if !rats_tls.alpn.is_empty() {
+                    bail!("The `rats_tls.alpn` field is not supported by tng-wasm");
+                }
+                Ok(Self::WebSocket {
+                    websocket,
+                    session_ttl_secs: session_ttl_secs.unwrap_or(DEFAULT_SESSION_TTL_SECS),
+                })
+            }
+        }
+    }
+
+    /// The scheme of the outer request, mirroring the scheme of the fetch URL.
+    fn scheme_from_url(&self, scheme: Option<&str>) -> &'static str {
+        match self {
+            Transport::OHttp(_) => OHttpSecurityLayer::scheme_from_url(scheme),
+            Transport::WebSocket { .. } => WebSocketSecurityLayer::scheme_from_url(scheme),
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub async fn fetch(
     url: String,
     init: web_sys::RequestInit,
     config: JsValue,
 ) -> Result<web_sys::Response, JsValue> {
-    let common_args: ingress::CommonArgs = serde_wasm_bindgen::from_value(config)
-        .map_err(|e| JsError::new(&format!("Failed to parse config: {e:?}")))?;
-
-    if common_args.web_page_inject {
-        Err(anyhow!("The `web_page_inject` field is not supported")).map_err(to_js_error)?
+    metrics::add(Counter::Requests, 1);
+    let result = fetch_with_config(url, init, config).await;
+    if result.is_err() {
+        metrics::add(Counter::Failures, 1);
     }
+    result
+}
 
-    let ohttp = common_args.ohttp.unwrap_or_default();
-    let ra_args = common_args
-        .ra_args
-        .clone()
-        .into_checked()
+async fn fetch_with_config(
+    url: String,
+    init: web_sys::RequestInit,
+    config: JsValue,
+) -> Result<web_sys::Response, JsValue> {
+    let fetch_config = FetchConfig::from_js(config)
+        .context("Failed to parse config")
         .map_err(to_js_error)?;
 
-    let (http_response, attestation_result) = dispatch_request(url, init, &ohttp, &ra_args).await?;
+    let ra_args = fetch_config.ra_args.into_checked().map_err(to_js_error)?;
+    let transport = Transport::from_config(
+        fetch_config.ohttp,
+        fetch_config.rats_tls,
+        fetch_config.session_ttl_secs,
+    )
+    .map_err(to_js_error)?;
+
+    let (http_response, attestation_result, endpoint) =
+        dispatch_request(url, init, &transport, &ra_args).await?;
 
     let web_response = convert_to_web_response(http_response).await?;
-    attach_attestation_info(web_response, attestation_result, &ra_args)
+    attach_attestation_info(web_response, attestation_result, &endpoint, &ra_args)
+}
+
+/// Returns the `attest_info` of the last completed `fetch` to the upstream of
+/// `url`, i.e. to the same host and port, or `undefined` if there is none yet.
+/// This lets a page show the attestation status of an upstream without holding
+/// on to the responses.
+#[wasm_bindgen]
+pub fn get_attest_info(url: String) -> Result<JsValue, JsValue> {
+    let request_uri = parse_request_uri(&url)?;
+    let endpoint = upstream_endpoint(&request_uri)?;
+    Ok(last_attest_info(&endpoint))
+}
+
+/// Drops the rats-TLS sessions and the OHTTP key configurations kept for reuse
+/// by the following fetches to the upstream of `url`, or to all the upstreams if
+/// `url` is not given, e.g. after the attestation policy of an upstream changed.
+#[wasm_bindgen]
+pub fn invalidate_sessions(url: Option<String>) -> Result<(), JsValue> {
+    match url {
+        Some(url) => {
+            let request_uri = parse_request_uri(&url)?;
+            let endpoint = upstream_endpoint(&request_uri)?;
+            session::invalidate_sessions(Some(&endpoint));
+        }
+        None => session::invalidate_sessions(None),
+    }
+    Ok(())
+}
+
+/// Registers `callback` to be called with the counters of the fetches on each
+/// change, i.e. `requests`, `handshakes`, `failures`, `bytes_sent` and
+/// `bytes_received`, so that the page can report them to its own telemetry.
+/// Replaces the previous callback, or removes it if `callback` is not given.
+#[wasm_bindgen]
+pub fn set_metrics_callback(callback: Option<js_sys::Function>) {
+    metrics::set_callback(callback);
 }
 
 /// Build a browser-side `web_sys::Request` from the caller's URL/init, convert
 /// it to an origin-form `http::Request`, and forward it through the OHTTP
-/// tunnel. Returns the upstream response together with its attestation result.
+/// or WebSocket tunnel. Returns the upstream response together with its
+/// attestation result and the upstream endpoint.
 async fn dispatch_request(
     url: String,
     init: web_sys::RequestInit,
-    ohttp: &OHttpArgs,
+    transport: &Transport,
     ra_args: &RaArgs,
-) -> Result<(axum::response::Response, AttestationResult), JsValue> {
+) -> Result<(axum::response::Response, AttestationResult, TngEndpoint), JsValue> {
     // 1. Construct the browser-side Request from the caller's URL/init.
     let web_request = web_sys::Request::new_with_str_and_init(&url, &init)?;
 
@@ -77,30 +193,34 @@ async fn dispatch_request(
     // TODO: note that in wasm mode, this field should be same as the http request in the body
     let endpoint = upstream_endpoint(&request_uri)?;
 
-    // 4. The outer OHTTP POST scheme mirrors the fetch URL's scheme (https ⇒
-    //    TLS, else plain http). Normalize it to a &'static str BEFORE
+    // 4. The outer OHTTP POST (or WebSocket) scheme mirrors the fetch URL's
+    //    scheme (https ⇒ TLS, else plain http). Normalize it to a &'static str BEFORE
     //    build_http_request moves `request_uri` — build_http_request strips
     //    scheme+authority (so the origin-form URI no longer carries the scheme
     //    afterwards), and scheme_from_url returns a &'static str literal that
     //    does not borrow the URI, so it survives the move with no allocation.
-    let url_scheme = OHttpSecurityLayer::scheme_from_url(request_uri.scheme_str());
+    let url_scheme = transport.scheme_from_url(request_uri.scheme_str());
 
     // 5. Build the http::Request (origin-form URI + Host header, matching the
-    //    daemon's http_proxy forwarding) and forward it through the OHTTP layer.
+    //    daemon's http_proxy forwarding) and forward it through the OHTTP or
+    //    WebSocket layer.
     let http_request = build_http_request(web_request, request_uri).await?;
-    forward_request(&endpoint, ohttp, url_scheme, ra_args, http_request)
-        .await
-        .map_err(to_js_error)
+    let (http_response, attestation_result) =
+        forward_request(&endpoint, transport, url_scheme, ra_args, http_request)
+            .await
+            .map_err(to_js_error)?;
+    Ok((http_response, attestation_result, endpoint))
 }
 
-/// Forward a built `http::Request` through the OHTTP security layer to the
-/// upstream endpoint, returning the response and its attestation result.
+/// Forward a built `http::Request` through the OHTTP or WebSocket security layer
+/// to the upstream endpoint, returning the response and its attestation result.
 async fn forward_request(
     endpoint: &TngEndpoint,
-    ohttp: &OHttpArgs,
+    transport: &Transport,
     // Outer OHTTP POST scheme, pre-normalized via OHttpSecurityLayer::scheme_from_url
     // from the fetch URL's scheme (https ⇒ https, else http). Native builds ignore this
-    // and derive the scheme from ohttp.tls instead.
+    // and derive the scheme from ohttp.tls instead. With the WebSocket transport, this
+    // is the WebSocket scheme instead (https ⇒ wss, else ws).
     url_scheme: &'static str,
     ra_args: &RaArgs,
     request: axum::extract::Request,
@@ -120,13 +240,65 @@ async fn forward_request(
     let shutdown = tokio_graceful::Shutdown::no_signal();
     let runtime = TokioRuntime::wasm_main_thread(shutdown.guard())?;
 
-    let ra_context = Arc::new(RaContext::from_ra_args(ra_args).await?);
-    let ohttp_security_layer =
-        OHttpSecurityLayer::new(ohttp, url_scheme, ra_context, runtime.clone()).await?;
-
-    let (response, attestation_result) = ohttp_security_layer
-        .forward_http_request(endpoint, request)
-        .await?;
+    let (response, attestation_result) = match transport {
+        Transport::OHttp(ohttp) => {
+            // Reuse the layer of the previous fetches, so that the key configuration of the
+            // gateway is only fetched again when it expires.
+            let layer_key = SessionKey::new(
+                endpoint.clone(),
+                format!("{ra_args:?} {ohttp:?} {url_scheme}"),
+            );
+            let ohttp_security_layer = match session::get_ohttp_layer(&layer_key) {
+                Some(ohttp_security_layer) => ohttp_security_layer,
+                None => {
+                    metrics::add(Counter::Handshakes, 1);
+                    let ra_context = Arc::new(RaContext::from_ra_args(ra_args).await?);
+                    let ohttp_security_layer = Rc::new(
+                        OHttpSecurityLayer::new(ohttp, url_scheme, ra_context, runtime.clone())
+                            .await?,
+                    );
+                    session::put_ohttp_layer(layer_key, ohttp_security_layer.clone());
+                    ohttp_security_layer
+                }
+            };
+            ohttp_security_layer
+                .forward_http_request(endpoint, request)
+                .await?
+        }
+        Transport::WebSocket {
+            websocket,
+            session_ttl_secs,
+        } => {
+            let session_key = SessionKey::new(
+                endpoint.clone(),
+                format!("{ra_args:?} {websocket:?} {url_scheme}"),
+            );
+            let mut session = match session::take_session(&session_key, *session_ttl_secs) {
+                Some(session) => {
+                    tracing::debug!("Reusing a cached rats-tls session");
+                    session
+                }
+                None => {
+                    metrics::add(Counter::Handshakes, 1);
+                    let ra_context = Arc::new(RaContext::from_ra_args(ra_args).await?);
+                    let websocket_security_layer = WebSocketSecurityLayer::new(
+                        websocket,
+                        url_scheme,
+                        ra_context,
+                        runtime.clone(),
+                    )
+                    .await?;
+                    CachedSession::new(websocket_security_layer.connect(endpoint).await?)
+                }
+            };
+            let response = session.session.send_http_request(request).await?;
+            let attestation_result = session.session.attestation_result().cloned();
+            if *session_ttl_secs > 0 {
+                session::put_session(session_key, session);
+            }
+            (response, attestation_result)
+        }
+    };
 
     tracing::info!(?attestation_result, "start forward task");
     let Some(attestation_result) = attestation_result else {
@@ -3,6 +3,7 @@ use futures::{AsyncReadExt, StreamExt, TryStreamExt};
 use tng::tunnel::endpoint::TngEndpoint;
 use wasm_bindgen::prelude::*;
 
+use super::metrics::{self, Counter};
 use super::to_js_error;
 
 /// Parse an absolute request URL into an `http::Uri`, mapping parse failures
@@ -83,6 +84,7 @@ pub(super) async fn build_http_request(
     // Get ReadableStream from web_sys::Request and convert to Vec<u8>
     let body = if let Some(body_stream) = gloo_request.body() {
         let body_bytes = read_body_to_bytes(body_stream).await.map_err(to_js_error)?;
+        metrics::add(Counter::BytesSent, body_bytes.len() as u64);
         axum::body::Body::from(body_bytes)
     } else {
         axum::body::Body::empty()
@@ -3,6 +3,7 @@ use futures::TryStreamExt;
 use http_body_util::BodyDataStream;
 use wasm_bindgen::prelude::*;
 
+use super::metrics::{self, Counter};
 use super::to_js_error;
 
 pub(super) async fn convert_to_web_response(
@@ -30,6 +31,7 @@ pub(super) async fn convert_to_web_response(
     let readable_stream = wasm_streams::ReadableStream::from_stream(
         BodyDataStream::new(body_stream)
             .map_ok(|v| {
+                metrics::add(Counter::BytesReceived, v.len() as u64);
                 let array = js_sys::Uint8Array::new_with_length(v.len() as u32);
                 array.copy_from(&v);
                 array.into()
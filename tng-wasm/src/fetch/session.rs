@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use tng::tunnel::{
+    endpoint::TngEndpoint,
+    ingress::protocol::{ohttp::security::OHttpSecurityLayer, websocket::WebSocketSession},
+};
+
+/// The default of the `session_ttl_secs` field of the config.
+pub(super) const DEFAULT_SESSION_TTL_SECS: u64 = 300;
+
+/// Identifies the sessions which can be shared by different fetches: the sessions are only
+/// reused by the fetches to the same upstream, with the same verification config and transport.
+#[derive(PartialEq, Eq, Hash, Clone)]
+pub(super) struct SessionKey {
+    endpoint: TngEndpoint,
+    /// The Debug representation of the verification config and of the transport, since the
+    /// sessions verified against another policy must not be reused.
+    config: String,
+}
+
+impl SessionKey {
+    pub fn new(endpoint: TngEndpoint, config: String) -> Self {
+        Self { endpoint, config }
+    }
+}
+
+/// A session taken out of the cache, or established, by a fetch.
+pub(super) struct CachedSession {
+    pub session: WebSocketSession,
+    /// When the session was established, in milliseconds since the Unix epoch.
+    established_at: f64,
+    /// The invalidations of the cache when the session was taken out, see [`SessionCache`].
+    generation: u64,
+}
+
+impl CachedSession {
+    pub fn new(session: WebSocketSession) -> Self {
+        Self {
+            session,
+            established_at: js_sys::Date::now(),
+            generation: SESSION_CACHE.with_borrow(|cache| cache.generation),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SessionCache {
+    /// The established sessions which are not in use by a fetch.
+    sessions: HashMap<SessionKey, Vec<CachedSession>>,
+    /// The OHTTP layers, which keep the key configurations advertised by the gateways, and the
+    /// attestation results of the gateways, until they expire.
+    ohttp_layers: HashMap<SessionKey, Rc<OHttpSecurityLayer>>,
+    /// Increased on each invalidation, so that the sessions in use by a fetch during an
+    /// invalidation are not put back afterwards.
+    generation: u64,
+}
+
+thread_local! {
+    static SESSION_CACHE: RefCell<SessionCache> = RefCell::new(SessionCache::default());
+}
+
+/// Takes out a cached session for `key` which is ready to send a request, if any. The sessions
+/// which are closed or older than `ttl_secs` are dropped.
+pub(super) fn take_session(key: &SessionKey, ttl_secs: u64) -> Option<CachedSession> {
+    let now = js_sys::Date::now();
+    SESSION_CACHE.with_borrow_mut(|cache| {
+        let cached = cache.sessions.get_mut(key)?;
+        cached.retain(|cached| {
+            !cached.session.is_closed() && now - cached.established_at < ttl_secs as f64 * 1000.0
+        });
+        let session = cached
+            .iter()
+            .position(|cached| cached.session.is_ready())
+            .map(|index| cached.swap_remove(index));
+        if cached.is_empty() {
+            cache.sessions.remove(key);
+        }
+        session
+    })
+}
+
+/// Puts back a session after a fetch, so that the following fetches to the same destination can
+/// reuse it. It becomes ready again once the response body has been read entirely.
+pub(super) fn put_session(key: SessionKey, session: CachedSession) {
+    SESSION_CACHE.with_borrow_mut(|cache| {
+        if session.generation == cache.generation {
+            cache.sessions.entry(key).or_default().push(session);
+        }
+    })
+}
+
+/// Returns the OHTTP layer for `key`, if one was created by a previous fetch.
+pub(super) fn get_ohttp_layer(key: &SessionKey) -> Option<Rc<OHttpSecurityLayer>> {
+    SESSION_CACHE.with_borrow(|cache| cache.ohttp_layers.get(key).cloned())
+}
+
+/// Keeps an OHTTP layer for the following fetches with the same `key`.
+pub(super) fn put_ohttp_layer(key: SessionKey, ohttp_layer: Rc<OHttpSecurityLayer>) {
+    SESSION_CACHE.with_borrow_mut(|cache| {
+        cache.ohttp_layers.insert(key, ohttp_layer);
+    })
+}
+
+/// Drops the cached sessions and OHTTP layers to `endpoint`, or all of them if `endpoint` is
+/// `None`. The sessions in use by the fetches in progress are dropped once they complete.
+pub(super) fn invalidate_sessions(endpoint: Option<&TngEndpoint>) {
+    SESSION_CACHE.with_borrow_mut(|cache| {
+        match endpoint {
+            Some(endpoint) => {
+                cache.sessions.retain(|key, _| &key.endpoint != endpoint);
+                cache
+                    .ohttp_layers
+                    .retain(|key, _| &key.endpoint != endpoint);
+            }
+            None => {
+                cache.sessions.clear();
+                cache.ohttp_layers.clear();
+            }
+        }
+        cache.generation += 1;
+    })
+}
@@ -17,3 +17,78 @@ async fn test_init() -> Result<(), JsError> {
 
     Ok(())
 }
+
+#[wasm_bindgen_test]
+fn test_get_attest_info_without_fetch() -> Result<(), JsValue> {
+    let attest_info = tng_wasm::fetch::get_attest_info("http://127.0.0.1:30001/".to_string())?;
+    assert!(attest_info.is_undefined());
+
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+async fn test_fetch_with_invalid_config() -> Result<(), JsValue> {
+    let fetch_error = |config: serde_json::Value| async move {
+        let config = serde_wasm_bindgen::to_value(&config)?;
+        let error = tng_wasm::fetch::fetch(
+            "http://127.0.0.1:30001/".to_string(),
+            web_sys::RequestInit::new(),
+            config,
+        )
+        .await
+        .expect_err("the config should be rejected");
+        Ok::<_, JsValue>(String::from(
+            error.unchecked_into::<js_sys::Error>().message(),
+        ))
+    };
+
+    let message = fetch_error(serde_json::json!({ "ohttp": {}, "unknown": 1 })).await?;
+    assert!(message.contains("Unknown field `unknown`"), "{message}");
+
+    let message = fetch_error(serde_json::json!({ "no_ra": "yes" })).await?;
+    assert!(message.contains("Invalid field `no_ra`"), "{message}");
+
+    let message = fetch_error(serde_json::json!({ "ohttp": { "path_rewrites": 1 } })).await?;
+    assert!(message.contains("Invalid field `ohttp`"), "{message}");
+
+    let message = fetch_error(serde_json::json!({ "ohttp": {}, "session_ttl_secs": 60 })).await?;
+    assert!(message.contains("`session_ttl_secs`"), "{message}");
+
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_invalidate_sessions() -> Result<(), JsValue> {
+    tng_wasm::fetch::invalidate_sessions(Some("http://127.0.0.1:30001/".to_string()))?;
+    tng_wasm::fetch::invalidate_sessions(None)?;
+    assert!(tng_wasm::fetch::invalidate_sessions(Some("not a url".to_string())).is_err());
+
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+async fn test_metrics_callback() -> Result<(), JsValue> {
+    let reported = js_sys::Array::new();
+    let callback = js_sys::Function::new_with_args("metrics", "this.push(metrics)").bind(&reported);
+    tng_wasm::fetch::set_metrics_callback(Some(callback));
+
+    let config = serde_wasm_bindgen::to_value(&serde_json::json!({ "unknown": 1 }))?;
+    let _ = tng_wasm::fetch::fetch(
+        "http://127.0.0.1:30001/".to_string(),
+        web_sys::RequestInit::new(),
+        config,
+    )
+    .await
+    .expect_err("the config should be rejected");
+    tng_wasm::fetch::set_metrics_callback(None);
+
+    // One report for the request, and one for its failure
+    assert_eq!(reported.length(), 2);
+    let metrics = reported.get(1);
+    for name in ["requests", "failures"] {
+        let value = js_sys::Reflect::get(&metrics, &name.into())?;
+        assert!(value.as_f64().is_some_and(|value| value >= 1.0), "{name}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,197 @@
+//! Accepting the streams of the trusted tunnel on the connections of the application, without
+//! running an egress.
+
+use std::{future::Future, sync::Arc};
+
+use anyhow::Result;
+use futures::StreamExt as _;
+use tokio_graceful::Shutdown;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::{
+    config::egress::CommonArgs,
+    tunnel::{
+        egress::stream_manager::{trusted::TrustedStreamManager, NextStream, StreamManager as _},
+        service_metrics::ServiceMetrics,
+    },
+    AttestationResult, CommonStreamTrait, TokioRuntime,
+};
+
+/// A stream decapsulated by a [`TngAcceptor`].
+pub struct AcceptedStream {
+    /// The plaintext stream from the peer.
+    pub stream: Box<dyn CommonStreamTrait>,
+    /// Whether the stream was carried through the tunnel. It is not if it matched the
+    /// `direct_forward` rules, in which case it is handed over as it was received.
+    pub encrypted: bool,
+    /// The attestation result of the peer, if it was attested.
+    pub attestation_result: Option<AttestationResult>,
+}
+
+/// Decapsulates the trusted streams carried by the connections which the application accepts
+/// itself, like an egress does for the connections to its listener, and hands them to a handler
+/// instead of forwarding them to an upstream.
+///
+/// The acceptor is configured with the parameters of an egress, e.g. `attest`, `rats_tls` or
+/// `ohttp`.
+///
+/// ```no_run
+/// use tng::acceptor::TngAcceptor;
+/// use tng::config::egress::CommonArgs;
+/// # async fn example(common_args: CommonArgs) -> anyhow::Result<()> {
+///
+/// let acceptor = TngAcceptor::new(&common_args).await?;
+/// let listener = tokio::net::TcpListener::bind("0.0.0.0:20001").await?;
+/// loop {
+///     let (connection, _) = listener.accept().await?;
+///     acceptor
+///         .serve_connection(connection, |accepted| async move {
+///             println!("Attested peer: {:?}", accepted.attestation_result);
+///         })
+///         .await?;
+/// }
+/// # }
+/// ```
+pub struct TngAcceptor {
+    stream_manager: Arc<TrustedStreamManager>,
+    runtime: TokioRuntime,
+    /// Stops serving the connections and the streams when the acceptor is dropped.
+    _cancel_on_drop: DropGuard,
+    _shutdown: Shutdown,
+}
+
+impl TngAcceptor {
+    /// Creates an acceptor with the `common_args` of an egress, getting the evidence from the
+    /// attestation agent if `attest` is set.
+    ///
+    /// Must be called within a tokio runtime, which the connections are served on.
+    pub async fn new(common_args: &CommonArgs) -> Result<Self> {
+        if let Some(buffer_size) = &common_args.buffer_size {
+            buffer_size.validate()?;
+        }
+
+        let cancel = CancellationToken::new();
+        let shutdown = {
+            let cancel = cancel.clone();
+            Shutdown::new(async move { cancel.cancelled().await })
+        };
+        let runtime = TokioRuntime::current(shutdown.guard())?;
+
+        let metrics = ServiceMetrics::noop();
+        let stream_manager =
+            Arc::new(TrustedStreamManager::new(common_args, &metrics, runtime.clone()).await?);
+
+        Ok(Self {
+            stream_manager,
+            runtime,
+            _cancel_on_drop: cancel.drop_guard(),
+            _shutdown: shutdown,
+        })
+    }
+
+    /// Serves a connection accepted by the application, calling `handler` in a task of its own
+    /// with each of the streams decapsulated from it, e.g. several of them with
+    /// `rats_tls.multiplex`.
+    ///
+    /// Returns once the connection is closed by the peer, or fails to be decapsulated, e.g. if
+    /// the peer is not a TNG ingress. The handlers may still be running then.
+    pub async fn serve_connection<F, Fut>(
+        &self,
+        connection: impl CommonStreamTrait + Sync,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(AcceptedStream) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut pending = self
+            .stream_manager
+            .consume_stream(Box::new(connection))
+            .await?;
+
+        let handler = Arc::new(handler);
+        while let Some(next_stream) = pending.next().await {
+            let next_stream = match next_stream {
+                Ok(next_stream) => next_stream,
+                Err(error) => {
+                    tracing::error!(?error, "Failed to get next stream");
+                    continue;
+                }
+            };
+
+            let accepted = match next_stream {
+                NextStream::Secured(stream, attestation_result) => AcceptedStream {
+                    stream,
+                    encrypted: true,
+                    attestation_result,
+                },
+                NextStream::DirectlyForward(stream) => AcceptedStream {
+                    stream,
+                    encrypted: false,
+                    attestation_result: None,
+                },
+            };
+            let handler = handler.clone();
+            self.runtime
+                .spawn_supervised_task_current_span(async move { handler(accepted).await });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use crate::{client::TngClient, tunnel::endpoint::TngEndpoint};
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_acceptor_serve_connection() -> Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let acceptor = TngAcceptor::new(&serde_json::from_value(json!({ "no_ra": true }))?).await?;
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        #[allow(clippy::disallowed_methods)]
+        tokio::task::spawn(async move {
+            let (connection, _) = listener.accept().await?;
+            acceptor
+                .serve_connection(connection, move |accepted| {
+                    let sender = sender.clone();
+                    async move {
+                        let AcceptedStream {
+                            stream,
+                            encrypted,
+                            attestation_result,
+                        } = accepted;
+                        let _ = sender.send((encrypted, attestation_result));
+                        let (mut reader, mut writer) = tokio::io::split(stream);
+                        let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                        let _ = writer.shutdown().await;
+                    }
+                })
+                .await?;
+            // Keep the acceptor until the stream is echoed
+            std::future::pending::<()>().await;
+            Ok::<_, anyhow::Error>(())
+        });
+
+        let client = TngClient::new(&serde_json::from_value(json!({ "no_ra": true }))?).await?;
+        let (mut stream, _) = client.connect(&TngEndpoint::new("127.0.0.1", port)).await?;
+        stream.write_all(b"hello").await?;
+        stream.shutdown().await?;
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await?;
+        assert_eq!(response, b"hello");
+
+        let (encrypted, attestation_result) = receiver.recv().await.unwrap();
+        assert!(encrypted);
+        assert!(attestation_result.is_none());
+
+        Ok(())
+    }
+}
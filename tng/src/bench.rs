@@ -0,0 +1,448 @@
+//! Built-in load testing, which is run by `tng bench`. A local echo server is put behind a
+//! `mapping` egress, which is reached through a `mapping` ingress of the same TNG instance, and
+//! concurrent clients send requests through the pair until the duration is over.
+
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context as _, Result};
+use serde_json::json;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+use web_time_compat::{Instant, InstantExt as _};
+
+use crate::config::TngConfig;
+use crate::runtime::{TngRuntime, TracingReloadHandle};
+use crate::tunnel::utils::runtime::TokioRuntime;
+
+/// How long to wait for the ingress and the egress to be ready, which includes fetching the
+/// evidence when remote attestation is configured.
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// The number of clients sending requests at the same time.
+    pub concurrency: usize,
+
+    /// The bytes of each request, which are echoed back by the server.
+    pub payload_size: usize,
+
+    /// How long the clients keep sending requests.
+    pub duration: Duration,
+
+    /// The requests sent on a connection before it is closed and a new one is opened.
+    pub requests_per_connection: usize,
+
+    /// Set `rats_tls.multiplex` on the ingress and the egress.
+    pub multiplex: bool,
+
+    /// The remote attestation fields of the ingress, e.g. `{"verify": {...}}`. Defaults to
+    /// `{"no_ra": true}`.
+    pub ingress_ra: Option<serde_json::Value>,
+
+    /// The remote attestation fields of the egress, e.g. `{"attest": {...}}`. Defaults to
+    /// `{"no_ra": true}`.
+    pub egress_ra: Option<serde_json::Value>,
+}
+
+impl BenchOptions {
+    fn validate(&self) -> Result<()> {
+        if self.concurrency == 0 {
+            bail!("The concurrency must be greater than 0");
+        }
+        if self.payload_size == 0 {
+            bail!("The payload size must be greater than 0");
+        }
+        if self.requests_per_connection == 0 {
+            bail!("The requests per connection must be greater than 0");
+        }
+        if self.duration.is_zero() {
+            bail!("The duration must be greater than 0");
+        }
+        Ok(())
+    }
+
+    /// The config of the TNG instance under test, with the egress forwarding to `echo_port`.
+    fn tng_config(&self, echo_port: u16, egress_port: u16, ingress_port: u16) -> Result<TngConfig> {
+        let mut ingress = json!({
+            "mapping": {
+                "in": { "host": "127.0.0.1", "port": ingress_port },
+                "out": { "host": "127.0.0.1", "port": egress_port }
+            },
+            "rats_tls": { "multiplex": self.multiplex }
+        });
+        merge_ra_fields(&mut ingress, self.ingress_ra.as_ref()).context("Invalid ingress RA")?;
+
+        let mut egress = json!({
+            "mapping": {
+                "in": { "host": "127.0.0.1", "port": egress_port },
+                "out": { "host": "127.0.0.1", "port": echo_port }
+            },
+            "rats_tls": { "multiplex": self.multiplex }
+        });
+        merge_ra_fields(&mut egress, self.egress_ra.as_ref()).context("Invalid egress RA")?;
+
+        serde_json::from_value(json!({
+            "add_ingress": [ingress],
+            "add_egress": [egress]
+        }))
+        .context("Failed to build the TNG config for the benchmark")
+    }
+}
+
+/// Adds the remote attestation fields to an ingress or egress entry.
+fn merge_ra_fields(entry: &mut serde_json::Value, ra: Option<&serde_json::Value>) -> Result<()> {
+    let no_ra = json!({ "no_ra": true });
+    let Some(fields) = ra.unwrap_or(&no_ra).as_object() else {
+        bail!("The remote attestation fields must be a JSON object");
+    };
+    if let Some(entry) = entry.as_object_mut() {
+        entry.extend(fields.clone());
+    }
+    Ok(())
+}
+
+/// The results of a benchmark run.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub elapsed: Duration,
+    pub payload_size: usize,
+    /// The requests echoed back successfully.
+    pub requests: u64,
+    /// The connections which got their first response. Each of them goes through a rats-tls
+    /// handshake, unless the sessions are multiplexed.
+    pub connections: u64,
+    /// The connections closed by an error.
+    pub errors: u64,
+    /// The latencies of the requests, sorted.
+    pub latencies: Vec<Duration>,
+    /// The time from opening a connection to its first response, sorted.
+    pub first_response_latencies: Vec<Duration>,
+}
+
+impl BenchReport {
+    pub fn requests_per_sec(&self) -> f64 {
+        self.requests as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// The payload bytes echoed back per second, which is also the rate in each direction.
+    pub fn throughput_bytes_per_sec(&self) -> f64 {
+        (self.requests * self.payload_size as u64) as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn connections_per_sec(&self) -> f64 {
+        self.connections as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn latency_percentile(&self, percent: f64) -> Option<Duration> {
+        percentile(&self.latencies, percent)
+    }
+
+    pub fn first_response_percentile(&self, percent: f64) -> Option<Duration> {
+        percentile(&self.first_response_latencies, percent)
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = |latency: Option<Duration>| match latency {
+            Some(latency) => format!("{:.3} ms", latency.as_secs_f64() * 1000.0),
+            None => "-".to_owned(),
+        };
+
+        writeln!(f, "Duration:        {:.2} s", self.elapsed.as_secs_f64())?;
+        writeln!(
+            f,
+            "Requests:        {} ({:.1} req/s)",
+            self.requests,
+            self.requests_per_sec()
+        )?;
+        writeln!(
+            f,
+            "Throughput:      {:.2} MiB/s in each direction",
+            self.throughput_bytes_per_sec() / (1024.0 * 1024.0)
+        )?;
+        writeln!(
+            f,
+            "Latency:         p50 {}, p99 {}, max {}",
+            millis(self.latency_percentile(50.0)),
+            millis(self.latency_percentile(99.0)),
+            millis(self.latencies.last().copied())
+        )?;
+        writeln!(
+            f,
+            "Connections:     {} ({:.1} conn/s)",
+            self.connections,
+            self.connections_per_sec()
+        )?;
+        writeln!(
+            f,
+            "First response:  p50 {}, p99 {}",
+            millis(self.first_response_percentile(50.0)),
+            millis(self.first_response_percentile(99.0))
+        )?;
+        write!(f, "Errors:          {}", self.errors)
+    }
+}
+
+/// The nearest-rank percentile of the sorted samples.
+fn percentile(sorted: &[Duration], percent: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.clamp(1, sorted.len()) - 1).copied()
+}
+
+/// The results collected by one client.
+#[derive(Default)]
+struct ClientStats {
+    requests: u64,
+    connections: u64,
+    errors: u64,
+    latencies: Vec<Duration>,
+    first_response_latencies: Vec<Duration>,
+}
+
+pub struct TngBench;
+
+impl TngBench {
+    /// Runs a TNG instance with an ingress and an egress in front of a local echo server, and
+    /// sends requests through them with `options.concurrency` clients.
+    pub async fn run(
+        options: BenchOptions,
+        reload_handle: &TracingReloadHandle,
+    ) -> Result<BenchReport> {
+        options.validate()?;
+
+        let cancel = CancellationToken::new();
+        let shutdown = {
+            let cancel = cancel.clone();
+            tokio_graceful::Shutdown::new(async move { cancel.cancelled().await })
+        };
+        let runtime = TokioRuntime::current(shutdown.guard())?;
+
+        let echo_listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .context("Failed to bind the echo server")?;
+        let echo_port = echo_listener.local_addr()?.port();
+        runtime.spawn_supervised_task_fn(move |runtime| serve_echo(runtime, echo_listener));
+
+        let (egress_port, ingress_port) = pick_port_pair()?;
+        let config = options.tng_config(echo_port, egress_port, ingress_port)?;
+        tracing::debug!(?config, "TNG config of the benchmark");
+
+        let tng_runtime = TngRuntime::from_config_with_reload_handle(config, reload_handle).await?;
+        let canceller = tng_runtime.canceller();
+        let (ready_sender, ready_receiver) = tokio::sync::oneshot::channel();
+
+        let (served, report) = tokio::join!(tng_runtime.serve_with_ready(ready_sender), async {
+            let report = async {
+                tokio::time::timeout(READY_TIMEOUT, ready_receiver)
+                    .await
+                    .context("Timeout waiting for the TNG instance to be ready")?
+                    .context("The TNG instance exited before it was ready")?;
+
+                tracing::info!(
+                    concurrency = options.concurrency,
+                    payload_size = options.payload_size,
+                    duration = ?options.duration,
+                    "Benchmark started"
+                );
+                drive_clients(
+                    &runtime,
+                    &options,
+                    SocketAddr::from((Ipv4Addr::LOCALHOST, ingress_port)),
+                )
+                .await
+            }
+            .await;
+            canceller.cancel();
+            report
+        });
+
+        drop(runtime);
+        cancel.cancel();
+        shutdown.shutdown().await;
+
+        served.context("The TNG instance failed during the benchmark")?;
+        report
+    }
+}
+
+/// Picks two different unused ports, for the egress and the ingress.
+fn pick_port_pair() -> Result<(u16, u16)> {
+    let pick = || portpicker::pick_unused_port().context("No unused port is available");
+    let first = pick()?;
+    for _ in 0..50 {
+        let second = pick()?;
+        if second != first {
+            return Ok((first, second));
+        }
+    }
+    bail!("Failed to pick two different unused ports")
+}
+
+/// Echoes back everything received on each accepted connection.
+async fn serve_echo(runtime: TokioRuntime, listener: TcpListener) -> Result<()> {
+    loop {
+        let (mut stream, _) = listener
+            .accept()
+            .await
+            .context("Failed to accept connection on the echo server")?;
+        runtime.spawn_supervised_task(async move {
+            let (mut reader, mut writer) = stream.split();
+            if let Err(error) = tokio::io::copy(&mut reader, &mut writer).await {
+                tracing::debug!(?error, "Echo connection closed with error");
+            }
+        });
+    }
+}
+
+async fn drive_clients(
+    runtime: &TokioRuntime,
+    options: &BenchOptions,
+    ingress_addr: SocketAddr,
+) -> Result<BenchReport> {
+    let payload: Arc<[u8]> = (0..options.payload_size).map(|i| i as u8).collect();
+    let started = Instant::get();
+    let deadline = started + options.duration;
+
+    let clients = (0..options.concurrency)
+        .map(|_| {
+            runtime.spawn_supervised_task(run_client(
+                ingress_addr,
+                payload.clone(),
+                options.requests_per_connection,
+                deadline,
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let mut report = BenchReport {
+        elapsed: Duration::ZERO,
+        payload_size: options.payload_size,
+        requests: 0,
+        connections: 0,
+        errors: 0,
+        latencies: vec![],
+        first_response_latencies: vec![],
+    };
+    for client in clients {
+        let stats = client
+            .await
+            .context("The benchmark client panicked")?
+            .assume_finished()?;
+        report.requests += stats.requests;
+        report.connections += stats.connections;
+        report.errors += stats.errors;
+        report.latencies.extend(stats.latencies);
+        report
+            .first_response_latencies
+            .extend(stats.first_response_latencies);
+    }
+    report.elapsed = started.elapsed();
+    report.latencies.sort_unstable();
+    report.first_response_latencies.sort_unstable();
+    Ok(report)
+}
+
+/// Opens connections to the ingress one after another and sends requests on them, until the
+/// deadline.
+async fn run_client(
+    ingress_addr: SocketAddr,
+    payload: Arc<[u8]>,
+    requests_per_connection: usize,
+    deadline: Instant,
+) -> ClientStats {
+    let mut stats = ClientStats::default();
+    let mut buffer = vec![0u8; payload.len()];
+
+    while Instant::get() < deadline {
+        let connected_at = Instant::get();
+        let result = async {
+            let mut stream = TcpStream::connect(ingress_addr)
+                .await
+                .context("Failed to connect to the ingress")?;
+            for i in 0..requests_per_connection {
+                if i > 0 && Instant::get() >= deadline {
+                    break;
+                }
+                let sent_at = Instant::get();
+                echo_request(&mut stream, &payload, &mut buffer).await?;
+                stats.requests += 1;
+                stats.latencies.push(sent_at.elapsed());
+                if i == 0 {
+                    stats.connections += 1;
+                    stats.first_response_latencies.push(connected_at.elapsed());
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+
+        if let Err(error) = result {
+            tracing::debug!(?error, "Benchmark connection failed");
+            stats.errors += 1;
+        }
+    }
+    stats
+}
+
+/// Sends the payload and reads the echoed response at the same time, so that a payload larger
+/// than the socket buffers does not block.
+async fn echo_request(stream: &mut TcpStream, payload: &[u8], buffer: &mut [u8]) -> Result<()> {
+    let (mut reader, mut writer) = stream.split();
+    let (written, read) = tokio::join!(writer.write_all(payload), reader.read_exact(buffer));
+    written.context("Failed to send the request")?;
+    read.context("Failed to receive the response")?;
+    if buffer != payload {
+        bail!("The response does not match the request");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let samples = (1..=100).map(Duration::from_millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&samples, 50.0), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&samples, 99.0), Some(Duration::from_millis(99)));
+        assert_eq!(
+            percentile(&samples, 100.0),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(percentile(&samples, 0.0), Some(Duration::from_millis(1)));
+        assert_eq!(percentile(&[], 99.0), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_bench_no_ra() -> Result<()> {
+        let report = TngBench::run(
+            BenchOptions {
+                concurrency: 2,
+                payload_size: 4096,
+                duration: Duration::from_secs(1),
+                requests_per_connection: 4,
+                multiplex: false,
+                ingress_ra: None,
+                egress_ra: None,
+            },
+            crate::tests::RELOAD_HANDLE
+                .get()
+                .expect("logger is not initialized"),
+        )
+        .await?;
+
+        assert!(report.requests > 0);
+        assert!(report.connections > 0);
+        assert_eq!(report.latencies.len() as u64, report.requests);
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
-use clap::{arg, Parser, Subcommand};
+use clap::{arg, Args, Parser, Subcommand};
+
+use tng::config::overrides::ConfigOverride;
 
 use crate::build::CLAP_LONG_VERSION;
 
@@ -27,26 +29,120 @@ pub enum GlobalSubcommand {
 
     #[command(name = "exec")]
     Exec(ExecOptions),
+
+    /// Measure the throughput and latency of an ingress and an egress running locally
+    #[command(name = "bench")]
+    Bench(BenchOptions),
+
+    /// Print the JSON Schema of the TNG configuration file
+    #[command(name = "schema")]
+    Schema(SchemaOptions),
+
+    /// Tools for the TNG configuration file
+    #[command(name = "config", subcommand)]
+    Config(ConfigSubcommand),
 }
 
-#[derive(Parser, Debug)]
-pub struct LaunchOptions {
+#[derive(Subcommand, Debug)]
+pub enum ConfigSubcommand {
+    /// Convert a config written for an older version of TNG into the current schema
+    #[command(name = "migrate")]
+    Migrate(MigrateOptions),
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigOptions {
     #[arg(short, long)]
     pub config_file: Option<PathBuf>,
 
+    /// Load the config by merging all *.json, *.yaml and *.yml files in this directory
+    #[arg(long, value_name = "DIR")]
+    pub config_dir: Option<PathBuf>,
+
     #[arg(long)]
     pub config_content: Option<String>,
+
+    /// Override a config field, e.g. `--set add_ingress.0.mapping.out.port=8080`. The value is
+    /// parsed as JSON, or taken as a string if it is not valid JSON
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub set: Vec<ConfigOverride>,
+
+    /// Add a metric exporter, given as the exporter type (e.g. `stdout`) or the exporter config in JSON
+    #[arg(long, value_name = "EXPORTER")]
+    pub metric_exporter: Vec<String>,
+
+    /// Add a trace exporter, given as the exporter type (e.g. `stdout`) or the exporter config in JSON
+    #[arg(long, value_name = "EXPORTER")]
+    pub trace_exporter: Vec<String>,
+
+    /// Serve the ttrpc control interface on this unix socket
+    #[arg(long, value_name = "PATH")]
+    pub control_socket: Option<String>,
 }
 
 #[derive(Parser, Debug)]
-pub struct ExecOptions {
-    #[arg(short, long)]
-    pub config_file: Option<PathBuf>,
+pub struct LaunchOptions {
+    #[command(flatten)]
+    pub config: ConfigOptions,
+}
 
-    #[arg(long)]
-    pub config_content: Option<String>,
+#[derive(Parser, Debug)]
+pub struct ExecOptions {
+    #[command(flatten)]
+    pub config: ConfigOptions,
 
     /// Command to execute (everything after --)
     #[arg(last = true, required = true)]
     pub command: Vec<String>,
 }
+
+#[derive(Parser, Debug)]
+pub struct BenchOptions {
+    /// The number of clients sending requests at the same time
+    #[arg(long, default_value_t = 16)]
+    pub concurrency: usize,
+
+    /// The bytes of each request, which are echoed back by the server
+    #[arg(long, default_value_t = 64 * 1024)]
+    pub payload_size: usize,
+
+    /// How long the clients keep sending requests, in seconds
+    #[arg(long, default_value_t = 10)]
+    pub duration_secs: u64,
+
+    /// The requests sent on a connection before a new one is opened
+    #[arg(long, default_value_t = 1)]
+    pub requests_per_connection: usize,
+
+    /// Multiplex the streams over pooled rats-tls sessions
+    #[arg(long)]
+    pub multiplex: bool,
+
+    /// The remote attestation fields of the ingress in JSON, e.g. `{"verify": {...}}`. No
+    /// remote attestation is done if not set
+    #[arg(long, value_name = "JSON")]
+    pub ingress_ra: Option<String>,
+
+    /// The remote attestation fields of the egress in JSON, e.g. `{"attest": {...}}`. No
+    /// remote attestation is done if not set
+    #[arg(long, value_name = "JSON")]
+    pub egress_ra: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SchemaOptions {
+    /// Write the schema to this file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct MigrateOptions {
+    /// The config file to migrate
+    #[arg(short, long)]
+    pub config_file: PathBuf,
+
+    /// Write the migrated config to this file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
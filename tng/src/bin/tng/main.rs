@@ -1,24 +1,87 @@
 #![deny(clippy::unwrap_used)]
 #![deny(clippy::expect_used)]
 
-use std::{
-    fs::{File, OpenOptions},
-    io::BufReader,
-};
+use std::fs::OpenOptions;
 
 use anyhow::{bail, Context};
 use clap::Parser as _;
-use cli::{Cli, GlobalSubcommand};
+use cli::{Cli, ConfigOptions, ConfigSubcommand, GlobalSubcommand};
 use tng::config::egress::EgressMode;
 use tng::config::ingress::IngressMode;
+use tng::config::migrate::migrate;
+use tng::config::overrides::{ConfigOverride, ConfigOverrides};
+use tng::config::source::ConfigSource;
 use tng::config::TngConfig;
-use tng::runtime::TngRuntime;
+use tng::runtime::{
+    LogFilterHandle, TngRuntime, ACCESS_LOG_TARGET, ACCESS_RECORD_TARGET, AUDIT_LOG_TARGET,
+};
 use tng::{build, show_banner};
 use tracing_subscriber::Layer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod cli;
 
+impl ConfigOptions {
+    /// Load the config from the source specified on the command line, with the overrides from
+    /// the command line applied. The source is also returned if the config can be loaded from it
+    /// again, i.e. it is a file or a directory.
+    fn load(self) -> anyhow::Result<(TngConfig, Option<ConfigSource>, ConfigOverrides)> {
+        let mut overrides = vec![];
+        for exporter in self.metric_exporter {
+            overrides.push(ConfigOverride::append(
+                "metric.exporters",
+                exporter_config(exporter),
+            )?);
+        }
+        for exporter in self.trace_exporter {
+            overrides.push(ConfigOverride::append(
+                "trace.exporters",
+                exporter_config(exporter),
+            )?);
+        }
+        if let Some(path) = self.control_socket {
+            overrides.push(ConfigOverride::set(
+                "control_interface.ttrpc.path",
+                path.into(),
+            )?);
+        }
+        // Generic overrides are applied last, so that they take precedence
+        overrides.extend(self.set);
+        let overrides = ConfigOverrides::new(overrides);
+
+        let source = match (self.config_file, self.config_dir, self.config_content) {
+            (None, None, Some(s)) => {
+                let config = serde_json::from_str(&s)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|config| overrides.apply_to(config))
+                    .context("Failed to load config")?;
+                return Ok((config, None, overrides));
+            }
+            (Some(path), None, None) => ConfigSource::File(path),
+            (None, Some(dir), None) => ConfigSource::Dir(dir),
+            (None, None, None) => {
+                bail!("Either --config-file, --config-dir or --config-content should be set")
+            }
+            _ => bail!(
+                "Only one of --config-file, --config-dir and --config-content can be set at the same time"
+            ),
+        };
+        let config = source
+            .load_with_overrides(&overrides)
+            .context("Failed to load config")?;
+        Ok((config, Some(source), overrides))
+    }
+}
+
+/// An exporter given on the command line, either as the exporter type or the exporter config in
+/// JSON.
+fn exporter_config(exporter: String) -> serde_json::Value {
+    match serde_json::from_str(&exporter) {
+        Ok(config @ serde_json::Value::Object(_)) => config,
+        _ => serde_json::json!({ "type": exporter }),
+    }
+}
+
 /// Reject hook modes when running via `tng launch`.
 /// Hook modes (IngressMode::Hook, EgressMode::Hook) are only allowed via `tng exec`.
 fn reject_hook_modes(config: &TngConfig) -> anyhow::Result<()> {
@@ -80,17 +143,26 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    // The filter of the printed logs can be adjusted later through the control interface
+    let log_filter = std::env::var(tracing_subscriber::EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|directives| tracing_subscriber::EnvFilter::try_new(directives).is_ok())
+        .unwrap_or_else(|| "info,tokio_graceful=off,rats_cert=info,tng=info".to_owned());
+    let (filter, log_filter_reload_handle) =
+        tracing_subscriber::reload::Layer::new(tracing_subscriber::EnvFilter::new(&log_filter));
+    let log_filter_handle = LogFilterHandle::new(log_filter_reload_handle, log_filter);
+
+    // The access logs, their structured records and the audit events always pass, so that the
+    // dedicated sinks receive them regardless of the log level filters
+    let pending_filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "info,tokio_graceful=off,rats_cert=trace,tng=trace".into())
+        .add_directive(format!("{ACCESS_LOG_TARGET}=info").parse()?)
+        .add_directive(format!("{ACCESS_RECORD_TARGET}=trace").parse()?)
+        .add_directive(format!("{AUDIT_LOG_TARGET}=info").parse()?);
+
     let subscriber_init = tracing_subscriber::registry()
-        .with(
-            pending_tracing_layers.with_filter(
-                tracing_subscriber::EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| "info,tokio_graceful=off,rats_cert=trace,tng=trace".into()),
-            ),
-        )
+        .with(pending_tracing_layers.with_filter(pending_filter))
         .with({
-            let filter = tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "info,tokio_graceful=off,rats_cert=info,tng=info".into());
-
             let base_layer = tracing_subscriber::fmt::layer().with_writer(log_writer.clone());
             if is_file {
                 base_layer.with_ansi(false).with_filter(filter)
@@ -122,25 +194,7 @@ async fn main() -> anyhow::Result<()> {
                 show_banner("daemon");
 
                 // Load config
-                let config: TngConfig = async {
-                    Ok::<_, anyhow::Error>(match (options.config_file, options.config_content) {
-                        (Some(_), Some(_)) => {
-                            bail!("Cannot set both --config-file and --config-content at the same time")
-                        }
-                        (None, None) => {
-                            bail!("Either --config-file or --config-content should be set")
-                        }
-                        (None, Some(s)) => serde_json::from_str(&s)?,
-                        (Some(path), None) => {
-                            tracing::info!(?path, "Loading config from");
-                            let file = File::open(path)?;
-                            let reader = BufReader::new(file);
-                            serde_json::from_reader(reader)?
-                        }
-                    })
-                }
-                .await
-                .context("Failed to load config")?;
+                let (config, config_source, config_overrides) = options.config.load()?;
 
                 tracing::debug!(?config, "TNG config");
 
@@ -148,10 +202,15 @@ async fn main() -> anyhow::Result<()> {
                 reject_hook_modes(&config)?;
 
                 tracing::info!("Starting tng instance now");
-                TngRuntime::from_config_with_reload_handle(config, &reload_handle)
-                    .await?
-                    .serve()
-                    .await?;
+                let mut tng_runtime =
+                    TngRuntime::from_config_with_reload_handle(config, &reload_handle).await?;
+                if let Some(config_source) = config_source {
+                    // Allow reloading the config file or directory on SIGHUP
+                    tng_runtime.set_config_source(config_source);
+                    tng_runtime.set_config_overrides(config_overrides);
+                }
+                tng_runtime.set_log_filter_handle(log_filter_handle);
+                tng_runtime.serve().await?;
 
                 tracing::info!("Exited gracefully");
             }
@@ -160,23 +219,7 @@ async fn main() -> anyhow::Result<()> {
 
                 use tng::exec::TngExec;
 
-                let config: TngConfig = {
-                    match (options.config_file, options.config_content) {
-                        (Some(_), Some(_)) => {
-                            bail!("Cannot set both --config-file and --config-content at the same time")
-                        }
-                        (None, None) => {
-                            bail!("Either --config-file or --config-content should be set")
-                        }
-                        (None, Some(s)) => serde_json::from_str(&s)?,
-                        (Some(path), None) => {
-                            tracing::info!(?path, "Loading config from");
-                            let file = File::open(path)?;
-                            let reader = BufReader::new(file);
-                            serde_json::from_reader(reader)?
-                        }
-                    }
-                };
+                let (config, _, _) = options.config.load()?;
 
                 TngExec::run(
                     config,
@@ -188,6 +231,62 @@ async fn main() -> anyhow::Result<()> {
 
                 tracing::info!("Exec session ended");
             }
+            GlobalSubcommand::Bench(options) => {
+                use tng::bench::{BenchOptions, TngBench};
+
+                let parse_ra = |ra: Option<String>, side: &str| {
+                    ra.map(|ra| serde_json::from_str(&ra))
+                        .transpose()
+                        .with_context(|| format!("Invalid JSON in --{side}-ra"))
+                };
+                let options = BenchOptions {
+                    concurrency: options.concurrency,
+                    payload_size: options.payload_size,
+                    duration: std::time::Duration::from_secs(options.duration_secs),
+                    requests_per_connection: options.requests_per_connection,
+                    multiplex: options.multiplex,
+                    ingress_ra: parse_ra(options.ingress_ra, "ingress")?,
+                    egress_ra: parse_ra(options.egress_ra, "egress")?,
+                };
+
+                let report = TngBench::run(options, &reload_handle).await?;
+                println!("{report}");
+            }
+            GlobalSubcommand::Config(ConfigSubcommand::Migrate(options)) => {
+                let path = &options.config_file;
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read config file {path:?}"))?;
+                let mut config: serde_json::Value = serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse config file {path:?}"))?;
+
+                let changes = migrate(&mut config)
+                    .with_context(|| format!("Failed to migrate config file {path:?}"))?;
+                serde_json::from_value::<TngConfig>(config.clone())
+                    .context("The migrated config is still invalid")?;
+
+                // The changes are reported to stderr, so that the migrated config can be piped
+                if changes.is_empty() {
+                    eprintln!("No legacy field found, the config is up to date");
+                }
+                for change in &changes {
+                    eprintln!("{change}");
+                }
+
+                let config = serde_json::to_string_pretty(&config)?;
+                match options.output {
+                    Some(path) => std::fs::write(&path, config)
+                        .with_context(|| format!("Failed to write config to {path:?}"))?,
+                    None => println!("{config}"),
+                }
+            }
+            GlobalSubcommand::Schema(options) => {
+                let schema = serde_json::to_string_pretty(&TngConfig::json_schema())?;
+                match options.output {
+                    Some(path) => std::fs::write(&path, schema)
+                        .with_context(|| format!("Failed to write schema to {path:?}"))?,
+                    None => println!("{schema}"),
+                }
+            }
         }
 
         Ok::<_, anyhow::Error>(())
@@ -0,0 +1,404 @@
+//! Dialing the upstreams through the trusted tunnel from within the application, without running
+//! an ingress.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use anyhow::{anyhow, Context as _, Result};
+use http::{uri::Scheme, Uri};
+use hyper::rt::{Read as _, Write as _};
+use hyper_util::client::legacy::{
+    connect::{Connected, Connection},
+    Client,
+};
+use tokio::io::DuplexStream;
+use tokio_graceful::Shutdown;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::{
+    config::ingress::CommonArgs,
+    tunnel::{
+        endpoint::TngEndpoint,
+        ingress::stream_manager::{trusted::TrustedStreamManager, StreamManager as _},
+        service_metrics::ServiceMetrics,
+        utils::circuit_breaker::CircuitBreaker,
+    },
+    AttestationResult, TokioIo, TokioRuntime,
+};
+
+/// A connector opening streams through the trusted tunnel, like an ingress does for the
+/// connections it accepts, but without binding any listener.
+///
+/// The client is configured with the parameters of an ingress, e.g. `verify`, `rats_tls` or
+/// `ohttp`, and the streams it opens are forwarded to the egress in front of the destination.
+/// With `ohttp`, the streams have to carry HTTP requests, as the connections accepted by the
+/// ingress.
+///
+/// ```no_run
+/// use tng::client::TngClient;
+/// use tng::config::ingress::CommonArgs;
+/// use tng::tunnel::endpoint::TngEndpoint;
+/// use tokio::io::AsyncWriteExt as _;
+/// # async fn example(common_args: CommonArgs) -> anyhow::Result<()> {
+///
+/// let client = TngClient::new(&common_args).await?;
+/// let (mut stream, attestation_result) =
+///     client.connect(&TngEndpoint::new("10.0.0.2", 20001)).await?;
+/// stream.write_all(b"hello").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TngClient {
+    stream_manager: TrustedStreamManager,
+    runtime: TokioRuntime,
+    pipe_buffer_size: usize,
+    /// Stops forwarding the streams opened by the client when it is dropped.
+    _cancel_on_drop: DropGuard,
+    _shutdown: Shutdown,
+}
+
+impl TngClient {
+    /// Creates a client with the `common_args` of an ingress. The remote attestation parameters
+    /// are checked here, but the sessions are only established when the streams are opened.
+    ///
+    /// Must be called within a tokio runtime, which the streams are forwarded on.
+    pub async fn new(common_args: &CommonArgs) -> Result<Self> {
+        let buffer_size = common_args.buffer_size.clone().unwrap_or_default();
+        buffer_size.validate()?;
+
+        let cancel = CancellationToken::new();
+        let shutdown = {
+            let cancel = cancel.clone();
+            Shutdown::new(async move { cancel.cancelled().await })
+        };
+        let runtime = TokioRuntime::current(shutdown.guard())?;
+
+        let metrics = ServiceMetrics::noop();
+        let stream_manager = TrustedStreamManager::new(
+            common_args,
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            None,
+            &metrics,
+            runtime.clone(),
+            CircuitBreaker::new(common_args.circuit_breaker.as_ref())?,
+        )
+        .await?;
+
+        Ok(Self {
+            stream_manager,
+            runtime,
+            pipe_buffer_size: buffer_size.forward,
+            _cancel_on_drop: cancel.drop_guard(),
+            _shutdown: shutdown,
+        })
+    }
+
+    /// Opens a stream to `endpoint` through the tunnel, returning it with the attestation result
+    /// of the peer, if it was attested.
+    ///
+    /// The stream is an in-memory pipe, whose other end is forwarded to the upstream in the
+    /// background until either side closes it, or the client is dropped.
+    pub async fn connect(
+        &self,
+        endpoint: &TngEndpoint,
+    ) -> Result<(DuplexStream, Option<AttestationResult>)> {
+        let (stream, downstream) = tokio::io::duplex(self.pipe_buffer_size);
+
+        let (forward_stream_task, attestation_result, _, _) = self
+            .stream_manager
+            .forward_stream(endpoint, Box::new(downstream))
+            .await
+            .with_context(|| {
+                format!("Failed to connect to upstream {endpoint} via trusted tunnel")
+            })?;
+
+        self.runtime.spawn_supervised_task_with_span(
+            tracing::info_span!("client", %endpoint),
+            async move {
+                if let Err(error) = forward_stream_task.await {
+                    tracing::error!(?error, "Failed to forward the stream to upstream");
+                }
+            },
+        );
+
+        Ok((stream, attestation_result))
+    }
+}
+
+/// A hyper connector opening the connections of an HTTP client through the trusted tunnel of a
+/// [`TngClient`], so that the application sends its HTTP requests through TNG directly, without
+/// the hop of a local `http_proxy` ingress.
+///
+/// The connections are opened to the authority of the request URI, or to the egress set with
+/// [`TngConnector::via`] for all of them. The connector does no TLS of its own, so the requests
+/// are sent over the tunnel as with the `http` scheme.
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use bytes::Bytes;
+/// use http_body_util::Empty;
+/// use tng::client::{TngClient, TngConnector};
+/// use tng::config::ingress::CommonArgs;
+/// use tng::tunnel::endpoint::TngEndpoint;
+/// # async fn example(common_args: CommonArgs) -> anyhow::Result<()> {
+///
+/// let client = Arc::new(TngClient::new(&common_args).await?);
+/// let http_client = TngConnector::new(client)
+///     .via(TngEndpoint::new("10.0.0.2", 20001))
+///     .http_client::<Empty<Bytes>>();
+/// let response = http_client.get("http://api.internal/v1/items".parse()?).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TngConnector {
+    client: Arc<TngClient>,
+    egress: Option<TngEndpoint>,
+}
+
+impl TngConnector {
+    pub fn new(client: Arc<TngClient>) -> Self {
+        Self {
+            client,
+            egress: None,
+        }
+    }
+
+    /// Opens all the connections to `egress`, whatever the authority of the request URI, e.g. an
+    /// egress which forwards them to a fixed upstream.
+    pub fn via(mut self, egress: TngEndpoint) -> Self {
+        self.egress = Some(egress);
+        self
+    }
+
+    /// Builds a hyper client with this connector, which implements
+    /// `tower::Service<http::Request<B>>` and pools the connections like any hyper client.
+    pub fn http_client<B>(&self) -> Client<Self, B>
+    where
+        B: hyper::body::Body + Send,
+        B::Data: Send,
+    {
+        Client::builder(self.client.runtime.clone()).build(self.clone())
+    }
+
+    fn endpoint(&self, uri: &Uri) -> Result<TngEndpoint> {
+        if let Some(egress) = &self.egress {
+            return Ok(egress.clone());
+        }
+
+        let host = uri
+            .host()
+            .ok_or_else(|| anyhow!("No host in the request URI '{uri}'"))?;
+        let port = uri.port_u16().unwrap_or_else(|| {
+            if uri.scheme() == Some(&Scheme::HTTPS) {
+                443u16
+            } else {
+                80u16
+            }
+        });
+        Ok(TngEndpoint::new(host, port))
+    }
+}
+
+impl tower::Service<Uri> for TngConnector {
+    type Response = TngConnection;
+    type Error = anyhow::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connector = self.clone();
+        Box::pin(async move {
+            let endpoint = connector.endpoint(&uri)?;
+            let (stream, attestation_result) = connector.client.connect(&endpoint).await?;
+            Ok(TngConnection {
+                io: TokioIo::new(stream),
+                attestation_result,
+            })
+        })
+    }
+}
+
+/// A connection opened by a [`TngConnector`]. The attestation result of the peer is set in the
+/// extensions of the responses received on it, as an `Option<AttestationResult>`.
+pub struct TngConnection {
+    io: TokioIo<DuplexStream>,
+    attestation_result: Option<AttestationResult>,
+}
+
+impl Connection for TngConnection {
+    fn connected(&self) -> Connected {
+        Connected::new().extra(self.attestation_result.clone())
+    }
+}
+
+impl hyper::rt::Read for TngConnection {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl hyper::rt::Write for TngConnection {
+    #[inline]
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use crate::{config::TngConfig, runtime::TngRuntime};
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_client_connect() -> Result<()> {
+        let egress_port = portpicker::pick_unused_port().unwrap();
+
+        let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let upstream_port = upstream.local_addr()?.port();
+        #[allow(clippy::disallowed_methods)]
+        tokio::task::spawn(async move {
+            while let Ok((mut stream, _)) = upstream.accept().await {
+                let (mut reader, mut writer) = stream.split();
+                let _ = tokio::io::copy(&mut reader, &mut writer).await;
+            }
+        });
+
+        let config: TngConfig = serde_json::from_value(json!({
+            "add_egress": [
+                {
+                    "mapping": {
+                        "in": { "host": "127.0.0.1", "port": egress_port },
+                        "out": { "host": "127.0.0.1", "port": upstream_port }
+                    },
+                    "no_ra": true
+                }
+            ]
+        }))?;
+        let (ready_sender, ready_receiver) = tokio::sync::oneshot::channel();
+        let tng_runtime = TngRuntime::from_config(config).await?;
+        let canceller = tng_runtime.canceller();
+        #[allow(clippy::disallowed_methods)]
+        let join_handle =
+            tokio::task::spawn(async move { tng_runtime.serve_with_ready(ready_sender).await });
+        ready_receiver.await?;
+
+        let common_args: CommonArgs = serde_json::from_value(json!({ "no_ra": true }))?;
+        let client = TngClient::new(&common_args).await?;
+        let (mut stream, attestation_result) = client
+            .connect(&TngEndpoint::new("127.0.0.1", egress_port))
+            .await?;
+        assert!(attestation_result.is_none());
+
+        stream.write_all(b"hello").await?;
+        stream.shutdown().await?;
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await?;
+        assert_eq!(response, b"hello");
+
+        drop(client);
+        canceller.cancel();
+        join_handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_connector_http_client() -> Result<()> {
+        let egress_port = portpicker::pick_unused_port().unwrap();
+
+        let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let upstream_port = upstream.local_addr()?.port();
+        #[allow(clippy::disallowed_methods)]
+        tokio::task::spawn(async move {
+            while let Ok((mut stream, _)) = upstream.accept().await {
+                let mut request = vec![];
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello")
+                    .await;
+            }
+        });
+
+        let config: TngConfig = serde_json::from_value(json!({
+            "add_egress": [
+                {
+                    "mapping": {
+                        "in": { "host": "127.0.0.1", "port": egress_port },
+                        "out": { "host": "127.0.0.1", "port": upstream_port }
+                    },
+                    "no_ra": true
+                }
+            ]
+        }))?;
+        let (ready_sender, ready_receiver) = tokio::sync::oneshot::channel();
+        let tng_runtime = TngRuntime::from_config(config).await?;
+        let canceller = tng_runtime.canceller();
+        #[allow(clippy::disallowed_methods)]
+        let join_handle =
+            tokio::task::spawn(async move { tng_runtime.serve_with_ready(ready_sender).await });
+        ready_receiver.await?;
+
+        let common_args: CommonArgs = serde_json::from_value(json!({ "no_ra": true }))?;
+        let client = Arc::new(TngClient::new(&common_args).await?);
+        let http_client = TngConnector::new(client)
+            .via(TngEndpoint::new("127.0.0.1", egress_port))
+            .http_client::<http_body_util::Empty<bytes::Bytes>>();
+        let response = http_client
+            .get("http://upstream.internal/".parse()?)
+            .await?;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(response
+            .extensions()
+            .get::<Option<AttestationResult>>()
+            .is_some_and(|attestation_result| attestation_result.is_none()));
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await?
+            .to_bytes();
+        assert_eq!(body.as_ref(), b"hello");
+
+        drop(http_client);
+        canceller.cancel();
+        join_handle.await??;
+        Ok(())
+    }
+}
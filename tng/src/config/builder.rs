@@ -0,0 +1,536 @@
+//! A typed builder of [`TngConfig`], for embedding TNG as a library without stitching the JSON
+//! configuration together.
+//!
+//! The RA parameters of each ingress and egress are tracked in its type: `no_ra()` can only be
+//! called before any of `attest()` and `verify()`, and the reverse, and only the ingresses and
+//! egresses with their RA parameters set can be added to the configuration.
+//!
+//! ```no_run
+//! use tng::config::builder::{EgressBuilder, IngressBuilder, TngConfigBuilder};
+//! use tng::config::mapping_rule::{MappingRule, RuleEndpoint};
+//! use tng::config::Endpoint;
+//! # fn verify_args() -> tng::config::ra::VerifyArgs { unimplemented!() }
+//!
+//! let config = TngConfigBuilder::new()
+//!     .ingress(
+//!         IngressBuilder::http_proxy(Endpoint {
+//!             host: Some("127.0.0.1".to_owned()),
+//!             port: 41000,
+//!         })
+//!         .verify(verify_args()),
+//!     )
+//!     .egress(
+//!         EgressBuilder::mapping(vec![MappingRule {
+//!             r#in: RuleEndpoint { host: None, port: 20001, port_end: None },
+//!             out: RuleEndpoint { host: Some([127, 0, 0, 1].into()), port: 30001, port_end: None },
+//!         }])
+//!         .no_ra(),
+//!     )
+//!     .build()?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! ```compile_fail
+//! use tng::config::builder::IngressBuilder;
+//! use tng::config::Endpoint;
+//! # fn verify_args() -> tng::config::ra::VerifyArgs { unimplemented!() }
+//!
+//! // `no_ra` is not available once `verify` is set.
+//! let ingress = IngressBuilder::http_proxy(Endpoint { host: None, port: 41000 })
+//!     .verify(verify_args())
+//!     .no_ra();
+//! ```
+//!
+//! ```compile_fail
+//! use tng::config::builder::{IngressBuilder, TngConfigBuilder};
+//! use tng::config::Endpoint;
+//!
+//! // An ingress without RA parameters can't be added.
+//! let config = TngConfigBuilder::new()
+//!     .ingress(IngressBuilder::http_proxy(Endpoint { host: None, port: 41000 }));
+//! ```
+
+use std::marker::PhantomData;
+#[cfg(not(wasm))]
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use cidr::IpCidr;
+
+use super::circuit_breaker::CircuitBreakerArgs;
+use super::connect_retry::ConnectRetryArgs;
+use super::control_interface::ControlInterfaceArgs;
+use super::egress::{self, AddEgressArgs, DirectForwardRules, EgressMappingArgs, EgressMode};
+use super::ingress::{
+    self, AddIngressArgs, IngressHttpProxyArgs, IngressMappingArgs, IngressMode, IngressSocks5Args,
+};
+use super::lazy_attest::LazyAttestArgs;
+use super::mapping_rule::{MappingDe, MappingRule};
+use super::observability::{
+    access_log::AccessLogArgs,
+    audit_log::AuditLogArgs,
+    log::LogArgs,
+    metric::{MetricArgs, MetricExporterType},
+    trace::TraceArgs,
+};
+use super::overload::OverloadArgs;
+use super::ra::{AttestArgs, VerifyArgs};
+use super::restart::RestartPolicyArgs;
+use super::{BufferSizeArgs, Endpoint, TngConfig};
+#[cfg(not(wasm))]
+use crate::tunnel::resolver::Resolver;
+
+/// The RA parameters of a builder are not set yet.
+#[derive(Debug)]
+pub struct RaUnset;
+
+/// `attest` and / or `verify` of a builder are set.
+#[derive(Debug)]
+pub struct RaSet;
+
+/// `no_ra` of a builder is set.
+#[derive(Debug)]
+pub struct NoRa;
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::RaUnset {}
+    impl Sealed for super::RaSet {}
+    impl Sealed for super::NoRa {}
+}
+
+/// The states in which `attest` and `verify` can be set, i.e. `no_ra` is not set.
+pub trait CanSetRa: sealed::Sealed {}
+
+impl CanSetRa for RaUnset {}
+impl CanSetRa for RaSet {}
+
+/// The states in which the RA parameters are complete, so that the builder can be added to the
+/// configuration.
+pub trait RaComplete: sealed::Sealed {}
+
+impl RaComplete for RaSet {}
+impl RaComplete for NoRa {}
+
+/// Builds the `add_ingress` entries of a [`TngConfigBuilder`].
+#[derive(Debug)]
+pub struct IngressBuilder<Ra = RaUnset> {
+    args: AddIngressArgs,
+    _ra: PhantomData<Ra>,
+}
+
+impl IngressBuilder<RaUnset> {
+    pub fn new(ingress_mode: IngressMode) -> Self {
+        Self {
+            args: AddIngressArgs {
+                ingress_mode,
+                common: ingress::CommonArgs::default(),
+            },
+            _ra: PhantomData,
+        }
+    }
+
+    /// A `mapping` ingress. The rules are checked by [`TngConfigBuilder::build`].
+    pub fn mapping(rules: Vec<MappingRule>) -> Self {
+        Self::new(IngressMode::Mapping(IngressMappingArgs { rules }))
+    }
+
+    /// An `http_proxy` ingress accepting all the destinations.
+    pub fn http_proxy(proxy_listen: Endpoint) -> Self {
+        Self::new(IngressMode::HttpProxy(IngressHttpProxyArgs {
+            proxy_listen,
+            dst_filters: vec![],
+            limits: None,
+        }))
+    }
+
+    /// A `socks5` ingress accepting all the destinations, without authentication.
+    pub fn socks5(proxy_listen: Endpoint) -> Self {
+        Self::new(IngressMode::Socks5(IngressSocks5Args {
+            proxy_listen,
+            dst_filters: vec![],
+            auth: None,
+            tls: None,
+        }))
+    }
+
+    /// Disables remote attestation. This SHOULD NOT be used in production environment.
+    pub fn no_ra(mut self) -> IngressBuilder<NoRa> {
+        self.args.common.ra_args.no_ra = true;
+        self.with_ra_state()
+    }
+}
+
+impl<Ra: CanSetRa> IngressBuilder<Ra> {
+    pub fn attest(mut self, attest: AttestArgs) -> IngressBuilder<RaSet> {
+        self.args.common.ra_args.attest = Some(attest);
+        self.with_ra_state()
+    }
+
+    pub fn verify(mut self, verify: VerifyArgs) -> IngressBuilder<RaSet> {
+        self.args.common.ra_args.verify = Some(verify);
+        self.with_ra_state()
+    }
+}
+
+impl<Ra> IngressBuilder<Ra> {
+    fn with_ra_state<T>(self) -> IngressBuilder<T> {
+        IngressBuilder {
+            args: self.args,
+            _ra: PhantomData,
+        }
+    }
+
+    pub fn ohttp(mut self, ohttp: ingress::OHttpArgs) -> Self {
+        self.args.common.ohttp = Some(ohttp);
+        self
+    }
+
+    pub fn web_page_inject(mut self, web_page_inject: bool) -> Self {
+        self.args.common.web_page_inject = web_page_inject;
+        self
+    }
+
+    pub fn rats_tls(mut self, rats_tls: ingress::RatsTlsArgs) -> Self {
+        self.args.common.rats_tls = Some(rats_tls);
+        self
+    }
+
+    pub fn restart(mut self, restart: RestartPolicyArgs) -> Self {
+        self.args.common.restart = Some(restart);
+        self
+    }
+
+    pub fn lazy_attest(mut self, lazy_attest: LazyAttestArgs) -> Self {
+        self.args.common.lazy_attest = Some(lazy_attest);
+        self
+    }
+
+    pub fn circuit_breaker(mut self, circuit_breaker: CircuitBreakerArgs) -> Self {
+        self.args.common.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    pub fn buffer_size(mut self, buffer_size: BufferSizeArgs) -> Self {
+        self.args.common.buffer_size = Some(buffer_size);
+        self
+    }
+
+    pub fn overload(mut self, overload: OverloadArgs) -> Self {
+        self.args.common.overload = Some(overload);
+        self
+    }
+
+    pub fn allowed_sources(mut self, allowed_sources: Vec<IpCidr>) -> Self {
+        self.args.common.allowed_sources = allowed_sources;
+        self
+    }
+
+    #[cfg(not(wasm))]
+    pub fn resolver(mut self, resolver: Arc<dyn Resolver>) -> Self {
+        self.args.common.resolver = Some(resolver);
+        self
+    }
+}
+
+/// Builds the `add_egress` entries of a [`TngConfigBuilder`].
+#[derive(Debug)]
+pub struct EgressBuilder<Ra = RaUnset> {
+    args: AddEgressArgs,
+    _ra: PhantomData<Ra>,
+}
+
+impl EgressBuilder<RaUnset> {
+    pub fn new(egress_mode: EgressMode) -> Self {
+        Self {
+            args: AddEgressArgs {
+                egress_mode,
+                common: egress::CommonArgs::default(),
+            },
+            _ra: PhantomData,
+        }
+    }
+
+    /// A `mapping` egress. The rules are checked by [`TngConfigBuilder::build`].
+    pub fn mapping(rules: Vec<MappingRule>) -> Self {
+        Self::new(EgressMode::Mapping(EgressMappingArgs { rules }))
+    }
+
+    /// Disables remote attestation. This SHOULD NOT be used in production environment.
+    pub fn no_ra(mut self) -> EgressBuilder<NoRa> {
+        self.args.common.ra_args.no_ra = true;
+        self.with_ra_state()
+    }
+}
+
+impl<Ra: CanSetRa> EgressBuilder<Ra> {
+    pub fn attest(mut self, attest: AttestArgs) -> EgressBuilder<RaSet> {
+        self.args.common.ra_args.attest = Some(attest);
+        self.with_ra_state()
+    }
+
+    pub fn verify(mut self, verify: VerifyArgs) -> EgressBuilder<RaSet> {
+        self.args.common.ra_args.verify = Some(verify);
+        self.with_ra_state()
+    }
+}
+
+impl<Ra> EgressBuilder<Ra> {
+    fn with_ra_state<T>(self) -> EgressBuilder<T> {
+        EgressBuilder {
+            args: self.args,
+            _ra: PhantomData,
+        }
+    }
+
+    pub fn ohttp(mut self, ohttp: egress::OHttpArgs) -> Self {
+        self.args.common.ohttp = Some(ohttp);
+        self
+    }
+
+    pub fn direct_forward(mut self, direct_forward: DirectForwardRules) -> Self {
+        self.args.common.direct_forward = Some(direct_forward);
+        self
+    }
+
+    pub fn rats_tls(mut self, rats_tls: egress::RatsTlsArgs) -> Self {
+        self.args.common.rats_tls = Some(rats_tls);
+        self
+    }
+
+    pub fn restart(mut self, restart: RestartPolicyArgs) -> Self {
+        self.args.common.restart = Some(restart);
+        self
+    }
+
+    pub fn lazy_attest(mut self, lazy_attest: LazyAttestArgs) -> Self {
+        self.args.common.lazy_attest = Some(lazy_attest);
+        self
+    }
+
+    pub fn circuit_breaker(mut self, circuit_breaker: CircuitBreakerArgs) -> Self {
+        self.args.common.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    pub fn connect_retry(mut self, connect_retry: ConnectRetryArgs) -> Self {
+        self.args.common.connect_retry = Some(connect_retry);
+        self
+    }
+
+    pub fn buffer_size(mut self, buffer_size: BufferSizeArgs) -> Self {
+        self.args.common.buffer_size = Some(buffer_size);
+        self
+    }
+
+    #[cfg(not(wasm))]
+    pub fn resolver(mut self, resolver: Arc<dyn Resolver>) -> Self {
+        self.args.common.resolver = Some(resolver);
+        self
+    }
+}
+
+/// Builds a [`TngConfig`], see the [module documentation](self).
+#[derive(Debug)]
+pub struct TngConfigBuilder {
+    config: TngConfig,
+}
+
+impl Default for TngConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TngConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: TngConfig {
+                control_interface: None,
+                metric: None,
+                trace: None,
+                log: None,
+                access_log: None,
+                audit_log: None,
+                shutdown_drain_timeout_secs: None,
+                runtime: None,
+                default_attest: None,
+                default_verify: None,
+                ra_profiles: Default::default(),
+                add_ingress: vec![],
+                add_egress: vec![],
+                admin_bind: None,
+            },
+        }
+    }
+
+    pub fn ingress<Ra: RaComplete>(mut self, ingress: IngressBuilder<Ra>) -> Self {
+        self.config.add_ingress.push(ingress.args);
+        self
+    }
+
+    pub fn egress<Ra: RaComplete>(mut self, egress: EgressBuilder<Ra>) -> Self {
+        self.config.add_egress.push(egress.args);
+        self
+    }
+
+    pub fn control_interface(mut self, control_interface: ControlInterfaceArgs) -> Self {
+        self.config.control_interface = Some(control_interface);
+        self
+    }
+
+    pub fn metric(mut self, metric: MetricArgs) -> Self {
+        self.config.metric = Some(metric);
+        self
+    }
+
+    /// Adds a metric exporter, e.g. a `MetricExporterType::Custom` one implemented by the
+    /// application, enabling the metrics if they are not yet.
+    pub fn metric_exporter(mut self, exporter: MetricExporterType) -> Self {
+        self.config
+            .metric
+            .get_or_insert_with(Default::default)
+            .exporters
+            .push(exporter);
+        self
+    }
+
+    pub fn trace(mut self, trace: TraceArgs) -> Self {
+        self.config.trace = Some(trace);
+        self
+    }
+
+    pub fn log(mut self, log: LogArgs) -> Self {
+        self.config.log = Some(log);
+        self
+    }
+
+    pub fn access_log(mut self, access_log: AccessLogArgs) -> Self {
+        self.config.access_log = Some(access_log);
+        self
+    }
+
+    pub fn audit_log(mut self, audit_log: AuditLogArgs) -> Self {
+        self.config.audit_log = Some(audit_log);
+        self
+    }
+
+    pub fn shutdown_drain_timeout_secs(mut self, shutdown_drain_timeout_secs: u64) -> Self {
+        self.config.shutdown_drain_timeout_secs = Some(shutdown_drain_timeout_secs);
+        self
+    }
+
+    pub fn admin_bind(mut self, admin_bind: Endpoint) -> Self {
+        self.config.admin_bind = Some(admin_bind);
+        self
+    }
+
+    /// Checks what the types can't, e.g. the overlapping mapping rules, which are otherwise
+    /// checked when the JSON configuration is parsed.
+    pub fn build(self) -> Result<TngConfig> {
+        for (i, ingress) in self.config.add_ingress.iter().enumerate() {
+            if let IngressMode::Mapping(IngressMappingArgs { rules }) = &ingress.ingress_mode {
+                MappingDe::Rules {
+                    rules: rules.clone(),
+                }
+                .into_checked("ingress mapping")
+                .with_context(|| format!("Invalid `add_ingress.{i}`"))?;
+            }
+        }
+        for (i, egress) in self.config.add_egress.iter().enumerate() {
+            if let EgressMode::Mapping(EgressMappingArgs { rules }) = &egress.egress_mode {
+                MappingDe::Rules {
+                    rules: rules.clone(),
+                }
+                .into_checked("egress mapping")
+                .with_context(|| format!("Invalid `add_egress.{i}`"))?;
+            }
+        }
+
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use crate::config::mapping_rule::{MappingRule, RuleEndpoint};
+    use crate::config::{Endpoint, TngConfig};
+
+    use super::{EgressBuilder, IngressBuilder, TngConfigBuilder};
+
+    fn rule(in_port: u16, out_port: u16) -> MappingRule {
+        MappingRule {
+            r#in: RuleEndpoint {
+                host: None,
+                port: in_port,
+                port_end: None,
+            },
+            out: RuleEndpoint {
+                host: Some([127, 0, 0, 1].into()),
+                port: out_port,
+                port_end: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_builder_matches_json() -> Result<()> {
+        let expected: TngConfig = serde_json::from_value(json!({
+            "add_ingress": [
+                {
+                    "http_proxy": {
+                        "proxy_listen": { "host": "127.0.0.1", "port": 41000 }
+                    },
+                    "verify": {
+                        "as_addr": "http://127.0.0.1:8080/",
+                        "policy_ids": ["default"]
+                    }
+                }
+            ],
+            "add_egress": [
+                {
+                    "mapping": {
+                        "in": { "port": 20001 },
+                        "out": { "host": "127.0.0.1", "port": 30001 }
+                    },
+                    "no_ra": true
+                }
+            ]
+        }))?;
+        let verify = expected.add_ingress[0]
+            .common
+            .ra_args
+            .verify
+            .clone()
+            .unwrap();
+
+        let config = TngConfigBuilder::new()
+            .ingress(
+                IngressBuilder::http_proxy(Endpoint {
+                    host: Some("127.0.0.1".to_owned()),
+                    port: 41000,
+                })
+                .verify(verify),
+            )
+            .egress(EgressBuilder::mapping(vec![rule(20001, 30001)]).no_ra())
+            .build()?;
+
+        assert_eq!(
+            serde_json::to_value(&config)?,
+            serde_json::to_value(&expected)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_checks_mapping_rules() {
+        let result = TngConfigBuilder::new()
+            .egress(EgressBuilder::mapping(vec![rule(20001, 30001), rule(20001, 30002)]).no_ra())
+            .build();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Stop connecting to an upstream endpoint for a while after it failed to be connected several
+/// times in a row, so that the streams to a dead backend fail fast instead of waiting for the
+/// connect timeout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerArgs {
+    /// The number of consecutive connect failures to an endpoint which opens its circuit.
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// The time, in seconds, the circuit stays open before a connection is tried again.
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_cooldown_secs() -> u64 {
+    10
+}
+
+impl CircuitBreakerArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.failure_threshold == 0 {
+            anyhow::bail!("`circuit_breaker.failure_threshold` must be greater than 0");
+        }
+        if self.cooldown_secs == 0 {
+            anyhow::bail!("`circuit_breaker.cooldown_secs` must be greater than 0");
+        }
+        Ok(())
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn test_circuit_breaker_args() -> Result<()> {
+        let args: CircuitBreakerArgs = serde_json::from_value(json!({}))?;
+        assert_eq!(args.failure_threshold, 5);
+        assert_eq!(args.cooldown(), Duration::from_secs(10));
+        args.validate()?;
+
+        let args: CircuitBreakerArgs = serde_json::from_value(json!({
+            "failure_threshold": 0,
+        }))?;
+        assert!(args.validate().is_err());
+        Ok(())
+    }
+}
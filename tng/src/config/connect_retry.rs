@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Retry the connections of an egress to its upstream which fail with a transient error, i.e.
+/// when the connection is refused or times out, to smooth over the restarts of the upstream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectRetryArgs {
+    /// The maximum number of retries after the first attempt.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// The delay, in milliseconds, before the first retry. It is doubled after each retry.
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+
+    /// The maximum delay, in milliseconds, between two attempts.
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    100
+}
+
+fn default_max_backoff_ms() -> u64 {
+    2000
+}
+
+impl ConnectRetryArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.initial_backoff_ms == 0 {
+            anyhow::bail!("`connect_retry.initial_backoff_ms` must be greater than 0");
+        }
+        if self.max_backoff_ms < self.initial_backoff_ms {
+            anyhow::bail!(
+                "`connect_retry.max_backoff_ms` must not be less than `connect_retry.initial_backoff_ms`"
+            );
+        }
+        Ok(())
+    }
+
+    /// The delay before the retry following `retries` previous retries, before the jitter is
+    /// applied.
+    pub fn backoff(&self, retries: u32) -> Duration {
+        let factor = 1u64.checked_shl(retries).unwrap_or(u64::MAX);
+        Duration::from_millis(
+            self.initial_backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn test_connect_retry_backoff() -> Result<()> {
+        let args: ConnectRetryArgs = serde_json::from_value(json!({}))?;
+        args.validate()?;
+        assert_eq!(args.max_retries, 3);
+        assert_eq!(args.backoff(0), Duration::from_millis(100));
+        assert_eq!(args.backoff(2), Duration::from_millis(400));
+        assert_eq!(args.backoff(10), Duration::from_millis(2000));
+        assert_eq!(args.backoff(100), Duration::from_millis(2000));
+
+        let args: ConnectRetryArgs = serde_json::from_value(json!({
+            "initial_backoff_ms": 500,
+            "max_backoff_ms": 100,
+        }))?;
+        assert!(args.validate().is_err());
+        Ok(())
+    }
+}
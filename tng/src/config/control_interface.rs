@@ -1,8 +1,10 @@
+use cidr::IpCidr;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::Endpoint;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct ControlInterfaceArgs {
     pub restful: Option<RestfulArgs>,
@@ -10,13 +12,88 @@ pub struct ControlInterfaceArgs {
     pub ttrpc: Option<TtrpcArgs>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RestfulArgs {
     #[serde(flatten)]
     pub address: Endpoint,
+
+    /// Source addresses (in CIDR notation, e.g. `10.0.0.0/8`) which are allowed to access the
+    /// interface. Requests from other sources are rejected with `403 Forbidden`. All sources are
+    /// allowed if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "Vec<String>")]
+    pub allowed_sources: Vec<IpCidr>,
+
+    /// Authentication of the clients. Any client can access the interface if not set, with
+    /// `unauthenticated_role`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<RestfulAuthArgs>,
+
+    /// The role of the clients when `auth` is not set. Only the status of the instance can be
+    /// queried by default: `operator` has to be set explicitly to let any client which reaches the
+    /// interface operate the instance, e.g. reload its configuration.
+    #[serde(default)]
+    pub unauthenticated_role: ControlRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RestfulAuthArgs {
+    /// Bearer tokens accepted in the `Authorization` header. If not empty, every request other
+    /// than the health checks must carry one of them, and is authorized with its role.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<TokenArgs>,
+
+    /// Serve the interface over TLS, and require the clients to present a certificate signed by
+    /// the client CA.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtls: Option<MtlsArgs>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TokenArgs {
+    pub token: String,
+
+    /// The role of the clients using this token, `read_only` unless set.
+    #[serde(default)]
+    pub role: ControlRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MtlsArgs {
+    /// Path to the PEM file of the server certificate chain.
+    pub cert: String,
+
+    /// Path to the PEM file of the server private key.
+    pub key: String,
+
+    /// Path to the PEM file of the CA certificates which the client certificates are verified
+    /// with.
+    pub client_ca: String,
+
+    /// The role of the clients authenticated by their certificates, `read_only` unless set.
+    /// Ignored if `tokens` is set, in which case the role is given by the token.
+    #[serde(default)]
+    pub role: ControlRole,
+}
+
+/// What a client of the control interface is allowed to do. The clients are only allowed to query
+/// the instance unless they are explicitly given the `operator` role.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlRole {
+    /// Query the health and the status of the instance.
+    #[default]
+    ReadOnly,
+    /// Also operate the instance, e.g. reload the configuration or drain a service.
+    Operator,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TtrpcArgs {
     pub path: String,
 }
@@ -66,15 +143,26 @@ mod tests {
         );
 
         let expected = TngConfig {
+            default_attest: None,
+            default_verify: None,
+            ra_profiles: Default::default(),
             admin_bind: None,
             metric: None,
             trace: None,
+            log: None,
+            access_log: None,
+            audit_log: None,
+            shutdown_drain_timeout_secs: None,
+            runtime: None,
             control_interface: Some(ControlInterfaceArgs {
                 restful: Some(RestfulArgs {
                     address: Endpoint {
                         host: Some("0.0.0.0".to_owned()),
                         port: 50000,
                     },
+                    allowed_sources: vec![],
+                    auth: None,
+                    unauthenticated_role: ControlRole::ReadOnly,
                 }),
                 ..Default::default()
             }),
@@ -98,10 +186,20 @@ mod tests {
                     ohttp: None,
                     rats_tls: None,
                     quic: None,
+                    restart: None,
+                    lazy_attest: None,
+                    circuit_breaker: None,
+                    buffer_size: None,
+                    overload: None,
+                    allowed_sources: vec![],
+                    tunnel_dns: None,
+                    resolver: None,
                     ra_args: RaArgsUnchecked {
                         no_ra: true,
                         attest: None,
                         verify: None,
+                        attest_profile: None,
+                        verify_profile: None,
                     },
                 },
             }],
@@ -144,9 +242,17 @@ mod tests {
         );
 
         let expected = TngConfig {
+            default_attest: None,
+            default_verify: None,
+            ra_profiles: Default::default(),
             admin_bind: None,
             metric: None,
             trace: None,
+            log: None,
+            access_log: None,
+            audit_log: None,
+            shutdown_drain_timeout_secs: None,
+            runtime: None,
             control_interface: Some(ControlInterfaceArgs {
                 ttrpc: Some(TtrpcArgs {
                     path: "/var/run/tng.sock".to_string(),
@@ -173,10 +279,20 @@ mod tests {
                     ohttp: None,
                     rats_tls: None,
                     quic: None,
+                    restart: None,
+                    lazy_attest: None,
+                    circuit_breaker: None,
+                    buffer_size: None,
+                    overload: None,
+                    allowed_sources: vec![],
+                    tunnel_dns: None,
+                    resolver: None,
                     ra_args: RaArgsUnchecked {
                         no_ra: true,
                         attest: None,
                         verify: None,
+                        attest_profile: None,
+                        verify_profile: None,
                     },
                 },
             }],
@@ -1,16 +1,27 @@
 use anyhow::bail;
 use cidr::Ipv4Cidr;
+use derivative::Derivative;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::{formats::PreferMany, serde_as, OneOrMany};
 
+use super::circuit_breaker::CircuitBreakerArgs;
+use super::connect_retry::ConnectRetryArgs;
+use super::lazy_attest::LazyAttestArgs;
+use super::listener::ListenerArgs;
 use super::mapping_rule::MappingDe;
+use super::ohttp_padding::OHttpPaddingPolicy;
 use super::ra::RaArgsUnchecked;
-use super::UdpQuicArgs;
+use super::restart::RestartPolicyArgs;
+use super::websocket::WebSocketArgs;
+use super::{BufferSizeArgs, UdpQuicArgs};
 use crate::config::egress_hook::EgressHookArgs;
 use crate::config::Endpoint;
 use crate::tunnel::access_log::EgressAccessMode;
+#[cfg(not(wasm))]
+use crate::tunnel::resolver::Resolver;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AddEgressArgs {
     #[serde(flatten)]
     pub egress_mode: EgressMode,
@@ -19,7 +30,8 @@ pub struct AddEgressArgs {
     pub common: CommonArgs,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize, Derivative, JsonSchema)]
+#[derivative(Debug)]
 #[serde(deny_unknown_fields)]
 pub struct CommonArgs {
     #[serde(alias = "decap_from_http")]
@@ -34,12 +46,51 @@ pub struct CommonArgs {
     #[serde(default = "Option::default")]
     pub quic: Option<UdpQuicArgs>,
 
+    /// Restart the service when it fails, instead of shutting down the instance.
+    #[serde(default = "Option::default")]
+    pub restart: Option<RestartPolicyArgs>,
+
+    /// Create the service in the background, retrying until the attestation agent is reachable,
+    /// instead of failing the startup of the instance.
+    #[serde(default = "Option::default")]
+    pub lazy_attest: Option<LazyAttestArgs>,
+
+    /// Fail fast the connections to an upstream endpoint which failed to be connected several times
+    /// in a row, for a cooldown period.
+    #[serde(default = "Option::default")]
+    pub circuit_breaker: Option<CircuitBreakerArgs>,
+
+    /// Retry the connections to the upstream which fail with a transient error.
+    #[serde(default = "Option::default")]
+    pub connect_retry: Option<ConnectRetryArgs>,
+
+    /// The sizes of the internal buffers used for forwarding.
+    #[serde(default = "Option::default")]
+    pub buffer_size: Option<BufferSizeArgs>,
+
+    /// Forward the plain TCP streams, e.g. of `direct_forward`, with io_uring instead of epoll.
+    /// Only supported on Linux, by TNG built with the `io-uring` feature.
+    #[serde(default)]
+    pub io_uring: bool,
+
+    /// Tuning of the TCP listeners of the egress. Only used by the `mapping` and `netfilter`
+    /// egresses.
+    #[serde(default = "Option::default")]
+    pub listener: Option<ListenerArgs>,
+
+    /// Resolves the domain names of the upstreams instead of the resolver of the system. Only
+    /// settable by library users, see [`Resolver`].
+    #[cfg(not(wasm))]
+    #[serde(skip)]
+    #[derivative(Debug = "ignore")]
+    pub resolver: Option<std::sync::Arc<dyn Resolver>>,
+
     #[serde(flatten)]
     pub ra_args: RaArgsUnchecked,
 }
 
 /// Configuration for rats-TLS transport.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct RatsTlsArgs {
     /// When `true`, uses HTTP/2 CONNECT tunneling to multiplex multiple
@@ -52,16 +103,69 @@ pub struct RatsTlsArgs {
     /// whose bandwidth is limited by the TLS encryption capacity of one CPU core.
     #[serde(default)]
     pub multiplex: bool,
+
+    /// When `true`, the flow-control windows of the HTTP/2 connections carried by the rats-TLS
+    /// sessions are sized from the bandwidth-delay product measured with PING frames, instead of
+    /// the fixed default ones, which limit the throughput of the streams over high-latency links.
+    /// Requires `multiplex`.
+    #[serde(default)]
+    pub adaptive_window: bool,
+
+    /// The ALPN protocols accepted in the rats-TLS handshake, instead of `h2` with `multiplex` or
+    /// `rats-tls` otherwise. Whether the sessions are multiplexed is still decided by `multiplex`,
+    /// whichever of them is negotiated.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alpn: Vec<String>,
+
+    /// Also accept the rats-TLS sessions carried inside WebSocket connections, as sent by
+    /// `tng-wasm` from a browser. The other connections are still decoded as plain rats-TLS.
+    /// Requires `multiplex` to be disabled.
+    #[serde(default = "Option::default")]
+    pub websocket: Option<WebSocketArgs>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl RatsTlsArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.adaptive_window && !self.multiplex {
+            bail!("`rats_tls.adaptive_window` requires `rats_tls.multiplex` to be enabled");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct DirectForwardRules(pub Vec<DirectForwardRule>);
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct DirectForwardRule {
-    pub http_path: String,
+    /// Regex matched against the path of the HTTP requests received over TCP.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_path: Option<String>,
+
+    /// Regex matched against the SNI in the ClientHello of the QUIC connections received by a
+    /// `mapping_udp` egress.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sni: Option<String>,
+}
+
+impl DirectForwardRule {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match (&self.http_path, &self.sni) {
+            (None, None) => {
+                bail!("one of `http_path`, `sni` must be specified in `direct_forward`")
+            }
+            (Some(_), Some(_)) => {
+                bail!(
+                    "Only one of `http_path` or `sni` can be specified in a `direct_forward` rule"
+                )
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -71,6 +175,18 @@ pub struct EgressMappingArgs {
     pub rules: Vec<super::mapping_rule::MappingRule>,
 }
 
+// The accepted input is described by `MappingDe`, which covers both the `rules` array and the
+// legacy `in`/`out` form.
+impl JsonSchema for EgressMappingArgs {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "EgressMappingArgs".into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        MappingDe::json_schema(generator)
+    }
+}
+
 impl<'de> Deserialize<'de> for EgressMappingArgs {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -84,7 +200,7 @@ impl<'de> Deserialize<'de> for EgressMappingArgs {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct EgressMappingUdpArgs {
     /// QUIC listener address.
@@ -109,7 +225,7 @@ pub struct EgressMappingUdpArgs {
 }
 
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct EgressNetfilterArgs {
     #[serde_as(as = "OneOrMany<_, PreferMany>")]
@@ -128,6 +244,12 @@ pub struct EgressNetfilterArgs {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub nocapture_cgroup: Vec<String>,
 
+    /// Traffic which is never redirected to TNG, even if it matches `capture_dst`, e.g. the health
+    /// checks of a load balancer or the backup traffic sent to the same ports.
+    #[serde(default = "Vec::new")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub capture_exclude: Vec<EgressNetfilterCaptureExcludeArgs>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub listen_port: Option<u16>,
 
@@ -135,11 +257,87 @@ pub struct EgressNetfilterArgs {
     pub so_mark: Option<u32>,
 }
 
+/// A rule of `capture_exclude`. A packet is excluded when it matches all the fields which are set.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EgressNetfilterCaptureExcludeArgs {
+    /// Source IPv4 address or CIDR.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    src: Option<Ipv4Cidr>,
+
+    /// Destination IPv4 address or CIDR.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    dst: Option<Ipv4Cidr>,
+
+    /// Destination port.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+
+    /// Optional end port for matching the destination ports [port, port_end].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port_end: Option<u16>,
+
+    /// Name of the interface the packet is received on. A trailing `+` matches all the interfaces
+    /// with the prefix. Traffic sent from the local host is not received on any interface.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interface: Option<String>,
+}
+
+pub struct EgressNetfilterCaptureExclude {
+    pub src: Option<Ipv4Cidr>,
+    pub dst: Option<Ipv4Cidr>,
+    pub port: Option<(u16, Option<u16>)>,
+    pub interface: Option<String>,
+}
+
+impl TryFrom<EgressNetfilterCaptureExcludeArgs> for EgressNetfilterCaptureExclude {
+    type Error = anyhow::Error;
+
+    fn try_from(value: EgressNetfilterCaptureExcludeArgs) -> Result<Self, Self::Error> {
+        let port = match (value.port, value.port_end) {
+            (None, None) => None,
+            (None, Some(_)) => bail!("`port_end` requires `port` to be specified"),
+            (Some(port), Some(end)) if end < port => {
+                bail!("`port_end` ({end}) must be >= `port` ({port})")
+            }
+            (Some(port), port_end) => Some((port, port_end)),
+        };
+
+        if let Some(interface) = &value.interface {
+            // Same limit as IFNAMSIZ in the kernel, which includes the trailing NUL
+            if interface.is_empty() || interface.len() > 15 {
+                bail!("`interface` must be 1 to 15 characters long");
+            }
+            if !interface
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@' | '+'))
+            {
+                bail!("`interface` contains invalid characters: {interface}");
+            }
+        }
+
+        if value.src.is_none() && value.dst.is_none() && port.is_none() && value.interface.is_none()
+        {
+            bail!("one of src, dst, port, interface must be specified");
+        }
+
+        Ok(Self {
+            src: value.src,
+            dst: value.dst,
+            port,
+            interface: value.interface,
+        })
+    }
+}
+
 /// Instead of using the EgressNetfilterCaptureDst directly, here we define a common struct for json parsing to get better deserialization error message.
 /// See https://github.com/serde-rs/serde/issues/2157
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct EgressNetfilterCaptureDstArgs {
+    #[schemars(with = "Option<String>")]
     host: Option<Ipv4Cidr>,
     ipset: Option<String>,
     port: Option<u16>,
@@ -206,7 +404,7 @@ pub enum EgressNetfilterCaptureDst {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum EgressMode {
     #[serde(rename = "mapping")]
@@ -241,7 +439,7 @@ impl EgressMode {
 /// including cross-origin settings and key management strategy.
 ///
 /// By default, if not explicitly configured, OHTTP is disabled.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct OHttpArgs {
     /// **(Deprecated)** Regular expressions matching paths that are allowed to bypass
@@ -304,13 +502,137 @@ pub struct OHttpArgs {
     /// outer OHTTP HTTP response.
     #[serde(default)]
     pub header_passthrough: Option<EgressHeaderPassthroughConfig>,
+
+    /// Path of the HTTP endpoint serving the current key configuration in the standard
+    /// `application/ohttp-keys` format (RFC 9458), so that standard OHTTP clients can bootstrap
+    /// without the TNG-specific key config API.
+    ///
+    /// The endpoint accepts plain `GET` requests and does not carry any attestation information.
+    /// It is disabled if not specified.
+    ///
+    /// Example:
+    /// ```json
+    /// "keys_endpoint": "/.well-known/ohttp-keys"
+    /// ```
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys_endpoint: Option<String>,
+
+    /// Path of the HTTP endpoint exposing the current attestation of this egress, i.e. the
+    /// attestation result (passport model) or the evidence (background check model) together with
+    /// the claims parsed from it, so that external monitors can validate the TEE posture without
+    /// speaking the tunnel protocol.
+    ///
+    /// The endpoint accepts plain `GET` requests and requires `attest` to be configured. It is
+    /// disabled if not specified.
+    ///
+    /// Example:
+    /// ```json
+    /// "attestation_endpoint": "/.tng/attestation"
+    /// ```
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation_endpoint: Option<String>,
+
+    /// Padding applied to the encapsulated responses, hiding the exact response sizes from
+    /// on-path observers. No padding is applied if not specified.
+    ///
+    /// Example:
+    /// ```json
+    /// "padding": {
+    ///   "mode": "random",
+    ///   "max_bytes": 256
+    /// }
+    /// ```
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub padding: Option<OHttpPaddingPolicy>,
+
+    /// Response returned to the clients that send plain HTTP requests instead of TNG traffic,
+    /// replacing the default `403` JSON error. The requests matching `direct_forward` are not
+    /// affected.
+    ///
+    /// Example:
+    /// ```json
+    /// "non_tng_response": {
+    ///   "status": 403,
+    ///   "content_type": "text/html",
+    ///   "body": "<h1>Please access {path} via TNG</h1>"
+    /// }
+    /// ```
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub non_tng_response: Option<NonTngResponseArgs>,
+}
+
+/// The response returned to non-TNG clients, either a page rendered from `body` or a redirect to
+/// `redirect_url`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NonTngResponseArgs {
+    /// Status code of the response. Defaults to `403`, or to `302` if `redirect_url` is set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    /// Value of the `Content-Type` header. Defaults to `text/plain; charset=utf-8`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+
+    /// Body template of the response. The placeholders `{code}`, `{message}` and `{path}` are
+    /// replaced with the error code, the error message and the path of the request. Defaults to
+    /// the JSON error returned when `non_tng_response` is not configured.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+
+    /// URL the clients are redirected to with the `Location` header. Mutually exclusive with
+    /// `body`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+}
+
+impl NonTngResponseArgs {
+    pub fn status(&self) -> u16 {
+        match (self.status, &self.redirect_url) {
+            (Some(status), _) => status,
+            (None, Some(_)) => 302,
+            (None, None) => 403,
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let status = self.status();
+        if !(100..=599).contains(&status) {
+            bail!("`ohttp.non_tng_response.status` must be a valid HTTP status code, got {status}");
+        }
+        if let Some(content_type) = &self.content_type {
+            if http::HeaderValue::from_str(content_type).is_err() {
+                bail!("`ohttp.non_tng_response.content_type` is not a valid header value");
+            }
+        }
+        if let Some(redirect_url) = &self.redirect_url {
+            if self.body.is_some() {
+                bail!("`ohttp.non_tng_response.body` and `ohttp.non_tng_response.redirect_url` are mutually exclusive");
+            }
+            if !(300..=399).contains(&status) {
+                bail!("`ohttp.non_tng_response.status` must be a redirection (3xx) status when `redirect_url` is set, got {status}");
+            }
+            if http::HeaderValue::from_str(redirect_url).is_err() {
+                bail!("`ohttp.non_tng_response.redirect_url` is not a valid header value");
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Defines the strategy for obtaining the HPKE private key used in OHTTP decryption.
 ///
 /// This is a tagged enum (`source` field) that specifies the key management model.
 /// Only one variant can be active at a time.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "source")]
 #[allow(clippy::large_enum_variant)]
 pub enum KeyArgs {
@@ -369,7 +691,7 @@ pub enum KeyArgs {
 ///   }
 /// }
 /// ```
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PeerSharedArgs {
     /// Interval (in seconds) between automatic key rotations.
     ///
@@ -396,6 +718,15 @@ pub struct PeerSharedArgs {
     #[serde(default = "Default::default")]
     pub peers_file: Option<String>,
 
+    /// Optional pre-shared token which every node in the cluster must be configured with.
+    ///
+    /// When set, all key material exchanged between peers is encrypted and authenticated with
+    /// a key derived from this token, so nodes which can reach the gossip port but do not know
+    /// the token can neither read the keys nor inject their own. Recommended whenever peers are
+    /// not verified with remote attestation (i.e. `verify` is not set).
+    #[serde(default = "Default::default")]
+    pub join_token: Option<String>,
+
     /// Define how this node proves its identity when connecting to others, and how to verify
     /// the identity of remote peers.
     #[serde(flatten)]
@@ -421,7 +752,7 @@ impl Default for KeyArgs {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct CorsConfig {
     /// Allow origins for CORS, e.g. ["https://example.com", "https://app.example.com"]
@@ -459,7 +790,7 @@ pub struct CorsConfig {
 /// Each field accepts either the literal string `"all"` (copy every header
 /// except the protected set) or an explicit allowlist of header names. Defaults
 /// to empty (copy nothing).
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct EgressHeaderPassthroughConfig {
     /// Outer (ciphertext) request → inner (plaintext) request.
@@ -470,7 +801,7 @@ pub struct EgressHeaderPassthroughConfig {
     pub response_headers: crate::config::header_passthrough::HeaderPassthroughSpec,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct AllowNonTngTrafficRegexes(Vec<String>);
 
@@ -480,7 +811,10 @@ impl From<AllowNonTngTrafficRegexes> for DirectForwardRules {
             value
                 .0
                 .into_iter()
-                .map(|s| DirectForwardRule { http_path: s })
+                .map(|s| DirectForwardRule {
+                    http_path: Some(s),
+                    sni: None,
+                })
                 .collect(),
         )
     }
@@ -493,7 +827,11 @@ mod tests {
 
     use crate::config::TngConfig;
 
-    use super::{EgressMode, EgressNetfilterCaptureDst, EgressNetfilterCaptureDstArgs};
+    use super::{
+        DirectForwardRule, DirectForwardRules, EgressMode, EgressNetfilterCaptureDst,
+        EgressNetfilterCaptureDstArgs, EgressNetfilterCaptureExclude,
+        EgressNetfilterCaptureExcludeArgs, NonTngResponseArgs, RatsTlsArgs,
+    };
 
     fn test_deserialize_egress_netfilter_common(value: serde_json::Value) -> Result<()> {
         let config: TngConfig = serde_json::from_value(value)?;
@@ -575,6 +913,46 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_deserialize_egress_netfilter_capture_exclude() -> Result<()> {
+        test_deserialize_egress_netfilter_common(json!(
+            {
+                "add_egress": [
+                    {
+                        "netfilter": {
+                            "capture_dst": [{ "port": 30001 }],
+                            "capture_exclude": [
+                                { "src": "10.0.0.0/8" },
+                                { "src": "192.168.1.10", "port": 30001 },
+                                { "interface": "eth1" },
+                                { "dst": "10.1.1.0/24", "port": 30000, "port_end": 30031 }
+                            ]
+                        },
+                        "attest": {
+                            "aa_addr": "unix:///run/confidential-containers/attestation-agent/attestation-agent.sock"
+                        }
+                    }
+                ]
+            }
+        ))?;
+
+        let exclude = |value: serde_json::Value| -> Result<EgressNetfilterCaptureExclude> {
+            EgressNetfilterCaptureExclude::try_from(serde_json::from_value::<
+                EgressNetfilterCaptureExcludeArgs,
+            >(value)?)
+        };
+        let rule = exclude(json!({ "interface": "bond+", "port": 8080 }))?;
+        assert_eq!(rule.interface.as_deref(), Some("bond+"));
+        assert_eq!(rule.port, Some((8080, None)));
+
+        assert!(exclude(json!({})).is_err());
+        assert!(exclude(json!({ "port_end": 30031 })).is_err());
+        assert!(exclude(json!({ "port": 30031, "port_end": 30000 })).is_err());
+        assert!(exclude(json!({ "interface": "eth0; reboot" })).is_err());
+        assert!(exclude(json!({ "interface": "an-interface-name-too-long" })).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_deserialize_egress_netfilter_capture_all() -> Result<()> {
         // Empty capture_dst = capture all TCP traffic
@@ -830,4 +1208,65 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_direct_forward_rule_validation() -> Result<()> {
+        let rules: DirectForwardRules = serde_json::from_value(json!([
+            { "http_path": "/public/.*" },
+            { "sni": "^public\\.example\\.com$" }
+        ]))?;
+        for rule in &rules.0 {
+            rule.validate()?;
+        }
+
+        let rule: DirectForwardRule = serde_json::from_value(json!({}))?;
+        assert!(rule.validate().is_err());
+
+        let rule: DirectForwardRule = serde_json::from_value(json!({
+            "http_path": "/public/.*",
+            "sni": "public.example.com"
+        }))?;
+        assert!(rule.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_tng_response_validation() -> Result<()> {
+        let parse = |value: serde_json::Value| -> Result<NonTngResponseArgs> {
+            Ok(serde_json::from_value(value)?)
+        };
+
+        let args = parse(json!({"body": "{message}", "content_type": "text/html"}))?;
+        args.validate()?;
+        assert_eq!(args.status(), 403);
+
+        let args = parse(json!({"redirect_url": "https://example.com"}))?;
+        args.validate()?;
+        assert_eq!(args.status(), 302);
+
+        assert!(
+            parse(json!({"status": 200, "redirect_url": "https://example.com"}))?
+                .validate()
+                .is_err()
+        );
+        assert!(
+            parse(json!({"body": "", "redirect_url": "https://example.com"}))?
+                .validate()
+                .is_err()
+        );
+        assert!(parse(json!({"status": 1000}))?.validate().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rats_tls_validation() -> Result<()> {
+        let args: RatsTlsArgs =
+            serde_json::from_value(json!({ "multiplex": true, "adaptive_window": true }))?;
+        args.validate()?;
+
+        let args: RatsTlsArgs = serde_json::from_value(json!({ "adaptive_window": true }))?;
+        assert!(args.validate().is_err());
+        Ok(())
+    }
 }
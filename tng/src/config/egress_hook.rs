@@ -1,6 +1,7 @@
 use std::net::Ipv4Addr;
 
 use anyhow::bail;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::{formats::PreferMany, serde_as, OneOrMany};
 
@@ -14,7 +15,7 @@ fn wildcard_ip() -> Ipv4Addr {
 /// Uses LD_PRELOAD to intercept the server application's bind()/getsockname()
 /// syscalls, redirecting listening sockets through the TNG tunnel.
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct EgressHookArgs {
     /// When `false` (default), accepted connections whose peer IP is a local
@@ -42,7 +43,7 @@ pub struct EgressHookArgs {
 ///
 /// When the server application binds to the specified address/port,
 /// TNG redirects it to a different (real) port.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct EgressHookInterceptEntry {
     /// IPv4 address to match (e.g., "0.0.0.0", "192.168.1.1").
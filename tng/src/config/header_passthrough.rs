@@ -16,6 +16,19 @@ impl std::convert::TryFrom<String> for All {
     }
 }
 
+impl schemars::JsonSchema for All {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "All".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "string",
+            "const": "all"
+        })
+    }
+}
+
 impl From<All> for String {
     fn from(_: All) -> Self {
         "all".to_string()
@@ -23,7 +36,9 @@ impl From<All> for String {
 }
 
 /// Either copy every non-protected header (`All`) or an explicit allowlist (`List`).
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
 #[serde(untagged)]
 pub enum HeaderPassthroughSpec {
     All(All),
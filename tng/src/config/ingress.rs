@@ -1,15 +1,29 @@
 use anyhow::bail;
-use cidr::Ipv4Cidr;
+use cidr::{IpCidr, Ipv4Cidr};
+use derivative::Derivative;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::{formats::PreferMany, serde_as, OneOrMany};
 
 use crate::tunnel::access_log::IngressAccessMode;
+#[cfg(all(feature = "__ingress-common", not(wasm)))]
+use crate::tunnel::ingress::protocol::rats_tls::transport::TransportLayerCreator;
+#[cfg(not(wasm))]
+use crate::tunnel::resolver::Resolver;
 
+use super::circuit_breaker::CircuitBreakerArgs;
+use super::lazy_attest::LazyAttestArgs;
 use super::mapping_rule::MappingDe;
 use super::match_rule::{HostMatchConfig, PortMatchConfig};
-use super::{ra::RaArgsUnchecked, Endpoint, UdpQuicArgs};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::ohttp_padding::OHttpPaddingPolicy;
+use super::overload::OverloadArgs;
+use super::restart::RestartPolicyArgs;
+#[cfg(not(wasm))]
+use super::tunnel_dns::TunnelDnsArgs;
+use super::websocket::WebSocketArgs;
+use super::{ra::RaArgsUnchecked, BufferSizeArgs, Endpoint, UdpQuicArgs};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AddIngressArgs {
     #[serde(flatten)]
     pub ingress_mode: IngressMode,
@@ -18,7 +32,8 @@ pub struct AddIngressArgs {
     pub common: CommonArgs,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize, Derivative, JsonSchema)]
+#[derivative(Debug)]
 #[serde(deny_unknown_fields)]
 pub struct CommonArgs {
     #[serde(default = "Option::default")]
@@ -34,12 +49,55 @@ pub struct CommonArgs {
     #[serde(default = "Option::default")]
     pub quic: Option<UdpQuicArgs>,
 
+    /// Restart the service when it fails, instead of shutting down the instance.
+    #[serde(default = "Option::default")]
+    pub restart: Option<RestartPolicyArgs>,
+
+    /// Create the service in the background, retrying until the attestation agent is reachable,
+    /// instead of failing the startup of the instance.
+    #[serde(default = "Option::default")]
+    pub lazy_attest: Option<LazyAttestArgs>,
+
+    /// Fail fast the connections to an upstream endpoint which failed to be connected several times
+    /// in a row, for a cooldown period.
+    #[serde(default = "Option::default")]
+    pub circuit_breaker: Option<CircuitBreakerArgs>,
+
+    /// The sizes of the internal buffers used for forwarding.
+    #[serde(default = "Option::default")]
+    pub buffer_size: Option<BufferSizeArgs>,
+
+    /// Reject new connections early when the ingress is overloaded.
+    #[serde(default = "Option::default")]
+    pub overload: Option<OverloadArgs>,
+
+    /// Source addresses (in CIDR notation, e.g. `10.0.0.0/8`) which are allowed to connect to the
+    /// listeners of the ingress. Connections from other sources are closed right after they are
+    /// accepted, before anything is read from them. All sources are allowed if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "Vec<String>")]
+    pub allowed_sources: Vec<IpCidr>,
+
+    /// Resolve the domain names of the upstreams with a DNS server inside the TEE network, through
+    /// a trusted tunnel.
+    #[cfg(not(wasm))]
+    #[serde(default = "Option::default")]
+    pub tunnel_dns: Option<TunnelDnsArgs>,
+
+    /// Resolves the domain names of the upstreams instead of the resolver of the system. Only
+    /// settable by library users, see [`Resolver`].
+    #[cfg(not(wasm))]
+    #[serde(skip)]
+    #[derivative(Debug = "ignore")]
+    pub resolver: Option<std::sync::Arc<dyn Resolver>>,
+
     #[serde(flatten)]
     pub ra_args: RaArgsUnchecked,
 }
 
 /// Configuration for rats-TLS transport.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Clone, Serialize, Deserialize, Default, Derivative, JsonSchema)]
+#[derivative(Debug)]
 #[serde(deny_unknown_fields)]
 pub struct RatsTlsArgs {
     /// When `true`, uses HTTP/2 CONNECT tunneling to multiplex multiple
@@ -52,9 +110,60 @@ pub struct RatsTlsArgs {
     /// whose bandwidth is limited by the TLS encryption capacity of one CPU core.
     #[serde(default)]
     pub multiplex: bool,
+
+    /// When `true`, the rats-TLS sessions to the upstreams of a `mapping` ingress are
+    /// established at startup and kept open, so that the first client connection does not wait
+    /// for the attestation handshake. Requires `multiplex`, since the sessions are only pooled
+    /// then.
+    #[serde(default)]
+    pub prewarm: bool,
+
+    /// When `true`, the flow-control windows of the HTTP/2 connections carried by the rats-TLS
+    /// sessions are sized from the bandwidth-delay product measured with PING frames, instead of
+    /// the fixed default ones, which limit the throughput of the streams over high-latency links.
+    /// Requires `multiplex`.
+    #[serde(default)]
+    pub adaptive_window: bool,
+
+    /// The time, in seconds, to establish a new rats-TLS session, covering the connection of the
+    /// transport layer, the TLS handshake and the verification of the peer. The streams waiting on
+    /// the session fail once it is exceeded. There is no timeout if not set.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handshake_timeout_secs: Option<u64>,
+
+    /// The ALPN protocols offered in the rats-TLS handshake, in order of preference, instead of
+    /// `h2` with `multiplex` or `rats-tls` otherwise. The egress must accept one of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alpn: Vec<String>,
+
+    /// Carry the rats-TLS sessions inside WebSocket connections to the upstream. Only supported
+    /// by `tng-wasm`, where raw TCP connections cannot be opened, and requires `multiplex` to be
+    /// disabled.
+    #[serde(default = "Option::default")]
+    pub websocket: Option<WebSocketArgs>,
+
+    /// The transport layer the rats-TLS sessions are established over, instead of TCP. Only
+    /// settable by library users, see [`TransportLayerCreator`].
+    #[cfg(all(feature = "__ingress-common", not(wasm)))]
+    #[serde(skip)]
+    #[derivative(Debug = "ignore")]
+    pub transport: Option<std::sync::Arc<dyn TransportLayerCreator>>,
+}
+
+impl RatsTlsArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.prewarm && !self.multiplex {
+            anyhow::bail!("`rats_tls.prewarm` requires `rats_tls.multiplex` to be enabled");
+        }
+        if self.adaptive_window && !self.multiplex {
+            anyhow::bail!("`rats_tls.adaptive_window` requires `rats_tls.multiplex` to be enabled");
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum IngressMode {
     #[serde(rename = "mapping")]
@@ -98,6 +207,18 @@ pub struct IngressMappingArgs {
     pub rules: Vec<super::mapping_rule::MappingRule>,
 }
 
+// The accepted input is described by `MappingDe`, which covers both the `rules` array and the
+// legacy `in`/`out` form.
+impl JsonSchema for IngressMappingArgs {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "IngressMappingArgs".into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        MappingDe::json_schema(generator)
+    }
+}
+
 impl<'de> Deserialize<'de> for IngressMappingArgs {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -111,7 +232,7 @@ impl<'de> Deserialize<'de> for IngressMappingArgs {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct IngressMappingUdpArgs {
     /// Local UDP socket to listen on.
@@ -140,7 +261,7 @@ pub struct IngressMappingUdpArgs {
 }
 
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IngressHttpProxyArgs {
     pub proxy_listen: Endpoint,
 
@@ -150,10 +271,57 @@ pub struct IngressHttpProxyArgs {
     #[serde(alias = "dst_filter")]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub dst_filters: Vec<EndpointMatcherConfig>,
+
+    /// Limits on the requests of the clients, protecting the ingress from slow or oversized
+    /// requests.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limits: Option<HttpProxyLimitsArgs>,
+}
+
+/// Limits on the requests which an `http_proxy` ingress accepts from its clients. The limits which
+/// are not set are left to the defaults of the HTTP server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HttpProxyLimitsArgs {
+    /// The maximum size, in bytes, of the headers of a request, at least 8192. A larger request is
+    /// answered with `431 Request Header Fields Too Large`.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_header_size: Option<usize>,
+
+    /// The maximum size, in bytes, of the body of a request forwarded by the reverse proxy. A
+    /// request declaring a larger `Content-Length` is answered with `413 Payload Too Large`, and a
+    /// streamed body is cut off once it exceeds the limit.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_body_size: Option<usize>,
+
+    /// The time, in seconds, which a client has to send the headers of a HTTP/1 request, after
+    /// which the connection is closed.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_read_timeout_secs: Option<u64>,
+}
+
+impl HttpProxyLimitsArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        // The HTTP/1 server of hyper buffers at least 8192 bytes
+        if self.max_header_size.is_some_and(|size| size < 8192) {
+            anyhow::bail!("`limits.max_header_size` must be at least 8192");
+        }
+        if self.max_body_size == Some(0) {
+            anyhow::bail!("`limits.max_body_size` must be greater than 0");
+        }
+        if self.header_read_timeout_secs == Some(0) {
+            anyhow::bail!("`limits.header_read_timeout_secs` must be greater than 0");
+        }
+        Ok(())
+    }
 }
 
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IngressNetfilterArgs {
     #[serde_as(as = "OneOrMany<_, PreferMany>")]
     #[serde(default = "Vec::new")]
@@ -168,9 +336,18 @@ pub struct IngressNetfilterArgs {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub nocapture_cgroup: Vec<String>,
 
+    /// Destinations which are never captured, even if they match `capture_dst`, e.g. the
+    /// attestation service or a management network.
+    #[serde_as(as = "OneOrMany<_, PreferMany>")]
+    #[serde(default = "Vec::new")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nocapture_dst: Vec<IngressNetfilterCaptureDstArgs>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub listen_port: Option<u16>,
 
+    /// The SO_MARK set on the sockets of the tunnel established by TNG. Packets carrying this mark
+    /// are never captured.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub so_mark: Option<u32>,
 }
@@ -180,7 +357,7 @@ pub struct IngressNetfilterArgs {
 /// Uses LD_PRELOAD to intercept the client application's connect() syscalls,
 /// routing matched connections through an internal HTTP CONNECT proxy into the TNG tunnel.
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct IngressHookArgs {
     /// Filter rules: which destination IP+port pairs should be intercepted.
@@ -209,11 +386,12 @@ pub struct IngressHookArgs {
 /// A single capture destination rule for ingress hook mode.
 ///
 /// Mirrors `IngressNetfilterCaptureDstArgs` structure for consistency.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct IngressHookCaptureDst {
     /// IPv4 address or CIDR prefix (e.g. "10.0.0.0/24" or "192.168.1.1").
     /// None = match any destination IP.
+    #[schemars(with = "Option<String>")]
     pub host: Option<Ipv4Cidr>,
 
     /// Destination port to intercept.
@@ -226,9 +404,10 @@ pub struct IngressHookCaptureDst {
 
 /// Instead of using the IngressNetfilterCaptureDst directly, here we define a common struct for json parsing to get better deserialization error message.
 /// See https://github.com/serde-rs/serde/issues/2157
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct IngressNetfilterCaptureDstArgs {
+    #[schemars(with = "Option<String>")]
     host: Option<Ipv4Cidr>,
     ipset: Option<String>,
     port: Option<u16>,
@@ -296,7 +475,7 @@ pub enum IngressNetfilterCaptureDst {
 }
 
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IngressSocks5Args {
     pub proxy_listen: Endpoint,
 
@@ -305,17 +484,32 @@ pub struct IngressSocks5Args {
     pub dst_filters: Vec<EndpointMatcherConfig>,
 
     pub auth: Option<Socks5AuthArgs>,
+
+    /// Terminate TLS on the listener (SOCKS over TLS), so that the credentials and the destinations
+    /// sent in the socks5 handshake are not visible on the network between the clients and TNG.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Socks5TlsArgs>,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Socks5AuthArgs {
     pub username: String,
 
     pub password: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Socks5TlsArgs {
+    /// Path to the PEM file of the server certificate chain.
+    pub cert: String,
+
+    /// Path to the PEM file of the server private key.
+    pub key: String,
+}
+
 /// Fallback outer OHTTP POST path used when no `path_rewrites` rule matches
 /// (including when `path_rewrites` is unset or empty).
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, JsonSchema)]
 pub enum PathDefault {
     /// Outer path = `/` (the historical default behavior).
     #[default]
@@ -326,7 +520,7 @@ pub enum PathDefault {
     Original,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct OHttpArgs {
     #[serde(default)]
@@ -376,9 +570,15 @@ pub struct OHttpArgs {
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub tls_ca_certs: Vec<String>,
+
+    /// Padding applied to the encapsulated requests, hiding the exact request sizes from
+    /// on-path observers. No padding is applied if not specified.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub padding: Option<OHttpPaddingPolicy>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct PathRewrite {
     pub match_regex: String,
@@ -395,7 +595,7 @@ pub struct PathRewrite {
 ///
 /// Each field accepts `"all"` (every header except the protected set) or an
 /// explicit allowlist. Defaults to empty (copy nothing).
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct IngressHeaderPassthroughConfig {
     /// Inner (plaintext) request → outer (ciphertext) request.
@@ -417,7 +617,7 @@ pub struct IngressHeaderPassthroughConfig {
 /// Note: `#[serde(deny_unknown_fields)]` is intentionally omitted because it is
 /// incompatible with `#[serde(flatten)]`. Typos in field names (e.g. `doman`)
 /// will silently produce an `All` matcher instead of erroring.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EndpointMatcherConfig {
     /// Host/address matching rule: domain, domain_regex, IP, or CIDR.
     ///
@@ -430,6 +630,11 @@ pub struct EndpointMatcherConfig {
     /// When a range is specified via `port_end`, matches ports in `[port, port_end]`.
     #[serde(flatten)]
     pub port_match: PortMatchConfig,
+    /// Excludes the matching endpoints instead. An endpoint matching any excluding rule is not
+    /// matched, whatever the other rules. If all the rules are excluding, all the other endpoints
+    /// are matched.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exclude: bool,
 }
 
 #[cfg(test)]
@@ -442,10 +647,122 @@ mod tests {
     use crate::config::TngConfig;
 
     use super::{
-        AddIngressArgs, IngressMode, IngressNetfilterCaptureDst, IngressNetfilterCaptureDstArgs,
-        OHttpArgs, PathDefault,
+        AddIngressArgs, HttpProxyLimitsArgs, IngressMode, IngressNetfilterCaptureDst,
+        IngressNetfilterCaptureDstArgs, OHttpArgs, PathDefault, RatsTlsArgs,
     };
 
+    #[test]
+    fn test_deserialize_ingress_http_proxy_limits() -> Result<()> {
+        let args: AddIngressArgs = serde_json::from_value(json!({
+            "http_proxy": {
+                "proxy_listen": { "port": 41000 },
+                "limits": {
+                    "max_header_size": 16384,
+                    "max_body_size": 1048576,
+                    "header_read_timeout_secs": 10
+                }
+            },
+            "no_ra": true
+        }))?;
+        match args.ingress_mode {
+            IngressMode::HttpProxy(http_proxy_args) => {
+                let limits = http_proxy_args.limits.expect("limits are set");
+                limits.validate()?;
+                assert_eq!(limits.max_header_size, Some(16384));
+                assert_eq!(limits.max_body_size, Some(1048576));
+                assert_eq!(limits.header_read_timeout_secs, Some(10));
+            }
+            _ => panic!("expected http_proxy mode"),
+        }
+
+        let limits: HttpProxyLimitsArgs =
+            serde_json::from_value(json!({ "max_header_size": 1024 }))?;
+        assert!(limits.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rats_tls_validation() -> Result<()> {
+        let args: RatsTlsArgs = serde_json::from_value(json!({
+            "multiplex": true,
+            "prewarm": true,
+            "adaptive_window": true
+        }))?;
+        args.validate()?;
+
+        let args: RatsTlsArgs = serde_json::from_value(json!({ "adaptive_window": true }))?;
+        assert!(args.validate().is_err());
+        let args: RatsTlsArgs = serde_json::from_value(json!({ "prewarm": true }))?;
+        assert!(args.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_ingress_allowed_sources() -> Result<()> {
+        let args: AddIngressArgs = serde_json::from_value(json!({
+            "socks5": {
+                "proxy_listen": { "port": 1080 }
+            },
+            "allowed_sources": ["10.0.0.0/8", "fd00::/8"],
+            "no_ra": true
+        }))?;
+        assert_eq!(args.common.allowed_sources.len(), 2);
+        assert!(args.common.allowed_sources[0].contains(&"10.1.2.3".parse()?));
+
+        let args: AddIngressArgs = serde_json::from_value(json!({
+            "socks5": {
+                "proxy_listen": { "port": 1080 }
+            },
+            "no_ra": true
+        }))?;
+        assert!(args.common.allowed_sources.is_empty());
+        assert!(serde_json::to_value(&args)?
+            .get("allowed_sources")
+            .is_none());
+
+        assert!(serde_json::from_value::<AddIngressArgs>(json!({
+            "socks5": {
+                "proxy_listen": { "port": 1080 }
+            },
+            "allowed_sources": ["10.0.0.1/8"],
+            "no_ra": true
+        }))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_ingress_socks5_tls() -> Result<()> {
+        let args: AddIngressArgs = serde_json::from_value(json!({
+            "socks5": {
+                "proxy_listen": { "port": 1080 },
+                "tls": {
+                    "cert": "/etc/tng/socks5.crt",
+                    "key": "/etc/tng/socks5.key"
+                }
+            },
+            "no_ra": true
+        }))?;
+        match args.ingress_mode {
+            IngressMode::Socks5(socks5_args) => {
+                let tls = socks5_args.tls.expect("tls is set");
+                assert_eq!(tls.cert, "/etc/tng/socks5.crt");
+                assert_eq!(tls.key, "/etc/tng/socks5.key");
+            }
+            _ => panic!("expected socks5 mode"),
+        }
+
+        assert!(serde_json::from_value::<AddIngressArgs>(json!({
+            "socks5": {
+                "proxy_listen": { "port": 1080 },
+                "tls": { "cert": "/etc/tng/socks5.crt" }
+            },
+            "no_ra": true
+        }))
+        .is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_deserialize_ingress_hook_capture_local_traffic() -> Result<()> {
         let args: AddIngressArgs = serde_json::from_value(json!({
@@ -533,6 +850,15 @@ mod tests {
                                     "port_end": 30031
                                 },
                             ],
+                            "nocapture_dst": [
+                                {
+                                    "host": "192.168.1.254",
+                                    "port": 8080
+                                },
+                                {
+                                    "port": 22
+                                },
+                            ],
                             "listen_port": 50000
                         },
                         "verify": {
@@ -1149,4 +1475,18 @@ mod tests {
         assert_eq!(args2.tls, Some(false));
         Ok(())
     }
+
+    #[test]
+    fn test_rats_tls_args_alpn() -> Result<()> {
+        let args: super::RatsTlsArgs = serde_json::from_value(json!({}))?;
+        assert!(args.alpn.is_empty());
+        assert!(serde_json::to_value(&args)?.get("alpn").is_none());
+
+        let args: super::RatsTlsArgs = serde_json::from_value(json!({
+            "multiplex": true,
+            "alpn": ["h2", "tng"]
+        }))?;
+        assert_eq!(args.alpn, vec!["h2", "tng"]);
+        Ok(())
+    }
 }
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Start an ingress or egress before the attestation agent is reachable, e.g. on hosts where the
+/// attestation agent is started after TNG. The service is created in the background, and retried
+/// until it succeeds, instead of failing the startup of the whole instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LazyAttestArgs {
+    /// The delay, in seconds, between two attempts to create the service.
+    #[serde(default = "default_retry_interval_secs")]
+    pub retry_interval_secs: u64,
+}
+
+fn default_retry_interval_secs() -> u64 {
+    5
+}
+
+impl LazyAttestArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.retry_interval_secs == 0 {
+            anyhow::bail!("`lazy_attest.retry_interval_secs` must be greater than 0");
+        }
+        Ok(())
+    }
+
+    pub fn retry_interval(&self) -> Duration {
+        Duration::from_secs(self.retry_interval_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn test_lazy_attest_args() -> Result<()> {
+        let args: LazyAttestArgs = serde_json::from_value(json!({}))?;
+        assert_eq!(args.retry_interval(), Duration::from_secs(5));
+        args.validate()?;
+
+        let args: LazyAttestArgs = serde_json::from_value(json!({
+            "retry_interval_secs": 0,
+        }))?;
+        assert!(args.validate().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The listen backlog used when `backlog` is not set, the same as the one of tokio.
+const DEFAULT_BACKLOG: u32 = 1024;
+
+/// Tuning of the TCP listeners of an egress, for the egresses receiving bursts of connections.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerArgs {
+    /// The length of the queue of the connections not accepted yet. It is capped by the
+    /// `net.core.somaxconn` sysctl on Linux.
+    #[serde(default = "Option::default")]
+    pub backlog: Option<u32>,
+
+    /// The number of listeners bound to each address with `SO_REUSEPORT`, each accepting in its
+    /// own task. The kernel spreads the new connections across them. Only supported on Linux.
+    #[serde(default = "default_acceptors")]
+    pub acceptors: usize,
+}
+
+fn default_acceptors() -> usize {
+    1
+}
+
+impl Default for ListenerArgs {
+    fn default() -> Self {
+        Self {
+            backlog: None,
+            acceptors: default_acceptors(),
+        }
+    }
+}
+
+impl ListenerArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.backlog == Some(0) {
+            anyhow::bail!("`listener.backlog` must be greater than 0");
+        }
+        if self.acceptors == 0 {
+            anyhow::bail!("`listener.acceptors` must be greater than 0");
+        }
+        if self.acceptors > 1
+            && !cfg!(any(
+                target_os = "android",
+                target_os = "fuchsia",
+                target_os = "linux"
+            ))
+        {
+            anyhow::bail!("`listener.acceptors` greater than 1 is only supported on Linux");
+        }
+        Ok(())
+    }
+
+    pub fn backlog(&self) -> i32 {
+        i32::try_from(self.backlog.unwrap_or(DEFAULT_BACKLOG)).unwrap_or(i32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn test_listener_args() -> Result<()> {
+        let args: ListenerArgs = serde_json::from_value(json!({}))?;
+        assert_eq!(args, ListenerArgs::default());
+        assert_eq!(args.backlog(), 1024);
+        args.validate()?;
+
+        let args: ListenerArgs = serde_json::from_value(json!({
+            "backlog": 4096,
+            "acceptors": 4,
+        }))?;
+        assert_eq!(args.backlog(), 4096);
+        args.validate()?;
+
+        let args: ListenerArgs = serde_json::from_value(json!({
+            "acceptors": 0,
+        }))?;
+        assert!(args.validate().is_err());
+        Ok(())
+    }
+}
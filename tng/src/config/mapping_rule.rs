@@ -1,9 +1,10 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::net::Ipv4Addr;
 
 /// Endpoint within a mapping rule. Host is always a single IPv4 address;
 /// port can be a single value or a closed range [port, port_end].
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RuleEndpoint {
     pub host: Option<Ipv4Addr>,
     pub port: u16,
@@ -13,14 +14,14 @@ pub struct RuleEndpoint {
 }
 
 /// A single mapping rule: one in→out forwarding pair.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MappingRule {
     pub r#in: RuleEndpoint,
     pub out: RuleEndpoint,
 }
 
 /// Legacy endpoint used during deserialization of old-style mapping configs.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct LegacyEndpoint {
     pub host: Option<Ipv4Addr>,
     pub port: u16,
@@ -28,7 +29,7 @@ pub struct LegacyEndpoint {
 
 /// Dual-mode deserialization for mapping args:
 /// either new `{ "rules": [...] }` or legacy `{ "in": {...}, "out": {...} }`.
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum MappingDe {
     /// New format: { "rules": [...] }
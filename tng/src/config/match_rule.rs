@@ -1,9 +1,10 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Host/address matching rule. Serialized flat so that old JSON configs
 /// work without a `"type"` discriminator. When none of the domain/ip fields
 /// are present, deserializes to `HostMatchConfig::All`.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(untagged)]
 pub enum HostMatchConfig {
     /// Exact domain name or Envoy-style wildcard: `"www.foo.com"`, `"*.foo.com"`, `"foo.*"`.
@@ -24,7 +25,7 @@ pub enum HostMatchConfig {
 ///
 /// When `port_end` is set together with `port`, matches destination ports in
 /// the range `[port, port_end]`.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct PortMatchConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<u16>,
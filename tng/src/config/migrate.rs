@@ -0,0 +1,310 @@
+//! Migration of configs written for older versions of TNG to the current schema.
+//!
+//! Some legacy fields are still accepted by aliases, while others are ignored or rejected now
+//! (e.g. `as_is_grpc` since the pluggable attestation providers were introduced). The migration
+//! rewrites all of them, so that old configs can be upgraded in one step with
+//! `tng config migrate`.
+
+use std::fmt;
+
+use anyhow::{bail, Result};
+use serde_json::{json, Map, Value};
+
+/// A change made to the config by the migration. Fields are addressed by dot-separated paths, in
+/// which array elements are addressed by their index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationChange {
+    /// The field is rewritten into its replacement in the current schema.
+    Rewritten { from: String, to: String },
+    /// The field is removed, since it has no effect anymore.
+    Dropped { path: String, reason: &'static str },
+}
+
+impl fmt::Display for MigrationChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationChange::Rewritten { from, to } => write!(f, "rewritten: `{from}` -> `{to}`"),
+            MigrationChange::Dropped { path, reason } => write!(f, "dropped: `{path}` ({reason})"),
+        }
+    }
+}
+
+/// Migrate the config in place, and return the changes made to it.
+pub fn migrate(config: &mut Value) -> Result<Vec<MigrationChange>> {
+    let Value::Object(config) = config else {
+        bail!("The config is not a JSON object");
+    };
+
+    let mut changes = vec![];
+    if config.remove("admin_bind").is_some() {
+        changes.push(MigrationChange::Dropped {
+            path: "admin_bind".into(),
+            reason: "the Envoy admin interface is no longer supported",
+        });
+    }
+
+    migrate_entries(config, "add_ingress", migrate_ingress, &mut changes)?;
+    migrate_entries(config, "add_egress", migrate_egress, &mut changes)?;
+
+    Ok(changes)
+}
+
+type MigrateEntry = fn(&mut Map<String, Value>, &str, &mut Vec<MigrationChange>) -> Result<()>;
+
+fn migrate_entries(
+    config: &mut Map<String, Value>,
+    field: &str,
+    migrate_entry: MigrateEntry,
+    changes: &mut Vec<MigrationChange>,
+) -> Result<()> {
+    let Some(entries) = config.get_mut(field) else {
+        return Ok(());
+    };
+    let Value::Array(entries) = entries else {
+        bail!("`{field}` is not an array");
+    };
+    for (i, entry) in entries.iter_mut().enumerate() {
+        let path = format!("{field}.{i}");
+        let Value::Object(entry) = entry else {
+            bail!("`{path}` is not an object");
+        };
+        migrate_entry(entry, &path, changes)?;
+    }
+    Ok(())
+}
+
+fn migrate_ingress(
+    entry: &mut Map<String, Value>,
+    path: &str,
+    changes: &mut Vec<MigrationChange>,
+) -> Result<()> {
+    rename(entry, path, "encap_in_http", "ohttp", changes)?;
+
+    // In TNG version <= 1.0.1, `dst_filters` is named as `dst_filter` and holds a single filter
+    if let Some(Value::Object(http_proxy)) = entry.get_mut("http_proxy") {
+        if let Some(dst_filter) = http_proxy.remove("dst_filter") {
+            let Value::Array(dst_filters) = http_proxy
+                .entry("dst_filters")
+                .or_insert_with(|| Value::Array(vec![]))
+            else {
+                bail!("`{path}.http_proxy.dst_filters` is not an array");
+            };
+            match dst_filter {
+                Value::Array(filters) => dst_filters.extend(filters),
+                filter => dst_filters.push(filter),
+            }
+            changes.push(MigrationChange::Rewritten {
+                from: format!("{path}.http_proxy.dst_filter"),
+                to: format!("{path}.http_proxy.dst_filters"),
+            });
+        }
+    }
+
+    migrate_ra(entry, path, changes)
+}
+
+fn migrate_egress(
+    entry: &mut Map<String, Value>,
+    path: &str,
+    changes: &mut Vec<MigrationChange>,
+) -> Result<()> {
+    rename(entry, path, "decap_from_http", "ohttp", changes)?;
+
+    let mut regexes = None;
+    if let Some(Value::Object(ohttp)) = entry.get_mut("ohttp") {
+        regexes = ohttp.remove("allow_non_tng_traffic_regexes");
+        if let Some(Value::Object(key)) = ohttp.get_mut("key") {
+            migrate_ra(key, &format!("{path}.ohttp.key"), changes)?;
+        }
+    }
+    match regexes {
+        None => {}
+        Some(Value::Null) => changes.push(MigrationChange::Dropped {
+            path: format!("{path}.ohttp.allow_non_tng_traffic_regexes"),
+            reason: "the field is empty",
+        }),
+        Some(Value::Array(regexes)) => {
+            // Since 2.2.4, the paths bypassing OHTTP are configured with `direct_forward`
+            let Value::Array(direct_forward) = entry
+                .entry("direct_forward")
+                .or_insert_with(|| Value::Array(vec![]))
+            else {
+                bail!("`{path}.direct_forward` is not an array");
+            };
+            direct_forward.extend(
+                regexes
+                    .into_iter()
+                    .map(|regex| json!({ "http_path": regex })),
+            );
+            changes.push(MigrationChange::Rewritten {
+                from: format!("{path}.ohttp.allow_non_tng_traffic_regexes"),
+                to: format!("{path}.direct_forward"),
+            });
+        }
+        Some(_) => bail!("`{path}.ohttp.allow_non_tng_traffic_regexes` is not an array"),
+    }
+
+    migrate_ra(entry, path, changes)
+}
+
+/// Migrate the remote attestation fields, which are flattened into the object.
+fn migrate_ra(
+    obj: &mut Map<String, Value>,
+    path: &str,
+    changes: &mut Vec<MigrationChange>,
+) -> Result<()> {
+    for field in ["attest", "verify"] {
+        let Some(Value::Object(args)) = obj.get_mut(field) else {
+            continue;
+        };
+        // Since 2.5.0, the attestation service API is selected with `as_type`
+        let Some(as_is_grpc) = args.remove("as_is_grpc") else {
+            continue;
+        };
+        let from = format!("{path}.{field}.as_is_grpc");
+        if as_is_grpc == Value::Bool(true) {
+            if args.contains_key("as_type") {
+                bail!("Both `{from}` and `{path}.{field}.as_type` are set");
+            }
+            args.insert("as_type".into(), "grpc".into());
+            changes.push(MigrationChange::Rewritten {
+                from,
+                to: format!("{path}.{field}.as_type"),
+            });
+        } else {
+            changes.push(MigrationChange::Dropped {
+                path: from,
+                reason: "the restful API is used by default",
+            });
+        }
+    }
+    Ok(())
+}
+
+fn rename(
+    obj: &mut Map<String, Value>,
+    path: &str,
+    from: &str,
+    to: &str,
+    changes: &mut Vec<MigrationChange>,
+) -> Result<()> {
+    let Some(value) = obj.remove(from) else {
+        return Ok(());
+    };
+    if obj.contains_key(to) {
+        bail!("Both `{path}.{from}` and `{path}.{to}` are set");
+    }
+    obj.insert(to.into(), value);
+    changes.push(MigrationChange::Rewritten {
+        from: format!("{path}.{from}"),
+        to: format!("{path}.{to}"),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::TngConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_migrate_legacy_config() -> Result<()> {
+        let mut config = json!({
+            "admin_bind": {"host": "0.0.0.0", "port": 9901},
+            "add_ingress": [
+                {
+                    "http_proxy": {
+                        "proxy_listen": {"host": "0.0.0.0", "port": 41000},
+                        "dst_filter": {"domain": "*.example.com", "port": 80}
+                    },
+                    "encap_in_http": {},
+                    "verify": {
+                        "as_addr": "http://127.0.0.1:50004/",
+                        "as_is_grpc": true,
+                        "policy_ids": ["default"]
+                    }
+                }
+            ],
+            "add_egress": [
+                {
+                    "mapping": {
+                        "in": {"host": "0.0.0.0", "port": 20001},
+                        "out": {"host": "127.0.0.1", "port": 30001}
+                    },
+                    "decap_from_http": {
+                        "allow_non_tng_traffic_regexes": ["/public/.*"]
+                    },
+                    "attest": {
+                        "aa_addr": "unix:///run/confidential-containers/attestation-agent/attestation-agent.sock"
+                    }
+                }
+            ]
+        });
+
+        let changes = migrate(&mut config)?;
+        assert_eq!(
+            changes,
+            vec![
+                MigrationChange::Dropped {
+                    path: "admin_bind".into(),
+                    reason: "the Envoy admin interface is no longer supported",
+                },
+                MigrationChange::Rewritten {
+                    from: "add_ingress.0.encap_in_http".into(),
+                    to: "add_ingress.0.ohttp".into(),
+                },
+                MigrationChange::Rewritten {
+                    from: "add_ingress.0.http_proxy.dst_filter".into(),
+                    to: "add_ingress.0.http_proxy.dst_filters".into(),
+                },
+                MigrationChange::Rewritten {
+                    from: "add_ingress.0.verify.as_is_grpc".into(),
+                    to: "add_ingress.0.verify.as_type".into(),
+                },
+                MigrationChange::Rewritten {
+                    from: "add_egress.0.decap_from_http".into(),
+                    to: "add_egress.0.ohttp".into(),
+                },
+                MigrationChange::Rewritten {
+                    from: "add_egress.0.ohttp.allow_non_tng_traffic_regexes".into(),
+                    to: "add_egress.0.direct_forward".into(),
+                },
+            ]
+        );
+
+        assert_eq!(config["add_ingress"][0]["verify"]["as_type"], json!("grpc"));
+        assert_eq!(
+            config["add_ingress"][0]["http_proxy"]["dst_filters"],
+            json!([{"domain": "*.example.com", "port": 80}])
+        );
+        assert_eq!(
+            config["add_egress"][0]["direct_forward"],
+            json!([{"http_path": "/public/.*"}])
+        );
+        assert_eq!(config["add_egress"][0]["ohttp"], json!({}));
+
+        // The migrated config is valid, and migrating it again changes nothing
+        serde_json::from_value::<TngConfig>(config.clone())?;
+        assert!(migrate(&mut config)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_conflict() {
+        let mut config = json!({
+            "add_egress": [
+                {
+                    "mapping": {
+                        "in": {"port": 20001},
+                        "out": {"host": "127.0.0.1", "port": 30001}
+                    },
+                    "decap_from_http": {},
+                    "ohttp": {},
+                    "no_ra": true
+                }
+            ]
+        });
+        assert!(migrate(&mut config).is_err());
+    }
+}
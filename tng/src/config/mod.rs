@@ -1,18 +1,41 @@
 use control_interface::ControlInterfaceArgs;
-use egress::AddEgressArgs;
+use egress::{AddEgressArgs, KeyArgs, OHttpArgs};
+use indexmap::IndexMap;
 use ingress::AddIngressArgs;
-use observability::{metric::MetricArgs, trace::TraceArgs};
+use observability::{
+    access_log::AccessLogArgs, audit_log::AuditLogArgs, log::LogArgs, metric::MetricArgs,
+    trace::TraceArgs,
+};
+use ra::{AttestArgs, RaProfile, VerifyArgs};
+use runtime::RuntimeArgs;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+pub mod builder;
+pub mod circuit_breaker;
+pub mod connect_retry;
 pub mod control_interface;
 pub mod egress;
 pub mod egress_hook;
 pub mod header_passthrough;
 pub mod ingress;
+pub mod lazy_attest;
+pub mod listener;
 pub mod mapping_rule;
 pub mod match_rule;
+pub mod migrate;
 pub mod observability;
+pub mod ohttp_padding;
+pub mod overload;
+pub mod overrides;
 pub mod ra;
+pub mod restart;
+pub mod runtime;
+#[cfg(not(wasm))]
+pub mod source;
+#[cfg(not(wasm))]
+pub mod tunnel_dns;
+pub mod websocket;
 
 // Shared types used by both tng and tng-hook
 pub use tng_hook_types::{
@@ -23,7 +46,7 @@ pub use tng_hook_types::{
 // Internal TNG types (not serialized to .so)
 pub use egress_hook::TngEgressHookMappingEntry;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct TngConfig {
     #[serde(default = "Option::default")]
@@ -38,6 +61,47 @@ pub struct TngConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub trace: Option<TraceArgs>,
 
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log: Option<LogArgs>,
+
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_log: Option<AccessLogArgs>,
+
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audit_log: Option<AuditLogArgs>,
+
+    /// How long to wait, in seconds, for the established connections to finish when the instance
+    /// is shut down. The connections still open after it are closed. All the connections are
+    /// waited for if not set.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shutdown_drain_timeout_secs: Option<u64>,
+
+    /// The layout of the runtimes which the protocol layers of the ingresses and egresses run on.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<RuntimeArgs>,
+
+    /// Attestation parameters inherited by every ingress and egress which neither sets `attest`
+    /// itself nor sets `no_ra`.
+    #[serde(default, deserialize_with = "ra::deserialize_with_tag_defaults")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_attest: Option<AttestArgs>,
+
+    /// Verification parameters inherited by every ingress and egress which neither sets `verify`
+    /// itself nor sets `no_ra`.
+    #[serde(default, deserialize_with = "ra::deserialize_with_tag_defaults")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_verify: Option<VerifyArgs>,
+
+    /// Named RA parameters, which ingresses and egresses refer to with
+    /// `"attest": {"profile": "<name>"}` or `"verify": {"profile": "<name>"}`.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub ra_profiles: IndexMap<String, RaProfile>,
+
     #[serde(default)]
     pub add_ingress: Vec<AddIngressArgs>,
 
@@ -50,7 +114,53 @@ pub struct TngConfig {
     pub admin_bind: Option<Endpoint>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl TngConfig {
+    /// Generate the JSON Schema of the configuration file, which can be used by editors and CI
+    /// linting to validate and autocomplete configs against this version of TNG.
+    pub fn json_schema() -> schemars::Schema {
+        schemars::schema_for!(TngConfig)
+    }
+
+    /// Resolve the RA parameters of the ingresses and egresses: references to `ra_profiles` are
+    /// replaced with the profiles, and `default_attest` / `default_verify` are inherited for the
+    /// parameters which are not set.
+    pub fn resolve_ra_args(&mut self) -> anyhow::Result<()> {
+        use anyhow::Context as _;
+
+        for (i, ingress) in self.add_ingress.iter_mut().enumerate() {
+            let ra_args = &mut ingress.common.ra_args;
+            ra_args
+                .resolve_profiles(&self.ra_profiles)
+                .with_context(|| format!("Invalid RA parameters in `add_ingress.{i}`"))?;
+            ra_args.inherit_defaults(self.default_attest.as_ref(), self.default_verify.as_ref());
+        }
+        for (i, egress) in self.add_egress.iter_mut().enumerate() {
+            let ra_args = &mut egress.common.ra_args;
+            ra_args
+                .resolve_profiles(&self.ra_profiles)
+                .with_context(|| format!("Invalid RA parameters in `add_egress.{i}`"))?;
+            ra_args.inherit_defaults(self.default_attest.as_ref(), self.default_verify.as_ref());
+
+            // The peers of the OHTTP key manager may refer to profiles too, but do not inherit
+            // the defaults
+            if let Some(OHttpArgs {
+                key: KeyArgs::PeerShared(peer_shared),
+                ..
+            }) = &mut egress.common.ohttp
+            {
+                peer_shared
+                    .ra_args
+                    .resolve_profiles(&self.ra_profiles)
+                    .with_context(|| {
+                        format!("Invalid RA parameters in `add_egress.{i}.ohttp.key`")
+                    })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Endpoint {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -59,7 +169,7 @@ pub struct Endpoint {
 }
 
 /// Per-entry QUIC configuration for UDP tunneling, shared by ingress and egress.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct UdpQuicArgs {
     /// Maximum QUIC datagram payload size. If not specified, quinn default is used.
@@ -67,6 +177,52 @@ pub struct UdpQuicArgs {
     pub max_datagram_size: Option<usize>,
 }
 
+/// Per-entry sizes of the internal buffers used for forwarding, shared by ingress and egress.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BufferSizeArgs {
+    /// Size in bytes of the buffer used for each direction when copying data between the
+    /// downstream and the upstream.
+    #[serde(default = "default_forward_buffer_size")]
+    pub forward: usize,
+
+    /// Size in bytes of the in-memory pipe between the HTTP layer and the tunnel, e.g. in the
+    /// reverse proxy of `http_proxy` and in the OHTTP server.
+    #[serde(default = "default_pipe_buffer_size")]
+    pub pipe: usize,
+}
+
+// The default buffer size used in tokio::io::copy_bidirectional is 8 KB, here we increase it to 512 KB to improve the performance.
+pub const DEFAULT_FORWARD_BUF_SIZE: usize = 512 * 1024;
+
+pub const DEFAULT_PIPE_BUF_SIZE: usize = 4096;
+
+fn default_forward_buffer_size() -> usize {
+    DEFAULT_FORWARD_BUF_SIZE
+}
+
+fn default_pipe_buffer_size() -> usize {
+    DEFAULT_PIPE_BUF_SIZE
+}
+
+impl Default for BufferSizeArgs {
+    fn default() -> Self {
+        Self {
+            forward: default_forward_buffer_size(),
+            pipe: default_pipe_buffer_size(),
+        }
+    }
+}
+
+impl BufferSizeArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.forward == 0 || self.pipe == 0 {
+            anyhow::bail!("The buffer sizes in `buffer_size` must be greater than 0");
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use std::net::Ipv4Addr;
@@ -82,8 +238,8 @@ pub mod tests {
     use crate::config::{
         egress::EgressMappingArgs,
         ra::{
-            AttesterArgs, CocoAttesterArgs, CocoConverterArgs, CocoVerifierArgs, ConverterArgs,
-            VerifierArgs,
+            AttesterArgs, CertMode, CocoAttesterArgs, CocoConverterArgs, CocoVerifierArgs,
+            ConverterArgs, VerifierArgs,
         },
     };
 
@@ -92,10 +248,18 @@ pub mod tests {
     #[test]
     fn test_serialize_deserialize() -> Result<()> {
         let config = TngConfig {
+            default_attest: None,
+            default_verify: None,
+            ra_profiles: Default::default(),
             admin_bind: None,
             control_interface: None,
             metric: None,
             trace: None,
+            log: None,
+            access_log: None,
+            audit_log: None,
+            shutdown_drain_timeout_secs: None,
+            runtime: None,
             add_ingress: vec![AddIngressArgs {
                 ingress_mode: IngressMode::Mapping(ingress::IngressMappingArgs {
                     rules: vec![MappingRule {
@@ -127,9 +291,18 @@ pub mod tests {
                         }),
                         tls: None,
                         tls_ca_certs: vec![],
+                        padding: None,
                     }),
                     rats_tls: None,
                     quic: None,
+                    restart: None,
+                    lazy_attest: None,
+                    circuit_breaker: None,
+                    buffer_size: None,
+                    overload: None,
+                    allowed_sources: vec![],
+                    tunnel_dns: None,
+                    resolver: None,
                     ra_args: RaArgsUnchecked {
                         no_ra: false,
                         attest: None,
@@ -138,6 +311,7 @@ pub mod tests {
                                 as_addr: "http://127.0.0.1:8080/".to_owned(),
                                 policy_ids: vec!["default".to_owned()],
                                 as_headers: Default::default(),
+                                as_request: None,
                             }),
                             verifier: VerifierArgs::Coco(CocoVerifierArgs::Restful {
                                 as_addr: Some("http://127.0.0.1:8080/".to_owned()),
@@ -147,7 +321,13 @@ pub mod tests {
                                 verify_signer_transparency: false,
                                 skip_as_token_cert_verify: false,
                             }),
-                        })
+                            required_claims: vec![],
+                            max_evidence_age: None,
+                            redact_claims: vec![],
+                            as_unreachable: Default::default(),
+                        }),
+                        attest_profile: None,
+                        verify_profile: None,
                     },
                 }
             }],
@@ -178,9 +358,21 @@ pub mod tests {
                                 "x-custom-header".to_owned()
                             ]),
                         }),
+                        keys_endpoint: None,
+                        attestation_endpoint: None,
+                        padding: None,
+                        non_tng_response: None,
                     }),
                     rats_tls: None,
                     quic: None,
+                    restart: None,
+                    lazy_attest: None,
+                    circuit_breaker: None,
+                    buffer_size: None,
+                    io_uring: false,
+                    listener: None,
+                    connect_retry: None,
+                    resolver: None,
                     ra_args: RaArgsUnchecked {
                         no_ra: false,
                         attest: Some(AttestArgs::BackgroundCheck {
@@ -188,8 +380,11 @@ pub mod tests {
                                 aa_addr: "unix:///run/confidential-containers/attestation-agent/attestation-agent.sock".to_owned(),
                             }),
                             refresh_interval: None,
+                            cert_mode: CertMode::Shared,
                         }),
                         verify: None,
+                        attest_profile: None,
+                        verify_profile: None,
                     },
                 }
             }],
@@ -215,10 +410,18 @@ pub mod tests {
 
         // Ingress config with header_passthrough
         let ingress_config = TngConfig {
+            default_attest: None,
+            default_verify: None,
+            ra_profiles: Default::default(),
             admin_bind: None,
             control_interface: None,
             metric: None,
             trace: None,
+            log: None,
+            access_log: None,
+            audit_log: None,
+            shutdown_drain_timeout_secs: None,
+            runtime: None,
             add_ingress: vec![AddIngressArgs {
                 ingress_mode: ingress::IngressMode::Mapping(ingress::IngressMappingArgs {
                     rules: vec![MappingRule {
@@ -248,13 +451,24 @@ pub mod tests {
                         }),
                         tls: None,
                         tls_ca_certs: vec![],
+                        padding: None,
                     }),
                     rats_tls: None,
                     quic: None,
+                    restart: None,
+                    lazy_attest: None,
+                    circuit_breaker: None,
+                    buffer_size: None,
+                    overload: None,
+                    allowed_sources: vec![],
+                    tunnel_dns: None,
+                    resolver: None,
                     ra_args: RaArgsUnchecked {
                         no_ra: false,
                         attest: None,
                         verify: None,
+                        attest_profile: None,
+                        verify_profile: None,
                     },
                 },
             }],
@@ -277,10 +491,18 @@ pub mod tests {
 
         // Egress config with header_passthrough (using netfilter mode)
         let egress_config = TngConfig {
+            default_attest: None,
+            default_verify: None,
+            ra_profiles: Default::default(),
             admin_bind: None,
             control_interface: None,
             metric: None,
             trace: None,
+            log: None,
+            access_log: None,
+            audit_log: None,
+            shutdown_drain_timeout_secs: None,
+            runtime: None,
             add_ingress: vec![],
             add_egress: vec![AddEgressArgs {
                 egress_mode: egress::EgressMode::Netfilter(egress::EgressNetfilterArgs {
@@ -288,6 +510,7 @@ pub mod tests {
                     capture_local_traffic: false,
                     capture_cgroup: vec![],
                     nocapture_cgroup: vec![],
+                    capture_exclude: vec![],
                     listen_port: None,
                     so_mark: None,
                 }),
@@ -303,13 +526,27 @@ pub mod tests {
                                 "x-custom".to_owned()
                             ]),
                         }),
+                        keys_endpoint: None,
+                        attestation_endpoint: None,
+                        padding: None,
+                        non_tng_response: None,
                     }),
                     rats_tls: None,
                     quic: None,
+                    restart: None,
+                    lazy_attest: None,
+                    circuit_breaker: None,
+                    buffer_size: None,
+                    io_uring: false,
+                    listener: None,
+                    connect_retry: None,
+                    resolver: None,
                     ra_args: RaArgsUnchecked {
                         no_ra: false,
                         attest: None,
                         verify: None,
+                        attest_profile: None,
+                        verify_profile: None,
                     },
                 },
             }],
@@ -331,10 +568,18 @@ pub mod tests {
 
         // Empty header_passthrough
         let empty_config = TngConfig {
+            default_attest: None,
+            default_verify: None,
+            ra_profiles: Default::default(),
             admin_bind: None,
             control_interface: None,
             metric: None,
             trace: None,
+            log: None,
+            access_log: None,
+            audit_log: None,
+            shutdown_drain_timeout_secs: None,
+            runtime: None,
             add_ingress: vec![],
             add_egress: vec![AddEgressArgs {
                 egress_mode: egress::EgressMode::Netfilter(egress::EgressNetfilterArgs {
@@ -342,6 +587,7 @@ pub mod tests {
                     capture_local_traffic: false,
                     capture_cgroup: vec![],
                     nocapture_cgroup: vec![],
+                    capture_exclude: vec![],
                     listen_port: None,
                     so_mark: None,
                 }),
@@ -355,13 +601,27 @@ pub mod tests {
                             request_headers: HeaderPassthroughSpec::default(),
                             response_headers: HeaderPassthroughSpec::default(),
                         }),
+                        keys_endpoint: None,
+                        attestation_endpoint: None,
+                        padding: None,
+                        non_tng_response: None,
                     }),
                     rats_tls: None,
                     quic: None,
+                    restart: None,
+                    lazy_attest: None,
+                    circuit_breaker: None,
+                    buffer_size: None,
+                    io_uring: false,
+                    listener: None,
+                    connect_retry: None,
+                    resolver: None,
                     ra_args: RaArgsUnchecked {
                         no_ra: false,
                         attest: None,
                         verify: None,
+                        attest_profile: None,
+                        verify_profile: None,
                     },
                 },
             }],
@@ -389,10 +649,18 @@ pub mod tests {
         use ingress::{CommonArgs, IngressMappingUdpArgs, IngressMode as IngressModeEnum};
 
         let config = TngConfig {
+            default_attest: None,
+            default_verify: None,
+            ra_profiles: Default::default(),
             admin_bind: None,
             control_interface: None,
             metric: None,
             trace: None,
+            log: None,
+            access_log: None,
+            audit_log: None,
+            shutdown_drain_timeout_secs: None,
+            runtime: None,
             add_ingress: vec![AddIngressArgs {
                 ingress_mode: IngressModeEnum::MappingUdp(IngressMappingUdpArgs {
                     r#in: Endpoint {
@@ -412,10 +680,20 @@ pub mod tests {
                     quic: Some(UdpQuicArgs {
                         max_datagram_size: Some(1200),
                     }),
+                    restart: None,
+                    lazy_attest: None,
+                    circuit_breaker: None,
+                    buffer_size: None,
+                    overload: None,
+                    allowed_sources: vec![],
+                    tunnel_dns: None,
+                    resolver: None,
                     ra_args: RaArgsUnchecked {
                         no_ra: true,
                         attest: None,
                         verify: None,
+                        attest_profile: None,
+                        verify_profile: None,
                     },
                 },
             }],
@@ -438,10 +716,20 @@ pub mod tests {
                     quic: Some(UdpQuicArgs {
                         max_datagram_size: Some(1200),
                     }),
+                    restart: None,
+                    lazy_attest: None,
+                    circuit_breaker: None,
+                    buffer_size: None,
+                    io_uring: false,
+                    listener: None,
+                    connect_retry: None,
+                    resolver: None,
                     ra_args: RaArgsUnchecked {
                         no_ra: true,
                         attest: None,
                         verify: None,
+                        attest_profile: None,
+                        verify_profile: None,
                     },
                 },
             }],
@@ -678,4 +966,164 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_json_schema() -> Result<()> {
+        let schema = serde_json::to_value(TngConfig::json_schema())?;
+
+        let properties = schema["properties"]
+            .as_object()
+            .ok_or_else(|| anyhow::anyhow!("schema has no properties"))?;
+        for field in [
+            "control_interface",
+            "metric",
+            "trace",
+            "add_ingress",
+            "add_egress",
+        ] {
+            assert!(properties.contains_key(field), "missing {field}");
+        }
+        assert_eq!(schema["additionalProperties"], serde_json::json!(false));
+
+        // Tags with injected defaults (e.g. `model`) must stay optional in the schema
+        let schema = schema.to_string();
+        assert!(!schema.contains(r#""required":["model"]"#));
+        assert!(schema.contains("AttestArgs"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_ra_defaults() -> Result<()> {
+        let mut config: TngConfig = serde_json::from_value(serde_json::json!({
+            "default_verify": {
+                "as_addr": "http://127.0.0.1:8080/",
+                "policy_ids": ["default"]
+            },
+            "add_ingress": [
+                {
+                    "mapping": {
+                        "in": {"port": 10001},
+                        "out": {"host": "127.0.0.1", "port": 20001}
+                    }
+                },
+                {
+                    "mapping": {
+                        "in": {"port": 10002},
+                        "out": {"host": "127.0.0.1", "port": 20002}
+                    },
+                    "verify": {
+                        "as_addr": "http://10.0.0.1:8080/",
+                        "policy_ids": ["prod"]
+                    }
+                },
+                {
+                    "mapping": {
+                        "in": {"port": 10003},
+                        "out": {"host": "127.0.0.1", "port": 20003}
+                    },
+                    "no_ra": true
+                }
+            ]
+        }))?;
+        config.resolve_ra_args()?;
+
+        let verify = |i: usize| -> Result<serde_json::Value> {
+            Ok(serde_json::to_value(
+                &config.add_ingress[i].common.ra_args.verify,
+            )?)
+        };
+        assert_eq!(verify(0)?["as_addr"], "http://127.0.0.1:8080/");
+        assert_eq!(verify(0)?["model"], "background_check");
+        assert_eq!(verify(1)?["as_addr"], "http://10.0.0.1:8080/");
+        assert!(config.add_ingress[2].common.ra_args.verify.is_none());
+        assert!(config.add_ingress[0].common.ra_args.attest.is_none());
+
+        config.add_ingress[0]
+            .common
+            .ra_args
+            .clone()
+            .into_checked()?;
+        config.add_ingress[2]
+            .common
+            .ra_args
+            .clone()
+            .into_checked()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_ra_profiles() -> Result<()> {
+        let config = serde_json::json!({
+            "ra_profiles": {
+                "prod-tdx": {
+                    "verify": {
+                        "as_addr": "http://127.0.0.1:8080/",
+                        "policy_ids": ["prod"]
+                    }
+                }
+            },
+            "add_egress": [
+                {
+                    "mapping": {
+                        "in": {"port": 20001},
+                        "out": {"host": "127.0.0.1", "port": 30001}
+                    },
+                    "verify": {"profile": "prod-tdx"}
+                }
+            ]
+        });
+
+        let mut resolved: TngConfig = serde_json::from_value(config.clone())?;
+        assert_eq!(
+            resolved.add_egress[0]
+                .common
+                .ra_args
+                .verify_profile
+                .as_deref(),
+            Some("prod-tdx")
+        );
+        resolved.resolve_ra_args()?;
+        let verify = serde_json::to_value(&resolved.add_egress[0].common.ra_args.verify)?;
+        assert_eq!(verify["policy_ids"], serde_json::json!(["prod"]));
+        assert!(resolved.add_egress[0]
+            .common
+            .ra_args
+            .verify_profile
+            .is_none());
+
+        // Referring to an undefined profile, or to a profile without the parameters, is an error
+        let mut config = config;
+        config["add_egress"][0]["attest"] = serde_json::json!({"profile": "prod-tdx"});
+        let mut invalid: TngConfig = serde_json::from_value(config.clone())?;
+        assert!(invalid.resolve_ra_args().is_err());
+        config["add_egress"][0]["verify"] = serde_json::json!({"profile": "dev"});
+        let mut invalid: TngConfig = serde_json::from_value(config.clone())?;
+        assert!(invalid.resolve_ra_args().is_err());
+
+        // A reference can not be mixed with inline parameters
+        config["add_egress"][0]["verify"] =
+            serde_json::json!({"profile": "prod-tdx", "policy_ids": ["default"]});
+        assert!(serde_json::from_value::<TngConfig>(config).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_buffer_size() -> Result<()> {
+        let buffer_size: BufferSizeArgs = serde_json::from_value(serde_json::json!({}))?;
+        assert_eq!(buffer_size, BufferSizeArgs::default());
+        assert_eq!(buffer_size.forward, DEFAULT_FORWARD_BUF_SIZE);
+        assert_eq!(buffer_size.pipe, DEFAULT_PIPE_BUF_SIZE);
+        buffer_size.validate()?;
+
+        let buffer_size: BufferSizeArgs =
+            serde_json::from_value(serde_json::json!({"pipe": 65536}))?;
+        assert_eq!(buffer_size.pipe, 65536);
+        assert_eq!(buffer_size.forward, DEFAULT_FORWARD_BUF_SIZE);
+
+        let buffer_size: BufferSizeArgs =
+            serde_json::from_value(serde_json::json!({"forward": 0}))?;
+        assert!(buffer_size.validate().is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,86 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::syslog::SyslogArgs;
+
+/// A dedicated sink of the access logs, which receives every access log regardless of the log
+/// level filters. At least one of `path` and `syslog` must be set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AccessLogArgs {
+    /// Path of the file the access logs are appended to.
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// The file is rotated once it would grow beyond this size, in MiB.
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u64,
+
+    /// The number of rotated files to keep, named `{path}.1` (the newest) to `{path}.{max_files}`.
+    /// The file is truncated instead of rotated if it is `0`.
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+
+    /// The format of the access log lines.
+    #[serde(default)]
+    pub format: AccessLogFormat,
+
+    /// Send the access logs to syslog as well.
+    #[serde(default)]
+    pub syslog: Option<SyslogArgs>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLogFormat {
+    /// The same text as the access logs printed to stdout.
+    #[default]
+    Text,
+    /// One JSON object per line, with stable field names.
+    Json,
+}
+
+fn default_max_size_mb() -> u64 {
+    100
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_access_log_config() -> Result<()> {
+        let args: AccessLogArgs =
+            serde_json::from_value(json!({"path": "/var/log/tng/access.log"}))?;
+        assert_eq!(
+            args,
+            AccessLogArgs {
+                path: Some("/var/log/tng/access.log".to_owned()),
+                max_size_mb: 100,
+                max_files: 5,
+                format: AccessLogFormat::Text,
+                syslog: None,
+            }
+        );
+        let args: AccessLogArgs =
+            serde_json::from_value(json!({"path": "/var/log/tng/access.log", "format": "json"}))?;
+        assert_eq!(args.format, AccessLogFormat::Json);
+        let args: AccessLogArgs = serde_json::from_value(
+            json!({"syslog": {"transport": "udp", "address": "127.0.0.1:514"}}),
+        )?;
+        assert_eq!(args.path, None);
+        assert!(args.syslog.is_some());
+        assert!(
+            serde_json::from_value::<AccessLogArgs>(json!({"path": "a.log", "max_file": 1}))
+                .is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A dedicated, append-only log of the attestation events, kept apart from the operational logs
+/// as compliance evidence. It receives every attestation event regardless of the log level
+/// filters.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AuditLogArgs {
+    /// Path of the file the attestation events are appended to. It is never rotated nor
+    /// truncated by TNG.
+    pub path: String,
+
+    /// Path of a file holding the secret key which the entries are chained with, by HMAC-SHA256.
+    /// The entries are not chained if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hmac_key_path: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_audit_log_config() -> Result<()> {
+        let args: AuditLogArgs = serde_json::from_value(json!({
+            "path": "/var/log/tng/audit.log",
+            "hmac_key_path": "/etc/tng/audit.key"
+        }))?;
+        assert_eq!(
+            args,
+            AuditLogArgs {
+                path: "/var/log/tng/audit.log".to_owned(),
+                hmac_key_path: Some("/etc/tng/audit.key".to_owned()),
+            }
+        );
+        assert!(serde_json::from_value::<AuditLogArgs>(json!({})).is_err());
+        assert!(
+            serde_json::from_value::<AuditLogArgs>(json!({"path": "a.log", "hmac_key": "x"}))
+                .is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::OltpCommonExporterConfig;
+
+/// Exporting of the logs to destinations other than stdout (or the file given by `--log-file`),
+/// e.g. for centralizing the logs of the gateway without scraping the container stdout.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LogArgs {
+    #[serde(default)]
+    pub exporters: Vec<LogExporterType>,
+
+    /// The most verbose level of the exported logs, one of `error`, `warn`, `info`, `debug` and
+    /// `trace`.
+    #[serde(default = "default_log_level")]
+    pub level: String,
+}
+
+fn default_log_level() -> String {
+    "info".to_owned()
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, JsonSchema)]
+#[serde(tag = "type")]
+#[serde(deny_unknown_fields)]
+pub enum LogExporterType {
+    /// Exporting in the OpenTelemetry Protocol (OTLP) format
+    #[serde(rename = "oltp", alias = "otlp")]
+    Oltp(OltpLogExporterConfig),
+
+    /// Appending the logs to a file as JSON lines
+    #[serde(rename = "file")]
+    File(FileLogExporterConfig),
+}
+
+pub type OltpLogExporterConfig = OltpCommonExporterConfig;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileLogExporterConfig {
+    /// Path of the file. It is created if missing, and appended to otherwise.
+    pub path: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use crate::config::observability::OltpExporterProtocol;
+
+    use super::*;
+
+    #[test]
+    fn test_log_config() -> Result<()> {
+        let args: LogArgs = serde_json::from_value(json!({
+            "exporters": [
+                {"type": "otlp", "protocol": "grpc", "endpoint": "http://127.0.0.1:4317"},
+                {"type": "file", "path": "/var/log/tng/tng.json"}
+            ]
+        }))?;
+        assert_eq!(
+            args,
+            LogArgs {
+                exporters: vec![
+                    LogExporterType::Oltp(OltpLogExporterConfig {
+                        protocol: OltpExporterProtocol::Grpc,
+                        endpoint: "http://127.0.0.1:4317".to_owned(),
+                        headers: None,
+                    }),
+                    LogExporterType::File(FileLogExporterConfig {
+                        path: "/var/log/tng/tng.json".to_owned(),
+                    }),
+                ],
+                level: "info".to_owned(),
+            }
+        );
+
+        let args: LogArgs = serde_json::from_value(json!({"level": "verbose"}))?;
+        assert!(args.max_level().is_err());
+        Ok(())
+    }
+}
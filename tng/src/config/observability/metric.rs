@@ -1,17 +1,36 @@
 use derivative::Derivative;
 use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::OltpCommonExporterConfig;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct MetricArgs {
     #[serde(default)]
     pub exporters: Vec<MetricExporterType>,
+
+    /// Label the connection and bytes metrics with the destination endpoint. Disabled if not
+    /// specified.
+    #[serde(default)]
+    pub destination_labels: Option<DestinationLabelsArgs>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DestinationLabelsArgs {
+    /// The maximum number of distinct destinations labeled per ingress or egress. Connections to
+    /// further destinations are counted with the `dst` label set to `other`.
+    #[serde(default = "destination_labels_default_max_destinations")]
+    pub max_destinations: usize,
+}
+
+fn destination_labels_default_max_destinations() -> usize {
+    100
 }
 
-#[derive(Clone, Serialize, Deserialize, Derivative)]
+#[derive(Clone, Serialize, Deserialize, Derivative, JsonSchema)]
 #[derivative(Debug, PartialEq)]
 #[serde(tag = "type")]
 pub enum MetricExporterType {
@@ -19,6 +38,15 @@ pub enum MetricExporterType {
     Stdout {
         #[serde(default = "stdout_config_default_step")]
         step: u64,
+
+        /// The format of the metrics.
+        #[serde(default)]
+        format: StdoutMetricFormat,
+
+        /// Path of the file the metrics are appended to instead of stdout, e.g. `/dev/fd/3`. Not
+        /// supported with the `log` format.
+        #[serde(default)]
+        path: Option<String>,
     },
 
     #[serde(rename = "falcon")]
@@ -28,38 +56,64 @@ pub enum MetricExporterType {
     #[serde(rename = "oltp")]
     Oltp(OltpMetricExporterConfig),
 
-    #[cfg(all(test, feature = "metric"))]
+    /// An exporter implemented by the application embedding TNG as a library, which is pushed
+    /// the metrics every `step` seconds. It can only be added with the Rust API, not in the JSON
+    /// configuration.
+    #[cfg(all(feature = "metric", not(wasm)))]
     #[serde(skip)]
-    #[serde(rename = "mock")]
-    Mock {
+    Custom {
         step: u64,
 
         #[derivative(Debug = "ignore")]
         #[derivative(PartialEq = "ignore")]
-        exporter: std::sync::Arc<
-            dyn crate::observability::metric::simple_exporter::SimpleMetricExporter
-                + Send
-                + Sync
-                + 'static,
-        >,
+        exporter: std::sync::Arc<dyn crate::SimpleMetricExporter + Send + Sync + 'static>,
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StdoutMetricFormat {
+    /// Printed with the logs, for debugging.
+    #[default]
+    Log,
+    /// One compact JSON object per metric per line.
+    Json,
+    /// One pretty-printed JSON object per metric.
+    Pretty,
+    /// One line of `key=value` pairs per metric.
+    KeyValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct FalconConfig {
     pub server_url: String,
-    pub endpoint: String,
+
+    /// The endpoint the metrics are reported for. Defaults to the hostname.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+
+    /// Extra tags attached to every metric.
     #[serde(default)]
     pub tags: IndexMap<String, String>,
+
+    /// Attributes of the metrics which are not converted into tags, e.g. `ingress_in`.
+    #[serde(default)]
+    pub exclude_attributes: Vec<String>,
+
     #[serde(default = "falcon_config_default_step")]
     pub step: u64,
+
+    /// The step of some metrics, by metric name, which must be a multiple of `step`. These metrics
+    /// are pushed only once every their own step.
+    #[serde(default)]
+    pub metric_steps: IndexMap<String, u64>,
 }
 
 fn falcon_config_default_step() -> u64 {
     60
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, JsonSchema)]
 pub struct OltpMetricExporterConfig {
     #[serde(flatten)]
     pub common: OltpCommonExporterConfig,
@@ -116,13 +170,37 @@ mod tests {
 
         let expected = MetricExporterType::Falcon(FalconConfig {
             server_url: "http://127.0.0.1:1988".to_owned(),
-            endpoint: "master-node".to_owned(),
+            endpoint: Some("master-node".to_owned()),
             tags: [
                 ("namespace".to_owned(), "ns1".to_owned()),
                 ("app".to_owned(), "tng".to_owned()),
             ]
             .into(),
+            exclude_attributes: vec![],
             step: 60,
+            metric_steps: Default::default(),
+        });
+
+        test_config_common(json_value, expected)?;
+
+        let json_value = json!(
+            {
+                "type": "falcon",
+                "server_url": "http://127.0.0.1:1988",
+                "exclude_attributes": ["ingress_in", "ingress_out"],
+                "metric_steps": {
+                    "cx_duration": 300
+                }
+            }
+        );
+
+        let expected = MetricExporterType::Falcon(FalconConfig {
+            server_url: "http://127.0.0.1:1988".to_owned(),
+            endpoint: None,
+            tags: Default::default(),
+            exclude_attributes: vec!["ingress_in".to_owned(), "ingress_out".to_owned()],
+            step: 60,
+            metric_steps: [("cx_duration".to_owned(), 300)].into(),
         });
 
         test_config_common(json_value, expected)?;
@@ -139,10 +217,40 @@ mod tests {
             }
         );
 
-        let expected = MetricExporterType::Stdout { step: 1 };
+        let expected = MetricExporterType::Stdout {
+            step: 1,
+            format: StdoutMetricFormat::Log,
+            path: None,
+        };
 
         test_config_common(json_value, expected)?;
 
+        let json_value = json!(
+            {
+                "type": "stdout",
+                "format": "key_value",
+                "path": "/dev/null"
+            }
+        );
+
+        let expected = MetricExporterType::Stdout {
+            step: 60,
+            format: StdoutMetricFormat::KeyValue,
+            path: Some("/dev/null".to_owned()),
+        };
+
+        test_config_common(json_value, expected)?;
+
+        // Metrics printed with the logs can not be written to a file
+        let json_value = json!(
+            {
+                "type": "stdout",
+                "path": "/dev/null"
+            }
+        );
+        let deserialized: MetricExporterType = serde_json::from_value(json_value)?;
+        assert!(deserialized.instantiate().is_err());
+
         Ok(())
     }
 
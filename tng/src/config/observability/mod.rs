@@ -1,11 +1,16 @@
 use std::collections::HashMap;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+pub mod access_log;
+pub mod audit_log;
+pub mod log;
 pub mod metric;
+pub mod syslog;
 pub mod trace;
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct OltpCommonExporterConfig {
     pub protocol: OltpExporterProtocol,
@@ -13,7 +18,7 @@ pub struct OltpCommonExporterConfig {
     pub endpoint: String,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub enum OltpExporterProtocol {
     #[serde(rename = "http/protobuf")]
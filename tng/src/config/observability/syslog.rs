@@ -0,0 +1,115 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Sending of logs to a syslog server in the RFC 5424 format, one message per datagram.
+// `deny_unknown_fields` is not supported together with `flatten`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct SyslogArgs {
+    #[serde(flatten)]
+    pub transport: SyslogTransport,
+
+    /// The facility of the messages.
+    #[serde(default)]
+    pub facility: SyslogFacility,
+
+    /// The `APP-NAME` of the messages.
+    #[serde(default = "default_app_name")]
+    pub app_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(tag = "transport", rename_all = "snake_case")]
+pub enum SyslogTransport {
+    /// Send to a syslog server over UDP.
+    Udp {
+        /// The address of the server, e.g. `127.0.0.1:514`.
+        address: String,
+    },
+    /// Send to a unix datagram socket of the local syslog daemon.
+    Unix {
+        #[serde(default = "default_unix_path")]
+        path: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogFacility {
+    User,
+    #[default]
+    Daemon,
+    Auth,
+    Authpriv,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl SyslogFacility {
+    /// The numerical code of the facility defined in RFC 5424.
+    pub fn code(&self) -> u8 {
+        match self {
+            SyslogFacility::User => 1,
+            SyslogFacility::Daemon => 3,
+            SyslogFacility::Auth => 4,
+            SyslogFacility::Authpriv => 10,
+            SyslogFacility::Local0 => 16,
+            SyslogFacility::Local1 => 17,
+            SyslogFacility::Local2 => 18,
+            SyslogFacility::Local3 => 19,
+            SyslogFacility::Local4 => 20,
+            SyslogFacility::Local5 => 21,
+            SyslogFacility::Local6 => 22,
+            SyslogFacility::Local7 => 23,
+        }
+    }
+}
+
+fn default_app_name() -> String {
+    "tng".to_owned()
+}
+
+fn default_unix_path() -> String {
+    "/dev/log".to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_syslog_config() -> Result<()> {
+        let args: SyslogArgs = serde_json::from_value(
+            json!({"transport": "udp", "address": "127.0.0.1:514", "facility": "local3"}),
+        )?;
+        assert_eq!(
+            args,
+            SyslogArgs {
+                transport: SyslogTransport::Udp {
+                    address: "127.0.0.1:514".to_owned()
+                },
+                facility: SyslogFacility::Local3,
+                app_name: "tng".to_owned(),
+            }
+        );
+
+        let args: SyslogArgs = serde_json::from_value(json!({"transport": "unix"}))?;
+        assert_eq!(
+            args.transport,
+            SyslogTransport::Unix {
+                path: "/dev/log".to_owned()
+            }
+        );
+        assert_eq!(args.facility.code(), 3);
+        assert!(serde_json::from_value::<SyslogArgs>(json!({"transport": "tcp"})).is_err());
+        Ok(())
+    }
+}
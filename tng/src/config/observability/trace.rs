@@ -1,20 +1,27 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::OltpCommonExporterConfig;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct TraceArgs {
     #[serde(default)]
     pub exporters: Vec<TraceExporterType>,
+
+    /// Ratio of the traces to be sampled, from `0.0` to `1.0`. A span whose parent is sampled,
+    /// e.g. in a context propagated from the client, is always sampled. All traces are sampled if
+    /// not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling_ratio: Option<f64>,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, JsonSchema)]
 #[serde(tag = "type")]
 #[serde(deny_unknown_fields)]
 pub enum TraceExporterType {
     /// Exporting in the OpenTelemetry Protocol (OTLP) format
-    #[serde(rename = "oltp")]
+    #[serde(rename = "oltp", alias = "otlp")]
     Oltp(OltpTraceExporterConfig),
 
     /// Exporting traces to stdout (for debug only)
@@ -148,6 +155,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_sampling_ratio() -> Result<()> {
+        let args: TraceArgs = serde_json::from_value(json!({
+            "exporters": [{"type": "otlp", "protocol": "grpc", "endpoint": "http://127.0.0.1:4317"}],
+            "sampling_ratio": 0.1
+        }))?;
+        assert_eq!(args.sampling_ratio, Some(0.1));
+        assert!(args.sampler().is_ok());
+
+        let args: TraceArgs = serde_json::from_value(json!({"sampling_ratio": 1.5}))?;
+        assert!(args.sampler().is_err());
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
     async fn test_multi_tng_instance() -> Result<()> {
         let mut tasks = futures::stream::FuturesUnordered::new();
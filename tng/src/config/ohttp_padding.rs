@@ -0,0 +1,27 @@
+use std::num::NonZeroUsize;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Padding appended to OHTTP-encapsulated messages before encryption, to reduce the leakage
+/// of payload sizes to anyone observing the encrypted traffic.
+///
+/// The padding consists of zero-valued bytes after the end of the Binary HTTP message, as
+/// allowed by RFC 9292, so the peer ignores it while decoding.
+///
+/// Example:
+/// ```json
+/// "padding": {
+///   "mode": "block",
+///   "block_size": 1024
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub enum OHttpPaddingPolicy {
+    /// Pad every message to a multiple of `block_size` bytes.
+    Block { block_size: NonZeroUsize },
+
+    /// Append a random number of bytes between 0 and `max_bytes` (inclusive) to every message.
+    Random { max_bytes: usize },
+}
@@ -0,0 +1,50 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Reject new connections early when an ingress is overloaded, instead of degrading all the
+/// connections it forwards. The limits which are not set are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OverloadArgs {
+    /// The maximum number of connections being established, i.e. accepted but not yet connected
+    /// to the upstream, e.g. while waiting for the handshake of the secure session.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_pending_connections: Option<usize>,
+
+    /// The maximum number of connections forwarded at once, including the ones being established.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_active_connections: Option<usize>,
+
+    /// The maximum average time, in milliseconds, to connect an accepted connection to the
+    /// upstream. New connections are rejected while it is exceeded and some connections are still
+    /// being established.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_establish_latency_ms: Option<u64>,
+
+    /// The maximum number of connections forwarded at once from the same source IP address, so
+    /// that a single misbehaving client cannot take all the capacity of the ingress.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_connections_per_source: Option<usize>,
+}
+
+impl OverloadArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.max_pending_connections == Some(0) {
+            anyhow::bail!("`overload.max_pending_connections` must be greater than 0");
+        }
+        if self.max_active_connections == Some(0) {
+            anyhow::bail!("`overload.max_active_connections` must be greater than 0");
+        }
+        if self.max_establish_latency_ms == Some(0) {
+            anyhow::bail!("`overload.max_establish_latency_ms` must be greater than 0");
+        }
+        if self.max_connections_per_source == Some(0) {
+            anyhow::bail!("`overload.max_connections_per_source` must be greater than 0");
+        }
+        Ok(())
+    }
+}
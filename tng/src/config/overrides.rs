@@ -0,0 +1,197 @@
+//! Overrides applied on top of the loaded configuration, e.g. `--set key=value` on the command
+//! line, which is handy for quick experiments and for injecting environment-specific values in
+//! entrypoint scripts.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use serde_json::Value;
+
+use super::TngConfig;
+
+/// A single override of a config field.
+///
+/// The field is addressed by a dot-separated path, in which array elements are addressed by their
+/// index, e.g. `add_ingress.0.mapping.out.port`. Missing objects on the path are created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigOverride {
+    path: Vec<String>,
+    value: Value,
+    /// Append the value to the array at the path, instead of replacing the field.
+    append: bool,
+}
+
+impl ConfigOverride {
+    /// Set the field at the path to the value.
+    pub fn set(path: &str, value: Value) -> Result<Self> {
+        Ok(Self {
+            path: parse_path(path)?,
+            value,
+            append: false,
+        })
+    }
+
+    /// Append the value to the array at the path. The array is created if missing.
+    pub fn append(path: &str, value: Value) -> Result<Self> {
+        Ok(Self {
+            path: parse_path(path)?,
+            value,
+            append: true,
+        })
+    }
+
+    fn apply(&self, config: &mut Value) -> Result<()> {
+        let path = self.path.join(".");
+        let mut current = config;
+        for segment in &self.path {
+            if current.is_null() {
+                *current = Value::Object(Default::default());
+            }
+            current = match current {
+                Value::Object(map) => map.entry(segment.as_str()).or_insert(Value::Null),
+                Value::Array(array) => {
+                    let len = array.len();
+                    segment
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| array.get_mut(index))
+                        .ok_or_else(|| {
+                            anyhow!("Failed to override `{path}`: `{segment}` is not a valid index of an array with {len} elements")
+                        })?
+                }
+                _ => bail!(
+                    "Failed to override `{path}`: `{segment}` can not be accessed on a non-object value"
+                ),
+            };
+        }
+
+        if self.append {
+            if current.is_null() {
+                *current = Value::Array(vec![]);
+            }
+            let Value::Array(array) = current else {
+                bail!("Failed to override `{path}`: the field is not an array");
+            };
+            array.push(self.value.clone());
+        } else {
+            *current = self.value.clone();
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `key=value` override. The value is parsed as JSON, or taken as a string if it is not
+/// valid JSON, so that `port=8080` sets a number while `host=127.0.0.1` sets a string.
+impl FromStr for ConfigOverride {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (path, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid override `{s}`, expected `key=value`"))?;
+        let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.into()));
+        Self::set(path, value)
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<String>> {
+    let path: Vec<String> = path.split('.').map(str::to_owned).collect();
+    if path.iter().any(String::is_empty) {
+        bail!("Invalid config path `{}`", path.join("."));
+    }
+    Ok(path)
+}
+
+/// Overrides applied in order on top of the loaded configuration.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides(Vec<ConfigOverride>);
+
+impl ConfigOverrides {
+    pub fn new(overrides: Vec<ConfigOverride>) -> Self {
+        Self(overrides)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Apply the overrides to the configuration in JSON and parse the result.
+    pub fn apply_to(&self, mut config: Value) -> Result<TngConfig> {
+        for config_override in &self.0 {
+            config_override.apply(&mut config)?;
+        }
+        serde_json::from_value(config).context("Invalid configuration")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_override() -> Result<()> {
+        assert_eq!(
+            "add_ingress.0.mapping.out.port=8080".parse::<ConfigOverride>()?,
+            ConfigOverride::set("add_ingress.0.mapping.out.port", json!(8080))?
+        );
+        assert_eq!(
+            "add_ingress.0.mapping.out.host=127.0.0.1".parse::<ConfigOverride>()?,
+            ConfigOverride::set("add_ingress.0.mapping.out.host", json!("127.0.0.1"))?
+        );
+        assert_eq!(
+            r#"metric={"exporters":[]}"#.parse::<ConfigOverride>()?,
+            ConfigOverride::set("metric", json!({"exporters": []}))?
+        );
+        assert!("no_ra".parse::<ConfigOverride>().is_err());
+        assert!("add_ingress..no_ra=true".parse::<ConfigOverride>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_overrides() -> Result<()> {
+        let config = json!({
+            "add_ingress": [
+                {
+                    "mapping": {
+                        "in": {"port": 10001},
+                        "out": {"host": "127.0.0.1", "port": 20001}
+                    },
+                    "no_ra": true
+                }
+            ]
+        });
+
+        let overrides = ConfigOverrides::new(vec![
+            "add_ingress.0.mapping.out.port=20002".parse()?,
+            ConfigOverride::append("metric.exporters", json!({"type": "stdout"}))?,
+            ConfigOverride::set("control_interface.ttrpc.path", json!("/run/tng.sock"))?,
+        ]);
+        let config = overrides.apply_to(config)?;
+        assert_eq!(
+            serde_json::to_value(&config.add_ingress[0])?["mapping"]["rules"][0]["out"]["port"],
+            json!(20002)
+        );
+        assert_eq!(
+            config
+                .metric
+                .ok_or_else(|| anyhow!("missing metric"))?
+                .exporters
+                .len(),
+            1
+        );
+        assert_eq!(
+            config
+                .control_interface
+                .and_then(|c| c.ttrpc)
+                .ok_or_else(|| anyhow!("missing ttrpc"))?
+                .path,
+            "/run/tng.sock"
+        );
+
+        let overrides = ConfigOverrides::new(vec!["add_ingress.1.no_ra=true".parse()?]);
+        assert!(overrides.apply_to(json!({"add_ingress": []})).is_err());
+        Ok(())
+    }
+}
@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 use std::path::Path;
 
-use anyhow::{anyhow, Context as _, Result};
-use serde::de::Deserializer;
+use anyhow::{anyhow, bail, Context as _, Result};
+use indexmap::IndexMap;
+use rats_cert::tee::claims::Claims;
+use schemars::JsonSchema;
+use serde::de::{DeserializeOwned, Deserializer};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use url::Url;
 
 use crate::error::TngError;
@@ -29,7 +33,7 @@ use crate::tunnel::utils::maybe_cached::RefreshStrategy;
 //    types unaware of backward-compat defaulting.
 
 /// Remote Attestation configuration parameters
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
 pub struct RaArgsUnchecked {
     /// Whether to disable Remote Attestation functionality
     #[serde(default = "bool::default")]
@@ -37,10 +41,73 @@ pub struct RaArgsUnchecked {
 
     /// Attestation parameters configuration (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<ArgsOrProfile<AttestArgs>>")]
     pub attest: Option<AttestArgs>,
 
     /// Verification parameters configuration (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<ArgsOrProfile<VerifyArgs>>")]
+    pub verify: Option<VerifyArgs>,
+
+    /// Name of the profile in `ra_profiles` which `attest` refers to, until it is resolved.
+    #[serde(skip)]
+    pub attest_profile: Option<String>,
+
+    /// Name of the profile in `ra_profiles` which `verify` refers to, until it is resolved.
+    #[serde(skip)]
+    pub verify_profile: Option<String>,
+}
+
+/// The `attest` / `verify` fields, which hold either the parameters themselves or a reference to
+/// a named profile in `ra_profiles`, e.g. `"verify": {"profile": "prod-tdx"}`.
+#[derive(JsonSchema)]
+#[serde(untagged, deny_unknown_fields)]
+enum ArgsOrProfile<T> {
+    Profile { profile: String },
+    Args(T),
+}
+
+impl<T: DeserializeOwned> ArgsOrProfile<T> {
+    fn from_value(value: serde_json::Value) -> serde_json::Result<Self> {
+        use serde::de::Error as _;
+
+        let Some(profile) = value.as_object().and_then(|obj| obj.get("profile")) else {
+            return from_value_with_tag_defaults(value).map(ArgsOrProfile::Args);
+        };
+        if value.as_object().is_some_and(|obj| obj.len() > 1) {
+            return Err(serde_json::Error::custom(
+                "`profile` can not be combined with other fields",
+            ));
+        }
+        let profile = profile
+            .as_str()
+            .ok_or_else(|| serde_json::Error::custom("`profile` must be a string"))?;
+        Ok(ArgsOrProfile::Profile {
+            profile: profile.to_owned(),
+        })
+    }
+
+    /// Split into the parameters and the name of the referenced profile.
+    fn split(this: Option<Self>) -> (Option<T>, Option<String>) {
+        match this {
+            None => (None, None),
+            Some(ArgsOrProfile::Args(args)) => (Some(args), None),
+            Some(ArgsOrProfile::Profile { profile }) => (None, Some(profile)),
+        }
+    }
+}
+
+/// A named set of RA parameters defined in the top-level `ra_profiles`, which ingresses and
+/// egresses refer to with `"attest": {"profile": "<name>"}` or `"verify": {"profile": "<name>"}`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RaProfile {
+    #[serde(default, deserialize_with = "deserialize_with_tag_defaults")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attest: Option<AttestArgs>,
+
+    #[serde(default, deserialize_with = "deserialize_with_tag_defaults")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub verify: Option<VerifyArgs>,
 }
 
@@ -59,36 +126,55 @@ impl<'de> Deserialize<'de> for RaArgsUnchecked {
 
         let raw = Raw::deserialize(deserializer)?;
 
-        let attest = raw
-            .attest
-            .map(|mut v| {
-                if let Some(obj) = v.as_object_mut() {
-                    inject_tag_defaults(obj);
-                }
-                serde_json::from_value::<AttestArgs>(v)
-            })
-            .transpose()
-            .map_err(serde::de::Error::custom)?;
+        let (attest, attest_profile) = ArgsOrProfile::split(
+            raw.attest
+                .map(ArgsOrProfile::<AttestArgs>::from_value)
+                .transpose()
+                .map_err(serde::de::Error::custom)?,
+        );
 
-        let verify = raw
-            .verify
-            .map(|mut v| {
-                if let Some(obj) = v.as_object_mut() {
-                    inject_tag_defaults(obj);
-                }
-                serde_json::from_value::<VerifyArgs>(v)
-            })
-            .transpose()
-            .map_err(serde::de::Error::custom)?;
+        let (verify, verify_profile) = ArgsOrProfile::split(
+            raw.verify
+                .map(ArgsOrProfile::<VerifyArgs>::from_value)
+                .transpose()
+                .map_err(serde::de::Error::custom)?,
+        );
 
         Ok(RaArgsUnchecked {
             no_ra: raw.no_ra,
             attest,
             verify,
+            attest_profile,
+            verify_profile,
         })
     }
 }
 
+/// Parse `AttestArgs` or `VerifyArgs` from raw JSON, with the tag defaults injected.
+fn from_value_with_tag_defaults<T: DeserializeOwned>(
+    mut value: serde_json::Value,
+) -> serde_json::Result<T> {
+    if let Some(obj) = value.as_object_mut() {
+        inject_tag_defaults(obj);
+    }
+    serde_json::from_value(value)
+}
+
+/// Deserialize an optional `AttestArgs` or `VerifyArgs` outside of `RaArgsUnchecked` (e.g. the
+/// top-level `default_attest` / `default_verify`), with the same tag defaults.
+pub(super) fn deserialize_with_tag_defaults<'de, D, T>(
+    deserializer: D,
+) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    Option::<serde_json::Value>::deserialize(deserializer)?
+        .map(from_value_with_tag_defaults)
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
 #[derive(Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
 pub enum RaArgs {
@@ -101,7 +187,62 @@ pub enum RaArgs {
 }
 
 impl RaArgsUnchecked {
+    /// Fill in the attestation and verification parameters which are not set, from the
+    /// top-level `default_attest` / `default_verify`. Nothing is inherited if `no_ra` is set.
+    pub fn inherit_defaults(
+        &mut self,
+        default_attest: Option<&AttestArgs>,
+        default_verify: Option<&VerifyArgs>,
+    ) {
+        if self.no_ra {
+            return;
+        }
+        if self.attest.is_none() {
+            self.attest = default_attest.cloned();
+        }
+        if self.verify.is_none() {
+            self.verify = default_verify.cloned();
+        }
+    }
+
+    /// Replace the references to named profiles with the parameters defined in the profiles.
+    pub fn resolve_profiles(&mut self, profiles: &IndexMap<String, RaProfile>) -> Result<()> {
+        if let Some(name) = self.attest_profile.take() {
+            let profile = profiles
+                .get(&name)
+                .with_context(|| format!("The RA profile `{name}` is not defined"))?;
+            self.attest = Some(
+                profile
+                    .attest
+                    .clone()
+                    .with_context(|| format!("The RA profile `{name}` has no `attest` field"))?,
+            );
+        }
+        if let Some(name) = self.verify_profile.take() {
+            let profile = profiles
+                .get(&name)
+                .with_context(|| format!("The RA profile `{name}` is not defined"))?;
+            self.verify = Some(
+                profile
+                    .verify
+                    .clone()
+                    .with_context(|| format!("The RA profile `{name}` has no `verify` field"))?,
+            );
+        }
+        Ok(())
+    }
+
     pub fn into_checked(self) -> Result<RaArgs, TngError> {
+        if let Some(name) = self
+            .attest_profile
+            .as_ref()
+            .or(self.verify_profile.as_ref())
+        {
+            return Err(TngError::InvalidParameter(anyhow!(
+                "The reference to RA profile `{name}` is not resolved"
+            )));
+        }
+
         let ra_args = if self.no_ra {
             // Sanity check
             if self.verify.is_some() {
@@ -145,16 +286,7 @@ impl RaArgsUnchecked {
                 | AttestArgs::BackgroundCheck { attester, .. } => match attester {
                     AttesterArgs::Coco(coco_attester) => match coco_attester {
                         CocoAttesterArgs::Uds { aa_addr } => {
-                            let aa_sock_file = aa_addr
-                                .strip_prefix("unix:///")
-                                .context("AA address must start with unix:///")
-                                .map_err(TngError::InvalidParameter)?;
-                            let aa_sock_file = Path::new("/").join(aa_sock_file);
-                            if !Path::new(&aa_sock_file).exists() {
-                                return Err(TngError::InvalidParameter(anyhow!(
-                                    "AA socket file {aa_sock_file:?} not found"
-                                )));
-                            }
+                            check_aa_addr(aa_addr).map_err(TngError::InvalidParameter)?;
                         }
                         // Builtin AA doesn't need socket file check
                         CocoAttesterArgs::Builtin => {
@@ -162,17 +294,7 @@ impl RaArgsUnchecked {
                         }
                     },
                     AttesterArgs::Ita(ita) => {
-                        let aa_sock_file = ita
-                            .aa_addr
-                            .strip_prefix("unix:///")
-                            .context("AA address must start with unix:///")
-                            .map_err(TngError::InvalidParameter)?;
-                        let aa_sock_file = Path::new("/").join(aa_sock_file);
-                        if !Path::new(&aa_sock_file).exists() {
-                            return Err(TngError::InvalidParameter(anyhow!(
-                                "AA socket file {aa_sock_file:?} not found"
-                            )));
-                        }
+                        check_aa_addr(&ita.aa_addr).map_err(TngError::InvalidParameter)?;
                     }
                     AttesterArgs::CocoAsr(args) => {
                         Url::parse(&args.asr_addr)
@@ -187,6 +309,40 @@ impl RaArgsUnchecked {
                 },
             };
 
+            if let AttestArgs::Passport {
+                refresh_interval: Some(_),
+                cert_mode: CertMode::PerSession,
+                ..
+            }
+            | AttestArgs::BackgroundCheck {
+                refresh_interval: Some(_),
+                cert_mode: CertMode::PerSession,
+                ..
+            } = attest_args
+            {
+                return Err(TngError::InvalidParameter(anyhow!(
+                    "The 'refresh_interval' should not be set with '\"cert_mode\": \"per_session\"', since the certificate is not reused"
+                )));
+            }
+
+            if let AttestArgs::Passport {
+                converter:
+                    ConverterArgs::Coco(
+                        CocoConverterArgs::Restful {
+                            as_request: Some(as_request),
+                            ..
+                        }
+                        | CocoConverterArgs::Grpc {
+                            as_request: Some(as_request),
+                            ..
+                        },
+                    ),
+                ..
+            } = attest_args
+            {
+                as_request.validate().map_err(TngError::InvalidParameter)?;
+            }
+
             if let AttestArgs::Passport {
                 converter: ConverterArgs::Ita(ita),
                 ..
@@ -213,9 +369,24 @@ impl RaArgsUnchecked {
                 _ => return Ok(ra_args),
             };
 
+            for requirement in verify_args.required_claims() {
+                requirement.validate().map_err(TngError::InvalidParameter)?;
+            }
+
+            if verify_args.max_evidence_age() == Some(0) {
+                return Err(TngError::InvalidParameter(anyhow!(
+                    "The 'max_evidence_age' must be greater than 0"
+                )));
+            }
+
+            verify_args
+                .as_unreachable()
+                .validate()
+                .map_err(TngError::InvalidParameter)?;
+
             // Check token_verify
             match verify_args {
-                VerifyArgs::Passport { verifier }
+                VerifyArgs::Passport { verifier, .. }
                 | VerifyArgs::BackgroundCheck { verifier, .. } => {
                     match verifier {
                         VerifierArgs::Coco(coco_verifier) => match coco_verifier {
@@ -289,13 +460,24 @@ impl RaArgsUnchecked {
             if let VerifyArgs::BackgroundCheck { converter, .. } = verify_args {
                 match converter {
                     ConverterArgs::Coco(coco_converter) => match coco_converter {
-                        CocoConverterArgs::Restful { as_addr, .. }
-                        | CocoConverterArgs::Grpc { as_addr, .. } => {
+                        CocoConverterArgs::Restful {
+                            as_addr,
+                            as_request,
+                            ..
+                        }
+                        | CocoConverterArgs::Grpc {
+                            as_addr,
+                            as_request,
+                            ..
+                        } => {
                             Url::parse(as_addr)
                                 .with_context(|| {
                                     format!("Invalid attestation service address: {}", as_addr)
                                 })
                                 .map_err(TngError::InvalidParameter)?;
+                            if let Some(as_request) = as_request {
+                                as_request.validate().map_err(TngError::InvalidParameter)?;
+                            }
                         }
                         // Validate builtin configuration
                         #[cfg(feature = "__builtin-as")]
@@ -359,6 +541,28 @@ impl RaArgsUnchecked {
     }
 }
 
+/// Checks the address of the AA, which is either the unix socket of the ttrpc API
+/// (`unix:///...`), or the address of the gRPC API (`http://...`).
+#[cfg(unix)]
+fn check_aa_addr(aa_addr: &str) -> Result<()> {
+    if aa_addr.starts_with("http://") {
+        let url = Url::parse(aa_addr).with_context(|| format!("Invalid AA address: {aa_addr}"))?;
+        if url.host().is_none() || url.port_or_known_default().is_none() {
+            bail!("AA address {aa_addr} must have a host and a port");
+        }
+        return Ok(());
+    }
+
+    let aa_sock_file = aa_addr
+        .strip_prefix("unix:///")
+        .context("AA address must start with unix:/// or http://")?;
+    let aa_sock_file = Path::new("/").join(aa_sock_file);
+    if !Path::new(&aa_sock_file).exists() {
+        bail!("AA socket file {aa_sock_file:?} not found");
+    }
+    Ok(())
+}
+
 // ---------------------------------------------------------------------------
 // Provider-tagged config enums (serde-derived)
 // ---------------------------------------------------------------------------
@@ -366,8 +570,9 @@ impl RaArgsUnchecked {
 /// Provider-tagged attester config. Serde reads "aa_provider" from flat JSON.
 /// Separate from as_provider because in Passport mode the attester and
 /// converter can use different providers.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "aa_provider", rename_all = "snake_case")]
+#[schemars(transform = optional_tags)]
 pub enum AttesterArgs {
     Coco(CocoAttesterArgs),
     Ita(ItaAttesterArgs),
@@ -375,9 +580,10 @@ pub enum AttesterArgs {
     ItaAsr(ItaAsrAttesterArgs),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ItaAttesterArgs {
-    /// Attestation agent address (unix socket path). ITA reuses CoCo AA via ttrpc.
+    /// Attestation agent address, either the unix socket of the ttrpc API (`unix:///...`) or the
+    /// address of the gRPC API (`http://...`). ITA reuses CoCo AA.
     pub aa_addr: String,
 }
 
@@ -388,7 +594,7 @@ impl ItaAttesterArgs {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CocoAsrAttesterArgs {
     /// API Server Rest HTTP address (e.g. `"http://127.0.0.1:8006"`)
     pub asr_addr: String,
@@ -405,7 +611,7 @@ impl CocoAsrAttesterArgs {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ItaAsrAttesterArgs {
     /// API Server Rest HTTP address (e.g. `"http://127.0.0.1:8006"`)
     pub asr_addr: String,
@@ -422,12 +628,14 @@ impl ItaAsrAttesterArgs {
 
 /// CoCo-internal attester variants. Serde reads "aa_type" from flat JSON.
 /// Default is Uds when aa_type is omitted (injected by custom Deserialize).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "aa_type", rename_all = "snake_case")]
+#[schemars(transform = optional_tags)]
 pub enum CocoAttesterArgs {
     /// Unix Domain Socket
     Uds {
-        /// Attestation agent address (unix socket path)
+        /// Attestation agent address, either the unix socket of the ttrpc API (`unix:///...`) or
+        /// the address of the gRPC API (`http://...`)
         aa_addr: String,
     },
     /// Builtin AA (embedded) - not implemented yet
@@ -447,14 +655,15 @@ fn default_ita_portal_url() -> String {
 }
 
 /// Provider-tagged converter config. Serde reads "as_provider" from flat JSON.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "as_provider", rename_all = "snake_case")]
+#[schemars(transform = optional_tags)]
 pub enum ConverterArgs {
     Coco(CocoConverterArgs),
     Ita(ItaConverterArgs),
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ItaConverterArgs {
     #[serde(default = "default_ita_api_url")]
     pub as_addr: String,
@@ -489,8 +698,9 @@ impl ItaConverterArgs {
 }
 
 /// CoCo-internal converter variants. Serde reads "as_type" from flat JSON.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "as_type", rename_all = "snake_case")]
+#[schemars(transform = optional_tags)]
 pub enum CocoConverterArgs {
     /// Restful API
     Restful {
@@ -502,6 +712,9 @@ pub enum CocoConverterArgs {
         /// Custom headers to be sent with attestation service requests
         #[serde(default)]
         as_headers: HashMap<String, String>,
+        /// Timeout and retries of the requests to the attestation service (optional)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        as_request: Option<AsRequestArgs>,
     },
     /// gRPC API
     Grpc {
@@ -513,28 +726,88 @@ pub enum CocoConverterArgs {
         /// Custom headers to be sent with attestation service requests
         #[serde(default)]
         as_headers: HashMap<String, String>,
+        /// Timeout and retries of the requests to the attestation service (optional)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        as_request: Option<AsRequestArgs>,
     },
     /// Builtin AS (embedded)
     #[cfg(feature = "__builtin-as")]
     Builtin {
         /// Attestation policy configuration for builtin AS
         #[serde(default)]
+        #[schemars(with = "serde_json::Value")]
         attestation_policy: rats_cert::cert::verify::PolicyConfig,
         /// Reference value configurations
         #[serde(default)]
+        #[schemars(with = "Vec<serde_json::Value>")]
         reference_values: Vec<rats_cert::cert::verify::ReferenceValueConfig>,
     },
 }
 
+/// Timeout and retries of the requests sent to the attestation service, so that a slow or
+/// restarting attestation service does not stall the handshakes for too long.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AsRequestArgs {
+    /// The timeout, in seconds, of each request. No timeout is applied if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+
+    /// The maximum number of retries after the first attempt, when the attestation service is
+    /// unreachable, times out, or fails with a server error.
+    #[serde(default)]
+    pub max_retries: usize,
+
+    /// The delay, in milliseconds, before the first retry. It is doubled after each retry.
+    #[serde(default = "default_as_request_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+
+    /// The maximum delay, in milliseconds, between two attempts.
+    #[serde(default = "default_as_request_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+fn default_as_request_initial_backoff_ms() -> u64 {
+    100
+}
+
+fn default_as_request_max_backoff_ms() -> u64 {
+    2000
+}
+
+impl AsRequestArgs {
+    fn validate(&self) -> Result<()> {
+        if self.timeout_secs == Some(0) {
+            bail!("`as_request.timeout_secs` must be greater than 0");
+        }
+        if self.max_backoff_ms < self.initial_backoff_ms {
+            bail!(
+                "`as_request.max_backoff_ms` must not be less than `as_request.initial_backoff_ms`"
+            );
+        }
+        Ok(())
+    }
+
+    pub fn to_config(&self) -> rats_cert::tee::coco::converter::AsRequestConfig {
+        rats_cert::tee::coco::converter::AsRequestConfig {
+            timeout: self.timeout_secs.map(std::time::Duration::from_secs),
+            max_retries: self.max_retries,
+            initial_backoff: std::time::Duration::from_millis(self.initial_backoff_ms),
+            max_backoff: std::time::Duration::from_millis(self.max_backoff_ms),
+        }
+    }
+}
+
 /// Provider-tagged verifier config. Serde reads "as_provider" from flat JSON.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "as_provider", rename_all = "snake_case")]
+#[schemars(transform = optional_tags)]
 pub enum VerifierArgs {
     Coco(CocoVerifierArgs),
     Ita(ItaVerifierArgs),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ItaVerifierArgs {
     #[serde(default = "default_ita_portal_url")]
     pub ita_jwks_addr: String,
@@ -542,6 +815,21 @@ pub struct ItaVerifierArgs {
     pub policy_ids: Vec<String>,
 }
 
+impl VerifierArgs {
+    /// The IDs of the policies which the tokens are verified against.
+    pub fn policy_ids(&self) -> &[String] {
+        match self {
+            Self::Coco(
+                CocoVerifierArgs::Restful { policy_ids, .. }
+                | CocoVerifierArgs::Grpc { policy_ids, .. },
+            ) => policy_ids,
+            #[cfg(feature = "__builtin-as")]
+            Self::Coco(CocoVerifierArgs::Builtin) => &[],
+            Self::Ita(ita) => &ita.policy_ids,
+        }
+    }
+}
+
 impl ItaVerifierArgs {
     pub fn to_verifier(&self) -> anyhow::Result<rats_cert::tee::ita::ItaVerifier> {
         rats_cert::tee::ita::ItaVerifier::new(&self.ita_jwks_addr, &self.policy_ids)
@@ -553,8 +841,9 @@ impl ItaVerifierArgs {
 /// Mirrors CocoConverterArgs structure. as_addr is Optional because verifier
 /// can work with just trusted_certs_paths (local cert trust) without AS.
 /// Invariant: if as_addr is None, as_headers must be empty (checked in into_checked).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "as_type", rename_all = "snake_case")]
+#[schemars(transform = optional_tags)]
 pub enum CocoVerifierArgs {
     /// Restful API
     Restful {
@@ -613,8 +902,9 @@ const EVIDENCE_REFRESH_INTERVAL_SECOND: u64 = 10 * 60; // 10 minutes
 /// ConverterArgs/VerifierArgs (all plain enums). If desired, it could be moved
 /// into AttesterArgs via a struct wrapper at the cost of one more level of
 /// indirection and inconsistency with the ConverterArgs/VerifierArgs enums.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "model", rename_all = "snake_case")]
+#[schemars(transform = optional_tags)]
 pub enum AttestArgs {
     /// Passport mode attestation parameters
     Passport {
@@ -624,6 +914,9 @@ pub enum AttestArgs {
         converter: ConverterArgs,
         /// Evidence refresh interval (seconds), optional
         refresh_interval: Option<u64>,
+        /// Whether the certificate of the rats-tls sessions is shared or generated per session
+        #[serde(default, skip_serializing_if = "CertMode::is_shared")]
+        cert_mode: CertMode,
     },
     /// Background check mode attestation parameters
     BackgroundCheck {
@@ -631,9 +924,32 @@ pub enum AttestArgs {
         attester: AttesterArgs,
         /// Evidence refresh interval (seconds), optional
         refresh_interval: Option<u64>,
+        /// Whether the certificate of the rats-tls sessions is shared or generated per session
+        #[serde(default, skip_serializing_if = "CertMode::is_shared")]
+        cert_mode: CertMode,
     },
 }
 
+/// How the attested certificate presented in the rats-tls handshakes is generated.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CertMode {
+    /// One certificate is presented in all the sessions, and replaced with a new one every
+    /// `refresh_interval`. The evidence is not fetched during the handshakes.
+    #[default]
+    Shared,
+    /// A new key pair and certificate, with fresh evidence, are generated for each session, so
+    /// that no two sessions present the same certificate. The evidence is fetched during each
+    /// handshake, which makes it slower.
+    PerSession,
+}
+
+impl CertMode {
+    fn is_shared(&self) -> bool {
+        *self == Self::Shared
+    }
+}
+
 #[cfg(unix)]
 impl AttestArgs {
     pub fn refresh_strategy(&self) -> RefreshStrategy {
@@ -654,16 +970,42 @@ impl AttestArgs {
             }
         }
     }
+
+    pub fn cert_mode(&self) -> CertMode {
+        match self {
+            Self::Passport { cert_mode, .. } | Self::BackgroundCheck { cert_mode, .. } => {
+                *cert_mode
+            }
+        }
+    }
+
+    /// The refresh strategy of the certificate of the rats-tls sessions.
+    pub fn cert_refresh_strategy(&self) -> RefreshStrategy {
+        match self.cert_mode() {
+            CertMode::Shared => self.refresh_strategy(),
+            CertMode::PerSession => RefreshStrategy::Always,
+        }
+    }
 }
 
 /// Verification parameters configuration enum.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "model", rename_all = "snake_case")]
+#[schemars(transform = optional_tags)]
 pub enum VerifyArgs {
     /// Passport mode verification parameters
     Passport {
         #[serde(flatten)]
         verifier: VerifierArgs,
+        /// Checks on the claims of the token, enforced locally once it is verified
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        required_claims: Vec<ClaimRequirement>,
+        /// The maximum age in seconds of the token, counted from its issuance
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_evidence_age: Option<u64>,
+        /// The claims of the token whose values are hidden wherever they are reported
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        redact_claims: Vec<ClaimRedaction>,
     },
     /// Background check mode verification parameters
     BackgroundCheck {
@@ -671,9 +1013,320 @@ pub enum VerifyArgs {
         converter: ConverterArgs,
         #[serde(flatten)]
         verifier: VerifierArgs,
+        /// Checks on the claims of the token, enforced locally once it is verified
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        required_claims: Vec<ClaimRequirement>,
+        /// The maximum age in seconds of the token, counted from its issuance
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_evidence_age: Option<u64>,
+        /// The claims of the token whose values are hidden wherever they are reported
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        redact_claims: Vec<ClaimRedaction>,
+        /// What to do with the peers when the attestation service can not be reached
+        #[serde(default, skip_serializing_if = "AsUnreachableArgs::is_fail_closed")]
+        as_unreachable: AsUnreachableArgs,
     },
 }
 
+impl VerifyArgs {
+    pub fn required_claims(&self) -> &[ClaimRequirement] {
+        match self {
+            Self::Passport {
+                required_claims, ..
+            }
+            | Self::BackgroundCheck {
+                required_claims, ..
+            } => required_claims,
+        }
+    }
+
+    pub fn max_evidence_age(&self) -> Option<u64> {
+        match self {
+            Self::Passport {
+                max_evidence_age, ..
+            }
+            | Self::BackgroundCheck {
+                max_evidence_age, ..
+            } => *max_evidence_age,
+        }
+    }
+
+    pub fn redact_claims(&self) -> &[ClaimRedaction] {
+        match self {
+            Self::Passport { redact_claims, .. } | Self::BackgroundCheck { redact_claims, .. } => {
+                redact_claims
+            }
+        }
+    }
+
+    /// The attestation service is only contacted to verify the peers in the background check
+    /// model, so the peers are always rejected when it is unreachable in the passport model.
+    pub fn as_unreachable(&self) -> &AsUnreachableArgs {
+        match self {
+            Self::Passport { .. } => &AsUnreachableArgs::FailClosed,
+            Self::BackgroundCheck { as_unreachable, .. } => as_unreachable,
+        }
+    }
+}
+
+/// What to do with a peer whose evidence can not be verified, since the attestation service can
+/// not be reached. This is a tradeoff between the availability of the tunnel and its security,
+/// which the operator must make deliberately.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "policy", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AsUnreachableArgs {
+    /// Reject the peer, as if its evidence failed the verification.
+    #[default]
+    FailClosed,
+    /// Accept the peer unverified, with the connection marked as degraded in the access logs and
+    /// in the metrics, until the attestation service has been unreachable for the grace period.
+    /// The peers are rejected after that, until the attestation service is reachable again.
+    FailOpen {
+        /// The grace period, in seconds, counted from the first peer which could not be verified.
+        #[serde(default = "default_as_unreachable_grace_period_secs")]
+        grace_period_secs: u64,
+    },
+}
+
+fn default_as_unreachable_grace_period_secs() -> u64 {
+    300
+}
+
+impl AsUnreachableArgs {
+    fn is_fail_closed(&self) -> bool {
+        *self == Self::FailClosed
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Self::FailOpen {
+            grace_period_secs: 0,
+        } = self
+        {
+            bail!("The 'as_unreachable.grace_period_secs' must be greater than 0");
+        }
+        Ok(())
+    }
+
+    /// How long the peers are accepted unverified while the attestation service is unreachable,
+    /// or `None` if they are rejected.
+    pub fn grace_period(&self) -> Option<std::time::Duration> {
+        match self {
+            Self::FailClosed => None,
+            Self::FailOpen { grace_period_secs } => {
+                Some(std::time::Duration::from_secs(*grace_period_secs))
+            }
+        }
+    }
+}
+
+/// A check on a claim of the attestation token of the peer, enforced locally once the token is
+/// verified, even if it passed the policies of the attestation service. This allows a gateway to
+/// enforce stricter floors than the attestation service, e.g. a minimum TCB version.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClaimRequirement {
+    /// The claim, flattened with `.` as separator, e.g. `submods.cpu0.ear.status`.
+    pub claim: String,
+
+    /// The lowest acceptable value of the claim, e.g. a TCB or security version number. The claim
+    /// must be an integer, or a string of a decimal or `0x`-prefixed hexadecimal integer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<u64>,
+
+    /// The acceptable values of the claim, e.g. the allowed measurements. Strings are compared
+    /// case-insensitively.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<Vec<Value>>,
+}
+
+impl ClaimRequirement {
+    fn validate(&self) -> Result<()> {
+        if self.min.is_none() && self.allowed.is_none() {
+            bail!(
+                "At least one of 'min' and 'allowed' must be set in the requirement on claim '{}'",
+                self.claim
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks the requirement against the claims of a verified token. The value of the claim is
+    /// hidden in the error as set by `redactions`.
+    pub fn check(&self, claims: &Claims, redactions: &[ClaimRedaction]) -> Result<()> {
+        let value = claims
+            .get(&self.claim)
+            .with_context(|| format!("Claim '{}' is missing", self.claim))?;
+        let shown = redact_claim(redactions, &self.claim, value);
+
+        if let Some(min) = self.min {
+            let number = match value {
+                Value::Number(number) => number.as_u64(),
+                Value::String(string) => match string
+                    .strip_prefix("0x")
+                    .or_else(|| string.strip_prefix("0X"))
+                {
+                    Some(hex) => u64::from_str_radix(hex, 16).ok(),
+                    None => string.parse().ok(),
+                },
+                _ => None,
+            }
+            .with_context(|| format!("Claim '{}' is not an integer: {shown}", self.claim))?;
+            if number < min {
+                bail!("Claim '{}' is {shown}, below the minimum {min}", self.claim);
+            }
+        }
+
+        if let Some(allowed) = &self.allowed {
+            let is_allowed = allowed.iter().any(|candidate| match (candidate, value) {
+                (Value::String(candidate), Value::String(value)) => {
+                    candidate.eq_ignore_ascii_case(value)
+                }
+                (candidate, value) => candidate == value,
+            });
+            if !is_allowed {
+                bail!(
+                    "Claim '{}' is {shown}, which is not one of the allowed values",
+                    self.claim
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A rule hiding the value of a claim of the attestation token of the peer wherever TNG reports
+/// it, e.g. in the logs and the audit log, since some claims contain identifiers which must not be
+/// persisted.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClaimRedaction {
+    /// The claim, flattened with `.` as separator. The claims nested under it are also matched,
+    /// e.g. `submods.cpu0` matches `submods.cpu0.ear.status`.
+    pub claim: String,
+
+    /// How the value is hidden.
+    #[serde(default)]
+    pub action: RedactionAction,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionAction {
+    /// The value is replaced with `[REDACTED]`.
+    #[default]
+    Redact,
+    /// The value is replaced with its SHA-256 digest, e.g. `sha256:<hex>`, so that the same values
+    /// can still be correlated.
+    Hash,
+}
+
+impl ClaimRedaction {
+    fn matches(&self, claim: &str) -> bool {
+        claim
+            .strip_prefix(self.claim.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    }
+}
+
+/// Returns the value of a claim as it can be reported, with the first of `redactions` matching
+/// the claim applied.
+pub fn redact_claim(redactions: &[ClaimRedaction], claim: &str, value: &Value) -> Value {
+    match redactions.iter().find(|redaction| redaction.matches(claim)) {
+        None => value.clone(),
+        Some(redaction) => match redaction.action {
+            RedactionAction::Redact => Value::from("[REDACTED]"),
+            RedactionAction::Hash => {
+                use sha2::{Digest as _, Sha256};
+                let digest = Sha256::digest(value.to_string());
+                Value::from(format!("sha256:{}", hex::encode(digest)))
+            }
+        },
+    }
+}
+
+/// Returns the claims as they can be reported, with `redactions` applied.
+pub fn redact_claims(redactions: &[ClaimRedaction], claims: &Claims) -> Claims {
+    claims
+        .iter()
+        .map(|(claim, value)| (claim.clone(), redact_claim(redactions, claim, value)))
+        .collect()
+}
+
+/// A change of the verification parameters of a running ingress or egress, made through the
+/// control interface. The parameters which are not set are kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyUpdate {
+    /// The IDs of the policies of the attestation service, replacing `policy_ids`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_ids: Option<Vec<String>>,
+
+    /// The checks on the claims of the token, replacing `required_claims`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_claims: Option<Vec<ClaimRequirement>>,
+}
+
+impl VerifyArgs {
+    /// Returns the verification parameters with an update applied, checking it like the
+    /// configuration.
+    pub fn with_update(&self, update: &VerifyUpdate) -> Result<Self> {
+        let mut verify_args = self.clone();
+
+        if let Some(new_policy_ids) = &update.policy_ids {
+            let verifier = match &mut verify_args {
+                VerifyArgs::Passport { verifier, .. } => verifier,
+                VerifyArgs::BackgroundCheck {
+                    converter,
+                    verifier,
+                    ..
+                } => {
+                    // The converter asks the attestation service to check the same policies
+                    match converter {
+                        ConverterArgs::Coco(
+                            CocoConverterArgs::Restful { policy_ids, .. }
+                            | CocoConverterArgs::Grpc { policy_ids, .. },
+                        ) => *policy_ids = new_policy_ids.clone(),
+                        #[cfg(feature = "__builtin-as")]
+                        ConverterArgs::Coco(CocoConverterArgs::Builtin { .. }) => {
+                            bail!("The builtin attestation service has no 'policy_ids'")
+                        }
+                        ConverterArgs::Ita(ita) => ita.policy_ids = new_policy_ids.clone(),
+                    }
+                    verifier
+                }
+            };
+            match verifier {
+                VerifierArgs::Coco(
+                    CocoVerifierArgs::Restful { policy_ids, .. }
+                    | CocoVerifierArgs::Grpc { policy_ids, .. },
+                ) => *policy_ids = new_policy_ids.clone(),
+                #[cfg(feature = "__builtin-as")]
+                VerifierArgs::Coco(CocoVerifierArgs::Builtin) => {
+                    bail!("The builtin attestation service has no 'policy_ids'")
+                }
+                VerifierArgs::Ita(ita) => ita.policy_ids = new_policy_ids.clone(),
+            }
+        }
+
+        if let Some(new_required_claims) = &update.required_claims {
+            for requirement in new_required_claims {
+                requirement.validate()?;
+            }
+            match &mut verify_args {
+                VerifyArgs::Passport {
+                    required_claims, ..
+                }
+                | VerifyArgs::BackgroundCheck {
+                    required_claims, ..
+                } => *required_claims = new_required_claims.clone(),
+            }
+        }
+
+        Ok(verify_args)
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -702,6 +1355,37 @@ fn inject_tag_defaults(obj: &mut serde_json::Map<String, serde_json::Value>) {
     }
 }
 
+/// Tags filled in by `inject_tag_defaults()`, which may be omitted from the config.
+const DEFAULTED_TAGS: &[&str] = &["model", "aa_provider", "as_provider", "aa_type", "as_type"];
+
+/// JSON Schema transform for the tagged RA enums: since the tags get defaults injected before
+/// deserialization, they must not be marked as required in the generated schema.
+fn optional_tags(schema: &mut schemars::Schema) {
+    if let Some(obj) = schema.as_object_mut() {
+        remove_required_tags(obj);
+    }
+}
+
+fn remove_required_tags(obj: &mut serde_json::Map<String, serde_json::Value>) {
+    if let Some(serde_json::Value::Array(required)) = obj.get_mut("required") {
+        required.retain(|name| {
+            !name
+                .as_str()
+                .is_some_and(|name| DEFAULTED_TAGS.contains(&name))
+        });
+    }
+    for value in obj.values_mut() {
+        match value {
+            serde_json::Value::Object(obj) => remove_required_tags(obj),
+            serde_json::Value::Array(items) => items
+                .iter_mut()
+                .filter_map(serde_json::Value::as_object_mut)
+                .for_each(remove_required_tags),
+            _ => {}
+        }
+    }
+}
+
 /// Fill `api_key` from `$ITA_API_KEY` env var if it's absent or null in the config.
 fn inject_ita_api_key_default(obj: &mut serde_json::Map<String, serde_json::Value>) {
     let has_key = obj
@@ -751,6 +1435,7 @@ mod tests {
             Some(AttestArgs::BackgroundCheck {
                 attester,
                 refresh_interval,
+                ..
             }) => {
                 match attester {
                     AttesterArgs::Coco(CocoAttesterArgs::Uds { aa_addr }) => {
@@ -791,6 +1476,7 @@ mod tests {
             Some(AttestArgs::BackgroundCheck {
                 attester,
                 refresh_interval,
+                ..
             }) => {
                 match attester {
                     AttesterArgs::Coco(CocoAttesterArgs::Uds { aa_addr }) => {
@@ -828,6 +1514,7 @@ mod tests {
                 attester,
                 converter,
                 refresh_interval,
+                ..
             }) => {
                 match attester {
                     AttesterArgs::Coco(CocoAttesterArgs::Uds { aa_addr }) => {
@@ -864,6 +1551,87 @@ mod tests {
         assert!(serialized.contains(r#""policy_ids":["policy1","policy2"]"#));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_attest_cert_mode() {
+        let json = json!(
+            {
+                "attest": {
+                    "aa_provider": "coco_asr",
+                    "asr_addr": "http://127.0.0.1:8006",
+                    "cert_mode": "per_session"
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        let attest_args = ra_args.attest.as_ref().expect("attest is set");
+        assert_eq!(attest_args.cert_mode(), CertMode::PerSession);
+        assert!(matches!(
+            attest_args.cert_refresh_strategy(),
+            RefreshStrategy::Always
+        ));
+        // The evidence of OHTTP is still cached
+        assert!(matches!(
+            attest_args.refresh_strategy(),
+            RefreshStrategy::Periodically { .. }
+        ));
+        ra_args.into_checked().expect("Failed to check");
+
+        // The certificate is shared by default
+        let json = json!(
+            {
+                "attest": {
+                    "aa_provider": "coco_asr",
+                    "asr_addr": "http://127.0.0.1:8006",
+                    "refresh_interval": 60
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        let attest_args = ra_args.attest.as_ref().expect("attest is set");
+        assert_eq!(attest_args.cert_mode(), CertMode::Shared);
+        assert!(matches!(
+            attest_args.cert_refresh_strategy(),
+            RefreshStrategy::Periodically { interval: 60, .. }
+        ));
+        let serialized = serde_json::to_string(&ra_args).expect("Failed to serialize");
+        assert!(!serialized.contains("cert_mode"));
+
+        let json = json!(
+            {
+                "attest": {
+                    "aa_provider": "coco_asr",
+                    "asr_addr": "http://127.0.0.1:8006",
+                    "refresh_interval": 60,
+                    "cert_mode": "per_session"
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        assert!(ra_args.into_checked().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_attest_aa_grpc_addr() {
+        // The gRPC API of the AA is not checked to be reachable
+        for attest in [
+            json!({"aa_addr": "http://127.0.0.1:50002"}),
+            json!({"aa_provider": "ita", "aa_addr": "http://127.0.0.1:50002"}),
+        ] {
+            let ra_args: RaArgsUnchecked =
+                serde_json::from_value(json!({ "attest": attest })).expect("Failed to deserialize");
+            ra_args.into_checked().expect("Failed to check");
+        }
+
+        for aa_addr in ["https://127.0.0.1:50002", "http://", "127.0.0.1:50002"] {
+            let ra_args: RaArgsUnchecked =
+                serde_json::from_value(json!({ "attest": { "aa_addr": aa_addr } }))
+                    .expect("Failed to deserialize");
+            assert!(ra_args.into_checked().is_err(), "{aa_addr}");
+        }
+    }
+
     #[test]
     #[should_panic]
     fn test_attest_bad_model() {
@@ -997,7 +1765,7 @@ mod tests {
         let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
 
         match &ra_args.verify {
-            Some(VerifyArgs::Passport { verifier }) => match verifier {
+            Some(VerifyArgs::Passport { verifier, .. }) => match verifier {
                 VerifierArgs::Coco(CocoVerifierArgs::Restful { policy_ids, .. }) => {
                     assert_eq!(policy_ids, &vec!["policy1", "policy2"]);
                 }
@@ -1012,6 +1780,243 @@ mod tests {
         assert!(serialized.contains(r#""policy_ids":["policy1","policy2"]"#));
     }
 
+    #[test]
+    fn test_verify_required_claims() {
+        let json = json!(
+            {
+                "verify": {
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "policy_ids": ["default"],
+                    "required_claims": [
+                        { "claim": "tcb.svn", "min": 3 },
+                        { "claim": "tcb.mr_td", "allowed": ["ABCD", "1234"] }
+                    ]
+                }
+            }
+        );
+
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        let verify_args = ra_args.verify.as_ref().expect("verify is set");
+        assert!(matches!(verify_args, VerifyArgs::BackgroundCheck { .. }));
+        let required_claims = verify_args.required_claims().to_vec();
+        assert_eq!(required_claims.len(), 2);
+        ra_args.into_checked().expect("Failed to check");
+
+        let check = |claims: serde_json::Value| {
+            let claims = claims.as_object().expect("claims are an object").clone();
+            required_claims
+                .iter()
+                .try_for_each(|requirement| requirement.check(&claims, &[]))
+        };
+        assert!(check(json!({"tcb.svn": 3, "tcb.mr_td": "abcd"})).is_ok());
+        assert!(check(json!({"tcb.svn": "0x4", "tcb.mr_td": "1234"})).is_ok());
+        assert!(check(json!({"tcb.svn": 2, "tcb.mr_td": "abcd"})).is_err());
+        assert!(check(json!({"tcb.svn": 3, "tcb.mr_td": "ffff"})).is_err());
+        assert!(check(json!({"tcb.mr_td": "abcd"})).is_err());
+        assert!(check(json!({"tcb.svn": "latest", "tcb.mr_td": "abcd"})).is_err());
+
+        // A requirement must check something
+        let json = json!(
+            {
+                "verify": {
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "policy_ids": ["default"],
+                    "required_claims": [{ "claim": "tcb.svn" }]
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        assert!(ra_args.into_checked().is_err());
+    }
+
+    #[test]
+    fn test_verify_redact_claims() {
+        let json = json!(
+            {
+                "verify": {
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "policy_ids": ["default"],
+                    "required_claims": [{ "claim": "tcb.mr_td", "allowed": ["ABCD"] }],
+                    "redact_claims": [
+                        { "claim": "tcb" },
+                        { "claim": "host.id", "action": "hash" }
+                    ]
+                }
+            }
+        );
+
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        let verify_args = ra_args.verify.as_ref().expect("verify is set");
+        let redactions = verify_args.redact_claims();
+        assert_eq!(redactions.len(), 2);
+        assert_eq!(redactions[0].action, RedactionAction::Redact);
+
+        let claims = json!({"tcb.mr_td": "ffff", "tcbx": 1, "host.id": "node-1"})
+            .as_object()
+            .expect("claims are an object")
+            .clone();
+        let redacted = redact_claims(redactions, &claims);
+        assert_eq!(redacted["tcb.mr_td"], json!("[REDACTED]"));
+        assert_eq!(redacted["tcbx"], json!(1));
+        assert!(redacted["host.id"]
+            .as_str()
+            .is_some_and(|digest| digest.starts_with("sha256:")));
+
+        let error = verify_args.required_claims()[0]
+            .check(&claims, redactions)
+            .expect_err("ffff is not allowed");
+        assert!(!error.to_string().contains("ffff"));
+    }
+
+    #[test]
+    fn test_verify_max_evidence_age() {
+        let json = json!(
+            {
+                "verify": {
+                    "model": "passport",
+                    "policy_ids": ["default"],
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "max_evidence_age": 300
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        let verify_args = ra_args.verify.as_ref().expect("verify is set");
+        assert_eq!(verify_args.max_evidence_age(), Some(300));
+        ra_args.into_checked().expect("Failed to check");
+
+        let json = json!(
+            {
+                "verify": {
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "policy_ids": ["default"],
+                    "max_evidence_age": 0
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        assert!(ra_args.into_checked().is_err());
+    }
+
+    #[test]
+    fn test_verify_as_unreachable() {
+        let json = json!(
+            {
+                "verify": {
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "policy_ids": ["default"]
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        let verify_args = ra_args.verify.as_ref().expect("verify is set");
+        assert_eq!(verify_args.as_unreachable(), &AsUnreachableArgs::FailClosed);
+        assert_eq!(verify_args.as_unreachable().grace_period(), None);
+        assert!(serde_json::to_value(verify_args)
+            .expect("Failed to serialize")
+            .get("as_unreachable")
+            .is_none());
+
+        let json = json!(
+            {
+                "verify": {
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "policy_ids": ["default"],
+                    "as_unreachable": {
+                        "policy": "fail_open"
+                    }
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        let verify_args = ra_args.verify.as_ref().expect("verify is set");
+        assert_eq!(
+            verify_args.as_unreachable().grace_period(),
+            Some(std::time::Duration::from_secs(300))
+        );
+        ra_args.into_checked().expect("Failed to check");
+
+        let json = json!(
+            {
+                "verify": {
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "policy_ids": ["default"],
+                    "as_unreachable": {
+                        "policy": "fail_open",
+                        "grace_period_secs": 0
+                    }
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        assert!(ra_args.into_checked().is_err());
+
+        let json = json!(
+            {
+                "verify": {
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "policy_ids": ["default"],
+                    "as_unreachable": {
+                        "policy": "fail_open",
+                        "grace_period": 60
+                    }
+                }
+            }
+        );
+        assert!(serde_json::from_value::<RaArgsUnchecked>(json).is_err());
+    }
+
+    #[test]
+    fn test_verify_with_update() {
+        let json = json!(
+            {
+                "verify": {
+                    "as_addr": "http://127.0.0.1:8080/",
+                    "policy_ids": ["default"],
+                    "required_claims": [{ "claim": "tcb.svn", "min": 3 }]
+                }
+            }
+        );
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        let verify_args = ra_args.verify.expect("verify is set");
+
+        let update: VerifyUpdate =
+            serde_json::from_value(json!({ "policy_ids": ["policy-v2"] })).unwrap();
+        let updated = verify_args.with_update(&update).expect("Failed to update");
+        match &updated {
+            VerifyArgs::BackgroundCheck {
+                converter:
+                    ConverterArgs::Coco(CocoConverterArgs::Restful {
+                        policy_ids: converter_policy_ids,
+                        ..
+                    }),
+                verifier,
+                ..
+            } => {
+                assert_eq!(converter_policy_ids, &vec!["policy-v2"]);
+                assert_eq!(verifier.policy_ids(), ["policy-v2"]);
+            }
+            _ => panic!("Expected a restful background check"),
+        }
+        // The claim requirements are kept, since they are not in the update
+        assert_eq!(updated.required_claims(), verify_args.required_claims());
+
+        let update: VerifyUpdate =
+            serde_json::from_value(json!({ "required_claims": [] })).unwrap();
+        let updated = verify_args.with_update(&update).expect("Failed to update");
+        assert!(updated.required_claims().is_empty());
+        assert!(matches!(
+            &updated,
+            VerifyArgs::BackgroundCheck { verifier, .. } if verifier.policy_ids() == ["default"]
+        ));
+
+        // The update is checked like the configuration
+        let update: VerifyUpdate =
+            serde_json::from_value(json!({ "required_claims": [{ "claim": "tcb.svn" }] })).unwrap();
+        assert!(verify_args.with_update(&update).is_err());
+        assert!(serde_json::from_value::<VerifyUpdate>(json!({ "max_evidence_age": 10 })).is_err());
+    }
+
     #[test]
     fn test_passport_verify_with_invalid_cert_path() {
         let json = json!(
@@ -1419,6 +2424,7 @@ mod tests {
                 attester,
                 converter,
                 refresh_interval,
+                ..
             }) => {
                 assert!(matches!(
                     attester,
@@ -1460,6 +2466,7 @@ mod tests {
             Some(AttestArgs::BackgroundCheck {
                 attester,
                 refresh_interval,
+                ..
             }) => {
                 assert!(matches!(
                     attester,
@@ -1495,6 +2502,7 @@ mod tests {
             Some(AttestArgs::BackgroundCheck {
                 attester,
                 refresh_interval,
+                ..
             }) => {
                 match attester {
                     AttesterArgs::Coco(CocoAttesterArgs::Uds { aa_addr }) => {
@@ -1737,6 +2745,7 @@ mod tests {
             Some(VerifyArgs::BackgroundCheck {
                 converter,
                 verifier,
+                ..
             }) => {
                 match converter {
                     ConverterArgs::Ita(ita) => {
@@ -1774,7 +2783,7 @@ mod tests {
         let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
 
         match &ra_args.verify {
-            Some(VerifyArgs::Passport { verifier }) => match verifier {
+            Some(VerifyArgs::Passport { verifier, .. }) => match verifier {
                 VerifierArgs::Ita(ita) => {
                     assert_eq!(ita.ita_jwks_addr, jwks_addr);
                     assert_eq!(ita.policy_ids, policy_ids);
@@ -1996,6 +3005,57 @@ mod tests {
         assert!(result.is_ok(), "should pass validation: {result:?}");
     }
 
+    #[test]
+    fn test_background_check_verify_as_request() {
+        let json = json!({
+            "verify": {
+                "model": "background_check",
+                "as_type": "grpc",
+                "as_addr": "http://127.0.0.1:50004/",
+                "policy_ids": ["default"],
+                "as_request": {
+                    "timeout_secs": 3,
+                    "max_retries": 2
+                }
+            }
+        });
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        match &ra_args.verify {
+            Some(VerifyArgs::BackgroundCheck {
+                converter:
+                    ConverterArgs::Coco(CocoConverterArgs::Grpc {
+                        as_request: Some(as_request),
+                        ..
+                    }),
+                ..
+            }) => {
+                let config = as_request.to_config();
+                assert_eq!(config.timeout, Some(std::time::Duration::from_secs(3)));
+                assert_eq!(config.max_retries, 2);
+                assert_eq!(
+                    config.initial_backoff,
+                    std::time::Duration::from_millis(100)
+                );
+                assert_eq!(config.max_backoff, std::time::Duration::from_millis(2000));
+            }
+            _ => panic!("Expected Coco/Grpc converter with as_request"),
+        }
+        ra_args.into_checked().expect("should pass validation");
+
+        let json = json!({
+            "verify": {
+                "model": "background_check",
+                "as_addr": "http://127.0.0.1:8080/",
+                "policy_ids": ["default"],
+                "as_request": {
+                    "timeout_secs": 0
+                }
+            }
+        });
+        let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
+        assert!(ra_args.into_checked().is_err());
+    }
+
     // Note: In background_check mode, as_addr is required for the converter
     // (CocoConverterArgs::Restful/Grpc). The skip_as_token_cert_verify flag
     // only affects the verifier's cert fetching, not the converter's AS access.
@@ -2195,7 +3255,7 @@ mod tests {
         });
         let ra_args: RaArgsUnchecked = serde_json::from_value(json).expect("Failed to deserialize");
         match &ra_args.verify {
-            Some(VerifyArgs::Passport { verifier }) => match verifier {
+            Some(VerifyArgs::Passport { verifier, .. }) => match verifier {
                 VerifierArgs::Coco(CocoVerifierArgs::Restful {
                     skip_as_token_cert_verify,
                     ..
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Restart an ingress or egress when it fails, instead of shutting down the whole instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RestartPolicyArgs {
+    /// The maximum number of consecutive restarts. The instance is shut down if the service still
+    /// fails after it. The count is reset once a restarted service is ready again.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// The delay, in seconds, before the first restart. It is doubled after each restart.
+    #[serde(default = "default_initial_backoff_secs")]
+    pub initial_backoff_secs: u64,
+
+    /// The maximum delay, in seconds, between two restarts.
+    #[serde(default = "default_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+fn default_initial_backoff_secs() -> u64 {
+    1
+}
+
+fn default_max_backoff_secs() -> u64 {
+    30
+}
+
+impl RestartPolicyArgs {
+    /// The delay before the restart following `retries` previous restarts.
+    pub fn backoff(&self, retries: u32) -> Duration {
+        let factor = 1u64.checked_shl(retries).unwrap_or(u64::MAX);
+        Duration::from_secs(
+            self.initial_backoff_secs
+                .saturating_mul(factor)
+                .min(self.max_backoff_secs),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn test_backoff() -> Result<()> {
+        let policy: RestartPolicyArgs = serde_json::from_value(json!({}))?;
+        assert_eq!(policy.max_retries, 5);
+        assert_eq!(policy.backoff(0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(8));
+        assert_eq!(policy.backoff(5), Duration::from_secs(30));
+        assert_eq!(policy.backoff(100), Duration::from_secs(30));
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The layout of the tokio runtimes which the protocol layers (rats-tls multiplexing and OHTTP)
+/// of the ingresses and egresses run on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeArgs {
+    /// The number of worker threads of each protocol runtime. Defaults to the number of CPU cores.
+    #[serde(default = "Option::default")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_worker_threads: Option<usize>,
+
+    /// When `true`, all the ingresses and egresses share one protocol runtime, instead of
+    /// creating one each.
+    #[serde(default)]
+    pub share_protocol_runtime: bool,
+}
+
+impl RuntimeArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.protocol_worker_threads == Some(0) {
+            anyhow::bail!("`runtime.protocol_worker_threads` must be greater than 0");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn test_runtime_args() -> Result<()> {
+        let args: RuntimeArgs = serde_json::from_value(json!({}))?;
+        assert_eq!(args, RuntimeArgs::default());
+        args.validate()?;
+
+        let args: RuntimeArgs = serde_json::from_value(json!({
+            "protocol_worker_threads": 0,
+        }))?;
+        assert!(args.validate().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+//! Loading of the configuration from the file system.
+//!
+//! Besides a single config file, the configuration can be assembled from a directory of drop-in
+//! files (conf.d style), so that different teams can manage their own ingresses and egresses in
+//! separate files, e.g. different keys of a Kubernetes ConfigMap.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use serde_json::{Map, Value};
+
+use super::overrides::ConfigOverrides;
+use super::TngConfig;
+
+/// Top-level fields whose entries are appended when merging config files, instead of being
+/// required to be identical.
+const APPENDED_FIELDS: &[&str] = &["add_ingress", "add_egress"];
+
+/// Where the configuration is loaded from.
+#[derive(Debug, Clone)]
+pub enum ConfigSource {
+    /// A single JSON or YAML config file.
+    File(PathBuf),
+    /// A directory whose `*.json`, `*.yaml` and `*.yml` files are merged into one configuration.
+    Dir(PathBuf),
+}
+
+impl ConfigSource {
+    pub fn load(&self) -> Result<TngConfig> {
+        self.load_with_overrides(&ConfigOverrides::default())
+    }
+
+    /// Load the configuration and apply the overrides on top of it.
+    pub fn load_with_overrides(&self, overrides: &ConfigOverrides) -> Result<TngConfig> {
+        let config = match self {
+            ConfigSource::File(path) => {
+                tracing::info!(?path, "Loading config from");
+                if overrides.is_empty() {
+                    // Parse the file directly, so that errors are reported with line numbers
+                    return load_file(path);
+                }
+                load_file(path)?
+            }
+            ConfigSource::Dir(dir) => {
+                tracing::info!(?dir, "Loading config from directory");
+                let mut merged = Map::new();
+                for path in list_config_files(dir)? {
+                    tracing::debug!(?path, "Merging config file");
+                    let value: Value = load_file(&path)?;
+                    let Value::Object(value) = value else {
+                        bail!("The config file {path:?} does not contain an object");
+                    };
+                    merge_config(&mut merged, value)
+                        .with_context(|| format!("Failed to merge config file {path:?}"))?;
+                }
+                Value::Object(merged)
+            }
+        };
+        overrides.apply_to(config)
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("yaml" | "yml")
+    )
+}
+
+fn load_file<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = File::open(path).with_context(|| format!("Failed to open config file {path:?}"))?;
+    let reader = BufReader::new(file);
+    if is_yaml(path) {
+        serde_norway::from_reader(reader)
+            .with_context(|| format!("Failed to parse YAML config file {path:?}"))
+    } else {
+        serde_json::from_reader(reader)
+            .with_context(|| format!("Failed to parse JSON config file {path:?}"))
+    }
+}
+
+/// List the config files in the directory, sorted by file name. Hidden files are skipped, which
+/// also excludes the `..data` entries created by Kubernetes for mounted ConfigMaps.
+fn list_config_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read config dir {dir:?}"))?
+    {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        let is_config = is_yaml(&path) || path.extension().is_some_and(|ext| ext == "json");
+        // Follow symlinks, since files of mounted ConfigMaps are symlinks
+        if is_config && std::fs::metadata(&path)?.is_file() {
+            files.push(path);
+        }
+    }
+    if files.is_empty() {
+        bail!("No *.json, *.yaml or *.yml config file found in {dir:?}");
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Merge a config file into the configuration merged so far. Ingresses and egresses are
+/// appended, objects are merged recursively, and any other field set in both must be identical.
+fn merge_config(merged: &mut Map<String, Value>, value: Map<String, Value>) -> Result<()> {
+    for (key, value) in value {
+        if APPENDED_FIELDS.contains(&key.as_str()) {
+            let Value::Array(entries) = value else {
+                bail!("The field `{key}` must be an array");
+            };
+            match merged
+                .entry(key.clone())
+                .or_insert_with(|| Value::Array(vec![]))
+            {
+                Value::Array(merged_entries) => merged_entries.extend(entries),
+                _ => bail!("The field `{key}` must be an array"),
+            }
+        } else {
+            merge_value(merged, key.clone(), value, &key)?;
+        }
+    }
+    Ok(())
+}
+
+fn merge_value(
+    merged: &mut Map<String, Value>,
+    key: String,
+    value: Value,
+    path: &str,
+) -> Result<()> {
+    let Some(existing) = merged.get_mut(&key) else {
+        merged.insert(key, value);
+        return Ok(());
+    };
+    match (existing, value) {
+        (Value::Object(existing), Value::Object(value)) => {
+            for (key, value) in value {
+                let path = format!("{path}.{key}");
+                merge_value(existing, key, value, &path)?;
+            }
+        }
+        (existing, value) => {
+            if *existing != value {
+                bail!("The field `{path}` is set to conflicting values: {existing} and {value}");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_config() -> Result<()> {
+        let mut merged = Map::new();
+        let files = [
+            json!({
+                "control_interface": {"restful": {"host": "0.0.0.0", "port": 50000}},
+                "add_ingress": [{"mapping": {"in": {"port": 10001}, "out": {"host": "127.0.0.1", "port": 20001}}, "no_ra": true}]
+            }),
+            json!({
+                "control_interface": {"ttrpc": {"path": "/run/tng.sock"}},
+                "add_ingress": [{"mapping": {"in": {"port": 10002}, "out": {"host": "127.0.0.1", "port": 20002}}, "no_ra": true}],
+                "add_egress": [{"mapping": {"in": {"port": 30001}, "out": {"host": "127.0.0.1", "port": 40001}}, "no_ra": true}]
+            }),
+            json!({
+                "control_interface": {"restful": {"host": "0.0.0.0", "port": 50000}}
+            }),
+        ];
+        for file in files {
+            merge_config(&mut merged, serde_json::from_value(file)?)?;
+        }
+
+        let config: TngConfig = serde_json::from_value(Value::Object(merged))?;
+        assert_eq!(config.add_ingress.len(), 2);
+        assert_eq!(config.add_egress.len(), 1);
+        let control_interface = config
+            .control_interface
+            .ok_or_else(|| anyhow::anyhow!("missing control_interface"))?;
+        assert!(control_interface.restful.is_some());
+        assert!(control_interface.ttrpc.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_config_conflict() -> Result<()> {
+        let mut merged = Map::new();
+        merge_config(
+            &mut merged,
+            serde_json::from_value(
+                json!({"control_interface": {"restful": {"host": "0.0.0.0", "port": 50000}}}),
+            )?,
+        )?;
+        let error = merge_config(
+            &mut merged,
+            serde_json::from_value(
+                json!({"control_interface": {"restful": {"host": "0.0.0.0", "port": 50001}}}),
+            )?,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("control_interface.restful.port"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_dir() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join("10-team-a.json"),
+            r#"{"add_ingress": [{"mapping": {"in": {"port": 10001}, "out": {"host": "127.0.0.1", "port": 20001}}, "no_ra": true}]}"#,
+        )?;
+        std::fs::write(
+            dir.path().join("20-team-b.yaml"),
+            "add_egress:\n  - mapping:\n      in:\n        port: 30001\n      out:\n        host: 127.0.0.1\n        port: 40001\n    no_ra: true\n",
+        )?;
+        std::fs::write(dir.path().join("README.md"), "not a config")?;
+        std::fs::create_dir(dir.path().join("..data"))?;
+
+        let config = ConfigSource::Dir(dir.path().to_owned()).load()?;
+        assert_eq!(config.add_ingress.len(), 1);
+        assert_eq!(config.add_egress.len(), 1);
+
+        let empty = tempfile::tempdir()?;
+        assert!(ConfigSource::Dir(empty.path().to_owned()).load().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::ingress::EndpointMatcherConfig;
+use super::Endpoint;
+
+/// Resolve the domain names of the upstreams with a DNS server inside the TEE network, by sending
+/// the queries through a trusted tunnel to an egress in `mapping_udp` mode, so that the resolver
+/// on the client side does not learn which upstreams are accessed.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TunnelDnsArgs {
+    /// The address of the egress forwarding the queries to the DNS server. Its host must be an
+    /// IPv4 address, since it is connected to before any name is resolved.
+    pub server: Endpoint,
+
+    /// The upstream endpoints whose names are resolved through the tunnel. The other names are
+    /// resolved as usual. All names are resolved through the tunnel if empty.
+    #[serde(default)]
+    pub dst_filters: Vec<EndpointMatcherConfig>,
+
+    /// The time, in seconds, to wait for the answer of a query, including the establishment of
+    /// the tunnel.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+impl TunnelDnsArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match &self.server.host {
+            Some(host) if host.parse::<Ipv4Addr>().is_ok() => {}
+            _ => anyhow::bail!("`tunnel_dns.server.host` must be an IPv4 address"),
+        }
+        if self.timeout_secs == 0 {
+            anyhow::bail!("`tunnel_dns.timeout_secs` must be greater than 0");
+        }
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn test_tunnel_dns_args() -> Result<()> {
+        let args: TunnelDnsArgs = serde_json::from_value(json!({
+            "server": { "host": "10.0.0.5", "port": 5353 },
+            "dst_filters": [{ "domain": "*.confidential.internal" }]
+        }))?;
+        assert_eq!(args.dst_filters.len(), 1);
+        assert_eq!(args.timeout(), Duration::from_secs(10));
+        args.validate()?;
+
+        let args: TunnelDnsArgs = serde_json::from_value(json!({
+            "server": { "host": "dns.confidential.internal", "port": 5353 }
+        }))?;
+        assert!(args.validate().is_err());
+
+        let args: TunnelDnsArgs = serde_json::from_value(json!({
+            "server": { "host": "10.0.0.5", "port": 5353 },
+            "timeout_secs": 0
+        }))?;
+        assert!(args.validate().is_err());
+
+        Ok(())
+    }
+}
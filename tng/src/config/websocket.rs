@@ -0,0 +1,54 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Carry the rats-TLS sessions inside WebSocket connections, for the clients which cannot open
+/// raw TCP connections, e.g. `tng-wasm` in a browser.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebSocketArgs {
+    /// The path of the WebSocket upgrade requests.
+    #[serde(default = "default_path")]
+    pub path: String,
+}
+
+fn default_path() -> String {
+    "/tng/websocket".to_string()
+}
+
+impl Default for WebSocketArgs {
+    fn default() -> Self {
+        Self {
+            path: default_path(),
+        }
+    }
+}
+
+impl WebSocketArgs {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.path.starts_with('/') {
+            anyhow::bail!("`rats_tls.websocket.path` must start with '/'");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn test_websocket_args() -> Result<()> {
+        let args: WebSocketArgs = serde_json::from_value(json!({}))?;
+        assert_eq!(args, WebSocketArgs::default());
+        assert_eq!(args.path, "/tng/websocket");
+        args.validate()?;
+
+        let args: WebSocketArgs = serde_json::from_value(json!({
+            "path": "tunnel",
+        }))?;
+        assert!(args.validate().is_err());
+        Ok(())
+    }
+}
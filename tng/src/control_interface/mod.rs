@@ -1,14 +1,16 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::{
-    config::control_interface::ControlInterfaceArgs,
+    config::{control_interface::ControlInterfaceArgs, ra::VerifyUpdate, TngConfig},
     error::TngError,
+    runtime::{ConfigReloadHandle, DrainSummary, LogFilterDirectives, ReloadSummary, ServiceKind},
     service::RegistedService,
-    state::TngState,
+    state::{ReadinessReport, TngState},
     status::{StatusProvider, StatusQueryResult},
-    tunnel::utils::runtime::TokioRuntime,
+    tunnel::{connections::ConnectionSelector, utils::runtime::TokioRuntime},
 };
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use async_trait::async_trait;
 use restful::RestfulControlInterface;
 use tokio::sync::mpsc::Sender;
@@ -33,9 +35,10 @@ impl ControlInterface {
     pub async fn new(
         args: ControlInterfaceArgs,
         state: Arc<TngState>,
+        reload_handle: ConfigReloadHandle,
         runtime: TokioRuntime,
     ) -> Result<Self> {
-        let core = Arc::new(ControlInterfaceCore::new(state));
+        let core = Arc::new(ControlInterfaceCore::new(state, reload_handle));
 
         Ok(match (args.restful, args.ttrpc) {
             (None, None) => {
@@ -43,7 +46,7 @@ impl ControlInterface {
             }
             (Some(args), None) => ControlInterface {
                 inner: ControlInterfaceInner::Restful(
-                    RestfulControlInterface::new(args, core).await?,
+                    RestfulControlInterface::new(args, core, runtime.clone()).await?,
                 ),
                 runtime,
             },
@@ -89,18 +92,81 @@ impl StatusProvider for ControlInterface {
 
 pub struct ControlInterfaceCore {
     state: Arc<TngState>,
+    reload_handle: ConfigReloadHandle,
 }
 
 impl ControlInterfaceCore {
-    pub fn new(state: Arc<TngState>) -> Self {
-        Self { state }
+    pub fn new(state: Arc<TngState>, reload_handle: ConfigReloadHandle) -> Self {
+        Self {
+            state,
+            reload_handle,
+        }
     }
 
     pub async fn livez(&self) -> bool {
         true
     }
 
+    /// Whether the instance has finished starting and all of its ingresses and egresses are ready.
     pub async fn readyz(&self) -> bool {
-        *self.state.ready.1.borrow()
+        self.readiness().await.ready
+    }
+
+    /// The readiness of the instance and of each of its ingresses and egresses.
+    pub async fn readiness(&self) -> ReadinessReport {
+        self.state.readiness().await
+    }
+    /// Reload the configuration. If no configuration is provided, it is loaded again from the
+    /// config file.
+    pub async fn reload(&self, config: Option<TngConfig>) -> Result<ReloadSummary> {
+        self.reload_handle.reload(config).await
+    }
+
+    /// Gracefully drain an ingress or egress: stop accepting new connections and wait for the
+    /// established ones to finish, closing those still open after the timeout.
+    pub async fn drain(
+        &self,
+        kind: ServiceKind,
+        id: usize,
+        timeout: Duration,
+    ) -> Result<DrainSummary> {
+        self.reload_handle.drain(kind, id, timeout).await
+    }
+
+    /// Close the selected connections of an ingress or egress, and return how many of them are
+    /// closed.
+    pub async fn terminate_connections(
+        &self,
+        kind: ServiceKind,
+        id: usize,
+        selector: &ConnectionSelector,
+    ) -> Result<usize> {
+        self.state
+            .terminate_connections(kind, id, selector)
+            .await
+            .ok_or_else(|| anyhow!("The {kind} {id} does not exist"))
+    }
+
+    /// Query the log filter if `directives` is `None`, or change it. The current filter is
+    /// returned.
+    pub async fn log_filter(&self, directives: Option<LogFilterDirectives>) -> Result<String> {
+        self.reload_handle.log_filter(directives).await
+    }
+
+    /// Restart an ingress or egress with its current configuration.
+    pub async fn restart(&self, kind: ServiceKind, id: usize) -> Result<()> {
+        self.reload_handle.restart(kind, id).await
+    }
+
+    /// Update the parameters which an ingress or egress verifies its peers with, from the next
+    /// handshake on. The update is kept when the service is restarted, but not written to the
+    /// configuration file.
+    pub async fn update_verify(
+        &self,
+        kind: ServiceKind,
+        id: usize,
+        update: VerifyUpdate,
+    ) -> Result<()> {
+        self.reload_handle.update_verify(kind, id, update).await
     }
 }
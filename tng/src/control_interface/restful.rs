@@ -1,25 +1,73 @@
-use std::{convert::Infallible, sync::Arc};
+use std::{
+    convert::Infallible,
+    fs::File,
+    io::BufReader,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::{Context, Result};
-use axum::{extract::Path, routing::get, Json, Router};
-use http::{HeaderValue, StatusCode};
-use tower::ServiceBuilder;
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Path, Query, State},
+    response::IntoResponse as _,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use cidr::IpCidr;
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use hyper_util::rt::TokioIo;
+use rustls::{server::WebPkiClientVerifier, RootCertStore, ServerConfig};
+use serde::Deserialize;
+use tokio_rustls::TlsAcceptor;
+use tower::{Service as _, ServiceBuilder};
 
 use crate::error::TngError;
+use crate::runtime::{LogFilterDirectives, ServiceKind};
 use crate::state::TngState;
 use crate::status::{StatusProvider, StatusQueryResult};
-use crate::{config::control_interface::RestfulArgs, HTTP_RESPONSE_SERVER_HEADER};
+use crate::tunnel::connections::ConnectionSelector;
+use crate::tunnel::utils::runtime::TokioRuntime;
+use crate::tunnel::utils::source_addr::is_source_allowed;
+use crate::{
+    config::{
+        control_interface::{ControlRole, MtlsArgs, RestfulArgs, TokenArgs},
+        ra::VerifyUpdate,
+        TngConfig,
+    },
+    HTTP_RESPONSE_SERVER_HEADER,
+};
 
 use super::ControlInterfaceCore;
 
 pub struct RestfulControlInterface {
     args: RestfulArgs,
     core: Arc<ControlInterfaceCore>,
+    /// Set if the interface is served over TLS with client certificates required.
+    tls_acceptor: Option<TlsAcceptor>,
+    runtime: TokioRuntime,
 }
 
 impl RestfulControlInterface {
-    pub async fn new(args: RestfulArgs, core: Arc<ControlInterfaceCore>) -> Result<Self> {
-        Ok(Self { args, core })
+    pub async fn new(
+        args: RestfulArgs,
+        core: Arc<ControlInterfaceCore>,
+        runtime: TokioRuntime,
+    ) -> Result<Self> {
+        let tls_acceptor = args
+            .auth
+            .as_ref()
+            .and_then(|auth| auth.mtls.as_ref())
+            .map(load_tls_acceptor)
+            .transpose()
+            .context("Failed to setup mTLS for the control interface")?;
+        Ok(Self {
+            args,
+            core,
+            tls_acceptor,
+            runtime,
+        })
     }
 
     pub async fn serve(&self) -> Result<()> {
@@ -43,15 +91,126 @@ impl RestfulControlInterface {
                     "/readyz",
                     get({
                         let core = self.core.clone();
-                        move || async move {
-                            if core.readyz().await {
-                                (StatusCode::OK, "ok")
-                            } else {
-                                (StatusCode::SERVICE_UNAVAILABLE, "not ok")
+                        move |Query(params): Query<ProbeParams>| async move {
+                            readyz_response(&core, params).await
+                        }
+                    }),
+                )
+                .route(
+                    "/reload",
+                    post({
+                        let core = self.core.clone();
+                        move |body: Bytes| async move { reload_response(&core, body).await }
+                    }),
+                )
+                .route(
+                    "/{kind}/{id}/drain",
+                    post({
+                        let core = self.core.clone();
+                        move |Path((kind, id)): Path<(ServiceKind, usize)>,
+                              Query(params): Query<DrainParams>| async move {
+                            drain_response(&core, kind, id, params).await
+                        }
+                    }),
+                )
+                .route(
+                    "/{kind}/{id}/restart",
+                    post({
+                        let core = self.core.clone();
+                        move |Path((kind, id)): Path<(ServiceKind, usize)>| async move {
+                            restart_response(&core, kind, id).await
+                        }
+                    }),
+                )
+                .route(
+                    "/{kind}/{id}/verify",
+                    put({
+                        let core = self.core.clone();
+                        move |Path((kind, id)): Path<(ServiceKind, usize)>,
+                              body: Bytes| async move {
+                            match serde_json::from_slice::<VerifyUpdate>(&body) {
+                                Ok(update) => {
+                                    update_verify_response(&core, kind, id, update).await
+                                }
+                                Err(error) => (
+                                    StatusCode::BAD_REQUEST,
+                                    Json(serde_json::json!({
+                                        "error": format!("Invalid request: {error}")
+                                    })),
+                                ),
+                            }
+                        }
+                    }),
+                )
+                .route(
+                    "/{kind}/{id}/connections",
+                    delete({
+                        let core = self.core.clone();
+                        move |Path((kind, id)): Path<(ServiceKind, usize)>,
+                              Query(params): Query<TerminateParams>| async move {
+                            match params.dst {
+                                Some(dst) => {
+                                    terminate_response(
+                                        &core,
+                                        kind,
+                                        id,
+                                        ConnectionSelector::Dst(dst),
+                                    )
+                                    .await
+                                }
+                                None => (
+                                    StatusCode::BAD_REQUEST,
+                                    Json(serde_json::json!({
+                                        "error": "The destination of the connections to close is required"
+                                    })),
+                                ),
                             }
                         }
                     }),
                 )
+                .route(
+                    "/{kind}/{id}/connections/{connection_id}",
+                    delete({
+                        let core = self.core.clone();
+                        move |Path((kind, id, connection_id)): Path<(ServiceKind, usize, u64)>| async move {
+                            terminate_response(&core, kind, id, ConnectionSelector::Id(connection_id))
+                                .await
+                        }
+                    }),
+                )
+                .route(
+                    "/log/filter",
+                    get({
+                        let core = self.core.clone();
+                        move || async move { log_filter_response(&core, None).await }
+                    })
+                    .put({
+                        let core = self.core.clone();
+                        move |body: Bytes| async move {
+                            match serde_json::from_slice::<LogFilterBody>(&body) {
+                                Ok(body) => {
+                                    log_filter_response(
+                                        &core,
+                                        Some(LogFilterDirectives::Set(body.filter)),
+                                    )
+                                    .await
+                                }
+                                Err(error) => (
+                                    StatusCode::BAD_REQUEST,
+                                    Json(serde_json::json!({
+                                        "error": format!("Invalid request: {error}")
+                                    })),
+                                ),
+                            }
+                        }
+                    })
+                    .delete({
+                        let core = self.core.clone();
+                        move || async move {
+                            log_filter_response(&core, Some(LogFilterDirectives::Reset)).await
+                        }
+                    }),
+                )
                 .route(
                     "/status/",
                     get({
@@ -70,7 +229,18 @@ impl RestfulControlInterface {
                         }
                     }),
                 )
-                .layer(ServiceBuilder::new().layer(axum::middleware::from_fn(add_server_header)));
+                .layer(
+                    ServiceBuilder::new()
+                        .layer(axum::middleware::from_fn(add_server_header))
+                        .layer(axum::middleware::from_fn_with_state(
+                            Arc::new(self.args.allowed_sources.clone()),
+                            check_source,
+                        ))
+                        .layer(axum::middleware::from_fn_with_state(
+                            Arc::new(Authorizer::new(&self.args)),
+                            authorize,
+                        )),
+                );
 
         let addr = (
             self.args.address.host.as_deref().unwrap_or("0.0.0.0"),
@@ -79,15 +249,37 @@ impl RestfulControlInterface {
         tracing::info!(
             host = %addr.0,
             port = addr.1,
+            tls = self.tls_acceptor.is_some(),
             "Restful Control interface listening"
         );
+        if self.args.allowed_sources.is_empty()
+            && self.args.auth.is_none()
+            && self.args.unauthenticated_role == ControlRole::Operator
+            && !addr.0.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+        {
+            tracing::warn!(
+                host = %addr.0,
+                "The control interface is reachable from any source, consider binding it on a loopback address or setting `allowed_sources` or `auth`"
+            );
+        }
         let listener = tokio::net::TcpListener::bind(addr).await.with_context(|| {
             format!(
                 "Failed to bind REST control interface on {}:{}",
                 addr.0, addr.1
             )
         })?;
-        axum::serve(listener, app).await?;
+        match &self.tls_acceptor {
+            Some(tls_acceptor) => {
+                serve_tls(listener, tls_acceptor.clone(), app, &self.runtime).await?
+            }
+            None => {
+                axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .await?
+            }
+        }
 
         tracing::info!("Restful Control interface stopping");
 
@@ -95,6 +287,154 @@ impl RestfulControlInterface {
     }
 }
 
+/// Serve the interface over TLS, which requires the clients to present a valid certificate.
+async fn serve_tls(
+    listener: tokio::net::TcpListener,
+    tls_acceptor: TlsAcceptor,
+    app: Router,
+    runtime: &TokioRuntime,
+) -> Result<()> {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                tracing::warn!(?error, "Failed to accept control interface connection");
+                continue;
+            }
+        };
+        let tls_acceptor = tls_acceptor.clone();
+        let app = app.clone();
+        let runtime_cloned = runtime.clone();
+        runtime.spawn_supervised_task(async move {
+            let stream = match tls_acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(error) => {
+                    tracing::warn!(%peer, ?error, "TLS handshake with control interface client failed");
+                    return;
+                }
+            };
+            let service = hyper::service::service_fn(
+                move |mut request: axum::extract::Request<hyper::body::Incoming>| {
+                    // Provide the peer address like `into_make_service_with_connect_info()`
+                    request.extensions_mut().insert(ConnectInfo(peer));
+                    app.clone().call(request)
+                },
+            );
+            if let Err(error) = hyper_util::server::conn::auto::Builder::new(runtime_cloned)
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!(%peer, ?error, "Control interface connection terminated");
+            }
+        });
+    }
+}
+
+fn load_tls_acceptor(mtls: &MtlsArgs) -> Result<TlsAcceptor> {
+    let open = |path: &str| -> Result<BufReader<File>> {
+        Ok(BufReader::new(
+            File::open(path).with_context(|| format!("Failed to open {path:?}"))?,
+        ))
+    };
+
+    let certs = rustls_pemfile::certs(&mut open(&mtls.cert)?)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid certificate file {:?}", mtls.cert))?;
+    let key = rustls_pemfile::private_key(&mut open(&mtls.key)?)
+        .with_context(|| format!("Invalid private key file {:?}", mtls.key))?
+        .with_context(|| format!("No private key found in {:?}", mtls.key))?;
+
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut open(&mtls.client_ca)?) {
+        roots
+            .add(cert.with_context(|| format!("Invalid CA file {:?}", mtls.client_ca))?)
+            .with_context(|| format!("Invalid CA certificate in {:?}", mtls.client_ca))?;
+    }
+    let verifier = WebPkiClientVerifier::builder(Arc::new(roots)).build()?;
+
+    let mut config = ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Authorization of the requests according to the roles of the clients.
+struct Authorizer {
+    tokens: Vec<TokenArgs>,
+    /// The role of the clients when no token is configured.
+    default_role: ControlRole,
+}
+
+impl Authorizer {
+    fn new(args: &RestfulArgs) -> Self {
+        let auth = args.auth.as_ref();
+        Self {
+            tokens: auth.map(|auth| auth.tokens.clone()).unwrap_or_default(),
+            // Without mTLS, the clients are not authenticated if there is no token either
+            default_role: match auth.and_then(|auth| auth.mtls.as_ref()) {
+                Some(mtls) => mtls.role,
+                None => args.unauthenticated_role,
+            },
+        }
+    }
+
+    /// The role of the client, or `None` if it is not authenticated.
+    fn role(&self, headers: &HeaderMap) -> Option<ControlRole> {
+        if self.tokens.is_empty() {
+            return Some(self.default_role);
+        }
+        let token = headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+        self.tokens
+            .iter()
+            .find(|args| constant_time_eq(args.token.as_bytes(), token.as_bytes()))
+            .map(|args| args.role)
+    }
+}
+
+/// The role required by the request, or `None` if the request is allowed for any client.
+fn required_role(method: &Method, path: &str) -> Option<ControlRole> {
+    match (method, path) {
+        // Keep the health checks open, so that they can be used by the probes
+        (&Method::GET | &Method::HEAD, "/livez" | "/readyz") => None,
+        (&Method::GET | &Method::HEAD, _) => Some(ControlRole::ReadOnly),
+        _ => Some(ControlRole::Operator),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Reject the requests from clients which are not authenticated, or not allowed to make them.
+async fn authorize(
+    State(authorizer): State<Arc<Authorizer>>,
+    req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let Some(required) = required_role(req.method(), req.uri().path()) else {
+        return next.run(req).await;
+    };
+    match authorizer.role(req.headers()) {
+        None => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            Json(serde_json::json!({"error": "unauthorized"})),
+        )
+            .into_response(),
+        Some(role) if role < required => (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "forbidden"})),
+        )
+            .into_response(),
+        Some(_) => next.run(req).await,
+    }
+}
+
 async fn add_server_header(
     req: axum::extract::Request,
     next: axum::middleware::Next,
@@ -107,6 +447,201 @@ async fn add_server_header(
     Ok(res)
 }
 
+/// Reject the requests whose source address is not in `allowed_sources`.
+async fn check_source(
+    State(allowed_sources): State<Arc<Vec<IpCidr>>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    if is_source_allowed(&allowed_sources, peer.ip()) {
+        next.run(req).await
+    } else {
+        tracing::warn!(%peer, "Rejected control interface request from a source not allowed");
+        (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "forbidden"})),
+        )
+            .into_response()
+    }
+}
+
+/// Reload with the configuration in the request body, or from the config file if the body is
+/// empty.
+async fn reload_response(
+    core: &ControlInterfaceCore,
+    body: Bytes,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let config = if body.is_empty() {
+        None
+    } else {
+        match serde_json::from_slice::<TngConfig>(&body) {
+            Ok(config) => Some(config),
+            Err(error) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"error": format!("Invalid config: {error}")})),
+                )
+            }
+        }
+    };
+
+    match core.reload(config).await {
+        Ok(summary) => (
+            StatusCode::OK,
+            Json(serde_json::to_value(summary).unwrap_or_default()),
+        ),
+        Err(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": format!("{error:#}")})),
+        ),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ProbeParams {
+    /// Report the readiness of each service in JSON, if present.
+    verbose: Option<String>,
+}
+
+async fn readyz_response(
+    core: &ControlInterfaceCore,
+    params: ProbeParams,
+) -> axum::response::Response {
+    let report = core.readiness().await;
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    if params.verbose.is_some() {
+        (
+            status,
+            Json(serde_json::to_value(report).unwrap_or_default()),
+        )
+            .into_response()
+    } else if report.ready {
+        (status, "ok").into_response()
+    } else {
+        (status, "not ok").into_response()
+    }
+}
+
+/// The default time to wait for the connections to finish when draining a service.
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+struct DrainParams {
+    timeout_secs: Option<u64>,
+}
+
+/// The status code of a failed operation on an ingress or egress.
+fn operation_error_status(error: &anyhow::Error) -> StatusCode {
+    match error.downcast_ref::<TngError>() {
+        Some(TngError::ServiceNotFound) => StatusCode::NOT_FOUND,
+        Some(TngError::InvalidVerifyUpdate(..)) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+async fn drain_response(
+    core: &ControlInterfaceCore,
+    kind: ServiceKind,
+    id: usize,
+    params: DrainParams,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let timeout = params
+        .timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_DRAIN_TIMEOUT);
+    match core.drain(kind, id, timeout).await {
+        Ok(summary) => (
+            StatusCode::OK,
+            Json(serde_json::to_value(summary).unwrap_or_default()),
+        ),
+        Err(error) => (
+            operation_error_status(&error),
+            Json(serde_json::json!({"error": format!("{error:#}")})),
+        ),
+    }
+}
+
+async fn restart_response(
+    core: &ControlInterfaceCore,
+    kind: ServiceKind,
+    id: usize,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match core.restart(kind, id).await {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({}))),
+        Err(error) => (
+            operation_error_status(&error),
+            Json(serde_json::json!({"error": format!("{error:#}")})),
+        ),
+    }
+}
+
+async fn update_verify_response(
+    core: &ControlInterfaceCore,
+    kind: ServiceKind,
+    id: usize,
+    update: VerifyUpdate,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match core.update_verify(kind, id, update).await {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({}))),
+        Err(error) => (
+            operation_error_status(&error),
+            Json(serde_json::json!({"error": format!("{error:#}")})),
+        ),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TerminateParams {
+    /// Close all the connections to the destination, given as `host:port` or only the host.
+    dst: Option<String>,
+}
+
+async fn terminate_response(
+    core: &ControlInterfaceCore,
+    kind: ServiceKind,
+    id: usize,
+    selector: ConnectionSelector,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match core.terminate_connections(kind, id, &selector).await {
+        Ok(closed) if closed == 0 && matches!(selector, ConnectionSelector::Id(_)) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "The connection does not exist"})),
+        ),
+        Ok(closed) => {
+            tracing::info!(%kind, id, ?selector, closed, "Connections terminated");
+            (StatusCode::OK, Json(serde_json::json!({"closed": closed})))
+        }
+        Err(error) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": format!("{error:#}")})),
+        ),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogFilterBody {
+    filter: String,
+}
+
+async fn log_filter_response(
+    core: &ControlInterfaceCore,
+    directives: Option<LogFilterDirectives>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match core.log_filter(directives).await {
+        Ok(filter) => (StatusCode::OK, Json(serde_json::json!({"filter": filter}))),
+        Err(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": format!("{error:#}")})),
+        ),
+    }
+}
+
 async fn status_response(
     state: Arc<TngState>,
     raw_path: String,
@@ -143,6 +678,68 @@ mod tests {
     use tokio::select;
 
     use super::*;
+
+    #[test]
+    fn test_authorize() -> Result<()> {
+        let args: RestfulArgs = serde_json::from_value(json!({
+            "port": 50000,
+            "auth": {
+                "tokens": [
+                    {"token": "viewer-token", "role": "read_only"},
+                    {"token": "admin-token", "role": "operator"},
+                    {"token": "default-token"}
+                ]
+            }
+        }))?;
+        let authorizer = Authorizer::new(&args);
+        let headers = |token: &str| -> Result<HeaderMap> {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, format!("Bearer {token}").parse()?);
+            Ok(headers)
+        };
+        assert_eq!(
+            authorizer.role(&headers("viewer-token")?),
+            Some(ControlRole::ReadOnly)
+        );
+        assert_eq!(
+            authorizer.role(&headers("admin-token")?),
+            Some(ControlRole::Operator)
+        );
+        assert_eq!(
+            authorizer.role(&headers("default-token")?),
+            Some(ControlRole::ReadOnly)
+        );
+        assert_eq!(authorizer.role(&headers("wrong-token")?), None);
+        assert_eq!(authorizer.role(&HeaderMap::new()), None);
+
+        // Without authentication, all the clients share the same role, which is read-only unless
+        // set otherwise
+        let args: RestfulArgs = serde_json::from_value(json!({"port": 50000}))?;
+        assert_eq!(
+            Authorizer::new(&args).role(&HeaderMap::new()),
+            Some(ControlRole::ReadOnly)
+        );
+        let args: RestfulArgs = serde_json::from_value(json!({
+            "port": 50000,
+            "unauthenticated_role": "operator"
+        }))?;
+        assert_eq!(
+            Authorizer::new(&args).role(&HeaderMap::new()),
+            Some(ControlRole::Operator)
+        );
+
+        assert_eq!(required_role(&Method::GET, "/readyz"), None);
+        assert_eq!(
+            required_role(&Method::GET, "/status/ingress/0/connections"),
+            Some(ControlRole::ReadOnly)
+        );
+        assert_eq!(
+            required_role(&Method::POST, "/reload"),
+            Some(ControlRole::Operator)
+        );
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
     async fn test_control_interface() -> Result<()> {
         let port = portpicker::pick_unused_port().unwrap();
@@ -215,6 +812,18 @@ mod tests {
                 assert!(resp.status() == StatusCode::OK);
             }
         }
+
+        // The clients are not allowed to operate the instance by default
+        {
+            let resp = reqwest::ClientBuilder::new()
+                .no_proxy()
+                .build()?
+                .post(format!("http://127.0.0.1:{port}/reload"))
+                .send()
+                .await?;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        }
+
         // stop tng
         canceller.cancel();
 
@@ -241,7 +850,8 @@ mod tests {
                 "control_interface": {
                     "restful": {
                         "host": "127.0.0.1",
-                        "port": port
+                        "port": port,
+                        "unauthenticated_role": "operator"
                     }
                 },
                 "add_ingress": [
@@ -324,6 +934,109 @@ mod tests {
             assert!(body["local_keys"].is_array());
         }
 
+        // /status/egress/0/connections should return the active connections
+        {
+            let resp = reqwest::ClientBuilder::new()
+                .no_proxy()
+                .build()?
+                .get(format!(
+                    "http://127.0.0.1:{port}/status/egress/0/connections"
+                ))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::OK, "got {}", resp.status());
+            let body: Vec<serde_json::Value> = resp.json().await?;
+            assert!(body.is_empty());
+        }
+
+        // Terminating connections
+        {
+            let client = reqwest::ClientBuilder::new().no_proxy().build()?;
+            let resp = client
+                .delete(format!(
+                    "http://127.0.0.1:{port}/egress/0/connections?dst=127.0.0.1"
+                ))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::OK, "got {}", resp.status());
+            let body: serde_json::Value = resp.json().await?;
+            assert_eq!(body, json!({"closed": 0}));
+
+            let resp = client
+                .delete(format!("http://127.0.0.1:{port}/egress/0/connections/42"))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::NOT_FOUND);
+        }
+
+        // Updating the verification parameters
+        {
+            let client = reqwest::ClientBuilder::new().no_proxy().build()?;
+            let resp = client
+                .put(format!("http://127.0.0.1:{port}/egress/0/verify"))
+                .json(&json!({"policy_ids": ["policy-v2"]}))
+                .send()
+                .await?;
+            // The egress does not verify its peers
+            assert!(resp.status() == StatusCode::BAD_REQUEST);
+
+            let resp = client
+                .put(format!("http://127.0.0.1:{port}/egress/1/verify"))
+                .json(&json!({"policy_ids": ["policy-v2"]}))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::NOT_FOUND);
+
+            let resp = client
+                .put(format!("http://127.0.0.1:{port}/egress/0/verify"))
+                .json(&json!({"max_evidence_age": 60}))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::BAD_REQUEST);
+        }
+
+        // Draining and restarting the ingress
+        {
+            let client = reqwest::ClientBuilder::new().no_proxy().build()?;
+            let resp = client
+                .post(format!(
+                    "http://127.0.0.1:{port}/ingress/0/drain?timeout_secs=1"
+                ))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::OK, "got {}", resp.status());
+            let body: serde_json::Value = resp.json().await?;
+            assert_eq!(body, json!({"finished": 0, "closed": 0}));
+
+            let resp = client
+                .get(format!("http://127.0.0.1:{port}/readyz?verbose"))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::SERVICE_UNAVAILABLE);
+            let body: serde_json::Value = resp.json().await?;
+            assert_eq!(body["ready"], json!(false));
+            assert_eq!(body["ingress"][0]["state"], json!("drained"));
+            assert_eq!(body["egress"][0], json!({"state": "ready"}));
+
+            let resp = client
+                .post(format!("http://127.0.0.1:{port}/ingress/0/restart"))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::OK, "got {}", resp.status());
+
+            let resp = client
+                .get(format!("http://127.0.0.1:{port}/readyz"))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::OK, "got {}", resp.status());
+
+            let resp = client
+                .post(format!("http://127.0.0.1:{port}/egress/999/restart"))
+                .send()
+                .await?;
+            assert!(resp.status() == StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
         // /status/egress/999/ohttp/keys should return 404
         {
             let resp = reqwest::ClientBuilder::new()
@@ -83,6 +83,9 @@ pub enum TngError {
     #[error("Failed to connect to upstream")]
     ConnectUpstreamFailed,
 
+    #[error("The circuit of upstream {0} is open since it failed to be connected too many times in a row, try again later")]
+    UpstreamCircuitOpen(String /* endpoint */),
+
     #[error("Failed to construct http response")]
     ConstructHttpResponseFailed(#[source] http::Error),
 
@@ -92,6 +95,9 @@ pub enum TngError {
     #[error("Failed to generate hpke configuration response")]
     GenServerHpkeConfigurationResponseFailed(#[source] anyhow::Error),
 
+    #[error("Failed to generate attestation info")]
+    GenAttestationInfoFailed(#[source] anyhow::Error),
+
     #[error("Not a valid OHTTP request")]
     InvalidOHttpRequest(#[source] anyhow::Error),
 
@@ -161,6 +167,12 @@ pub enum TngError {
 
     #[error("Status path not found")]
     StatusPathNotFound,
+
+    #[error("Service not found")]
+    ServiceNotFound,
+
+    #[error("Invalid verification parameters")]
+    InvalidVerifyUpdate(#[source] anyhow::Error),
 }
 
 /// Error response structure
@@ -193,6 +205,7 @@ impl IntoResponse for TngError {
 
             // Not Found / Upstream issues
             TngError::ConnectUpstreamFailed => StatusCode::BAD_GATEWAY,
+            TngError::UpstreamCircuitOpen(..) => StatusCode::SERVICE_UNAVAILABLE,
 
             // Timeouts / Network failures
             TngError::HttpPlainTextForwardError(..) => StatusCode::BAD_GATEWAY,
@@ -233,12 +246,15 @@ impl IntoResponse for TngError {
             | TngError::ClientRequestKeyConfigFailed(..)
             | TngError::ClientSelectHpkeConfigurationFailed(..)
             | TngError::GenServerHpkeConfigurationResponseFailed(..)
+            | TngError::GenAttestationInfoFailed(..)
             | TngError::CreateOHttpClientFailed(..)
             | TngError::RaContextCreationFailed(..)
             | TngError::LoadPrivateKeyFailed(..) => StatusCode::INTERNAL_SERVER_ERROR,
             TngError::InvalidParameter(..) => StatusCode::INTERNAL_SERVER_ERROR,
             TngError::WatchFileFailed(..) => StatusCode::INTERNAL_SERVER_ERROR,
             TngError::StatusPathNotFound => StatusCode::NOT_FOUND,
+            TngError::ServiceNotFound => StatusCode::NOT_FOUND,
+            TngError::InvalidVerifyUpdate(..) => StatusCode::BAD_REQUEST,
             #[cfg(feature = "__egress-common")]
             TngError::SerfCrateError(..) => StatusCode::INTERNAL_SERVER_ERROR,
             TngError::KeyUpdateMessageEncodeError(..)
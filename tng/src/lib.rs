@@ -3,6 +3,12 @@
 
 use shadow_rs::shadow;
 
+#[cfg(all(not(wasm), feature = "__egress-common"))]
+pub mod acceptor;
+#[cfg(not(wasm))]
+pub mod bench;
+#[cfg(all(not(wasm), feature = "__ingress-common"))]
+pub mod client;
 pub mod config;
 #[cfg(not(wasm))]
 mod control_interface;
@@ -19,6 +25,8 @@ mod service;
 mod state;
 #[cfg(not(wasm))]
 pub(crate) mod status;
+#[cfg(all(unix, not(wasm)))]
+mod systemd;
 pub mod tunnel;
 
 shadow!(build);
@@ -30,6 +38,18 @@ pub(crate) const HTTP_REQUEST_USER_AGENT_HEADER: &str =
 pub(crate) const HTTP_RESPONSE_SERVER_HEADER: &str =
     const_format::concatcp!("tng/", crate::build::PKG_VERSION);
 
+#[cfg(all(not(wasm), feature = "__egress-common"))]
+pub use crate::acceptor::TngAcceptor;
+#[cfg(all(not(wasm), feature = "__ingress-common"))]
+pub use crate::client::TngClient;
+#[cfg(all(feature = "metric", not(wasm)))]
+pub use crate::observability::metric::simple_exporter::{
+    MetricValue, SimpleMetric, SimpleMetricExporter, ValueType,
+};
+pub use crate::tunnel::access_log::{
+    subscribe_access_log, AccessLogEvent, AccessLogState, AccessMode, EgressAccessMode,
+    IngressAccessMode,
+};
 pub use crate::tunnel::attestation_result::AttestationResult;
 pub use crate::tunnel::ra_context::RaContext;
 pub use crate::tunnel::stream::{CommonStreamTrait, ContextualStream};
@@ -65,7 +85,10 @@ pub(crate) mod tests {
     use once_cell::sync::OnceCell;
     use scopeguard::defer;
     use serde_json::json;
-    use tokio::select;
+    use tokio::{
+        io::{AsyncRead, AsyncReadExt as _},
+        select,
+    };
     use tokio_util::sync::CancellationToken;
     use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
@@ -137,6 +160,30 @@ pub(crate) mod tests {
         res
     }
 
+    /// Reads the HTTP/2 frames sent by the peer until its SETTINGS frame, and returns the
+    /// SETTINGS_INITIAL_WINDOW_SIZE in it, if set. The connection preface of a client must have
+    /// been consumed.
+    pub async fn read_h2_initial_window_size(
+        stream: &mut (impl AsyncRead + Unpin),
+    ) -> Result<Option<u32>> {
+        loop {
+            let mut header = [0u8; 9];
+            stream.read_exact(&mut header).await?;
+            let mut payload =
+                vec![0u8; u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize];
+            stream.read_exact(&mut payload).await?;
+            // A SETTINGS frame which is not an acknowledgement
+            if header[3] == 0x4 && header[4] & 0x1 == 0 {
+                return Ok(payload
+                    .chunks_exact(6)
+                    .find(|setting| setting[..2] == [0, 0x4])
+                    .map(|setting| {
+                        u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]])
+                    }));
+            }
+        }
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
     async fn test_exit_on_cancel() -> Result<()> {
         let config: TngConfig = serde_json::from_value(json!(
@@ -0,0 +1,330 @@
+//! The dedicated sink of the access logs, configured with `access_log`.
+//!
+//! The access logs are the events of [`ACCESS_LOG_TARGET`], or the structured records of
+//! [`ACCESS_RECORD_TARGET`] in the JSON format. They are appended to a file which is rotated by
+//! size, and/or sent to syslog, regardless of the log level filters of the other logs.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use serde_json::{json, Map, Value};
+use tracing::{Event, Subscriber};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_subscriber::{fmt::MakeWriter as _, layer::Context, Layer};
+use web_time_compat::{SystemTime, SystemTimeExt as _};
+
+use crate::config::observability::access_log::{AccessLogArgs, AccessLogFormat};
+use crate::tunnel::access_log::{ACCESS_LOG_TARGET, ACCESS_RECORD_TARGET};
+
+use super::{
+    file::rfc3339,
+    syslog::{SyslogFormatter, SyslogSeverity, SyslogWriter},
+    EventFields,
+};
+
+/// The `MSGID` of the access logs sent to syslog.
+const SYSLOG_MSG_ID: &str = "access";
+
+/// Appends the access logs to a file and/or sends them to syslog, one line per connection.
+pub struct AccessLogLayer {
+    file: Option<NonBlocking>,
+    syslog: Option<(NonBlocking, SyslogFormatter)>,
+    format: AccessLogFormat,
+    /// Flush the pending lines when the layer is dropped.
+    _guards: Vec<WorkerGuard>,
+}
+
+impl AccessLogLayer {
+    pub fn new(args: &AccessLogArgs) -> Result<Self> {
+        if args.path.is_none() && args.syslog.is_none() {
+            bail!("Either `path` or `syslog` must be set in `access_log`");
+        }
+
+        let mut guards = vec![];
+        let file = match &args.path {
+            Some(path) => {
+                let file = RotatingFile::open(
+                    Path::new(path),
+                    args.max_size_mb.saturating_mul(1024 * 1024),
+                    args.max_files,
+                )
+                .with_context(|| format!("Failed to open access log file {path:?}"))?;
+                let (writer, guard) = tracing_appender::non_blocking(file);
+                guards.push(guard);
+                Some(writer)
+            }
+            None => None,
+        };
+        let syslog = match &args.syslog {
+            Some(syslog_args) => {
+                let (writer, guard) =
+                    tracing_appender::non_blocking(SyslogWriter::connect(&syslog_args.transport)?);
+                guards.push(guard);
+                Some((writer, SyslogFormatter::new(syslog_args)))
+            }
+            None => None,
+        };
+        Ok(Self {
+            file,
+            syslog,
+            format: args.format,
+            _guards: guards,
+        })
+    }
+}
+
+/// Format an access record as a JSON object, e.g.
+///
+/// ```json
+/// {"timestamp":"2025-01-01T00:00:00.000000Z","direction":"ingress","mode":"mapping","state":"closed","downstream":{"remote":"127.0.0.1:54321","local":"127.0.0.1:10001"},"upstream":{"remote":"10.0.0.2:20001","local":"10.0.0.1:54322"},"policy":"tunnel","attested":true,"degraded":false,"session_id":3,"bytes":{"tx":1024,"rx":512},"duration":1500}
+/// ```
+///
+/// Fields unknown in the state of the connection are `null`, e.g. `upstream` if the connection
+/// was not routed.
+fn format_record(fields: EventFields) -> String {
+    let mut fields: Map<String, Value> = fields
+        .fields
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), Value::from(value)))
+        .collect();
+    let mut take = |name: &str| fields.remove(name).unwrap_or(Value::Null);
+
+    let downstream = json!({
+        "remote": take("downstream_remote"),
+        "local": take("downstream_local"),
+    });
+    let upstream = match take("upstream_remote") {
+        Value::Null => Value::Null,
+        remote => json!({"remote": remote, "local": take("upstream_local")}),
+    };
+    let bytes = match (take("tx_bytes"), take("rx_bytes")) {
+        (Value::Null, _) | (_, Value::Null) => Value::Null,
+        (tx, rx) => json!({"tx": tx, "rx": rx}),
+    };
+    let record = json!({
+        "timestamp": rfc3339(SystemTime::get()),
+        "direction": take("direction"),
+        "mode": take("mode"),
+        "state": take("state"),
+        "downstream": downstream,
+        "upstream": upstream,
+        "policy": take("policy"),
+        "attested": take("attested"),
+        "degraded": take("degraded"),
+        "session_id": take("session_id"),
+        "bytes": bytes,
+        "duration": take("duration_ms"),
+    });
+    record.to_string()
+}
+
+impl<S: Subscriber> Layer<S> for AccessLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let target = match self.format {
+            AccessLogFormat::Text => ACCESS_LOG_TARGET,
+            AccessLogFormat::Json => ACCESS_RECORD_TARGET,
+        };
+        if metadata.target() != target {
+            return;
+        }
+
+        let now = SystemTime::get();
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+        let message = match self.format {
+            AccessLogFormat::Text => fields.message.unwrap_or_default(),
+            AccessLogFormat::Json => format_record(fields),
+        };
+
+        // Errors can not be logged here, since the log may be an access log again
+        if let Some(writer) = &self.file {
+            let line = match self.format {
+                AccessLogFormat::Text => {
+                    format!("{} {} {message}\n", rfc3339(now), metadata.level())
+                }
+                AccessLogFormat::Json => format!("{message}\n"),
+            };
+            let _ = writer.make_writer().write_all(line.as_bytes());
+        }
+        if let Some((writer, formatter)) = &self.syslog {
+            let line = formatter.format(
+                SyslogSeverity::from(metadata.level()),
+                now,
+                SYSLOG_MSG_ID,
+                &message,
+            );
+            let _ = writer.make_writer().write_all(line.as_bytes());
+        }
+    }
+}
+
+/// A file which is rotated once it would grow beyond the max size. The rotated files are named
+/// `{path}.1` to `{path}.{max_files}`, from the newest to the oldest.
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_size: u64, max_files: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_owned(),
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.max_files > 0 {
+            // The oldest file is overwritten by the one next to it
+            for index in (1..self.max_files).rev() {
+                match std::fs::rename(self.rotated_path(index), self.rotated_path(index + 1)) {
+                    Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(error)
+                    }
+                    _ => {}
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    use crate::tunnel::access_log::{AccessAccepted, IngressAccessMode};
+
+    use super::*;
+
+    #[test]
+    fn test_json_access_log() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("access.log");
+        let layer = AccessLogLayer::new(&AccessLogArgs {
+            path: Some(path.to_str().context("invalid path")?.to_owned()),
+            max_size_mb: 100,
+            max_files: 5,
+            format: AccessLogFormat::Json,
+            syslog: None,
+        })?;
+
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            let accepted = AccessAccepted::new_ingress(
+                "127.0.0.1:54321".parse()?,
+                "127.0.0.1:10001".parse()?,
+                IngressAccessMode::Mapping,
+            );
+            let mut established = accepted
+                .into_routed("10.0.0.2:20001", true)
+                .into_established(Some("10.0.0.1:54322".parse()?), true, false, Some(3));
+            established.set_transferred(1024, 512);
+            drop(established);
+
+            drop(AccessAccepted::new_ingress(
+                "127.0.0.1:54323".parse()?,
+                "127.0.0.1:10001".parse()?,
+                IngressAccessMode::Mapping,
+            ));
+            Ok::<_, anyhow::Error>(())
+        })?;
+
+        let lines = std::fs::read_to_string(&path)?;
+        let lines: Vec<Value> = lines
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["direction"], "ingress");
+        assert_eq!(lines[0]["mode"], "mapping");
+        assert_eq!(lines[0]["state"], "established");
+        assert_eq!(
+            lines[0]["downstream"],
+            json!({"remote": "127.0.0.1:54321", "local": "127.0.0.1:10001"})
+        );
+        assert_eq!(
+            lines[0]["upstream"],
+            json!({"remote": "10.0.0.2:20001", "local": "10.0.0.1:54322"})
+        );
+        assert_eq!(lines[0]["policy"], "tunnel");
+        assert_eq!(lines[0]["attested"], true);
+        assert_eq!(lines[0]["degraded"], false);
+        assert_eq!(lines[0]["session_id"], 3);
+        assert_eq!(lines[0]["bytes"], Value::Null);
+        assert_eq!(lines[0]["duration"], Value::Null);
+
+        // The completion of the connection
+        assert_eq!(lines[1]["state"], "closed");
+        assert_eq!(lines[1]["session_id"], 3);
+        assert_eq!(lines[1]["bytes"], json!({"tx": 1024, "rx": 512}));
+        assert!(lines[1]["duration"].is_u64());
+
+        assert_eq!(lines[2]["state"], "accepted");
+        assert_eq!(lines[2]["upstream"], Value::Null);
+        assert_eq!(lines[2]["policy"], Value::Null);
+        assert_eq!(lines[2]["session_id"], Value::Null);
+        assert_eq!(lines[2]["bytes"], Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotating_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("access.log");
+        let mut file = RotatingFile::open(&path, 10, 2)?;
+        for line in ["line-1\n", "line-2\n", "line-3\n", "line-4\n"] {
+            file.write_all(line.as_bytes())?;
+        }
+
+        // Each line exceeds the max size together with the previous one, and only 2 rotated files
+        // are kept
+        assert_eq!(std::fs::read_to_string(&path)?, "line-4\n");
+        assert_eq!(std::fs::read_to_string(file.rotated_path(1))?, "line-3\n");
+        assert_eq!(std::fs::read_to_string(file.rotated_path(2))?, "line-2\n");
+        assert!(!file.rotated_path(3).exists());
+
+        let mut file = RotatingFile::open(&path, 10, 0)?;
+        file.write_all(b"line-5\n")?;
+        assert_eq!(std::fs::read_to_string(&path)?, "line-5\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,467 @@
+//! The dedicated audit log of the attestation events, configured with `audit_log`.
+//!
+//! The audit events are the events of [`AUDIT_LOG_TARGET`], e.g. the verification of the
+//! attestation token of a peer. They are appended to a file as one JSON object per line,
+//! regardless of the log level filters of the other logs. The file is never rotated, and the
+//! entries can be chained with HMAC-SHA256, so that removing or altering an entry breaks the
+//! chain of the entries after it.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read as _, Seek as _, SeekFrom, Write as _};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use anyhow::{bail, Context as _, Result};
+use hmac::{Hmac, Mac as _};
+use serde_json::{Map, Value};
+use sha2::Sha256;
+use tracing::{span, Event, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+use web_time_compat::{SystemTime, SystemTimeExt as _};
+
+use crate::config::observability::audit_log::AuditLogArgs;
+use crate::tunnel::audit_log::AUDIT_LOG_TARGET;
+
+use super::{file::rfc3339, EventFields};
+
+/// Appends the attestation events to a file, one JSON object per line.
+pub struct AuditLogLayer {
+    /// Written to synchronously and in order, since an entry which is lost or reordered would
+    /// break the chain.
+    chain: Mutex<AuditChain>,
+}
+
+/// The file of the audit log, and the last entry appended to it.
+struct AuditChain {
+    file: File,
+    hmac_key: Option<Vec<u8>>,
+    seq: u64,
+    /// The HMAC of the last entry, or empty if there is none.
+    last_hmac: String,
+    /// Whether the last line may be incomplete, e.g. since writing it failed, in which case it is
+    /// terminated before the next entry is appended.
+    torn: bool,
+}
+
+/// The number of audit entries which failed to be written, since the failures can not be logged
+/// from within the logging.
+static WRITE_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the number of audit entries which failed to be written since the process started.
+pub fn write_failures() -> u64 {
+    WRITE_FAILURES.load(Ordering::Relaxed)
+}
+
+/// The size of the blocks in which an existing audit log is read backwards, to find its last
+/// entry without reading the whole file.
+const TAIL_BLOCK_SIZE: u64 = 8 * 1024;
+
+/// The fields of a span, recorded when the span is created, which are added to the context of the
+/// audit events within it, e.g. the address of the peer which the `serve` span is created for.
+struct SpanFields(Map<String, Value>);
+
+impl AuditLogLayer {
+    pub fn new(args: &AuditLogArgs) -> Result<Self> {
+        let hmac_key = match &args.hmac_key_path {
+            Some(path) => {
+                let key = std::fs::read(path)
+                    .with_context(|| format!("Failed to read audit log HMAC key {path:?}"))?;
+                let key = key.trim_ascii_end().to_vec();
+                if key.is_empty() {
+                    bail!("The audit log HMAC key {path:?} is empty");
+                }
+                Some(key)
+            }
+            None => None,
+        };
+
+        let path = &args.path;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log file {path:?}"))?;
+        let tail = last_entry(&mut file)
+            .with_context(|| format!("Failed to resume audit log file {path:?}"))?;
+        if tail.skipped_lines > 0 {
+            tracing::warn!(
+                path,
+                skipped_lines = tail.skipped_lines,
+                "The last lines of the audit log are not valid entries, e.g. since TNG was killed while writing them, the chain continues from the entry before them"
+            );
+        }
+
+        Ok(Self {
+            chain: Mutex::new(AuditChain {
+                file,
+                hmac_key,
+                seq: tail.seq,
+                last_hmac: tail.hmac,
+                torn: !tail.terminated,
+            }),
+        })
+    }
+}
+
+/// The end of an existing audit log, which the chain continues from.
+#[derive(Debug, Default, PartialEq)]
+struct AuditTail {
+    /// The `seq` of the last valid entry, or 0 if there is none.
+    seq: u64,
+    /// The `hmac` of the last valid entry, or empty if there is none or it is not chained.
+    hmac: String,
+    /// The number of lines after the last valid entry which are not valid entries.
+    skipped_lines: usize,
+    /// Whether the file is empty or ends with a line break.
+    terminated: bool,
+}
+
+/// Finds the last valid entry of an existing audit log, reading the file backwards from its end.
+/// The lines after it, e.g. a line torn since TNG was killed while writing it, are skipped.
+fn last_entry(file: &mut File) -> Result<AuditTail> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut pos = len;
+    let mut tail = AuditTail {
+        terminated: true,
+        ..Default::default()
+    };
+    // The part of the file from `pos` which is not parsed yet
+    let mut pending = Vec::new();
+    loop {
+        while let Some(line) = pop_line(&mut pending, pos == 0) {
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_slice::<Value>(&line).unwrap_or_default();
+            match entry["seq"].as_u64() {
+                Some(seq) => {
+                    tail.seq = seq;
+                    tail.hmac = entry["hmac"].as_str().unwrap_or_default().to_owned();
+                    return Ok(tail);
+                }
+                None => tail.skipped_lines += 1,
+            }
+        }
+        if pos == 0 {
+            return Ok(tail);
+        }
+
+        let read = pos.min(TAIL_BLOCK_SIZE);
+        pos -= read;
+        let mut block = vec![0; read as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut block)?;
+        if pos + read == len {
+            tail.terminated = block.last() == Some(&b'\n');
+        }
+        block.extend_from_slice(&pending);
+        pending = block;
+    }
+}
+
+/// Removes the last line from the end of a part of the file. The bytes after the last line break
+/// are only a complete line if they follow it, or if the part starts at the start of the file.
+fn pop_line(pending: &mut Vec<u8>, at_start: bool) -> Option<Vec<u8>> {
+    match pending.iter().rposition(|&byte| byte == b'\n') {
+        Some(index) => {
+            let line = pending.split_off(index + 1);
+            pending.pop();
+            Some(line)
+        }
+        None if at_start && !pending.is_empty() => Some(std::mem::take(pending)),
+        None => None,
+    }
+}
+
+impl AuditChain {
+    fn append(&mut self, fields: Map<String, Value>) -> std::io::Result<()> {
+        self.seq += 1;
+        // The sequence number comes first, so that a gap is obvious when reading the file
+        let mut entry = Map::new();
+        entry.insert("seq".into(), self.seq.into());
+        entry.extend(fields);
+
+        let mut hmac = None;
+        let mut line = match &self.hmac_key {
+            Some(key) => {
+                entry.insert("prev_hmac".into(), self.last_hmac.clone().into());
+                let line = Value::Object(entry).to_string();
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take keys of any size");
+                mac.update(line.as_bytes());
+                let entry_hmac = hex::encode(mac.finalize().into_bytes());
+
+                // The HMAC covers the line as it is without the `hmac` field, which is appended
+                // as the last field
+                let line = format!(
+                    r#"{},"hmac":"{entry_hmac}"}}"#,
+                    line.strip_suffix('}').unwrap_or(&line)
+                );
+                hmac = Some(entry_hmac);
+                line
+            }
+            None => Value::Object(entry).to_string(),
+        };
+        line.push('\n');
+        if self.torn {
+            line.insert(0, '\n');
+        }
+
+        // The entry is on the disk once it is appended, so that it is not lost on a crash. If it
+        // is not, the next entry is chained to the entry before it, and the gap in `seq` shows
+        // the entry lost.
+        self.file
+            .write_all(line.as_bytes())
+            .and_then(|()| self.file.sync_data())
+            .inspect_err(|_| self.torn = true)?;
+        self.torn = false;
+        if let Some(hmac) = hmac {
+            self.last_hmac = hmac;
+        }
+        Ok(())
+    }
+}
+
+impl<S> Layer<S> for AuditLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = EventFields::default();
+        attrs.record(&mut fields);
+        if fields.fields.is_empty() {
+            return;
+        }
+        let fields = fields
+            .fields
+            .into_iter()
+            .map(|(name, value)| (format!("{}.{name}", span.name()), Value::from(value)))
+            .collect();
+        span.extensions_mut().insert(SpanFields(fields));
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().target() != AUDIT_LOG_TARGET {
+            return;
+        }
+
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+
+        let mut entry = Map::new();
+        entry.insert("timestamp".into(), rfc3339(SystemTime::get()).into());
+        entry.extend(
+            fields
+                .fields
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), Value::from(value))),
+        );
+        let mut context = Map::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>() {
+                    context.extend(fields.clone());
+                }
+            }
+        }
+        entry.insert("context".into(), context.into());
+
+        // Errors can not be logged here, since the log may be an audit event again. They are
+        // counted instead, and exported as a metric.
+        let written = match self.chain.lock() {
+            Ok(mut chain) => chain.append(entry).is_ok(),
+            Err(_) => false,
+        };
+        if !written {
+            WRITE_FAILURES.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    use crate::tunnel::audit_log::AttestationAudit;
+    use crate::tunnel::provider::ProviderType;
+
+    use super::*;
+
+    /// Checks the chain of the entries of an audit log, returning the entries.
+    fn verify_chain(content: &str, key: &[u8]) -> Result<Vec<Value>> {
+        let mut prev_hmac = String::new();
+        let mut entries = vec![];
+        for line in content.lines() {
+            let entry: Value = serde_json::from_str(line)?;
+            let hmac = entry["hmac"].as_str().context("no hmac")?;
+            if entry["prev_hmac"] != prev_hmac.as_str() {
+                bail!("The chain is broken at seq {}", entry["seq"]);
+            }
+            let unsigned = line
+                .strip_suffix(&format!(r#","hmac":"{hmac}"}}"#))
+                .context("hmac is not the last field")?;
+            let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+            mac.update(format!("{unsigned}}}").as_bytes());
+            mac.verify_slice(&hex::decode(hmac)?)
+                .map_err(|_| anyhow::anyhow!("The hmac of seq {} is invalid", entry["seq"]))?;
+            prev_hmac = hmac.to_owned();
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    #[test]
+    fn test_audit_log_hmac_chain() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("audit.log");
+        let key_path = dir.path().join("audit.key");
+        std::fs::write(&key_path, "secret\n")?;
+        let args = AuditLogArgs {
+            path: path.to_str().context("invalid path")?.to_owned(),
+            hmac_key_path: Some(key_path.to_str().context("invalid path")?.to_owned()),
+        };
+        let audit = AttestationAudit {
+            model: "passport",
+            provider: ProviderType::Coco,
+            policy_ids: vec!["default".to_owned()],
+        };
+        let claims = serde_json::json!({"iat": 1}).as_object().unwrap().clone();
+
+        let layer = AuditLogLayer::new(&args)?;
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            tracing::info_span!("serve", client = "10.0.0.1:40000").in_scope(|| {
+                audit.emit(Some(&claims), &Ok(()));
+                audit.emit(None, &Err(rats_cert::errors::Error::RuntimeDataMismatch));
+            });
+            tracing::info!("Not an audit event");
+        });
+        // The chain continues from the last entry once the log is opened again
+        let layer = AuditLogLayer::new(&args)?;
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            audit.emit(Some(&claims), &Ok(()));
+        });
+
+        let content = std::fs::read_to_string(&path)?;
+        let entries = verify_chain(&content, b"secret")?;
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry["seq"].as_u64())
+                .collect::<Vec<_>>(),
+            [Some(1), Some(2), Some(3)]
+        );
+        assert_eq!(entries[0]["outcome"], "accepted");
+        assert_eq!(entries[0]["provider"], "coco");
+        assert_eq!(entries[0]["policy_ids"], "default");
+        assert!(entries[0]["claims_digest"]
+            .as_str()
+            .is_some_and(|digest| digest.starts_with("sha256:")));
+        assert_eq!(
+            entries[0]["context"],
+            serde_json::json!({"serve.client": "10.0.0.1:40000"})
+        );
+        assert_eq!(entries[1]["outcome"], "rejected");
+        assert_eq!(entries[1]["error"], "Runtime data mismatch");
+
+        // Altering an entry breaks the chain
+        let tampered = content.replacen("rejected", "accepted", 1);
+        assert!(verify_chain(&tampered, b"secret").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_log_last_entry() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("audit.log");
+        let tail = |content: &str| -> Result<AuditTail> {
+            std::fs::write(&path, content)?;
+            last_entry(&mut File::open(&path)?)
+        };
+
+        assert_eq!(
+            tail("")?,
+            AuditTail {
+                terminated: true,
+                ..Default::default()
+            }
+        );
+
+        // The entries span several blocks
+        let content = (1..=1000)
+            .map(|seq| format!(r#"{{"seq":{seq},"hmac":"{seq:064x}"}}"#) + "\n")
+            .collect::<String>();
+        assert!(content.len() as u64 > 2 * TAIL_BLOCK_SIZE);
+        assert_eq!(
+            tail(&content)?,
+            AuditTail {
+                seq: 1000,
+                hmac: format!("{:064x}", 1000),
+                skipped_lines: 0,
+                terminated: true,
+            }
+        );
+
+        // A line torn by a crash is skipped
+        assert_eq!(
+            tail(&format!(r#"{content}{{"seq":1001,"hm"#))?,
+            AuditTail {
+                seq: 1000,
+                hmac: format!("{:064x}", 1000),
+                skipped_lines: 1,
+                terminated: false,
+            }
+        );
+        assert_eq!(
+            tail("{\"seq\"\n\n")?,
+            AuditTail {
+                skipped_lines: 1,
+                terminated: true,
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_log_resume_after_torn_line() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("audit.log");
+        let key_path = dir.path().join("audit.key");
+        std::fs::write(&key_path, "secret")?;
+        let args = AuditLogArgs {
+            path: path.to_str().context("invalid path")?.to_owned(),
+            hmac_key_path: Some(key_path.to_str().context("invalid path")?.to_owned()),
+        };
+        let audit = AttestationAudit {
+            model: "passport",
+            provider: ProviderType::Coco,
+            policy_ids: vec!["default".to_owned()],
+        };
+
+        let layer = AuditLogLayer::new(&args)?;
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            audit.emit(None, &Ok(()));
+        });
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(br#"{"seq":2,"timest"#)?;
+
+        let layer = AuditLogLayer::new(&args)?;
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            audit.emit(None, &Ok(()));
+        });
+
+        // The torn line is kept, and the chain continues from the entry before it
+        let content = std::fs::read_to_string(&path)?;
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], r#"{"seq":2,"timest"#);
+        let entries = verify_chain(&format!("{}\n{}\n", lines[0], lines[2]), b"secret")?;
+        assert_eq!(entries[1]["seq"], 2);
+        Ok(())
+    }
+}
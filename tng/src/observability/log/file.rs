@@ -0,0 +1,136 @@
+use std::fs::OpenOptions;
+use std::io::Write as _;
+
+use anyhow::{Context as _, Result};
+use serde_json::{Map, Value};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_subscriber::{fmt::MakeWriter as _, layer::Context, registry::LookupSpan, Layer};
+use web_time_compat::{SystemTime, SystemTimeExt as _};
+
+use super::EventFields;
+
+/// Appends every event to a file as a JSON object per line, e.g.
+///
+/// ```json
+/// {"timestamp":"2025-01-01T00:00:00.000000Z","level":"INFO","target":"tng::runtime","spans":["ingress"],"message":"...","fields":{"id":0}}
+/// ```
+pub struct JsonFileLayer {
+    writer: NonBlocking,
+    max_level: Level,
+    /// Flushes the pending lines when the layer is dropped.
+    _guard: WorkerGuard,
+}
+
+impl JsonFileLayer {
+    pub fn open(path: &str, max_level: Level) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open log file {path:?}"))?;
+        let (writer, guard) = tracing_appender::non_blocking(file);
+        Ok(Self {
+            writer,
+            max_level,
+            _guard: guard,
+        })
+    }
+}
+
+impl<S> Layer<S> for JsonFileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > self.max_level {
+            return;
+        }
+
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+
+        let mut record = Map::new();
+        record.insert("timestamp".into(), rfc3339(SystemTime::get()).into());
+        record.insert("level".into(), metadata.level().as_str().into());
+        record.insert("target".into(), metadata.target().into());
+        if let Some(scope) = ctx.event_scope(event) {
+            record.insert(
+                "spans".into(),
+                scope
+                    .from_root()
+                    .map(|span| Value::from(span.name()))
+                    .collect(),
+            );
+        }
+        if let Some(message) = fields.message {
+            record.insert("message".into(), message.into());
+        }
+        record.insert(
+            "fields".into(),
+            fields
+                .fields
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), Value::from(value)))
+                .collect::<Map<_, _>>()
+                .into(),
+        );
+
+        let mut line = Value::Object(record).to_string();
+        line.push('\n');
+        // Errors can not be logged here, since the log would be written to this file again
+        let _ = self.writer.make_writer().write_all(line.as_bytes());
+    }
+}
+
+/// Format the time in RFC 3339 with microseconds, in UTC.
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let time = time::OffsetDateTime::from(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        time.year(),
+        u8::from(time.month()),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second(),
+        time.microsecond()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    use super::*;
+
+    #[test]
+    fn test_json_file_layer() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("tng.json");
+        let layer = JsonFileLayer::open(path.to_str().context("invalid path")?, Level::INFO)?;
+
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            tracing::info_span!("ingress").in_scope(|| {
+                tracing::info!(id = 1, dst = "127.0.0.1:80", "New connection");
+                tracing::debug!("Filtered out by the level");
+            })
+        });
+
+        let lines = std::fs::read_to_string(&path)?;
+        let lines: Vec<Value> = lines
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["spans"], serde_json::json!(["ingress"]));
+        assert_eq!(lines[0]["message"], "New connection");
+        assert_eq!(
+            lines[0]["fields"],
+            serde_json::json!({"id": 1, "dst": "127.0.0.1:80"})
+        );
+        Ok(())
+    }
+}
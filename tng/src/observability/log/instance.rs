@@ -0,0 +1,29 @@
+use std::str::FromStr as _;
+
+use anyhow::{Context as _, Result};
+use tracing::Level;
+
+use crate::{
+    config::observability::log::{FileLogExporterConfig, LogArgs, LogExporterType},
+    runtime::TracingLayer,
+};
+
+use super::{file::JsonFileLayer, otlp::OtlpLogLayer};
+
+impl LogArgs {
+    /// The most verbose level of the exported logs.
+    pub fn max_level(&self) -> Result<Level> {
+        Level::from_str(&self.level).with_context(|| format!("Invalid log level `{}`", self.level))
+    }
+}
+
+impl LogExporterType {
+    pub fn instantiate(&self, max_level: Level) -> Result<TracingLayer> {
+        Ok(match self {
+            LogExporterType::Oltp(config) => Box::new(OtlpLogLayer::new(config, max_level)?),
+            LogExporterType::File(FileLogExporterConfig { path }) => {
+                Box::new(JsonFileLayer::open(path, max_level)?)
+            }
+        })
+    }
+}
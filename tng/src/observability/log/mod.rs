@@ -0,0 +1,74 @@
+//! Exporting of the logs to destinations other than stdout, configured with `log.exporters`.
+//!
+//! Each exporter is a tracing layer, which is added to the subscriber when the instance is
+//! created, in the same way as the trace exporters.
+
+pub mod access_log;
+pub mod audit_log;
+pub mod file;
+pub mod instance;
+pub mod otlp;
+pub mod syslog;
+
+/// Fields of an event, collected in the order they are recorded. The `message` field is taken
+/// out as the body of the log.
+#[derive(Default)]
+struct EventFields {
+    message: Option<String>,
+    fields: Vec<(&'static str, FieldValue)>,
+}
+
+enum FieldValue {
+    Str(String),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Bool(bool),
+}
+
+impl From<FieldValue> for serde_json::Value {
+    fn from(value: FieldValue) -> Self {
+        match value {
+            FieldValue::Str(value) => value.into(),
+            FieldValue::I64(value) => value.into(),
+            FieldValue::U64(value) => value.into(),
+            FieldValue::F64(value) => value.into(),
+            FieldValue::Bool(value) => value.into(),
+        }
+    }
+}
+
+impl EventFields {
+    fn record(&mut self, field: &tracing::field::Field, value: FieldValue) {
+        match value {
+            FieldValue::Str(message) if field.name() == "message" => self.message = Some(message),
+            value => self.fields.push((field.name(), value)),
+        }
+    }
+}
+
+impl tracing::field::Visit for EventFields {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.record(field, FieldValue::Str(value.to_owned()))
+    }
+
+    fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+        self.record(field, FieldValue::I64(value))
+    }
+
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        self.record(field, FieldValue::U64(value))
+    }
+
+    fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+        self.record(field, FieldValue::F64(value))
+    }
+
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        self.record(field, FieldValue::Bool(value))
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.record(field, FieldValue::Str(format!("{value:?}")))
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use opentelemetry::logs::{AnyValue, LogRecord as _, Logger as _, LoggerProvider as _, Severity};
+use opentelemetry_otlp::{WithExportConfig as _, WithHttpConfig as _, WithTonicConfig as _};
+use opentelemetry_sdk::logs::{SdkLogger, SdkLoggerProvider};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, Layer};
+use web_time_compat::{SystemTime, SystemTimeExt as _};
+
+use crate::config::observability::{log::OltpLogExporterConfig, OltpExporterProtocol};
+
+use super::{EventFields, FieldValue};
+
+/// Events from the crates used by the exporter itself are not exported, otherwise exporting a log
+/// would produce more logs to be exported.
+const SKIPPED_TARGETS: &[&str] = &["opentelemetry", "tonic", "tower", "hyper", "h2", "reqwest"];
+
+/// Forwards every event as an OpenTelemetry log record to an OTLP endpoint.
+pub struct OtlpLogLayer {
+    logger: SdkLogger,
+    max_level: Level,
+    /// Kept alive, since the batched records are exported by it.
+    _provider: SdkLoggerProvider,
+}
+
+impl OtlpLogLayer {
+    pub fn new(config: &OltpLogExporterConfig, max_level: Level) -> Result<Self> {
+        let OltpLogExporterConfig {
+            protocol,
+            endpoint,
+            headers,
+        } = config;
+        let log_exporter = match protocol {
+            OltpExporterProtocol::HttpProtobuf | OltpExporterProtocol::HttpJson => {
+                let mut builder = opentelemetry_otlp::LogExporter::builder()
+                    .with_http()
+                    .with_endpoint(endpoint)
+                    .with_protocol(match protocol {
+                        OltpExporterProtocol::HttpProtobuf => {
+                            opentelemetry_otlp::Protocol::HttpBinary
+                        }
+                        OltpExporterProtocol::HttpJson => opentelemetry_otlp::Protocol::HttpJson,
+                        OltpExporterProtocol::Grpc => unreachable!(),
+                    })
+                    .with_timeout(Duration::from_secs(5));
+                if let Some(headers) = headers {
+                    builder = builder.with_headers(headers.clone())
+                }
+                builder
+                    .build()
+                    .context("Failed to create OTLP Http exporter")?
+            }
+            OltpExporterProtocol::Grpc => {
+                let mut builder = opentelemetry_otlp::LogExporter::builder()
+                    .with_tonic()
+                    .with_endpoint(endpoint)
+                    .with_protocol(opentelemetry_otlp::Protocol::Grpc)
+                    .with_compression(opentelemetry_otlp::Compression::Gzip)
+                    .with_timeout(Duration::from_secs(5));
+                if let Some(headers) = headers {
+                    builder = builder.with_metadata(tonic::metadata::MetadataMap::from_headers(
+                        http::HeaderMap::try_from(headers)
+                            .context("Failed to parse to HTTP headers")?,
+                    ))
+                }
+                builder
+                    .build()
+                    .context("Failed to create OTLP gRPC exporter")?
+            }
+        };
+
+        let batch =
+            opentelemetry_sdk::logs::log_processor_with_async_runtime::BatchLogProcessor::builder(
+                log_exporter,
+                opentelemetry_sdk::runtime::Tokio,
+            )
+            .build();
+        let provider = SdkLoggerProvider::builder()
+            .with_log_processor(batch)
+            .with_resource(crate::observability::otlp_resource())
+            .build();
+
+        Ok(Self {
+            logger: provider.logger("tng"),
+            max_level,
+            _provider: provider,
+        })
+    }
+}
+
+impl<S: Subscriber> Layer<S> for OtlpLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > self.max_level
+            || SKIPPED_TARGETS
+                .iter()
+                .any(|target| metadata.target().starts_with(target))
+        {
+            return;
+        }
+
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+
+        let mut record = self.logger.create_log_record();
+        record.set_timestamp(SystemTime::get());
+        record.set_severity_number(match *metadata.level() {
+            Level::ERROR => Severity::Error,
+            Level::WARN => Severity::Warn,
+            Level::INFO => Severity::Info,
+            Level::DEBUG => Severity::Debug,
+            Level::TRACE => Severity::Trace,
+        });
+        record.set_severity_text(metadata.level().as_str());
+        record.set_target(metadata.target().to_owned());
+        if let Some(message) = fields.message {
+            record.set_body(AnyValue::from(message));
+        }
+        for (name, value) in fields.fields {
+            let value = match value {
+                FieldValue::Str(value) => AnyValue::from(value),
+                FieldValue::I64(value) => AnyValue::from(value),
+                // OTLP has no unsigned integers
+                FieldValue::U64(value) => match i64::try_from(value) {
+                    Ok(value) => AnyValue::from(value),
+                    Err(_) => AnyValue::from(value.to_string()),
+                },
+                FieldValue::F64(value) => AnyValue::from(value),
+                FieldValue::Bool(value) => AnyValue::from(value),
+            };
+            record.add_attribute(name, value);
+        }
+        self.logger.emit(record);
+    }
+}
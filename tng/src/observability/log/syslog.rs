@@ -0,0 +1,180 @@
+//! Sending of logs to syslog in the RFC 5424 format, over UDP or a unix datagram socket.
+
+use std::io::Write;
+use std::net::{SocketAddr, ToSocketAddrs as _, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+use anyhow::{Context as _, Result};
+use web_time_compat::SystemTime;
+
+use crate::config::observability::syslog::{SyslogArgs, SyslogTransport};
+
+use super::file::rfc3339;
+
+/// The severity of a syslog message, defined in RFC 5424.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyslogSeverity {
+    Error = 3,
+    Warning = 4,
+    Informational = 6,
+    Debug = 7,
+}
+
+impl From<&tracing::Level> for SyslogSeverity {
+    fn from(level: &tracing::Level) -> Self {
+        match *level {
+            tracing::Level::ERROR => SyslogSeverity::Error,
+            tracing::Level::WARN => SyslogSeverity::Warning,
+            tracing::Level::INFO => SyslogSeverity::Informational,
+            _ => SyslogSeverity::Debug,
+        }
+    }
+}
+
+/// Formats the messages with the RFC 5424 header of this host.
+pub struct SyslogFormatter {
+    facility: u8,
+    hostname: String,
+    app_name: String,
+    proc_id: u32,
+}
+
+impl SyslogFormatter {
+    pub fn new(args: &SyslogArgs) -> Self {
+        Self {
+            facility: args.facility.code(),
+            hostname: hostname().unwrap_or_else(|| "-".to_owned()),
+            app_name: args.app_name.clone(),
+            proc_id: std::process::id(),
+        }
+    }
+
+    /// Format a message, e.g. `<30>1 2025-01-01T00:00:00.000000Z host tng 1234 access - message`.
+    /// Structured data is not used, so the message is the whole body of the log.
+    pub fn format(
+        &self,
+        severity: SyslogSeverity,
+        time: SystemTime,
+        msg_id: &str,
+        message: &str,
+    ) -> String {
+        format!(
+            "<{}>1 {} {} {} {} {} - {}",
+            u16::from(self.facility) * 8 + severity as u16,
+            rfc3339(time),
+            self.hostname,
+            self.app_name,
+            self.proc_id,
+            msg_id,
+            message
+        )
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    nix::unistd::gethostname().ok()?.into_string().ok()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    None
+}
+
+/// Sends every write as a single datagram to the syslog server.
+pub enum SyslogWriter {
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+}
+
+impl SyslogWriter {
+    pub fn connect(transport: &SyslogTransport) -> Result<Self> {
+        match transport {
+            SyslogTransport::Udp { address } => {
+                let server = address
+                    .to_socket_addrs()
+                    .ok()
+                    .and_then(|mut addrs| addrs.next())
+                    .with_context(|| format!("Failed to resolve syslog server {address}"))?;
+                let local: SocketAddr = match server {
+                    SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+                    SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+                };
+                let socket =
+                    UdpSocket::bind(local).context("Failed to bind UDP socket for syslog")?;
+                socket
+                    .connect(server)
+                    .with_context(|| format!("Failed to connect to syslog server {address}"))?;
+                Ok(SyslogWriter::Udp(socket))
+            }
+            #[cfg(unix)]
+            SyslogTransport::Unix { path } => {
+                let socket =
+                    UnixDatagram::unbound().context("Failed to create unix socket for syslog")?;
+                socket
+                    .connect(path)
+                    .with_context(|| format!("Failed to connect to syslog socket {path}"))?;
+                Ok(SyslogWriter::Unix(socket))
+            }
+            #[cfg(not(unix))]
+            SyslogTransport::Unix { .. } => {
+                anyhow::bail!("The unix transport of syslog is not supported on this platform")
+            }
+        }
+    }
+}
+
+impl Write for SyslogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            SyslogWriter::Udp(socket) => socket.send(buf),
+            #[cfg(unix)]
+            SyslogWriter::Unix(socket) => socket.send(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::config::observability::syslog::SyslogFacility;
+
+    use super::*;
+
+    #[test]
+    fn test_send_to_udp() -> Result<()> {
+        let server = UdpSocket::bind("127.0.0.1:0")?;
+        server.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let args = SyslogArgs {
+            transport: SyslogTransport::Udp {
+                address: server.local_addr()?.to_string(),
+            },
+            facility: SyslogFacility::Local0,
+            app_name: "tng".to_owned(),
+        };
+
+        let formatter = SyslogFormatter::new(&args);
+        let message = formatter.format(
+            SyslogSeverity::Informational,
+            SystemTime::UNIX_EPOCH,
+            "access",
+            "hello",
+        );
+        SyslogWriter::connect(&args.transport)?.write_all(message.as_bytes())?;
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf)?;
+        let received = std::str::from_utf8(&buf[..len])?;
+        // local0 (16) * 8 + informational (6)
+        assert!(received.starts_with("<134>1 1970-01-01T00:00:00.000000Z "));
+        assert!(received.ends_with(&format!(" tng {} access - hello", std::process::id())));
+        Ok(())
+    }
+}
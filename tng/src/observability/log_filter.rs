@@ -0,0 +1,111 @@
+//! Adjustment of the log filter of a running instance, so that e.g. `tng=debug,rats_cert=trace`
+//! can be switched on while chasing an incident, and reverted afterwards without a restart.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context as _, Result};
+use tracing_subscriber::{reload, EnvFilter};
+
+type SetFilter = dyn Fn(EnvFilter) -> Result<()> + Send + Sync;
+
+/// A handle to change the filter of the logs printed by TNG.
+///
+/// This struct is free to be cloned and used anywhere.
+#[derive(Clone)]
+pub struct LogFilterHandle {
+    inner: Arc<LogFilterInner>,
+}
+
+struct LogFilterInner {
+    set_filter: Box<SetFilter>,
+    /// The filter at startup, which is restored by [`LogFilterHandle::reset`].
+    initial: String,
+    current: Mutex<String>,
+}
+
+impl LogFilterHandle {
+    /// Create a handle from the reload handle of the filter, which is currently set to `initial`.
+    pub fn new<S: 'static>(
+        handle: reload::Handle<EnvFilter, S>,
+        initial: impl Into<String>,
+    ) -> Self {
+        let initial = initial.into();
+        Self {
+            inner: Arc::new(LogFilterInner {
+                set_filter: Box::new(move |filter| {
+                    handle
+                        .reload(filter)
+                        .context("Failed to reload the log filter")
+                }),
+                current: Mutex::new(initial.clone()),
+                initial,
+            }),
+        }
+    }
+
+    /// The directives of the current filter.
+    pub fn current(&self) -> String {
+        self.inner
+            .current
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .clone()
+    }
+
+    /// Replace the filter with the directives, in the same syntax as `RUST_LOG`.
+    pub fn set(&self, directives: &str) -> Result<()> {
+        let filter = EnvFilter::try_new(directives)
+            .with_context(|| format!("Invalid log filter `{directives}`"))?;
+        let mut current = self.inner.current.lock().unwrap_or_else(|p| p.into_inner());
+        (self.inner.set_filter)(filter)?;
+        *current = directives.to_owned();
+        Ok(())
+    }
+
+    /// Restore the filter at startup.
+    pub fn reset(&self) -> Result<()> {
+        self.set(&self.inner.initial)
+    }
+}
+
+impl fmt::Debug for LogFilterHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogFilterHandle")
+            .field("initial", &self.inner.initial)
+            .field("current", &self.current())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt as _;
+    use tracing_subscriber::Layer as _;
+
+    use super::*;
+
+    #[test]
+    fn test_set_and_reset_log_filter() -> Result<()> {
+        let (filter, reload_handle) = reload::Layer::new(EnvFilter::new("info"));
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_subscriber::fmt::layer().with_filter(filter));
+        let handle = LogFilterHandle::new(reload_handle, "info");
+
+        tracing::subscriber::with_default(subscriber, || -> Result<()> {
+            assert!(!tracing::enabled!(tracing::Level::DEBUG));
+
+            handle.set("info,tng=debug")?;
+            assert_eq!(handle.current(), "info,tng=debug");
+            assert!(tracing::enabled!(tracing::Level::DEBUG));
+
+            assert!(handle.set("tng=not_a_level").is_err());
+            assert_eq!(handle.current(), "info,tng=debug");
+
+            handle.reset()?;
+            assert_eq!(handle.current(), "info");
+            assert!(!tracing::enabled!(tracing::Level::DEBUG));
+            Ok(())
+        })
+    }
+}
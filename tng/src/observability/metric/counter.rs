@@ -37,6 +37,18 @@ impl<T> AttributedCounter<opentelemetry::metrics::UpDownCounter<T>, T> {
     }
 }
 
+impl<T> AttributedCounter<opentelemetry::metrics::Gauge<T>, T> {
+    pub fn record(&self, value: T) {
+        self.inner.record(value, &self.cached_kvs);
+    }
+}
+
+impl<T> AttributedCounter<opentelemetry::metrics::Histogram<T>, T> {
+    pub fn record(&self, value: T) {
+        self.inner.record(value, &self.cached_kvs);
+    }
+}
+
 pub trait WithAttributes<T> {
     fn with_attributes(
         self,
@@ -81,3 +93,39 @@ impl<T> WithAttributes<T> for opentelemetry::metrics::UpDownCounter<T> {
         }
     }
 }
+
+impl<T> WithAttributes<T> for opentelemetry::metrics::Gauge<T> {
+    fn with_attributes(
+        self,
+        attributes: Arc<IndexMap<String, String>>,
+    ) -> AttributedCounter<Self, T> {
+        let cached_kvs: Vec<KeyValue> = attributes
+            .iter()
+            .map(|att| KeyValue::new(att.0.clone(), att.1.clone()))
+            .collect_vec();
+        AttributedCounter::<Self, T> {
+            inner: self,
+            attributes,
+            cached_kvs: Arc::new(cached_kvs),
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<T> WithAttributes<T> for opentelemetry::metrics::Histogram<T> {
+    fn with_attributes(
+        self,
+        attributes: Arc<IndexMap<String, String>>,
+    ) -> AttributedCounter<Self, T> {
+        let cached_kvs: Vec<KeyValue> = attributes
+            .iter()
+            .map(|att| KeyValue::new(att.0.clone(), att.1.clone()))
+            .collect_vec();
+        AttributedCounter::<Self, T> {
+            inner: self,
+            attributes,
+            cached_kvs: Arc::new(cached_kvs),
+            _marker: Default::default(),
+        }
+    }
+}
@@ -17,10 +17,12 @@ use crate::{
 impl MetricExporterType {
     pub fn instantiate(&self) -> Result<MetricExporterInstance> {
         match self {
-            MetricExporterType::Stdout { step } => Ok(MetricExporterInstance::Simple(
-                *step,
-                Arc::new(StdoutExporter {}),
-            )),
+            MetricExporterType::Stdout { step, format, path } => {
+                Ok(MetricExporterInstance::Simple(
+                    *step,
+                    Arc::new(StdoutExporter::new(*format, path.as_deref())?),
+                ))
+            }
             MetricExporterType::Falcon(falcon_config) => {
                 let falcon_exporter =
                     crate::observability::metric::simple_exporter::falcon::FalconExporter::new(
@@ -31,8 +33,7 @@ impl MetricExporterType {
                     Arc::new(falcon_exporter),
                 ))
             }
-            #[cfg(test)]
-            MetricExporterType::Mock { step, exporter } => {
+            MetricExporterType::Custom { step, exporter } => {
                 Ok(MetricExporterInstance::Simple(*step, exporter.clone()))
             }
             MetricExporterType::Oltp(OltpMetricExporterConfig {
@@ -1,7 +1,8 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, UNIX_EPOCH};
 
 use again::RetryPolicy;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
@@ -122,7 +123,11 @@ impl<'de> serde::de::Visitor<'de> for FalconTagsVisitor {
 
 pub struct FalconExporter {
     falcon_config: FalconConfig,
+    endpoint: String,
     client: reqwest::Client,
+    /// The number of pushes so far, to push the metrics with a larger step only once every few
+    /// pushes.
+    rounds: AtomicU64,
 }
 
 const APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
@@ -131,25 +136,61 @@ const MAX_PUSH_RETRY: usize = 5;
 
 impl FalconExporter {
     pub fn new(falcon_config: FalconConfig) -> Result<Self> {
+        for (name, step) in &falcon_config.metric_steps {
+            if *step == 0 || step % falcon_config.step != 0 {
+                bail!(
+                    "The step of metric `{name}` ({step}s) must be a multiple of the step of the falcon exporter ({}s)",
+                    falcon_config.step
+                );
+            }
+        }
+        let endpoint = match &falcon_config.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => hostname().context("Failed to get the hostname as the falcon endpoint")?,
+        };
+
         Ok(Self {
             falcon_config,
+            endpoint,
             client: reqwest::ClientBuilder::new()
                 .no_proxy()
                 .user_agent(APP_USER_AGENT)
                 .build()?,
+            rounds: AtomicU64::new(0),
         })
     }
 
+    /// The step of the metric. The step of a histogram applies to all the `{name}_count`,
+    /// `{name}_sum` and `{name}_bucket` metrics flattened from it.
+    fn metric_step(&self, name: &str) -> u64 {
+        let steps = &self.falcon_config.metric_steps;
+        steps
+            .get(name)
+            .or_else(|| {
+                ["_count", "_sum", "_bucket"]
+                    .iter()
+                    .find_map(|suffix| steps.get(name.strip_suffix(suffix)?))
+            })
+            .copied()
+            .unwrap_or(self.falcon_config.step)
+    }
+
     fn construct_metric(&self, metric: &SimpleMetric) -> Result<FalconMetric> {
         Ok(FalconMetric {
-            endpoint: self.falcon_config.endpoint.clone(),
+            endpoint: self.endpoint.clone(),
             metric: metric.name.to_owned(),
             value: metric.value.clone(),
-            step: self.falcon_config.step,
+            step: self.metric_step(&metric.name),
             counter_type: metric.value_type.into(),
             tags: {
                 let mut tags = self.falcon_config.tags.clone();
-                tags.extend(metric.attributes.clone());
+                tags.extend(
+                    metric
+                        .attributes
+                        .iter()
+                        .filter(|(key, _)| !self.falcon_config.exclude_attributes.contains(key))
+                        .map(|(key, value)| (key.clone(), value.clone())),
+                );
                 tags.into()
             },
             timestamp: metric
@@ -164,10 +205,17 @@ impl FalconExporter {
 #[async_trait]
 impl SimpleMetricExporter for FalconExporter {
     async fn push(&self, metrics: &[SimpleMetric]) -> Result<()> {
+        let round = self.rounds.fetch_add(1, Ordering::Relaxed);
         let falcon_metrics = metrics
             .iter()
+            .filter(|metric| {
+                round % (self.metric_step(&metric.name) / self.falcon_config.step) == 0
+            })
             .map(|metric| self.construct_metric(metric))
             .collect::<Result<Vec<_>>>()?;
+        if falcon_metrics.is_empty() {
+            return Ok(());
+        }
 
         tracing::trace!(
             metrics_json = %serde_json::to_string(&falcon_metrics).unwrap_or_else(|error| format!("{error:#}")),
@@ -199,6 +247,18 @@ impl SimpleMetricExporter for FalconExporter {
     }
 }
 
+#[cfg(unix)]
+fn hostname() -> Result<String> {
+    nix::unistd::gethostname()?
+        .into_string()
+        .map_err(|hostname| anyhow::anyhow!("The hostname {hostname:?} is not valid UTF-8"))
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Result<String> {
+    bail!("The `endpoint` of the falcon exporter must be set on this platform")
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -255,13 +315,15 @@ mod tests {
 
         let falcon_config = FalconConfig {
             server_url: "http://127.0.0.1:1988".to_owned(),
-            endpoint: "master-node".to_owned(),
+            endpoint: Some("master-node".to_owned()),
             tags: [
                 ("namespace".to_owned(), "ns1".to_owned()),
                 ("app".to_owned(), "tng".to_owned()),
             ]
             .into(),
+            exclude_attributes: vec![],
             step: 60,
+            metric_steps: Default::default(),
         };
 
         // Setup an exporter
@@ -349,6 +411,51 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_metric_steps_and_excluded_attributes() -> Result<()> {
+        let exporter = FalconExporter::new(FalconConfig {
+            server_url: "http://127.0.0.1:1988".to_owned(),
+            endpoint: None,
+            tags: Default::default(),
+            exclude_attributes: vec!["ingress_in".to_owned()],
+            step: 60,
+            metric_steps: [("cx_duration".to_owned(), 300)].into(),
+        })?;
+        assert!(!exporter.endpoint.is_empty());
+        assert_eq!(exporter.metric_step("cx_active"), 60);
+        assert_eq!(exporter.metric_step("cx_duration_bucket"), 300);
+        assert_eq!(exporter.metric_step("cx_duration_sum"), 300);
+
+        let falcon_metric = exporter.construct_metric(&SimpleMetric {
+            name: "cx_duration_count".to_owned(),
+            value: 3.into(),
+            value_type: ValueType::Counter,
+            attributes: [
+                ("ingress_id".to_string(), "1".to_string()),
+                ("ingress_in".to_string(), "0.0.0.0:10001".to_string()),
+            ]
+            .into(),
+            time: SystemTime::get(),
+        })?;
+        assert_eq!(falcon_metric.step, 300);
+        assert_eq!(
+            falcon_metric.tags,
+            [("ingress_id".to_string(), "1".to_string())].into()
+        );
+
+        // The step of a metric must be a multiple of the step of the exporter
+        assert!(FalconExporter::new(FalconConfig {
+            server_url: "http://127.0.0.1:1988".to_owned(),
+            endpoint: Some("master-node".to_owned()),
+            tags: Default::default(),
+            exclude_attributes: vec![],
+            step: 60,
+            metric_steps: [("cx_duration".to_owned(), 90)].into(),
+        })
+        .is_err());
+        Ok(())
+    }
+
     pub async fn launch_fake_falcon_server(port: u16) -> tokio::sync::mpsc::UnboundedReceiver<()> {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 
@@ -19,7 +19,7 @@ pub enum ValueType {
     Gauge,
 }
 
-type MetricValue = serde_json::Number;
+pub type MetricValue = serde_json::Number;
 
 #[derive(Debug, PartialEq)]
 pub struct SimpleMetric {
@@ -38,6 +38,9 @@ pub struct SimpleMetric {
 /// A simple metric exporter for exporting metrics to other place. This trait is a simplified
 /// version of opentelemetry rust exporter and it is designed to be used with
 /// OpenTelemetryMetricExporterAdapter.
+///
+/// Library users can implement it, or pass a closure, to receive the metrics of TNG with their
+/// own exporter, see `MetricExporterType::Custom`.
 pub trait SimpleMetricExporter {
     async fn push(&self, metrics: &[SimpleMetric]) -> Result<()>;
 }
@@ -169,25 +172,65 @@ impl<T: SimpleMetricExporter> OpenTelemetryMetricExporterAdapter<T> {
                         None => None,
                     },
 
+                    // Histograms are flattened into the `_count`, `_sum` and cumulative
+                    // `_bucket` counters, in the same way as Prometheus does.
+                    opentelemetry_sdk::metrics::data::AggregatedMetrics::F64(
+                        opentelemetry_sdk::metrics::data::MetricData::Histogram(histogram),
+                    ) => {
+                        if let Some(data_point) = histogram.data_points().last() {
+                            let name = metric.name();
+                            let attrs = to_attributes(data_point.attributes().collect_vec());
+                            let mut push = |name: String, value: MetricValue, attributes| {
+                                out_metrics.push(SimpleMetric {
+                                    name,
+                                    value,
+                                    value_type: ValueType::Counter,
+                                    attributes,
+                                    time: histogram.time(),
+                                })
+                            };
+
+                            push(
+                                format!("{name}_count"),
+                                data_point.count().into(),
+                                attrs.clone(),
+                            );
+                            push(
+                                format!("{name}_sum"),
+                                serde_json::Number::from_f64(data_point.sum()).with_context(
+                                    || {
+                                        format!(
+                                            "Failed to convert num {} to json",
+                                            data_point.sum()
+                                        )
+                                    },
+                                )?,
+                                attrs.clone(),
+                            );
+                            let mut cumulative = 0;
+                            for (bound, count) in
+                                data_point.bounds().zip(data_point.bucket_counts())
+                            {
+                                cumulative += count;
+                                let mut attrs = attrs.clone();
+                                attrs.insert("le".to_string(), bound.to_string());
+                                push(format!("{name}_bucket"), cumulative.into(), attrs);
+                            }
+                        }
+                        None
+                    }
+
                     _ => {
                         bail!("Unsupported data type");
                     }
                 };
 
                 if let Some((value, time, value_type, attributes)) = value_and_time {
-                    let mut attrs = IndexMap::new();
-
-                    attributes
-                        .iter()
-                        .for_each(|opentelemetry::KeyValue { key, value, .. }| {
-                            attrs.insert(key.to_string(), value.to_string());
-                        });
-
                     out_metrics.push(SimpleMetric {
                         name: metric.name().to_string(),
                         value,
                         value_type,
-                        attributes: attrs,
+                        attributes: to_attributes(attributes),
                         time,
                     });
                 }
@@ -198,6 +241,18 @@ impl<T: SimpleMetricExporter> OpenTelemetryMetricExporterAdapter<T> {
     }
 }
 
+fn to_attributes(attributes: Vec<&opentelemetry::KeyValue>) -> IndexMap<String, String> {
+    let mut attrs = IndexMap::new();
+
+    attributes
+        .iter()
+        .for_each(|opentelemetry::KeyValue { key, value, .. }| {
+            attrs.insert(key.to_string(), value.to_string());
+        });
+
+    attrs
+}
+
 impl<T: SimpleMetricExporter + std::marker::Sync + std::marker::Send + 'static>
     opentelemetry_sdk::metrics::exporter::PushMetricExporter
     for OpenTelemetryMetricExporterAdapter<T>
@@ -253,6 +308,7 @@ mod tests {
     #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
     async fn test_exporter() -> Result<()> {
         let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let in_port = portpicker::pick_unused_port().unwrap();
 
         let mut config: TngConfig = serde_json::from_value(json!(
             {
@@ -263,7 +319,7 @@ mod tests {
                     {
                         "mapping": {
                             "in": {
-                                "port": portpicker::pick_unused_port().unwrap()
+                                "port": in_port
                             },
                             "out": {
                                 "host": "127.0.0.1",
@@ -281,10 +337,15 @@ mod tests {
             .as_mut()
             .unwrap()
             .exporters
-            .push(MetricExporterType::Mock {
+            .push(MetricExporterType::Custom {
                 step: 1,
-                exporter: Arc::new(move |_metric_and_values: &[SimpleMetric]| {
-                    let _ = tx.send(());
+                exporter: Arc::new(move |metric_and_values: &[SimpleMetric]| {
+                    let _ = tx.send(
+                        metric_and_values
+                            .iter()
+                            .map(|metric| metric.name.clone())
+                            .collect_vec(),
+                    );
                     Ok(())
                 }),
             });
@@ -299,15 +360,22 @@ mod tests {
             tokio::task::spawn(async move { tng_runtime.serve_with_ready(ready_sender).await });
 
         ready_receiver.await?;
-        // tng is ready now, wait a while for exporter to send data
+        // tng is ready now, make a connection which fails since the upstream is not listening, so
+        // that the connection duration is recorded
+        drop(tokio::net::TcpStream::connect(("127.0.0.1", in_port)).await?);
+
+        // wait a while for exporter to send data
 
         tokio::time::sleep(std::time::Duration::from_secs(5)).await;
 
         canceller.cancel();
 
         // At least get metrics two times
-        assert_eq!(rx.try_recv(), Ok(()));
-        assert_eq!(rx.try_recv(), Ok(()));
+        assert!(rx.try_recv().is_ok());
+        let names = rx.try_recv()?;
+        assert!(names.contains(&"cx_total".to_string()));
+        assert!(names.contains(&"cx_duration_count".to_string()));
+        assert!(names.contains(&"cx_duration_bucket".to_string()));
 
         select! {
             _ = tokio::time::sleep(std::time::Duration::from_secs(5)) => {
@@ -1,19 +1,111 @@
-use anyhow::Result;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+
+use anyhow::{bail, Context as _, Result};
 use async_trait::async_trait;
+use serde_json::json;
+
+use crate::config::observability::metric::StdoutMetricFormat;
+use crate::observability::log::file::rfc3339;
 
-use super::SimpleMetric;
+use super::{SimpleMetric, ValueType};
 
 use super::SimpleMetricExporter;
 
-pub struct StdoutExporter {}
+pub struct StdoutExporter {
+    format: StdoutMetricFormat,
+    /// Where the metrics are written, unless they are printed with the logs.
+    writer: Option<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl StdoutExporter {
+    pub fn new(format: StdoutMetricFormat, path: Option<&str>) -> Result<Self> {
+        let writer: Option<Box<dyn Write + Send>> = match (format, path) {
+            (StdoutMetricFormat::Log, None) => None,
+            (StdoutMetricFormat::Log, Some(_)) => {
+                bail!("The `path` of the stdout exporter is not supported with the `log` format")
+            }
+            (_, None) => Some(Box::new(std::io::stdout())),
+            (_, Some(path)) => Some(Box::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open metric file {path:?}"))?,
+            )),
+        };
+        Ok(Self {
+            format,
+            writer: writer.map(Mutex::new),
+        })
+    }
+
+    fn format_metric(&self, metric: &SimpleMetric) -> Result<String> {
+        let value_type = match metric.value_type {
+            ValueType::Counter => "counter",
+            ValueType::Gauge => "gauge",
+        };
+        let line = match self.format {
+            StdoutMetricFormat::Log | StdoutMetricFormat::Json | StdoutMetricFormat::Pretty => {
+                let record = json!({
+                    "timestamp": rfc3339(metric.time),
+                    "name": metric.name,
+                    "type": value_type,
+                    "value": metric.value,
+                    "attributes": metric.attributes,
+                });
+                if self.format == StdoutMetricFormat::Pretty {
+                    serde_json::to_string_pretty(&record)?
+                } else {
+                    record.to_string()
+                }
+            }
+            StdoutMetricFormat::KeyValue => {
+                let mut line = format!(
+                    "timestamp={} name={} type={value_type} value={}",
+                    rfc3339(metric.time),
+                    key_value_escape(&metric.name),
+                    metric.value
+                );
+                for (key, value) in &metric.attributes {
+                    line.push_str(&format!(" {key}={}", key_value_escape(value)));
+                }
+                line
+            }
+        };
+        Ok(line)
+    }
+}
 
-impl StdoutExporter {}
+/// Quote the value if it contains spaces, `=` or quotes, so that each line can be split into
+/// `key=value` pairs.
+fn key_value_escape(value: &str) -> String {
+    if value.is_empty() || value.contains([' ', '=', '"']) {
+        format!("{value:?}")
+    } else {
+        value.to_owned()
+    }
+}
 
 #[async_trait]
 impl SimpleMetricExporter for StdoutExporter {
     async fn push(&self, metrics: &[SimpleMetric]) -> Result<()> {
-        tracing::info!(?metrics, "current metrics");
-        Ok(())
+        let Some(writer) = &self.writer else {
+            tracing::info!(?metrics, "current metrics");
+            return Ok(());
+        };
+
+        let mut lines = String::new();
+        for metric in metrics {
+            lines.push_str(&self.format_metric(metric)?);
+            lines.push('\n');
+        }
+        let mut writer = writer.lock().unwrap_or_else(|p| p.into_inner());
+        writer
+            .write_all(lines.as_bytes())
+            .and_then(|()| writer.flush())
+            .context("Failed to write metrics")
     }
 }
 
@@ -22,11 +114,54 @@ mod tests {
 
     use crate::{config::TngConfig, runtime::TngRuntime};
     use scopeguard::defer;
-    use serde_json::json;
     use tokio::select;
+    use web_time_compat::{SystemTime, SystemTimeExt};
 
     use super::*;
 
+    #[tokio::test]
+    async fn test_formats() -> Result<()> {
+        let metric = SimpleMetric {
+            name: "cx_active".to_owned(),
+            value: 3.into(),
+            value_type: ValueType::Gauge,
+            attributes: [
+                ("ingress_id".to_string(), "1".to_string()),
+                ("ingress_type".to_string(), "http proxy".to_string()),
+            ]
+            .into(),
+            time: SystemTime::get(),
+        };
+
+        let exporter = StdoutExporter::new(StdoutMetricFormat::Json, None)?;
+        let line: serde_json::Value = serde_json::from_str(&exporter.format_metric(&metric)?)?;
+        assert_eq!(line["name"], "cx_active");
+        assert_eq!(line["type"], "gauge");
+        assert_eq!(line["value"], 3);
+        assert_eq!(
+            line["attributes"],
+            json!({"ingress_id": "1", "ingress_type": "http proxy"})
+        );
+
+        let exporter = StdoutExporter::new(StdoutMetricFormat::KeyValue, None)?;
+        let line = exporter.format_metric(&metric)?;
+        assert!(line.starts_with("timestamp="));
+        assert!(line.ends_with(
+            r#" name=cx_active type=gauge value=3 ingress_id=1 ingress_type="http proxy""#
+        ));
+
+        // The metrics are appended to the file
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("metrics.log");
+        let exporter = StdoutExporter::new(
+            StdoutMetricFormat::Json,
+            Some(path.to_str().context("invalid path")?),
+        )?;
+        exporter.push(&[metric]).await?;
+        assert_eq!(std::fs::read_to_string(&path)?.lines().count(), 1);
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
     async fn test_exporter() -> Result<()> {
         let config: TngConfig = serde_json::from_value(json!(
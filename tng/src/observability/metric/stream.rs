@@ -1,12 +1,15 @@
 use std::{
     pin::Pin,
+    sync::Mutex,
     task::{Context, Poll},
 };
 
-use opentelemetry::metrics::Counter;
+use opentelemetry::metrics::{Counter, Histogram};
 use pin_project::pin_project;
+use web_time_compat::Instant;
 
 use super::counter::AttributedCounter;
+use crate::tunnel::utils::forward::{SpliceCounter, SpliceSocket};
 
 const COUNTER_FLUSH_THRESHOLD: u64 = 1024 * 1024; // 1 MB
 
@@ -49,6 +52,39 @@ pub struct StreamWithCounter<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + s
     pub(crate) tx: PendingCounter,
 
     pub(crate) rx: PendingCounter,
+
+    /// When the stream is accepted, taken once the first byte from the upstream is written to it.
+    pub(crate) first_byte: Option<(Instant, AttributedCounter<Histogram<f64>, f64>)>,
+}
+
+impl<T> SpliceSocket for StreamWithCounter<T>
+where
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + std::marker::Unpin + SpliceSocket,
+{
+    fn tcp_socket(&self) -> Option<&tokio::net::TcpStream> {
+        self.inner.tcp_socket()
+    }
+
+    /// The bytes are added to the counters directly, since they are moved in large chunks.
+    fn splice_counter(&self) -> SpliceCounter {
+        let rx = self.rx.counter.clone();
+        let tx = self.tx.counter.clone();
+        let first_byte = Mutex::new(self.first_byte.clone());
+        let inner = self.inner.splice_counter();
+        Box::new(move |read, written| {
+            if read > 0 {
+                rx.add(read);
+            }
+            if written > 0 {
+                tx.add(written);
+                let first_byte = first_byte.lock().unwrap_or_else(|p| p.into_inner()).take();
+                if let Some((accepted_at, histogram)) = first_byte {
+                    histogram.record(accepted_at.elapsed().as_secs_f64());
+                }
+            }
+            inner(read, written)
+        })
+    }
 }
 
 impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + std::marker::Unpin> tokio::io::AsyncWrite
@@ -64,6 +100,11 @@ impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + std::marker::Unpin> tokio
         let ret = this.inner.poll_write(cx, buf);
         if let Poll::Ready(Ok(sz)) = ret {
             this.tx.add(sz as u64);
+            if sz > 0 {
+                if let Some((accepted_at, histogram)) = this.first_byte.take() {
+                    histogram.record(accepted_at.elapsed().as_secs_f64());
+                }
+            }
         }
         ret
     }
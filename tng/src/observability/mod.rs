@@ -1,6 +1,8 @@
 #[cfg(feature = "metric")]
 pub mod metric;
 
+pub mod log;
+pub mod log_filter;
 pub mod trace;
 
 #[cfg(any(feature = "metric", feature = "trace"))]
@@ -1,16 +1,30 @@
 use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use opentelemetry_otlp::{WithExportConfig as _, WithHttpConfig as _, WithTonicConfig};
+use opentelemetry_sdk::trace::Sampler;
 
 use crate::{
     config::observability::{
-        trace::{OltpTraceExporterConfig, TraceExporterType},
+        trace::{OltpTraceExporterConfig, TraceArgs, TraceExporterType},
         OltpExporterProtocol,
     },
     observability::trace::opentelemetry_span_processor::ShutdownInStandaloneTokioThreadSpanProcessor,
 };
 
+impl TraceArgs {
+    /// The sampler shared by all the exporters.
+    pub fn sampler(&self) -> Result<Sampler> {
+        Ok(match self.sampling_ratio {
+            None => Sampler::ParentBased(Box::new(Sampler::AlwaysOn)),
+            Some(ratio) if (0.0..=1.0).contains(&ratio) => {
+                Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio)))
+            }
+            Some(ratio) => bail!("The sampling_ratio {ratio} is not between 0.0 and 1.0"),
+        })
+    }
+}
+
 impl TraceExporterType {
     pub fn instantiate(&self) -> Result<TraceExporterInstance> {
         match self {
@@ -77,7 +91,10 @@ pub enum TraceExporterInstance {
 }
 
 impl TraceExporterInstance {
-    pub fn into_sdk_tracer_provider(self) -> opentelemetry_sdk::trace::SdkTracerProvider {
+    pub fn into_sdk_tracer_provider(
+        self,
+        sampler: Sampler,
+    ) -> opentelemetry_sdk::trace::SdkTracerProvider {
         match self {
             TraceExporterInstance::OpenTelemetryOltp(span_exporter) => {
                 let batch =
@@ -86,12 +103,14 @@ impl TraceExporterInstance {
 
                 opentelemetry_sdk::trace::SdkTracerProvider::builder()
                     .with_span_processor(batch)
+                    .with_sampler(sampler)
                     .with_resource(crate::observability::otlp_resource())
                     .build()
             }
             TraceExporterInstance::OpenTelemetryStdout(span_exporter) => {
                 opentelemetry_sdk::trace::SdkTracerProvider::builder()
                     .with_simple_exporter(span_exporter)
+                    .with_sampler(sampler)
                     .with_resource(crate::observability::otlp_resource())
                     .build()
             }
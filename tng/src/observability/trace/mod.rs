@@ -2,3 +2,5 @@
 pub mod instance;
 #[cfg(feature = "trace")]
 pub mod opentelemetry_span_processor;
+#[cfg(feature = "trace")]
+pub mod propagation;
@@ -1,9 +1,23 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::error::TngError;
+use crate::observability::log::access_log::AccessLogLayer;
+use crate::observability::log::audit_log::{self, AuditLogLayer};
+pub use crate::observability::log_filter::LogFilterHandle;
 use crate::observability::metric::simple_exporter::noop::NoopMeterProvider;
 use crate::service::RegistedService;
-use crate::state::{EgressStatusHandle, IngressStatusHandle, TngState};
+use crate::state::{
+    EgressStatusHandle, IngressStatusHandle, ReadinessCell, ServiceReadiness, ServiceState,
+    TngState,
+};
+use crate::status::{StatusProvider, StatusQueryResult};
 use crate::tunnel::access_log::IngressAccessMode as AccessIngressMode;
+pub use crate::tunnel::access_log::{ACCESS_LOG_TARGET, ACCESS_RECORD_TARGET};
+pub use crate::tunnel::audit_log::AUDIT_LOG_TARGET;
+use crate::tunnel::connections::ConnectionSelector;
 use crate::tunnel::egress::flow::EgressFlow;
 use crate::tunnel::egress::mapping::MappingEgress;
 use crate::tunnel::ingress::flow::IngressFlow;
@@ -11,23 +25,62 @@ use crate::tunnel::ingress::hook::HookIngress;
 use crate::tunnel::ingress::socks5::Socks5Ingress;
 use crate::tunnel::ingress::{http_proxy::HttpProxyIngress, mapping::MappingIngress};
 use crate::tunnel::service_metrics::ServiceMetricsCreator;
+use crate::tunnel::utils::buffer_pool::{BufferPool, BufferPoolStats};
+use crate::tunnel::utils::runtime::supervised_task::SupervisedTaskResult;
 use crate::tunnel::utils::runtime::TokioRuntime;
 use crate::{
-    config::{egress::EgressMode, ingress::IngressMode, TngConfig},
+    config::{
+        egress::{AddEgressArgs, EgressMode},
+        ingress::{AddIngressArgs, IngressMode},
+        lazy_attest::LazyAttestArgs,
+        overrides::ConfigOverrides,
+        ra::VerifyUpdate,
+        restart::RestartPolicyArgs,
+        source::ConfigSource,
+        TngConfig, DEFAULT_PIPE_BUF_SIZE,
+    },
     control_interface::ControlInterface,
 };
 
-use anyhow::{bail, Context as _, Result};
+use anyhow::{anyhow, bail, Context as _, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
 use opentelemetry::metrics::MeterProvider;
 use opentelemetry::trace::TracerProvider;
+use opentelemetry::KeyValue;
 use scopeguard::defer;
+use serde::{Deserialize, Serialize};
 use tokio_graceful::Shutdown;
 use tokio_util::sync::CancellationToken;
 use tracing::{Instrument, Span};
 
 pub struct TngRuntime {
     services: Vec<(Arc<dyn RegistedService>, Span)>,
+    ingresses: Vec<ServiceEntry>,
+    egresses: Vec<ServiceEntry>,
+    /// The ids of the next ingress and egress started. The id of a service is never reused, so
+    /// that it keeps pointing to the same service across reloads.
+    next_ingress_id: usize,
+    next_egress_id: usize,
     state: Arc<TngState>,
+    /// The configuration currently applied
+    config: TngConfig,
+    config_source: Option<ConfigSource>,
+    /// Set if the log filter can be adjusted through the control interface
+    log_filter: Option<LogFilterHandle>,
+    /// Applied on top of the configuration loaded from `config_source`
+    config_overrides: ConfigOverrides,
+    reload_channel: (
+        tokio::sync::mpsc::Sender<RuntimeRequest>,
+        tokio::sync::mpsc::Receiver<RuntimeRequest>,
+    ),
+    /// Where the ingresses and egresses report their failures once they are ready, which shuts
+    /// down the instance.
+    error_channel: (
+        tokio::sync::mpsc::Sender<anyhow::Error>,
+        tokio::sync::mpsc::Receiver<anyhow::Error>,
+    ),
+    service_metrics_creator: ServiceMetricsCreator,
     meter_provider: Arc<dyn MeterProvider + Send + Sync>,
     shutdown: Shutdown,
     // This is a cancel token which can be called from the caller to cancel the task. Note that this funnction will not call the cancel() function on this.
@@ -35,10 +88,11 @@ pub struct TngRuntime {
     runtime: TokioRuntime,
 }
 
-pub type TracingReloadHandle = tracing_subscriber::reload::Handle<
-    Vec<Box<dyn tracing_subscriber::Layer<tracing_subscriber::Registry> + Send + Sync>>,
-    tracing_subscriber::Registry,
->;
+pub type TracingLayer =
+    Box<dyn tracing_subscriber::Layer<tracing_subscriber::Registry> + Send + Sync>;
+
+pub type TracingReloadHandle =
+    tracing_subscriber::reload::Handle<Vec<TracingLayer>, tracing_subscriber::Registry>;
 
 impl TngRuntime {
     #[cfg(test)]
@@ -60,6 +114,9 @@ impl TngRuntime {
             tracing::warn!("The field `admin_bind` in configuration is ignored, since envoy admin interface is deprecated");
             tng_config.admin_bind = None;
         }
+        tng_config
+            .resolve_ra_args()
+            .context("Invalid configuration")?;
 
         let canceller = CancellationToken::new();
 
@@ -90,191 +147,78 @@ impl TngRuntime {
 
         // Create TokioRuntime with the shutdown guard with currently running tokio runtime.
         let runtime = crate::tunnel::utils::runtime::TokioRuntime::current(shutdown.guard())?;
+        let runtime = match &tng_config.runtime {
+            Some(runtime_args) => {
+                runtime_args.validate()?;
+                runtime.with_protocol_runtime_layout(runtime_args)
+            }
+            None => runtime,
+        };
 
         let meter_provider =
             Self::setup_metric_exporter(&tng_config).context("Failed to setup metric exporter")?;
 
-        let service_metrics_creator = ServiceMetricsCreator::new_creator(meter_provider.clone());
+        let service_metrics_creator = ServiceMetricsCreator::new_creator(
+            meter_provider.clone(),
+            tng_config
+                .metric
+                .as_ref()
+                .and_then(|metric| metric.destination_labels.clone()),
+        );
 
         Self::setup_trace_exporter(&tng_config, reload_handle)
             .context("Failed to setup trace exporter")?;
 
+        Self::setup_log_exporter(&tng_config, reload_handle)
+            .context("Failed to setup log exporter")?;
+
+        Self::setup_access_log(&tng_config, reload_handle).context("Failed to setup access log")?;
+
+        Self::setup_audit_log(&tng_config, reload_handle).context("Failed to setup audit log")?;
+
         // Create all ingress and egress.
-        let mut services: Vec<(Arc<dyn RegistedService>, Span)> = vec![];
         let mut state = TngState::new();
 
+        let mut ingresses = vec![];
         for (id, add_ingress) in tng_config.add_ingress.iter().enumerate() {
-            let add_ingress = add_ingress.clone();
-            let span = tracing::info_span!("ingress", id);
-
-            let service = async {
-                Ok::<Arc<dyn RegistedService >, anyhow::Error>(match &add_ingress.ingress_mode {
-                    IngressMode::Mapping(mapping_args) => {
-                        Arc::new(IngressFlow::new(
-                            MappingIngress::new(id, mapping_args).await?,
-                            &add_ingress.common,
-                            &service_metrics_creator,
-                            runtime.clone(),
-                        )
-                        .await?) as Arc<_>
-                    }
-                    IngressMode::HttpProxy(http_proxy_args) => {
-                        Arc::new(IngressFlow::new(
-                            HttpProxyIngress::new(
-                                id,
-                                http_proxy_args,
-                                AccessIngressMode::HttpProxy,
-                            )
-                            .await?,
-                            &add_ingress.common,
-                            &service_metrics_creator,
-                            runtime.clone(),
-                        )
-                        .await?) as Arc<_>
-                    }
-                    IngressMode::Netfilter(netfilter_args) => {
-                        #[cfg(not(target_os = "linux"))]
-                        {
-                            let _ = netfilter_args;
-                            anyhow::bail!("Using ingress with 'netfilter' type is not supported on OS other than Linux");
-                        }
-
-                        #[cfg(target_os = "linux")]
-                        {
-                            use crate::tunnel::ingress::netfilter::NetfilterIngress;
-                            Arc::new(IngressFlow::new(
-                                NetfilterIngress::new(id, netfilter_args).await?,
-                                &add_ingress.common,
-                                &service_metrics_creator,
-                                runtime.clone(),
-                            )
-                            .await?) as Arc<_>
-                        }
-                    }
-                    IngressMode::Socks5(socks5_args) => {
-                        Arc::new(IngressFlow::new(
-                            Socks5Ingress::new(id, socks5_args).await?,
-                            &add_ingress.common,
-                            &service_metrics_creator,
-                            runtime.clone(),
-                        )
-                        .await?) as Arc<_>
-                    }
-                    IngressMode::Hook(hook_args) => {
-                        Arc::new(IngressFlow::new(
-                            HookIngress::new(id, hook_args).await?,
-                            &add_ingress.common,
-                            &service_metrics_creator,
-                            runtime.clone(),
-                        )
-                        .await?) as Arc<_>
-                    }
-                    #[cfg(feature = "ingress-mapping-udp")]
-                    IngressMode::MappingUdp(mapping_udp_args) => {
-                        use crate::tunnel::ingress::datagram_flow::DatagramIngressFlow;
-                        use crate::tunnel::ingress::mapping_udp::MappingUdpIngress;
-
-                        let mut ingress = MappingUdpIngress::new(id, mapping_udp_args).await?;
-                        ingress.set_max_datagram_size(add_ingress.common.quic.as_ref().and_then(|q| q.max_datagram_size));
-
-                        Arc::new(
-                            DatagramIngressFlow::new(
-                                ingress,
-                                &add_ingress.common,
-                                &service_metrics_creator,
-                                runtime.clone(),
-                            )
-                            .await?,
-                        ) as Arc<_>
-                    }
-                })
-            }.instrument(span.clone()).await?;
-
+            let entry =
+                ServiceEntry::new_ingress(id, add_ingress, &service_metrics_creator, &runtime)
+                    .await?;
             state.add_ingress(IngressStatusHandle {
-                flow: Arc::downgrade(&service),
+                id,
+                flow: Arc::downgrade(&entry.service),
+                readiness: entry.readiness.clone(),
             });
-            services.push((service, span));
+            ingresses.push(entry);
         }
 
+        let mut egresses = vec![];
         for (id, add_egress) in tng_config.add_egress.iter().enumerate() {
-            let add_egress = add_egress.clone();
-            let span = tracing::info_span!("egress", id);
-
-            let service = async {
-                Ok::<Arc<dyn RegistedService >, anyhow::Error>(match &add_egress.egress_mode {
-                    EgressMode::Mapping(mapping_args) => {
-                        Arc::new(EgressFlow::new(
-                            MappingEgress::new(id, mapping_args).await?,
-                            &add_egress.common,
-                            &service_metrics_creator,
-                            runtime.clone(),
-                        )
-                        .await?) as Arc<_>
-                    }
-                    EgressMode::Netfilter(netfilter_args) => {
-                        #[cfg(not(target_os = "linux"))]
-                        {
-                            let _ = netfilter_args;
-                            anyhow::bail!("Using egress with 'netfilter' type is not supported on OS other than Linux");
-                        }
-
-                        #[cfg(target_os = "linux")]
-                        {
-                            use crate::tunnel::egress::netfilter::NetfilterEgress;
-                            Arc::new(EgressFlow::new(
-                                NetfilterEgress::new(id, netfilter_args).await?,
-                                &add_egress.common,
-                                &service_metrics_creator,
-                                runtime.clone(),
-                            )
-                            .await?) as Arc<_>
-                        }
-                    }
-                    EgressMode::Hook(hook_args) => {
-                        use crate::tunnel::egress::hook::HookEgress;
-
-                        Arc::new(EgressFlow::new(
-                            HookEgress::new(id, hook_args),
-                            &add_egress.common,
-                            &service_metrics_creator,
-                            runtime.clone(),
-                        )
-                        .await?) as Arc<_>
-                    }
-                    #[cfg(feature = "egress-mapping-udp")]
-                    EgressMode::MappingUdp(mapping_udp_args) => {
-                        use crate::tunnel::egress::datagram_flow::DatagramEgressFlow;
-                        use crate::tunnel::egress::mapping_udp::MappingUdpEgress;
-
-                        let mut egress = MappingUdpEgress::new(id, mapping_udp_args).await?;
-                        egress.set_max_datagram_size(add_egress.common.quic.as_ref().and_then(|q| q.max_datagram_size));
-
-                        Arc::new(
-                            DatagramEgressFlow::new(
-                                egress,
-                                &add_egress.common,
-                                &service_metrics_creator,
-                                runtime.clone(),
-                            )
-                            .await?,
-                        ) as Arc<_>
-                    }
-                })
-            }.instrument(span.clone()).await?;
-
+            let entry =
+                ServiceEntry::new_egress(id, add_egress, &service_metrics_creator, &runtime)
+                    .await?;
             state.add_egress(EgressStatusHandle {
-                flow: Arc::downgrade(&service),
+                id,
+                flow: Arc::downgrade(&entry.service),
+                readiness: entry.readiness.clone(),
             });
-            services.push((service, span));
+            egresses.push(entry);
         }
 
         let state = Arc::new(state);
 
+        let reload_channel = tokio::sync::mpsc::channel(RELOAD_CHANNEL_SIZE);
+        let config_reload_handle = ConfigReloadHandle {
+            sender: reload_channel.0.clone(),
+        };
+
         // Launch Control Interface
-        if let Some(args) = tng_config.control_interface {
-            let control_interface = ControlInterface::new(args, state.clone(), runtime.clone())
-                .await
-                .context("Failed to init control interface")?;
+        let mut services: Vec<(Arc<dyn RegistedService>, Span)> = vec![];
+        if let Some(args) = tng_config.control_interface.clone() {
+            let control_interface =
+                ControlInterface::new(args, state.clone(), config_reload_handle, runtime.clone())
+                    .await
+                    .context("Failed to init control interface")?;
             services.push((
                 Arc::new(control_interface),
                 tracing::info_span!("control_interface"),
@@ -283,7 +227,18 @@ impl TngRuntime {
 
         Ok(Self {
             services,
+            next_ingress_id: ingresses.len(),
+            next_egress_id: egresses.len(),
+            ingresses,
+            egresses,
             state,
+            config: tng_config,
+            config_source: None,
+            log_filter: None,
+            config_overrides: ConfigOverrides::default(),
+            reload_channel,
+            error_channel: tokio::sync::mpsc::channel(ERROR_CHANNEL_SIZE),
+            service_metrics_creator,
             meter_provider,
             shutdown,
             canceller,
@@ -295,6 +250,31 @@ impl TngRuntime {
         Arc::clone(&self.state)
     }
 
+    /// Set where the configuration is loaded from. Once set, the configuration will be reloaded
+    /// from this source on SIGHUP, or when a reload without new configuration is requested from
+    /// the control interface.
+    pub fn set_config_source(&mut self, source: ConfigSource) {
+        self.config_source = Some(source);
+    }
+
+    /// Set the overrides applied whenever the configuration is loaded again from the config
+    /// source, so that they are kept across reloads.
+    pub fn set_config_overrides(&mut self, overrides: ConfigOverrides) {
+        self.config_overrides = overrides;
+    }
+
+    /// Set the handle to adjust the log filter, which is then exposed on the control interface.
+    pub fn set_log_filter_handle(&mut self, log_filter: LogFilterHandle) {
+        self.log_filter = Some(log_filter);
+    }
+
+    /// Get a handle to reload the configuration of this instance while it is serving.
+    pub fn config_reload_handle(&self) -> ConfigReloadHandle {
+        ConfigReloadHandle {
+            sender: self.reload_channel.0.clone(),
+        }
+    }
+
     pub fn canceller(&self) -> CancellationToken {
         self.canceller.clone()
     }
@@ -325,11 +305,37 @@ impl TngRuntime {
             });
         }
 
+        // Notify systemd of the readiness and liveness of the instance, if started by it.
+        #[cfg(unix)]
+        let sd_notifier = match crate::systemd::SdNotifier::from_env() {
+            Ok(notifier) => notifier.map(Arc::new),
+            Err(error) => {
+                tracing::warn!(?error, "Failed to setup the notification to systemd");
+                None
+            }
+        };
+        #[cfg(unix)]
+        if let Some(notifier) = &sd_notifier {
+            if let Some(interval) = notifier.watchdog_interval() {
+                tracing::debug!(?interval, "Sending watchdog keepalives to systemd");
+                let notifier = notifier.clone();
+                self.runtime.spawn_supervised_task(async move {
+                    let mut ticker = tokio::time::interval(interval);
+                    loop {
+                        ticker.tick().await;
+                        if let Err(error) = notifier.notify("WATCHDOG=1") {
+                            tracing::warn!(?error, "Failed to send watchdog keepalive to systemd");
+                        }
+                    }
+                });
+            }
+        }
+
         // Setup all services
-        let service_count = self.services.len();
-        let (mut ready_receiver, mut error_receiver) = {
+        let service_count = self.services.len() + self.ingresses.len() + self.egresses.len();
+        let mut ready_receiver = {
             let (ready_sender, ready_receiver) = tokio::sync::mpsc::channel(service_count);
-            let (error_sender, error_receiver) = tokio::sync::mpsc::channel(service_count);
+            let error_sender = self.error_channel.0.clone();
 
             for (service, span) in self.services.drain(..) {
                 let ready_sender = ready_sender.clone();
@@ -342,9 +348,27 @@ impl TngRuntime {
                         }
                     });
             }
-            (ready_receiver, error_receiver)
+            for entry in self.ingresses.iter_mut().chain(self.egresses.iter_mut()) {
+                entry.spawn(&self.runtime, ready_sender.clone(), error_sender.clone());
+            }
+            ready_receiver
         };
 
+        // Reload the configuration on SIGHUP, if it is loaded from a file or directory.
+        #[cfg(unix)]
+        if self.config_source.is_some() {
+            let reload_handle = self.config_reload_handle();
+            let mut sighup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .context("Failed to listen for SIGHUP")?;
+            self.runtime.spawn_supervised_task(async move {
+                while sighup.recv().await.is_some() {
+                    tracing::info!("Received SIGHUP, reloading configuration");
+                    // The result is logged by the reload itself
+                    let _ = reload_handle.reload(None).await;
+                }
+            });
+        }
+
         let check_services_ready = async {
             for _ in 0..service_count {
                 ready_receiver.recv().await;
@@ -358,20 +382,115 @@ impl TngRuntime {
             .build();
         live.record(0, &[]);
 
+        // The health of each service is observed on every export, so that it follows the drains,
+        // failures and reloads of the services.
+        let state = Arc::downgrade(&self.state);
+        let _service_health = meter
+            .u64_observable_gauge("service_health")
+            .with_description("Indicates the service is ready or not, with its status and reason")
+            .with_callback(move |observer| {
+                let Some(services) = state
+                    .upgrade()
+                    .and_then(|state| state.try_services_readiness())
+                else {
+                    return;
+                };
+                for (kind, id, readiness) in services {
+                    let mut attributes = vec![
+                        KeyValue::new(format!("{kind}_id"), id.to_string()),
+                        KeyValue::new("status", readiness.health()),
+                    ];
+                    if let Some(reason) = readiness.reason_summary() {
+                        attributes.push(KeyValue::new("reason", reason));
+                    }
+                    observer.observe(u64::from(readiness.is_ready()), &attributes);
+                }
+            })
+            .build();
+
+        // The buffer pool is shared by all the services, so its stats are reported unlabeled
+        let _buffer_pool_gauges = [
+            (
+                "buffer_pool_in_use_buffers",
+                "Current number of forwarding buffers in use by the streams",
+                (|stats| stats.in_use_buffers) as fn(BufferPoolStats) -> u64,
+            ),
+            (
+                "buffer_pool_idle_buffers",
+                "Current number of forwarding buffers kept in the pool for reuse",
+                |stats| stats.idle_buffers,
+            ),
+            (
+                "buffer_pool_idle_bytes",
+                "Current bytes of the forwarding buffers kept in the pool for reuse",
+                |stats| stats.idle_bytes,
+            ),
+        ]
+        .map(|(name, description, value)| {
+            meter
+                .u64_observable_gauge(name)
+                .with_description(description)
+                .with_callback(move |observer| {
+                    observer.observe(value(BufferPool::global().stats()), &[])
+                })
+                .build()
+        });
+        let _buffer_pool_counters = [
+            (
+                "buffer_pool_allocated_total",
+                "Total forwarding buffers allocated since the pool had none to reuse",
+                (|stats| stats.allocated_total) as fn(BufferPoolStats) -> u64,
+            ),
+            (
+                "buffer_pool_reused_total",
+                "Total forwarding buffers reused from the pool",
+                |stats| stats.reused_total,
+            ),
+        ]
+        .map(|(name, description, value)| {
+            meter
+                .u64_observable_counter(name)
+                .with_description(description)
+                .with_callback(move |observer| {
+                    observer.observe(value(BufferPool::global().stats()), &[])
+                })
+                .build()
+        });
+
+        // Failures to write the audit log can not be logged, so they are only reported here
+        let _audit_log_write_failures = self.config.audit_log.is_some().then(|| {
+            meter
+                .u64_observable_counter("audit_log_write_failures_total")
+                .with_description("Total audit log entries which failed to be written")
+                .with_callback(|observer| observer.observe(audit_log::write_failures(), &[]))
+                .build()
+        });
+
         let maybe_err = tokio::select! {
             _ = check_services_ready => {
                 tracing::info!(service_count, "All services are ready");
                 live.record(1, &[]);
 
                 let _ = self.state.ready.0.send(true); // Ignore any error occuring during send
+                #[cfg(unix)]
+                if let Some(notifier) = &sd_notifier {
+                    if let Err(error) = notifier.notify("READY=1") {
+                        tracing::warn!(?error, "Failed to notify systemd of the readiness");
+                    }
+                }
 
-                // Now waiting for exiting signal
-                tokio::select! {
-                    maybe_err = error_receiver.recv() => {maybe_err}
-                    _ = self.runtime.shutdown_guard().cancelled() => None
+                // Now waiting for exiting signal, and handle the reload requests in the meantime
+                loop {
+                    tokio::select! {
+                        maybe_err = self.error_channel.1.recv() => break maybe_err,
+                        _ = self.runtime.shutdown_guard().cancelled() => break None,
+                        Some(request) = self.reload_channel.1.recv() => {
+                            self.handle_request(request).await;
+                        }
+                    }
                 }
             }
-            maybe_err = error_receiver.recv() => {maybe_err}
+            maybe_err = self.error_channel.1.recv() => {maybe_err}
             _ = self.runtime.shutdown_guard().cancelled() => None
         };
 
@@ -381,19 +500,553 @@ impl TngRuntime {
             tracing::info!("Shutting down the instance");
         }
 
+        #[cfg(unix)]
+        if let Some(notifier) = &sd_notifier {
+            if let Err(error) = notifier.notify("STOPPING=1") {
+                tracing::warn!(?error, "Failed to notify systemd of the shutdown");
+            }
+        }
+
+        let drain_timeout = self
+            .config
+            .shutdown_drain_timeout_secs
+            .map(Duration::from_secs);
+        if let Some(timeout) = drain_timeout {
+            self.drain_on_shutdown(timeout).await;
+        }
+
         // Trigger the shutdown guard to gracefully shutdown all the tokio tasks.
         self.canceller.cancel();
 
         // Wait for the shutdown guard to complete.
         {
             drop(self.runtime); // Drop the runtime to release the shutdown_guard hold by the runtime
-            self.shutdown.shutdown().await;
+            drop(self.ingresses);
+            drop(self.egresses);
+            match drain_timeout {
+                // The connections are closed already, so the remaining tasks are not waited for
+                // longer than the drain timeout again
+                Some(timeout) => {
+                    if self.shutdown.shutdown_with_limit(timeout).await.is_err() {
+                        tracing::warn!("Some tasks are still running after the drain timeout");
+                    }
+                }
+                None => self.shutdown.shutdown().await,
+            }
         }
 
         tracing::debug!("The instance is shutdown complete");
         Ok(())
     }
 
+    async fn handle_request(&mut self, request: RuntimeRequest) {
+        match request {
+            RuntimeRequest::Reload { config, reply } => {
+                let result = self.reload(config).await;
+                match &result {
+                    Ok(summary) => tracing::info!(?summary, "Configuration reloaded"),
+                    Err(error) => tracing::error!(?error, "Failed to reload configuration"),
+                }
+                let _ = reply.send(result); // Ignore any error occuring during send
+            }
+            RuntimeRequest::Drain {
+                kind,
+                id,
+                timeout,
+                reply,
+            } => self.drain(kind, id, timeout, reply).await,
+            RuntimeRequest::LogFilter { directives, reply } => {
+                let result = self.update_log_filter(directives);
+                if let Ok(filter) = &result {
+                    tracing::info!(%filter, "Log filter updated");
+                }
+                let _ = reply.send(result); // Ignore any error occuring during send
+            }
+            RuntimeRequest::Restart { kind, id, reply } => {
+                let result = self.restart(kind, id).await;
+                match &result {
+                    Ok(()) => tracing::info!(%kind, id, "Service restarted"),
+                    Err(error) => tracing::error!(%kind, id, ?error, "Failed to restart service"),
+                }
+                let _ = reply.send(result); // Ignore any error occuring during send
+            }
+            RuntimeRequest::UpdateVerify {
+                kind,
+                id,
+                update,
+                reply,
+            } => {
+                let result = self.update_verify(kind, id, &update).await;
+                if result.is_ok() {
+                    tracing::info!(%kind, id, ?update, "Verification parameters updated");
+                }
+                let _ = reply.send(result); // Ignore any error occuring during send
+            }
+        }
+    }
+
+    /// Stop the service from accepting new connections, and wait for its connections to finish in
+    /// the background. The connections still open after the timeout are closed. The service stays
+    /// stopped until it is restarted, or started again by a reload.
+    async fn drain(
+        &mut self,
+        kind: ServiceKind,
+        id: usize,
+        timeout: Duration,
+        reply: tokio::sync::oneshot::Sender<Result<DrainSummary>>,
+    ) {
+        let entries = match kind {
+            ServiceKind::Ingress => &mut self.ingresses,
+            ServiceKind::Egress => &mut self.egresses,
+        };
+        let Some(entry) = entries.iter_mut().find(|entry| entry.id == id) else {
+            let _ = reply.send(
+                Err(TngError::ServiceNotFound)
+                    .with_context(|| format!("The {kind} {id} does not exist")),
+            );
+            return;
+        };
+        tracing::info!(%kind, id, ?timeout, "Draining service");
+        entry.stop_accepting().await;
+        entry.drained = true;
+        entry.readiness.set(ServiceReadiness::new(
+            ServiceState::Drained,
+            "drained through the control interface",
+        ));
+
+        // Do not block other requests while waiting for the connections
+        let service = entry.service.clone();
+        self.runtime
+            .spawn_supervised_task_with_span(entry.span.clone(), async move {
+                let active = service.active_connections();
+                let wait = async {
+                    while service.active_connections() > 0 {
+                        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+                    }
+                };
+                let closed = match tokio::time::timeout(timeout, wait).await {
+                    Ok(()) => 0,
+                    Err(_) => {
+                        let closed = service.active_connections();
+                        service.close_connections();
+                        closed
+                    }
+                };
+                let summary = DrainSummary {
+                    finished: active.saturating_sub(closed),
+                    closed,
+                };
+                tracing::info!(?summary, "Service drained");
+                let _ = reply.send(Ok(summary)); // Ignore any error occuring during send
+            });
+    }
+
+    /// Stop accepting new connections on all the services before shutting down, and wait for the
+    /// established connections to finish. The connections still open after the timeout are closed.
+    async fn drain_on_shutdown(&mut self, timeout: Duration) {
+        for entry in self.ingresses.iter_mut().chain(self.egresses.iter_mut()) {
+            entry.stop_accepting().await;
+        }
+        let services: Vec<_> = self
+            .ingresses
+            .iter()
+            .chain(self.egresses.iter())
+            .map(|entry| entry.service.clone())
+            .collect();
+        let active_connections = || {
+            services
+                .iter()
+                .map(|service| service.active_connections())
+                .sum::<usize>()
+        };
+
+        let active = active_connections();
+        tracing::info!(active, ?timeout, "Draining connections before shutdown");
+        let wait = async {
+            while active_connections() > 0 {
+                tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+            }
+        };
+        let aborted = match tokio::time::timeout(timeout, wait).await {
+            Ok(()) => 0,
+            Err(_) => {
+                let aborted = active_connections();
+                for service in &services {
+                    service.close_connections();
+                }
+                aborted
+            }
+        };
+        tracing::info!(
+            drained = active.saturating_sub(aborted),
+            aborted,
+            "Connections drained before shutdown"
+        );
+    }
+
+    /// Replace the service with a new one created from the same configuration, including the
+    /// updates of the verification parameters made through the control interface. Like a reload,
+    /// the connections established on the old service are kept until they are closed.
+    async fn restart(&mut self, kind: ServiceKind, id: usize) -> Result<()> {
+        let index = match kind {
+            ServiceKind::Ingress => self.ingresses.iter().position(|entry| entry.id == id),
+            ServiceKind::Egress => self.egresses.iter().position(|entry| entry.id == id),
+        }
+        .ok_or(TngError::ServiceNotFound)
+        .with_context(|| format!("The {kind} {id} does not exist"))?;
+        let mut entry = match kind {
+            ServiceKind::Ingress => {
+                let add_ingress = &self.config.add_ingress[index];
+                if matches!(add_ingress.ingress_mode, IngressMode::Hook(_)) {
+                    bail!("Ingress {id} uses 'hook' mode, which can not be restarted");
+                }
+                ServiceEntry::new_ingress(
+                    id,
+                    add_ingress,
+                    &self.service_metrics_creator,
+                    &self.runtime,
+                )
+                .await?
+            }
+            ServiceKind::Egress => {
+                let add_egress = &self.config.add_egress[index];
+                if matches!(add_egress.egress_mode, EgressMode::Hook(_)) {
+                    bail!("Egress {id} uses 'hook' mode, which can not be restarted");
+                }
+                ServiceEntry::new_egress(
+                    id,
+                    add_egress,
+                    &self.service_metrics_creator,
+                    &self.runtime,
+                )
+                .await?
+            }
+        };
+
+        let entries = match kind {
+            ServiceKind::Ingress => &mut self.ingresses,
+            ServiceKind::Egress => &mut self.egresses,
+        };
+        // Compared on reload with the configuration loaded, which has no updates
+        entry.config = entries[index].config.clone();
+        let old = std::mem::replace(&mut entries[index], entry);
+        // Stop the old service first, so that the new one can listen on the same port
+        old.stop().await;
+
+        // Like a reload, failures of the restarted service are reported to the caller until it is
+        // ready.
+        let (ready_sender, mut ready_receiver) = tokio::sync::mpsc::channel(1);
+        let (error_sender, mut error_receiver) = tokio::sync::mpsc::channel(1);
+        entries[index].spawn(&self.runtime, ready_sender, error_sender);
+        self.update_state().await;
+
+        tokio::select! {
+            _ = ready_receiver.recv() => {}
+            Some(error) = error_receiver.recv() => {
+                return Err(error.context("The restarted service failed"));
+            }
+            _ = self.runtime.shutdown_guard().cancelled() => {
+                bail!("The instance is shutting down");
+            }
+        }
+        self.forward_service_errors(error_receiver);
+        Ok(())
+    }
+
+    /// Update the parameters which the service verifies its peers with. The update is also recorded
+    /// in the configuration of the service, so that it is kept when the service is restarted.
+    async fn update_verify(
+        &mut self,
+        kind: ServiceKind,
+        id: usize,
+        update: &VerifyUpdate,
+    ) -> Result<()> {
+        let index = match kind {
+            ServiceKind::Ingress => self.ingresses.iter().position(|entry| entry.id == id),
+            ServiceKind::Egress => self.egresses.iter().position(|entry| entry.id == id),
+        }
+        .ok_or(TngError::ServiceNotFound)
+        .with_context(|| format!("The {kind} {id} does not exist"))?;
+        let (entry, ra_args) = match kind {
+            ServiceKind::Ingress => (
+                &self.ingresses[index],
+                &mut self.config.add_ingress[index].common.ra_args,
+            ),
+            ServiceKind::Egress => (
+                &self.egresses[index],
+                &mut self.config.add_egress[index].common.ra_args,
+            ),
+        };
+        let verify = ra_args
+            .verify
+            .as_ref()
+            .context("The peers are not verified, since `verify` is not configured")
+            .and_then(|verify| verify.with_update(update))
+            .map_err(TngError::InvalidVerifyUpdate)?;
+
+        entry.service.update_verify(update).await?;
+        ra_args.verify = Some(verify);
+        Ok(())
+    }
+
+    /// Apply the log filter change, and return the current filter.
+    fn update_log_filter(&self, directives: Option<LogFilterDirectives>) -> Result<String> {
+        let Some(log_filter) = &self.log_filter else {
+            bail!("The log filter can not be adjusted on this instance");
+        };
+        match directives {
+            None => {}
+            Some(LogFilterDirectives::Set(directives)) => log_filter.set(&directives)?,
+            Some(LogFilterDirectives::Reset) => log_filter.reset()?,
+        }
+        Ok(log_filter.current())
+    }
+
+    /// Point the status handles to the services currently running.
+    async fn update_state(&self) {
+        self.state
+            .replace(
+                self.ingresses
+                    .iter()
+                    .map(|entry| IngressStatusHandle {
+                        id: entry.id,
+                        flow: Arc::downgrade(&entry.service),
+                        readiness: entry.readiness.clone(),
+                    })
+                    .collect(),
+                self.egresses
+                    .iter()
+                    .map(|entry| EgressStatusHandle {
+                        id: entry.id,
+                        flow: Arc::downgrade(&entry.service),
+                        readiness: entry.readiness.clone(),
+                    })
+                    .collect(),
+            )
+            .await;
+    }
+
+    /// Apply a new configuration to the running instance.
+    ///
+    /// The ingresses and egresses in the new configuration are compared with the running ones.
+    /// Services whose configuration is unchanged keep running with their connections untouched,
+    /// services not present in the new configuration are stopped and the new or modified ones are
+    /// started. If the configuration is not specified, it is loaded again from the config source.
+    ///
+    /// If any of the new or modified services fails before it is ready, e.g. it can not listen on
+    /// its port, the services stopped by the reload are started again and the configuration is
+    /// left unchanged.
+    async fn reload(&mut self, new_config: Option<TngConfig>) -> Result<ReloadSummary> {
+        let mut new_config = match new_config {
+            Some(new_config) => new_config,
+            None => {
+                let Some(source) = &self.config_source else {
+                    bail!("The configuration is not loaded from a file, so it must be provided to reload");
+                };
+                source
+                    .load_with_overrides(&self.config_overrides)
+                    .context("Failed to load config")?
+            }
+        };
+        new_config
+            .resolve_ra_args()
+            .context("Invalid configuration")?;
+
+        // Only the ingresses and egresses can be changed at runtime. The fields are compared
+        // without being serialized where possible, since the custom metric exporters can not be.
+        for (field, changed) in [
+            (
+                "control_interface",
+                serde_json::to_value(&self.config.control_interface)?
+                    != serde_json::to_value(&new_config.control_interface)?,
+            ),
+            ("metric", self.config.metric != new_config.metric),
+            ("trace", self.config.trace != new_config.trace),
+            ("log", self.config.log != new_config.log),
+            (
+                "access_log",
+                self.config.access_log != new_config.access_log,
+            ),
+            ("audit_log", self.config.audit_log != new_config.audit_log),
+            ("runtime", self.config.runtime != new_config.runtime),
+        ] {
+            if changed {
+                tracing::warn!(
+                    field,
+                    "The field is changed in the new configuration, which is ignored since it can not be reloaded without a restart"
+                );
+            }
+        }
+
+        let ingress_plan = ReloadPlan::new(
+            &self.ingresses,
+            new_config
+                .add_ingress
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<Result<_, _>>()?,
+        );
+        let egress_plan = ReloadPlan::new(
+            &self.egresses,
+            new_config
+                .add_egress
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<Result<_, _>>()?,
+        );
+
+        // Create the new services first, so that nothing is changed if any of them is invalid.
+        // The new services get fresh ids, while the kept ones keep theirs.
+        let mut new_ingresses = HashMap::new();
+        for &index in &ingress_plan.to_start {
+            let add_ingress = &new_config.add_ingress[index];
+            if matches!(add_ingress.ingress_mode, IngressMode::Hook(_)) {
+                bail!("Ingress entry {index} uses 'hook' mode, which can not be reloaded");
+            }
+            let id = self.next_ingress_id;
+            self.next_ingress_id += 1;
+            let entry = ServiceEntry::new_ingress(
+                id,
+                add_ingress,
+                &self.service_metrics_creator,
+                &self.runtime,
+            )
+            .await
+            .with_context(|| format!("Failed to create ingress {index}"))?;
+            new_ingresses.insert(index, entry);
+        }
+        let mut new_egresses = HashMap::new();
+        for &index in &egress_plan.to_start {
+            let add_egress = &new_config.add_egress[index];
+            if matches!(add_egress.egress_mode, EgressMode::Hook(_)) {
+                bail!("Egress entry {index} uses 'hook' mode, which can not be reloaded");
+            }
+            let id = self.next_egress_id;
+            self.next_egress_id += 1;
+            let entry = ServiceEntry::new_egress(
+                id,
+                add_egress,
+                &self.service_metrics_creator,
+                &self.runtime,
+            )
+            .await
+            .with_context(|| format!("Failed to create egress {index}"))?;
+            new_egresses.insert(index, entry);
+        }
+
+        // Stop the removed services before starting the new ones, since they may listen on the
+        // same ports. They are only dropped once the new ones are ready, so that they can be
+        // started again if the reload fails.
+        for &index in &ingress_plan.to_stop {
+            self.ingresses[index].stop_accepting().await;
+        }
+        for &index in &egress_plan.to_stop {
+            self.egresses[index].stop_accepting().await;
+        }
+
+        // Failures of the services started by a reload are reported to the caller until they are
+        // ready, instead of shutting down the whole instance.
+        let started = new_ingresses.len() + new_egresses.len();
+        let (ready_sender, mut ready_receiver) = tokio::sync::mpsc::channel(started + 1);
+        let (error_sender, mut error_receiver) = tokio::sync::mpsc::channel(1);
+        for entry in new_ingresses.values_mut().chain(new_egresses.values_mut()) {
+            entry.spawn(&self.runtime, ready_sender.clone(), error_sender.clone());
+        }
+        drop(error_sender);
+
+        // Wait for the new services to be ready
+        let mut failure = None;
+        for _ in 0..started {
+            tokio::select! {
+                _ = ready_receiver.recv() => {}
+                Some(error) = error_receiver.recv() => {
+                    failure = Some(error.context("A service started by the reload failed"));
+                    break;
+                }
+                _ = self.runtime.shutdown_guard().cancelled() => {
+                    bail!("The instance is shutting down");
+                }
+            }
+        }
+        if let Some(error) = failure {
+            // Roll back to the services running before the reload
+            for entry in new_ingresses
+                .into_values()
+                .chain(new_egresses.into_values())
+            {
+                entry.stop().await;
+            }
+            for (entries, to_stop) in [
+                (&mut self.ingresses, &ingress_plan.to_stop),
+                (&mut self.egresses, &egress_plan.to_stop),
+            ] {
+                for &index in to_stop {
+                    let entry = &mut entries[index];
+                    if !entry.drained {
+                        entry.respawn(&self.runtime, self.error_channel.0.clone());
+                    }
+                }
+            }
+            return Err(error);
+        }
+        self.forward_service_errors(error_receiver);
+
+        // Assemble the services in the order of the new configuration.
+        let summary = ReloadSummary {
+            ingress: ingress_plan.summary(),
+            egress: egress_plan.summary(),
+        };
+        let assemble = |kept: &[Option<usize>],
+                        old: Vec<ServiceEntry>,
+                        new: &mut HashMap<usize, ServiceEntry>|
+         -> Vec<ServiceEntry> {
+            let mut old = old.into_iter().map(Some).collect::<Vec<_>>();
+            kept.iter()
+                .enumerate()
+                .filter_map(|(index, kept)| match kept {
+                    Some(kept) => old[*kept].take(),
+                    None => new.remove(&index),
+                })
+                .collect()
+        };
+        self.ingresses = assemble(
+            &ingress_plan.kept,
+            std::mem::take(&mut self.ingresses),
+            &mut new_ingresses,
+        );
+        self.egresses = assemble(
+            &egress_plan.kept,
+            std::mem::take(&mut self.egresses),
+            &mut new_egresses,
+        );
+        // The services kept are still running with the updates made through the control interface
+        for (id, kept) in ingress_plan.kept.iter().enumerate() {
+            if let Some(index) = kept {
+                new_config.add_ingress[id] = self.config.add_ingress[*index].clone();
+            }
+        }
+        for (id, kept) in egress_plan.kept.iter().enumerate() {
+            if let Some(index) = kept {
+                new_config.add_egress[id] = self.config.add_egress[*index].clone();
+            }
+        }
+        self.config = new_config;
+        self.update_state().await;
+
+        Ok(summary)
+    }
+
+    /// Report the failures received on `receiver` like those of the services the instance is
+    /// started with, once the caller is no longer waiting for the services to be ready.
+    fn forward_service_errors(&self, mut receiver: tokio::sync::mpsc::Receiver<anyhow::Error>) {
+        let error_sender = self.error_channel.0.clone();
+        self.runtime.spawn_supervised_task(async move {
+            while let Some(error) = receiver.recv().await {
+                let _ = error_sender.send(error).await; // Ignore any error occuring during send
+            }
+        });
+    }
+
     fn setup_metric_exporter(
         tng_config: &TngConfig,
     ) -> Result<Arc<dyn MeterProvider + Send + Sync>> {
@@ -422,9 +1075,10 @@ impl TngRuntime {
         reload_handle: &TracingReloadHandle,
     ) -> Result<()> {
         if let Some(log_args) = &tng_config.trace {
+            let sampler = log_args.sampler()?;
             for exporter in &log_args.exporters {
                 let exporter = exporter.instantiate()?;
-                let tracer_provider = exporter.into_sdk_tracer_provider();
+                let tracer_provider = exporter.into_sdk_tracer_provider(sampler.clone());
 
                 // Note here we register the tracer provider into tracing crate, so there is no need to call `opentelemetry::global::set_tracer_provider()`
                 let tracer = tracer_provider.tracer("tng");
@@ -444,4 +1098,1038 @@ impl TngRuntime {
 
         Ok(())
     }
+
+    fn setup_log_exporter(
+        tng_config: &TngConfig,
+        reload_handle: &TracingReloadHandle,
+    ) -> Result<()> {
+        if let Some(log_args) = &tng_config.log {
+            let max_level = log_args.max_level()?;
+            for exporter in &log_args.exporters {
+                let layer = exporter.instantiate(max_level)?;
+                let reload_result = reload_handle.modify(|layers| {
+                    (*layers).push(layer);
+                });
+                match reload_result {
+                    Ok(_) => {}
+                    Err(error) => tracing::warn!(?error, "Unable to add new layer"),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn setup_access_log(tng_config: &TngConfig, reload_handle: &TracingReloadHandle) -> Result<()> {
+        if let Some(access_log_args) = &tng_config.access_log {
+            let layer: TracingLayer = Box::new(AccessLogLayer::new(access_log_args)?);
+            let reload_result = reload_handle.modify(|layers| {
+                (*layers).push(layer);
+            });
+            match reload_result {
+                Ok(_) => {}
+                Err(error) => tracing::warn!(?error, "Unable to add new layer"),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn setup_audit_log(tng_config: &TngConfig, reload_handle: &TracingReloadHandle) -> Result<()> {
+        if let Some(audit_log_args) = &tng_config.audit_log {
+            let layer: TracingLayer = Box::new(AuditLogLayer::new(audit_log_args)?);
+            let reload_result = reload_handle.modify(|layers| {
+                (*layers).push(layer);
+            });
+            match reload_result {
+                Ok(_) => {}
+                Err(error) => tracing::warn!(?error, "Unable to add new layer"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The size of the channel for pending reload requests.
+const RELOAD_CHANNEL_SIZE: usize = 8;
+
+/// The size of the channel for the failures of the services.
+const ERROR_CHANNEL_SIZE: usize = 8;
+
+/// How often the remaining connections are checked while a service is being drained.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An ingress or egress service managed by the runtime.
+struct ServiceEntry {
+    /// Identifies the service in the status API and in its logs and metrics.
+    id: usize,
+    /// The configuration which the service is created from, without the updates made through the
+    /// control interface, used to detect changes on reload.
+    config: serde_json::Value,
+    service: Arc<dyn RegistedService>,
+    span: Span,
+    /// Cancelled to stop the service when it is removed by a reload.
+    stopper: CancellationToken,
+    task: Option<tokio::task::JoinHandle<SupervisedTaskResult<()>>>,
+    /// Whether the service is stopped by a drain. A drained service is never kept by a reload.
+    drained: bool,
+    readiness: ReadinessCell,
+    /// How the service is restarted when it fails. The instance is shut down if not set.
+    restart: Option<RestartPolicyArgs>,
+}
+
+impl ServiceEntry {
+    async fn new_ingress(
+        id: usize,
+        add_ingress: &AddIngressArgs,
+        service_metrics_creator: &ServiceMetricsCreator,
+        runtime: &TokioRuntime,
+    ) -> Result<Self> {
+        let span = tracing::info_span!("ingress", id);
+        let readiness = ReadinessCell::default();
+        let service = match &add_ingress.common.lazy_attest {
+            Some(lazy_attest) => {
+                lazy_attest.validate()?;
+                let add_ingress = add_ingress.clone();
+                let service_metrics_creator = service_metrics_creator.clone();
+                let runtime = runtime.clone();
+                Arc::new(LazyService::new(
+                    lazy_attest,
+                    readiness.clone(),
+                    Box::new(move || {
+                        let add_ingress = add_ingress.clone();
+                        let service_metrics_creator = service_metrics_creator.clone();
+                        let runtime = runtime.clone();
+                        Box::pin(async move {
+                            Self::create_ingress(
+                                id,
+                                &add_ingress,
+                                &service_metrics_creator,
+                                &runtime,
+                            )
+                            .await
+                        })
+                    }),
+                )) as Arc<_>
+            }
+            None => {
+                Self::create_ingress(id, add_ingress, service_metrics_creator, runtime)
+                    .instrument(span.clone())
+                    .await?
+            }
+        };
+        Ok(Self {
+            id,
+            config: serde_json::to_value(add_ingress)?,
+            service,
+            span,
+            stopper: CancellationToken::new(),
+            task: None,
+            drained: false,
+            readiness,
+            restart: add_ingress.common.restart.clone(),
+        })
+    }
+
+    async fn new_egress(
+        id: usize,
+        add_egress: &AddEgressArgs,
+        service_metrics_creator: &ServiceMetricsCreator,
+        runtime: &TokioRuntime,
+    ) -> Result<Self> {
+        let span = tracing::info_span!("egress", id);
+        let readiness = ReadinessCell::default();
+        let service = match &add_egress.common.lazy_attest {
+            Some(lazy_attest) => {
+                lazy_attest.validate()?;
+                let add_egress = add_egress.clone();
+                let service_metrics_creator = service_metrics_creator.clone();
+                let runtime = runtime.clone();
+                Arc::new(LazyService::new(
+                    lazy_attest,
+                    readiness.clone(),
+                    Box::new(move || {
+                        let add_egress = add_egress.clone();
+                        let service_metrics_creator = service_metrics_creator.clone();
+                        let runtime = runtime.clone();
+                        Box::pin(async move {
+                            Self::create_egress(id, &add_egress, &service_metrics_creator, &runtime)
+                                .await
+                        })
+                    }),
+                )) as Arc<_>
+            }
+            None => {
+                Self::create_egress(id, add_egress, service_metrics_creator, runtime)
+                    .instrument(span.clone())
+                    .await?
+            }
+        };
+        Ok(Self {
+            id,
+            config: serde_json::to_value(add_egress)?,
+            service,
+            span,
+            stopper: CancellationToken::new(),
+            task: None,
+            drained: false,
+            readiness,
+            restart: add_egress.common.restart.clone(),
+        })
+    }
+
+    async fn create_ingress(
+        id: usize,
+        add_ingress: &AddIngressArgs,
+        service_metrics_creator: &ServiceMetricsCreator,
+        runtime: &TokioRuntime,
+    ) -> Result<Arc<dyn RegistedService>> {
+        let pipe_buffer_size = add_ingress
+            .common
+            .buffer_size
+            .as_ref()
+            .map_or(DEFAULT_PIPE_BUF_SIZE, |buffer_size| buffer_size.pipe);
+
+        Ok(match &add_ingress.ingress_mode {
+            IngressMode::Mapping(mapping_args) => Arc::new(
+                IngressFlow::new(
+                    MappingIngress::new(id, mapping_args).await?,
+                    &add_ingress.common,
+                    service_metrics_creator,
+                    runtime.clone(),
+                )
+                .await?,
+            ) as Arc<_>,
+            IngressMode::HttpProxy(http_proxy_args) => Arc::new(
+                IngressFlow::new(
+                    HttpProxyIngress::new(
+                        id,
+                        http_proxy_args,
+                        AccessIngressMode::HttpProxy,
+                        pipe_buffer_size,
+                    )
+                    .await?,
+                    &add_ingress.common,
+                    service_metrics_creator,
+                    runtime.clone(),
+                )
+                .await?,
+            ) as Arc<_>,
+            IngressMode::Netfilter(netfilter_args) => {
+                #[cfg(not(target_os = "linux"))]
+                {
+                    let _ = netfilter_args;
+                    anyhow::bail!("Using ingress with 'netfilter' type is not supported on OS other than Linux");
+                }
+
+                #[cfg(target_os = "linux")]
+                {
+                    use crate::tunnel::ingress::netfilter::NetfilterIngress;
+                    Arc::new(
+                        IngressFlow::new(
+                            NetfilterIngress::new(id, netfilter_args).await?,
+                            &add_ingress.common,
+                            service_metrics_creator,
+                            runtime.clone(),
+                        )
+                        .await?,
+                    ) as Arc<_>
+                }
+            }
+            IngressMode::Socks5(socks5_args) => Arc::new(
+                IngressFlow::new(
+                    Socks5Ingress::new(id, socks5_args).await?,
+                    &add_ingress.common,
+                    service_metrics_creator,
+                    runtime.clone(),
+                )
+                .await?,
+            ) as Arc<_>,
+            IngressMode::Hook(hook_args) => Arc::new(
+                IngressFlow::new(
+                    HookIngress::new(id, hook_args, pipe_buffer_size).await?,
+                    &add_ingress.common,
+                    service_metrics_creator,
+                    runtime.clone(),
+                )
+                .await?,
+            ) as Arc<_>,
+            #[cfg(feature = "ingress-mapping-udp")]
+            IngressMode::MappingUdp(mapping_udp_args) => {
+                use crate::tunnel::ingress::datagram_flow::DatagramIngressFlow;
+                use crate::tunnel::ingress::mapping_udp::MappingUdpIngress;
+
+                let mut ingress = MappingUdpIngress::new(id, mapping_udp_args).await?;
+                ingress.set_max_datagram_size(
+                    add_ingress
+                        .common
+                        .quic
+                        .as_ref()
+                        .and_then(|q| q.max_datagram_size),
+                );
+
+                Arc::new(
+                    DatagramIngressFlow::new(
+                        ingress,
+                        &add_ingress.common,
+                        service_metrics_creator,
+                        runtime.clone(),
+                    )
+                    .await?,
+                ) as Arc<_>
+            }
+        })
+    }
+
+    async fn create_egress(
+        id: usize,
+        add_egress: &AddEgressArgs,
+        service_metrics_creator: &ServiceMetricsCreator,
+        runtime: &TokioRuntime,
+    ) -> Result<Arc<dyn RegistedService>> {
+        Ok(match &add_egress.egress_mode {
+            EgressMode::Mapping(mapping_args) => Arc::new(
+                EgressFlow::new(
+                    MappingEgress::new(id, mapping_args).await?,
+                    &add_egress.common,
+                    service_metrics_creator,
+                    runtime.clone(),
+                )
+                .await?,
+            ) as Arc<_>,
+            EgressMode::Netfilter(netfilter_args) => {
+                #[cfg(not(target_os = "linux"))]
+                {
+                    let _ = netfilter_args;
+                    anyhow::bail!("Using egress with 'netfilter' type is not supported on OS other than Linux");
+                }
+
+                #[cfg(target_os = "linux")]
+                {
+                    use crate::tunnel::egress::netfilter::NetfilterEgress;
+                    Arc::new(
+                        EgressFlow::new(
+                            NetfilterEgress::new(id, netfilter_args).await?,
+                            &add_egress.common,
+                            service_metrics_creator,
+                            runtime.clone(),
+                        )
+                        .await?,
+                    ) as Arc<_>
+                }
+            }
+            EgressMode::Hook(hook_args) => {
+                use crate::tunnel::egress::hook::HookEgress;
+
+                Arc::new(
+                    EgressFlow::new(
+                        HookEgress::new(id, hook_args),
+                        &add_egress.common,
+                        service_metrics_creator,
+                        runtime.clone(),
+                    )
+                    .await?,
+                ) as Arc<_>
+            }
+            #[cfg(feature = "egress-mapping-udp")]
+            EgressMode::MappingUdp(mapping_udp_args) => {
+                use crate::tunnel::egress::datagram_flow::DatagramEgressFlow;
+                use crate::tunnel::egress::mapping_udp::MappingUdpEgress;
+
+                let mut egress = MappingUdpEgress::new(id, mapping_udp_args).await?;
+                egress.set_max_datagram_size(
+                    add_egress
+                        .common
+                        .quic
+                        .as_ref()
+                        .and_then(|q| q.max_datagram_size),
+                );
+
+                Arc::new(
+                    DatagramEgressFlow::new(
+                        egress,
+                        &add_egress.common,
+                        service_metrics_creator,
+                        runtime.clone(),
+                    )
+                    .await?,
+                ) as Arc<_>
+            }
+        })
+    }
+
+    fn spawn(
+        &mut self,
+        runtime: &TokioRuntime,
+        ready_sender: tokio::sync::mpsc::Sender<()>,
+        error_sender: tokio::sync::mpsc::Sender<anyhow::Error>,
+    ) {
+        let service = self.service.clone();
+        let stopper = self.stopper.clone();
+        let readiness = self.readiness.clone();
+        let restart = self.restart.clone();
+        self.task = Some(
+            runtime.spawn_supervised_task_with_span(self.span.clone(), async move {
+                // Only the first ready signal is forwarded, since a restarted service is not
+                // counted again by the runtime
+                let mut ready_sender = Some(ready_sender);
+                let mut retries = 0;
+                loop {
+                    // Intercept the ready signal to update the readiness of this service
+                    let (service_ready_sender, mut service_ready_receiver) =
+                        tokio::sync::mpsc::channel(1);
+                    let mut became_ready = false;
+                    let forward_ready = async {
+                        if service_ready_receiver.recv().await.is_some() {
+                            became_ready = true;
+                            readiness.set(ServiceReadiness::ready());
+                            if let Some(ready_sender) = ready_sender.take() {
+                                let _ = ready_sender.send(()).await; // Ignore any error occuring during send
+                            }
+                        }
+                        std::future::pending::<()>().await
+                    };
+
+                    let error = tokio::select! {
+                        _ = stopper.cancelled() => {
+                            tracing::info!("service stopped");
+                            return;
+                        }
+                        _ = forward_ready => return,
+                        res = service.serve(service_ready_sender) => {
+                            match res {
+                                Ok(()) => {
+                                    readiness.set(ServiceReadiness::new(
+                                        ServiceState::Failed,
+                                        "the service exited unexpectedly",
+                                    ));
+                                    return;
+                                }
+                                Err(error) => error,
+                            }
+                        }
+                    };
+                    tracing::error!(?error, "service failed");
+
+                    // A service which was ready before failing starts over with the retries
+                    if became_ready {
+                        retries = 0;
+                    }
+                    let Some(policy) = restart
+                        .as_ref()
+                        .filter(|policy| retries < policy.max_retries)
+                    else {
+                        readiness.set(ServiceReadiness::new(
+                            ServiceState::Failed,
+                            format!("{error:#}"),
+                        ));
+                        let _ = error_sender.send(error).await;
+                        return;
+                    };
+
+                    let backoff = policy.backoff(retries);
+                    retries += 1;
+                    tracing::warn!(retries, ?backoff, "Restarting the failed service");
+                    readiness.set(ServiceReadiness::new(
+                        ServiceState::Starting,
+                        format!("restarting after failure: {error:#}"),
+                    ));
+                    tokio::select! {
+                        _ = stopper.cancelled() => {
+                            tracing::info!("service stopped");
+                            return;
+                        }
+                        _ = tokio::time::sleep(backoff) => {}
+                    }
+                }
+            }),
+        );
+    }
+
+    /// Start the service again after it is stopped from accepting new connections.
+    fn respawn(
+        &mut self,
+        runtime: &TokioRuntime,
+        error_sender: tokio::sync::mpsc::Sender<anyhow::Error>,
+    ) {
+        self.stopper = CancellationToken::new();
+        // Nobody waits for the service to be ready again
+        self.spawn(runtime, tokio::sync::mpsc::channel(1).0, error_sender);
+    }
+
+    /// Stop accepting new connections on this service. The connections already established are
+    /// kept until they are closed.
+    async fn stop(mut self) {
+        self.stop_accepting().await;
+    }
+
+    async fn stop_accepting(&mut self) {
+        self.stopper.cancel();
+        if let Some(task) = self.task.take() {
+            // Wait for the listeners to be released
+            let _ = task.await;
+        }
+    }
+}
+
+/// Creates a service of a [`LazyService`].
+type ServiceCreator =
+    Box<dyn Fn() -> BoxFuture<'static, Result<Arc<dyn RegistedService>>> + Send + Sync>;
+
+/// A service with `lazy_attest`, which is created when it is served instead of when the instance
+/// is created, and retried until it succeeds, e.g. once the attestation agent is reachable. Until
+/// then, the service is reported as starting with the last error as the reason.
+struct LazyService {
+    create: ServiceCreator,
+    retry_interval: Duration,
+    readiness: ReadinessCell,
+    service: tokio::sync::OnceCell<Arc<dyn RegistedService>>,
+}
+
+impl LazyService {
+    fn new(lazy_attest: &LazyAttestArgs, readiness: ReadinessCell, create: ServiceCreator) -> Self {
+        Self {
+            create,
+            retry_interval: lazy_attest.retry_interval(),
+            readiness,
+            service: tokio::sync::OnceCell::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl RegistedService for LazyService {
+    async fn serve(&self, ready: tokio::sync::mpsc::Sender<()>) -> Result<()> {
+        let service = loop {
+            match self.service.get_or_try_init(|| (self.create)()).await {
+                Ok(service) => break service,
+                Err(error) => {
+                    tracing::warn!(
+                        ?error,
+                        retry_interval = ?self.retry_interval,
+                        "Failed to create the service, retrying since `lazy_attest` is set"
+                    );
+                    self.readiness.set(ServiceReadiness::new(
+                        ServiceState::Starting,
+                        format!("waiting for the attestation to be available: {error:#}"),
+                    ));
+                    tokio::time::sleep(self.retry_interval).await;
+                }
+            }
+        };
+        service.serve(ready).await
+    }
+
+    fn active_connections(&self) -> usize {
+        self.service
+            .get()
+            .map_or(0, |service| service.active_connections())
+    }
+
+    fn close_connections(&self) {
+        if let Some(service) = self.service.get() {
+            service.close_connections();
+        }
+    }
+
+    fn terminate_connections(&self, selector: &ConnectionSelector) -> usize {
+        self.service
+            .get()
+            .map_or(0, |service| service.terminate_connections(selector))
+    }
+
+    async fn update_verify(&self, update: &VerifyUpdate) -> Result<()> {
+        match self.service.get() {
+            Some(service) => service.update_verify(update).await,
+            None => bail!("The service is not created yet, since the attestation is not available"),
+        }
+    }
+}
+
+#[async_trait]
+impl StatusProvider for LazyService {
+    async fn query_status(&self, path: &[&str]) -> Result<StatusQueryResult, TngError> {
+        match self.service.get() {
+            Some(service) => service.query_status(path).await,
+            None => Err(TngError::StatusPathNotFound),
+        }
+    }
+}
+
+/// How the running services of one kind (ingress or egress) are changed by a reload.
+struct ReloadPlan {
+    /// For each entry in the new configuration, the index of the running service to keep.
+    /// `None` if a new service should be started for it.
+    kept: Vec<Option<usize>>,
+    /// The entries in the new configuration for which a new service should be started.
+    to_start: Vec<usize>,
+    /// The indexes of the running services to stop.
+    to_stop: Vec<usize>,
+}
+
+impl ReloadPlan {
+    fn new(running: &[ServiceEntry], new_configs: Vec<serde_json::Value>) -> Self {
+        Self::from_configs(
+            &running
+                .iter()
+                .map(|entry| (!entry.drained).then_some(&entry.config))
+                .collect::<Vec<_>>(),
+            &new_configs,
+        )
+    }
+
+    /// The running services given as `None` are never kept.
+    fn from_configs(
+        running: &[Option<&serde_json::Value>],
+        new_configs: &[serde_json::Value],
+    ) -> Self {
+        let mut claimed = vec![false; running.len()];
+        let kept = new_configs
+            .iter()
+            .map(|new_config| {
+                let index = (0..running.len())
+                    .find(|&index| !claimed[index] && running[index] == Some(new_config))?;
+                claimed[index] = true;
+                Some(index)
+            })
+            .collect::<Vec<_>>();
+
+        let to_start = kept
+            .iter()
+            .enumerate()
+            .filter_map(|(id, kept)| kept.is_none().then_some(id))
+            .collect();
+        let to_stop = claimed
+            .iter()
+            .enumerate()
+            .filter_map(|(index, claimed)| (!claimed).then_some(index))
+            .collect();
+
+        Self {
+            kept,
+            to_start,
+            to_stop,
+        }
+    }
+
+    fn summary(&self) -> ReloadCounts {
+        ReloadCounts {
+            kept: self.kept.len() - self.to_start.len(),
+            started: self.to_start.len(),
+            stopped: self.to_stop.len(),
+        }
+    }
+}
+
+/// The result of a configuration reload.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadSummary {
+    pub ingress: ReloadCounts,
+    pub egress: ReloadCounts,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadCounts {
+    /// The number of services which are unchanged and kept running.
+    pub kept: usize,
+    /// The number of services which are added or modified, and started.
+    pub started: usize,
+    /// The number of services which are removed or modified, and stopped.
+    pub stopped: usize,
+}
+
+/// The kind of a service which can be operated through the control interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceKind {
+    Ingress,
+    Egress,
+}
+
+impl fmt::Display for ServiceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceKind::Ingress => write!(f, "ingress"),
+            ServiceKind::Egress => write!(f, "egress"),
+        }
+    }
+}
+
+/// The result of draining a service.
+#[derive(Debug, Clone, Serialize)]
+pub struct DrainSummary {
+    /// The number of connections which are finished before the timeout.
+    pub finished: usize,
+    /// The number of connections which are still open after the timeout, and closed.
+    pub closed: usize,
+}
+
+enum RuntimeRequest {
+    Reload {
+        config: Option<TngConfig>,
+        reply: tokio::sync::oneshot::Sender<Result<ReloadSummary>>,
+    },
+    Drain {
+        kind: ServiceKind,
+        id: usize,
+        timeout: Duration,
+        reply: tokio::sync::oneshot::Sender<Result<DrainSummary>>,
+    },
+    Restart {
+        kind: ServiceKind,
+        id: usize,
+        reply: tokio::sync::oneshot::Sender<Result<()>>,
+    },
+    UpdateVerify {
+        kind: ServiceKind,
+        id: usize,
+        update: VerifyUpdate,
+        reply: tokio::sync::oneshot::Sender<Result<()>>,
+    },
+    /// Query the log filter if `directives` is `None`, or change it.
+    LogFilter {
+        directives: Option<LogFilterDirectives>,
+        reply: tokio::sync::oneshot::Sender<Result<String>>,
+    },
+}
+
+/// A change of the log filter.
+#[derive(Debug, Clone)]
+pub enum LogFilterDirectives {
+    /// Replace the filter with the directives, in the same syntax as `RUST_LOG`.
+    Set(String),
+    /// Restore the filter at startup.
+    Reset,
+}
+
+/// A handle to reload the configuration of a serving [`TngRuntime`], and to operate its
+/// services.
+#[derive(Clone)]
+pub struct ConfigReloadHandle {
+    sender: tokio::sync::mpsc::Sender<ReloadRequest>,
+}
+
+impl ConfigReloadHandle {
+    /// Reload with the new configuration, or with the configuration loaded from the config file
+    /// again if `config` is `None`.
+    pub async fn reload(&self, config: Option<TngConfig>) -> Result<ReloadSummary> {
+        let (reply, receiver) = tokio::sync::oneshot::channel();
+        self.sender
+            .send(RuntimeRequest::Reload { config, reply })
+            .await
+            .map_err(|_| anyhow!("The instance is not serving"))?;
+        receiver
+            .await
+            .context("The instance exited before the reload is finished")?
+    }
+
+    /// Drain the service: stop accepting new connections and wait for the established ones to
+    /// finish, closing those still open after the timeout.
+    pub async fn drain(
+        &self,
+        kind: ServiceKind,
+        id: usize,
+        timeout: Duration,
+    ) -> Result<DrainSummary> {
+        let (reply, receiver) = tokio::sync::oneshot::channel();
+        self.sender
+            .send(RuntimeRequest::Drain {
+                kind,
+                id,
+                timeout,
+                reply,
+            })
+            .await
+            .map_err(|_| anyhow!("The instance is not serving"))?;
+        receiver
+            .await
+            .context("The instance exited before the drain is finished")?
+    }
+
+    /// Query the log filter if `directives` is `None`, or change it. The current filter is
+    /// returned.
+    pub async fn log_filter(&self, directives: Option<LogFilterDirectives>) -> Result<String> {
+        let (reply, receiver) = tokio::sync::oneshot::channel();
+        self.sender
+            .send(RuntimeRequest::LogFilter { directives, reply })
+            .await
+            .map_err(|_| anyhow!("The instance is not serving"))?;
+        receiver
+            .await
+            .context("The instance exited before the log filter is updated")?
+    }
+
+    /// Restart the service with the same configuration.
+    pub async fn restart(&self, kind: ServiceKind, id: usize) -> Result<()> {
+        let (reply, receiver) = tokio::sync::oneshot::channel();
+        self.sender
+            .send(RuntimeRequest::Restart { kind, id, reply })
+            .await
+            .map_err(|_| anyhow!("The instance is not serving"))?;
+        receiver
+            .await
+            .context("The instance exited before the restart is finished")?
+    }
+
+    /// Update the parameters which the service verifies its peers with, from the next handshake
+    /// on.
+    pub async fn update_verify(
+        &self,
+        kind: ServiceKind,
+        id: usize,
+        update: VerifyUpdate,
+    ) -> Result<()> {
+        let (reply, receiver) = tokio::sync::oneshot::channel();
+        self.sender
+            .send(RuntimeRequest::UpdateVerify {
+                kind,
+                id,
+                update,
+                reply,
+            })
+            .await
+            .map_err(|_| anyhow!("The instance is not serving"))?;
+        receiver
+            .await
+            .context("The instance exited before the update is finished")?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::status::{StatusProvider, StatusQueryResult};
+
+    #[test]
+    fn test_reload_plan() {
+        let (a, b, c, d) = (json!("a"), json!("b"), json!("c"), json!("d"));
+
+        // `b` is removed, `d` is added and `c` is moved
+        let plan = ReloadPlan::from_configs(
+            &[Some(&a), Some(&b), Some(&c)],
+            &[a.clone(), c.clone(), d.clone()],
+        );
+        assert_eq!(plan.kept, vec![Some(0), Some(2), None]);
+        assert_eq!(plan.to_start, vec![2]);
+        assert_eq!(plan.to_stop, vec![1]);
+
+        // Duplicated entries are matched one by one
+        let plan =
+            ReloadPlan::from_configs(&[Some(&a), Some(&a)], &[a.clone(), a.clone(), a.clone()]);
+        assert_eq!(plan.kept, vec![Some(0), Some(1), None]);
+        assert_eq!(plan.to_start, vec![2]);
+        assert!(plan.to_stop.is_empty());
+
+        // Drained services are started again
+        let plan = ReloadPlan::from_configs(&[None, Some(&b)], &[a.clone(), b.clone()]);
+        assert_eq!(plan.kept, vec![None, Some(1)]);
+        assert_eq!(plan.to_start, vec![0]);
+        assert_eq!(plan.to_stop, vec![0]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+    async fn test_reload_config() -> Result<()> {
+        let ingress = |port: u16| {
+            json!({
+                "mapping": {
+                    "in": { "port": port },
+                    "out": {
+                        "host": "127.0.0.1",
+                        "port": portpicker::pick_unused_port().unwrap()
+                    }
+                },
+                "no_ra": true
+            })
+        };
+        let (port1, port2) = (
+            portpicker::pick_unused_port().unwrap(),
+            portpicker::pick_unused_port().unwrap(),
+        );
+        let (ingress1, ingress2) = (ingress(port1), ingress(port2));
+
+        let config: TngConfig = serde_json::from_value(json!({ "add_ingress": [ingress1] }))?;
+        let (ready_sender, ready_receiver) = tokio::sync::oneshot::channel();
+        let tng_runtime = TngRuntime::from_config(config).await?;
+        let canceller = tng_runtime.canceller();
+        let reload_handle = tng_runtime.config_reload_handle();
+
+        #[allow(clippy::disallowed_methods)]
+        let join_handle =
+            tokio::task::spawn(async move { tng_runtime.serve_with_ready(ready_sender).await });
+        ready_receiver.await?;
+
+        // Add a new ingress, the existing one is kept
+        let summary = reload_handle
+            .reload(Some(serde_json::from_value(
+                json!({ "add_ingress": [ingress1, ingress2] }),
+            )?))
+            .await?;
+        assert_eq!(
+            (
+                summary.ingress.kept,
+                summary.ingress.started,
+                summary.ingress.stopped
+            ),
+            (1, 1, 0)
+        );
+        tokio::net::TcpStream::connect(("127.0.0.1", port1)).await?;
+        tokio::net::TcpStream::connect(("127.0.0.1", port2)).await?;
+
+        // Remove the first ingress
+        let summary = reload_handle
+            .reload(Some(serde_json::from_value(
+                json!({ "add_ingress": [ingress2] }),
+            )?))
+            .await?;
+        assert_eq!(
+            (
+                summary.ingress.kept,
+                summary.ingress.started,
+                summary.ingress.stopped
+            ),
+            (1, 0, 1)
+        );
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", port1))
+            .await
+            .is_err());
+        tokio::net::TcpStream::connect(("127.0.0.1", port2)).await?;
+
+        // Replace the ingress with one which can not listen on its port, the reload is rolled back
+        let occupied = std::net::TcpListener::bind("0.0.0.0:0")?;
+        let port3 = occupied.local_addr()?.port();
+        assert!(reload_handle
+            .reload(Some(serde_json::from_value(
+                json!({ "add_ingress": [ingress(port3)] }),
+            )?))
+            .await
+            .is_err());
+        let mut retries = 0;
+        while let Err(error) = tokio::net::TcpStream::connect(("127.0.0.1", port2)).await {
+            retries += 1;
+            if retries > 50 {
+                return Err(error.into());
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+
+        // There is no config file to reload from
+        assert!(reload_handle.reload(None).await.is_err());
+
+        canceller.cancel();
+        join_handle.await??;
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+    async fn test_reload_keeps_ids() -> Result<()> {
+        let ingress = || {
+            json!({
+                "mapping": {
+                    "in": { "port": portpicker::pick_unused_port().unwrap() },
+                    "out": {
+                        "host": "127.0.0.1",
+                        "port": portpicker::pick_unused_port().unwrap()
+                    }
+                },
+                "no_ra": true
+            })
+        };
+        let (a, b, c) = (ingress(), ingress(), ingress());
+
+        let config: TngConfig = serde_json::from_value(json!({ "add_ingress": [a, b] }))?;
+        let (ready_sender, ready_receiver) = tokio::sync::oneshot::channel();
+        let tng_runtime = TngRuntime::from_config(config).await?;
+        let canceller = tng_runtime.canceller();
+        let reload_handle = tng_runtime.config_reload_handle();
+        let state = tng_runtime.state();
+
+        #[allow(clippy::disallowed_methods)]
+        let join_handle =
+            tokio::task::spawn(async move { tng_runtime.serve_with_ready(ready_sender).await });
+        ready_receiver.await?;
+
+        let ids = || async {
+            match state.query_status(&["ingress"]).await {
+                Ok(StatusQueryResult::Subtree(ids)) => Ok(ids),
+                _ => Err(anyhow!("Failed to list the ingresses")),
+            }
+        };
+        assert_eq!(ids().await?, ["0", "1"]);
+
+        // `b` moves to the front and keeps its id, `c` gets an id which was never used
+        reload_handle
+            .reload(Some(serde_json::from_value(
+                json!({ "add_ingress": [b, c] }),
+            )?))
+            .await?;
+        assert_eq!(ids().await?, ["1", "2"]);
+
+        // The id of the removed `a` is not reused
+        reload_handle
+            .reload(Some(serde_json::from_value(
+                json!({ "add_ingress": [a, b, c] }),
+            )?))
+            .await?;
+        assert_eq!(ids().await?, ["3", "1", "2"]);
+
+        canceller.cancel();
+        join_handle.await??;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "metric")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+    async fn test_reload_with_custom_metric_exporter() -> Result<()> {
+        use crate::config::observability::metric::{MetricArgs, MetricExporterType};
+        use crate::SimpleMetric;
+
+        let ingress = || {
+            json!({
+                "mapping": {
+                    "in": { "port": portpicker::pick_unused_port().unwrap() },
+                    "out": {
+                        "host": "127.0.0.1",
+                        "port": portpicker::pick_unused_port().unwrap()
+                    }
+                },
+                "no_ra": true
+            })
+        };
+        let metric = MetricArgs {
+            exporters: vec![MetricExporterType::Custom {
+                step: 60,
+                exporter: Arc::new(|_: &[SimpleMetric]| Ok(())),
+            }],
+            ..Default::default()
+        };
+
+        let mut config: TngConfig = serde_json::from_value(json!({ "add_ingress": [ingress()] }))?;
+        config.metric = Some(metric.clone());
+        let (ready_sender, ready_receiver) = tokio::sync::oneshot::channel();
+        let tng_runtime = TngRuntime::from_config(config).await?;
+        let canceller = tng_runtime.canceller();
+        let reload_handle = tng_runtime.config_reload_handle();
+
+        #[allow(clippy::disallowed_methods)]
+        let join_handle =
+            tokio::task::spawn(async move { tng_runtime.serve_with_ready(ready_sender).await });
+        ready_receiver.await?;
+
+        // The custom exporter, which can not be serialized, does not prevent the reload
+        let mut new_config: TngConfig =
+            serde_json::from_value(json!({ "add_ingress": [ingress()] }))?;
+        new_config.metric = Some(metric);
+        let summary = reload_handle.reload(Some(new_config)).await?;
+        assert_eq!((summary.ingress.started, summary.ingress.stopped), (1, 1));
+
+        canceller.cancel();
+        join_handle.await??;
+
+        Ok(())
+    }
 }
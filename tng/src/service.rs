@@ -1,8 +1,10 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
 use tokio::sync::mpsc::Sender;
 
+use crate::config::ra::VerifyUpdate;
 use crate::status::StatusProvider;
+use crate::tunnel::connections::ConnectionSelector;
 
 /// The registered service is a core component of the TNG runtime. After the TNG runtime is created,
 /// they service will be started and keeping running in a background async task. Any service failed
@@ -13,4 +15,24 @@ use crate::status::StatusProvider;
 #[async_trait]
 pub trait RegistedService: StatusProvider + Send + Sync {
     async fn serve(&self, ready: Sender<()>) -> Result<()>;
+
+    /// The number of connections still being served, which are waited for when the service is
+    /// drained.
+    fn active_connections(&self) -> usize {
+        0
+    }
+
+    /// Close all the connections still being served, once a drain times out.
+    fn close_connections(&self) {}
+
+    /// Close the selected connections, and return how many of them are closed.
+    fn terminate_connections(&self, _selector: &ConnectionSelector) -> usize {
+        0
+    }
+
+    /// Update the parameters which the peers are verified with. They apply to the handshakes from
+    /// now on, while the established sessions are kept.
+    async fn update_verify(&self, _update: &VerifyUpdate) -> Result<()> {
+        bail!("Updating the verification parameters is not supported by the service")
+    }
 }
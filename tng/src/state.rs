@@ -1,14 +1,120 @@
 use std::borrow::Cow;
-use std::sync::Weak;
+use std::sync::{Arc, Mutex, Weak};
 
 use crate::error::TngError;
+use crate::runtime::ServiceKind;
 use crate::service::RegistedService;
 use crate::status::{StatusProvider, StatusQueryResult};
+use crate::tunnel::connections::ConnectionSelector;
 use async_trait::async_trait;
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+/// The maximum length of the reason reported in the `service_health` metric.
+const REASON_SUMMARY_MAX_LEN: usize = 128;
+
+/// The lifecycle state of an ingress or egress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceState {
+    /// The service is created, but not accepting connections yet.
+    Starting,
+    Ready,
+    /// The service is drained through the control interface.
+    Drained,
+    /// The service exited with an error.
+    Failed,
+}
+
+/// The readiness of an ingress or egress, with the reason when it is not ready.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceReadiness {
+    pub state: ServiceState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ServiceReadiness {
+    pub fn new(state: ServiceState, reason: impl Into<String>) -> Self {
+        Self {
+            state,
+            reason: Some(reason.into()),
+        }
+    }
+
+    pub fn ready() -> Self {
+        Self {
+            state: ServiceState::Ready,
+            reason: None,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state == ServiceState::Ready
+    }
+
+    /// The health reported in the `service_health` metric: `ready`, `degraded` if the service is
+    /// not accepting connections but has not failed, e.g. while starting or drained, or `failed`.
+    pub fn health(&self) -> &'static str {
+        match self.state {
+            ServiceState::Ready => "ready",
+            ServiceState::Starting | ServiceState::Drained => "degraded",
+            ServiceState::Failed => "failed",
+        }
+    }
+
+    /// The first line of the reason, truncated to keep the metric attribute short.
+    pub fn reason_summary(&self) -> Option<String> {
+        let reason = self.reason.as_deref()?.lines().next().unwrap_or_default();
+        Some(match reason.char_indices().nth(REASON_SUMMARY_MAX_LEN) {
+            Some((end, _)) => format!("{}...", &reason[..end]),
+            None => reason.to_owned(),
+        })
+    }
+}
+
+impl Default for ServiceReadiness {
+    fn default() -> Self {
+        Self::new(
+            ServiceState::Starting,
+            "waiting for the service to start listening",
+        )
+    }
+}
+
+/// The readiness of a service, updated by the runtime and read by the control interface.
+///
+/// This struct is free to be cloned and used anywhere.
+#[derive(Debug, Clone, Default)]
+pub struct ReadinessCell(Arc<Mutex<ServiceReadiness>>);
+
+impl ReadinessCell {
+    pub fn get(&self) -> ServiceReadiness {
+        self.0.lock().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+
+    pub fn set(&self, readiness: ServiceReadiness) {
+        *self.0.lock().unwrap_or_else(|p| p.into_inner()) = readiness;
+    }
+}
+
+/// The readiness of the instance and of each of its ingresses and egresses.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    /// Whether the instance is ready, i.e. it has finished starting and all of its ingresses and
+    /// egresses are ready.
+    pub ready: bool,
+    pub ingress: Vec<ServiceReadiness>,
+    pub egress: Vec<ServiceReadiness>,
+}
 
 /// Lightweight handle for querying an egress's status tree.
+#[derive(Clone)]
 pub struct EgressStatusHandle {
+    /// The id of the egress, which is kept across reloads.
+    pub id: usize,
     pub flow: Weak<dyn RegistedService>,
+    pub readiness: ReadinessCell,
 }
 
 #[async_trait]
@@ -23,8 +129,12 @@ impl StatusProvider for EgressStatusHandle {
 }
 
 /// Lightweight handle for querying an ingress's status tree.
+#[derive(Clone)]
 pub struct IngressStatusHandle {
+    /// The id of the ingress, which is kept across reloads.
+    pub id: usize,
     pub flow: Weak<dyn RegistedService>,
+    pub readiness: ReadinessCell,
 }
 
 #[async_trait]
@@ -43,8 +153,9 @@ pub struct TngState {
         tokio::sync::watch::Sender<bool>,
         tokio::sync::watch::Receiver<bool>,
     ),
-    pub egresses: Vec<EgressStatusHandle>,
-    pub ingresses: Vec<IngressStatusHandle>,
+    /// Replaced as a whole when the configuration is reloaded.
+    pub egresses: RwLock<Vec<EgressStatusHandle>>,
+    pub ingresses: RwLock<Vec<IngressStatusHandle>>,
 }
 
 impl Default for TngState {
@@ -57,17 +168,100 @@ impl TngState {
     pub fn new() -> Self {
         TngState {
             ready: tokio::sync::watch::channel(false),
-            egresses: Vec::new(),
-            ingresses: Vec::new(),
+            egresses: RwLock::new(Vec::new()),
+            ingresses: RwLock::new(Vec::new()),
         }
     }
 
     pub fn add_egress(&mut self, handle: EgressStatusHandle) {
-        self.egresses.push(handle);
+        self.egresses.get_mut().push(handle);
     }
 
     pub fn add_ingress(&mut self, handle: IngressStatusHandle) {
-        self.ingresses.push(handle);
+        self.ingresses.get_mut().push(handle);
+    }
+
+    /// Replace all the handles after the configuration is reloaded.
+    pub async fn replace(
+        &self,
+        ingresses: Vec<IngressStatusHandle>,
+        egresses: Vec<EgressStatusHandle>,
+    ) {
+        *self.ingresses.write().await = ingresses;
+        *self.egresses.write().await = egresses;
+    }
+
+    /// Close the selected connections of an ingress or egress, and return how many of them are
+    /// closed. `None` is returned if the service does not exist.
+    pub async fn terminate_connections(
+        &self,
+        kind: ServiceKind,
+        id: usize,
+        selector: &ConnectionSelector,
+    ) -> Option<usize> {
+        let flow = match kind {
+            ServiceKind::Ingress => self
+                .ingresses
+                .read()
+                .await
+                .iter()
+                .find(|handle| handle.id == id)?
+                .flow
+                .upgrade()?,
+            ServiceKind::Egress => self
+                .egresses
+                .read()
+                .await
+                .iter()
+                .find(|handle| handle.id == id)?
+                .flow
+                .upgrade()?,
+        };
+        Some(flow.terminate_connections(selector))
+    }
+
+    /// The readiness of each service without waiting, or `None` if the services are being
+    /// replaced at the moment.
+    pub fn try_services_readiness(&self) -> Option<Vec<(ServiceKind, usize, ServiceReadiness)>> {
+        let ingresses = self.ingresses.try_read().ok()?;
+        let egresses = self.egresses.try_read().ok()?;
+        let ingresses = ingresses
+            .iter()
+            .enumerate()
+            .map(|(id, handle)| (ServiceKind::Ingress, id, handle.readiness.get()));
+        let egresses = egresses
+            .iter()
+            .enumerate()
+            .map(|(id, handle)| (ServiceKind::Egress, id, handle.readiness.get()));
+        Some(ingresses.chain(egresses).collect())
+    }
+
+    /// Report the readiness of the instance and of each service.
+    pub async fn readiness(&self) -> ReadinessReport {
+        let ingress: Vec<_> = self
+            .ingresses
+            .read()
+            .await
+            .iter()
+            .map(|handle| handle.readiness.get())
+            .collect();
+        let egress: Vec<_> = self
+            .egresses
+            .read()
+            .await
+            .iter()
+            .map(|handle| handle.readiness.get())
+            .collect();
+        let ready = *self.ready.1.borrow()
+            && ingress
+                .iter()
+                .chain(egress.iter())
+                .all(ServiceReadiness::is_ready);
+        ReadinessReport {
+            ready,
+            ingress,
+            egress,
+        }
     }
 }
 
@@ -77,22 +271,33 @@ impl StatusProvider for TngState {
         match path {
             [] => {
                 let mut children = Vec::new();
-                if !self.egresses.is_empty() {
+                if !self.egresses.read().await.is_empty() {
                     children.push(Cow::Borrowed("egress"));
                 }
-                if !self.ingresses.is_empty() {
+                if !self.ingresses.read().await.is_empty() {
                     children.push(Cow::Borrowed("ingress"));
                 }
                 Ok(StatusQueryResult::Subtree(children))
             }
             ["egress"] => Ok(StatusQueryResult::Subtree(
-                (0..self.egresses.len())
-                    .map(|i| Cow::Owned(i.to_string()))
+                self.egresses
+                    .read()
+                    .await
+                    .iter()
+                    .map(|handle| Cow::Owned(handle.id.to_string()))
                     .collect(),
             )),
             ["egress", id, rest @ ..] => {
                 if let Ok(id) = id.parse::<usize>() {
-                    if let Some(handle) = self.egresses.get(id) {
+                    // Clone the handle out, so that the lock is not held during the query
+                    let handle = self
+                        .egresses
+                        .read()
+                        .await
+                        .iter()
+                        .find(|handle| handle.id == id)
+                        .cloned();
+                    if let Some(handle) = handle {
                         handle.query_status(rest).await
                     } else {
                         Err(TngError::StatusPathNotFound)
@@ -102,13 +307,23 @@ impl StatusProvider for TngState {
                 }
             }
             ["ingress"] => Ok(StatusQueryResult::Subtree(
-                (0..self.ingresses.len())
-                    .map(|i| Cow::Owned(i.to_string()))
+                self.ingresses
+                    .read()
+                    .await
+                    .iter()
+                    .map(|handle| Cow::Owned(handle.id.to_string()))
                     .collect(),
             )),
             ["ingress", id, rest @ ..] => {
                 if let Ok(id) = id.parse::<usize>() {
-                    if let Some(handle) = self.ingresses.get(id) {
+                    let handle = self
+                        .ingresses
+                        .read()
+                        .await
+                        .iter()
+                        .find(|handle| handle.id == id)
+                        .cloned();
+                    if let Some(handle) = handle {
                         handle.query_status(rest).await
                     } else {
                         Err(TngError::StatusPathNotFound)
@@ -132,4 +347,63 @@ mod tests {
         let result = state.query_status(&[]).await;
         assert!(matches!(result, Ok(StatusQueryResult::Subtree(ref v)) if v.is_empty()));
     }
+
+    #[tokio::test]
+    async fn test_readiness() {
+        let mut state = TngState::new();
+        let ingress = ReadinessCell::default();
+        state.add_ingress(IngressStatusHandle {
+            flow: Weak::<crate::control_interface::ControlInterface>::new(),
+            readiness: ingress.clone(),
+        });
+
+        let report = state.readiness().await;
+        assert!(!report.ready);
+        assert_eq!(report.ingress[0].state, ServiceState::Starting);
+
+        let _ = state.ready.0.send(true);
+        ingress.set(ServiceReadiness::ready());
+        let report = state.readiness().await;
+        assert!(report.ready);
+        assert_eq!(
+            serde_json::to_value(&report.ingress[0]).ok(),
+            Some(serde_json::json!({"state": "ready"}))
+        );
+
+        ingress.set(ServiceReadiness::new(
+            ServiceState::Failed,
+            "port bind failed",
+        ));
+        assert!(!state.readiness().await.ready);
+    }
+
+    #[test]
+    fn test_health() {
+        let readiness = ReadinessCell::default();
+        let mut state = TngState::new();
+        state.add_egress(EgressStatusHandle {
+            flow: Weak::<crate::control_interface::ControlInterface>::new(),
+            readiness: readiness.clone(),
+        });
+
+        let services = state.try_services_readiness().unwrap_or_default();
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].0, ServiceKind::Egress);
+        assert_eq!(services[0].2.health(), "degraded");
+
+        readiness.set(ServiceReadiness::ready());
+        assert_eq!(readiness.get().health(), "ready");
+        assert_eq!(readiness.get().reason_summary(), None);
+
+        readiness.set(ServiceReadiness::new(
+            ServiceState::Failed,
+            format!("{}\ncaused by: address in use", "x".repeat(200)),
+        ));
+        let readiness = readiness.get();
+        assert_eq!(readiness.health(), "failed");
+        assert_eq!(
+            readiness.reason_summary(),
+            Some(format!("{}...", "x".repeat(REASON_SUMMARY_MAX_LEN)))
+        );
+    }
 }
@@ -0,0 +1,211 @@
+//! Integration with the service manager: the notifications following the `sd_notify(3)` protocol
+//! of systemd, and the listening sockets passed with socket activation, see `sd_listen_fds(3)`.
+//!
+//! All the notifications are no-ops if the instance is not started by systemd, i.e. the
+//! `NOTIFY_SOCKET` environment variable is not set. Likewise, there is no inherited listener if
+//! `LISTEN_FDS` is not set.
+
+use std::net::TcpListener;
+use std::os::fd::{FromRawFd as _, OwnedFd, RawFd};
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+
+const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+const WATCHDOG_USEC_ENV: &str = "WATCHDOG_USEC";
+const WATCHDOG_PID_ENV: &str = "WATCHDOG_PID";
+const LISTEN_FDS_ENV: &str = "LISTEN_FDS";
+const LISTEN_PID_ENV: &str = "LISTEN_PID";
+
+/// The first file descriptor passed by the service manager, `SD_LISTEN_FDS_START`.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// The TCP listeners passed by the service manager, read from the environment on first use.
+static INHERITED_LISTENERS: OnceLock<Vec<TcpListener>> = OnceLock::new();
+
+pub(crate) struct SdNotifier {
+    socket: UnixDatagram,
+    address: SocketAddr,
+}
+
+impl SdNotifier {
+    /// Connects to the socket given by `NOTIFY_SOCKET`, or returns `None` if it is not set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(path) = std::env::var_os(NOTIFY_SOCKET_ENV) else {
+            return Ok(None);
+        };
+        let path = path.to_string_lossy();
+        if path.is_empty() {
+            return Ok(None);
+        }
+
+        let address = Self::parse_address(&path)
+            .with_context(|| format!("Invalid {NOTIFY_SOCKET_ENV} address: {path}"))?;
+        let socket = UnixDatagram::unbound().context("Failed to create the notify socket")?;
+        Ok(Some(Self { socket, address }))
+    }
+
+    fn parse_address(path: &str) -> Result<SocketAddr> {
+        // An address starting with '@' is in the abstract namespace
+        if let Some(name) = path.strip_prefix('@') {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            {
+                #[cfg(target_os = "android")]
+                use std::os::android::net::SocketAddrExt as _;
+                #[cfg(target_os = "linux")]
+                use std::os::linux::net::SocketAddrExt as _;
+                return Ok(SocketAddr::from_abstract_name(name.as_bytes())?);
+            }
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            {
+                let _ = name;
+                anyhow::bail!("abstract socket addresses are not supported on this platform");
+            }
+        }
+        Ok(SocketAddr::from_pathname(path)?)
+    }
+
+    /// Sends the `state` lines, e.g. `READY=1`, to the service manager.
+    pub fn notify(&self, state: &str) -> Result<()> {
+        self.socket
+            .send_to_addr(state.as_bytes(), &self.address)
+            .with_context(|| format!("Failed to send {state:?} to the service manager"))?;
+        Ok(())
+    }
+
+    /// The interval to send `WATCHDOG=1` at, which is half of the watchdog timeout set by
+    /// `WatchdogSec=`, or `None` if the watchdog is not enabled for this process.
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        let usec = std::env::var(WATCHDOG_USEC_ENV).ok()?.parse::<u64>().ok()?;
+        if usec == 0 {
+            return None;
+        }
+        if let Ok(pid) = std::env::var(WATCHDOG_PID_ENV) {
+            if pid.parse::<u32>().ok()? != std::process::id() {
+                return None;
+            }
+        }
+        Some(Duration::from_micros(usec / 2))
+    }
+}
+
+/// Returns a duplicate of the TCP listener passed by the service manager on `addr`, if any, to
+/// accept on it instead of binding a new one.
+///
+/// The inherited listeners stay open for the lifetime of the instance, so that the listener of an
+/// ingress or egress reloaded on the same address gets it again. Since the service manager keeps
+/// them open across restarts as well, the connections arriving while the instance is restarted,
+/// e.g. to upgrade it, wait in the backlog instead of being refused.
+pub(crate) fn inherited_listener(addr: std::net::SocketAddr) -> Result<Option<TcpListener>> {
+    let listeners = INHERITED_LISTENERS.get_or_init(listeners_from_env);
+    let Some(listener) = listeners
+        .iter()
+        .find(|listener| listener.local_addr().ok() == Some(addr))
+    else {
+        return Ok(None);
+    };
+    tracing::debug!(%addr, "Use the listener passed by the service manager");
+    let listener = listener
+        .try_clone()
+        .with_context(|| format!("Failed to duplicate the listener passed on {addr}"))?;
+    Ok(Some(listener))
+}
+
+fn listeners_from_env() -> Vec<TcpListener> {
+    let Some(count) = std::env::var(LISTEN_FDS_ENV)
+        .ok()
+        .and_then(|count| count.parse::<RawFd>().ok())
+    else {
+        return vec![];
+    };
+    // The file descriptors were passed to another process, e.g. the parent of this one
+    if std::env::var(LISTEN_PID_ENV)
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        != Some(std::process::id())
+    {
+        return vec![];
+    }
+
+    (LISTEN_FDS_START..LISTEN_FDS_START.saturating_add(count))
+        .filter_map(|fd| {
+            // SAFETY: the service manager passes these file descriptors to this process, which
+            // does not use them anywhere else.
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            match listener_from_fd(fd) {
+                Ok(listener) => listener,
+                Err(error) => {
+                    tracing::warn!(
+                        fd,
+                        ?error,
+                        "Ignored the file descriptor passed by the service manager"
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Converts `fd` to a TCP listener, or returns `None` if it is another kind of file descriptor,
+/// which is closed then.
+fn listener_from_fd(fd: OwnedFd) -> Result<Option<TcpListener>> {
+    use nix::sys::socket::{getsockopt, sockopt, SockType};
+
+    if getsockopt(&fd, sockopt::SockType)? != SockType::Stream
+        || !getsockopt(&fd, sockopt::AcceptConn)?
+    {
+        return Ok(None);
+    }
+    let socket = socket2::Socket::from(fd);
+    if socket.local_addr()?.as_socket().is_none() {
+        return Ok(None);
+    }
+    // Not inherited by the processes spawned by this one
+    nix::fcntl::fcntl(
+        &socket,
+        nix::fcntl::FcntlArg::F_SETFD(nix::fcntl::FdFlag::FD_CLOEXEC),
+    )?;
+    socket.set_nonblocking(true)?;
+    Ok(Some(socket.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("notify.sock");
+        let receiver = UnixDatagram::bind(&path)?;
+
+        let notifier = SdNotifier {
+            socket: UnixDatagram::unbound()?,
+            address: SdNotifier::parse_address(&path.to_string_lossy())?,
+        };
+        notifier.notify("READY=1")?;
+
+        let mut buf = [0u8; 64];
+        let len = receiver.recv(&mut buf)?;
+        assert_eq!(&buf[..len], b"READY=1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_listener_from_fd() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let listener = listener_from_fd(OwnedFd::from(listener))?.context("Not a listener")?;
+        assert_eq!(listener.local_addr()?, addr);
+
+        let stream = std::net::TcpStream::connect(addr)?;
+        assert!(listener_from_fd(OwnedFd::from(stream))?.is_none());
+
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0")?;
+        assert!(listener_from_fd(OwnedFd::from(socket))?.is_none());
+        Ok(())
+    }
+}
@@ -1,5 +1,19 @@
 use std::fmt::Display;
 use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+use web_time_compat::{Instant, InstantExt as _, SystemTime, SystemTimeExt as _};
+
+/// The target of the access log events, which are also written to the dedicated sink configured
+/// with `access_log`.
+#[cfg_attr(wasm, allow(dead_code))]
+pub const ACCESS_LOG_TARGET: &str = module_path!();
+
+/// The target of the structured access records, emitted at the `trace` level along with every
+/// access log, for the dedicated sink to write them in JSON.
+pub const ACCESS_RECORD_TARGET: &str = concat!(module_path!(), "::record");
 
 /// The type of ingress that accepted the downstream connection.
 #[derive(Debug, Clone, Copy)]
@@ -47,13 +61,24 @@ impl Display for EgressAccessMode {
 
 // --- Unified mode wrapper for Display purposes ---
 
-#[derive(Clone, Copy)]
+/// The ingress or egress that accepted the downstream connection.
+#[derive(Debug, Clone, Copy)]
 #[cfg_attr(wasm, allow(dead_code))]
-enum AccessMode {
+pub enum AccessMode {
     Ingress(IngressAccessMode),
     Egress(EgressAccessMode),
 }
 
+impl AccessMode {
+    /// `ingress` or `egress`.
+    pub fn direction(&self) -> &'static str {
+        match self {
+            AccessMode::Ingress(_) => "ingress",
+            AccessMode::Egress(_) => "egress",
+        }
+    }
+}
+
 impl Display for AccessMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -63,6 +88,143 @@ impl Display for AccessMode {
     }
 }
 
+/// The state of the connection in which an access log is produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLogState {
+    /// The connection failed before it was routed to an upstream.
+    Accepted,
+    /// The connection failed before the upstream was connected.
+    Routed,
+    /// The upstream is connected.
+    Established,
+    /// The connection is closed, after it was established.
+    Closed,
+}
+
+impl Display for AccessLogState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessLogState::Accepted => write!(f, "accepted"),
+            AccessLogState::Routed => write!(f, "routed"),
+            AccessLogState::Established => write!(f, "established"),
+            AccessLogState::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+/// An access log delivered to the receivers of [`subscribe_access_log()`], with the fields known
+/// in its state.
+#[derive(Debug, Clone)]
+pub struct AccessLogEvent {
+    /// When the access log was produced.
+    pub timestamp: SystemTime,
+    pub state: AccessLogState,
+    pub mode: AccessMode,
+    pub downstream_remote: SocketAddr,
+    pub downstream_local: SocketAddr,
+    /// The upstream the connection is routed to, once it is routed.
+    pub upstream_remote: Option<String>,
+    /// The local address of the connection to the upstream, if there is one.
+    pub upstream_local: Option<SocketAddr>,
+    /// Whether the connection goes through the tunnel, once it is routed.
+    pub encrypted: Option<bool>,
+    pub attested: bool,
+    /// Whether the peer was accepted without being verified, since the attestation service was
+    /// unreachable.
+    pub degraded: bool,
+    /// ID of the rats-tls session the stream is multiplexed on, if any.
+    pub session_id: Option<u64>,
+    /// Bytes sent to the downstream, if counted. Only set when the connection is closed.
+    pub tx_bytes: Option<u64>,
+    /// Bytes received from the downstream, if counted. Only set when the connection is closed.
+    pub rx_bytes: Option<u64>,
+    /// How long the connection lasted. Only set when the connection is closed.
+    pub duration: Option<Duration>,
+}
+
+/// The number of access logs kept for a receiver of [`subscribe_access_log()`] which has not
+/// received them yet.
+const ACCESS_LOG_CHANNEL_CAPACITY: usize = 4096;
+
+static ACCESS_LOG_SENDER: OnceLock<broadcast::Sender<AccessLogEvent>> = OnceLock::new();
+
+fn access_log_sender() -> &'static broadcast::Sender<AccessLogEvent> {
+    ACCESS_LOG_SENDER.get_or_init(|| broadcast::channel(ACCESS_LOG_CHANNEL_CAPACITY).0)
+}
+
+/// Subscribes to the access logs of all the ingresses and egresses in the process, which are
+/// delivered as [`AccessLogEvent`]s from the moment this is called, regardless of the log filters
+/// and of `access_log`. This allows the embedders to feed them to their own pipelines, e.g. for
+/// billing or auditing, without parsing the logs.
+///
+/// The receiver should be drained promptly: once it falls behind by more than 4096 events, the
+/// oldest ones are dropped and the next `recv()` returns [`broadcast::error::RecvError::Lagged`]
+/// with the number of events missed.
+pub fn subscribe_access_log() -> broadcast::Receiver<AccessLogEvent> {
+    access_log_sender().subscribe()
+}
+
+/// The structured form of an access log, with the fields known in its state.
+struct AccessRecord<'a> {
+    state: AccessLogState,
+    mode: AccessMode,
+    downstream_remote: SocketAddr,
+    downstream_local: SocketAddr,
+    upstream_remote: Option<&'a str>,
+    upstream_local: Option<SocketAddr>,
+    encrypted: Option<bool>,
+    attested: bool,
+    degraded: bool,
+    session_id: Option<u64>,
+    transferred: Option<(u64, u64)>,
+    duration: Option<Duration>,
+}
+
+impl AccessRecord<'_> {
+    fn emit(&self) {
+        tracing::trace!(
+            target: ACCESS_RECORD_TARGET,
+            state = %self.state,
+            direction = self.mode.direction(),
+            mode = %self.mode,
+            downstream_remote = %self.downstream_remote,
+            downstream_local = %self.downstream_local,
+            upstream_remote = self.upstream_remote,
+            upstream_local = self.upstream_local.map(tracing::field::display),
+            policy = self
+                .encrypted
+                .map(|encrypted| if encrypted { "tunnel" } else { "direct" }),
+            attested = self.attested,
+            degraded = self.degraded,
+            session_id = self.session_id,
+            tx_bytes = self.transferred.map(|(tx, _)| tx),
+            rx_bytes = self.transferred.map(|(_, rx)| rx),
+            duration_ms = self.duration.map(|duration| duration.as_millis() as u64),
+        );
+
+        let sender = access_log_sender();
+        if sender.receiver_count() > 0 {
+            // Fails only if the receivers were dropped in the meantime
+            let _ = sender.send(AccessLogEvent {
+                timestamp: SystemTime::get(),
+                state: self.state,
+                mode: self.mode,
+                downstream_remote: self.downstream_remote,
+                downstream_local: self.downstream_local,
+                upstream_remote: self.upstream_remote.map(str::to_owned),
+                upstream_local: self.upstream_local,
+                encrypted: self.encrypted,
+                attested: self.attested,
+                degraded: self.degraded,
+                session_id: self.session_id,
+                tx_bytes: self.transferred.map(|(tx, _)| tx),
+                rx_bytes: self.transferred.map(|(_, rx)| rx),
+                duration: self.duration,
+            });
+        }
+    }
+}
+
 // --- State 1: AccessAccepted ---
 
 /// Downstream connection accepted, but routing to upstream not yet determined.
@@ -141,6 +303,21 @@ impl Drop for AccessAccepted {
     fn drop(&mut self) {
         if self.need_print {
             tracing::error!("{}", self);
+            AccessRecord {
+                state: AccessLogState::Accepted,
+                mode: self.mode,
+                downstream_remote: self.downstream_remote,
+                downstream_local: self.downstream_local,
+                upstream_remote: None,
+                upstream_local: None,
+                encrypted: None,
+                attested: false,
+                degraded: false,
+                session_id: None,
+                transferred: None,
+                duration: None,
+            }
+            .emit();
         }
     }
 }
@@ -161,14 +338,18 @@ pub struct AccessRouted {
 
 #[allow(dead_code)]
 impl AccessRouted {
-    /// Transition to AccessEstablished. Consumes self.
+    /// Transition to AccessEstablished, which logs the start of the connection. Consumes self.
+    /// `degraded` is set if the peer was accepted without being verified, since the attestation
+    /// service was unreachable.
     pub fn into_established(
         mut self,
         upstream_local: Option<SocketAddr>,
         attested: bool,
+        degraded: bool,
+        session_id: Option<u64>,
     ) -> AccessEstablished {
         self.need_print = false;
-        AccessEstablished {
+        let established = AccessEstablished {
             downstream_remote: self.downstream_remote,
             downstream_local: self.downstream_local,
             mode: self.mode,
@@ -176,8 +357,15 @@ impl AccessRouted {
             upstream_local,
             encrypted: self.encrypted,
             attested,
+            degraded,
+            session_id,
+            established_at: Instant::get(),
+            transferred: None,
             need_print: true,
-        }
+        };
+        tracing::info!("{}", established);
+        established.record(AccessLogState::Established, None).emit();
+        established
     }
 }
 
@@ -195,14 +383,29 @@ impl Drop for AccessRouted {
     fn drop(&mut self) {
         if self.need_print {
             tracing::error!("{}", self);
+            AccessRecord {
+                state: AccessLogState::Routed,
+                mode: self.mode,
+                downstream_remote: self.downstream_remote,
+                downstream_local: self.downstream_local,
+                upstream_remote: Some(&self.upstream_remote),
+                upstream_local: None,
+                encrypted: Some(self.encrypted),
+                attested: false,
+                degraded: false,
+                session_id: None,
+                transferred: None,
+                duration: None,
+            }
+            .emit();
         }
     }
 }
 
 // --- State 3: AccessEstablished ---
 
-/// Upstream TCP connected, ready to forward.
-/// Drop logs at INFO level.
+/// Upstream TCP connected, ready to forward. Logs at INFO level when created.
+/// Drop logs the completion at INFO level, so it should be kept until the forwarding is finished.
 pub struct AccessEstablished {
     downstream_remote: SocketAddr,
     downstream_local: SocketAddr,
@@ -211,10 +414,39 @@ pub struct AccessEstablished {
     upstream_local: Option<SocketAddr>,
     encrypted: bool,
     attested: bool,
+    /// Whether the peer was accepted without being verified.
+    degraded: bool,
+    /// ID of the rats-tls session the stream is multiplexed on, if any.
+    session_id: Option<u64>,
+    established_at: Instant,
+    /// Bytes sent to and received from the downstream, if counted.
+    transferred: Option<(u64, u64)>,
     need_print: bool,
 }
 
-impl AccessEstablished {}
+impl AccessEstablished {
+    /// Set the bytes sent to and received from the downstream during the connection.
+    pub fn set_transferred(&mut self, tx_bytes: u64, rx_bytes: u64) {
+        self.transferred = Some((tx_bytes, rx_bytes));
+    }
+
+    fn record(&self, state: AccessLogState, duration: Option<Duration>) -> AccessRecord<'_> {
+        AccessRecord {
+            state,
+            mode: self.mode,
+            downstream_remote: self.downstream_remote,
+            downstream_local: self.downstream_local,
+            upstream_remote: Some(&self.upstream_remote),
+            upstream_local: self.upstream_local,
+            encrypted: Some(self.encrypted),
+            attested: self.attested,
+            degraded: self.degraded,
+            session_id: self.session_id,
+            transferred: self.transferred,
+            duration,
+        }
+    }
+}
 
 impl Display for AccessEstablished {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -231,6 +463,12 @@ impl Display for AccessEstablished {
         // Only print attested if encrypted is true (meaningful only with tunnel)
         // Actually per spec, always print attested in established state
         write!(f, " attested={}", self.attested)?;
+        if self.degraded {
+            write!(f, " degraded=true")?;
+        }
+        if let Some(session_id) = self.session_id {
+            write!(f, " session_id={session_id}")?;
+        }
         Ok(())
     }
 }
@@ -238,7 +476,16 @@ impl Display for AccessEstablished {
 impl Drop for AccessEstablished {
     fn drop(&mut self) {
         if self.need_print {
-            tracing::info!("{}", self);
+            let duration = self.established_at.elapsed();
+            match self.transferred {
+                Some((tx_bytes, rx_bytes)) => tracing::info!(
+                    "{} — closed tx_bytes={tx_bytes} rx_bytes={rx_bytes} duration={:.3}s",
+                    self,
+                    duration.as_secs_f64()
+                ),
+                None => tracing::info!("{} — closed duration={:.3}s", self, duration.as_secs_f64()),
+            }
+            self.record(AccessLogState::Closed, Some(duration)).emit();
         }
     }
 }
@@ -284,7 +531,7 @@ mod tests {
             EgressAccessMode::Hook,
         );
         let routed = accepted.into_routed("10.0.0.2:443", false);
-        let established = routed.into_established(None, false);
+        let established = routed.into_established(None, false, false, None);
         assert_eq!(
             format!("{established}"),
             "downstream_remote=10.0.0.1:54321 -> downstream_local=0.0.0.0:8080(hook) -> upstream_remote=10.0.0.2:443 — encrypted=false attested=false"
@@ -300,14 +547,83 @@ mod tests {
             IngressAccessMode::Mapping,
         );
         let routed = accepted.into_routed("10.0.0.2:443", true);
-        let established = routed.into_established(Some("10.0.0.1:54322".parse().unwrap()), true);
+        let established = routed.into_established(
+            Some("10.0.0.1:54322".parse().unwrap()),
+            true,
+            false,
+            Some(3),
+        );
+        assert_eq!(
+            format!("{established}"),
+            "downstream_remote=10.0.0.1:54321 -> downstream_local=0.0.0.0:8080(mapping) -> upstream_local=10.0.0.1:54322 -> upstream_remote=10.0.0.2:443 — encrypted=true attested=true session_id=3"
+        );
+        std::mem::forget(established);
+    }
+
+    #[test]
+    fn test_access_established_display_degraded() {
+        let accepted = AccessAccepted::new_egress(
+            "10.0.0.1:54321".parse().unwrap(),
+            "0.0.0.0:8080".parse().unwrap(),
+            EgressAccessMode::Mapping,
+        );
+        let routed = accepted.into_routed("10.0.0.2:443", true);
+        let established = routed.into_established(None, false, true, None);
         assert_eq!(
             format!("{established}"),
-            "downstream_remote=10.0.0.1:54321 -> downstream_local=0.0.0.0:8080(mapping) -> upstream_local=10.0.0.1:54322 -> upstream_remote=10.0.0.2:443 — encrypted=true attested=true"
+            "downstream_remote=10.0.0.1:54321 -> downstream_local=0.0.0.0:8080(mapping) -> upstream_remote=10.0.0.2:443 — encrypted=true attested=false degraded=true"
         );
         std::mem::forget(established);
     }
 
+    #[test]
+    fn test_subscribe_access_log() {
+        let mut receiver = subscribe_access_log();
+
+        // Other tests may produce access logs at the same time, so only the ones of this
+        // downstream are checked
+        let downstream_remote: SocketAddr = "10.0.0.9:54321".parse().unwrap();
+        let mut established = AccessAccepted::new_ingress(
+            downstream_remote,
+            "0.0.0.0:8080".parse().unwrap(),
+            IngressAccessMode::Socks5,
+        )
+        .into_routed("10.0.0.2:443", true)
+        .into_established(None, true, false, Some(3));
+        established.set_transferred(1024, 512);
+        drop(established);
+        drop(AccessAccepted::new_ingress(
+            downstream_remote,
+            "0.0.0.0:8080".parse().unwrap(),
+            IngressAccessMode::Socks5,
+        ));
+
+        let events: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok())
+            .filter(|event| event.downstream_remote == downstream_remote)
+            .collect();
+        assert_eq!(events.len(), 3);
+
+        assert_eq!(events[0].state, AccessLogState::Established);
+        assert_eq!(events[0].mode.direction(), "ingress");
+        assert_eq!(events[0].mode.to_string(), "socks5");
+        assert_eq!(events[0].upstream_remote.as_deref(), Some("10.0.0.2:443"));
+        assert_eq!(events[0].encrypted, Some(true));
+        assert!(events[0].attested);
+        assert!(!events[0].degraded);
+        assert_eq!(events[0].session_id, Some(3));
+        assert_eq!(events[0].tx_bytes, None);
+        assert!(events[0].duration.is_none());
+
+        assert_eq!(events[1].state, AccessLogState::Closed);
+        assert_eq!(events[1].tx_bytes, Some(1024));
+        assert_eq!(events[1].rx_bytes, Some(512));
+        assert!(events[1].duration.is_some());
+
+        assert_eq!(events[2].state, AccessLogState::Accepted);
+        assert_eq!(events[2].upstream_remote, None);
+        assert_eq!(events[2].encrypted, None);
+    }
+
     #[test]
     fn test_ingress_mode_display() {
         assert_eq!(format!("{}", IngressAccessMode::Mapping), "mapping");
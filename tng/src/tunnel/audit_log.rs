@@ -0,0 +1,80 @@
+use rats_cert::errors::{Error, Result};
+use rats_cert::tee::claims::Claims;
+use sha2::{Digest as _, Sha256};
+
+use super::provider::ProviderType;
+
+/// The target of the attestation audit events, which are also written to the dedicated audit log
+/// configured with `audit_log`.
+#[cfg_attr(wasm, allow(dead_code))]
+pub const AUDIT_LOG_TARGET: &str = module_path!();
+
+/// The verification of the attestation tokens of the peers, as recorded in the audit log.
+pub(crate) struct AttestationAudit {
+    /// `passport` or `background_check`.
+    pub model: &'static str,
+    pub provider: ProviderType,
+    pub policy_ids: Vec<String>,
+}
+
+impl AttestationAudit {
+    /// Emits the audit event of the verification of a token, with the claims of the token if they
+    /// could be parsed, even if the token was rejected.
+    pub fn emit(&self, claims: Option<&Claims>, result: &Result<()>) {
+        let claims_digest = claims
+            .and_then(|claims| serde_json::to_vec(claims).ok())
+            .map(|claims| format!("sha256:{}", hex::encode(Sha256::digest(claims))));
+        let policy_ids = self.policy_ids.join(",");
+
+        match result {
+            Ok(()) => tracing::info!(
+                target: AUDIT_LOG_TARGET,
+                event = "verify",
+                outcome = "accepted",
+                model = self.model,
+                provider = self.provider.as_str(),
+                policy_ids = policy_ids.as_str(),
+                claims_digest = claims_digest.as_deref(),
+                "Attestation of the peer accepted"
+            ),
+            Err(error) => tracing::warn!(
+                target: AUDIT_LOG_TARGET,
+                event = "verify",
+                outcome = "rejected",
+                model = self.model,
+                provider = self.provider.as_str(),
+                policy_ids = policy_ids.as_str(),
+                claims_digest = claims_digest.as_deref(),
+                error = %error_chain(error),
+                "Attestation of the peer rejected"
+            ),
+        }
+    }
+
+    /// Emits the audit event of a peer accepted without being verified, since the attestation
+    /// service was unreachable and `as_unreachable` is `fail_open`.
+    pub fn emit_degraded(&self, error: &Error) {
+        tracing::warn!(
+            target: AUDIT_LOG_TARGET,
+            event = "verify",
+            outcome = "degraded",
+            model = self.model,
+            provider = self.provider.as_str(),
+            policy_ids = self.policy_ids.join(",").as_str(),
+            error = %error_chain(error),
+            "Attestation of the peer skipped, since the attestation service is unreachable"
+        )
+    }
+}
+
+/// Formats an error with its sources, e.g. the reason why a required claim is not met.
+fn error_chain(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+    message
+}
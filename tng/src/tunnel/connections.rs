@@ -0,0 +1,406 @@
+//! Tracking of the active connections of an ingress or egress.
+//!
+//! The connections are listed through the control interface at
+//! `/status/{ingress,egress}/{id}/connections`, which helps to find out who is using a tunnel
+//! without capturing packets. A connection can also be closed by its ID or destination, e.g.
+//! when the attestation of a peer is found to be revoked.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+
+use pin_project::pin_project;
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+use web_time_compat::{Instant, InstantExt};
+
+use super::endpoint::TngEndpoint;
+use super::utils::forward::{SpliceCounter, SpliceSocket};
+
+/// The attestation status of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttestationStatus {
+    /// The tunnel to the peer is not established yet.
+    Pending,
+    /// The peer is verified with remote attestation.
+    Attested,
+    /// The connection is not protected by remote attestation, e.g. it is forwarded directly or
+    /// `no_ra` is set.
+    NotAttested,
+}
+
+impl AttestationStatus {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => AttestationStatus::Pending,
+            1 => AttestationStatus::Attested,
+            _ => AttestationStatus::NotAttested,
+        }
+    }
+}
+
+/// The live statistics of a connection, updated while it is being forwarded.
+#[derive(Debug)]
+pub struct ConnectionStats {
+    id: u64,
+    peer: SocketAddr,
+    dst: String,
+    via_tunnel: bool,
+    attestation: AtomicU8,
+    tx_bytes: AtomicU64,
+    rx_bytes: AtomicU64,
+    established_at: Instant,
+    /// Cancelled to close the connection.
+    closer: CancellationToken,
+}
+
+impl ConnectionStats {
+    /// Whether the destination is the `host:port` or the host given.
+    fn matches_dst(&self, dst: &str) -> bool {
+        self.dst == dst
+            || self
+                .dst
+                .rsplit_once(':')
+                .is_some_and(|(host, _)| host == dst)
+    }
+
+    fn snapshot(&self, now: Instant) -> ConnectionSnapshot {
+        ConnectionSnapshot {
+            id: self.id,
+            peer: self.peer,
+            dst: self.dst.clone(),
+            via_tunnel: self.via_tunnel,
+            attestation: AttestationStatus::from_u8(self.attestation.load(Ordering::Relaxed)),
+            tx_bytes: self.tx_bytes.load(Ordering::Relaxed),
+            rx_bytes: self.rx_bytes.load(Ordering::Relaxed),
+            age_secs: now.duration_since(self.established_at).as_secs(),
+        }
+    }
+}
+
+/// The connections to close.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionSelector {
+    /// The connection with the ID.
+    Id(u64),
+    /// All the connections to the destination, given as `host:port` or only the host.
+    Dst(String),
+}
+
+/// A connection as listed by the control interface.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionSnapshot {
+    pub id: u64,
+    /// Address of the downstream peer.
+    pub peer: SocketAddr,
+    /// Destination of the connection.
+    pub dst: String,
+    /// Whether the connection goes through the trusted tunnel, instead of being forwarded
+    /// directly.
+    pub via_tunnel: bool,
+    pub attestation: AttestationStatus,
+    /// Bytes sent to the downstream peer.
+    pub tx_bytes: u64,
+    /// Bytes received from the downstream peer.
+    pub rx_bytes: u64,
+    pub age_secs: u64,
+}
+
+/// The active connections of a service.
+///
+/// This struct is free to be cloned and used anywhere.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionTracker {
+    inner: Arc<TrackerInner>,
+}
+
+#[derive(Debug, Default)]
+struct TrackerInner {
+    next_id: AtomicU64,
+    connections: Mutex<BTreeMap<u64, Arc<ConnectionStats>>>,
+    /// Cancelled to close all the connections, e.g. when the service is drained.
+    closer: CancellationToken,
+}
+
+impl ConnectionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking a connection. It is tracked until the returned handle is dropped.
+    pub fn track(&self, peer: SocketAddr, dst: &TngEndpoint, via_tunnel: bool) -> ConnectionHandle {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let attestation = if via_tunnel {
+            AttestationStatus::Pending
+        } else {
+            AttestationStatus::NotAttested
+        };
+        let stats = Arc::new(ConnectionStats {
+            id,
+            peer,
+            dst: dst.to_string(),
+            via_tunnel,
+            attestation: AtomicU8::new(attestation as u8),
+            tx_bytes: AtomicU64::new(0),
+            rx_bytes: AtomicU64::new(0),
+            established_at: Instant::get(),
+            closer: self.inner.closer.child_token(),
+        });
+        self.inner
+            .connections
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .insert(id, stats.clone());
+
+        ConnectionHandle {
+            tracker: Arc::downgrade(&self.inner),
+            stats,
+        }
+    }
+
+    /// List the active connections, ordered by the time they were accepted.
+    pub fn snapshot(&self) -> Vec<ConnectionSnapshot> {
+        let now = Instant::get();
+        self.inner
+            .connections
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .values()
+            .map(|stats| stats.snapshot(now))
+            .collect()
+    }
+
+    /// The number of active connections.
+    pub fn len(&self) -> usize {
+        self.inner
+            .connections
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Close all the active connections, and any connection tracked later.
+    pub fn close_all(&self) {
+        self.inner.closer.cancel();
+    }
+
+    /// Resolved once [`Self::close_all`] is called. The tasks serving the connections should stop
+    /// forwarding when it is resolved.
+    pub async fn closed(&self) {
+        self.inner.closer.cancelled().await
+    }
+
+    /// Close the selected connections, and return how many of them are closed.
+    pub fn close(&self, selector: &ConnectionSelector) -> usize {
+        let connections = self
+            .inner
+            .connections
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+        let mut closed = 0;
+        for stats in connections.values() {
+            let selected = match selector {
+                ConnectionSelector::Id(id) => stats.id == *id,
+                ConnectionSelector::Dst(dst) => stats.matches_dst(dst),
+            };
+            if selected && !stats.closer.is_cancelled() {
+                stats.closer.cancel();
+                closed += 1;
+            }
+        }
+        closed
+    }
+}
+
+/// A tracked connection, which is removed from the tracker when dropped.
+pub struct ConnectionHandle {
+    tracker: Weak<TrackerInner>,
+    stats: Arc<ConnectionStats>,
+}
+
+impl ConnectionHandle {
+    /// Wrap the downstream stream, so that the bytes transferred on it are counted.
+    pub fn wrap_stream<T>(&self, stream: T) -> TrackedStream<T> {
+        TrackedStream {
+            inner: stream,
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Resolved once the connection is closed through the tracker, either alone or with all the
+    /// other connections. The task serving the connection should stop forwarding then.
+    pub async fn closed(&self) {
+        self.stats.closer.cancelled().await
+    }
+
+    /// The bytes sent to and received from the downstream peer so far.
+    pub fn transferred(&self) -> (u64, u64) {
+        (
+            self.stats.tx_bytes.load(Ordering::Relaxed),
+            self.stats.rx_bytes.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn set_attested(&self, attested: bool) {
+        let status = if attested {
+            AttestationStatus::Attested
+        } else {
+            AttestationStatus::NotAttested
+        };
+        self.stats
+            .attestation
+            .store(status as u8, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ConnectionHandle {
+    fn drop(&mut self) {
+        if let Some(tracker) = self.tracker.upgrade() {
+            tracker
+                .connections
+                .lock()
+                .unwrap_or_else(|p| p.into_inner())
+                .remove(&self.stats.id);
+        }
+    }
+}
+
+#[pin_project]
+pub struct TrackedStream<T> {
+    #[pin]
+    inner: T,
+    stats: Arc<ConnectionStats>,
+}
+
+impl<T: SpliceSocket> SpliceSocket for TrackedStream<T> {
+    fn tcp_socket(&self) -> Option<&tokio::net::TcpStream> {
+        self.inner.tcp_socket()
+    }
+
+    fn splice_counter(&self) -> SpliceCounter {
+        let stats = self.stats.clone();
+        let inner = self.inner.splice_counter();
+        Box::new(move |read, written| {
+            stats.rx_bytes.fetch_add(read, Ordering::Relaxed);
+            stats.tx_bytes.fetch_add(written, Ordering::Relaxed);
+            inner(read, written)
+        })
+    }
+}
+
+impl<T: tokio::io::AsyncWrite> tokio::io::AsyncWrite for TrackedStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.project();
+        let ret = this.inner.poll_write(cx, buf);
+        if let Poll::Ready(Ok(sz)) = ret {
+            this.stats.tx_bytes.fetch_add(sz as u64, Ordering::Relaxed);
+        }
+        ret
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
+impl<T: tokio::io::AsyncRead> tokio::io::AsyncRead for TrackedStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.project();
+        let filled = buf.filled().len();
+        let ret = this.inner.poll_read(cx, buf);
+        this.stats
+            .rx_bytes
+            .fetch_add((buf.filled().len() - filled) as u64, Ordering::Relaxed);
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_track_connections() -> anyhow::Result<()> {
+        let tracker = ConnectionTracker::new();
+        let dst = TngEndpoint::new("127.0.0.1", 8080);
+
+        let direct = tracker.track("10.0.0.1:40000".parse()?, &dst, false);
+        let tunneled = tracker.track("10.0.0.2:40000".parse()?, &dst, true);
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut stream = tunneled.wrap_stream(server);
+        client.write_all(&[0; 20]).await?;
+        stream.read_exact(&mut [0; 20]).await?;
+        stream.write_all(&[0; 10]).await?;
+
+        let connections = tracker.snapshot();
+        assert_eq!(connections.len(), 2);
+        assert_eq!(connections[0].attestation, AttestationStatus::NotAttested);
+        assert_eq!(connections[1].attestation, AttestationStatus::Pending);
+        assert_eq!(connections[1].dst, "127.0.0.1:8080");
+        assert_eq!((connections[1].tx_bytes, connections[1].rx_bytes), (10, 20));
+
+        tunneled.set_attested(true);
+        drop(direct);
+        drop(stream);
+        let connections = tracker.snapshot();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].peer, "10.0.0.2:40000".parse()?);
+        assert_eq!(connections[0].attestation, AttestationStatus::Attested);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_close_connections() -> anyhow::Result<()> {
+        let tracker = ConnectionTracker::new();
+        let peer = "10.0.0.1:40000".parse()?;
+        let a = tracker.track(peer, &TngEndpoint::new("10.1.0.1", 80), true);
+        let b = tracker.track(peer, &TngEndpoint::new("10.1.0.1", 443), true);
+        let c = tracker.track(peer, &TngEndpoint::new("10.1.0.2", 443), true);
+
+        assert_eq!(tracker.close(&ConnectionSelector::Id(a.stats.id)), 1);
+        a.closed().await;
+        // Closing a connection again has no effect
+        assert_eq!(tracker.close(&ConnectionSelector::Id(a.stats.id)), 0);
+
+        assert_eq!(
+            tracker.close(&ConnectionSelector::Dst("10.1.0.2:443".into())),
+            1
+        );
+        c.closed().await;
+        assert_eq!(
+            tracker.close(&ConnectionSelector::Dst("10.1.0.1".into())),
+            1
+        );
+        b.closed().await;
+
+        // Closing all the connections also closes the ones tracked later
+        tracker.close_all();
+        tracker
+            .track(peer, &TngEndpoint::new("10.1.0.3", 80), true)
+            .closed()
+            .await;
+        Ok(())
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{self, IoSliceMut};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{ready, Context, Poll};
+use web_time_compat::{Duration, Instant, InstantExt};
+
+use anyhow::{Context as _, Result};
+use quinn::udp::{RecvMeta, Transmit};
+use quinn::{AsyncUdpSocket, Runtime, UdpPoller};
+use tokio::net::UdpSocket;
+
+use crate::config::egress::DirectForwardRules;
+use crate::tunnel::endpoint::{EndpointAddr, TngEndpoint};
+use crate::tunnel::utils::quic_inspector::{ClientHelloInfo, QuicInitialInspector};
+use crate::tunnel::utils::runtime::TokioRuntime;
+
+/// The maximum number of datagrams held back from a client until its ClientHello is complete.
+const MAX_HELD_DATAGRAMS: usize = 4;
+
+/// The maximum number of clients whose ClientHello is being inspected at the same time. The
+/// datagrams of the other clients are handed over to the QUIC endpoint without inspection.
+const MAX_INSPECTING_CLIENTS: usize = 1024;
+
+/// The time after which the datagrams held back from a client with an incomplete ClientHello are
+/// handed over to the QUIC endpoint.
+const INSPECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Forwards the QUIC connections whose ClientHello matches the `sni` rules of `direct_forward`
+/// to the backend as they were received, instead of terminating them as TNG traffic.
+#[derive(Clone)]
+pub(crate) struct QuicDirectForward {
+    sni_regexes: Vec<regex::Regex>,
+    idle_timeout: Duration,
+    runtime: TokioRuntime,
+}
+
+impl QuicDirectForward {
+    /// Returns `None` if there is no rule matching the SNI of QUIC connections.
+    pub fn new(
+        rules: &DirectForwardRules,
+        idle_timeout: Duration,
+        runtime: TokioRuntime,
+    ) -> Result<Option<Self>> {
+        let mut sni_regexes = vec![];
+        for rule in &rules.0 {
+            rule.validate()?;
+            match &rule.sni {
+                Some(regex) => sni_regexes.push(
+                    regex::Regex::new(regex).with_context(|| format!("Invalid regex: {regex}"))?,
+                ),
+                None => {
+                    tracing::warn!(?rule, "Ignoring the `direct_forward` rule which does not match the SNI of QUIC connections")
+                }
+            }
+        }
+
+        if sni_regexes.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            sni_regexes,
+            idle_timeout,
+            runtime,
+        }))
+    }
+
+    fn should_forward_directly(&self, client_hello: &ClientHelloInfo) -> bool {
+        client_hello.sni.as_deref().is_some_and(|sni| {
+            self.sni_regexes
+                .iter()
+                .any(|sni_regex| sni_regex.is_match(sni))
+        })
+    }
+
+    /// Binds the UDP socket of the QUIC endpoint, which hands the datagrams of the connections to
+    /// be forwarded directly over to the backend.
+    pub async fn bind(
+        self,
+        listen_addr: SocketAddr,
+        backend_ep: &TngEndpoint,
+    ) -> Result<(Arc<dyn AsyncUdpSocket>, Arc<dyn Runtime>)> {
+        let backend_addr = match backend_ep.addr() {
+            EndpointAddr::Ipv4(ip) => SocketAddr::from((*ip, backend_ep.port())),
+            EndpointAddr::Domain(domain) => {
+                tokio::net::lookup_host((domain.as_str(), backend_ep.port()))
+                    .await
+                    .with_context(|| format!("Failed to resolve the backend {backend_ep}"))?
+                    .next()
+                    .with_context(|| format!("No address found for the backend {backend_ep}"))?
+            }
+        };
+
+        let quinn_runtime = quinn::default_runtime().context("No async runtime found")?;
+
+        let socket = std::net::UdpSocket::bind(listen_addr)
+            .with_context(|| format!("Failed to bind UDP socket on {listen_addr}"))?;
+        socket.set_nonblocking(true)?;
+        // The replies of the backend are sent from the same socket the client sends to
+        let reply_socket = UdpSocket::from_std(socket.try_clone()?)?;
+        let inner = quinn_runtime.wrap_udp_socket(socket)?;
+
+        let socket = DirectForwardSocket {
+            inner,
+            state: Mutex::new(InspectState::default()),
+            shared: Arc::new(Shared {
+                direct_forward: self,
+                backend_addr,
+                reply_socket,
+                sessions: Mutex::new(HashMap::new()),
+            }),
+        };
+
+        Ok((Arc::new(socket), quinn_runtime))
+    }
+}
+
+struct Shared {
+    direct_forward: QuicDirectForward,
+    backend_addr: SocketAddr,
+    reply_socket: UdpSocket,
+    sessions: Mutex<HashMap<SocketAddr, Arc<DirectSession>>>,
+}
+
+/// A client whose datagrams are forwarded directly to the backend.
+struct DirectSession {
+    backend_socket: UdpSocket,
+    last_activity: Mutex<Instant>,
+}
+
+/// A datagram received from the socket, which may carry several segments of `meta.stride` bytes.
+struct HeldDatagram {
+    meta: RecvMeta,
+    data: Vec<u8>,
+}
+
+struct Inspecting {
+    inspector: QuicInitialInspector,
+    held: Vec<HeldDatagram>,
+    since: Instant,
+}
+
+#[derive(Default)]
+struct InspectState {
+    inspecting: HashMap<SocketAddr, Inspecting>,
+    /// The datagrams held back during an inspection, to be handed over to the QUIC endpoint
+    released: VecDeque<HeldDatagram>,
+}
+
+enum Verdict {
+    /// Hand the datagram over to the QUIC endpoint
+    Pass,
+    /// The datagram has been held back or forwarded to the backend
+    Taken,
+}
+
+pub(super) struct DirectForwardSocket {
+    inner: Arc<dyn AsyncUdpSocket>,
+    state: Mutex<InspectState>,
+    shared: Arc<Shared>,
+}
+
+impl fmt::Debug for DirectForwardSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DirectForwardSocket")
+            .field("inner", &self.inner)
+            .field("backend_addr", &self.shared.backend_addr)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DirectForwardSocket {
+    fn classify(&self, state: &mut InspectState, meta: RecvMeta, data: &[u8]) -> Verdict {
+        let session = lock(&self.shared.sessions).get(&meta.addr).cloned();
+        if let Some(session) = session {
+            session.forward(meta, data);
+            return Verdict::Taken;
+        }
+
+        let first_segment = &data[..meta.stride.min(data.len())];
+        let Some(mut inspecting) = state.inspecting.remove(&meta.addr) else {
+            if !QuicInitialInspector::is_initial(first_segment) {
+                return Verdict::Pass;
+            }
+            if state.inspecting.len() >= MAX_INSPECTING_CLIENTS {
+                Self::release_expired(state);
+                if state.inspecting.len() >= MAX_INSPECTING_CLIENTS {
+                    return Verdict::Pass;
+                }
+            }
+            let inspecting = Inspecting {
+                inspector: QuicInitialInspector::default(),
+                held: vec![],
+                since: Instant::get(),
+            };
+            return self.inspect(state, inspecting, meta, data);
+        };
+
+        if !QuicInitialInspector::is_initial(first_segment) {
+            // The handshake has moved on without a complete ClientHello
+            state.released.extend(inspecting.held.drain(..));
+            return Verdict::Pass;
+        }
+        self.inspect(state, inspecting, meta, data)
+    }
+
+    fn inspect(
+        &self,
+        state: &mut InspectState,
+        mut inspecting: Inspecting,
+        meta: RecvMeta,
+        data: &[u8],
+    ) -> Verdict {
+        let first_segment = &data[..meta.stride.min(data.len())];
+        match inspecting.inspector.feed(first_segment) {
+            Ok(Some(client_hello)) => {
+                if self
+                    .shared
+                    .direct_forward
+                    .should_forward_directly(&client_hello)
+                {
+                    match self.new_session(meta.addr, &client_hello) {
+                        Ok(session) => {
+                            for held in inspecting.held {
+                                session.forward(held.meta, &held.data);
+                            }
+                            session.forward(meta, data);
+                            return Verdict::Taken;
+                        }
+                        Err(error) => {
+                            tracing::error!(client = %meta.addr, ?error, "Failed to forward the QUIC connection directly");
+                        }
+                    }
+                }
+                state.released.extend(inspecting.held);
+                Verdict::Pass
+            }
+            Ok(None)
+                if inspecting.inspector.has_first_fragment()
+                    && inspecting.held.len() < MAX_HELD_DATAGRAMS =>
+            {
+                inspecting.held.push(HeldDatagram {
+                    meta,
+                    data: data.to_vec(),
+                });
+                state.inspecting.insert(meta.addr, inspecting);
+                Verdict::Taken
+            }
+            Ok(None) | Err(_) => {
+                // Not the first flight of a QUIC connection which can be inspected, leave it to
+                // the QUIC endpoint
+                state.released.extend(inspecting.held);
+                Verdict::Pass
+            }
+        }
+    }
+
+    /// Hands the datagrams held back for too long over to the QUIC endpoint.
+    fn release_expired(state: &mut InspectState) {
+        let InspectState {
+            inspecting,
+            released,
+        } = state;
+        inspecting.retain(|_, inspecting| {
+            if inspecting.since.elapsed() < INSPECT_TIMEOUT {
+                return true;
+            }
+            released.extend(inspecting.held.drain(..));
+            false
+        });
+    }
+
+    fn new_session(
+        &self,
+        client: SocketAddr,
+        client_hello: &ClientHelloInfo,
+    ) -> Result<Arc<DirectSession>> {
+        let backend_addr = self.shared.backend_addr;
+        let bind_addr: SocketAddr = if backend_addr.is_ipv4() {
+            "0.0.0.0:0".parse()?
+        } else {
+            "[::]:0".parse()?
+        };
+        let backend_socket = std::net::UdpSocket::bind(bind_addr)?;
+        backend_socket.connect(backend_addr)?;
+        backend_socket.set_nonblocking(true)?;
+
+        let session = Arc::new(DirectSession {
+            backend_socket: UdpSocket::from_std(backend_socket)?,
+            last_activity: Mutex::new(Instant::get()),
+        });
+        lock(&self.shared.sessions).insert(client, session.clone());
+
+        tracing::info!(
+            %client,
+            sni = client_hello.sni.as_deref(),
+            "Forwarding QUIC connection directly"
+        );
+
+        let shared = self.shared.clone();
+        let session_task = session.clone();
+        self.shared
+            .direct_forward
+            .runtime
+            .spawn_supervised_task(async move {
+                if let Err(error) = session_task.reply(&shared, client).await {
+                    tracing::warn!(%client, ?error, "Failed to forward datagrams from the backend");
+                }
+                lock(&shared.sessions).remove(&client);
+                tracing::debug!(%client, "Directly forwarded QUIC connection finished");
+            });
+
+        Ok(session)
+    }
+}
+
+impl DirectSession {
+    /// Forwards a datagram from the client to the backend. The datagram is dropped if the socket
+    /// is not ready, as a router would do.
+    fn forward(&self, meta: RecvMeta, data: &[u8]) {
+        for segment in data.chunks(meta.stride.max(1)) {
+            let _ = self.backend_socket.try_send(segment);
+        }
+        *lock(&self.last_activity) = Instant::get();
+    }
+
+    /// Forwards the datagrams from the backend to the client until the session is idle.
+    async fn reply(&self, shared: &Shared, client: SocketAddr) -> Result<()> {
+        let idle_timeout = shared.direct_forward.idle_timeout;
+        let mut buf = vec![0u8; 65535];
+        loop {
+            tokio::select! {
+                res = self.backend_socket.recv(&mut buf) => {
+                    let n = res?;
+                    shared.reply_socket.send_to(&buf[..n], client).await?;
+                    *lock(&self.last_activity) = Instant::get();
+                }
+                _ = tokio::time::sleep(idle_timeout) => {
+                    let last_activity = *lock(&self.last_activity);
+                    if last_activity.elapsed() >= idle_timeout {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Locks `mutex`, recovering from a poisoned lock by taking the inner data: none of the critical
+/// sections leaves the state inconsistent if it panics.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+impl AsyncUdpSocket for DirectForwardSocket {
+    fn create_io_poller(self: Arc<Self>) -> Pin<Box<dyn UdpPoller>> {
+        self.inner.clone().create_io_poller()
+    }
+
+    fn try_send(&self, transmit: &Transmit) -> io::Result<()> {
+        self.inner.try_send(transmit)
+    }
+
+    fn poll_recv(
+        &self,
+        cx: &mut Context,
+        bufs: &mut [IoSliceMut<'_>],
+        meta: &mut [RecvMeta],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            let mut state = lock(&self.state);
+
+            // Hand the datagrams released after an inspection over first
+            let mut count = 0;
+            while count < bufs.len().min(meta.len()) {
+                let Some(held) = state.released.pop_front() else {
+                    break;
+                };
+                if held.data.len() > bufs[count].len() {
+                    continue;
+                }
+                bufs[count][..held.data.len()].copy_from_slice(&held.data);
+                meta[count] = held.meta;
+                count += 1;
+            }
+            if count > 0 {
+                return Poll::Ready(Ok(count));
+            }
+            drop(state);
+
+            let received = ready!(self.inner.poll_recv(cx, bufs, meta))?;
+
+            let mut state = lock(&self.state);
+            let mut kept = 0;
+            for i in 0..received {
+                let datagram = &bufs[i][..meta[i].len];
+                if let Verdict::Pass = self.classify(&mut state, meta[i], datagram) {
+                    bufs.swap(kept, i);
+                    meta.swap(kept, i);
+                    kept += 1;
+                }
+            }
+            if kept > 0 {
+                return Poll::Ready(Ok(kept));
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    fn max_transmit_segments(&self) -> usize {
+        self.inner.max_transmit_segments()
+    }
+
+    fn max_receive_segments(&self) -> usize {
+        self.inner.max_receive_segments()
+    }
+
+    fn may_fragment(&self) -> bool {
+        self.inner.may_fragment()
+    }
+}
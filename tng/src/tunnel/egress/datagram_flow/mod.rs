@@ -22,6 +22,10 @@ use crate::tunnel::service_metrics::ServiceMetricsCreator;
 use crate::tunnel::utils::runtime::TokioRuntime;
 use crate::tunnel::utils::rustls::config::TlsConfigGenerator;
 
+pub(super) use direct_forward::QuicDirectForward;
+
+mod direct_forward;
+
 /// Trait for a single QUIC connection on the egress side.
 ///
 /// Wraps the protocol-specific connection (e.g. quinn::Connection)
@@ -70,16 +74,19 @@ pub(super) trait EgressDatagramTrait: Send + Sync {
     fn idle_timeout_secs(&self) -> u64;
 
     /// Bind the QUIC listener. The Flow provides the TLS config generator
-    /// so RA context is managed centrally (same pattern as ingress).
+    /// so RA context is managed centrally (same pattern as ingress), and the
+    /// `direct_forward` rules matching the SNI of QUIC connections, if any.
     async fn bind_listener(
         &self,
         tls_gen: &TlsConfigGenerator,
+        direct_forward: Option<QuicDirectForward>,
     ) -> Result<Arc<dyn EgressDatagramListener>>;
 }
 
 pub struct DatagramEgressFlow {
     egress: Box<dyn EgressDatagramTrait>,
     tls_gen: TlsConfigGenerator,
+    direct_forward: Option<QuicDirectForward>,
     metrics: ServiceMetrics,
     runtime: TokioRuntime,
 }
@@ -100,9 +107,19 @@ impl DatagramEgressFlow {
             Arc::new(crate::tunnel::ra_context::RaContext::from_ra_args(&ra_args).await?);
         let tls_gen = TlsConfigGenerator::new(ra_context, runtime.clone()).await?;
 
+        let direct_forward = match &common_args.direct_forward {
+            Some(rules) => QuicDirectForward::new(
+                rules,
+                Duration::from_secs(egress.idle_timeout_secs()),
+                runtime.clone(),
+            )?,
+            None => None,
+        };
+
         Ok(Self {
             egress: Box::new(egress),
             tls_gen,
+            direct_forward,
             metrics,
             runtime,
         })
@@ -112,7 +129,10 @@ impl DatagramEgressFlow {
 #[async_trait::async_trait]
 impl RegistedService for DatagramEgressFlow {
     async fn serve(&self, ready: Sender<()>) -> Result<()> {
-        let listener = self.egress.bind_listener(&self.tls_gen).await?;
+        let listener = self
+            .egress
+            .bind_listener(&self.tls_gen, self.direct_forward.clone())
+            .await?;
         let actual_addr = listener.local_addr()?;
         tracing::info!("UDP mapping egress QUIC listener on {}", actual_addr);
 
@@ -137,10 +157,10 @@ impl RegistedService for DatagramEgressFlow {
                 &backend_ep,
                 true, // from_trusted_tunnel
             );
-            let access_established = access_routed.into_established(None, false);
+            let access_established = access_routed.into_established(None, false, false, None);
 
-            // Spawn per-connection forwarding task — access_established logs on drop
-            // when this task ends (connection close or error).
+            // Spawn per-connection forwarding task — access_established logs the completion on
+            // drop when this task ends (connection close or error).
             let metrics = self.metrics.clone();
             let active_cx = metrics.new_cx();
             let runtime_spawn = self.runtime.clone();
@@ -1,21 +1,29 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use auto_enums::auto_enum;
 use futures::Stream;
 use futures::StreamExt;
 use indexmap::IndexMap;
+use rand::Rng as _;
 use tokio::sync::mpsc::Sender;
 
+use crate::config::connect_retry::ConnectRetryArgs;
 use crate::config::egress::CommonArgs;
+use crate::config::listener::ListenerArgs;
+use crate::config::ra::VerifyUpdate;
 use crate::error::TngError;
 use crate::status::{StatusProvider, StatusQueryResult};
 use crate::tunnel::access_log::{AccessAccepted, EgressAccessMode};
+use crate::tunnel::attestation_result::AttestationResult;
+use crate::tunnel::connections::{ConnectionSelector, ConnectionTracker};
+use crate::tunnel::resolver::Resolver;
 use crate::tunnel::service_metrics::ServiceMetrics;
 use crate::tunnel::service_metrics::ServiceMetricsCreator;
 use crate::tunnel::utils;
+use crate::tunnel::utils::circuit_breaker::CircuitBreaker;
 use crate::{service::RegistedService, CommonStreamTrait, ContextualStream};
 
 use super::stream_manager::{trusted::TrustedStreamManager, StreamManager};
@@ -26,7 +34,13 @@ pub struct EgressFlow {
     egress: Box<dyn EgressTrait>,
     trusted_stream_manager: Arc<TrustedStreamManager>,
     metrics: ServiceMetrics,
+    connections: ConnectionTracker,
     runtime: TokioRuntime,
+    forward_buffer_size: usize,
+    io_uring: bool,
+    circuit_breaker: CircuitBreaker,
+    connect_retry: Option<ConnectRetryArgs>,
+    resolver: Option<Arc<dyn Resolver>>,
 }
 
 #[async_trait]
@@ -41,6 +55,9 @@ pub(super) trait EgressTrait: Sync + Send {
     /// Accept incomming streams. The returned stream should be a stream of incomming accepted streams.
     /// Note that this method should be called only once.
     async fn accept(&self, runtime: TokioRuntime) -> Result<Incomming>;
+
+    /// Give the egress the tuning of its TCP listeners, for the egresses which bind them.
+    fn set_listener_args(&mut self, _listener: ListenerArgs) {}
 }
 
 pub(super) type Incomming<'a> = Box<dyn Stream<Item = Result<AcceptedStream>> + Send + 'a>;
@@ -58,6 +75,40 @@ pub(super) struct AcceptedStream {
     pub encrypted: bool,
 }
 
+/// The accepted streams queued from the accept tasks of [`accept_in_tasks`].
+const ACCEPT_QUEUE_SIZE: usize = 128;
+
+/// Accepts from each of the `streams` in its own task, so that the listeners sharded with
+/// `SO_REUSEPORT` accept the connections in parallel. The tasks stop when the returned stream is
+/// dropped.
+pub(super) fn accept_in_tasks(
+    runtime: &TokioRuntime,
+    streams: Vec<Incomming<'static>>,
+) -> Incomming<'static> {
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(ACCEPT_QUEUE_SIZE);
+    for stream in streams {
+        let sender = sender.clone();
+        runtime.spawn_supervised_task_current_span(async move {
+            let mut stream = Box::into_pin(stream);
+            loop {
+                tokio::select! {
+                    _ = sender.closed() => break,
+                    next = stream.next() => {
+                        let Some(next) = next else {
+                            break;
+                        };
+                        if sender.send(next).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Box::new(futures::stream::poll_fn(move |cx| receiver.poll_recv(cx)))
+}
+
 impl EgressFlow {
     #[allow(private_bounds)]
     pub async fn new(
@@ -66,19 +117,44 @@ impl EgressFlow {
         service_metrics_creator: &ServiceMetricsCreator,
         runtime: TokioRuntime,
     ) -> Result<Self> {
-        let egress = Box::new(egress);
+        let mut egress = Box::new(egress);
+
+        if let Some(listener) = &common_args.listener {
+            listener.validate()?;
+            egress.set_listener_args(listener.clone());
+        }
+
+        let buffer_size = common_args.buffer_size.clone().unwrap_or_default();
+        buffer_size.validate()?;
+
+        if common_args.io_uring && !cfg!(all(target_os = "linux", feature = "io-uring")) {
+            bail!(
+                "`io_uring` is only supported on Linux, by TNG built with the `io-uring` feature"
+            );
+        }
+
+        let circuit_breaker = CircuitBreaker::new(common_args.circuit_breaker.as_ref())?;
+        if let Some(connect_retry) = &common_args.connect_retry {
+            connect_retry.validate()?;
+        }
 
         let metric_attributes = egress.metric_attributes();
         let metrics = service_metrics_creator.new_service_metrics(metric_attributes);
 
         let trusted_stream_manager =
-            Arc::new(TrustedStreamManager::new(common_args, runtime.clone()).await?);
+            Arc::new(TrustedStreamManager::new(common_args, &metrics, runtime.clone()).await?);
 
         Ok(Self {
             egress,
             metrics,
+            connections: ConnectionTracker::new(),
             trusted_stream_manager,
             runtime,
+            forward_buffer_size: buffer_size.forward,
+            io_uring: common_args.io_uring,
+            circuit_breaker,
+            connect_retry: common_args.connect_retry.clone(),
+            resolver: common_args.resolver.clone(),
         })
     }
 }
@@ -114,6 +190,22 @@ impl RegistedService for EgressFlow {
 
         Ok(())
     }
+
+    fn active_connections(&self) -> usize {
+        self.connections.len()
+    }
+
+    fn close_connections(&self) {
+        self.connections.close_all();
+    }
+
+    fn terminate_connections(&self, selector: &ConnectionSelector) -> usize {
+        self.connections.close(selector)
+    }
+
+    async fn update_verify(&self, update: &VerifyUpdate) -> Result<()> {
+        self.trusted_stream_manager.update_verify(update).await
+    }
 }
 
 impl EgressFlow {
@@ -139,6 +231,12 @@ impl EgressFlow {
 
         let trusted_stream_manager = self.trusted_stream_manager.clone();
         let metrics = self.metrics.clone();
+        let connections = self.connections.clone();
+        let forward_buffer_size = self.forward_buffer_size;
+        let io_uring = self.io_uring;
+        let circuit_breaker = self.circuit_breaker.clone();
+        let connect_retry = self.connect_retry.clone();
+        let resolver = self.resolver.clone();
 
         // TODO: stop all task when downstream is already closed
 
@@ -156,11 +254,19 @@ impl EgressFlow {
                 // This is a per-connection decision, made once when the TCP accept occurs.
                 if let Err(error) = forward_to_upstream(
                     &metrics,
+                    &connections,
+                    &circuit_breaker,
+                    connect_retry.as_ref(),
+                    resolver.as_deref(),
+                    src,
                     access_accepted,
                     &dst,
                     stream,
                     false,
                     false,
+                    false,
+                    forward_buffer_size,
+                    io_uring,
                     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
                     transport_so_mark,
                 )
@@ -180,7 +286,16 @@ impl EgressFlow {
                 }
             };
 
-            while let Some(next_stream) = pending.next().await {
+            loop {
+                // Stop serving the streams multiplexed on this connection, once the connections
+                // of the service are closed
+                let next_stream = tokio::select! {
+                    next_stream = pending.next() => next_stream,
+                    _ = connections.closed() => break,
+                };
+                let Some(next_stream) = next_stream else {
+                    break;
+                };
                 let next_stream = match next_stream {
                     Ok(next_stream) => next_stream,
                     Err(error) => {
@@ -194,6 +309,10 @@ impl EgressFlow {
                     let dst = dst.clone();
                     let access_accepted = access_accepted.clone_for_multiplexing();
                     let metrics = metrics.clone();
+                    let connections = connections.clone();
+                    let circuit_breaker = circuit_breaker.clone();
+                    let connect_retry = connect_retry.clone();
+                    let resolver = resolver.clone();
 
                     async move {
                         // Protocol-level direct forward: determined by TransportLayer
@@ -213,16 +332,28 @@ impl EgressFlow {
                         // - Secured(stream, None): OHTTP/RATS-TLS decrypted, no attestation
                         // - DirectlyForward(stream): plain HTTP matched by direct_forward rule
                         let encrypted = next_stream.is_secured();
-                        let attested = next_stream.attestation_result().is_some();
+                        let attestation_result = next_stream.attestation_result();
+                        let attested =
+                            attestation_result.is_some_and(|result| !result.is_degraded());
+                        let degraded =
+                            attestation_result.is_some_and(AttestationResult::is_degraded);
                         let downstream = next_stream.into_stream();
 
                         if let Err(error) = forward_to_upstream(
                             &metrics,
+                            &connections,
+                            &circuit_breaker,
+                            connect_retry.as_ref(),
+                            resolver.as_deref(),
+                            src,
                             access_accepted,
                             &dst,
                             downstream,
                             encrypted,
                             attested,
+                            degraded,
+                            forward_buffer_size,
+                            io_uring,
                             #[cfg(any(
                                 target_os = "android",
                                 target_os = "fuchsia",
@@ -245,44 +376,144 @@ impl EgressFlow {
 ///
 /// Handles the full lifecycle: create metrics context, connect to upstream,
 /// transition access log states, forward streams, and mark success.
+#[allow(clippy::too_many_arguments)]
 async fn forward_to_upstream(
     metrics: &ServiceMetrics,
+    connections: &ConnectionTracker,
+    circuit_breaker: &CircuitBreaker,
+    connect_retry: Option<&ConnectRetryArgs>,
+    resolver: Option<&dyn Resolver>,
+    src: SocketAddr,
     access_accepted: AccessAccepted,
     dst: &TngEndpoint,
     downstream: Box<dyn CommonStreamTrait>,
     encrypted: bool,
     attested: bool,
+    degraded: bool,
+    forward_buffer_size: usize,
+    io_uring: bool,
     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
     transport_so_mark: Option<u32>,
 ) -> Result<()> {
+    let metrics = metrics.for_destination(dst);
     let active_cx = metrics.new_cx();
+    let connection = connections.track(src, dst, encrypted);
+    connection.set_attested(attested);
 
     let access_routed = access_accepted.into_routed(dst, encrypted);
 
-    let upstream = dst
-        .tcp_connect(
-            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-            transport_so_mark,
+    let upstream = circuit_breaker
+        .connect(
+            dst,
+            connect_upstream(
+                dst,
+                connect_retry,
+                resolver,
+                #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                transport_so_mark,
+            ),
         )
         .await
         .context("Failed to connect to upstream")?;
     let egress_local = upstream.local_addr().context("Failed to get local addr")?;
     let upstream = ContextualStream::new(upstream, "egress-tcp-connect");
 
-    // Print access log — Transition to AccessEstablished: upstream connected, then drop immediately to log
-    access_routed.into_established(Some(egress_local), attested);
-
-    let downstream = metrics.new_wrapped_stream(downstream);
-
-    utils::forward::forward_stream(upstream, downstream).await;
+    // Print access log — Transition to AccessEstablished: upstream connected. The completion is
+    // logged when it is dropped after forwarding, with the bytes transferred
+    let mut access_established =
+        access_routed.into_established(Some(egress_local), attested, degraded, None);
+
+    let downstream = connection.wrap_stream(metrics.new_wrapped_stream(downstream));
+
+    let closed = tokio::select! {
+        _ = utils::forward::forward_stream_zero_copy(upstream, downstream, forward_buffer_size, io_uring) => false,
+        _ = connection.closed() => true,
+    };
+    let (tx_bytes, rx_bytes) = connection.transferred();
+    access_established.set_transferred(tx_bytes, rx_bytes);
+    drop(access_established);
+    if closed {
+        bail!("The connection is closed through the control interface");
+    }
 
     active_cx.mark_finished_successfully();
     Ok(())
 }
 
+/// Connects to the upstream, retrying the transient failures as configured by `connect_retry`.
+async fn connect_upstream(
+    dst: &TngEndpoint,
+    connect_retry: Option<&ConnectRetryArgs>,
+    resolver: Option<&dyn Resolver>,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    transport_so_mark: Option<u32>,
+) -> Result<tokio::net::TcpStream> {
+    let mut retries = 0;
+    loop {
+        let error = match dst
+            .tcp_connect(
+                #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                transport_so_mark,
+                resolver,
+            )
+            .await
+        {
+            Ok(upstream) => return Ok(upstream),
+            Err(error) => error,
+        };
+
+        let connect_retry = match connect_retry {
+            Some(connect_retry)
+                if retries < connect_retry.max_retries && is_transient_connect_error(&error) =>
+            {
+                connect_retry
+            }
+            _ => return Err(error),
+        };
+
+        // Up to half of the backoff is taken off at random, so that the connections failed
+        // together do not retry all at once
+        let backoff = connect_retry.backoff(retries);
+        let backoff = backoff.mul_f64(1.0 - rand::rng().random_range(0.0..0.5));
+        tracing::debug!(
+            ?error,
+            retries,
+            ?backoff,
+            "Failed to connect to upstream, retrying"
+        );
+        tokio::time::sleep(backoff).await;
+        retries += 1;
+    }
+}
+
+/// Whether the connect error may go away by itself, e.g. while the upstream is restarting.
+fn is_transient_connect_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<std::io::Error>().is_some_and(|error| {
+            matches!(
+                error.kind(),
+                std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::TimedOut
+            )
+        })
+    })
+}
+
 #[async_trait]
 impl StatusProvider for EgressFlow {
     async fn query_status(&self, path: &[&str]) -> Result<StatusQueryResult, TngError> {
-        self.trusted_stream_manager.query_status(path).await
+        match path {
+            [] => {
+                let mut children = match self.trusted_stream_manager.query_status(path).await? {
+                    StatusQueryResult::Subtree(children) => children,
+                    StatusQueryResult::Value(_) => vec![],
+                };
+                children.push("connections".into());
+                Ok(StatusQueryResult::Subtree(children))
+            }
+            ["connections"] => Ok(StatusQueryResult::Value(
+                serde_json::to_value(self.connections.snapshot()).unwrap_or_default(),
+            )),
+            _ => self.trusted_stream_manager.query_status(path).await,
+        }
     }
 }
@@ -6,15 +6,17 @@ use indexmap::IndexMap;
 use tokio::net::TcpListener;
 
 use crate::{
-    config::egress::EgressMappingArgs,
+    config::{egress::EgressMappingArgs, listener::ListenerArgs},
     tunnel::access_log::{AccessAccepted, EgressAccessMode},
     tunnel::{
-        egress::flow::AcceptedStream, endpoint::TngEndpoint, utils::runtime::TokioRuntime,
-        utils::socket::SetListenerSockOpts,
+        egress::flow::AcceptedStream,
+        endpoint::TngEndpoint,
+        utils::runtime::TokioRuntime,
+        utils::socket::{bind_tcp_listeners, SetListenerSockOpts},
     },
 };
 
-use super::flow::{EgressTrait, Incomming};
+use super::flow::{accept_in_tasks, EgressTrait, Incomming};
 
 use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
@@ -22,6 +24,7 @@ use std::sync::Arc;
 pub struct MappingEgress {
     id: usize,
     rules: Vec<crate::config::mapping_rule::MappingRule>,
+    listener: ListenerArgs,
 }
 
 impl MappingEgress {
@@ -33,6 +36,7 @@ impl MappingEgress {
         Ok(Self {
             id,
             rules: mapping_args.rules.clone(),
+            listener: ListenerArgs::default(),
         })
     }
 }
@@ -71,7 +75,7 @@ impl EgressTrait for MappingEgress {
         None
     }
 
-    async fn accept(&self, _runtime: TokioRuntime) -> Result<Incomming> {
+    async fn accept(&self, runtime: TokioRuntime) -> Result<Incomming> {
         struct ListenerTarget {
             listener: TcpListener,
             local_addr: SocketAddr,
@@ -89,38 +93,38 @@ impl EgressTrait for MappingEgress {
                 for port in rule.r#in.port..=port_end {
                     let offset = port - rule.r#in.port;
                     let out_port = offset_base + offset;
-                    let addr = format!("{host}:{port}");
+                    let addr = SocketAddr::from((host, port));
                     tracing::debug!(%addr, "Add TCP listener");
 
-                    let listener = TcpListener::bind(&addr).await.with_context(|| {
-                        format!("Failed to bind mapping egress listener on {addr}")
-                    })?;
-                    listener.set_listener_common_sock_opts()?;
-                    let local_addr = listener.local_addr()?;
+                    let listeners =
+                        bind_tcp_listeners(addr, &self.listener).with_context(|| {
+                            format!("Failed to bind mapping egress listener on {addr}")
+                        })?;
                     let out_ep = Arc::new(TngEndpoint::from_ipv4(out_host, out_port));
 
-                    targets.push(ListenerTarget {
-                        listener,
-                        local_addr,
-                        out_ep,
-                    });
+                    for listener in listeners {
+                        targets.push(ListenerTarget {
+                            local_addr: listener.local_addr()?,
+                            listener,
+                            out_ep: Arc::clone(&out_ep),
+                        });
+                    }
                 }
             } else {
-                let addr = format!("{host}:{}", rule.r#in.port);
+                let addr = SocketAddr::from((host, rule.r#in.port));
                 tracing::debug!(%addr, "Add TCP listener");
 
-                let listener = TcpListener::bind(&addr)
-                    .await
+                let listeners = bind_tcp_listeners(addr, &self.listener)
                     .with_context(|| format!("Failed to bind mapping egress listener on {addr}"))?;
-                listener.set_listener_common_sock_opts()?;
-                let local_addr = listener.local_addr()?;
                 let out_ep = Arc::new(TngEndpoint::from_ipv4(out_host, rule.out.port));
 
-                targets.push(ListenerTarget {
-                    listener,
-                    local_addr,
-                    out_ep,
-                });
+                for listener in listeners {
+                    targets.push(ListenerTarget {
+                        local_addr: listener.local_addr()?,
+                        listener,
+                        out_ep: Arc::clone(&out_ep),
+                    });
+                }
             }
         }
 
@@ -153,6 +157,18 @@ impl EgressTrait for MappingEgress {
             })
             .collect();
 
+        if self.listener.acceptors > 1 {
+            let streams = streams
+                .into_iter()
+                .map(|stream| Box::new(stream) as Incomming<'static>)
+                .collect();
+            return Ok(accept_in_tasks(&runtime, streams));
+        }
+
         Ok(Box::new(select_all(streams)))
     }
+
+    fn set_listener_args(&mut self, listener: ListenerArgs) {
+        self.listener = listener;
+    }
 }
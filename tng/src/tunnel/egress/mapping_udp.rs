@@ -9,7 +9,7 @@ use quinn::crypto::rustls::QuicServerConfig;
 
 use crate::config::egress::EgressMappingUdpArgs;
 use crate::tunnel::egress::datagram_flow::{
-    EgressDatagramConnection, EgressDatagramListener, EgressDatagramTrait,
+    EgressDatagramConnection, EgressDatagramListener, EgressDatagramTrait, QuicDirectForward,
 };
 use crate::tunnel::endpoint::TngEndpoint;
 use crate::tunnel::utils::rustls::config::alpn::Alpn;
@@ -165,6 +165,7 @@ impl EgressDatagramTrait for MappingUdpEgress {
     async fn bind_listener(
         &self,
         tls_gen: &TlsConfigGenerator,
+        direct_forward: Option<QuicDirectForward>,
     ) -> Result<Arc<dyn EgressDatagramListener>> {
         let listen_addr: SocketAddr =
             format!("{}:{}", self.listen_addr, self.listen_port).parse()?;
@@ -175,8 +176,21 @@ impl EgressDatagramTrait for MappingUdpEgress {
 
         let server_config =
             quinn::ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls_config.0)?));
-        let endpoint = quinn::Endpoint::server(server_config, listen_addr)
-            .context("Failed to bind QUIC endpoint")?;
+        let endpoint = match direct_forward {
+            Some(direct_forward) => {
+                let (socket, runtime) = direct_forward
+                    .bind(listen_addr, &self.backend_endpoint())
+                    .await?;
+                quinn::Endpoint::new_with_abstract_socket(
+                    quinn::EndpointConfig::default(),
+                    Some(server_config),
+                    socket,
+                    runtime,
+                )
+            }
+            None => quinn::Endpoint::server(server_config, listen_addr),
+        }
+        .context("Failed to bind QUIC endpoint")?;
 
         Ok(Arc::new(QuicEgressListener {
             endpoint,
@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use crate::{
-    config::egress::EgressNetfilterCaptureDst,
+    config::egress::{EgressNetfilterCaptureDst, EgressNetfilterCaptureExclude},
     tunnel::utils::iptables::{format_dport, IptablesRuleGenerator},
 };
 
@@ -52,6 +52,14 @@ impl IptablesRuleGenerator for NetfilterEgress {
             self.so_mark
         );
 
+        // Ignore packets matching any capture_exclude
+        for exclude in &self.capture_exclude {
+            invoke_script += &format!(
+                "iptables -t nat -A TNG_EGRESS_{id} -p tcp {}-j RETURN ; ",
+                Self::exclude_match(exclude)
+            );
+        }
+
         // Handle cgroup filtering
         if !self.capture_cgroup.is_empty() {
             if !is_cgroup_v2() {
@@ -116,6 +124,24 @@ impl IptablesRuleGenerator for NetfilterEgress {
 }
 
 impl NetfilterEgress {
+    /// Generate the iptables match arguments of a capture_exclude rule, with a trailing space.
+    fn exclude_match(exclude: &EgressNetfilterCaptureExclude) -> String {
+        let mut args = String::new();
+        if let Some(interface) = &exclude.interface {
+            args += &format!("-i {interface} ");
+        }
+        if let Some(src) = &exclude.src {
+            args += &format!("--src {}/{} ", src.first_address(), src.network_length());
+        }
+        if let Some(dst) = &exclude.dst {
+            args += &format!("--dst {}/{} ", dst.first_address(), dst.network_length());
+        }
+        if let Some((port, port_end)) = &exclude.port {
+            args += &format!("--dport {} ", format_dport(*port, port_end.as_ref()));
+        }
+        args
+    }
+
     /// Generate REDIRECT rules matching all capture_dst entries.
     ///
     /// When `capture_local_traffic` is false, adds `! --src-type LOCAL`
@@ -1,27 +1,32 @@
 use anyhow::{bail, Context, Result};
 use async_stream::stream;
 use async_trait::async_trait;
+use futures::stream::{select_all, BoxStream};
 use futures::StreamExt;
 use indexmap::IndexMap;
 use socket2::SockRef;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 use tokio::net::TcpListener;
 
 use crate::{
-    config::egress::{EgressNetfilterArgs, EgressNetfilterCaptureDst},
+    config::{
+        egress::{EgressNetfilterArgs, EgressNetfilterCaptureDst, EgressNetfilterCaptureExclude},
+        listener::ListenerArgs,
+    },
     tunnel::access_log::{AccessAccepted, EgressAccessMode},
     tunnel::{
         egress::flow::AcceptedStream,
         endpoint::TngEndpoint,
         utils::{
-            iptables::IptablesExecutor,
+            iptables::{IptablesExecutor, IptablesGuard},
             runtime::TokioRuntime,
-            socket::{SetListenerSockOpts, TCP_CONNECT_SO_MARK_DEFAULT},
+            socket::{bind_tcp_listeners, SetListenerSockOpts, TCP_CONNECT_SO_MARK_DEFAULT},
         },
     },
 };
 
-use super::flow::{EgressTrait, Incomming};
+use super::flow::{accept_in_tasks, EgressTrait, Incomming};
 
 mod iptables;
 
@@ -31,8 +36,10 @@ pub struct NetfilterEgress {
     capture_local_traffic: bool,
     capture_cgroup: Vec<String>,
     nocapture_cgroup: Vec<String>,
+    capture_exclude: Vec<EgressNetfilterCaptureExclude>,
     listen_port: u16,
     so_mark: u32,
+    listener: ListenerArgs,
 }
 
 impl NetfilterEgress {
@@ -53,6 +60,14 @@ impl NetfilterEgress {
             .map(TryInto::try_into)
             .collect::<Result<Vec<_>>>()?;
 
+        let capture_exclude = netfilter_args
+            .capture_exclude
+            .iter()
+            .map(Clone::clone)
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<_>>>()
+            .context("Invalid `capture_exclude`")?;
+
         let so_mark = netfilter_args
             .so_mark
             .unwrap_or(TCP_CONNECT_SO_MARK_DEFAULT);
@@ -63,8 +78,10 @@ impl NetfilterEgress {
             capture_local_traffic: netfilter_args.capture_local_traffic,
             capture_cgroup: netfilter_args.capture_cgroup.clone(),
             nocapture_cgroup: netfilter_args.nocapture_cgroup.clone(),
+            capture_exclude,
             listen_port,
             so_mark,
+            listener: ListenerArgs::default(),
         })
     }
 }
@@ -88,72 +105,103 @@ impl EgressTrait for NetfilterEgress {
         Some(self.so_mark)
     }
 
-    async fn accept(&self, _runtime: TokioRuntime) -> Result<Incomming> {
+    async fn accept(&self, runtime: TokioRuntime) -> Result<Incomming> {
         // Listen on 0.0.0.0 to capture traffic redirected by the nat OUTPUT chain.
         // REDIRECT sends packets to the listener's address; 0.0.0.0 captures all interfaces.
-        let listen_addr = format!("0.0.0.0:{}", self.listen_port);
+        let listen_addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, self.listen_port));
         tracing::debug!(%listen_addr, "Add TCP listener");
 
         // Setup iptables
-        let iptables_guard = IptablesExecutor::setup(self).await?;
+        let iptables_guard = Arc::new(IptablesExecutor::setup(self).await?);
 
-        let listener = TcpListener::bind(&listen_addr).await.with_context(|| {
+        let listeners = bind_tcp_listeners(listen_addr, &self.listener).with_context(|| {
             format!("Failed to bind netfilter egress listener on {listen_addr}")
         })?;
-        listener.set_listener_common_sock_opts()?;
 
-        let listen_addr = listener.local_addr()?;
+        let listen_addr = listeners
+            .first()
+            .context("No netfilter egress listener is bound")?
+            .local_addr()?;
+
+        let streams: Vec<_> = listeners
+            .into_iter()
+            .map(|listener| {
+                let iptables_guard = Arc::clone(&iptables_guard);
+                Self::accept_from_listener(listener, listen_addr, iptables_guard)
+            })
+            .collect();
+
+        if self.listener.acceptors > 1 {
+            let streams = streams
+                .into_iter()
+                .map(|stream| Box::new(stream) as Incomming<'static>)
+                .collect();
+            return Ok(accept_in_tasks(&runtime, streams));
+        }
 
-        Ok(Box::new(
-            stream! {
-                let _iptables_guard = iptables_guard; // Move iptables guard to here to keep it alive
-                loop {
-                    yield listener.accept_with_common_sock_opts().await
-                }
+        Ok(Box::new(select_all(streams)))
+    }
+
+    fn set_listener_args(&mut self, listener: ListenerArgs) {
+        self.listener = listener;
+    }
+}
+
+impl NetfilterEgress {
+    fn accept_from_listener(
+        listener: TcpListener,
+        listen_addr: SocketAddr,
+        iptables_guard: Arc<IptablesGuard>,
+    ) -> BoxStream<'static, Result<AcceptedStream>> {
+        stream! {
+            let _iptables_guard = iptables_guard; // Move iptables guard to here to keep it alive
+            loop {
+                yield listener.accept_with_common_sock_opts().await
+            }
+        }
+        .map(move |res| {
+            let (stream, peer_addr) = res?;
+            let socket_ref = SockRef::from(&stream);
+
+            // Use SO_ORIGINAL_DST to retrieve the original destination.
+            // For OUTPUT-redirected traffic (nat REDIRECT), SO_ORIGINAL_DST returns
+            // the pre-redirect destination. For PREROUTING-TPROXY traffic, it also
+            // returns the original destination since TPROXY doesn't modify it.
+            let orig_dst = socket_ref
+                .original_dst()
+                .context("failed to get original destination")?
+                .as_socket()
+                .context("should be a ip address")?;
+
+            // Check if the original destination is the same as the listener port to prevent from the recursion.
+            if listen_addr.port() == orig_dst.port() && orig_dst.ip().is_loopback() {
+                Err(anyhow::anyhow!("The original destination is the same as the listener port, recursion is detected"))?
             }
-            .map(move |res| {
-                let (stream, peer_addr) = res?;
-                let socket_ref = SockRef::from(&stream);
-
-                // Use SO_ORIGINAL_DST to retrieve the original destination.
-                // For OUTPUT-redirected traffic (nat REDIRECT), SO_ORIGINAL_DST returns
-                // the pre-redirect destination. For PREROUTING-TPROXY traffic, it also
-                // returns the original destination since TPROXY doesn't modify it.
-                let orig_dst = socket_ref
-                    .original_dst()
-                    .context("failed to get original destination")?
-                    .as_socket()
-                    .context("should be a ip address")?;
-
-                // Check if the original destination is the same as the listener port to prevent from the recursion.
-                if listen_addr.port() == orig_dst.port() && orig_dst.ip().is_loopback() {
-                    Err(anyhow::anyhow!("The original destination is the same as the listener port, recursion is detected"))?
-                }
 
-                let dst = match orig_dst.ip() {
-                    std::net::IpAddr::V4(ip) => {
-                        Arc::new(TngEndpoint::from_ipv4(ip, orig_dst.port()))
-                    }
-                    std::net::IpAddr::V6(_) => {
-                        bail!("SO_ORIGINAL_DST returned an IPv6 address, which is not supported")
-                    }
-                };
-
-                let access_accepted = AccessAccepted::new_egress(
-                    peer_addr,
-                    listen_addr,
-                    EgressAccessMode::Netfilter,
-                );
-                Ok(AcceptedStream {
-                    stream: Box::new(crate::ContextualStream::new(stream, "egress-netfilter")),
-                    src: peer_addr,
-                    dst,
-                    listener_addr: listen_addr,
-                    egress_mode: EgressAccessMode::Netfilter,
-                    access_accepted,
-                    encrypted: true,
-                })
-            }),
-        ))
+            let dst = match orig_dst.ip() {
+                std::net::IpAddr::V4(ip) => {
+                    Arc::new(TngEndpoint::from_ipv4(ip, orig_dst.port()))
+                }
+                std::net::IpAddr::V6(_) => {
+                    bail!("SO_ORIGINAL_DST returned an IPv6 address, which is not supported")
+                }
+            };
+
+            let access_accepted = AccessAccepted::new_egress(
+                peer_addr,
+                listen_addr,
+                EgressAccessMode::Netfilter,
+            );
+            Ok(AcceptedStream {
+                stream: Box::new(crate::ContextualStream::new(stream, "egress-netfilter")),
+                src: peer_addr,
+                dst,
+                listener_addr: listen_addr,
+                egress_mode: EgressAccessMode::Netfilter,
+                access_accepted,
+                encrypted: true,
+            })
+        })
+        .boxed()
     }
 }
@@ -1,9 +1,6 @@
 use anyhow::{Context, Result};
 
-use crate::{
-    config::egress::{DirectForwardRule, DirectForwardRules},
-    tunnel::utils::http_inspector::RequestInfo,
-};
+use crate::{config::egress::DirectForwardRules, tunnel::utils::http_inspector::RequestInfo};
 
 pub struct DirectForwardTrafficDetector {
     rule_matchers: Vec<RuleMatcher>,
@@ -11,10 +8,15 @@ pub struct DirectForwardTrafficDetector {
 
 impl DirectForwardTrafficDetector {
     pub fn new(rules: DirectForwardRules) -> Result<Self> {
+        // The rules matching the SNI of QUIC connections do not apply to TCP traffic
         let rule_matchers = rules
             .0
             .iter()
-            .map(RuleMatcher::new)
+            .map(|rule| {
+                rule.validate()?;
+                rule.http_path.as_deref().map(RuleMatcher::new).transpose()
+            })
+            .filter_map(Result::transpose)
             .collect::<Result<Vec<_>>>()?;
 
         Ok(Self { rule_matchers })
@@ -32,9 +34,7 @@ struct RuleMatcher {
 }
 
 impl RuleMatcher {
-    pub fn new(rule: &DirectForwardRule) -> Result<Self> {
-        let regex = &rule.http_path;
-
+    pub fn new(regex: &str) -> Result<Self> {
         Ok(Self {
             http_path_regex: regex::Regex::new(regex)
                 .with_context(|| format!("Invalid regex: {regex}"))?,
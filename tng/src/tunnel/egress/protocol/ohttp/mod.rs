@@ -5,7 +5,9 @@ use crate::{
     config::egress::OHttpArgs,
     tunnel::{
         egress::{
-            protocol::ohttp::security::OHttpSecurityLayer,
+            protocol::ohttp::security::{
+                key_manager::metrics::KeyManagerMetrics, OHttpSecurityLayer,
+            },
             stream_manager::trusted::{ProtocolStreamDecoder, ProtocolStreamDecoderOutput},
         },
         ra_context::RaContext,
@@ -29,11 +31,20 @@ impl OHttpStreamDecoder {
     pub async fn new(
         ra_context: Arc<RaContext>,
         ohttp_args: OHttpArgs,
+        key_manager_metrics: KeyManagerMetrics,
+        pipe_buffer_size: usize,
         runtime: TokioRuntime,
     ) -> Result<Self> {
         Ok(Self {
             security_layer: Arc::new(
-                OHttpSecurityLayer::new(ra_context, ohttp_args, runtime.clone()).await?,
+                OHttpSecurityLayer::new(
+                    ra_context,
+                    ohttp_args,
+                    key_manager_metrics,
+                    pipe_buffer_size,
+                    runtime.clone(),
+                )
+                .await?,
             ),
             runtime,
         })
@@ -0,0 +1,167 @@
+use std::pin::Pin;
+
+use anyhow::{bail, Context as _};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use rats_cert::tee::claims::Claims;
+use rats_cert::tee::{
+    AttesterPipeline, GenericAttester as _, GenericConverter as _, GenericEvidence as _, ReportData,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::TngError;
+use crate::tunnel::egress::protocol::ohttp::security::api::OhttpServerApi;
+use crate::tunnel::ohttp::protocol::ServerAttestationInfo;
+use crate::tunnel::ra_context::{AttestContext, RaContext};
+use crate::tunnel::utils::maybe_cached::{Expire, MaybeCached};
+use crate::TokioRuntime;
+
+/// Query parameters of the attestation info endpoint
+#[derive(Deserialize, Debug, Default)]
+pub struct AttestationInfoQuery {
+    /// Challenge token obtained by the caller from its attestation service. Only used in
+    /// background check model, where the evidence is generated for each request.
+    pub challenge_token: Option<String>,
+}
+
+/// Summary of the current attestation of the egress
+#[derive(Serialize, Debug)]
+pub struct AttestationInfoResponse {
+    /// The attestation result (passport model) or the evidence (background check model)
+    #[serde(flatten)]
+    pub attestation_info: ServerAttestationInfo,
+    /// Claims parsed from the attestation result or the evidence
+    pub claims: Claims,
+}
+
+impl OhttpServerApi {
+    /// Attestation info endpoint
+    /// GET {attestation_endpoint}
+    ///
+    /// This endpoint exposes the current attestation of the egress to external monitors, which
+    /// validate the TEE posture without speaking the tunnel protocol. In passport model, the
+    /// attestation result is cached with the refresh strategy of `attest`.
+    pub async fn get_attestation_info(
+        &self,
+        query: AttestationInfoQuery,
+        runtime: TokioRuntime,
+    ) -> Result<Response, TngError> {
+        match self.ra_context.attest_context() {
+            Some(attest_ctx @ AttestContext::Passport { .. }) => {
+                let cache = self
+                    .attestation_info_cache
+                    .get_or_try_init(|| {
+                        let ra_context = self.ra_context.clone();
+                        MaybeCached::new(runtime, attest_ctx.refresh_strategy(), move || {
+                            let ra_context = ra_context.clone();
+                            Box::pin(async move {
+                                tracing::info!("Regenerating attestation info");
+                                let response =
+                                    Self::get_attestation_info_internal(&ra_context, None).await?;
+                                Ok((response, Expire::NoExpire))
+                            }) as Pin<Box<_>>
+                        })
+                    })
+                    .await?;
+
+                let latest = cache.get_latest().await?;
+                Ok(Json(latest.as_ref()).into_response())
+            }
+            _ => Self::get_attestation_info_internal(&self.ra_context, query.challenge_token)
+                .await
+                .map(|response| Json(response).into_response()),
+        }
+    }
+
+    async fn get_attestation_info_internal(
+        ra_context: &RaContext,
+        challenge_token: Option<String>,
+    ) -> Result<AttestationInfoResponse, TngError> {
+        async {
+            let mut userdata = Claims::new();
+
+            Ok(match ra_context.attest_context() {
+                Some(AttestContext::Passport {
+                    attester,
+                    converter,
+                    ..
+                }) => {
+                    // fetch a challenge token from attestation service
+                    let challenge_token = converter.get_nonce().await?;
+                    userdata.insert("challenge_token".to_owned(), challenge_token.into());
+
+                    let token = AttesterPipeline::new(attester, converter)
+                        .get_evidence(&ReportData::Claims(userdata))
+                        .await?;
+                    let claims = token
+                        .get_claims()
+                        .context("Failed to parse claims from the attestation result")?;
+                    let as_provider = token.provider_type();
+
+                    AttestationInfoResponse {
+                        attestation_info: ServerAttestationInfo::Passport {
+                            attestation_result: token.into_str(),
+                            as_provider: Some(as_provider),
+                        },
+                        claims,
+                    }
+                }
+                Some(AttestContext::BackgroundCheck { attester, .. }) => {
+                    if let Some(challenge_token) = challenge_token {
+                        userdata.insert("challenge_token".to_owned(), challenge_token.into());
+                    }
+
+                    let tng_evidence = attester.get_evidence(&ReportData::Claims(userdata)).await?;
+                    let claims = tng_evidence
+                        .get_claims()
+                        .context("Failed to parse claims from the evidence")?;
+                    let aa_provider = tng_evidence.provider_type();
+
+                    AttestationInfoResponse {
+                        attestation_info: ServerAttestationInfo::BackgroundCheck {
+                            evidence: tng_evidence.serialize_to_json()?,
+                            aa_provider: Some(aa_provider),
+                        },
+                        claims,
+                    }
+                }
+                None => bail!("The egress is not configured to attest itself"),
+            })
+        }
+        .await
+        .map_err(TngError::GenAttestationInfoFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::tunnel::provider::ProviderType;
+
+    #[test]
+    fn test_attestation_info_response_format() -> anyhow::Result<()> {
+        let mut claims = Claims::new();
+        claims.insert("tee".to_owned(), "tdx".into());
+
+        let response = AttestationInfoResponse {
+            attestation_info: ServerAttestationInfo::Passport {
+                attestation_result: "eyJ.eyJ.sig".to_owned(),
+                as_provider: Some(ProviderType::Coco),
+            },
+            claims,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&response)?,
+            json!({
+                "model": "passport",
+                "attestation_result": "eyJ.eyJ.sig",
+                "as_provider": serde_json::to_value(ProviderType::Coco)?,
+                "claims": { "tee": "tdx" }
+            })
+        );
+        Ok(())
+    }
+}
@@ -20,7 +20,7 @@ impl OhttpServerApi {
         &self,
     ) -> Result<Json<AttestationChallengeResponse>, TngError> {
         async {
-            match self.ra_context.verify_context() {
+            match self.ra_context.verify_context().as_deref() {
                 Some(verify_ctx) => match verify_ctx {
                     VerifyContext::Passport { .. } => {
                         bail!("Passport model is expected but got background check attestation from client")
@@ -48,7 +48,7 @@ impl OhttpServerApi {
         Json(payload): Json<AttestationVerifyRequest>,
     ) -> Result<Json<AttestationVerifyResponse>, TngError> {
         async {
-            match self.ra_context.verify_context() {
+            match self.ra_context.verify_context().as_deref() {
                 Some(verify_ctx) => match verify_ctx {
                     VerifyContext::Passport { .. } => {
                         bail!("Passport model is expected but got background check attestation from client")
@@ -13,6 +13,7 @@ use rats_cert::tee::{AttesterPipeline, GenericAttester as _, GenericConverter as
 use std::pin::Pin;
 #[cfg(unix)]
 use std::sync::Arc;
+use web_time_compat::{SystemTime, SystemTimeExt as _};
 
 use crate::error::TngError;
 use crate::tunnel::egress::protocol::ohttp::security::api::OhttpServerApi;
@@ -20,6 +21,7 @@ use crate::tunnel::egress::protocol::ohttp::security::context::TngStreamContext;
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::KeyInfo;
 #[cfg(unix)]
 use crate::tunnel::ohttp::key_config::{KeyConfigExtend, PublicKeyData};
+use crate::tunnel::ohttp::protocol::header::OHTTP_KEYS_CONTENT_TYPE;
 #[cfg(unix)]
 use crate::tunnel::ohttp::protocol::userdata::ServerUserData;
 #[cfg(unix)]
@@ -176,6 +178,39 @@ impl OhttpServerApi {
         response
     }
 
+    /// Standard key advertisement endpoint
+    /// GET {keys_endpoint}
+    ///
+    /// This endpoint serves the client visible key in the `application/ohttp-keys` format
+    /// defined in Section 3.2 of RFC 9458, so that standard OHTTP clients can bootstrap without
+    /// the TNG-specific interface above. No attestation information is included.
+    pub async fn get_ohttp_keys(&self) -> Result<Response, TngError> {
+        let client_visible_key = self.key_manager.get_client_visible_key().await?;
+
+        // Let clients cache the key config until the key is no longer handed out. It is still
+        // accepted until it expires, so the requests of the clients which fetch it late succeed.
+        let max_age = client_visible_key
+            .stale_at
+            .duration_since(SystemTime::get())
+            .unwrap_or_default()
+            .as_secs();
+
+        let body =
+            KeyConfig::encode_list(&[client_visible_key.key_config]).map_err(TngError::from)?;
+
+        Ok((
+            [
+                (
+                    http::header::CONTENT_TYPE,
+                    OHTTP_KEYS_CONTENT_TYPE.to_owned(),
+                ),
+                (http::header::CACHE_CONTROL, format!("max-age={max_age}")),
+            ],
+            body,
+        )
+            .into_response())
+    }
+
     #[allow(unused_variables)]
     async fn get_hpke_configuration_internal(
         ra_context: &RaContext,
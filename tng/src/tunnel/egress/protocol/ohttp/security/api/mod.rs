@@ -1,3 +1,5 @@
+#[cfg(unix)]
+pub mod attestation_info;
 pub mod background_check;
 pub mod key_config;
 pub mod tunnel;
@@ -7,12 +9,16 @@ use std::sync::Arc;
 use anyhow::Result;
 use async_trait::async_trait;
 #[cfg(unix)]
-use tokio::sync::RwLock;
+use tokio::sync::{OnceCell, RwLock};
 
 use crate::config::egress::KeyArgs;
+use crate::config::ohttp_padding::OHttpPaddingPolicy;
 use crate::error::TngError;
 use crate::status::{StatusProvider, StatusQueryResult};
+#[cfg(unix)]
+use crate::tunnel::egress::protocol::ohttp::security::api::attestation_info::AttestationInfoResponse;
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::file::FileBasedKeyManager;
+use crate::tunnel::egress::protocol::ohttp::security::key_manager::metrics::KeyManagerMetrics;
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::peer_shared::PeerSharedKeyManager;
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::{
     self_generated::SelfGeneratedKeyManager, KeyManager,
@@ -44,6 +50,8 @@ pub struct OhttpServerApi {
     ra_context: Arc<RaContext>,
     /// Key manager for OHTTP key configurations
     key_manager: Arc<dyn KeyManager>,
+    /// Metrics of the OHTTP key lifecycle, shared with the key manager
+    key_manager_metrics: KeyManagerMetrics,
     /// Cache for storing passport mode key configuration responses
     ///
     /// In passport mode, the server generates an attestation (passport) that is cached
@@ -52,10 +60,16 @@ pub struct OhttpServerApi {
     /// When keys change, the cache is invalidated and regenerated.
     #[cfg(unix)]
     passport_cache: Arc<RwLock<Option<PassportCache>>>,
+    /// Cache for the response of the attestation info endpoint in passport mode, created on the
+    /// first request to the endpoint.
+    #[cfg(unix)]
+    attestation_info_cache: OnceCell<MaybeCached<AttestationInfoResponse, TngError>>,
     /// Headers to copy from the outer request to the inner (decrypted) request.
     passthrough_request_headers: Arc<crate::config::header_passthrough::HeaderPassthroughSpec>,
     /// Headers to copy from the inner (upstream) response to the outer response.
     passthrough_response_headers: Arc<crate::config::header_passthrough::HeaderPassthroughSpec>,
+    /// Padding applied to the encapsulated responses.
+    padding: Option<OHttpPaddingPolicy>,
 }
 
 impl OhttpServerApi {
@@ -65,30 +79,45 @@ impl OhttpServerApi {
     pub async fn new(
         ra_context: Arc<RaContext>,
         key: KeyArgs,
+        key_manager_metrics: KeyManagerMetrics,
         runtime: TokioRuntime,
         passthrough_request_headers: Arc<crate::config::header_passthrough::HeaderPassthroughSpec>,
         passthrough_response_headers: Arc<crate::config::header_passthrough::HeaderPassthroughSpec>,
+        padding: Option<OHttpPaddingPolicy>,
     ) -> Result<Self, TngError> {
         // Create key manager based on configuration
         let key_manager: Arc<dyn KeyManager> = match key {
-            KeyArgs::SelfGenerated { rotation_interval } => Arc::new(
-                SelfGeneratedKeyManager::new_with_auto_refresh(runtime, rotation_interval)?,
-            ),
-            KeyArgs::File { path } => {
-                Arc::new(FileBasedKeyManager::new(runtime, path.into()).await?)
-            }
-            KeyArgs::PeerShared(peer_shared_args) => {
-                Arc::new(PeerSharedKeyManager::new(runtime, peer_shared_args).await?)
+            KeyArgs::SelfGenerated { rotation_interval } => {
+                Arc::new(SelfGeneratedKeyManager::new_with_auto_refresh(
+                    runtime,
+                    rotation_interval,
+                    key_manager_metrics.clone(),
+                )?)
             }
+            KeyArgs::File { path } => Arc::new(
+                FileBasedKeyManager::new(runtime, path.into(), key_manager_metrics.clone()).await?,
+            ),
+            KeyArgs::PeerShared(peer_shared_args) => Arc::new(
+                PeerSharedKeyManager::new_with_metrics(
+                    runtime,
+                    peer_shared_args,
+                    key_manager_metrics.clone(),
+                )
+                .await?,
+            ),
         };
 
         Ok(OhttpServerApi {
             ra_context,
             key_manager,
+            key_manager_metrics,
             #[cfg(unix)]
             passport_cache: Arc::new(RwLock::new(None)),
+            #[cfg(unix)]
+            attestation_info_cache: OnceCell::new(),
             passthrough_request_headers,
             passthrough_response_headers,
+            padding,
         })
     }
 }
@@ -19,6 +19,7 @@ use crate::error::TngError;
 use crate::tunnel::egress::protocol::ohttp::security::api::OhttpServerApi;
 use crate::tunnel::egress::protocol::ohttp::security::context::TngStreamContext;
 use crate::tunnel::ohttp::key_config::PublicKeyData;
+use crate::tunnel::ohttp::padding::pad_message_stream;
 use crate::tunnel::ohttp::protocol::header::{
     OHTTP_CHUNKED_REQUEST_CONTENT_TYPE, OHTTP_CHUNKED_RESPONSE_CONTENT_TYPE,
 };
@@ -132,7 +133,12 @@ impl OhttpServerApi {
             // Get key by hint
             self.key_manager
                 .get_key_by_public_key(&PublicKeyData::new(hint.public_key))
-                .await?
+                .await
+                .inspect_err(|error| {
+                    if matches!(error, TngError::ServerKeyConfigNotFound(_)) {
+                        self.key_manager_metrics.on_key_not_found();
+                    }
+                })?
         } else {
             return Err(TngError::ServerKeyConfigHintNotSpecified);
         };
@@ -199,9 +205,11 @@ impl OhttpServerApi {
 
         // Encode the response to bhttp message
         let bhttp_encoder = BhttpEncoder::from_response(response);
+        // Pad the bhttp message to hide the response size
+        let bhttp_encoder = Box::pin(pad_message_stream(bhttp_encoder, self.padding));
         // Encrypt to get the ohttp message
         let encrypted_response = {
-            let (response_read, response_write) = tokio::io::duplex(4096);
+            let (response_read, response_write) = tokio::io::duplex(context.pipe_buffer_size);
             let server_response =
                 server_response_encapsulator.encapsulate_response(response_write.compat())?;
             context.runtime.spawn_supervised_task_current_span(async {
@@ -254,7 +262,7 @@ impl OhttpServerApi {
         &self,
         client_auth: Option<ClientAuth>,
     ) -> Result<()> {
-        match (client_auth, self.ra_context.verify_context()) {
+        match (client_auth, self.ra_context.verify_context().as_deref()) {
             (
                 Some(ClientAuth::AttestedPublicKey(AttestedPublicKey {
                     attestation_result,
@@ -9,6 +9,8 @@ pub struct TngStreamContext {
         Box<dyn CommonStreamTrait + Sync>,
         Option<AttestationResult>,
     )>,
+    /// Size in bytes of the in-memory pipe to the upstream.
+    pub pipe_buffer_size: usize,
 }
 
 impl TngStreamContext {
@@ -25,7 +27,7 @@ impl TngStreamContext {
     {
         // TODO: maybe adjust forward the request to the upstream server with reqwest?
 
-        let (s1, s2) = tokio::io::duplex(4096);
+        let (s1, s2) = tokio::io::duplex(self.pipe_buffer_size);
 
         self.sender
             .send((Box::new(s2), attestation_result))
@@ -10,7 +10,7 @@ use tokio::sync::RwLock;
 
 use crate::status::{StatusProvider, StatusQueryResult};
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::{
-    KeyInfo, KeyManager, KeyStatus,
+    metrics::KeyManagerMetrics, KeyAuditReport, KeyDigest, KeyInfo, KeyManager, KeyStatus,
 };
 use crate::tunnel::ohttp::key_config::{KeyConfigExtend, PublicKeyData};
 use crate::{
@@ -93,6 +93,7 @@ impl FileBasedKeyManager {
     ///
     /// * `runtime` - The Tokio runtime used to spawn the background watch task.
     /// * `path` - Path to the PEM-encoded PKCS#8 private key file.
+    /// * `metrics` - Metrics reporting each key loaded from the file.
     ///
     /// # Returns
     ///
@@ -105,8 +106,14 @@ impl FileBasedKeyManager {
     /// - `TngError::LoadPrivateKeyFailed` if the initial PEM file cannot be read or parsed.
     /// - `TngError::WatchFileFailed` if the file watcher cannot be initialized.
     ///
-    pub async fn new(runtime: TokioRuntime, path: PathBuf) -> Result<Self, TngError> {
+    pub async fn new(
+        runtime: TokioRuntime,
+        path: PathBuf,
+        metrics: KeyManagerMetrics,
+    ) -> Result<Self, TngError> {
         let key_info = Self::load_key_from_pem(&path).await?;
+        metrics.on_key_generated(1);
+        metrics.record_key_set(std::iter::once(&key_info));
 
         let inner = Arc::new(FileBasedKeyManagerInner {
             key: RwLock::new((key_info.key_config.public_key()?, key_info)),
@@ -140,6 +147,8 @@ impl FileBasedKeyManager {
                                 };
 
                                 *write = (public_key, new_key_info.clone());
+                                metrics.on_key_generated(1);
+                                metrics.record_key_set(std::iter::once(&new_key_info));
 
                                 tracing::info!(?path, "Successfully reloaded OHTTP key from file");
                             }
@@ -196,7 +205,10 @@ impl KeyManager for FileBasedKeyManager {
 impl StatusProvider for FileBasedKeyManager {
     async fn query_status(&self, path: &[&str]) -> Result<StatusQueryResult, TngError> {
         match path {
-            [] => Ok(StatusQueryResult::Subtree(vec!["keys".into()])),
+            [] => Ok(StatusQueryResult::Subtree(vec![
+                "keys".into(),
+                "key_audit".into(),
+            ])),
             ["keys"] => {
                 let key = self.inner.key.read().await;
                 let (public_key, info) = &*key;
@@ -218,6 +230,17 @@ impl StatusProvider for FileBasedKeyManager {
                         TngError::StatusPathNotFound
                     })
             }
+            ["key_audit"] => {
+                let key = self.inner.key.read().await;
+                let (public_key, info) = &*key;
+                let report = KeyAuditReport::local("file", vec![KeyDigest::new(public_key, info)]);
+                serde_json::to_value(&report)
+                    .map(StatusQueryResult::Value)
+                    .map_err(|e| {
+                        tracing::error!(?e, "Failed to serialise key audit report");
+                        TngError::StatusPathNotFound
+                    })
+            }
             _ => Err(TngError::StatusPathNotFound),
         }
     }
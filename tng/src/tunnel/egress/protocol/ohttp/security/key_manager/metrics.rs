@@ -0,0 +1,169 @@
+//! Metrics for the OHTTP key lifecycle
+//!
+//! Every key manager reports its key generations, rotations and expirations through
+//! [`KeyManagerMetrics`], so that operators can alert on rotation failures (e.g. the
+//! `ohttp_key_rotated_total` counter stops increasing while `ohttp_keys_active` drops to 0).
+
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use opentelemetry::metrics::{Counter, Gauge, MeterProvider as _};
+
+use crate::observability::metric::counter::{AttributedCounter, WithAttributes};
+use crate::observability::metric::simple_exporter::noop::NoopMeterProvider;
+use crate::tunnel::egress::protocol::ohttp::security::key_manager::{KeyInfo, KeyStatus};
+use crate::tunnel::service_metrics::ServiceMetrics;
+
+/// Metrics of the OHTTP keys held by a key manager.
+///
+/// This struct is free to be cloned and used anywhere.
+#[derive(Debug, Clone)]
+pub struct KeyManagerMetrics {
+    key_generated_total: AttributedCounter<Counter<u64>, u64>,
+    key_rotated_total: AttributedCounter<Counter<u64>, u64>,
+    key_expired_total: AttributedCounter<Counter<u64>, u64>,
+    key_not_found_total: AttributedCounter<Counter<u64>, u64>,
+    keys_pending: AttributedCounter<Gauge<u64>, u64>,
+    keys_active: AttributedCounter<Gauge<u64>, u64>,
+    keys_stale: AttributedCounter<Gauge<u64>, u64>,
+    active_key_id: AttributedCounter<Gauge<u64>, u64>,
+}
+
+impl KeyManagerMetrics {
+    pub fn new(service_metrics: &ServiceMetrics) -> Self {
+        Self::with_meter(service_metrics.meter(), service_metrics.attributes())
+    }
+
+    fn with_meter(
+        meter: &opentelemetry::metrics::Meter,
+        attributes: Arc<IndexMap<String, String>>,
+    ) -> Self {
+        let key_generated_total = meter
+            .u64_counter("ohttp_key_generated_total")
+            .with_description("Total number of OHTTP keys generated or loaded by this instance")
+            .build()
+            .with_attributes(attributes.clone());
+        key_generated_total.add(0);
+
+        let key_rotated_total = meter
+            .u64_counter("ohttp_key_rotated_total")
+            .with_description("Total number of OHTTP keys transitioned from active to stale")
+            .build()
+            .with_attributes(attributes.clone());
+        key_rotated_total.add(0);
+
+        let key_expired_total = meter
+            .u64_counter("ohttp_key_expired_total")
+            .with_description("Total number of expired OHTTP keys removed from the key set")
+            .build()
+            .with_attributes(attributes.clone());
+        key_expired_total.add(0);
+
+        let key_not_found_total = meter
+            .u64_counter("ohttp_key_not_found_total")
+            .with_description(
+                "Total number of OHTTP requests rejected because the key they were encrypted with is unknown",
+            )
+            .build()
+            .with_attributes(attributes.clone());
+        key_not_found_total.add(0);
+
+        let keys_pending = meter
+            .u64_gauge("ohttp_keys_pending")
+            .with_description("The number of pending OHTTP keys")
+            .build()
+            .with_attributes(attributes.clone());
+        keys_pending.record(0);
+
+        let keys_active = meter
+            .u64_gauge("ohttp_keys_active")
+            .with_description("The number of active OHTTP keys")
+            .build()
+            .with_attributes(attributes.clone());
+        keys_active.record(0);
+
+        let keys_stale = meter
+            .u64_gauge("ohttp_keys_stale")
+            .with_description("The number of stale OHTTP keys")
+            .build()
+            .with_attributes(attributes.clone());
+        keys_stale.record(0);
+
+        let active_key_id = meter
+            .u64_gauge("ohttp_active_key_id")
+            .with_description("The key ID of the OHTTP key currently handed out to clients")
+            .build()
+            .with_attributes(attributes);
+
+        Self {
+            key_generated_total,
+            key_rotated_total,
+            key_expired_total,
+            key_not_found_total,
+            keys_pending,
+            keys_active,
+            keys_stale,
+            active_key_id,
+        }
+    }
+
+    /// Metrics which are not exported anywhere.
+    pub fn noop() -> Self {
+        Self::with_meter(
+            &NoopMeterProvider::new().meter("tng"),
+            Arc::new(IndexMap::new()),
+        )
+    }
+
+    pub fn on_key_generated(&self, count: u64) {
+        self.key_generated_total.add(count);
+    }
+
+    pub fn on_key_rotated(&self, count: u64) {
+        self.key_rotated_total.add(count);
+    }
+
+    pub fn on_key_expired(&self, count: u64) {
+        self.key_expired_total.add(count);
+    }
+
+    pub fn on_key_not_found(&self) {
+        self.key_not_found_total.add(1);
+    }
+
+    /// Update the gauges from the current key set.
+    ///
+    /// The `active_key_id` gauge is set to the key ID of the active key with the latest
+    /// `expire_at`, which is the one handed out to new clients.
+    pub fn record_key_set<'a>(&self, keys: impl Iterator<Item = &'a KeyInfo>) {
+        let (mut pending, mut active, mut stale) = (0, 0, 0);
+        let mut client_visible: Option<&KeyInfo> = None;
+
+        for key_info in keys {
+            match key_info.status {
+                KeyStatus::Pending => pending += 1,
+                KeyStatus::Active => {
+                    active += 1;
+                    if client_visible.is_none_or(|k| k.expire_at < key_info.expire_at) {
+                        client_visible = Some(key_info);
+                    }
+                }
+                KeyStatus::Stale => stale += 1,
+            }
+        }
+
+        self.keys_pending.record(pending);
+        self.keys_active.record(active);
+        self.keys_stale.record(stale);
+        if let Some(key_info) = client_visible {
+            self.active_key_id
+                .record(key_info.key_config.key_id() as u64);
+        }
+    }
+}
+
+impl Default for KeyManagerMetrics {
+    fn default() -> Self {
+        Self::noop()
+    }
+}
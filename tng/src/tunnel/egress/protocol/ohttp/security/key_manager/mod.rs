@@ -12,8 +12,11 @@ use web_time_compat::{SystemTime, SystemTimeExt};
 
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::BTreeSet;
 
 pub mod file;
+pub mod metrics;
 pub mod peer_shared;
 pub mod self_generated;
 
@@ -157,6 +160,109 @@ impl std::fmt::Debug for KeyInfo {
     }
 }
 
+/// Public view of a key, without the private key, as reported by the key audit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyDigest {
+    pub key_id: u8,
+    pub public_key: PublicKeyData,
+    pub status: KeyStatus,
+    #[serde(with = "humantime_serde")]
+    pub expire_at: SystemTime,
+}
+
+impl KeyDigest {
+    pub fn new(public_key: &PublicKeyData, key_info: &KeyInfo) -> Self {
+        Self {
+            key_id: key_info.key_config.key_id(),
+            public_key: public_key.clone(),
+            status: key_info.status,
+            expire_at: key_info.expire_at,
+        }
+    }
+}
+
+/// Report of the keys known by this node compared with the keys known by its peers.
+///
+/// Served at `/status/egress/<id>/ohttp/key_audit`, to debug requests rejected because the
+/// client encrypted with a key this node does not know.
+#[derive(Debug, Serialize)]
+pub struct KeyAuditReport {
+    key_manager_type: &'static str,
+    /// ID of this node, if the key manager is part of a cluster
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node_id: Option<String>,
+    local_keys: Vec<KeyDigest>,
+    peers: Vec<PeerKeyAudit>,
+    /// Whether every peer which responded knows exactly the same set of keys as this node
+    consistent: bool,
+}
+
+/// Keys reported by a single peer, and how they differ from the local keys.
+#[derive(Debug, Serialize)]
+pub struct PeerKeyAudit {
+    node_id: String,
+    /// Keys reported by the peer, or `None` if the peer did not respond in time
+    keys: Option<Vec<KeyDigest>>,
+    /// Keys known by the peer but not by this node
+    missing_locally: Vec<PublicKeyData>,
+    /// Keys known by this node but not by the peer
+    missing_on_peer: Vec<PublicKeyData>,
+}
+
+impl KeyAuditReport {
+    /// Build a report for a key manager which does not share keys with any peer.
+    pub fn local(key_manager_type: &'static str, local_keys: Vec<KeyDigest>) -> Self {
+        Self::with_peers(key_manager_type, None, local_keys, vec![])
+    }
+
+    /// Build a report comparing the local keys with the keys reported by each peer.
+    pub fn with_peers(
+        key_manager_type: &'static str,
+        node_id: Option<String>,
+        mut local_keys: Vec<KeyDigest>,
+        peers: Vec<(String, Option<Vec<KeyDigest>>)>,
+    ) -> Self {
+        local_keys.sort_by(|a, b| a.public_key.cmp(&b.public_key));
+        let local_set: BTreeSet<&PublicKeyData> =
+            local_keys.iter().map(|key| &key.public_key).collect();
+
+        let peers = peers
+            .into_iter()
+            .map(|(node_id, keys)| {
+                let (missing_locally, missing_on_peer) = match &keys {
+                    Some(keys) => {
+                        let peer_set: BTreeSet<&PublicKeyData> =
+                            keys.iter().map(|key| &key.public_key).collect();
+                        (
+                            peer_set.difference(&local_set).cloned().cloned().collect(),
+                            local_set.difference(&peer_set).cloned().cloned().collect(),
+                        )
+                    }
+                    None => (vec![], vec![]),
+                };
+                PeerKeyAudit {
+                    node_id,
+                    keys,
+                    missing_locally,
+                    missing_on_peer,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let consistent = peers
+            .iter()
+            .all(|peer| peer.missing_locally.is_empty() && peer.missing_on_peer.is_empty());
+
+        Self {
+            key_manager_type,
+            node_id,
+            local_keys,
+            peers,
+            consistent,
+        }
+    }
+}
+
 /// Format a `SystemTime` for debug output.
 ///
 /// `SystemTime` is `std::time::SystemTime` on both native and wasm (via
@@ -209,6 +315,50 @@ mod tests {
         }
     }
 
+    fn make_test_key_digest(public_key: &[u8]) -> KeyDigest {
+        KeyDigest {
+            key_id: 0,
+            public_key: PublicKeyData::new(public_key.to_vec()),
+            status: KeyStatus::Active,
+            expire_at: SystemTime::get(),
+        }
+    }
+
+    #[test]
+    fn test_key_audit_report_consistency() {
+        let local = vec![make_test_key_digest(b"a"), make_test_key_digest(b"b")];
+
+        let report = KeyAuditReport::with_peers(
+            "peer_shared",
+            Some("local".into()),
+            local.clone(),
+            vec![
+                ("same".into(), Some(local.clone())),
+                ("silent".into(), None),
+            ],
+        );
+        assert!(report.consistent);
+
+        let report = KeyAuditReport::with_peers(
+            "peer_shared",
+            Some("local".into()),
+            local,
+            vec![(
+                "diverged".into(),
+                Some(vec![make_test_key_digest(b"b"), make_test_key_digest(b"c")]),
+            )],
+        );
+        assert!(!report.consistent);
+        assert_eq!(
+            report.peers[0].missing_locally,
+            vec![PublicKeyData::new(b"c".to_vec())]
+        );
+        assert_eq!(
+            report.peers[0].missing_on_peer,
+            vec![PublicKeyData::new(b"a".to_vec())]
+        );
+    }
+
     #[test]
     fn test_key_info_debug_output() {
         let info = make_test_key_info(1);
@@ -1,7 +1,7 @@
 use crate::error::TngError;
 use crate::status::{StatusProvider, StatusQueryResult};
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::{
-    KeyInfo, KeyManager, KeyStatus,
+    KeyAuditReport, KeyDigest, KeyInfo, KeyManager, KeyStatus,
 };
 use crate::tunnel::ohttp::key_config::PublicKeyData;
 
@@ -85,7 +85,10 @@ impl KeyManager for super::PeerSharedKeyManager {
 impl StatusProvider for super::PeerSharedKeyManager {
     async fn query_status(&self, path: &[&str]) -> Result<StatusQueryResult, TngError> {
         match path {
-            [] => Ok(StatusQueryResult::Subtree(vec!["keys".into()])),
+            [] => Ok(StatusQueryResult::Subtree(vec![
+                "keys".into(),
+                "key_audit".into(),
+            ])),
             ["keys"] => {
                 let node_id = self.serf.memberlist().local_id().to_string();
 
@@ -123,6 +126,32 @@ impl StatusProvider for super::PeerSharedKeyManager {
                         TngError::StatusPathNotFound
                     })
             }
+            ["key_audit"] => {
+                let node_id = self.serf.memberlist().local_id().to_string();
+
+                let local_keys = {
+                    let cks = self.inner.cluster_key_set.read().await;
+                    cks.iter_keys()
+                        .map(|(public_key, info)| KeyDigest::new(public_key, info))
+                        .collect()
+                };
+
+                let peers = self
+                    .query_key_digests_from_cluster()
+                    .await
+                    .inspect_err(|error| {
+                        tracing::warn!(?error, "Failed to query key digests from cluster")
+                    })?;
+
+                let report =
+                    KeyAuditReport::with_peers("peer_shared", Some(node_id), local_keys, peers);
+                serde_json::to_value(&report)
+                    .map(StatusQueryResult::Value)
+                    .map_err(|e| {
+                        tracing::error!(?e, "Failed to serialise key audit report");
+                        TngError::StatusPathNotFound
+                    })
+            }
             _ => Err(TngError::StatusPathNotFound),
         }
     }
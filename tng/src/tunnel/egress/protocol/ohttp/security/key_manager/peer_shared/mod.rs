@@ -2,6 +2,7 @@ mod cluster_key_set;
 mod key_manager;
 mod memberlist_rats_quic;
 mod runtime;
+mod sealer;
 mod serf;
 mod serf_message;
 
@@ -0,0 +1,121 @@
+//! Sealing of key material exchanged between peers with a pre-shared join token.
+//!
+//! When `join_token` is configured, every serf payload carrying private keys (cluster key set
+//! queries, key queries and key set broadcasts) is encrypted with HPKE to a X25519 key pair
+//! derived from the token. Only nodes configured with the same token can derive the key pair,
+//! so a node which can reach the gossip port but does not know the token can neither read the
+//! key material nor inject its own keys into the cluster.
+
+use anyhow::{anyhow, Context, Result};
+use hpke::aead::ChaCha20Poly1305;
+use hpke::kdf::HkdfSha256;
+use hpke::kem::X25519HkdfSha256;
+use hpke::{Deserializable as _, Kem, OpModeR, OpModeS, Serializable as _};
+use rand::SeedableRng as _;
+use rand_chacha::ChaCha12Rng;
+
+/// HPKE info string, binding the sealed payloads to this usage.
+const SEALER_INFO: &[u8] = b"tng peer_shared key material v1";
+
+/// Length of the encapsulated key prepended to every sealed payload.
+const ENCAPPED_KEY_LEN: usize = 32;
+
+pub(super) struct KeyMaterialSealer {
+    sk: <X25519HkdfSha256 as Kem>::PrivateKey,
+    pk: <X25519HkdfSha256 as Kem>::PublicKey,
+}
+
+impl KeyMaterialSealer {
+    pub fn new(join_token: &str) -> Self {
+        let (sk, pk) = X25519HkdfSha256::derive_keypair(join_token.as_bytes());
+        Self { sk, pk }
+    }
+
+    /// Encrypt the payload. The output is the encapsulated key followed by the ciphertext.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut rng = ChaCha12Rng::from_os_rng();
+        let (encapped_key, ciphertext) =
+            hpke::single_shot_seal::<ChaCha20Poly1305, HkdfSha256, X25519HkdfSha256, _>(
+                &OpModeS::Base,
+                &self.pk,
+                SEALER_INFO,
+                plaintext,
+                &[],
+                &mut rng,
+            )
+            .map_err(|e| anyhow!("failed to seal key material: {e:?}"))?;
+
+        let mut sealed = encapped_key.to_bytes().to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a payload produced by [`Self::seal`] on a node with the same join token.
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < ENCAPPED_KEY_LEN {
+            return Err(anyhow!("sealed key material is too short"));
+        }
+        let (encapped_key, ciphertext) = sealed.split_at(ENCAPPED_KEY_LEN);
+        let encapped_key = <X25519HkdfSha256 as Kem>::EncappedKey::from_bytes(encapped_key)
+            .map_err(|e| anyhow!("invalid encapsulated key: {e:?}"))?;
+
+        hpke::single_shot_open::<ChaCha20Poly1305, HkdfSha256, X25519HkdfSha256>(
+            &OpModeR::Base,
+            &self.sk,
+            &encapped_key,
+            SEALER_INFO,
+            ciphertext,
+            &[],
+        )
+        .map_err(|e| anyhow!("{e:?}"))
+        .context(
+            "failed to open key material, the peer may be configured with a different join_token",
+        )
+    }
+}
+
+/// Seal the payload if a join token is configured, or pass it through unchanged.
+pub(super) fn seal_if_configured(
+    sealer: Option<&KeyMaterialSealer>,
+    payload: Vec<u8>,
+) -> Result<Vec<u8>> {
+    match sealer {
+        Some(sealer) => sealer.seal(&payload),
+        None => Ok(payload),
+    }
+}
+
+/// Open the payload if a join token is configured, or pass it through unchanged.
+pub(super) fn open_if_configured(
+    sealer: Option<&KeyMaterialSealer>,
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    match sealer {
+        Some(sealer) => sealer.open(payload),
+        None => Ok(payload.to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open_with_same_token() -> Result<()> {
+        let sealer = KeyMaterialSealer::new("secret");
+        let sealed = sealer.seal(b"key material")?;
+        assert_ne!(&sealed[ENCAPPED_KEY_LEN..], b"key material");
+
+        let opened = KeyMaterialSealer::new("secret").open(&sealed)?;
+        assert_eq!(opened, b"key material");
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_with_different_token_fails() -> Result<()> {
+        let sealed = KeyMaterialSealer::new("secret").seal(b"key material")?;
+        assert!(KeyMaterialSealer::new("other").open(&sealed).is_err());
+        assert!(KeyMaterialSealer::new("secret").open(&sealed[..8]).is_err());
+        Ok(())
+    }
+}
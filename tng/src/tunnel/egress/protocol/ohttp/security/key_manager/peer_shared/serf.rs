@@ -3,15 +3,21 @@ use crate::error::TngError;
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::peer_shared::cluster_key_set::ClusterKeySet;
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::peer_shared::memberlist_rats_quic::RatsQuic;
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::peer_shared::runtime::InstrumentedRuntime;
+use crate::tunnel::egress::protocol::ohttp::security::key_manager::peer_shared::sealer::{
+    open_if_configured, seal_if_configured, KeyMaterialSealer,
+};
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::peer_shared::serf_message::pb;
-use crate::tunnel::egress::protocol::ohttp::security::key_manager::{KeyInfo, KeyStatus};
+use crate::tunnel::egress::protocol::ohttp::security::key_manager::metrics::KeyManagerMetrics;
+use crate::tunnel::egress::protocol::ohttp::security::key_manager::{
+    KeyDigest, KeyInfo, KeyStatus,
+};
 use crate::tunnel::ohttp::key_config::{KeyConfigExtend, PublicKeyData};
 use crate::tunnel::utils::runtime::TokioRuntime;
 use crate::tunnel::utils::file_watcher::FileWatcher;
 
 use again::RetryPolicy;
 use anyhow::{anyhow, Context, Result};
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use prost::Message;
 use scopeguard::defer;
 use serf::delegate::CompositeDelegate;
@@ -39,6 +45,7 @@ use crate::tunnel::ra_context::RaContext;
 // Serf protocol constants
 const SERF_QUERY_CLUSTER_KEY_SET: &str = "query_cluster_key_set";
 const SERF_QUERY_KEY: &str = "query_key";
+const SERF_QUERY_KEY_DIGEST: &str = "query_key_digest";
 const SERF_USER_EVENT_BROADCAST_CLUSTER_KEY_SET: &str = "broadcast_cluster_key_set";
 
 pub struct PeerSharedKeyManager {
@@ -65,10 +72,33 @@ pub(super) struct PeerSharedKeyManagerInner {
     /// Notify key_watcher to check immediately
     /// Shared with ClusterKeySet for unified notification
     pub(super) check_notify: Arc<tokio::sync::Notify>,
+    /// Metrics of the key lifecycle on this node
+    pub(super) metrics: KeyManagerMetrics,
+    /// Seals key material exchanged with peers, if a join token is configured
+    pub(super) sealer: Option<KeyMaterialSealer>,
 }
 
 impl PeerSharedKeyManager {
+    #[cfg(test)]
     pub async fn new(runtime: TokioRuntime, peer_shared: PeerSharedArgs) -> Result<Self, TngError> {
+        Self::new_with_metrics(runtime, peer_shared, KeyManagerMetrics::noop()).await
+    }
+
+    pub async fn new_with_metrics(
+        runtime: TokioRuntime,
+        peer_shared: PeerSharedArgs,
+        metrics: KeyManagerMetrics,
+    ) -> Result<Self, TngError> {
+        let sealer = peer_shared
+            .join_token
+            .as_deref()
+            .map(KeyMaterialSealer::new);
+        if sealer.is_none() && peer_shared.ra_args.verify.is_none() {
+            tracing::warn!(
+                "Neither `join_token` nor `verify` is configured for the peer_shared key manager, any node which can reach the gossip port is able to join the cluster and receive key material"
+            );
+        }
+
         // Step 1: Initialize Serf node and network transport
         let (serf, subscriber) = Self::setup_serf(&runtime, &peer_shared).await?;
 
@@ -76,17 +106,26 @@ impl PeerSharedKeyManager {
         Self::spawn_cluster_join_tasks(&runtime, &serf, &peer_shared).await?;
 
         // Step 3: Preboot phase - synchronize cluster key set
-        let cluster_key_set = Self::preboot(&serf, peer_shared.rotation_interval).await?;
+        let cluster_key_set = Self::preboot(
+            &serf,
+            peer_shared.rotation_interval,
+            sealer.as_ref(),
+            &metrics,
+        )
+        .await?;
 
         // Step 4: Initialize inner state
         // Create shared notify and pass it to ClusterKeySet
         let check_notify = Arc::new(tokio::sync::Notify::new());
         let mut cluster_key_set = cluster_key_set;
         cluster_key_set.set_notify(check_notify.clone());
+        metrics.record_key_set(cluster_key_set.iter_keys().map(|(_, ki)| ki));
 
         let inner = Arc::new(PeerSharedKeyManagerInner {
             cluster_key_set: RwLock::new(cluster_key_set),
             check_notify,
+            metrics,
+            sealer,
         });
 
         // Step 5: Spawn key watcher task (handles key status transitions)
@@ -292,7 +331,12 @@ impl PeerSharedKeyManager {
     ///
     /// This phase is blocking - it waits until we have synchronized with
     /// the cluster or determined we are the first node.
-    async fn preboot(serf: &Serf, rotation_interval: u64) -> Result<ClusterKeySet, TngError> {
+    async fn preboot(
+        serf: &Serf,
+        rotation_interval: u64,
+        sealer: Option<&KeyMaterialSealer>,
+        metrics: &KeyManagerMetrics,
+    ) -> Result<ClusterKeySet, TngError> {
         tracing::info!("Starting preboot phase: synchronizing cluster key set");
 
         loop {
@@ -326,8 +370,19 @@ impl PeerSharedKeyManager {
 
                 // Wait for all responses until channel closes or timeout
                 while let Ok(response) = resp_rx.recv().await {
+                    let payload = match open_if_configured(sealer, response.payload()) {
+                        Ok(payload) => payload,
+                        Err(error) => {
+                            tracing::warn!(
+                                ?error,
+                                peer_node_id = ?response.from(),
+                                "Ignoring cluster key set from peer"
+                            );
+                            continue;
+                        }
+                    };
                     if let Ok(pb_response) =
-                        pb::QueryClusterKeySetResponse::decode(response.payload().as_ref())
+                        pb::QueryClusterKeySetResponse::decode(payload.as_ref())
                     {
                         if let Some(pb_cks) = pb_response.cluster_key_set {
                             if let Ok(remote_key_set) = ClusterKeySet::try_from(pb_cks) {
@@ -396,6 +451,7 @@ impl PeerSharedKeyManager {
             public_key_hex = %hex::encode(public_key.as_ref()),
             "Created initial active key"
         );
+        metrics.on_key_generated(1);
         Ok(ClusterKeySet::new(
             public_key,
             initial_key,
@@ -442,6 +498,7 @@ impl PeerSharedKeyManager {
                     //    rotation when the bootstrap-created initial key (which skips the
                     //    Pending->Active path) becomes stale.
                     let stale_transitioned = cks.transition_active_to_stale(now);
+                    inner.metrics.on_key_rotated(stale_transitioned as u64);
                     if stale_transitioned > 0 {
                         let stale_keys: Vec<String> = cks
                             .iter_keys()
@@ -457,6 +514,7 @@ impl PeerSharedKeyManager {
 
                     // 3. Remove expired stale keys
                     let removed_count = cks.remove_expired_keys(now);
+                    inner.metrics.on_key_expired(removed_count as u64);
                     if removed_count > 0 {
                         let remaining_keys: Vec<String> = cks
                             .iter_keys()
@@ -484,6 +542,10 @@ impl PeerSharedKeyManager {
                     //    pending key to drive convergence to a single active key.
                     let multiple_active_no_pending = cks.has_multiple_active_without_pending();
 
+                    inner
+                        .metrics
+                        .record_key_set(cks.iter_keys().map(|(_, ki)| ki));
+
                     // Trigger check_and_key_rotation when:
                     // - A pending key was activated (normal rotation path), OR
                     // - An active key became stale via transition, OR
@@ -545,16 +607,21 @@ impl PeerSharedKeyManager {
         // Try to generate pending key (will fail if already has pending key)
         let generated = {
             let mut cks = inner.cluster_key_set.write().await;
-            match cks.generate_pending_key_if_none() {
+            let generated = match cks.generate_pending_key_if_none() {
                 Ok(generated) => generated,
                 Err(error) => {
                     tracing::error!(?error, "Failed to generate pending key");
                     return;
                 }
-            }
+            };
+            inner
+                .metrics
+                .record_key_set(cks.iter_keys().map(|(_, ki)| ki));
+            generated
         };
 
         if let Some(public_key) = generated {
+            inner.metrics.on_key_generated(1);
             tracing::info!(
                 ?public_key,
                 public_key_hex = %hex::encode(public_key.as_ref()),
@@ -612,10 +679,16 @@ impl PeerSharedKeyManager {
                 e
             ))
         })?;
-
-        serf.user_event(SERF_USER_EVENT_BROADCAST_CLUSTER_KEY_SET, event_buf, false)
-            .await
-            .map_err(|e| TngError::SerfCrateError(anyhow!("Failed to broadcast: {}", e)))?;
+        let event_buf = seal_if_configured(inner.sealer.as_ref(), event_buf.to_vec())
+            .map_err(TngError::KeyUpdateMessageDecodeError)?;
+
+        serf.user_event(
+            SERF_USER_EVENT_BROADCAST_CLUSTER_KEY_SET,
+            Bytes::from(event_buf),
+            false,
+        )
+        .await
+        .map_err(|e| TngError::SerfCrateError(anyhow!("Failed to broadcast: {}", e)))?;
 
         Ok(())
     }
@@ -735,6 +808,8 @@ impl PeerSharedKeyManager {
 
                 let mut response_buf = BytesMut::new();
                 response.encode(&mut response_buf)?;
+                let response_buf =
+                    seal_if_configured(inner.sealer.as_ref(), response_buf.to_vec())?;
 
                 query.respond(response_buf.into()).await?;
             }
@@ -753,11 +828,28 @@ impl PeerSharedKeyManager {
 
                     let mut response_buf = BytesMut::new();
                     response.encode(&mut response_buf)?;
+                    let response_buf =
+                        seal_if_configured(inner.sealer.as_ref(), response_buf.to_vec())?;
 
                     query.respond(response_buf.into()).await?;
                 }
                 // If not found, don't respond (empty response)
             }
+            SERF_QUERY_KEY_DIGEST => {
+                let cks = inner.cluster_key_set.read().await;
+
+                let response = pb::QueryKeyDigestResponse {
+                    keys: cks
+                        .iter_keys()
+                        .map(|(public_key, key_info)| KeyDigest::new(public_key, key_info).into())
+                        .collect(),
+                };
+
+                let mut response_buf = BytesMut::new();
+                response.encode(&mut response_buf)?;
+
+                query.respond(response_buf.into()).await?;
+            }
             _ => {
                 tracing::warn!(query_name = %query.name(), "Ignoring unknown query");
             }
@@ -773,7 +865,8 @@ impl PeerSharedKeyManager {
     ) -> Result<()> {
         match event_name {
             SERF_USER_EVENT_BROADCAST_CLUSTER_KEY_SET => {
-                let event = pb::BroadcastClusterKeySetEvent::decode(payload)?;
+                let payload = open_if_configured(inner.sealer.as_ref(), payload)?;
+                let event = pb::BroadcastClusterKeySetEvent::decode(payload.as_ref())?;
 
                 if let Some(pb_cluster_key_set) = event.cluster_key_set {
                     let remote_key_set: ClusterKeySet = pb_cluster_key_set.try_into()?;
@@ -833,7 +926,15 @@ impl PeerSharedKeyManager {
                 continue;
             }
 
-            match pb::QueryKeyResponse::decode(response.payload().as_ref()) {
+            let payload = match open_if_configured(self.inner.sealer.as_ref(), response.payload()) {
+                Ok(payload) => payload,
+                Err(error) => {
+                    tracing::warn!(?error, node_id = ?response.from(), "Ignoring key from peer");
+                    continue;
+                }
+            };
+
+            match pb::QueryKeyResponse::decode(payload.as_ref()) {
                 Ok(query_response) => {
                     if let Some(pb_key_info) = query_response.key_info {
                         match TryInto::<KeyInfo>::try_into(pb_key_info) {
@@ -869,6 +970,71 @@ impl PeerSharedKeyManager {
         Ok(None)
     }
 
+    /// Query the public view of the keys known by every other member of the cluster.
+    ///
+    /// Returns the node ID of each alive member together with the keys it reported, or
+    /// `None` if it did not respond before the query timed out.
+    pub(crate) async fn query_key_digests_from_cluster(
+        &self,
+    ) -> Result<Vec<(String, Option<Vec<KeyDigest>>)>, TngError> {
+        let local_id = self.serf.local_id().clone();
+        let mut peers: Vec<(String, Option<Vec<KeyDigest>>)> = self
+            .serf
+            .members()
+            .await
+            .iter()
+            .filter(|m| m.status == MemberStatus::Alive && *m.node().id() != local_id)
+            .map(|m| (m.node().id().to_string(), None))
+            .collect();
+
+        if peers.is_empty() {
+            return Ok(peers);
+        }
+
+        let request = pb::QueryKeyDigestRequest {};
+        let mut request_buf = BytesMut::new();
+        request
+            .encode(&mut request_buf)
+            .context("Failed to encode query request")
+            .map_err(TngError::KeyUpdateMessageDecodeError)?;
+
+        let query_response = self
+            .serf
+            .query(SERF_QUERY_KEY_DIGEST, request_buf, None)
+            .await
+            .context("Failed to send query")
+            .map_err(TngError::SerfCrateError)?;
+
+        let resp_rx = query_response.response_rx();
+
+        // Wait for all responses until channel closes or timeout
+        while let Ok(response) = resp_rx.recv().await {
+            let node_id = response.from().id().to_string();
+            let keys = match pb::QueryKeyDigestResponse::decode(response.payload().as_ref())
+                .map_err(anyhow::Error::from)
+                .and_then(|response| {
+                    response
+                        .keys
+                        .into_iter()
+                        .map(KeyDigest::try_from)
+                        .collect::<Result<Vec<_>>>()
+                }) {
+                Ok(keys) => keys,
+                Err(error) => {
+                    tracing::warn!(?error, %node_id, "Failed to decode key digest from peer");
+                    continue;
+                }
+            };
+
+            match peers.iter_mut().find(|(id, _)| *id == node_id) {
+                Some((_, peer_keys)) => *peer_keys = Some(keys),
+                None => peers.push((node_id, Some(keys))),
+            }
+        }
+
+        Ok(peers)
+    }
+
     /// Join a set of peers after construction.
     ///
     /// Each peer is joined via `retry_join_peer` (exponential backoff until
@@ -1110,10 +1276,13 @@ mod tests {
             port,
             peers,
             peers_file: None,
+            join_token: None,
             ra_args: RaArgsUnchecked {
                 no_ra: true,
                 attest: None,
                 verify: None,
+                attest_profile: None,
+                verify_profile: None,
             },
         }
     }
@@ -1475,6 +1644,87 @@ mod tests {
         .expect("test_node_join_key_sync failed");
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+    async fn test_node_join_key_sync_with_join_token() {
+        run_test_with_tokio_runtime(async |runtime| {
+            let port_a = portpicker::pick_unused_port().unwrap();
+            let port_b = portpicker::pick_unused_port().unwrap();
+
+            // Start bootstrap node A
+            let mut args_a = make_peer_shared_args(port_a, vec![], 300);
+            args_a.join_token = Some("cluster-secret".into());
+            let manager_a = PeerSharedKeyManager::new(runtime.clone(), args_a).await?;
+
+            let a_pk = manager_a
+                .get_client_visible_key()
+                .await?
+                .key_config
+                .public_key()?;
+
+            // Start node B joining A with the same token
+            let mut args_b =
+                make_peer_shared_args(port_b, vec![format!("127.0.0.1:{}", port_a)], 300);
+            args_b.join_token = Some("cluster-secret".into());
+            let manager_b = PeerSharedKeyManager::new(runtime, args_b).await?;
+
+            // B can open the sealed key set and receives A's key
+            wait_for_key_in_cks(&manager_b, &a_pk, Duration::from_secs(10)).await?;
+
+            Ok(())
+        })
+        .await
+        .expect("test_node_join_key_sync_with_join_token failed");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+    async fn test_node_join_key_sync_with_wrong_join_token() {
+        run_test_with_tokio_runtime(async |runtime| {
+            let port_a = portpicker::pick_unused_port().unwrap();
+            let port_b = portpicker::pick_unused_port().unwrap();
+
+            // Start bootstrap node A
+            let mut args_a = make_peer_shared_args(port_a, vec![], 300);
+            args_a.join_token = Some("cluster-secret".into());
+            let manager_a = PeerSharedKeyManager::new(runtime.clone(), args_a).await?;
+
+            let a_pk = manager_a
+                .get_client_visible_key()
+                .await?
+                .key_config
+                .public_key()?;
+
+            // Start node B joining A with another token
+            let mut args_b =
+                make_peer_shared_args(port_b, vec![format!("127.0.0.1:{}", port_a)], 300);
+            args_b.join_token = Some("wrong-secret".into());
+            let manager_b = PeerSharedKeyManager::new(runtime, args_b).await?;
+
+            let b_pk = manager_b
+                .get_client_visible_key()
+                .await?
+                .key_config
+                .public_key()?;
+
+            // B joins the gossip, but the nodes can not open the key material of each other
+            wait_for_member_count(&manager_b, 2, Duration::from_secs(10)).await?;
+            assert!(
+                wait_for_key_in_cks(&manager_b, &a_pk, Duration::from_secs(3))
+                    .await
+                    .is_err()
+            );
+            assert!(
+                wait_for_key_in_cks(&manager_a, &b_pk, Duration::from_secs(3))
+                    .await
+                    .is_err()
+            );
+            assert_ne!(a_pk, b_pk);
+
+            Ok(())
+        })
+        .await
+        .expect("test_node_join_key_sync_with_wrong_join_token failed");
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
     async fn test_retry_join_on_startup() {
         run_test_with_tokio_runtime(async |runtime| {
@@ -1972,6 +2222,45 @@ mod tests {
         .expect("test_query_key_fallback failed");
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
+    async fn test_query_key_digests_from_cluster() {
+        run_test_with_tokio_runtime(async |runtime| {
+            let port_a = portpicker::pick_unused_port().unwrap();
+            let port_b = portpicker::pick_unused_port().unwrap();
+
+            // Start A (bootstrap)
+            let args_a = make_peer_shared_args(port_a, vec![], 300);
+            let manager_a = PeerSharedKeyManager::new(runtime.clone(), args_a).await?;
+
+            let a_pk = manager_a
+                .get_client_visible_key()
+                .await?
+                .key_config
+                .public_key()?;
+
+            // Start B joining A
+            let args_b = make_peer_shared_args(port_b, vec![format!("127.0.0.1:{}", port_a)], 300);
+            let manager_b = PeerSharedKeyManager::new(runtime.clone(), args_b).await?;
+
+            wait_for_key_in_cks(&manager_b, &a_pk, Duration::from_secs(10)).await?;
+
+            // B should see exactly one peer (A), reporting A's key
+            let peers = manager_b.query_key_digests_from_cluster().await?;
+            assert_eq!(peers.len(), 1, "should report exactly one peer");
+            let (node_id, keys) = &peers[0];
+            assert_eq!(*node_id, manager_a.serf.local_id().to_string());
+            let keys = keys.as_ref().expect("peer A should respond");
+            assert!(
+                keys.iter().any(|key| key.public_key == a_pk),
+                "peer A should report its active key"
+            );
+
+            Ok(())
+        })
+        .await
+        .expect("test_query_key_digests_from_cluster failed");
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 10)]
     async fn test_get_client_visible_key_consistency() {
         run_test_with_tokio_runtime(async |runtime| {
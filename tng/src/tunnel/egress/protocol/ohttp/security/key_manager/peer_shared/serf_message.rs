@@ -7,7 +7,8 @@ use pkcs8::{DecodePrivateKey, LineEnding};
 use prost_types::Timestamp;
 use std::time::SystemTime;
 
-use crate::tunnel::egress::protocol::ohttp::security::key_manager::{self, KeyInfo};
+use crate::tunnel::egress::protocol::ohttp::security::key_manager::{self, KeyDigest, KeyInfo};
+use crate::tunnel::ohttp::key_config::PublicKeyData;
 
 // ========== Rust to Protobuf ==========
 
@@ -25,6 +26,17 @@ impl TryFrom<KeyInfo> for pb::KeyInfo {
     }
 }
 
+impl From<KeyDigest> for pb::KeyDigest {
+    fn from(value: KeyDigest) -> Self {
+        Self {
+            key_id: value.key_id as u32,
+            public_key: value.public_key.into_vec(),
+            status: value.status as i32,
+            expire_at: Some(system_time_to_timestamp(value.expire_at)),
+        }
+    }
+}
+
 impl TryFrom<ohttp::KeyConfig> for pb::KeyConfig {
     type Error = anyhow::Error;
 
@@ -90,6 +102,25 @@ impl TryFrom<pb::KeyInfo> for KeyInfo {
     }
 }
 
+impl TryFrom<pb::KeyDigest> for KeyDigest {
+    type Error = anyhow::Error;
+
+    fn try_from(value: pb::KeyDigest) -> Result<Self, Self::Error> {
+        let key_id = u8::try_from(value.key_id)
+            .with_context(|| format!("key_id {} out of range for u8", value.key_id))?;
+        let status = value.status.try_into().context("invalid KeyStatus")?;
+        let expire_at =
+            timestamp_to_system_time(value.expire_at).context("invalid expire_at timestamp")?;
+
+        Ok(Self {
+            key_id,
+            public_key: PublicKeyData::new(value.public_key),
+            status,
+            expire_at,
+        })
+    }
+}
+
 impl TryFrom<pb::KeyConfig> for ohttp::KeyConfig {
     type Error = anyhow::Error;
 
@@ -1,7 +1,7 @@
 use crate::error::TngError;
 use crate::status::{StatusProvider, StatusQueryResult};
 use crate::tunnel::egress::protocol::ohttp::security::key_manager::{
-    KeyInfo, KeyManager, KeyStatus,
+    metrics::KeyManagerMetrics, KeyAuditReport, KeyDigest, KeyInfo, KeyManager, KeyStatus,
 };
 use crate::tunnel::ohttp::key_config::{KeyConfigExtend, PublicKeyData};
 use crate::tunnel::utils::runtime::supervised_task::SupervisedTaskResult;
@@ -51,6 +51,9 @@ pub struct RandomKeyManagerInner {
     keys: tokio::sync::RwLock<HashMap<PublicKeyData, KeyInfo>>,
 
     rotation_interval: u64,
+
+    /// Metrics of the key lifecycle
+    metrics: KeyManagerMetrics,
 }
 
 impl SelfGeneratedKeyManager {
@@ -61,10 +64,12 @@ impl SelfGeneratedKeyManager {
     pub fn new_with_auto_refresh(
         runtime: TokioRuntime,
         rotation_interval: u64,
+        metrics: KeyManagerMetrics,
     ) -> Result<Self, TngError> {
         let inner = Arc::new(RandomKeyManagerInner {
             keys: tokio::sync::RwLock::new(HashMap::new()),
             rotation_interval,
+            metrics,
         });
 
         let inner_clone = inner.clone();
@@ -131,14 +136,20 @@ impl RandomKeyManagerInner {
         let mut keys = self.keys.write().await;
 
         // Remove expired keys
+        let count_before = keys.len();
         keys.retain(|_, key_info| key_info.expire_at > now);
+        self.metrics
+            .on_key_expired((count_before - keys.len()) as u64);
 
         // Mark stale keys
+        let mut rotated = 0;
         for (_, key_info) in keys.iter_mut() {
             if key_info.stale_at <= now && matches!(key_info.status, KeyStatus::Active) {
                 key_info.status = KeyStatus::Stale;
+                rotated += 1;
             }
         }
+        self.metrics.on_key_rotated(rotated);
 
         // Add new active key if needed
         let have_active_key = keys
@@ -162,8 +173,11 @@ impl RandomKeyManagerInner {
                 KeyInfo::generate(new_key_id, KeyStatus::Active, now, self.rotation_interval)?;
             tracing::info!(?key_info, "New OHTTP key generated");
             keys.insert(key_info.key_config.public_key()?, key_info);
+            self.metrics.on_key_generated(1);
         }
 
+        self.metrics.record_key_set(keys.values());
+
         Ok(())
     }
 }
@@ -202,7 +216,10 @@ impl Drop for SelfGeneratedKeyManager {
 impl StatusProvider for SelfGeneratedKeyManager {
     async fn query_status(&self, path: &[&str]) -> Result<StatusQueryResult, TngError> {
         match path {
-            [] => Ok(StatusQueryResult::Subtree(vec!["keys".into()])),
+            [] => Ok(StatusQueryResult::Subtree(vec![
+                "keys".into(),
+                "key_audit".into(),
+            ])),
             ["keys"] => {
                 let keys = self.inner.keys.read().await;
                 let local_keys: Vec<KeyValue<'_>> = keys
@@ -227,6 +244,20 @@ impl StatusProvider for SelfGeneratedKeyManager {
                         TngError::StatusPathNotFound
                     })
             }
+            ["key_audit"] => {
+                let keys = self.inner.keys.read().await;
+                let local_keys = keys
+                    .iter()
+                    .map(|(public_key, info)| KeyDigest::new(public_key, info))
+                    .collect();
+                let report = KeyAuditReport::local("self_generated", local_keys);
+                serde_json::to_value(&report)
+                    .map(StatusQueryResult::Value)
+                    .map_err(|e| {
+                        tracing::error!(?e, "Failed to serialise key audit report");
+                        TngError::StatusPathNotFound
+                    })
+            }
             _ => Err(TngError::StatusPathNotFound),
         }
     }
@@ -11,7 +11,9 @@ use crate::status::{StatusProvider, StatusQueryResult};
 use crate::{
     config::egress::OHttpArgs,
     tunnel::{
-        egress::protocol::ohttp::security::{context::TngStreamContext, server::OhttpServer},
+        egress::protocol::ohttp::security::{
+            context::TngStreamContext, key_manager::metrics::KeyManagerMetrics, server::OhttpServer,
+        },
         ra_context::RaContext,
     },
     AttestationResult, CommonStreamTrait, TokioRuntime,
@@ -22,22 +24,28 @@ pub mod context;
 pub mod cors_fallback;
 #[allow(dead_code)]
 pub mod key_manager;
+pub mod non_tng_response;
 pub mod server;
 
 pub struct OHttpSecurityLayer {
     runtime: TokioRuntime,
     ohttp_server: OhttpServer,
+    pipe_buffer_size: usize,
 }
 
 impl OHttpSecurityLayer {
     pub async fn new(
         ra_context: Arc<RaContext>,
         ohttp_args: OHttpArgs,
+        key_manager_metrics: KeyManagerMetrics,
+        pipe_buffer_size: usize,
         runtime: TokioRuntime,
     ) -> Result<Self> {
         Ok(Self {
             runtime: runtime.clone(),
-            ohttp_server: OhttpServer::new(ra_context, ohttp_args, runtime).await?,
+            ohttp_server: OhttpServer::new(ra_context, ohttp_args, key_manager_metrics, runtime)
+                .await?,
+            pipe_buffer_size,
         })
     }
     pub async fn handle_stream(
@@ -52,6 +60,7 @@ impl OHttpSecurityLayer {
             let state = TngStreamContext {
                 runtime: self.runtime.clone(),
                 sender,
+                pipe_buffer_size: self.pipe_buffer_size,
             };
             let app = self
                 .ohttp_server
@@ -60,7 +69,14 @@ impl OHttpSecurityLayer {
 
             let hyper_service = hyper::service::service_fn(
                 move |request: axum::extract::Request<hyper::body::Incoming>| {
-                    app.clone().call(request)
+                    // Adopt the trace context propagated by the ingress
+                    let span = tracing::info_span!("request");
+                    #[cfg(feature = "trace")]
+                    crate::observability::trace::propagation::set_parent_from_headers(
+                        &span,
+                        request.headers(),
+                    );
+                    app.clone().call(request).instrument(span)
                 },
             );
 
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use axum::response::{IntoResponse as _, Response};
+use http::{header, HeaderValue, StatusCode};
+
+use crate::{config::egress::NonTngResponseArgs, error::TngError};
+
+/// The customized response returned to the clients that send plain HTTP requests instead of TNG
+/// traffic, as configured with `ohttp.non_tng_response`.
+pub struct NonTngResponse {
+    status: StatusCode,
+    content_type: HeaderValue,
+    body: Option<String>,
+    location: Option<HeaderValue>,
+}
+
+impl NonTngResponse {
+    pub fn new(args: &NonTngResponseArgs) -> Result<Self> {
+        args.validate()?;
+
+        Ok(Self {
+            status: StatusCode::from_u16(args.status())?,
+            content_type: match &args.content_type {
+                Some(content_type) => HeaderValue::from_str(content_type)?,
+                None => HeaderValue::from_static("text/plain; charset=utf-8"),
+            },
+            body: args.body.clone(),
+            location: args
+                .redirect_url
+                .as_deref()
+                .map(HeaderValue::from_str)
+                .transpose()?,
+        })
+    }
+
+    /// Renders the response to a request with the given path, which was rejected with `error`.
+    pub fn render(&self, error: TngError, path: &str) -> Response {
+        let mut response = match (&self.location, &self.body) {
+            (Some(location), _) => {
+                let mut response = self.status.into_response();
+                response
+                    .headers_mut()
+                    .insert(header::LOCATION, location.clone());
+                response
+            }
+            (None, Some(body)) => {
+                let code: &str = error.as_ref();
+                let body = body
+                    .replace("{code}", code)
+                    .replace("{message}", &format!("{:#}", anyhow::Error::new(error)))
+                    .replace("{path}", path);
+                let mut response = (self.status, body).into_response();
+                response
+                    .headers_mut()
+                    .insert(header::CONTENT_TYPE, self.content_type.clone());
+                response
+            }
+            (None, None) => error.into_response(),
+        };
+        *response.status_mut() = self.status;
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt as _;
+
+    use super::*;
+
+    async fn body_of(response: Response) -> String {
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_render_body_template() -> Result<()> {
+        let args: NonTngResponseArgs = serde_json::from_value(serde_json::json!({
+            "status": 401,
+            "content_type": "application/json",
+            "body": r#"{"error": "{code}", "path": "{path}"}"#
+        }))?;
+        let response =
+            NonTngResponse::new(&args)?.render(TngError::RejectNonTngRequest, "/index.html");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(
+            body_of(response).await,
+            r#"{"error": "RejectNonTngRequest", "path": "/index.html"}"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_render_redirect() -> Result<()> {
+        let args: NonTngResponseArgs = serde_json::from_value(serde_json::json!({
+            "redirect_url": "https://example.com/install-tng"
+        }))?;
+        let response = NonTngResponse::new(&args)?.render(TngError::RejectNonTngRequest, "/");
+
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(
+            response.headers().get(header::LOCATION).unwrap(),
+            "https://example.com/install-tng"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_render_default_body() -> Result<()> {
+        let args: NonTngResponseArgs =
+            serde_json::from_value(serde_json::json!({ "status": 404 }))?;
+        let response = NonTngResponse::new(&args)?.render(TngError::RejectNonTngRequest, "/");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(body_of(response)
+            .await
+            .contains("\"code\":\"RejectNonTngRequest\""));
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use axum::{
     extract::{FromRequest, Request, State},
     middleware::Next,
     response::{IntoResponse, Response},
+    routing::get,
     Json, Router,
 };
 use http::{HeaderMap, HeaderName, HeaderValue, Method};
@@ -16,6 +17,8 @@ use tower_http::{
 use web_time_compat::{Instant, InstantExt};
 
 use crate::status::{StatusProvider, StatusQueryResult};
+#[cfg(unix)]
+use crate::tunnel::egress::protocol::ohttp::security::api::attestation_info::AttestationInfoQuery;
 use crate::tunnel::ra_context::RaContext;
 use crate::{
     config::egress::{CorsConfig, OHttpArgs},
@@ -29,10 +32,13 @@ use crate::{
 use crate::{
     tunnel::egress::protocol::ohttp::security::{
         api::OhttpServerApi, context::TngStreamContext, cors_fallback,
+        key_manager::metrics::KeyManagerMetrics, non_tng_response::NonTngResponse,
     },
     HTTP_RESPONSE_SERVER_HEADER,
 };
 use async_trait::async_trait;
+#[cfg(unix)]
+use axum::extract::Query;
 
 /// TNG OHTTP Server implementation
 ///
@@ -43,6 +49,12 @@ pub struct OhttpServer {
     api: Arc<OhttpServerApi>,
     /// The configuration for the CORS
     cors_layer: Option<CorsLayer>,
+    /// The path of the standard key advertisement endpoint, if enabled
+    keys_endpoint: Option<String>,
+    /// The path of the attestation info endpoint, if enabled
+    attestation_endpoint: Option<String>,
+    /// The response returned to non-TNG clients, if customized
+    non_tng_response: Option<Arc<NonTngResponse>>,
 }
 
 impl OhttpServer {
@@ -50,8 +62,31 @@ impl OhttpServer {
     pub async fn new(
         ra_context: Arc<RaContext>,
         ohttp_args: OHttpArgs,
+        key_manager_metrics: KeyManagerMetrics,
         runtime: TokioRuntime,
     ) -> Result<Self> {
+        if let Some(keys_endpoint) = &ohttp_args.keys_endpoint {
+            if !keys_endpoint.starts_with('/') {
+                bail!("`keys_endpoint` must start with '/', got '{keys_endpoint}'");
+            }
+        }
+
+        if let Some(attestation_endpoint) = &ohttp_args.attestation_endpoint {
+            if !attestation_endpoint.starts_with('/') {
+                bail!("`attestation_endpoint` must start with '/', got '{attestation_endpoint}'");
+            }
+            if Some(attestation_endpoint) == ohttp_args.keys_endpoint.as_ref() {
+                bail!("`attestation_endpoint` and `keys_endpoint` must be different paths");
+            }
+            #[cfg(unix)]
+            let attested = ra_context.attest_context().is_some();
+            #[cfg(not(unix))]
+            let attested = false;
+            if !attested {
+                bail!("`attestation_endpoint` requires `attest` to be configured");
+            }
+        }
+
         let (passthrough_request_headers, passthrough_response_headers) = (
             Arc::new(
                 ohttp_args
@@ -74,9 +109,11 @@ impl OhttpServer {
                 OhttpServerApi::new(
                     ra_context,
                     ohttp_args.key,
+                    key_manager_metrics,
                     runtime,
                     passthrough_request_headers,
                     passthrough_response_headers,
+                    ohttp_args.padding,
                 )
                 .await?,
             ),
@@ -84,6 +121,14 @@ impl OhttpServer {
                 Some(cors_config) => Some(Self::construct_cors_layer(cors_config)?),
                 None => None,
             },
+            keys_endpoint: ohttp_args.keys_endpoint,
+            attestation_endpoint: ohttp_args.attestation_endpoint,
+            non_tng_response: ohttp_args
+                .non_tng_response
+                .as_ref()
+                .map(NonTngResponse::new)
+                .transpose()?
+                .map(Arc::new),
         })
     }
 
@@ -157,8 +202,53 @@ impl OhttpServer {
 
     /// Create the TNG HTTP routes with the server instance
     pub fn create_routes(&self, state: TngStreamContext) -> Router<TngStreamContext> {
-        let router = Router::new().fallback({
+        let router = Router::new();
+
+        // Serve the standard key advertisement endpoint, which does not require the
+        // `x-tng-ohttp-api` header.
+        let router = match &self.keys_endpoint {
+            Some(keys_endpoint) => router.route(
+                keys_endpoint,
+                get({
+                    let api = Arc::clone(&self.api);
+                    move || async move {
+                        api.get_ohttp_keys().await.map_err(|error| {
+                            tracing::error!(?error, "OHTTP server failed to serve key configs");
+                            error
+                        })
+                    }
+                }),
+            ),
+            None => router,
+        };
+
+        // Serve the attestation info endpoint for external monitors.
+        #[cfg(unix)]
+        let router = match &self.attestation_endpoint {
+            Some(attestation_endpoint) => router.route(
+                attestation_endpoint,
+                get({
+                    let api = Arc::clone(&self.api);
+                    move |State(context): State<TngStreamContext>,
+                          Query(query): Query<AttestationInfoQuery>| async move {
+                        api.get_attestation_info(query, context.runtime)
+                            .await
+                            .map_err(|error| {
+                                tracing::error!(
+                                    ?error,
+                                    "OHTTP server failed to serve attestation info"
+                                );
+                                error
+                            })
+                    }
+                }),
+            ),
+            None => router,
+        };
+
+        let router = router.fallback({
             let api = Arc::clone(&self.api);
+            let non_tng_response = self.non_tng_response.clone();
             move |state: State<TngStreamContext>, req: Request| async move {
                 let method = req.method().clone();
                 let uri = req.uri().clone();
@@ -173,7 +263,7 @@ impl OhttpServer {
                     ?content_len,
                     "OHTTP server received incoming request"
                 );
-                handler(state.0, api.clone(), req)
+                let result = handler(state.0, api.clone(), req)
                     .await
                     .map_err(|error: TngError| {
                         // Let's log the error before return to client
@@ -185,7 +275,13 @@ impl OhttpServer {
                             "OHTTP server failed to handle request"
                         );
                         error
-                    })
+                    });
+                match (result, &non_tng_response) {
+                    (Err(error @ TngError::RejectNonTngRequest), Some(non_tng_response)) => {
+                        Ok(non_tng_response.render(error, uri.path()))
+                    }
+                    (result, _) => result,
+                }
             }
         });
 
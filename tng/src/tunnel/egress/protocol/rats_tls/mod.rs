@@ -1,14 +1,19 @@
 use std::sync::Arc;
 
 use crate::{
+    config::websocket::WebSocketArgs,
     error::TngError,
     status::{StatusProvider, StatusQueryResult},
     tunnel::{
         egress::{
-            protocol::rats_tls::{security::RatsTlsSecurityLayer, wrapping::RatsTlsWrappingLayer},
+            protocol::rats_tls::{
+                security::RatsTlsSecurityLayer, websocket::WebSocketTransport,
+                wrapping::RatsTlsWrappingLayer,
+            },
             stream_manager::trusted::{ProtocolStreamDecoder, ProtocolStreamDecoderOutput},
         },
         ra_context::RaContext,
+        service_metrics::ServiceMetrics,
     },
     CommonStreamTrait, TokioRuntime,
 };
@@ -19,22 +24,45 @@ use async_trait::async_trait;
 use futures::StreamExt;
 
 pub mod security;
+pub mod websocket;
 pub mod wrapping;
 
 pub struct RatsTlsStreamDecoder {
     security_layer: RatsTlsSecurityLayer,
+    multiplex: bool,
+    /// Whether the flow-control windows of the HTTP/2 connections are sized from the measured
+    /// bandwidth-delay product.
+    adaptive_window: bool,
+    /// Set if the rats-TLS sessions may also be carried inside WebSocket connections.
+    websocket_transport: Option<WebSocketTransport>,
     runtime: TokioRuntime,
 }
 
 impl RatsTlsStreamDecoder {
     pub async fn new(
         ra_context: Arc<RaContext>,
+        metrics: ServiceMetrics,
         runtime: TokioRuntime,
         multiplex: bool,
+        alpn: &[String],
+        adaptive_window: bool,
+        websocket: Option<WebSocketArgs>,
+        pipe_buffer_size: usize,
     ) -> Result<Self> {
         Ok(Self {
-            security_layer: RatsTlsSecurityLayer::new(ra_context, runtime.clone(), multiplex)
-                .await?,
+            security_layer: RatsTlsSecurityLayer::new(
+                ra_context,
+                metrics,
+                runtime.clone(),
+                multiplex,
+                alpn,
+            )
+            .await?,
+            multiplex,
+            adaptive_window,
+            websocket_transport: websocket.map(|websocket| {
+                WebSocketTransport::new(websocket, pipe_buffer_size, runtime.clone())
+            }),
             runtime,
         })
     }
@@ -46,6 +74,10 @@ impl ProtocolStreamDecoder for RatsTlsStreamDecoder {
         &self,
         input: Box<dyn CommonStreamTrait + Sync + 'static>,
     ) -> Result<ProtocolStreamDecoderOutput> {
+        let input = match &self.websocket_transport {
+            Some(websocket_transport) => websocket_transport.unwrap_stream(input).await?,
+            None => input,
+        };
         let (tls_stream, attestation_result) = self.security_layer.handshake(input).await?;
 
         // Check negotiated ALPN protocol
@@ -53,16 +85,20 @@ impl ProtocolStreamDecoder for RatsTlsStreamDecoder {
         let negotiated_alpn = tls_session.alpn_protocol();
         tracing::debug!(?negotiated_alpn, "ALPN negotiated on egress TLS handshake");
 
-        if negotiated_alpn == Some(b"h2") {
+        // Only the protocols of the configured mode are accepted, so negotiating any of them
+        // implies that mode
+        if self.multiplex && negotiated_alpn.is_some() {
             // H2 mode (multiplex=true): spawn HTTP/2 server and yield streams from it
             let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
             let _runtime = self.runtime.clone();
+            let adaptive_window = self.adaptive_window;
             self.runtime
                 .spawn_supervised_task_fn_current_span(move |runtime| async move {
                     RatsTlsWrappingLayer::unwrap_stream(
                         tls_stream,
                         attestation_result,
                         sender,
+                        adaptive_window,
                         runtime,
                     )
                     .await;
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use crate::tunnel::{
     attestation_result::AttestationResult,
     ra_context::RaContext,
+    service_metrics::ServiceMetrics,
     stream::CommonStreamTrait,
     utils::{
         runtime::TokioRuntime,
@@ -11,23 +12,34 @@ use crate::tunnel::{
 };
 use anyhow::Result;
 use tracing::Instrument;
+use web_time_compat::{Instant, InstantExt as _};
 
 pub(super) struct RatsTlsSecurityLayer {
     tls_config_generator: TlsConfigGenerator,
-    multiplex: bool,
+    metrics: ServiceMetrics,
+    /// The ALPN protocols accepted in the handshakes of the rats-tls sessions.
+    alpn_protocols: Vec<Vec<u8>>,
 }
 
 impl RatsTlsSecurityLayer {
     pub async fn new(
         ra_context: Arc<RaContext>,
+        metrics: ServiceMetrics,
         runtime: TokioRuntime,
         multiplex: bool,
+        alpn: &[String],
     ) -> Result<Self> {
         let tls_config_generator = TlsConfigGenerator::new(ra_context, runtime).await?;
 
         Ok(Self {
             tls_config_generator,
-            multiplex,
+            metrics,
+            alpn_protocols: if multiplex {
+                Alpn::Http2
+            } else {
+                Alpn::RatsTls
+            }
+            .or_configured(alpn),
         })
     }
 
@@ -40,20 +52,20 @@ impl RatsTlsSecurityLayer {
     )> {
         async {
             // Prepare TLS config
-            let alpn = if self.multiplex {
-                Alpn::Http2
-            } else {
-                Alpn::RatsTls
-            };
             let tls_server_config = self
                 .tls_config_generator
-                .get_lazy_one_time_rustls_server_config(alpn)
-                .await?;
+                .get_lazy_one_time_rustls_server_config(Alpn::RatsTls)
+                .await?
+                .with_alpn_protocols(self.alpn_protocols.clone());
 
             tracing::debug!("Start to estabilish rats-tls connection");
 
-            let (security_layer_stream, attestation_result) =
-                tls_server_config.handshake_with_stream(stream).await?;
+            let handshake_started_at = Instant::get();
+            let (security_layer_stream, attestation_result) = tls_server_config
+                .handshake_with_stream(stream, &self.metrics)
+                .await?;
+            self.metrics
+                .record_handshake(handshake_started_at.elapsed());
 
             tracing::debug!("New rats-tls connection established");
             Ok((security_layer_stream, attestation_result))
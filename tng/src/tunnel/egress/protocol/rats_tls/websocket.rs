@@ -0,0 +1,145 @@
+//! Decoding of the rats-TLS sessions carried inside WebSocket connections, which are opened by
+//! `tng-wasm` since a browser cannot open raw TCP connections.
+
+use std::io::Cursor;
+
+use anyhow::{Context as _, Result};
+use async_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio::io::AsyncReadExt as _;
+use tokio_util::compat::FuturesAsyncReadCompatExt as _;
+use ws_stream_tungstenite::WsStream;
+
+use crate::{config::websocket::WebSocketArgs, CommonStreamTrait, TokioRuntime};
+
+/// The content type of the TLS records carrying handshake messages, which is the first byte of
+/// every rats-TLS session, while a WebSocket connection starts with an HTTP request.
+const TLS_CONTENT_TYPE_HANDSHAKE: u8 = 0x16;
+
+pub(super) struct WebSocketTransport {
+    path: String,
+    pipe_buffer_size: usize,
+    runtime: TokioRuntime,
+}
+
+impl WebSocketTransport {
+    pub fn new(args: WebSocketArgs, pipe_buffer_size: usize, runtime: TokioRuntime) -> Self {
+        Self {
+            path: args.path,
+            pipe_buffer_size,
+            runtime,
+        }
+    }
+
+    /// Returns the stream carrying the rats-TLS session, which is either `input` itself, or the
+    /// payload of the WebSocket connection if `input` is a WebSocket upgrade request.
+    pub async fn unwrap_stream(
+        &self,
+        input: Box<dyn CommonStreamTrait + Sync + 'static>,
+    ) -> Result<Box<dyn CommonStreamTrait + Sync + 'static>> {
+        let (mut reader, writer) = tokio::io::split(input);
+        let first_byte = reader
+            .read_u8()
+            .await
+            .context("Failed to read from stream")?;
+        let stream = tokio::io::join(Cursor::new([first_byte]).chain(reader), writer);
+        if first_byte == TLS_CONTENT_TYPE_HANDSHAKE {
+            return Ok(Box::new(stream));
+        }
+
+        let path = self.path.clone();
+        let websocket = async_tungstenite::tokio::accept_hdr_async(
+            stream,
+            move |request: &Request, response: Response| {
+                if request.uri().path() == path {
+                    Ok(response)
+                } else {
+                    let mut response = ErrorResponse::new(None);
+                    *response.status_mut() = http::StatusCode::NOT_FOUND;
+                    Err(response)
+                }
+            },
+        )
+        .await
+        .context("Failed to accept the WebSocket connection")?;
+        tracing::debug!("Decoding the rats-TLS session from a WebSocket connection");
+
+        // Hand over the payload through a pipe, so that the stream of the rats-TLS session is
+        // `Sync` like the other ones.
+        let mut websocket = WsStream::new(websocket).compat();
+        let (mut pipe, stream) = tokio::io::duplex(self.pipe_buffer_size);
+        self.runtime.spawn_supervised_task_current_span(async move {
+            if let Err(error) = tokio::io::copy_bidirectional(&mut websocket, &mut pipe).await {
+                tracing::debug!(?error, "The WebSocket connection is closed with error");
+            }
+        });
+
+        Ok(Box::new(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use super::*;
+
+    fn new_transport(shutdown: &tokio_graceful::Shutdown) -> Result<WebSocketTransport> {
+        Ok(WebSocketTransport::new(
+            WebSocketArgs::default(),
+            4096,
+            TokioRuntime::current(shutdown.guard())?,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_unwrap_websocket_stream() -> Result<()> {
+        let shutdown = tokio_graceful::Shutdown::no_signal();
+        let transport = new_transport(&shutdown)?;
+        let (client, server) = tokio::io::duplex(4096);
+
+        let client = async {
+            let (websocket, _) =
+                async_tungstenite::tokio::client_async("ws://localhost/tng/websocket", client)
+                    .await?;
+            let mut stream = WsStream::new(websocket).compat();
+            stream
+                .write_all(&[TLS_CONTENT_TYPE_HANDSHAKE, 1, 2, 3])
+                .await?;
+            stream.flush().await?;
+            Ok::<_, anyhow::Error>(stream)
+        };
+        let server = async {
+            let mut stream = transport.unwrap_stream(Box::new(server)).await?;
+            let mut buf = [0u8; 4];
+            stream.read_exact(&mut buf).await?;
+            Ok::<_, anyhow::Error>(buf)
+        };
+
+        let (_client, buf) = tokio::try_join!(client, server)?;
+        assert_eq!(buf, [TLS_CONTENT_TYPE_HANDSHAKE, 1, 2, 3]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unwrap_plain_stream() -> Result<()> {
+        let shutdown = tokio_graceful::Shutdown::no_signal();
+        let transport = new_transport(&shutdown)?;
+        let (mut client, server) = tokio::io::duplex(4096);
+
+        client.write_all(&[TLS_CONTENT_TYPE_HANDSHAKE, 1]).await?;
+        let mut stream = transport.unwrap_stream(Box::new(server)).await?;
+        let mut buf = [0u8; 2];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(buf, [TLS_CONTENT_TYPE_HANDSHAKE, 1]);
+
+        // A WebSocket upgrade to another path is refused
+        let (client, server) = tokio::io::duplex(4096);
+        let (client, server) = tokio::join!(
+            async_tungstenite::tokio::client_async("ws://localhost/other", client),
+            transport.unwrap_stream(Box::new(server))
+        );
+        assert!(client.is_err());
+        assert!(server.is_err());
+        Ok(())
+    }
+}
@@ -31,6 +31,7 @@ impl RatsTlsWrappingLayer {
             Box<dyn CommonStreamTrait + Sync>,
             Option<AttestationResult>,
         )>,
+        adaptive_window: bool,
         runtime: TokioRuntime,
     ) {
         let runtime_cloned = runtime.clone();
@@ -65,6 +66,7 @@ impl RatsTlsWrappingLayer {
 
         if let Err(error) = hyper::server::conn::http2::Builder::new(runtime_cloned)
             .keep_alive_interval(None)
+            .adaptive_window(adaptive_window)
             .serve_connection(TokioIo::new(tls_stream), svc)
             .instrument(span)
             .await
@@ -119,6 +121,10 @@ impl RatsTlsWrappingLayer {
                 }
             });
             Ok(Response::new(Body::empty()).into_response())
+        } else if req.method() == Method::OPTIONS {
+            // Sent by the ingresses with `prewarm` to keep the session from being idle
+            tracing::debug!(stream_id, "Received keep-alive probe");
+            Ok(Response::new(Body::empty()).into_response())
         } else {
             Ok(error_response(
                 StatusCode::BAD_REQUEST,
@@ -127,3 +133,49 @@ impl RatsTlsWrappingLayer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::bail;
+    use tokio::io::AsyncWriteExt as _;
+
+    use super::*;
+    use crate::tests::read_h2_initial_window_size;
+
+    /// Serves a HTTP/2 connection as on the rats-tls sessions, and returns the initial window size
+    /// advertised to the ingress.
+    async fn advertised_window_size(adaptive_window: bool) -> Result<Option<u32>> {
+        let shutdown = tokio_graceful::Shutdown::no_signal();
+        let (mut client, server) = tokio::io::duplex(4096);
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let serve = RatsTlsWrappingLayer::unwrap_stream(
+            server,
+            None,
+            sender,
+            adaptive_window,
+            TokioRuntime::current(shutdown.guard())?,
+        );
+
+        let read = async {
+            // The connection preface of the client, with an empty SETTINGS frame
+            client
+                .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+                .await?;
+            client.write_all(&[0, 0, 0, 0x4, 0, 0, 0, 0, 0]).await?;
+            read_h2_initial_window_size(&mut client).await
+        };
+        tokio::select! {
+            result = read => result,
+            _ = serve => bail!("The HTTP/2 server exited before the SETTINGS frame is read"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_window() -> Result<()> {
+        // The windows start from the size defined by the spec and grow with the measured
+        // bandwidth-delay product, instead of the fixed default ones
+        assert_eq!(advertised_window_size(true).await?, Some(65535));
+        assert_ne!(advertised_window_size(false).await?, Some(65535));
+        Ok(())
+    }
+}
@@ -5,15 +5,30 @@ use crate::{tunnel::stream::CommonStreamTrait, AttestationResult};
 
 pub mod trusted;
 
+/// Decapsulates the streams carried by a connection accepted by an egress, which is what the
+/// egresses share regardless of how they accept the connections and where they forward the
+/// streams to. [`trusted::TrustedStreamManager`] decapsulates the streams of the trusted tunnel.
+///
+/// Library users can compose it into egresses of their own, with a
+/// [`ServiceMetrics`](crate::tunnel::service_metrics::ServiceMetrics) and a
+/// [`TokioRuntime`](crate::TokioRuntime) of their own. Note that, unlike the configuration, this
+/// API follows the internals of TNG and may change in a minor release.
+#[allow(async_fn_in_trait)]
 pub trait StreamManager {
+    /// Returns the streams decapsulated from `stream`, e.g. several of them with
+    /// `rats_tls.multiplex`, until the connection is closed.
     async fn consume_stream(
         &self,
         stream: Box<dyn CommonStreamTrait + std::marker::Sync + 'static>,
     ) -> Result<BoxStream<'static, Result<NextStream>>>;
 }
 
+/// A stream decapsulated by a [`StreamManager`].
 pub enum NextStream {
+    /// A stream carried through the tunnel, with the attestation result of the peer if it was
+    /// attested.
     Secured(Box<dyn CommonStreamTrait>, Option<AttestationResult>),
+    /// A connection matching the `direct_forward` rules, handed over as it was received.
     DirectlyForward(Box<dyn CommonStreamTrait>),
 }
 
@@ -3,20 +3,21 @@ use std::sync::Arc;
 use crate::error::TngError;
 use crate::status::{StatusProvider, StatusQueryResult};
 use crate::{
-    config::egress::CommonArgs,
+    config::{egress::CommonArgs, ra::VerifyUpdate, DEFAULT_PIPE_BUF_SIZE},
     tunnel::{
         attestation_result::AttestationResult,
         egress::{
             protocol::{
                 common::transport::{MaybeDirectlyForward, TransportLayer},
-                ohttp::OHttpStreamDecoder,
+                ohttp::{security::key_manager::metrics::KeyManagerMetrics, OHttpStreamDecoder},
                 rats_tls::RatsTlsStreamDecoder,
             },
             stream_manager::NextStream,
         },
         ra_context::RaContext,
+        service_metrics::ServiceMetrics,
         stream::CommonStreamTrait,
-        utils::runtime::TokioRuntime,
+        utils::{runtime::TokioRuntime, rustls::config::alpn::Alpn},
     },
 };
 use anyhow::bail;
@@ -40,16 +41,25 @@ pub trait ProtocolStreamDecoder: StatusProvider {
     ) -> Result<ProtocolStreamDecoderOutput>;
 }
 
+/// Decapsulates the streams of the trusted tunnel, with the `rats_tls` or `ohttp` protocol set in
+/// the `common_args` of the egress. The connections matching `direct_forward` are handed over as
+/// they are.
 pub struct TrustedStreamManager {
     transport_layer: TransportLayer,
 
     decoder: Box<dyn ProtocolStreamDecoder + Send + Sync + 'static>,
 
+    ra_context: Arc<RaContext>,
+
     runtime: TokioRuntime,
 }
 
 impl TrustedStreamManager {
-    pub async fn new(common_args: &CommonArgs, parent_runtime: TokioRuntime) -> Result<Self> {
+    pub async fn new(
+        common_args: &CommonArgs,
+        metrics: &ServiceMetrics,
+        parent_runtime: TokioRuntime,
+    ) -> Result<Self> {
         if common_args.ohttp.is_some() && common_args.rats_tls.is_some() {
             bail!("Cannot specify both `ohttp` and `rats_tls` — they are mutually exclusive");
         }
@@ -69,7 +79,7 @@ impl TrustedStreamManager {
         let runtime = if is_h2_or_ohttp {
             #[cfg(not(wasm))]
             {
-                TokioRuntime::new_multi_thread(parent_runtime.shutdown_guard().clone())?
+                parent_runtime.new_protocol_runtime()?
             }
             #[cfg(wasm)]
             {
@@ -93,23 +103,58 @@ impl TrustedStreamManager {
             )?,
             decoder: match &common_args.ohttp {
                 Some(ohttp_args) => Box::new(
-                    OHttpStreamDecoder::new(ra_context, ohttp_args.clone(), runtime.clone())
-                        .await?,
+                    OHttpStreamDecoder::new(
+                        ra_context.clone(),
+                        ohttp_args.clone(),
+                        KeyManagerMetrics::new(metrics),
+                        common_args
+                            .buffer_size
+                            .as_ref()
+                            .map_or(DEFAULT_PIPE_BUF_SIZE, |buffer_size| buffer_size.pipe),
+                        runtime.clone(),
+                    )
+                    .await?,
                 ),
                 None => {
-                    let multiplex = common_args
-                        .rats_tls
-                        .as_ref()
-                        .unwrap_or(&Default::default())
-                        .multiplex;
+                    let rats_tls = common_args.rats_tls.clone().unwrap_or_default();
+                    Alpn::validate_configured(&rats_tls.alpn)?;
+                    rats_tls.validate()?;
+                    if let Some(websocket) = &rats_tls.websocket {
+                        websocket.validate()?;
+                        if rats_tls.multiplex {
+                            bail!(
+                                "`rats_tls.websocket` requires `rats_tls.multiplex` to be disabled"
+                            );
+                        }
+                    }
                     Box::new(
-                        RatsTlsStreamDecoder::new(ra_context, runtime.clone(), multiplex).await?,
+                        RatsTlsStreamDecoder::new(
+                            ra_context.clone(),
+                            metrics.clone(),
+                            runtime.clone(),
+                            rats_tls.multiplex,
+                            &rats_tls.alpn,
+                            rats_tls.adaptive_window,
+                            rats_tls.websocket,
+                            common_args
+                                .buffer_size
+                                .as_ref()
+                                .map_or(DEFAULT_PIPE_BUF_SIZE, |buffer_size| buffer_size.pipe),
+                        )
+                        .await?,
                     )
                 }
             },
+            ra_context,
             runtime,
         })
     }
+
+    /// Updates the parameters which the downstreams are verified with, from the next handshake
+    /// on.
+    pub async fn update_verify(&self, update: &VerifyUpdate) -> Result<()> {
+        self.ra_context.update_verify(update).await
+    }
 }
 
 impl StreamManager for TrustedStreamManager {
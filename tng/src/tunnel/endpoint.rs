@@ -2,9 +2,11 @@ use std::fmt::{Debug, Display};
 use std::net::Ipv4Addr;
 
 #[cfg(not(wasm))]
-use crate::tunnel::utils::socket::tcp_connect;
+use crate::tunnel::resolver::Resolver;
 #[cfg(not(wasm))]
-use anyhow::Result;
+use crate::tunnel::utils::socket::{tcp_connect, tcp_connect_addrs};
+#[cfg(not(wasm))]
+use anyhow::{Context as _, Result};
 
 /// The address component of a TNG endpoint — either an IPv4 address or a domain name.
 #[derive(Clone, Eq, Hash, PartialEq, Debug)]
@@ -117,15 +119,19 @@ impl TngEndpoint {
     /// `(&str, u16)`, both of which implement `tokio::net::ToSocketAddrs`
     /// directly. This avoids the `format!`/`to_string()` round-trip that
     /// allocates a `"host:port"` string only for the resolver to re-parse.
+    ///
+    /// Domains are resolved with `resolver` if it is set, instead of the
+    /// resolver of the system.
     #[cfg(not(wasm))]
     pub async fn tcp_connect(
         &self,
         #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
         #[rustfmt::skip]
         so_mark: Option<u32>,
+        resolver: Option<&dyn Resolver>,
     ) -> Result<tokio::net::TcpStream> {
-        match &self.addr {
-            EndpointAddr::Ipv4(ip) => {
+        match (&self.addr, resolver) {
+            (EndpointAddr::Ipv4(ip), _) => {
                 tcp_connect(
                     (*ip, self.port),
                     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
@@ -133,7 +139,7 @@ impl TngEndpoint {
                 )
                 .await
             }
-            EndpointAddr::Domain(d) => {
+            (EndpointAddr::Domain(d), None) => {
                 tcp_connect(
                     (d.as_str(), self.port),
                     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
@@ -141,6 +147,18 @@ impl TngEndpoint {
                 )
                 .await
             }
+            (EndpointAddr::Domain(d), Some(resolver)) => {
+                let addrs = resolver
+                    .resolve(d, self.port)
+                    .await
+                    .with_context(|| format!("Failed to resolve '{d}'"))?;
+                tcp_connect_addrs(
+                    addrs,
+                    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                    so_mark,
+                )
+                .await
+            }
         }
     }
 }
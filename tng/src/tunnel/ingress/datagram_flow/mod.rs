@@ -93,6 +93,10 @@ impl DatagramIngressFlow {
         service_metrics_creator: &ServiceMetricsCreator,
         runtime: TokioRuntime,
     ) -> Result<Self> {
+        if !common_args.allowed_sources.is_empty() {
+            anyhow::bail!("`allowed_sources` is not supported by the `mapping_udp` ingress");
+        }
+
         let metric_attributes = ingress.metric_attributes();
         let metrics = service_metrics_creator.new_service_metrics(metric_attributes);
 
@@ -162,7 +166,7 @@ impl RegistedService for DatagramIngressFlow {
                                 true, // to_trusted_tunnel
                             );
                             let access_established =
-                                access_routed.into_established(None, false);
+                                access_routed.into_established(None, false, false, None);
 
                             let last_activity = Arc::new(Mutex::new(Instant::get()));
 
@@ -1,36 +1,54 @@
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
 use futures::Stream;
 use futures::StreamExt;
 use indexmap::IndexMap;
 use tokio::sync::mpsc::Sender;
+use tracing::Instrument;
 
 use crate::config::ingress::CommonArgs;
+use crate::config::ra::VerifyUpdate;
 use crate::error::TngError;
 use crate::status::{StatusProvider, StatusQueryResult};
 use crate::tunnel::access_log::{AccessAccepted, IngressAccessMode};
+use crate::tunnel::attestation_result::AttestationResult;
+use crate::tunnel::connections::{ConnectionSelector, ConnectionTracker};
 use crate::tunnel::endpoint::TngEndpoint;
 use crate::tunnel::service_metrics::ServiceMetrics;
 use crate::tunnel::service_metrics::ServiceMetricsCreator;
+use crate::tunnel::utils::circuit_breaker::CircuitBreaker;
 use crate::tunnel::utils::runtime::TokioRuntime;
 use crate::{service::RegistedService, tunnel::stream::CommonStreamTrait};
 
 use super::stream_manager::{
     trusted::TrustedStreamManager, unprotected::UnprotectedStreamManager, StreamManager,
 };
+use super::tunnel_dns::TunnelDnsResolver;
+use overload::{Admission, OverloadController};
+use source_filter::SourceFilter;
 
+pub mod overload;
+pub mod source_filter;
 pub mod stream_router;
 
+/// The interval to probe the prewarmed sessions at, which is shorter than the idle timeout of the
+/// pooled sessions.
+const PREWARM_INTERVAL: Duration = Duration::from_secs(30);
+
 pub struct IngressFlow {
     ingress: Box<dyn IngressTrait>,
     trusted_stream_manager: Arc<TrustedStreamManager>,
     unprotected_stream_manager: Arc<UnprotectedStreamManager>,
     metrics: ServiceMetrics,
+    connections: ConnectionTracker,
     runtime: TokioRuntime,
+    prewarm: bool,
+    overload: OverloadController,
 }
 
 #[async_trait]
@@ -49,6 +67,22 @@ pub(super) trait IngressTrait: Sync + Send {
     /// Accept incomming streams. The returned stream should be a stream of incomming accepted streams.
     /// Note that this method should be called only once.
     async fn accept(&self, runtime: TokioRuntime) -> Result<Incomming>;
+
+    /// Return the upstream endpoints which are known before any stream is accepted, so that the
+    /// sessions to them can be established in advance.
+    fn known_endpoints(&self) -> Vec<TngEndpoint> {
+        vec![]
+    }
+
+    /// Give the ingress the overload controller of the flow, for the ingresses which reject new
+    /// connections with a response before they are accepted, e.g. `http_proxy`.
+    fn set_overload_controller(&mut self, _overload: OverloadController) {}
+
+    /// Give the ingress the filter of the sources allowed to connect, which is checked on accept,
+    /// before anything is read from the connections. Only called if `allowed_sources` is set.
+    fn set_source_filter(&mut self, _source_filter: SourceFilter) -> Result<()> {
+        bail!("`allowed_sources` is only supported by the `mapping`, `socks5` and `http_proxy` ingresses")
+    }
 }
 
 pub(super) type Incomming<'a> = Pin<Box<dyn Stream<Item = Result<AcceptedStream>> + Send + 'a>>;
@@ -72,34 +106,71 @@ impl IngressFlow {
         service_metrics_creator: &ServiceMetricsCreator,
         runtime: TokioRuntime,
     ) -> Result<Self> {
-        let ingress = Box::new(ingress);
+        let mut ingress = Box::new(ingress);
+
+        let buffer_size = common_args.buffer_size.clone().unwrap_or_default();
+        buffer_size.validate()?;
+        if let Some(overload_args) = &common_args.overload {
+            overload_args.validate()?;
+        }
 
         let metric_attributes = ingress.metric_attributes();
         let metrics = service_metrics_creator.new_service_metrics(metric_attributes);
 
+        let overload = OverloadController::new(common_args.overload.clone(), metrics.clone());
+        ingress.set_overload_controller(overload.clone());
+
+        let source_filter = SourceFilter::new(common_args.allowed_sources.clone());
+        if !source_filter.is_empty() {
+            ingress.set_source_filter(source_filter)?;
+        }
+
         #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
         let transport_so_mark = ingress.transport_so_mark();
 
+        // Resolve the upstreams through the trusted tunnel, with the resolver configured otherwise
+        // as the fallback.
+        let mut common_args = common_args.clone();
+        if let Some(tunnel_dns_args) = &common_args.tunnel_dns {
+            common_args.resolver = Some(Arc::new(
+                TunnelDnsResolver::new(tunnel_dns_args, &common_args, runtime.clone()).await?,
+            ));
+        }
+        let common_args = &common_args;
+
+        let circuit_breaker = CircuitBreaker::new(common_args.circuit_breaker.as_ref())?;
+
         let trusted_stream_manager = Arc::new(
             TrustedStreamManager::new(
                 common_args,
                 #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
                 transport_so_mark,
+                &metrics,
                 runtime.clone(),
+                circuit_breaker.clone(),
             )
             .await?,
         );
         let unprotected_stream_manager = Arc::new(UnprotectedStreamManager::new(
             #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
             transport_so_mark,
+            common_args.resolver.clone(),
+            buffer_size.forward,
+            circuit_breaker,
         ));
 
         Ok(Self {
             ingress,
             metrics,
+            connections: ConnectionTracker::new(),
             trusted_stream_manager,
             unprotected_stream_manager,
             runtime,
+            prewarm: common_args
+                .rats_tls
+                .as_ref()
+                .is_some_and(|rats_tls| rats_tls.prewarm),
+            overload,
         })
     }
 }
@@ -112,27 +183,86 @@ impl RegistedService for IngressFlow {
 
         ready.send(()).await?;
 
-        while let Some(next) = incomming.next().await {
-            let accepted_stream = match next {
-                Ok(next) => next,
-                Err(error) => {
-                    tracing::error!(?error, "Failed to accept incomming stream");
+        let serve_incomming = async {
+            while let Some(next) = incomming.next().await {
+                let accepted_stream = match next {
+                    Ok(next) => next,
+                    Err(error) => {
+                        tracing::error!(?error, "Failed to accept incomming stream");
+                        continue;
+                    }
+                };
+
+                // The stream is closed when it is dropped here
+                let Ok(admission) = self.overload.admit(accepted_stream.src.ip()) else {
                     continue;
-                }
-            };
+                };
 
-            self.serve_in_async_task_no_throw_error(accepted_stream, self.runtime.clone())
+                self.serve_in_async_task_no_throw_error(
+                    accepted_stream,
+                    admission,
+                    self.runtime.clone(),
+                )
                 .await;
+            }
+        };
+
+        // The sessions are kept warm for as long as the ingress is served
+        tokio::select! {
+            () = serve_incomming => {},
+            () = self.keep_sessions_warm() => {},
         }
 
         Ok(())
     }
+
+    fn active_connections(&self) -> usize {
+        self.connections.len()
+    }
+
+    fn close_connections(&self) {
+        self.connections.close_all();
+    }
+
+    fn terminate_connections(&self, selector: &ConnectionSelector) -> usize {
+        self.connections.close(selector)
+    }
+
+    async fn update_verify(&self, update: &VerifyUpdate) -> Result<()> {
+        self.trusted_stream_manager.update_verify(update).await
+    }
 }
 
 impl IngressFlow {
+    /// Establishes the sessions to the known upstreams of the ingress, and probes them
+    /// periodically to keep them open. Never returns if `rats_tls.prewarm` is enabled.
+    async fn keep_sessions_warm(&self) {
+        let endpoints = if self.prewarm {
+            self.ingress.known_endpoints()
+        } else {
+            vec![]
+        };
+        if endpoints.is_empty() {
+            return std::future::pending().await;
+        }
+
+        let mut ticker = tokio::time::interval(PREWARM_INTERVAL);
+        loop {
+            ticker.tick().await;
+            futures::future::join_all(endpoints.iter().map(|endpoint| async move {
+                if let Err(error) = self.trusted_stream_manager.prewarm(endpoint).await {
+                    tracing::warn!(?error, %endpoint, "Failed to prewarm the session to upstream");
+                }
+            }))
+            .instrument(tracing::info_span!("prewarm"))
+            .await;
+        }
+    }
+
     async fn serve_in_async_task_no_throw_error(
         &self,
         accepted_stream: AcceptedStream,
+        mut admission: Admission,
         runtime: TokioRuntime,
     ) {
         let AcceptedStream {
@@ -148,6 +278,7 @@ impl IngressFlow {
         let trusted_stream_manager = self.trusted_stream_manager.clone();
         let unprotected_stream_manager = self.unprotected_stream_manager.clone();
         let metrics = self.metrics.clone();
+        let connections = self.connections.clone();
 
         // TODO: stop all task when downstream is already closed
 
@@ -158,18 +289,22 @@ impl IngressFlow {
                     tracing::debug!(%src, %dst, encrypted, "Acquire connection to upstream");
 
                     // TODO: merge .new_cx() and .new_wrapped_stream()
+                    let metrics = metrics.for_destination(&dst);
                     let active_cx = metrics.new_cx();
                     let stream = metrics.new_wrapped_stream(stream);
+                    let connection = connections.track(src, &dst, encrypted);
+                    let stream = connection.wrap_stream(stream);
 
                     // Transition to AccessRouted: dst and encrypted are known here
                     let access_routed = access_accepted.into_routed(&dst, encrypted);
 
                     let attestation_result;
                     let upstream_local;
+                    let session_id;
                     let forward_stream_task = if !encrypted {
                         // Forward via unprotected tcp
-                        let (forward_stream_task, att, up_local) = unprotected_stream_manager
-                            .forward_stream(&dst, Box::new(stream))
+                        let (forward_stream_task, att, up_local, id) = unprotected_stream_manager
+                            .forward_spliceable_stream(&dst, stream)
                             .await
                             .with_context(|| {
                                 format!("Failed to connect to upstream {dst} via unprotected tcp")
@@ -177,10 +312,11 @@ impl IngressFlow {
 
                         attestation_result = att;
                         upstream_local = up_local;
+                        session_id = id;
                         forward_stream_task
                     } else {
                         // Forward via trusted tunnel
-                        let (forward_stream_task, att, up_local) = trusted_stream_manager
+                        let (forward_stream_task, att, up_local, id) = trusted_stream_manager
                             .forward_stream(&dst, Box::new(stream))
                             .await
                             .with_context(|| {
@@ -189,14 +325,38 @@ impl IngressFlow {
 
                         attestation_result = att;
                         upstream_local = up_local;
+                        session_id = id;
                         forward_stream_task
                     };
-
-                    // Print access log — Transition to AccessEstablished: upstream connected, then drop immediately to log
-                    access_routed.into_established(upstream_local, attestation_result.is_some());
+                    admission.established();
+
+                    let attested = attestation_result
+                        .as_ref()
+                        .is_some_and(|result| !result.is_degraded());
+                    let degraded = attestation_result
+                        .as_ref()
+                        .is_some_and(AttestationResult::is_degraded);
+                    connection.set_attested(attested);
+
+                    // Print access log — Transition to AccessEstablished: upstream connected. The
+                    // completion is logged when it is dropped after forwarding, with the bytes
+                    // transferred
+                    let mut access_established = access_routed.into_established(
+                        upstream_local,
+                        attested,
+                        degraded,
+                        session_id,
+                    );
 
                     // let forward_stream_task = pin!(forward_stream_task);
-                    match forward_stream_task.await {
+                    let result = tokio::select! {
+                        result = forward_stream_task => result,
+                        _ = connection.closed() => Err(anyhow!("The connection is closed through the control interface")),
+                    };
+                    let (tx_bytes, rx_bytes) = connection.transferred();
+                    access_established.set_transferred(tx_bytes, rx_bytes);
+                    drop(access_established);
+                    match result {
                         Err(error) => {
                             tracing::error!(
                                 %dst,
@@ -223,6 +383,19 @@ impl IngressFlow {
 #[async_trait]
 impl StatusProvider for IngressFlow {
     async fn query_status(&self, path: &[&str]) -> Result<StatusQueryResult, TngError> {
-        self.trusted_stream_manager.query_status(path).await
+        match path {
+            [] => {
+                let mut children = match self.trusted_stream_manager.query_status(path).await? {
+                    StatusQueryResult::Subtree(children) => children,
+                    StatusQueryResult::Value(_) => vec![],
+                };
+                children.push("connections".into());
+                Ok(StatusQueryResult::Subtree(children))
+            }
+            ["connections"] => Ok(StatusQueryResult::Value(
+                serde_json::to_value(self.connections.snapshot()).unwrap_or_default(),
+            )),
+            _ => self.trusted_stream_manager.query_status(path).await,
+        }
     }
 }
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use web_time_compat::{Instant, InstantExt as _};
+
+use crate::config::overload::OverloadArgs;
+use crate::tunnel::service_metrics::ServiceMetrics;
+use crate::tunnel::utils::source_addr::canonical_ip;
+
+/// The weight of a new sample in the moving average of the establish latency is `1 / 2^SHIFT`,
+/// like in the smoothed RTT of TCP.
+const LATENCY_SMOOTHING_SHIFT: u32 = 3;
+
+/// Why a new connection is rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverloadReason {
+    PendingConnections,
+    ActiveConnections,
+    EstablishLatency,
+    SourceConnections,
+}
+
+impl fmt::Display for OverloadReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OverloadReason::PendingConnections => "too many connections are being established",
+            OverloadReason::ActiveConnections => "too many connections are active",
+            OverloadReason::EstablishLatency => "connections take too long to be established",
+            OverloadReason::SourceConnections => {
+                "too many connections are active from the same source"
+            }
+        })
+    }
+}
+
+/// Decides whether an ingress admits new connections, from the connections it is establishing
+/// and forwarding. The state is shared by all the clones.
+#[derive(Debug, Clone)]
+pub struct OverloadController {
+    inner: Arc<ControllerInner>,
+}
+
+#[derive(Debug)]
+struct ControllerInner {
+    /// All the connections are admitted if not set.
+    args: Option<OverloadArgs>,
+    /// The connections admitted and not yet connected to the upstream.
+    pending: AtomicUsize,
+    /// All the connections admitted, including the pending ones.
+    active: AtomicUsize,
+    /// The moving average of the time to connect to the upstream, in microseconds.
+    establish_latency_us: AtomicU64,
+    /// The connections admitted from each source, only tracked if `max_connections_per_source` is
+    /// set. The sources without connections are removed.
+    per_source: Mutex<HashMap<IpAddr, usize>>,
+    metrics: ServiceMetrics,
+}
+
+impl ControllerInner {
+    fn per_source(&self) -> MutexGuard<'_, HashMap<IpAddr, usize>> {
+        self.per_source.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+impl OverloadController {
+    pub fn new(args: Option<OverloadArgs>, metrics: ServiceMetrics) -> Self {
+        Self {
+            inner: Arc::new(ControllerInner {
+                args,
+                pending: AtomicUsize::new(0),
+                active: AtomicUsize::new(0),
+                establish_latency_us: AtomicU64::new(0),
+                per_source: Mutex::new(HashMap::new()),
+                metrics,
+            }),
+        }
+    }
+
+    fn overload_reason(&self) -> Option<OverloadReason> {
+        let inner = &self.inner;
+        let args = inner.args.as_ref()?;
+
+        let pending = inner.pending.load(Ordering::Relaxed);
+        if args
+            .max_pending_connections
+            .is_some_and(|max| pending >= max)
+        {
+            return Some(OverloadReason::PendingConnections);
+        }
+        if args
+            .max_active_connections
+            .is_some_and(|max| inner.active.load(Ordering::Relaxed) >= max)
+        {
+            return Some(OverloadReason::ActiveConnections);
+        }
+        // The average is only updated by the pending connections, so it is not checked once they
+        // are all finished, or no connection would be admitted again
+        if let Some(max) = args.max_establish_latency_ms {
+            let latency_us = inner.establish_latency_us.load(Ordering::Relaxed);
+            if pending > 0 && latency_us > max.saturating_mul(1000) {
+                return Some(OverloadReason::EstablishLatency);
+            }
+        }
+        None
+    }
+
+    /// The maximum number of connections from the same source, if they are tracked.
+    fn max_connections_per_source(&self) -> Option<usize> {
+        self.inner.args.as_ref()?.max_connections_per_source
+    }
+
+    fn source_overloaded(&self, src: IpAddr) -> bool {
+        self.max_connections_per_source()
+            .is_some_and(|max| self.inner.per_source().get(&src).is_some_and(|n| *n >= max))
+    }
+
+    /// Checks whether a new connection from `src` can be admitted, without counting it. A
+    /// rejected connection is recorded in the `cx_rejected` metric, or in the
+    /// `cx_rejected_per_source` metric if its source has too many connections.
+    pub fn check_admission(&self, src: IpAddr) -> Result<(), OverloadReason> {
+        let src = canonical_ip(src);
+        match self.overload_reason() {
+            Some(reason) => {
+                self.inner.metrics.record_rejected();
+                tracing::debug!(%reason, "Rejecting new connection since the ingress is overloaded");
+                Err(reason)
+            }
+            None if self.source_overloaded(src) => {
+                let reason = OverloadReason::SourceConnections;
+                self.inner.metrics.record_rejected_per_source();
+                tracing::debug!(%reason, %src, "Rejecting new connection since its source has too many connections");
+                Err(reason)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Admits a new connection from `src`, which is counted until the returned [`Admission`] is
+    /// dropped.
+    pub fn admit(&self, src: IpAddr) -> Result<Admission, OverloadReason> {
+        let src = canonical_ip(src);
+        self.check_admission(src)?;
+        self.inner.pending.fetch_add(1, Ordering::Relaxed);
+        self.inner.active.fetch_add(1, Ordering::Relaxed);
+        let src = self.max_connections_per_source().map(|_| {
+            let mut per_source = self.inner.per_source();
+            *per_source.entry(src).or_default() += 1;
+            src
+        });
+        Ok(Admission {
+            inner: self.inner.clone(),
+            accepted_at: Instant::get(),
+            established: false,
+            src,
+        })
+    }
+}
+
+/// A connection admitted by the [`OverloadController`].
+pub struct Admission {
+    inner: Arc<ControllerInner>,
+    accepted_at: Instant,
+    established: bool,
+    /// The source the connection is counted for, if the connections are tracked per source.
+    src: Option<IpAddr>,
+}
+
+impl Admission {
+    /// Marks the connection as connected to the upstream, and records the time it took.
+    pub fn established(&mut self) {
+        if std::mem::replace(&mut self.established, true) {
+            return;
+        }
+        self.inner.pending.fetch_sub(1, Ordering::Relaxed);
+
+        let sample = u64::try_from(self.accepted_at.elapsed().as_micros()).unwrap_or(u64::MAX);
+        let _ = self.inner.establish_latency_us.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |average| {
+                Some(if average == 0 {
+                    sample
+                } else {
+                    average - (average >> LATENCY_SMOOTHING_SHIFT)
+                        + (sample >> LATENCY_SMOOTHING_SHIFT)
+                })
+            },
+        );
+    }
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        if !self.established {
+            self.inner.pending.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.inner.active.fetch_sub(1, Ordering::Relaxed);
+
+        if let Some(src) = self.src {
+            let mut per_source = self.inner.per_source();
+            if let Some(count) = per_source.get_mut(&src) {
+                *count -= 1;
+                if *count == 0 {
+                    per_source.remove(&src);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::observability::metric::simple_exporter::noop::NoopMeterProvider;
+    use crate::tunnel::service_metrics::ServiceMetricsCreator;
+
+    use super::*;
+
+    fn controller(args: OverloadArgs) -> OverloadController {
+        let creator = ServiceMetricsCreator::new_creator(Arc::new(NoopMeterProvider::new()), None);
+        OverloadController::new(
+            Some(args),
+            creator.new_service_metrics([("ingress_id".to_owned(), "0".to_owned())]),
+        )
+    }
+
+    #[test]
+    fn test_overload_connections() {
+        let overload = controller(OverloadArgs {
+            max_pending_connections: Some(1),
+            max_active_connections: Some(2),
+            max_establish_latency_ms: None,
+            max_connections_per_source: None,
+        });
+        let src = IpAddr::from([10, 0, 0, 1]);
+
+        let mut first = overload.admit(src).expect("admitted");
+        assert_eq!(
+            overload.admit(src).err(),
+            Some(OverloadReason::PendingConnections)
+        );
+
+        first.established();
+        let mut second = overload.admit(src).expect("admitted");
+        second.established();
+        assert_eq!(
+            overload.check_admission(src),
+            Err(OverloadReason::ActiveConnections)
+        );
+
+        drop(first);
+        assert_eq!(overload.check_admission(src), Ok(()));
+        drop(second);
+    }
+
+    #[test]
+    fn test_overload_connections_per_source() {
+        let overload = controller(OverloadArgs {
+            max_connections_per_source: Some(2),
+            ..Default::default()
+        });
+        let first_src = IpAddr::from([10, 0, 0, 1]);
+        let second_src = IpAddr::from([10, 0, 0, 2]);
+
+        let first = overload.admit(first_src).expect("admitted");
+        let second = overload.admit(first_src).expect("admitted");
+        assert_eq!(
+            overload.admit(first_src).err(),
+            Some(OverloadReason::SourceConnections)
+        );
+        // IPv4-mapped IPv6 addresses are counted for the IPv4 source
+        assert_eq!(
+            overload.check_admission("::ffff:10.0.0.1".parse().unwrap()),
+            Err(OverloadReason::SourceConnections)
+        );
+        let other = overload.admit(second_src).expect("admitted");
+
+        drop(first);
+        let third = overload.admit(first_src).expect("admitted");
+
+        drop((second, third, other));
+        assert!(overload.inner.per_source().is_empty());
+    }
+}
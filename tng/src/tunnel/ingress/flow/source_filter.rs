@@ -0,0 +1,58 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use cidr::IpCidr;
+
+use crate::tunnel::utils::source_addr::is_source_allowed;
+
+/// Decides whether the connections accepted by the listeners of an ingress are allowed, from their
+/// source address. The list is shared by all the clones.
+#[derive(Debug, Clone, Default)]
+pub struct SourceFilter {
+    /// All the sources are allowed if empty.
+    allowed_sources: Arc<Vec<IpCidr>>,
+}
+
+impl SourceFilter {
+    pub fn new(allowed_sources: Vec<IpCidr>) -> Self {
+        Self {
+            allowed_sources: Arc::new(allowed_sources),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allowed_sources.is_empty()
+    }
+
+    /// Checks whether a connection from `peer` is allowed. A rejected connection is logged, and
+    /// should be closed before anything is read from it.
+    pub fn allows(&self, peer: SocketAddr) -> bool {
+        if is_source_allowed(&self.allowed_sources, peer.ip()) {
+            true
+        } else {
+            tracing::warn!(%peer, "Rejected connection from a source not allowed");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_filter() {
+        let filter = SourceFilter::new(vec![]);
+        assert!(filter.allows("192.168.1.1:1234".parse().unwrap()));
+
+        let filter = SourceFilter::new(vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "fd00::/8".parse().unwrap(),
+        ]);
+        assert!(filter.allows("10.1.2.3:1234".parse().unwrap()));
+        assert!(filter.allows("[fd00::1]:1234".parse().unwrap()));
+        assert!(filter.allows("[::ffff:10.1.2.3]:1234".parse().unwrap()));
+        assert!(!filter.allows("192.168.1.1:1234".parse().unwrap()));
+        assert!(!filter.allows("[fe80::1]:1234".parse().unwrap()));
+    }
+}
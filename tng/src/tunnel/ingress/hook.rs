@@ -8,8 +8,9 @@ use futures::StreamExt;
 use indexmap::IndexMap;
 use tokio::net::TcpListener;
 
-use crate::config::ingress::IngressHookArgs;
+use crate::config::ingress::{HttpProxyLimitsArgs, IngressHookArgs};
 use crate::tunnel::access_log::IngressAccessMode;
+use crate::tunnel::ingress::flow::overload::OverloadController;
 use crate::tunnel::ingress::flow::stream_router::StreamRouter;
 use crate::tunnel::ingress::flow::{Incomming, IngressTrait};
 use crate::tunnel::utils::endpoint_matcher::EndpointMatcher;
@@ -31,10 +32,16 @@ pub struct HookIngress {
     listener: TcpListener,
     listener_addr: SocketAddr,
     stream_router: Arc<StreamRouter>,
+    pipe_buffer_size: usize,
+    overload: Option<OverloadController>,
 }
 
 impl HookIngress {
-    pub async fn new(id: usize, hook_args: &IngressHookArgs) -> Result<Self> {
+    pub async fn new(
+        id: usize,
+        hook_args: &IngressHookArgs,
+        pipe_buffer_size: usize,
+    ) -> Result<Self> {
         let listen_addr = hook_args
             .proxy_listen
             .as_deref()
@@ -74,6 +81,8 @@ impl HookIngress {
             listener,
             listener_addr,
             stream_router,
+            pipe_buffer_size,
+            overload: None,
         })
     }
 }
@@ -97,6 +106,10 @@ impl IngressTrait for HookIngress {
         IngressAccessMode::Hook
     }
 
+    fn set_overload_controller(&mut self, overload: OverloadController) {
+        self.overload = Some(overload);
+    }
+
     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
     fn transport_so_mark(&self) -> Option<u32> {
         None
@@ -106,6 +119,8 @@ impl IngressTrait for HookIngress {
         let listener_addr = self.listener_addr;
         let stream_router = self.stream_router.clone();
         let mode = self.ingress_mode();
+        let pipe_buffer_size = self.pipe_buffer_size;
+        let overload = self.overload.clone();
 
         Ok(Box::pin(
             stream! {
@@ -118,6 +133,7 @@ impl IngressTrait for HookIngress {
                 move |res| {
                     let runtime = runtime.clone();
                     let stream_router = stream_router.clone();
+                    let overload = overload.clone();
 
                     Box::pin(stream! {
                         match res {
@@ -133,6 +149,9 @@ impl IngressTrait for HookIngress {
                                         sender,
                                         listener_addr,
                                         mode,
+                                        pipe_buffer_size,
+                                        overload,
+                                        HttpProxyLimitsArgs::default(),
                                     )
                                     .await
                                 });
@@ -1,5 +1,6 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::anyhow;
 use anyhow::{Context, Result};
@@ -11,8 +12,10 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use futures::StreamExt as _;
-use http::{uri::Scheme, HeaderValue, Request, Uri};
+use http::{header, uri::Scheme, HeaderValue, Request, Uri, Version};
+use http_body_util::Limited;
 use hyper::body::Incoming;
+use hyper_util::rt::TokioTimer;
 use hyper_util::service::TowerToHyperService;
 use indexmap::IndexMap;
 use tokio::net::{TcpListener, TcpStream};
@@ -20,13 +23,15 @@ use tokio::sync::mpsc::UnboundedSender;
 use tower::ServiceBuilder;
 use tracing::Instrument;
 
-use crate::config::ingress::IngressHttpProxyArgs;
+use crate::config::ingress::{HttpProxyLimitsArgs, IngressHttpProxyArgs};
 use crate::tunnel::access_log::{AccessAccepted, IngressAccessMode};
 use crate::tunnel::endpoint::TngEndpoint;
+use crate::tunnel::ingress::flow::overload::{OverloadController, OverloadReason};
+use crate::tunnel::ingress::flow::source_filter::SourceFilter;
 use crate::tunnel::ingress::flow::stream_router::StreamRouter;
 use crate::tunnel::utils::endpoint_matcher::EndpointMatcher;
 use crate::tunnel::utils::runtime::TokioRuntime;
-use crate::tunnel::utils::socket::SetListenerSockOpts;
+use crate::tunnel::utils::socket::{bind_tcp_listener, SetListenerSockOpts};
 use crate::tunnel::utils::tokio::TokioIo;
 use crate::HTTP_RESPONSE_SERVER_HEADER;
 
@@ -34,6 +39,44 @@ use super::flow::{AcceptedStream, Incomming, IngressTrait};
 
 const TNG_HTTP_FORWARD_HEADER: &str = "X-Tng-Http-Forward";
 
+/// The `UNAVAILABLE` status code of gRPC, returned to the gRPC clients when the request can not be
+/// forwarded to the upstream.
+const GRPC_STATUS_UNAVAILABLE: u32 = 14;
+
+/// Whether the request is a gRPC request, which is forwarded with HTTP/2 to preserve its trailers.
+fn is_grpc_request<B>(req: &Request<B>) -> bool {
+    req.version() == Version::HTTP_2
+        && req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("application/grpc"))
+}
+
+/// A "Trailers-Only" gRPC response carrying an error, so that the gRPC clients get a meaningful
+/// status instead of failing to parse a plain HTTP error.
+fn grpc_error_response(code: u32, message: &str) -> Response {
+    // The message is percent-encoded as required by the gRPC protocol
+    let message = message
+        .bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'%' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect::<String>();
+    let mut response = Response::new(Body::empty());
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/grpc"),
+    );
+    headers.insert("grpc-status", HeaderValue::from(code));
+    if let Ok(message) = HeaderValue::from_str(&message) {
+        headers.insert("grpc-message", message);
+    }
+    response
+}
+
 pub enum RouteResult {
     // At least in this time, we got no error, and this request should be handled in background.
     HandleInBackgroud,
@@ -41,6 +84,8 @@ pub enum RouteResult {
     Error(/*code*/ StatusCode, /* msg */ String),
     // We got a response to send to the client from upstream. No background task is remained.
     UpstreamResponse(Response),
+    // The ingress is overloaded and the request is rejected before anything is set up.
+    Overloaded(OverloadReason),
 }
 
 impl From<RouteResult> for Response {
@@ -52,6 +97,19 @@ impl From<RouteResult> for Response {
                 (code, msg).into_response()
             }
             RouteResult::UpstreamResponse(response) => response,
+            RouteResult::Overloaded(reason) => {
+                // Logged when it is rejected, so that an overloaded ingress is not also flooded
+                // with error logs
+                let mut response = (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    format!("TNG ingress is overloaded: {reason}"),
+                )
+                    .into_response();
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+                response
+            }
         }
     }
 }
@@ -61,10 +119,30 @@ struct RequestHelper {
 }
 
 impl RequestHelper {
-    pub fn from_request(req: Request<Incoming>) -> Self {
-        Self {
-            req: req.map(Body::new),
-        }
+    /// Wraps a request of the downstream, cutting off its body once it exceeds `max_body_size`.
+    pub fn from_request(req: Request<Incoming>, max_body_size: Option<usize>) -> Self {
+        let req = match max_body_size {
+            Some(limit) if req.method() != Method::CONNECT => {
+                req.map(|body| Body::new(Limited::new(body, limit)))
+            }
+            _ => req.map(Body::new),
+        };
+        Self { req }
+    }
+
+    /// Rejects a request declaring a body larger than `max_body_size`, before it is forwarded.
+    fn check_content_length(req: &Request<Incoming>, max_body_size: usize) -> Option<RouteResult> {
+        let content_length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())?;
+        (content_length > max_body_size as u64).then(|| {
+            RouteResult::Error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("The request body of {content_length} bytes exceeds the limit of {max_body_size} bytes"),
+            )
+        })
     }
 
     pub fn get_dst(&self) -> Result<TngEndpoint> {
@@ -118,6 +196,7 @@ impl RequestHelper {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn handle(
         self,
         stream_router: Arc<StreamRouter>,
@@ -126,7 +205,16 @@ impl RequestHelper {
         sender: UnboundedSender<AcceptedStream>,
         listener_addr: SocketAddr,
         mode: IngressAccessMode,
+        pipe_buffer_size: usize,
+        overload: Option<OverloadController>,
     ) -> RouteResult {
+        if let Some(Err(reason)) = overload
+            .as_ref()
+            .map(|overload| overload.check_admission(peer_addr.ip()))
+        {
+            return RouteResult::Overloaded(reason);
+        }
+
         let dst = match self.get_dst() {
             Ok(dst) => dst,
             Err(e) => return RouteResult::Error(StatusCode::BAD_REQUEST, format!("{e:#}")),
@@ -188,7 +276,14 @@ impl RequestHelper {
                     return RouteResult::Error(StatusCode::BAD_REQUEST, "recursion is detected".to_string())
                 }
 
-                let (s1, s2) = tokio::io::duplex(4096);
+                let grpc = is_grpc_request(&self.req);
+                let dst_authority = dst.to_string();
+                let grpc_unavailable = |msg: String| {
+                    tracing::error!(?msg, "responding gRPC errors to downstream");
+                    RouteResult::UpstreamResponse(grpc_error_response(GRPC_STATUS_UNAVAILABLE, &msg))
+                };
+
+                let (s1, s2) = tokio::io::duplex(pipe_buffer_size);
 
                 let send_accepted_stream = async {
                     let encrypted = stream_router.should_forward_via_tunnel(&dst);
@@ -201,46 +296,90 @@ impl RequestHelper {
                 };
 
                 let send_task = async {
-                    // TODO: support send both http1 and http2 payload
-                    let (mut sender, conn) =
-                        hyper::client::conn::http1::handshake(TokioIo::new(s1))
-                            .await
-                            .context("Failed during http handshake with upstream")?;
-
-                    let http_conn_span = tracing::info_span!("http_conn");
-                    runtime.spawn_supervised_task_with_span(http_conn_span, async move {
-                        if let Err(error) = conn.await {
-                            tracing::error!(?error, "The HTTP connection with upstream is broken");
-                        }
-                    });
-
                     let mut req = self.req;
 
-                    // Remove scheme and authority, but keep path and query in the request URI.
-                    let mut parts = req.uri().clone().into_parts();
-                    parts.authority = None;
-                    parts.scheme = None;
-                    *req.uri_mut() = http::Uri::from_parts(parts).with_context(|| {
-                        format!(
-                            "Failed convert uri {} for forwarding http request to upstream",
-                            req.uri()
-                        )
-                    })?;
+                    if grpc {
+                        // HTTP/2 requires the scheme and the authority in the request URI, so they
+                        // are kept, and filled in if the downstream omitted them.
+                        let mut parts = req.uri().clone().into_parts();
+                        if parts.scheme.is_none() {
+                            parts.scheme = Some(Scheme::HTTP);
+                        }
+                        if parts.authority.is_none() {
+                            parts.authority = Some(dst_authority.parse().with_context(|| {
+                                format!("Invalid authority {dst_authority} for forwarding gRPC request to upstream")
+                            })?);
+                        }
+                        *req.uri_mut() = http::Uri::from_parts(parts).with_context(|| {
+                            format!(
+                                "Failed convert uri {} for forwarding gRPC request to upstream",
+                                req.uri()
+                            )
+                        })?;
+                    } else {
+                        // Remove scheme and authority, but keep path and query in the request URI.
+                        let mut parts = req.uri().clone().into_parts();
+                        parts.authority = None;
+                        parts.scheme = None;
+                        *req.uri_mut() = http::Uri::from_parts(parts).with_context(|| {
+                            format!(
+                                "Failed convert uri {} for forwarding http request to upstream",
+                                req.uri()
+                            )
+                        })?;
+                    }
 
                     // Add a header to detect recursion
                     req.headers_mut().remove(TNG_HTTP_FORWARD_HEADER);
                     req.headers_mut().insert(TNG_HTTP_FORWARD_HEADER, HeaderValue::from_static("true"));
 
-                    tracing::debug!("Forwarding HTTP request to upstream now");
-                    sender
-                        .send_request(req)
-                        .await
-                        .map(|res| res.into_response())
-                        .context("Failed to send http request to upstream")
+                    let http_conn_span = tracing::info_span!("http_conn");
+                    if grpc {
+                        // gRPC carries its status in the trailers, which HTTP/1.1 drops, so it is
+                        // forwarded with HTTP/2 end-to-end.
+                        let (mut sender, conn) =
+                            hyper::client::conn::http2::handshake(runtime.clone(), TokioIo::new(s1))
+                                .await
+                                .context("Failed during http2 handshake with upstream")?;
+
+                        runtime.spawn_supervised_task_with_span(http_conn_span, async move {
+                            if let Err(error) = conn.await {
+                                tracing::error!(?error, "The HTTP/2 connection with upstream is broken");
+                            }
+                        });
+
+                        tracing::debug!("Forwarding gRPC request to upstream now");
+                        sender
+                            .send_request(req)
+                            .await
+                            .map(|res| res.into_response())
+                            .context("Failed to send gRPC request to upstream")
+                    } else {
+                        // TODO: support send both http1 and http2 payload
+                        let (mut sender, conn) =
+                            hyper::client::conn::http1::handshake(TokioIo::new(s1))
+                                .await
+                                .context("Failed during http handshake with upstream")?;
+
+                        runtime.spawn_supervised_task_with_span(http_conn_span, async move {
+                            if let Err(error) = conn.await {
+                                tracing::error!(?error, "The HTTP connection with upstream is broken");
+                            }
+                        });
+
+                        tracing::debug!("Forwarding HTTP request to upstream now");
+                        sender
+                            .send_request(req)
+                            .await
+                            .map(|res| res.into_response())
+                            .context("Failed to send http request to upstream")
+                    }
                 };
 
                 match tokio::join!(send_accepted_stream, send_task) {
                     // TODO: send_accepted_stream is just send a accpted stream to IngressFlow, we need a better way to get error propagated back to here, so that we can get errors raised during the forwarding, and then report it to the downstream.
+                    (Err(e), _) if grpc => grpc_unavailable(format!("{e:#}")),
+                    (Ok(_), Err(e)) if grpc => grpc_unavailable(format!("{e:#}")),
                     (Err(e), _) => RouteResult::Error(StatusCode::BAD_REQUEST, format!("{e:#}")),
                     (Ok(_), Ok(response)) => RouteResult::UpstreamResponse(response),
                     (Ok(_), Err(e)) => {
@@ -262,6 +401,10 @@ pub struct HttpProxyIngress {
     listener: TcpListener,
     listener_addr: SocketAddr,
     stream_router: Arc<StreamRouter>,
+    pipe_buffer_size: usize,
+    overload: Option<OverloadController>,
+    source_filter: SourceFilter,
+    limits: HttpProxyLimitsArgs,
 }
 
 impl HttpProxyIngress {
@@ -269,6 +412,7 @@ impl HttpProxyIngress {
         id: usize,
         http_proxy_args: &IngressHttpProxyArgs,
         mode: IngressAccessMode,
+        pipe_buffer_size: usize,
     ) -> Result<Self> {
         let listen_addr = http_proxy_args
             .proxy_listen
@@ -278,6 +422,9 @@ impl HttpProxyIngress {
             .to_owned();
         let listen_port = http_proxy_args.proxy_listen.port;
 
+        let limits = http_proxy_args.limits.clone().unwrap_or_default();
+        limits.validate()?;
+
         let stream_router = Arc::new(StreamRouter::with_endpoint_matcher(EndpointMatcher::new(
             &http_proxy_args.dst_filters,
         )?));
@@ -288,13 +435,9 @@ impl HttpProxyIngress {
         // The port is bound here at construction time.
         let listen_addr_full = format!("{}:{}", listen_addr, listen_port);
         tracing::debug!(%listen_addr_full, "Add TCP listener");
-        let std_listener = std::net::TcpListener::bind(&listen_addr_full).with_context(|| {
+        let listener = bind_tcp_listener(&listen_addr_full).with_context(|| {
             format!("Failed to bind http_proxy ingress listener on {listen_addr_full}")
         })?;
-        std_listener
-            .set_nonblocking(true)
-            .context("Failed to set nonblocking on listener")?;
-        let listener = TcpListener::from_std(std_listener)?;
         listener.set_listener_common_sock_opts()?;
         let listener_addr = listener.local_addr()?;
 
@@ -306,6 +449,10 @@ impl HttpProxyIngress {
             listener,
             listener_addr,
             stream_router,
+            pipe_buffer_size,
+            overload: None,
+            source_filter: SourceFilter::default(),
+            limits,
         })
     }
 }
@@ -334,20 +481,39 @@ impl IngressTrait for HttpProxyIngress {
         self.mode
     }
 
+    fn set_overload_controller(&mut self, overload: OverloadController) {
+        self.overload = Some(overload);
+    }
+
+    fn set_source_filter(&mut self, source_filter: SourceFilter) -> Result<()> {
+        self.source_filter = source_filter;
+        Ok(())
+    }
+
     async fn accept(&self, runtime: TokioRuntime) -> Result<Incomming> {
         let listener_addr = self.listener_addr;
         let mode = self.mode;
+        let pipe_buffer_size = self.pipe_buffer_size;
+        let overload = self.overload.clone();
+        let limits = self.limits.clone();
 
         Ok(Box::pin(
             stream! {
                 loop {
-                    yield self.listener.accept_with_common_sock_opts().await
+                    let res = self.listener.accept_with_common_sock_opts().await;
+                    // The stream is closed before any request is read when it is dropped here
+                    if matches!(&res, Ok((_, peer_addr)) if !self.source_filter.allows(*peer_addr)) {
+                        continue;
+                    }
+                    yield res
                 }
             }.flat_map_unordered(
                 None, // Unlimited concurrency of http proxy session
                 move |res| {
                     let runtime = runtime.clone();
                     let stream_router = self.stream_router.clone();
+                    let overload = overload.clone();
+                    let limits = limits.clone();
 
                     Box::pin(stream! {
                         match res {
@@ -356,7 +522,7 @@ impl IngressTrait for HttpProxyIngress {
                                 let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
 
                                 runtime.spawn_supervised_task_fn_current_span(move |runtime| async move {
-                                    serve_http_proxy_no_throw_error(stream, stream_router, runtime, peer_addr, sender, listener_addr, mode)
+                                    serve_http_proxy_no_throw_error(stream, stream_router, runtime, peer_addr, sender, listener_addr, mode, pipe_buffer_size, overload, limits)
                                         .await
                                 });
 
@@ -373,6 +539,7 @@ impl IngressTrait for HttpProxyIngress {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn serve_http_proxy_no_throw_error(
     in_stream: TcpStream,
     stream_router: Arc<StreamRouter>,
@@ -381,26 +548,41 @@ pub(super) async fn serve_http_proxy_no_throw_error(
     sender: UnboundedSender<AcceptedStream>,
     listener_addr: SocketAddr,
     mode: IngressAccessMode,
+    pipe_buffer_size: usize,
+    overload: Option<OverloadController>,
+    limits: HttpProxyLimitsArgs,
 ) {
     let runtime_cloned = runtime.clone();
+    let max_body_size = limits.max_body_size;
 
     let svc = {
         ServiceBuilder::new().service(tower::service_fn(move |req| {
             let stream_router = stream_router.clone();
             let runtime = runtime.clone();
             let sender = sender.clone();
+            let overload = overload.clone();
 
             async move {
-                let route_result = RequestHelper::from_request(req)
-                    .handle(
-                        stream_router,
-                        runtime,
-                        peer_addr,
-                        sender,
-                        listener_addr,
-                        mode,
-                    )
-                    .await;
+                let too_large = max_body_size.and_then(|max_body_size| {
+                    RequestHelper::check_content_length(&req, max_body_size)
+                });
+                let route_result = match too_large {
+                    Some(route_result) => route_result,
+                    None => {
+                        RequestHelper::from_request(req, max_body_size)
+                            .handle(
+                                stream_router,
+                                runtime,
+                                peer_addr,
+                                sender,
+                                listener_addr,
+                                mode,
+                                pipe_buffer_size,
+                                overload,
+                            )
+                            .await
+                    }
+                };
 
                 let mut response: axum::response::Response = route_result.into();
                 response.headers_mut().insert(
@@ -415,7 +597,20 @@ pub(super) async fn serve_http_proxy_no_throw_error(
 
     if let Err(error) = async {
         let executor = runtime_cloned;
-        hyper_util::server::conn::auto::Builder::new(executor)
+        let mut builder = hyper_util::server::conn::auto::Builder::new(executor);
+        if let Some(max_header_size) = limits.max_header_size {
+            builder.http1().max_buf_size(max_header_size);
+            builder
+                .http2()
+                .max_header_list_size(max_header_size.try_into().unwrap_or(u32::MAX));
+        }
+        if let Some(timeout) = limits.header_read_timeout_secs {
+            builder
+                .http1()
+                .timer(TokioTimer::new())
+                .header_read_timeout(Duration::from_secs(timeout));
+        }
+        builder
             .serve_connection_with_upgrades(TokioIo::new(in_stream), svc)
             .await
     }
@@ -424,3 +619,46 @@ pub(super) async fn serve_http_proxy_no_throw_error(
         tracing::error!(?error, "Failed to serve HTTP proxy downstream connection");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_grpc_request() {
+        let request = |version, content_type| {
+            Request::builder()
+                .version(version)
+                .header(header::CONTENT_TYPE, content_type)
+                .body(())
+                .unwrap()
+        };
+        assert!(is_grpc_request(&request(
+            Version::HTTP_2,
+            "application/grpc"
+        )));
+        assert!(is_grpc_request(&request(
+            Version::HTTP_2,
+            "application/grpc+proto"
+        )));
+        assert!(!is_grpc_request(&request(
+            Version::HTTP_2,
+            "application/json"
+        )));
+        assert!(!is_grpc_request(&request(
+            Version::HTTP_11,
+            "application/grpc"
+        )));
+    }
+
+    #[test]
+    fn test_grpc_error_response() {
+        let response = grpc_error_response(GRPC_STATUS_UNAVAILABLE, "upstream 100% down\n");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["grpc-status"], "14");
+        assert_eq!(
+            response.headers()["grpc-message"],
+            "upstream 100%25 down%0A"
+        );
+    }
+}
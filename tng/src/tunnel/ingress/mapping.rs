@@ -6,7 +6,7 @@ use anyhow::{Context, Result};
 use async_stream::stream;
 use async_trait::async_trait;
 use futures::stream::select_all;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use tokio::net::TcpListener;
 
 use crate::config::ingress::IngressMappingArgs;
@@ -14,13 +14,15 @@ use crate::tunnel::access_log::{AccessAccepted, IngressAccessMode};
 use crate::tunnel::endpoint::TngEndpoint;
 use crate::tunnel::ingress::flow::AcceptedStream;
 use crate::tunnel::utils::runtime::TokioRuntime;
-use crate::tunnel::utils::socket::SetListenerSockOpts;
+use crate::tunnel::utils::socket::{bind_tcp_listener, SetListenerSockOpts};
 
+use super::flow::source_filter::SourceFilter;
 use super::flow::{Incomming, IngressTrait};
 
 pub struct MappingIngress {
     id: usize,
     rules: Vec<crate::config::mapping_rule::MappingRule>,
+    source_filter: SourceFilter,
 }
 
 impl MappingIngress {
@@ -32,6 +34,7 @@ impl MappingIngress {
         Ok(Self {
             id,
             rules: mapping_args.rules.clone(),
+            source_filter: SourceFilter::default(),
         })
     }
 }
@@ -75,6 +78,11 @@ impl IngressTrait for MappingIngress {
         None
     }
 
+    fn set_source_filter(&mut self, source_filter: SourceFilter) -> Result<()> {
+        self.source_filter = source_filter;
+        Ok(())
+    }
+
     async fn accept(&self, _runtime: TokioRuntime) -> Result<Incomming> {
         struct ListenerTarget {
             listener: TcpListener,
@@ -96,7 +104,7 @@ impl IngressTrait for MappingIngress {
                     let addr = format!("{host}:{port}");
                     tracing::debug!(%addr, "Add TCP listener");
 
-                    let listener = TcpListener::bind(&addr).await.with_context(|| {
+                    let listener = bind_tcp_listener(&addr).with_context(|| {
                         format!("Failed to bind mapping ingress listener on {addr}")
                     })?;
                     listener.set_listener_common_sock_opts()?;
@@ -113,7 +121,7 @@ impl IngressTrait for MappingIngress {
                 let addr = format!("{host}:{}", rule.r#in.port);
                 tracing::debug!(%addr, "Add TCP listener");
 
-                let listener = TcpListener::bind(&addr).await.with_context(|| {
+                let listener = bind_tcp_listener(&addr).with_context(|| {
                     format!("Failed to bind mapping ingress listener on {addr}")
                 })?;
                 listener.set_listener_common_sock_opts()?;
@@ -131,9 +139,12 @@ impl IngressTrait for MappingIngress {
         let streams: Vec<_> = targets
             .into_iter()
             .map(|target| {
+                let source_filter = self.source_filter.clone();
                 Box::pin(stream! {
                     loop {
                         match target.listener.accept_with_common_sock_opts().await {
+                            // The stream is closed when it is dropped here
+                            Ok((_, peer_addr)) if !source_filter.allows(peer_addr) => {}
                             Ok((stream, peer_addr)) => {
                                 let access_accepted = AccessAccepted::new_ingress(
                                     peer_addr,
@@ -159,4 +170,18 @@ impl IngressTrait for MappingIngress {
 
         Ok(Box::pin(select_all(streams)))
     }
+
+    fn known_endpoints(&self) -> Vec<TngEndpoint> {
+        let mut endpoints = IndexSet::new();
+        for rule in &self.rules {
+            let Some(out_host) = rule.out.host else {
+                continue;
+            };
+            let port_end = rule.r#in.port_end.unwrap_or(rule.r#in.port);
+            for offset in 0..=port_end.saturating_sub(rule.r#in.port) {
+                endpoints.insert(TngEndpoint::from_ipv4(out_host, rule.out.port + offset));
+            }
+        }
+        endpoints.into_iter().collect()
+    }
 }
@@ -17,6 +17,8 @@ pub mod mapping_udp;
 pub mod netfilter;
 #[cfg(feature = "ingress-socks5")]
 pub mod socks5;
+#[cfg(not(wasm))]
+pub mod tunnel_dns;
 
 #[cfg(feature = "ingress-mapping-udp")]
 pub mod datagram_flow;
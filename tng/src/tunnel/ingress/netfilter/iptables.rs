@@ -13,11 +13,57 @@ use super::NetfilterIngress;
 const IPTABLES_FW_MARK_BASE: u32 = 566;
 const IP_ROUTE_TABLE_NUM_BASE: u32 = 239;
 
+/// Returns the fw_mark set on the captured packets of the ingress with the id. A per-instance
+/// fw_mark is used to avoid conflicts when multiple ingress netfilter instances run concurrently.
+pub(super) fn fw_mark(id: usize) -> u32 {
+    IPTABLES_FW_MARK_BASE + id as u32
+}
+
 fn is_cgroup_v2() -> bool {
     // https://rootlesscontaine.rs/getting-started/common/cgroup2/#checking-whether-cgroup-v2-is-already-enabled
     Path::new("/sys/fs/cgroup/cgroup.controllers").exists()
 }
 
+/// Returns the iptables match arguments selecting the TCP packets sent to the destination.
+fn iptables_dst_match(dst: &IngressNetfilterCaptureDst) -> String {
+    match dst {
+        IngressNetfilterCaptureDst::HostOnly { host } => {
+            format!(
+                "-p tcp --dst {}/{}",
+                host.first_address(),
+                host.network_length()
+            )
+        }
+        IngressNetfilterCaptureDst::IpSetOnly { ipset } => {
+            format!("-p tcp -m set --match-set {ipset} dst")
+        }
+        IngressNetfilterCaptureDst::PortOnly { port, port_end } => {
+            let dport = format_dport(*port, port_end.as_ref());
+            format!("-p tcp --dport {dport}")
+        }
+        IngressNetfilterCaptureDst::HostAndPort {
+            host,
+            port,
+            port_end,
+        } => {
+            let dport = format_dport(*port, port_end.as_ref());
+            format!(
+                "-p tcp --dst {}/{} --dport {dport}",
+                host.first_address(),
+                host.network_length()
+            )
+        }
+        IngressNetfilterCaptureDst::IpSetAndPort {
+            ipset,
+            port,
+            port_end,
+        } => {
+            let dport = format_dport(*port, port_end.as_ref());
+            format!("-p tcp --dport {dport} -m set --match-set {ipset} dst")
+        }
+    }
+}
+
 #[async_trait]
 impl IptablesRuleGenerator for NetfilterIngress {
     async fn gen_script(&self) -> Result<(String, String)> {
@@ -29,7 +75,7 @@ impl IptablesRuleGenerator for NetfilterIngress {
         let id = self.id;
         // Use per-instance fw_mark and route_table to avoid conflicts
         // when multiple ingress netfilter instances run concurrently.
-        let fw_mark = IPTABLES_FW_MARK_BASE + id as u32;
+        let fw_mark = fw_mark(id);
         let route_table = IP_ROUTE_TABLE_NUM_BASE + id as u32;
         let listen_port = self.listen_port;
 
@@ -137,52 +183,24 @@ impl IptablesRuleGenerator for NetfilterIngress {
             }
         }
 
+        // In the second stage, if the packet match any nocapture_dst, then we skip it.
+        for nocapture_dst in &self.nocapture_dst {
+            let dst_match = iptables_dst_match(nocapture_dst);
+            tproxy_invoke_script += &format!(
+                "iptables -t mangle -A TNG_INGRESS_{id}_OUTPUT_STAGE_2 {dst_match} -j RETURN ;"
+            );
+        }
+
         // In the second stage, if the packet match any capture_dst, then we set the netfilter mark on the packet it so that it can be re-routed to local with rule based route rule, and we can handle them in the prerouting chain
         if self.capture_dst.is_empty() {
             tproxy_invoke_script +=
                 &format!("iptables -t mangle -A TNG_INGRESS_{id}_OUTPUT_STAGE_2 -p tcp -j MARK --set-mark {fw_mark}/0xffffff ;");
         } else {
             for capture_dst in &self.capture_dst {
-                match capture_dst {
-                    IngressNetfilterCaptureDst::HostOnly { host } => {
-                        tproxy_invoke_script += &format!(
-                            "iptables -t mangle -A TNG_INGRESS_{id}_OUTPUT_STAGE_2 -p tcp --dst {}/{} -j MARK --set-mark {fw_mark}/0xffffff ;",
-                            host.first_address(), host.network_length()
-                        );
-                    }
-                    IngressNetfilterCaptureDst::IpSetOnly { ipset } => {
-                        tproxy_invoke_script += &format!(
-                            "iptables -t mangle -A TNG_INGRESS_{id}_OUTPUT_STAGE_2 -p tcp -m set --match-set {ipset} dst -j MARK --set-mark {fw_mark}/0xffffff ;"
-                        );
-                    }
-                    IngressNetfilterCaptureDst::PortOnly { port, port_end } => {
-                        let dport = format_dport(*port, port_end.as_ref());
-                        tproxy_invoke_script += &format!(
-                            "iptables -t mangle -A TNG_INGRESS_{id}_OUTPUT_STAGE_2 -p tcp --dport {dport} -j MARK --set-mark {fw_mark}/0xffffff ;"
-                        );
-                    }
-                    IngressNetfilterCaptureDst::HostAndPort {
-                        host,
-                        port,
-                        port_end,
-                    } => {
-                        let dport = format_dport(*port, port_end.as_ref());
-                        tproxy_invoke_script += &format!(
-                            "iptables -t mangle -A TNG_INGRESS_{id}_OUTPUT_STAGE_2 -p tcp --dst {}/{} --dport {dport} -j MARK --set-mark {fw_mark}/0xffffff ;",
-                            host.first_address(), host.network_length()
-                        );
-                    }
-                    IngressNetfilterCaptureDst::IpSetAndPort {
-                        ipset,
-                        port,
-                        port_end,
-                    } => {
-                        let dport = format_dport(*port, port_end.as_ref());
-                        tproxy_invoke_script += &format!(
-                            "iptables -t mangle -A TNG_INGRESS_{id}_OUTPUT_STAGE_2 -p tcp --dport {dport} -m set --match-set {ipset} dst -j MARK --set-mark {fw_mark}/0xffffff ;"
-                        );
-                    }
-                }
+                let dst_match = iptables_dst_match(capture_dst);
+                tproxy_invoke_script += &format!(
+                    "iptables -t mangle -A TNG_INGRESS_{id}_OUTPUT_STAGE_2 {dst_match} -j MARK --set-mark {fw_mark}/0xffffff ;"
+                );
             }
         }
 
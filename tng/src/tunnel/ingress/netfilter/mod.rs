@@ -28,6 +28,7 @@ pub struct NetfilterIngress {
     capture_dst: Vec<IngressNetfilterCaptureDst>,
     capture_cgroup: Vec<String>,
     nocapture_cgroup: Vec<String>,
+    nocapture_dst: Vec<IngressNetfilterCaptureDst>,
     listen_port: u16,
     so_mark: u32,
 }
@@ -50,15 +51,31 @@ impl NetfilterIngress {
             .map(TryInto::try_into)
             .collect::<Result<Vec<_>>>()?;
 
+        let nocapture_dst = netfilter_args
+            .nocapture_dst
+            .iter()
+            .map(Clone::clone)
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<_>>>()
+            .context("Invalid `nocapture_dst`")?;
+
         let so_mark = netfilter_args
             .so_mark
             .unwrap_or(TCP_CONNECT_SO_MARK_DEFAULT);
+        // Packets without any mark have the mark 0, so they would all be skipped.
+        if so_mark == 0 {
+            bail!("`so_mark` must not be 0");
+        }
+        if so_mark & 0xffffff == iptables::fw_mark(id) {
+            bail!("`so_mark` {so_mark} conflicts with the mark used to capture the traffic");
+        }
 
         Ok(Self {
             id,
             capture_dst,
             capture_cgroup: netfilter_args.capture_cgroup.clone(),
             nocapture_cgroup: netfilter_args.nocapture_cgroup.clone(),
+            nocapture_dst,
             listen_port,
             so_mark,
         })
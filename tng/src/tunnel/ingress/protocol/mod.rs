@@ -2,6 +2,8 @@ pub mod ohttp;
 
 #[cfg(not(wasm))]
 pub mod rats_tls;
+#[cfg(wasm)]
+pub mod websocket;
 
 use std::{future::Future, net::SocketAddr, pin::Pin};
 
@@ -21,6 +23,7 @@ pub type ProtocolStreamForwarderOutput = (
     ForwardTask,
     Option<AttestationResult>,
     /* upstream_local */ Option<SocketAddr>,
+    /* session_id */ Option<u64>,
 );
 
 #[cfg(not(wasm))]
@@ -31,4 +34,10 @@ pub trait ProtocolStreamForwarder: StatusProvider {
         endpoint: &'a TngEndpoint,
         downstream: Box<dyn CommonStreamTrait + 'static>,
     ) -> Result<ProtocolStreamForwarderOutput>;
+
+    /// Establishes the session to `endpoint` ahead of the first stream, and keeps it open. It is
+    /// called periodically, and does nothing for the protocols without long-lived sessions.
+    async fn prewarm(&self, _endpoint: &TngEndpoint) -> Result<()> {
+        Ok(())
+    }
 }
@@ -18,6 +18,7 @@ mod ohttp_stream_forwarder {
                 ProtocolStreamForwarderOutput,
             },
             ra_context::RaContext,
+            resolver::Resolver,
         },
         CommonStreamTrait, TokioIo, TokioRuntime,
     };
@@ -36,6 +37,7 @@ mod ohttp_stream_forwarder {
         pub async fn new(
             #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
             transport_so_mark: Option<u32>,
+            resolver: Option<Arc<dyn Resolver>>,
             ohttp_args: &OHttpArgs,
             ra_context: Arc<RaContext>,
             runtime: TokioRuntime,
@@ -49,6 +51,7 @@ mod ohttp_stream_forwarder {
                             target_os = "linux"
                         ))]
                         transport_so_mark,
+                        resolver,
                         ohttp_args,
                         ra_context,
                         runtime.clone(),
@@ -75,6 +78,13 @@ mod ohttp_stream_forwarder {
                     move |request: http::Request<hyper::body::Incoming>| {
                         let security_layer = security_layer.clone();
                         let endpoint = endpoint.clone();
+                        // Continue the trace of the client if it sends a `traceparent` header
+                        let span = tracing::info_span!("request");
+                        #[cfg(feature = "trace")]
+                        crate::observability::trace::propagation::set_parent_from_headers(
+                            &span,
+                            request.headers(),
+                        );
                         async move {
                             Ok::<_, Infallible>(
                                 match security_layer
@@ -89,6 +99,7 @@ mod ohttp_stream_forwarder {
                                 },
                             )
                         }
+                        .instrument(span)
                     },
                 );
 
@@ -106,6 +117,7 @@ mod ohttp_stream_forwarder {
                     // TODO: ohttp always return None attestation result in stream level, which may cause misunderstanding when user is reading the logs.
                     None,
                     None, // OHTTP does not have a separate upstream connection
+                    None,
                 ))
             }
             .instrument(tracing::info_span!("security"))
@@ -49,22 +49,12 @@ use crate::tunnel::ohttp::protocol::userdata::ClientUserData;
 #[cfg(unix)]
 use crate::tunnel::ra_context::AttestContext;
 use crate::{
-    error::CheckErrorResponse as _,
-    tunnel::{
-        ohttp::protocol::{
-            metadata::{metadata::ClientAuth, Metadata, NoAuth, METADATA_MAX_LEN},
-            userdata::ServerUserData,
-            AttestationChallengeResponse, AttestationRequest, AttestationVerifyRequest,
-            AttestationVerifyResponse, KeyConfigRequest, KeyConfigResponse, ServerAttestationInfo,
-        },
-        utils::maybe_cached::{Expire, MaybeCached, RefreshStrategy},
-    },
-};
-use crate::{
+    config::ohttp_padding::OHttpPaddingPolicy,
     error::TngError,
     tunnel::{
         ohttp::{
             key_config::KeyConfigExtend,
+            padding::pad_message_stream,
             protocol::{
                 header::{
                     OhttpApi, OHTTP_CHUNKED_REQUEST_CONTENT_TYPE,
@@ -77,6 +67,18 @@ use crate::{
     },
     AttestationResult, TokioRuntime,
 };
+use crate::{
+    error::CheckErrorResponse as _,
+    tunnel::{
+        ohttp::protocol::{
+            metadata::{metadata::ClientAuth, Metadata, NoAuth, METADATA_MAX_LEN},
+            userdata::ServerUserData,
+            AttestationChallengeResponse, AttestationRequest, AttestationVerifyRequest,
+            AttestationVerifyResponse, KeyConfigRequest, KeyConfigResponse, ServerAttestationInfo,
+        },
+        utils::maybe_cached::{Expire, MaybeCached, RefreshStrategy},
+    },
+};
 
 const DEFAULT_KEY_CONFIG_REFRESH_SECOND: u64 = 5 * 60; // 5 minutes
 
@@ -99,6 +101,8 @@ pub struct OHttpClientInner {
     /// Headers to copy from the outer OHTTP response to the inner (plaintext) response.
     #[allow(unused)]
     passthrough_response_headers: Arc<crate::config::header_passthrough::HeaderPassthroughSpec>,
+    /// Padding applied to the encapsulated requests.
+    padding: Option<OHttpPaddingPolicy>,
 }
 
 struct KeyStoreValue {
@@ -142,7 +146,7 @@ impl OHttpClient {
             server_attestation: ksv
                 .server_attestation_result
                 .as_ref()
-                .map(|ar| ar.token_str().to_string()),
+                .and_then(|ar| ar.token().map(str::to_owned)),
         })
     }
 
@@ -153,6 +157,7 @@ impl OHttpClient {
         runtime: TokioRuntime,
         passthrough_request_headers: Arc<crate::config::header_passthrough::HeaderPassthroughSpec>,
         passthrough_response_headers: Arc<crate::config::header_passthrough::HeaderPassthroughSpec>,
+        padding: Option<OHttpPaddingPolicy>,
     ) -> Result<Self> {
         let refresh_strategy = {
             #[cfg(unix)]
@@ -182,6 +187,7 @@ impl OHttpClient {
             runtime: runtime.clone(),
             passthrough_request_headers,
             passthrough_response_headers,
+            padding,
         });
 
         let key_store_value = MaybeCached::new(runtime.clone(), refresh_strategy, {
@@ -243,7 +249,7 @@ impl OHttpClientInner {
         let (server_key_config, token) = {
             let verify_context = self.ra_context.verify_context();
 
-            match verify_context {
+            match verify_context.as_deref() {
                 Some(VerifyContext::Passport { verifier }) => {
                     // Request hpke configuration for server
                     let response = self
@@ -288,6 +294,7 @@ impl OHttpClientInner {
                 Some(VerifyContext::BackgroundCheck {
                     converter,
                     verifier,
+                    ..
                 }) => {
                     // fetch a challenge token from attestation service
                     let challenge_token = converter
@@ -546,6 +553,8 @@ impl OHttpClientInner {
 
         // Encode the request to bhttp message
         let bhttp_encoder = BhttpEncoder::from_request(request);
+        // Pad the bhttp message to hide the request size
+        let bhttp_encoder = Box::pin(pad_message_stream(bhttp_encoder, self.padding));
 
         // Encrypt to get the ohttp message
         let mut key_config = server_key_config_list
@@ -687,6 +696,14 @@ impl OHttpClientInner {
             request_builder = request_builder.header(name, value);
         }
 
+        // Propagate the trace context to the egress, replacing the one passed through, if any
+        #[cfg(feature = "trace")]
+        {
+            let mut trace_headers = http::HeaderMap::new();
+            crate::observability::trace::propagation::inject_trace_context(&mut trace_headers);
+            request_builder = request_builder.headers(trace_headers);
+        }
+
         let response = request_builder
             .body(ohttp_request_body)
             .send()
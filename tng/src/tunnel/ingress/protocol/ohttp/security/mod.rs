@@ -5,12 +5,17 @@ use std::{collections::HashMap, sync::Arc};
 
 #[cfg(not(wasm))]
 use crate::status::{StatusProvider, StatusQueryResult};
+#[cfg(not(wasm))]
+use crate::tunnel::resolver::{ReqwestResolver, Resolver};
 #[cfg(unix)]
 use crate::tunnel::utils::socket::{
     TCP_KEEPALIVE_IDLE_SECS, TCP_KEEPALIVE_INTERVAL_SECS, TCP_KEEPALIVE_PROBE_COUNT,
 };
 use crate::{
-    config::ingress::{OHttpArgs, PathDefault},
+    config::{
+        ingress::{OHttpArgs, PathDefault},
+        ohttp_padding::OHttpPaddingPolicy,
+    },
     error::TngError,
     tunnel::{
         endpoint::TngEndpoint,
@@ -72,6 +77,7 @@ fn build_ohttp_http_client(
     ohttp_args: &OHttpArgs,
     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
     transport_so_mark: Option<u32>,
+    resolver: Option<Arc<dyn Resolver>>,
 ) -> Result<reqwest::Client> {
     let mut builder = reqwest::Client::builder();
     builder = builder.default_headers({
@@ -97,6 +103,10 @@ fn build_ohttp_http_client(
         builder = builder.tcp_mark(transport_so_mark);
     }
 
+    if let Some(resolver) = resolver {
+        builder = builder.dns_resolver(Arc::new(ReqwestResolver(resolver)));
+    }
+
     for path in &ohttp_args.tls_ca_certs {
         let pem =
             std::fs::read(path).with_context(|| format!("Failed to read TLS CA cert: {path}"))?;
@@ -152,12 +162,15 @@ pub struct OHttpSecurityLayer {
     passthrough_request_headers: Arc<crate::config::header_passthrough::HeaderPassthroughSpec>,
     /// Headers to copy from the outer (ciphertext) response to the inner (plaintext) response.
     passthrough_response_headers: Arc<crate::config::header_passthrough::HeaderPassthroughSpec>,
+    /// Padding applied to the encapsulated requests.
+    padding: Option<OHttpPaddingPolicy>,
 }
 
 impl OHttpSecurityLayer {
     pub async fn new(
         #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
         transport_so_mark: Option<u32>,
+        #[cfg(not(wasm))] resolver: Option<Arc<dyn Resolver>>,
         ohttp_args: &OHttpArgs,
         // Outer OHTTP POST scheme already normalized via
         // `OHttpSecurityLayer::scheme_from_url` from the URL the caller passed
@@ -186,6 +199,7 @@ impl OHttpSecurityLayer {
                     ohttp_args,
                     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
                     transport_so_mark,
+                    resolver,
                 )?
             }
             #[cfg(wasm)]
@@ -222,6 +236,7 @@ impl OHttpSecurityLayer {
             runtime,
             passthrough_request_headers,
             passthrough_response_headers,
+            padding: ohttp_args.padding,
         })
     }
 
@@ -329,6 +344,7 @@ impl OHttpSecurityLayer {
                     self.runtime.clone(),
                     self.passthrough_request_headers.clone(),
                     self.passthrough_response_headers.clone(),
+                    self.padding,
                 )
                 .await
                 .map_err(TngError::CreateOHttpClientFailed)?,
@@ -474,6 +490,7 @@ mod tests {
             &ohttp_args,
             #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
             None,
+            None,
         )?;
         Ok(())
     }
@@ -491,6 +508,7 @@ mod tests {
             &ohttp_args,
             #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
             None,
+            None,
         )
         .unwrap_err();
 
@@ -528,6 +546,7 @@ mod tests {
             &ohttp_args,
             #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
             None,
+            None,
         )
         .unwrap_err();
         let msg = format!("{:#}", error);
@@ -547,6 +566,7 @@ mod tests {
             &ohttp_args,
             #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
             None,
+            None,
         )?;
         Ok(())
     }
@@ -1,5 +1,6 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::{
     error::TngError,
@@ -11,39 +12,53 @@ use crate::{
             ProtocolStreamForwarderOutput,
         },
         ra_context::RaContext,
+        service_metrics::ServiceMetrics,
         utils,
+        utils::circuit_breaker::CircuitBreaker,
     },
     AttestationResult, CommonStreamTrait, ContextualStream, TokioRuntime,
 };
 
 use anyhow::Result;
 use async_trait::async_trait;
+use transport::TransportLayerCreator;
 
 mod security;
-mod transport;
+pub mod transport;
 mod wrapping;
 
 pub struct RatsTlsStreamForwarder {
     security_layer: RatsTlsSecurityLayer,
+    forward_buffer_size: usize,
 }
 
 impl RatsTlsStreamForwarder {
     pub async fn new(
-        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-        transport_so_mark: Option<u32>,
+        transport_layer_creator: Arc<dyn TransportLayerCreator>,
         ra_context: Arc<RaContext>,
+        metrics: ServiceMetrics,
         runtime: TokioRuntime,
         multiplex: bool,
+        handshake_timeout: Option<Duration>,
+        alpn: &[String],
+        adaptive_window: bool,
+        forward_buffer_size: usize,
+        circuit_breaker: CircuitBreaker,
     ) -> Result<Self> {
         Ok(Self {
             security_layer: RatsTlsSecurityLayer::new(
-                #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-                transport_so_mark,
+                transport_layer_creator,
                 ra_context,
+                metrics,
                 runtime,
                 multiplex,
+                handshake_timeout,
+                alpn,
+                adaptive_window,
+                circuit_breaker,
             )
             .await?,
+            forward_buffer_size,
         })
     }
 
@@ -76,17 +91,24 @@ impl ProtocolStreamForwarder for RatsTlsStreamForwarder {
         endpoint: &'a TngEndpoint,
         downstream: Box<dyn CommonStreamTrait + 'static>,
     ) -> Result<ProtocolStreamForwarderOutput> {
-        let (upstream, local_addr, attestation_result, _session_id) =
+        let (upstream, local_addr, attestation_result, session_id) =
             self.connect(endpoint.clone()).await?;
+        let forward_buffer_size = self.forward_buffer_size;
         Ok((
-            Box::pin(async {
-                let _: () = utils::forward::forward_stream(upstream, downstream).await;
+            Box::pin(async move {
+                let _: () =
+                    utils::forward::forward_stream(upstream, downstream, forward_buffer_size).await;
                 Ok(())
             }),
             attestation_result,
             local_addr,
+            Some(session_id),
         ))
     }
+
+    async fn prewarm(&self, endpoint: &TngEndpoint) -> Result<()> {
+        self.security_layer.prewarm(endpoint.clone()).await
+    }
 }
 
 #[async_trait]
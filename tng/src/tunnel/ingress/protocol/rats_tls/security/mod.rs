@@ -1,24 +1,28 @@
 pub mod pool;
 
 use std::{
-    collections::HashMap,
     future::Future,
     net::SocketAddr,
     pin::Pin,
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, Weak,
     },
     task::Poll,
+    time::Duration,
 };
 
-use anyhow::{Context as _, Result};
+use anyhow::{anyhow, Context as _, Result};
 use http::Uri;
 use hyper_util::client::legacy::Client;
 use pin_project::pin_project;
-use pool::{ClientPool, HyperClientType, PoolKey};
-use tokio::sync::RwLock;
+use pool::{ClientPool, ClientPoolMetrics, HyperClientType, PoolKey, PooledSessionGuard};
+#[cfg(not(wasm))]
+use tokio::time as tokio_time;
+#[cfg(wasm)]
+use tokio_with_wasm::alias::time as tokio_time;
 use tracing::{Instrument, Span};
+use web_time_compat::{Instant, InstantExt as _};
 
 use crate::{
     tunnel::{
@@ -26,7 +30,9 @@ use crate::{
         endpoint::{EndpointAddr, TngEndpoint},
         ingress::protocol::rats_tls::wrapping::RatsTlsWrappingLayer,
         ra_context::RaContext,
+        service_metrics::{HandshakePhase, ServiceMetrics},
         utils::{
+            circuit_breaker::CircuitBreaker,
             runtime::TokioRuntime,
             rustls::config::{alpn::Alpn, TlsConfigGenerator},
             tokio::TokioIo,
@@ -35,7 +41,9 @@ use crate::{
     CommonStreamTrait,
 };
 
-use super::transport::{RatsTlsTransportLayerConnector, RatsTlsTransportLayerCreator};
+use super::transport::{
+    RatsTlsTransportLayerConnector, RatsTlsTransportLayerCreator, TransportLayerCreator,
+};
 
 #[derive(Clone)]
 pub struct RatsTlsClient {
@@ -45,38 +53,88 @@ pub struct RatsTlsClient {
 
 pub struct RatsTlsSecurityLayer {
     next_id: AtomicU64,
-    pool: RwLock<ClientPool>,
+    pool: Arc<ClientPool>,
     transport_layer_creator: RatsTlsTransportLayerCreator,
     tls_config_generator: Arc<TlsConfigGenerator>,
+    metrics: ServiceMetrics,
+    pool_metrics: ClientPoolMetrics,
     runtime: TokioRuntime,
     multiplex: bool,
+    handshake_timeout: Option<Duration>,
+    /// The ALPN protocols offered in the handshakes of the rats-tls sessions.
+    alpn_protocols: Vec<Vec<u8>>,
+    /// Whether the flow-control windows of the HTTP/2 connections are sized from the measured
+    /// bandwidth-delay product.
+    adaptive_window: bool,
 }
 
 impl RatsTlsSecurityLayer {
     pub async fn new(
-        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-        transport_so_mark: Option<u32>,
+        transport_layer_creator: Arc<dyn TransportLayerCreator>,
         ra_context: Arc<RaContext>,
+        metrics: ServiceMetrics,
         runtime: TokioRuntime,
         multiplex: bool,
+        handshake_timeout: Option<Duration>,
+        alpn: &[String],
+        adaptive_window: bool,
+        circuit_breaker: CircuitBreaker,
     ) -> Result<Self> {
-        let transport_layer_creator = RatsTlsTransportLayerCreator::new(
-            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-            transport_so_mark,
-        );
+        let transport_layer_creator =
+            RatsTlsTransportLayerCreator::new(transport_layer_creator, circuit_breaker);
         let tls_config_generator =
             Arc::new(TlsConfigGenerator::new(ra_context, runtime.clone()).await?);
 
+        let pool = Arc::new(ClientPool::new());
+        if multiplex {
+            if let Some(rotation) = tls_config_generator.subscribe_cert_rotation() {
+                Self::drain_on_cert_rotation(&runtime, rotation, Arc::downgrade(&pool));
+            }
+        }
+
         Ok(Self {
             next_id: AtomicU64::new(0),
-            pool: RwLock::new(HashMap::new()),
+            pool,
             transport_layer_creator,
             tls_config_generator,
+            pool_metrics: ClientPoolMetrics::new(&metrics),
+            metrics,
             runtime,
             multiplex,
+            handshake_timeout,
+            alpn_protocols: if multiplex {
+                Alpn::Http2
+            } else {
+                Alpn::RatsTls
+            }
+            .or_configured(alpn),
+            adaptive_window,
         })
     }
 
+    /// Drains the pool each time the local certificate is rotated, so that the new streams are
+    /// carried by sessions established with the new certificate, while the streams open on the
+    /// previous sessions are left to complete.
+    fn drain_on_cert_rotation(
+        runtime: &TokioRuntime,
+        mut rotation: tokio::sync::watch::Receiver<Arc<rustls::sign::CertifiedKey>>,
+        pool: Weak<ClientPool>,
+    ) {
+        runtime.spawn_supervised_task_current_span(async move {
+            // Stops once the certificate is not refreshed anymore, or the security layer is dropped
+            while rotation.changed().await.is_ok() {
+                let Some(pool) = pool.upgrade() else {
+                    break;
+                };
+                let drained = pool.drain().await;
+                tracing::info!(
+                    drained,
+                    "Local certificate rotated, draining the pooled rats-tls sessions"
+                );
+            }
+        });
+    }
+
     async fn create_security_connector(
         &self,
         pool_key: &PoolKey,
@@ -88,11 +146,17 @@ impl RatsTlsSecurityLayer {
         Ok(SecurityConnector {
             tls_config_generator: self.tls_config_generator.clone(),
             transport_layer_connector,
+            metrics: self.metrics.clone(),
+            pool_metrics: self.pool_metrics.clone(),
             security_layer_span: Span::current(),
+            handshake_timeout: self.handshake_timeout,
+            alpn_protocols: self.alpn_protocols.clone(),
         })
     }
 
-    async fn get_client(&self, pool_key: &PoolKey) -> Result<RatsTlsClient> {
+    /// Returns the pooled client for `pool_key`, creating it if there is none, and whether it was
+    /// in the pool already.
+    async fn get_client(&self, pool_key: &PoolKey) -> Result<(RatsTlsClient, bool)> {
         self.get_client_with_span(pool_key, Span::current())
             .instrument(tracing::info_span!(
                 "security",
@@ -105,55 +169,50 @@ impl RatsTlsSecurityLayer {
         &self,
         pool_key: &PoolKey,
         parent_span: Span,
-    ) -> Result<RatsTlsClient> {
-        // Try to get the client from pool
-        let client = {
-            let read = self.pool.read().await;
-            read.get(pool_key).cloned()
-        };
+    ) -> Result<(RatsTlsClient, bool)> {
+        let (client, reused) = self
+            .pool
+            .get_or_try_create(pool_key, || async {
+                let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+                tracing::debug!(
+                    session_id = id,
+                    "No rats-tls session found, create a new one"
+                );
+
+                // Prepare the security connector
+                let connector = self
+                    .create_security_connector(pool_key, parent_span)
+                    .await?;
 
-        let client = match client {
-            Some(c) => {
-                Span::current().record("session_id", c.id);
-                tracing::debug!(session_id = c.id, "Reuse existed rats-tls session");
-                c
-            }
-            None => {
-                // If client not exist then we need to create one
-                let mut write = self.pool.write().await;
-                // Check if client has been created by other "task"
-                match write.get(pool_key) {
-                    Some(c) => {
-                        Span::current().record("session_id", c.id);
-                        tracing::debug!(session_id = c.id, "Reuse existed rats-tls session");
-                        c.clone()
-                    }
-                    None => {
-                        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
-                        Span::current().record("session_id", id);
-                        tracing::debug!(
-                            session_id = id,
-                            "No rats-tls session found, create a new one"
-                        );
-
-                        // Prepare the security connector
-                        let connector = self
-                            .create_security_connector(pool_key, parent_span)
-                            .await?;
-
-                        // Build the hyper client from the security connector.
-                        let client = RatsTlsClient {
-                            id,
-                            hyper: Client::builder(self.runtime.clone()).build(connector),
-                        };
-                        write.insert(pool_key.to_owned(), client.clone());
-                        client
-                    }
-                }
-            }
-        };
+                // Build the hyper client from the security connector. Only HTTP/2 is spoken on
+                // the sessions, which also makes hyper establish a single session at a time for
+                // the streams waiting on it, instead of one handshake for each of them.
+                Ok(RatsTlsClient {
+                    id,
+                    hyper: hyper_client_builder(self.runtime.clone(), self.adaptive_window)
+                        .build(connector),
+                })
+            })
+            .await?;
 
-        Ok(client)
+        Span::current().record("session_id", client.id);
+        if reused {
+            tracing::debug!(session_id = client.id, "Reuse existed rats-tls session");
+        }
+        Ok((client, reused))
+    }
+
+    /// Establishes the pooled session to `endpoint` if there is none, and keeps it open with a
+    /// keep-alive probe. Does nothing if multiplexing is disabled, since the sessions are not
+    /// pooled then.
+    pub async fn prewarm(&self, endpoint: TngEndpoint) -> Result<()> {
+        if !self.multiplex {
+            return Ok(());
+        }
+        let (client, _) = self.get_client(&PoolKey::new(endpoint)).await?;
+        RatsTlsWrappingLayer::probe_hyper(&client)
+            .instrument(tracing::info_span!("wrapping", mode = "h2"))
+            .await
     }
 
     pub async fn allocate_secured_stream(
@@ -166,18 +225,28 @@ impl RatsTlsSecurityLayer {
         /* session_id */ u64,
     )> {
         if !self.multiplex {
-            let (stream, local_addr, att, session_id) = RatsTlsWrappingLayer::create_stream_raw(
-                &self.transport_layer_creator,
-                &self.tls_config_generator,
-                &endpoint,
-                &self.runtime,
-            )
-            .instrument(tracing::info_span!("wrapping", mode = "rats-tls"))
-            .await?;
+            let (stream, local_addr, att, session_id) =
+                with_handshake_timeout(self.handshake_timeout, &self.pool_metrics, async {
+                    RatsTlsWrappingLayer::create_stream_raw(
+                        &self.transport_layer_creator,
+                        &self.tls_config_generator,
+                        &endpoint,
+                        &self.metrics,
+                        self.alpn_protocols.clone(),
+                        &self.runtime,
+                    )
+                    .await
+                    .inspect_err(|_| self.pool_metrics.handshake_failed())
+                })
+                .instrument(tracing::info_span!("wrapping", mode = "rats-tls"))
+                .await?;
             Ok((Box::new(stream), local_addr, att, session_id))
         } else {
             let pool_key = PoolKey::new(endpoint);
-            let client = self.get_client(&pool_key).await?;
+            let (client, reused) = self.get_client(&pool_key).await?;
+            if reused {
+                self.pool_metrics.session_reused();
+            }
             let (stream, local_addr, att, session_id) =
                 RatsTlsWrappingLayer::create_stream_from_hyper(&client)
                     .instrument(tracing::info_span!("wrapping", mode = "h2"))
@@ -187,11 +256,49 @@ impl RatsTlsSecurityLayer {
     }
 }
 
+/// Fails `future` if it does not establish the session within `handshake_timeout`, instead of
+/// leaving the streams waiting on a peer which accepted the connection but never completes the
+/// handshake. Only the timeout is counted as a failed handshake here.
+async fn with_handshake_timeout<T>(
+    handshake_timeout: Option<Duration>,
+    pool_metrics: &ClientPoolMetrics,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let Some(handshake_timeout) = handshake_timeout else {
+        return future.await;
+    };
+    tokio_time::timeout(handshake_timeout, future)
+        .await
+        .map_err(|_| {
+            pool_metrics.handshake_failed();
+            anyhow!(
+                "Timed out establishing the rats-tls session after {}s",
+                handshake_timeout.as_secs()
+            )
+        })?
+}
+
+/// Builds the hyper clients speaking HTTP/2 on the rats-tls sessions.
+fn hyper_client_builder(
+    runtime: TokioRuntime,
+    adaptive_window: bool,
+) -> hyper_util::client::legacy::Builder {
+    let mut builder = Client::builder(runtime);
+    builder
+        .http2_only(true)
+        .http2_adaptive_window(adaptive_window);
+    builder
+}
+
 #[derive(Clone)]
 pub struct SecurityConnector {
     tls_config_generator: Arc<TlsConfigGenerator>,
     transport_layer_connector: RatsTlsTransportLayerConnector,
+    metrics: ServiceMetrics,
+    pool_metrics: ClientPoolMetrics,
     security_layer_span: Span,
+    handshake_timeout: Option<Duration>,
+    alpn_protocols: Vec<Vec<u8>>,
 }
 
 impl SecurityConnector {}
@@ -214,49 +321,78 @@ impl tower::Service<Uri> for SecurityConnector {
     fn call(&mut self, uri: Uri /* Not use this as destination endpoint */) -> Self::Future {
         let tls_config_generator = self.tls_config_generator.clone();
         let mut transport_layer_connector = self.transport_layer_connector.clone();
+        let metrics = self.metrics.clone();
+        let pool_metrics = self.pool_metrics.clone();
+        let handshake_timeout = self.handshake_timeout;
+        let alpn_protocols = self.alpn_protocols.clone();
+        let timeout_pool_metrics = pool_metrics.clone();
+        let connect = async move {
+            let tls_client_config = tls_config_generator
+                .get_lazy_one_time_rustls_client_config(Alpn::Http2)
+                .await?
+                .with_alpn_protocols(alpn_protocols);
+
+            let transport_started_at = Instant::get();
+            let transport_layer_stream = transport_layer_connector
+                .call(uri.clone())
+                .await
+                .inspect_err(|_| pool_metrics.handshake_failed())?;
+            metrics
+                .record_handshake_phase(HandshakePhase::Transport, transport_started_at.elapsed());
+
+            tracing::debug!("Creating rats-tls connection");
+            async {
+                let host = uri.host().context("Host is empty")?;
+                // Model the URI host as an `EndpointAddr` so the TLS
+                // handshake can build a `ServerName` without formatting a
+                // string for the IPv4 case.
+                let server_name = EndpointAddr::from_host(host);
+                let handshake_started_at = Instant::get();
+                let (security_layer_stream, attestation_result) = tls_client_config
+                    .handshake_with_stream(&server_name, transport_layer_stream.stream, &metrics)
+                    .await
+                    .inspect_err(|_| pool_metrics.handshake_failed())?;
+                metrics.record_handshake(handshake_started_at.elapsed());
+
+                tracing::debug!("New rats-tls connection established");
+                Ok::<_, anyhow::Error>(
+                    StreamWithAttestationResult::wrap_with_attestation_result(
+                        TokioIo::new(security_layer_stream),
+                        attestation_result,
+                    )
+                    .with_local_addr(transport_layer_stream.local_addr)
+                    .with_pooled_session(pool_metrics.session_created()),
+                )
+            }
+            .await
+            .context("Failed to establish rats-tls connection as client")
+        };
         Box::pin(
             async move {
-                let tls_client_config = tls_config_generator
-                    .get_lazy_one_time_rustls_client_config(Alpn::Http2)
-                    .await?;
-
-                let transport_layer_stream = transport_layer_connector.call(uri.clone()).await?;
-
-                tracing::debug!("Creating rats-tls connection");
-                async {
-                    let host = uri.host().context("Host is empty")?;
-                    // Model the URI host as an `EndpointAddr` so the TLS
-                    // handshake can build a `ServerName` without formatting a
-                    // string for the IPv4 case.
-                    let server_name = EndpointAddr::from_host(host);
-                    let (security_layer_stream, attestation_result) = tls_client_config
-                        .handshake_with_stream(&server_name, transport_layer_stream.into_inner())
-                        .await?;
-
-                    tracing::debug!("New rats-tls connection established");
-                    Ok::<_, anyhow::Error>(
-                        StreamWithAttestationResult::wrap_with_attestation_result(
-                            TokioIo::new(security_layer_stream),
-                            attestation_result,
-                        ),
-                    )
-                }
-                .await
-                .context("Failed to establish rats-tls connection as client")
+                with_handshake_timeout(handshake_timeout, &timeout_pool_metrics, connect).await
             }
             .instrument(self.security_layer_span.clone()),
         )
     }
 }
 
-pub type RatsTlsConnection =
-    StreamWithAttestationResult<TokioIo<tokio_rustls::client::TlsStream<tokio::net::TcpStream>>>;
+pub type RatsTlsConnection = StreamWithAttestationResult<
+    TokioIo<tokio_rustls::client::TlsStream<Box<dyn CommonStreamTrait + Sync>>>,
+>;
+
+/// The local address of the transport layer stream of a connection, which is carried in the
+/// extensions of the responses.
+#[derive(Debug, Clone, Copy)]
+pub struct TransportLocalAddr(pub Option<SocketAddr>);
 
 #[pin_project]
 pub struct StreamWithAttestationResult<T> {
     #[pin]
     inner: T,
     attestation_result: Option<AttestationResult>,
+    local_addr: Option<SocketAddr>,
+    /// Counts the session in the pool metrics until the connection is closed.
+    pooled_session: Option<PooledSessionGuard>,
 }
 
 impl<T> StreamWithAttestationResult<T> {
@@ -267,19 +403,34 @@ impl<T> StreamWithAttestationResult<T> {
         Self {
             inner,
             attestation_result,
+            local_addr: None,
+            pooled_session: None,
         }
     }
+
+    pub fn with_local_addr(mut self, local_addr: Option<SocketAddr>) -> Self {
+        self.local_addr = local_addr;
+        self
+    }
+
+    pub fn with_pooled_session(mut self, guard: PooledSessionGuard) -> Self {
+        self.pooled_session = Some(guard);
+        self
+    }
 }
 
 impl hyper_util::client::legacy::connect::Connection for RatsTlsConnection {
     fn connected(&self) -> hyper_util::client::legacy::connect::Connected {
-        let (tcp, tls) = self.inner.inner().get_ref();
+        let (_, tls) = self.inner.inner().get_ref();
+        let connected = hyper_util::client::legacy::connect::Connected::new();
         let connected = if tls.alpn_protocol() == Some(b"h2") {
-            tcp.connected().negotiated_h2()
+            connected.negotiated_h2()
         } else {
-            tcp.connected()
+            connected
         };
-        connected.extra(self.attestation_result.clone())
+        connected
+            .extra(self.attestation_result.clone())
+            .extra(TransportLocalAddr(self.local_addr))
     }
 }
 
@@ -338,3 +489,97 @@ impl<T: hyper::rt::Write + hyper::rt::Read + Unpin> hyper::rt::Write
         self.project().inner.poll_write_vectored(cx, bufs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use anyhow::bail;
+    use hyper_util::client::legacy::connect::{Connected, Connection};
+    use tokio::io::AsyncReadExt as _;
+
+    use super::*;
+    use crate::tests::read_h2_initial_window_size;
+
+    /// A plain connection to the peer, without a rats-tls session.
+    struct PlainConnection(TokioIo<tokio::io::DuplexStream>);
+
+    impl Connection for PlainConnection {
+        fn connected(&self) -> Connected {
+            Connected::new()
+        }
+    }
+
+    impl hyper::rt::Read for PlainConnection {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: hyper::rt::ReadBufCursor<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            hyper::rt::Read::poll_read(Pin::new(&mut self.get_mut().0), cx, buf)
+        }
+    }
+
+    impl hyper::rt::Write for PlainConnection {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            hyper::rt::Write::poll_write(Pin::new(&mut self.get_mut().0), cx, buf)
+        }
+
+        fn poll_flush(
+            self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            hyper::rt::Write::poll_flush(Pin::new(&mut self.get_mut().0), cx)
+        }
+
+        fn poll_shutdown(
+            self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            hyper::rt::Write::poll_shutdown(Pin::new(&mut self.get_mut().0), cx)
+        }
+    }
+
+    /// Sends a request with a hyper client built as for the rats-tls sessions, and returns the
+    /// initial window size it advertises to the egress.
+    async fn advertised_window_size(adaptive_window: bool) -> Result<Option<u32>> {
+        let shutdown = tokio_graceful::Shutdown::no_signal();
+        let (client, mut server) = tokio::io::duplex(4096);
+        let client = Arc::new(Mutex::new(Some(client)));
+        let connector = tower::service_fn(move |_: Uri| {
+            let client = client.lock().unwrap_or_else(|p| p.into_inner()).take();
+            Box::pin(async move {
+                let client = client.context("Only one connection is expected")?;
+                Ok::<_, anyhow::Error>(PlainConnection(TokioIo::new(client)))
+            })
+        });
+        let hyper = hyper_client_builder(TokioRuntime::current(shutdown.guard())?, adaptive_window)
+            .build::<_, http_body_util::Empty<bytes::Bytes>>(connector);
+        let request =
+            hyper.request(http::Request::get("http://tng.test/").body(Default::default())?);
+
+        let read = async {
+            // The connection preface of the client
+            let mut preface = [0u8; 24];
+            server.read_exact(&mut preface).await?;
+            read_h2_initial_window_size(&mut server).await
+        };
+        tokio::select! {
+            result = read => result,
+            _ = request => bail!("The request completed before the SETTINGS frame is read"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_window() -> Result<()> {
+        // The windows start from the size defined by the spec and grow with the measured
+        // bandwidth-delay product, instead of the fixed default ones
+        assert_eq!(advertised_window_size(true).await?, Some(65535));
+        assert_ne!(advertised_window_size(false).await?, Some(65535));
+        Ok(())
+    }
+}
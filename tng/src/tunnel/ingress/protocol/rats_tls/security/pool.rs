@@ -1,16 +1,107 @@
 use http_body_util::combinators::BoxBody;
 use hyper_util::client::legacy::Client;
+use opentelemetry::metrics::{Counter, UpDownCounter};
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
 use std::convert::Infallible;
-use std::hash::Hash;
+use std::future::Future;
+use std::hash::{BuildHasher as _, Hash};
+use std::sync::Arc;
+use tokio::sync::{OnceCell, RwLock};
 
+use crate::observability::metric::counter::{AttributedCounter, WithAttributes};
 use crate::tunnel::endpoint::TngEndpoint;
+use crate::tunnel::service_metrics::ServiceMetrics;
 
 use super::{RatsTlsClient, SecurityConnector};
 
 pub type HyperClientType = Client<SecurityConnector, BoxBody<bytes::Bytes, Infallible>>;
 
-pub type ClientPool = HashMap<PoolKey, RatsTlsClient>;
+/// The number of shards of the pool, so that the lookups of the sessions to different endpoints
+/// do not contend on the same lock.
+const POOL_SHARDS: usize = 16;
+
+type ClientPoolShard = RwLock<HashMap<PoolKey, Arc<OnceCell<RatsTlsClient>>>>;
+
+/// The clients of the pooled sessions, by the endpoint they connect to.
+///
+/// A client is created only once for each endpoint: the tasks asking for an endpoint which has no
+/// client yet wait for the one creating it, instead of creating their own.
+pub struct ClientPool {
+    shards: Vec<ClientPoolShard>,
+    hasher: RandomState,
+}
+
+impl ClientPool {
+    pub fn new() -> Self {
+        Self {
+            shards: (0..POOL_SHARDS).map(|_| Default::default()).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard(&self, pool_key: &PoolKey) -> &ClientPoolShard {
+        let index = self.hasher.hash_one(pool_key) as usize % self.shards.len();
+        &self.shards[index]
+    }
+
+    /// Returns the client for `pool_key`, creating it with `create` if there is none, and whether
+    /// it existed already.
+    pub async fn get_or_try_create<F, Fut>(
+        &self,
+        pool_key: &PoolKey,
+        create: F,
+    ) -> anyhow::Result<(RatsTlsClient, bool)>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = anyhow::Result<RatsTlsClient>>,
+    {
+        let shard = self.shard(pool_key);
+
+        // Try to read the entry, and only take the write lock to insert an uninitialized one
+        let cell = {
+            let read = shard.read().await;
+            read.get(pool_key).cloned()
+        };
+        let cell = match cell {
+            Some(cell) => cell,
+            None => shard
+                .write()
+                .await
+                .entry(pool_key.to_owned())
+                .or_default()
+                .clone(),
+        };
+
+        let mut created = false;
+        let client = cell
+            .get_or_try_init(|| async {
+                created = true;
+                create().await
+            })
+            .await?;
+        Ok((client.clone(), !created))
+    }
+
+    /// Removes all the clients from the pool, so that the next streams establish new sessions,
+    /// and returns how many were removed. The streams open on the sessions of the removed clients
+    /// are not interrupted, and each of these sessions is closed once its last stream is.
+    pub async fn drain(&self) -> usize {
+        let mut drained = 0;
+        for shard in &self.shards {
+            let mut shard = shard.write().await;
+            drained += shard.len();
+            shard.clear();
+        }
+        drained
+    }
+}
+
+impl Default for ClientPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[derive(PartialEq, Eq, Hash, Clone)]
 pub struct PoolKey {
@@ -27,3 +118,100 @@ impl PoolKey {
         &self.endpoint
     }
 }
+
+/// Metrics of the rats-tls sessions pooled for multiplexing, to tune the multiplexing and to
+/// diagnose the churn of the sessions.
+///
+/// This struct is free to be cloned and used anywhere.
+#[derive(Debug, Clone)]
+pub struct ClientPoolMetrics {
+    pool_size: AttributedCounter<UpDownCounter<i64>, i64>,
+    session_created_total: AttributedCounter<Counter<u64>, u64>,
+    session_reused_total: AttributedCounter<Counter<u64>, u64>,
+    session_evicted_total: AttributedCounter<Counter<u64>, u64>,
+    handshake_failed_total: AttributedCounter<Counter<u64>, u64>,
+}
+
+impl ClientPoolMetrics {
+    pub fn new(service_metrics: &ServiceMetrics) -> Self {
+        let meter = service_metrics.meter();
+        let attributes = service_metrics.attributes();
+
+        let pool_size = meter
+            .i64_up_down_counter("rats_tls_pool_size")
+            .with_description("The number of rats-tls sessions open in the pool")
+            .build()
+            .with_attributes(attributes.clone());
+        pool_size.add(0);
+
+        let session_created_total = meter
+            .u64_counter("rats_tls_session_created_total")
+            .with_description("Total number of rats-tls sessions established")
+            .build()
+            .with_attributes(attributes.clone());
+        session_created_total.add(0);
+
+        let session_reused_total = meter
+            .u64_counter("rats_tls_session_reused_total")
+            .with_description("Total number of streams opened on a pooled rats-tls session")
+            .build()
+            .with_attributes(attributes.clone());
+        session_reused_total.add(0);
+
+        let session_evicted_total = meter
+            .u64_counter("rats_tls_session_evicted_total")
+            .with_description(
+                "Total number of rats-tls sessions removed from the pool, e.g. closed by the peer or after being idle",
+            )
+            .build()
+            .with_attributes(attributes.clone());
+        session_evicted_total.add(0);
+
+        let handshake_failed_total = meter
+            .u64_counter("rats_tls_handshake_failed_total")
+            .with_description("Total number of failures to establish a rats-tls session")
+            .build()
+            .with_attributes(attributes.clone());
+        handshake_failed_total.add(0);
+
+        Self {
+            pool_size,
+            session_created_total,
+            session_reused_total,
+            session_evicted_total,
+            handshake_failed_total,
+        }
+    }
+
+    /// Record a new session, which is counted in the pool until the returned guard is dropped.
+    pub fn session_created(&self) -> PooledSessionGuard {
+        self.session_created_total.add(1);
+        self.pool_size.add(1);
+        PooledSessionGuard {
+            pool_size: self.pool_size.clone(),
+            session_evicted_total: self.session_evicted_total.clone(),
+        }
+    }
+
+    pub fn session_reused(&self) {
+        self.session_reused_total.add(1);
+    }
+
+    pub fn handshake_failed(&self) {
+        self.handshake_failed_total.add(1);
+    }
+}
+
+/// Held by a pooled session, and records its eviction from the pool when dropped.
+#[derive(Debug)]
+pub struct PooledSessionGuard {
+    pool_size: AttributedCounter<UpDownCounter<i64>, i64>,
+    session_evicted_total: AttributedCounter<Counter<u64>, u64>,
+}
+
+impl Drop for PooledSessionGuard {
+    fn drop(&mut self) {
+        self.pool_size.add(-1);
+        self.session_evicted_total.add(1);
+    }
+}
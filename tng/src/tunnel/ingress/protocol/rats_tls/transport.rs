@@ -1,22 +1,51 @@
 use std::{
     future::Future,
+    net::SocketAddr,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
 use anyhow::{Context as _, Result};
+use async_trait::async_trait;
 use tracing::{Instrument, Span};
 
 use super::security::pool::PoolKey;
-use crate::tunnel::utils::tokio::TokioIo;
+use crate::{
+    tunnel::{endpoint::TngEndpoint, resolver::Resolver, utils::circuit_breaker::CircuitBreaker},
+    CommonStreamTrait,
+};
 
-/// The transport layer creator is used to create the transport layer.
-pub struct RatsTlsTransportLayerCreator {
+/// Opens the streams which the rats-TLS sessions of an ingress are established over, i.e. the
+/// transport layer under the security layer.
+///
+/// The built-in [`TcpTransportLayerCreator`] connects to the upstream with TCP. Library users can
+/// implement this trait to carry the sessions over something else, e.g. a message bus or a serial
+/// link, and set it as the `transport` of the `rats_tls` options of an ingress. The stream only
+/// has to deliver the bytes in order: the rats-TLS handshake and the encryption are done on top
+/// of it by TNG.
+#[async_trait]
+pub trait TransportLayerCreator: Send + Sync + 'static {
+    /// Opens a new stream to `endpoint`, the upstream the ingress forwards to.
+    async fn connect(&self, endpoint: &TngEndpoint) -> Result<TransportLayerStream>;
+}
+
+/// A stream opened by a [`TransportLayerCreator`].
+pub struct TransportLayerStream {
+    pub stream: Box<dyn CommonStreamTrait + Sync>,
+    /// The local address of the stream, reported in the access log, if there is one.
+    pub local_addr: Option<SocketAddr>,
+}
+
+/// Connects to the upstream with TCP, which is the transport layer used unless another one is
+/// set.
+pub struct TcpTransportLayerCreator {
     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
     so_mark: Option<u32>,
+    resolver: Option<Arc<dyn Resolver>>,
 }
 
-impl RatsTlsTransportLayerCreator {
+impl TcpTransportLayerCreator {
     pub fn new(
         #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
         so_mark: Option<u32>,
@@ -24,6 +53,48 @@ impl RatsTlsTransportLayerCreator {
         Self {
             #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
             so_mark,
+            resolver: None,
+        }
+    }
+
+    /// Resolves the domain names of the upstreams with `resolver`, instead of the resolver of
+    /// the system.
+    pub fn with_resolver(mut self, resolver: Option<Arc<dyn Resolver>>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+}
+
+#[async_trait]
+impl TransportLayerCreator for TcpTransportLayerCreator {
+    async fn connect(&self, endpoint: &TngEndpoint) -> Result<TransportLayerStream> {
+        let tcp_stream = endpoint
+            .tcp_connect(
+                #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                self.so_mark,
+                self.resolver.as_deref(),
+            )
+            .await?;
+        let local_addr = tcp_stream.local_addr().ok();
+
+        Ok(TransportLayerStream {
+            stream: Box::new(tcp_stream),
+            local_addr,
+        })
+    }
+}
+
+/// The transport layer creator is used to create the transport layer.
+pub struct RatsTlsTransportLayerCreator {
+    inner: Arc<dyn TransportLayerCreator>,
+    circuit_breaker: CircuitBreaker,
+}
+
+impl RatsTlsTransportLayerCreator {
+    pub fn new(inner: Arc<dyn TransportLayerCreator>, circuit_breaker: CircuitBreaker) -> Self {
+        Self {
+            inner,
+            circuit_breaker,
         }
     }
 }
@@ -36,8 +107,8 @@ impl RatsTlsTransportLayerCreator {
     ) -> Result<RatsTlsTransportLayerConnector> {
         Ok(RatsTlsTransportLayerConnector {
             pool_key: pool_key.clone(),
-            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-            so_mark: self.so_mark,
+            inner: self.inner.clone(),
+            circuit_breaker: self.circuit_breaker.clone(),
             transport_layer_span: tracing::info_span!(parent: parent_span, "transport", type = "rats-tls"),
         })
     }
@@ -47,13 +118,13 @@ impl RatsTlsTransportLayerCreator {
 #[derive(Clone)]
 pub struct RatsTlsTransportLayerConnector {
     pub pool_key: PoolKey,
-    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-    pub so_mark: Option<u32>,
+    pub inner: Arc<dyn TransportLayerCreator>,
+    pub circuit_breaker: CircuitBreaker,
     pub transport_layer_span: Span,
 }
 
 impl<Req> tower::Service<Req> for RatsTlsTransportLayerConnector {
-    type Response = TokioIo<tokio::net::TcpStream>;
+    type Response = TransportLayerStream;
     type Error = anyhow::Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
@@ -62,25 +133,114 @@ impl<Req> tower::Service<Req> for RatsTlsTransportLayerConnector {
     }
 
     fn call(&mut self, _: Req) -> Self::Future {
-        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-        let so_mark = self.so_mark;
+        let inner = self.inner.clone();
         let dst = self.pool_key.get_endpoint().to_owned();
+        let circuit_breaker = self.circuit_breaker.clone();
 
         let fut = async move {
-            tracing::debug!("Establishing the underlying tcp connection with upstream");
+            tracing::debug!("Establishing the underlying transport connection with upstream");
 
-            let tcp_stream = dst
-                .tcp_connect(
-                    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-                    so_mark,
-                )
+            circuit_breaker
+                .connect(&dst, inner.connect(&dst))
                 .await
-                .context("Failed to establish the underlying tcp connection for rats-tls")?;
-
-            Ok(TokioIo::new(tcp_stream))
+                .context("Failed to establish the underlying transport connection for rats-tls")
         }
         .instrument(self.transport_layer_span.clone());
 
         Box::pin(fut)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use crate::{config::TngConfig, runtime::TngRuntime};
+
+    use super::*;
+
+    /// Counts the streams opened, and opens them with TCP.
+    struct CountingTransportLayerCreator {
+        inner: TcpTransportLayerCreator,
+        count: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl TransportLayerCreator for CountingTransportLayerCreator {
+        async fn connect(&self, endpoint: &TngEndpoint) -> Result<TransportLayerStream> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            self.inner.connect(endpoint).await
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_custom_transport_layer() -> Result<()> {
+        let ingress_port = portpicker::pick_unused_port().unwrap();
+        let egress_port = portpicker::pick_unused_port().unwrap();
+
+        let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let upstream_port = upstream.local_addr()?.port();
+        #[allow(clippy::disallowed_methods)]
+        tokio::task::spawn(async move {
+            while let Ok((mut stream, _)) = upstream.accept().await {
+                let (mut reader, mut writer) = stream.split();
+                let _ = tokio::io::copy(&mut reader, &mut writer).await;
+            }
+        });
+
+        let mut config: TngConfig = serde_json::from_value(json!({
+            "add_ingress": [
+                {
+                    "mapping": {
+                        "in": { "port": ingress_port },
+                        "out": { "host": "127.0.0.1", "port": egress_port }
+                    },
+                    "no_ra": true
+                }
+            ],
+            "add_egress": [
+                {
+                    "mapping": {
+                        "in": { "host": "127.0.0.1", "port": egress_port },
+                        "out": { "host": "127.0.0.1", "port": upstream_port }
+                    },
+                    "no_ra": true
+                }
+            ]
+        }))?;
+        let count = Arc::new(AtomicUsize::new(0));
+        config.add_ingress[0].common.rats_tls = Some(crate::config::ingress::RatsTlsArgs {
+            transport: Some(Arc::new(CountingTransportLayerCreator {
+                inner: TcpTransportLayerCreator::new(
+                    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                    None,
+                ),
+                count: count.clone(),
+            })),
+            ..Default::default()
+        });
+
+        let (ready_sender, ready_receiver) = tokio::sync::oneshot::channel();
+        let tng_runtime = TngRuntime::from_config(config).await?;
+        let canceller = tng_runtime.canceller();
+        #[allow(clippy::disallowed_methods)]
+        let join_handle =
+            tokio::task::spawn(async move { tng_runtime.serve_with_ready(ready_sender).await });
+        ready_receiver.await?;
+
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", ingress_port)).await?;
+        stream.write_all(b"hello").await?;
+        stream.shutdown().await?;
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await?;
+        assert_eq!(response, b"hello");
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        canceller.cancel();
+        join_handle.await??;
+        Ok(())
+    }
+}
@@ -4,14 +4,16 @@ use anyhow::{bail, Context as _, Result};
 use http::{Request, StatusCode, Version};
 use http_body_util::combinators::BoxBody;
 use tower::Service;
+use web_time_compat::{Instant, InstantExt as _};
 
-use super::security::RatsTlsClient;
+use super::security::{RatsTlsClient, TransportLocalAddr};
 use super::transport::RatsTlsTransportLayerCreator;
 use crate::{
     tunnel::{
         attestation_result::AttestationResult,
         endpoint::TngEndpoint,
         ingress::protocol::rats_tls::security::pool::PoolKey,
+        service_metrics::{HandshakePhase, ServiceMetrics},
         utils::{
             self,
             runtime::TokioRuntime,
@@ -63,11 +65,10 @@ impl RatsTlsWrappingLayer {
             );
         }
 
-        let local_addr = resp
+        let TransportLocalAddr(local_addr) = *resp
             .extensions()
-            .get::<hyper_util::client::legacy::connect::HttpInfo>()
-            .context("Can not get local addr")?
-            .local_addr();
+            .get::<TransportLocalAddr>()
+            .context("Can not get local addr")?;
 
         let upgraded = hyper::upgrade::on(&mut resp)
             .await
@@ -82,7 +83,33 @@ impl RatsTlsWrappingLayer {
             "Trusted tunnel established (H2 upgrade OK)"
         );
 
-        Ok((stream, Some(local_addr), attestation_result, client.id))
+        Ok((stream, local_addr, attestation_result, client.id))
+    }
+
+    /// Sends a keep-alive probe on the pooled session, which establishes the session if there is
+    /// none, and keeps it from being closed for being idle. No stream is opened to the upstream.
+    pub async fn probe_hyper(client: &RatsTlsClient) -> Result<()> {
+        let req = Request::options("https://tng.internal/")
+            .version(Version::HTTP_2)
+            .body(BoxBody::new(http_body_util::Empty::new()))?;
+
+        tracing::debug!(session_id = client.id, "Sending keep-alive probe");
+
+        let resp = client
+            .hyper
+            .request(req)
+            .await
+            .context("Failed to send keep-alive probe")?;
+
+        // The egresses which do not know the probe reject it, but the session is open anyway
+        if resp.status() != StatusCode::OK {
+            tracing::debug!(
+                session_id = client.id,
+                status = %resp.status(),
+                "Keep-alive probe rejected by the egress"
+            );
+        }
+        Ok(())
     }
 
     /// Create a direct TLS stream without HTTP/2 CONNECT tunneling.
@@ -91,6 +118,8 @@ impl RatsTlsWrappingLayer {
         transport_layer_creator: &RatsTlsTransportLayerCreator,
         tls_config_generator: &TlsConfigGenerator,
         endpoint: &TngEndpoint,
+        metrics: &ServiceMetrics,
+        alpn_protocols: Vec<Vec<u8>>,
         _runtime: &TokioRuntime,
     ) -> Result<(
         impl CommonStreamTrait + Sync,
@@ -105,19 +134,23 @@ impl RatsTlsWrappingLayer {
 
         let tls_client_config = tls_config_generator
             .get_lazy_one_time_rustls_client_config(Alpn::RatsTls)
-            .await?;
+            .await?
+            .with_alpn_protocols(alpn_protocols);
 
-        let tcp_stream: tokio::net::TcpStream = connector
+        let transport_started_at = Instant::get();
+        let transport_layer_stream = connector
             .call(http::Request::new(()))
             .await
-            .context("Failed to establish TCP connection for rats-tls")?
-            .into_inner();
+            .context("Failed to establish transport connection for rats-tls")?;
+        metrics.record_handshake_phase(HandshakePhase::Transport, transport_started_at.elapsed());
 
-        let local_addr = tcp_stream.local_addr().ok();
+        let local_addr = transport_layer_stream.local_addr;
 
+        let handshake_started_at = Instant::get();
         let (tls_stream, attestation_result) = tls_client_config
-            .handshake_with_stream(endpoint.addr(), tcp_stream)
+            .handshake_with_stream(endpoint.addr(), transport_layer_stream.stream, metrics)
             .await?;
+        metrics.record_handshake(handshake_started_at.elapsed());
 
         tracing::debug!("Rats-TLS tunnel established");
 
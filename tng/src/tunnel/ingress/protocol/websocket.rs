@@ -0,0 +1,133 @@
+//! Forwarding of the HTTP requests of `tng-wasm` through rats-TLS sessions carried inside
+//! WebSocket connections, since a browser cannot open raw TCP connections. The egress decodes
+//! them when its `rats_tls.websocket` is set.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context as _, Result};
+use tokio_util::compat::FuturesAsyncReadCompatExt as _;
+use ws_stream_wasm::WsMeta;
+
+use crate::{
+    config::websocket::WebSocketArgs,
+    tunnel::{
+        endpoint::TngEndpoint,
+        utils::rustls::config::{alpn::Alpn, TlsConfigGenerator},
+    },
+    AttestationResult, RaContext, TokioIo, TokioRuntime,
+};
+
+pub struct WebSocketSecurityLayer {
+    path: String,
+    /// `ws` or `wss`, see [`Self::scheme_from_url`].
+    scheme: &'static str,
+    tls_config_generator: TlsConfigGenerator,
+    runtime: TokioRuntime,
+}
+
+impl WebSocketSecurityLayer {
+    pub async fn new(
+        websocket_args: &WebSocketArgs,
+        url_scheme: &'static str,
+        ra_context: Arc<RaContext>,
+        runtime: TokioRuntime,
+    ) -> Result<Self> {
+        websocket_args.validate()?;
+
+        Ok(Self {
+            path: websocket_args.path.clone(),
+            scheme: url_scheme,
+            tls_config_generator: TlsConfigGenerator::new(ra_context, runtime.clone()).await?,
+            runtime,
+        })
+    }
+
+    /// Derive the scheme of the WebSocket URL from the scheme of the URL the caller passed to the
+    /// wasm `fetch` interface. `Some("https")` ⇒ `wss`, anything else ⇒ `ws`.
+    pub fn scheme_from_url(scheme: Option<&str>) -> &'static str {
+        match scheme {
+            Some("https") => "wss",
+            _ => "ws",
+        }
+    }
+
+    /// Establishes a new rats-TLS session to `endpoint`, over which HTTP requests can then be
+    /// sent.
+    pub async fn connect(&self, endpoint: &TngEndpoint) -> Result<WebSocketSession> {
+        let url = format!(
+            "{}://{}{}",
+            self.scheme,
+            endpoint.http_authority(),
+            self.path
+        );
+        tracing::debug!(url, "Connecting to the upstream with WebSocket");
+        let (_, websocket) = WsMeta::connect(&url, None)
+            .await
+            .map_err(|error| anyhow!("Failed to connect to {url}: {error}"))?;
+
+        let (tls_stream, attestation_result) = self
+            .tls_config_generator
+            .get_lazy_one_time_rustls_client_config(Alpn::RatsTls)
+            .await?
+            .handshake_with_stream(endpoint.addr(), websocket.into_io().compat())
+            .await?;
+        tracing::debug!("New rats-tls connection established");
+
+        let (send_request, connection) =
+            hyper::client::conn::http1::handshake(TokioIo::new(tls_stream))
+                .await
+                .context("Failed to establish HTTP connection to upstream")?;
+        self.runtime.spawn_supervised_task_current_span(async move {
+            if let Err(error) = connection.await {
+                tracing::debug!(
+                    ?error,
+                    "The HTTP connection to upstream is closed with error"
+                );
+            }
+        });
+
+        Ok(WebSocketSession {
+            send_request,
+            attestation_result,
+        })
+    }
+}
+
+/// A rats-TLS session carried inside a WebSocket connection, which can be kept to send the
+/// following requests to the same upstream, as long as the upstream keeps the HTTP connection
+/// alive.
+pub struct WebSocketSession {
+    send_request: hyper::client::conn::http1::SendRequest<axum::body::Body>,
+    attestation_result: Option<AttestationResult>,
+}
+
+impl WebSocketSession {
+    pub fn attestation_result(&self) -> Option<&AttestationResult> {
+        self.attestation_result.as_ref()
+    }
+
+    /// Whether a request can be sent right now, i.e. the response to the previous one has been
+    /// read entirely.
+    pub fn is_ready(&self) -> bool {
+        self.send_request.is_ready()
+    }
+
+    /// Whether the HTTP connection has been closed, by either side.
+    pub fn is_closed(&self) -> bool {
+        self.send_request.is_closed()
+    }
+
+    /// Sends `request` over this session, and returns the response.
+    pub async fn send_http_request(
+        &mut self,
+        request: axum::extract::Request,
+    ) -> Result<axum::response::Response> {
+        let response = self
+            .send_request
+            .send_request(request)
+            .await
+            .context("Failed to send HTTP request to upstream")?;
+
+        Ok(response.map(axum::body::Body::new))
+    }
+}
@@ -1,3 +1,5 @@
+use std::fs::File;
+use std::io::BufReader;
 use std::sync::Arc;
 
 use anyhow::{bail, Context, Result};
@@ -6,16 +8,20 @@ use async_trait::async_trait;
 use fast_socks5::server::Socks5ServerProtocol;
 use futures::StreamExt;
 use indexmap::IndexMap;
-use tokio::net::{TcpListener, TcpStream};
+use rustls::ServerConfig;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::TlsAcceptor;
 
-use crate::config::ingress::{IngressSocks5Args, Socks5AuthArgs};
+use crate::config::ingress::{IngressSocks5Args, Socks5AuthArgs, Socks5TlsArgs};
 use crate::tunnel::access_log::{AccessAccepted, IngressAccessMode};
 use crate::tunnel::endpoint::TngEndpoint;
 use crate::tunnel::ingress::flow::AcceptedStream;
+use crate::tunnel::stream::CommonStreamTrait;
 use crate::tunnel::utils::endpoint_matcher::EndpointMatcher;
 use crate::tunnel::utils::runtime::TokioRuntime;
-use crate::tunnel::utils::socket::SetListenerSockOpts;
+use crate::tunnel::utils::socket::{bind_tcp_listener, SetListenerSockOpts};
 
+use super::flow::source_filter::SourceFilter;
 use super::flow::stream_router::StreamRouter;
 use super::flow::{Incomming, IngressTrait};
 
@@ -27,7 +33,9 @@ pub struct Socks5Ingress {
     listen_addr: String,
     listen_port: u16,
     auth: Arc<Option<Socks5AuthArgs>>,
+    tls_acceptor: Option<TlsAcceptor>,
     stream_router: Arc<StreamRouter>,
+    source_filter: SourceFilter,
 }
 
 impl Socks5Ingress {
@@ -44,20 +52,50 @@ impl Socks5Ingress {
             &socks5_args.dst_filters,
         )?));
 
+        // Load the certificate at startup, so that a broken one is not only noticed by the clients
+        let tls_acceptor = socks5_args
+            .tls
+            .as_ref()
+            .map(load_tls_acceptor)
+            .transpose()
+            .context("Failed to load the TLS certificate of the socks5 ingress")?;
+
         Ok(Self {
             id,
             listen_addr,
             listen_port,
             auth: Arc::new(socks5_args.auth.clone()),
+            tls_acceptor,
             stream_router,
+            source_filter: SourceFilter::default(),
         })
     }
 }
 
-async fn serve_socks5(
-    in_stream: TcpStream,
+fn load_tls_acceptor(tls: &Socks5TlsArgs) -> Result<TlsAcceptor> {
+    let open = |path: &str| -> Result<BufReader<File>> {
+        Ok(BufReader::new(
+            File::open(path).with_context(|| format!("Failed to open {path:?}"))?,
+        ))
+    };
+
+    let certs = rustls_pemfile::certs(&mut open(&tls.cert)?)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid certificate file {:?}", tls.cert))?;
+    let key = rustls_pemfile::private_key(&mut open(&tls.key)?)
+        .with_context(|| format!("Invalid private key file {:?}", tls.key))?
+        .with_context(|| format!("No private key found in {:?}", tls.key))?;
+
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+async fn serve_socks5<S: AsyncRead + AsyncWrite + Unpin>(
+    in_stream: S,
     auth: Arc<Option<Socks5AuthArgs>>,
-) -> Result<(TcpStream, TngEndpoint)> {
+) -> Result<(S, TngEndpoint)> {
     tracing::trace!("Start serving stream as socks5 connection");
 
     let proto = match auth.as_ref() {
@@ -131,12 +169,16 @@ impl IngressTrait for Socks5Ingress {
         None
     }
 
+    fn set_source_filter(&mut self, source_filter: SourceFilter) -> Result<()> {
+        self.source_filter = source_filter;
+        Ok(())
+    }
+
     async fn accept(&self, runtime: TokioRuntime) -> Result<Incomming> {
         let listen_addr = format!("{}:{}", self.listen_addr, self.listen_port);
         tracing::debug!(%listen_addr, "Add TCP listener");
 
-        let listener = TcpListener::bind(&listen_addr)
-            .await
+        let listener = bind_tcp_listener(&listen_addr)
             .with_context(|| format!("Failed to bind socks5 ingress listener on {listen_addr}"))?;
         listener.set_listener_common_sock_opts()?;
 
@@ -145,7 +187,12 @@ impl IngressTrait for Socks5Ingress {
         Ok(Box::pin(
             stream! {
                 loop {
-                    yield listener.accept_with_common_sock_opts().await
+                    let res = listener.accept_with_common_sock_opts().await;
+                    // The stream is closed before the socks5 handshake when it is dropped here
+                    if matches!(&res, Ok((_, peer_addr)) if !self.source_filter.allows(*peer_addr)) {
+                        continue;
+                    }
+                    yield res
                 }
             }
             .map(move |res| {
@@ -154,13 +201,43 @@ impl IngressTrait for Socks5Ingress {
                     let (stream, peer_addr) = res?;
 
                     let auth = self.auth.clone();
+                    let tls_acceptor = self.tls_acceptor.clone();
 
                     // Run socks5 protocol in a separate task to add parallelism with multi-cpu
                     let (stream, dst) = runtime
                         .spawn_supervised_task_current_span(async move {
-                            serve_socks5(stream, auth)
-                                .await
-                                .context("Failed to serve socks5 connection")
+                            let (stream, dst): (Box<dyn CommonStreamTrait + Send>, _) =
+                                match tls_acceptor {
+                                    Some(tls_acceptor) => {
+                                        let stream = tls_acceptor
+                                            .accept(stream)
+                                            .await
+                                            .context("TLS handshake with socks5 client failed")?;
+                                        let (stream, dst) = serve_socks5(stream, auth)
+                                            .await
+                                            .context("Failed to serve socks5 connection")?;
+                                        (
+                                            Box::new(crate::ContextualStream::new(
+                                                stream,
+                                                "ingress-socks5-tls",
+                                            )),
+                                            dst,
+                                        )
+                                    }
+                                    None => {
+                                        let (stream, dst) = serve_socks5(stream, auth)
+                                            .await
+                                            .context("Failed to serve socks5 connection")?;
+                                        (
+                                            Box::new(crate::ContextualStream::new(
+                                                stream,
+                                                "ingress-socks5",
+                                            )),
+                                            dst,
+                                        )
+                                    }
+                                };
+                            anyhow::Ok((stream, dst))
                         })
                         .await?
                         .assume_finished()??;
@@ -175,7 +252,7 @@ impl IngressTrait for Socks5Ingress {
                         IngressAccessMode::Socks5,
                     );
                     Ok(AcceptedStream {
-                        stream: Box::new(crate::ContextualStream::new(stream, "ingress-socks5")),
+                        stream,
                         src: peer_addr,
                         dst: Arc::new(dst),
                         encrypted,
@@ -9,8 +9,23 @@ use crate::{
 };
 use anyhow::Result;
 
+pub use crate::tunnel::utils::circuit_breaker::CircuitBreaker;
+
+/// Forwards the streams accepted by an ingress to their upstreams, which is what the ingresses
+/// share regardless of how they accept the streams. [`trusted::TrustedStreamManager`] forwards
+/// them through the trusted tunnel, and [`unprotected::UnprotectedStreamManager`] forwards them
+/// directly, e.g. for the endpoints matching `direct_forward`.
+///
+/// Library users can compose them into ingresses of their own, accepting the streams in a way
+/// TNG does not, with a [`ServiceMetrics`](crate::tunnel::service_metrics::ServiceMetrics) and a
+/// [`TokioRuntime`](crate::TokioRuntime) of their own. Note that, unlike the configuration, this
+/// API follows the internals of TNG and may change in a minor release.
 #[allow(async_fn_in_trait)]
 pub trait StreamManager {
+    /// Connects to `endpoint`, and returns the task forwarding `downstream` to it, which should
+    /// be spawned, along with the attestation result of the peer if it was attested, the local
+    /// address of the connection to the upstream if there is one, and the ID of the rats-tls
+    /// session the stream is multiplexed on, if any.
     async fn forward_stream<'a>(
         &self,
         endpoint: &'a TngEndpoint,
@@ -20,5 +35,6 @@ pub trait StreamManager {
         Pin<Box<dyn Future<Output = Result<()>> + std::marker::Send + 'static>>,
         Option<AttestationResult>,
         /* upstream_local */ Option<SocketAddr>,
+        /* session_id */ Option<u64>,
     )>;
 }
@@ -2,28 +2,41 @@ use std::future::Future;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{bail, Result};
 use async_trait::async_trait;
 
+use crate::config::ra::VerifyUpdate;
 use crate::error::TngError;
 use crate::status::{StatusProvider, StatusQueryResult};
 use crate::tunnel::ingress::protocol::ohttp::OHttpStreamForwarder;
-use crate::tunnel::ingress::protocol::rats_tls::RatsTlsStreamForwarder;
+use crate::tunnel::ingress::protocol::rats_tls::{
+    transport::TcpTransportLayerCreator, RatsTlsStreamForwarder,
+};
 use crate::tunnel::ingress::protocol::ProtocolStreamForwarder;
 use crate::tunnel::ingress::stream_manager::TngEndpoint;
 use crate::tunnel::ra_context::RaContext;
+use crate::tunnel::service_metrics::ServiceMetrics;
+use crate::tunnel::utils::rustls::config::alpn::Alpn;
 use crate::CommonStreamTrait;
 use crate::{
-    config::ingress::CommonArgs,
-    tunnel::{attestation_result::AttestationResult, utils::runtime::TokioRuntime},
+    config::{ingress::CommonArgs, DEFAULT_FORWARD_BUF_SIZE},
+    tunnel::{
+        attestation_result::AttestationResult,
+        utils::{circuit_breaker::CircuitBreaker, runtime::TokioRuntime},
+    },
 };
 
 use super::StreamManager;
 
+/// Forwards the streams through the trusted tunnel, with the `rats_tls` or `ohttp` protocol set
+/// in the `common_args` of the ingress.
 pub struct TrustedStreamManager {
     stream_forwarder: Box<dyn ProtocolStreamForwarder + Send + Sync + 'static>,
 
+    ra_context: Arc<RaContext>,
+
     #[allow(unused)]
     runtime: TokioRuntime,
 }
@@ -33,7 +46,9 @@ impl TrustedStreamManager {
         common_args: &CommonArgs,
         #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
         transport_so_mark: Option<u32>,
+        metrics: &ServiceMetrics,
         parent_runtime: TokioRuntime,
+        circuit_breaker: CircuitBreaker,
     ) -> Result<Self> {
         if common_args.web_page_inject {
             bail!("The `web_page_inject` field is not supported")
@@ -43,6 +58,17 @@ impl TrustedStreamManager {
             bail!("Cannot specify both `ohttp` and `rats_tls` — they are mutually exclusive");
         }
 
+        if let Some(rats_tls) = &common_args.rats_tls {
+            rats_tls.validate()?;
+            if rats_tls.websocket.is_some() {
+                bail!("`rats_tls.websocket` is only supported by tng-wasm");
+            }
+            if rats_tls.handshake_timeout_secs == Some(0) {
+                bail!("`rats_tls.handshake_timeout_secs` must be greater than 0");
+            }
+            Alpn::validate_configured(&rats_tls.alpn)?;
+        }
+
         // Use a standalone runtime for ohttp and H2 multiplex scenarios to avoid
         // contention with the traffic capture module. For multiplex=false (single TLS
         // per stream), share the parent runtime since there is no H2 task scheduling overhead.
@@ -55,7 +81,7 @@ impl TrustedStreamManager {
         let runtime = if is_h2_or_ohttp {
             #[cfg(not(wasm))]
             {
-                TokioRuntime::new_multi_thread(parent_runtime.shutdown_guard().clone())?
+                parent_runtime.new_protocol_runtime()?
             }
             #[cfg(wasm)]
             {
@@ -86,41 +112,70 @@ impl TrustedStreamManager {
                                 target_os = "linux"
                             ))]
                             transport_so_mark,
+                            common_args.resolver.clone(),
                             ohttp_args,
-                            ra_context,
+                            ra_context.clone(),
                             runtime.clone(),
                         )
                         .await?,
                     ),
 
                     None => {
-                        let multiplex = common_args
-                            .rats_tls
-                            .as_ref()
-                            .unwrap_or(&Default::default())
-                            .multiplex;
+                        let rats_tls = common_args.rats_tls.clone().unwrap_or_default();
+                        let transport_layer_creator = rats_tls.transport.unwrap_or_else(|| {
+                            Arc::new(
+                                TcpTransportLayerCreator::new(
+                                    #[cfg(any(
+                                        target_os = "android",
+                                        target_os = "fuchsia",
+                                        target_os = "linux"
+                                    ))]
+                                    transport_so_mark,
+                                )
+                                .with_resolver(common_args.resolver.clone()),
+                            )
+                        });
                         Box::new(
                             RatsTlsStreamForwarder::new(
-                                #[cfg(any(
-                                    target_os = "android",
-                                    target_os = "fuchsia",
-                                    target_os = "linux"
-                                ))]
-                                transport_so_mark,
-                                ra_context,
+                                transport_layer_creator,
+                                ra_context.clone(),
+                                metrics.clone(),
                                 runtime.clone(),
-                                multiplex,
+                                rats_tls.multiplex,
+                                rats_tls.handshake_timeout_secs.map(Duration::from_secs),
+                                &rats_tls.alpn,
+                                rats_tls.adaptive_window,
+                                common_args
+                                    .buffer_size
+                                    .as_ref()
+                                    .map_or(DEFAULT_FORWARD_BUF_SIZE, |buffer_size| {
+                                        buffer_size.forward
+                                    }),
+                                circuit_breaker,
                             )
                             .await?,
                         )
                     }
                 }
             },
+            ra_context,
             runtime,
         })
     }
 }
 
+impl TrustedStreamManager {
+    /// Establishes the session to `endpoint` ahead of the first stream, and keeps it open.
+    pub async fn prewarm(&self, endpoint: &TngEndpoint) -> Result<()> {
+        self.stream_forwarder.prewarm(endpoint).await
+    }
+
+    /// Updates the parameters which the upstreams are verified with, from the next handshake on.
+    pub async fn update_verify(&self, update: &VerifyUpdate) -> Result<()> {
+        self.ra_context.update_verify(update).await
+    }
+}
+
 impl StreamManager for TrustedStreamManager {
     async fn forward_stream<'a>(
         &self,
@@ -130,6 +185,7 @@ impl StreamManager for TrustedStreamManager {
         Pin<Box<dyn Future<Output = Result<()>> + std::marker::Send + 'static>>,
         Option<AttestationResult>,
         /* upstream_local */ Option<SocketAddr>,
+        /* session_id */ Option<u64>,
     )> {
         self.stream_forwarder
             .forward_stream(endpoint, downstream)
@@ -1,38 +1,47 @@
-use std::{future::Future, net::SocketAddr, pin::Pin};
+use std::{future::Future, net::SocketAddr, pin::Pin, sync::Arc};
 
 use anyhow::{Context as _, Result};
 
 use crate::{
-    tunnel::{attestation_result::AttestationResult, endpoint::TngEndpoint, utils},
+    tunnel::{
+        attestation_result::AttestationResult,
+        endpoint::TngEndpoint,
+        resolver::Resolver,
+        utils,
+        utils::{circuit_breaker::CircuitBreaker, forward::SpliceSocket},
+    },
     CommonStreamTrait, ContextualStream,
 };
 
 use super::StreamManager;
 
+/// Forwards the streams to the upstreams directly, without the tunnel.
 pub struct UnprotectedStreamManager {
     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
     transport_so_mark: Option<u32>,
+    resolver: Option<Arc<dyn Resolver>>,
+    forward_buffer_size: usize,
+    circuit_breaker: CircuitBreaker,
 }
 
 impl UnprotectedStreamManager {
     pub fn new(
         #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
         transport_so_mark: Option<u32>,
+        resolver: Option<Arc<dyn Resolver>>,
+        forward_buffer_size: usize,
+        circuit_breaker: CircuitBreaker,
     ) -> Self {
         Self {
             #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
             transport_so_mark,
+            resolver,
+            forward_buffer_size,
+            circuit_breaker,
         }
     }
 }
 
-#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
-impl Default for UnprotectedStreamManager {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl StreamManager for UnprotectedStreamManager {
     async fn forward_stream<'a>(
         &self,
@@ -43,11 +52,35 @@ impl StreamManager for UnprotectedStreamManager {
         Pin<Box<dyn Future<Output = Result<()>> + std::marker::Send + 'static>>,
         Option<AttestationResult>,
         /* upstream_local */ Option<SocketAddr>,
+        /* session_id */ Option<u64>,
     )> {
-        let upstream = endpoint
-            .tcp_connect(
-                #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-                self.transport_so_mark,
+        self.forward_spliceable_stream(endpoint, downstream).await
+    }
+}
+
+impl UnprotectedStreamManager {
+    /// Same as [`StreamManager::forward_stream`], but the data is moved with splice(2) if the
+    /// downstream is a plain TCP socket.
+    pub async fn forward_spliceable_stream(
+        &self,
+        endpoint: &TngEndpoint,
+        downstream: impl CommonStreamTrait + SpliceSocket,
+    ) -> Result<(
+        /* forward_stream_task */
+        Pin<Box<dyn Future<Output = Result<()>> + std::marker::Send + 'static>>,
+        Option<AttestationResult>,
+        /* upstream_local */ Option<SocketAddr>,
+        /* session_id */ Option<u64>,
+    )> {
+        let upstream = self
+            .circuit_breaker
+            .connect(
+                endpoint,
+                endpoint.tcp_connect(
+                    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                    self.transport_so_mark,
+                    self.resolver.as_deref(),
+                ),
             )
             .await
             .with_context(|| {
@@ -55,14 +88,22 @@ impl StreamManager for UnprotectedStreamManager {
             })?;
         let upstream_local = upstream.local_addr().context("Failed to get local addr")?;
         let upstream = ContextualStream::new(upstream, "ingress-unprotected-tcp");
+        let forward_buffer_size = self.forward_buffer_size;
 
         Ok((
-            Box::pin(async {
-                let _: () = utils::forward::forward_stream(upstream, downstream).await;
+            Box::pin(async move {
+                let _: () = utils::forward::forward_stream_zero_copy(
+                    upstream,
+                    downstream,
+                    forward_buffer_size,
+                    false,
+                )
+                .await;
                 Ok(())
             }) as Pin<Box<_>>,
             None,
             Some(upstream_local),
+            None,
         ))
     }
 }
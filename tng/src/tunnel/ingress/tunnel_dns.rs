@@ -0,0 +1,497 @@
+//! Resolution of the upstream domain names through a trusted tunnel.
+//!
+//! The DNS queries are sent as QUIC datagrams to an egress in `mapping_udp` mode, which forwards
+//! them to a DNS server inside the TEE network. Only the egress and the DNS server learn which
+//! names are resolved.
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use scopeguard::defer;
+use tokio::sync::{oneshot, Mutex};
+
+use crate::config::ingress::CommonArgs;
+use crate::config::tunnel_dns::TunnelDnsArgs;
+use crate::config::Endpoint;
+use crate::tunnel::endpoint::TngEndpoint;
+use crate::tunnel::ra_context::RaContext;
+use crate::tunnel::resolver::{Resolver, SystemResolver};
+use crate::tunnel::udp::quic_tunnel::QuicDatagramTunnelClient;
+use crate::tunnel::utils::endpoint_matcher::EndpointMatcher;
+use crate::tunnel::utils::runtime::TokioRuntime;
+use crate::tunnel::utils::rustls::config::alpn::Alpn;
+use crate::tunnel::utils::rustls::config::TlsConfigGenerator;
+
+const DNS_HEADER_LEN: usize = 12;
+const DNS_TYPE_A: u16 = 1;
+const DNS_CLASS_IN: u16 = 1;
+const DNS_RCODE_NXDOMAIN: u16 = 3;
+
+/// How many times a query is sent within the timeout, since the datagrams may be lost.
+const DNS_ATTEMPTS: u32 = 3;
+
+/// How many random ids are tried for a query before giving up, when most ids are in use by the
+/// pending queries.
+const DNS_ID_ATTEMPTS: usize = 16;
+
+/// Resolves the names matching `tunnel_dns.dst_filters` through the trusted tunnel, and the other
+/// names with the resolver the ingress would use otherwise.
+pub struct TunnelDnsResolver {
+    server: Endpoint,
+    matcher: EndpointMatcher,
+    timeout: Duration,
+    max_datagram_size: Option<usize>,
+    fallback: Arc<dyn Resolver>,
+    tls_gen: TlsConfigGenerator,
+    runtime: TokioRuntime,
+    /// The tunnel to the DNS server, shared by the concurrent queries. It is reestablished by the
+    /// next query after a failure.
+    tunnel: Mutex<Option<Arc<DnsTunnel>>>,
+    /// The answers received from the DNS server, until their TTL expires.
+    cache: std::sync::Mutex<HashMap<String, (Vec<Ipv4Addr>, Instant)>>,
+}
+
+impl TunnelDnsResolver {
+    pub async fn new(
+        tunnel_dns_args: &TunnelDnsArgs,
+        common_args: &CommonArgs,
+        runtime: TokioRuntime,
+    ) -> Result<Self> {
+        tunnel_dns_args.validate()?;
+
+        let ra_args = common_args.ra_args.clone().into_checked()?;
+        let ra_context = Arc::new(RaContext::from_ra_args(&ra_args).await?);
+
+        Ok(Self {
+            server: tunnel_dns_args.server.clone(),
+            matcher: EndpointMatcher::new(&tunnel_dns_args.dst_filters)?,
+            timeout: tunnel_dns_args.timeout(),
+            max_datagram_size: common_args
+                .quic
+                .as_ref()
+                .and_then(|quic| quic.max_datagram_size),
+            fallback: common_args
+                .resolver
+                .clone()
+                .unwrap_or_else(|| Arc::new(SystemResolver)),
+            tls_gen: TlsConfigGenerator::new(ra_context, runtime.clone()).await?,
+            runtime,
+            tunnel: Mutex::new(None),
+            cache: Default::default(),
+        })
+    }
+
+    async fn query(&self, name: &str) -> Result<Vec<Ipv4Addr>> {
+        if let Some((addrs, expire_at)) = self
+            .cache
+            .lock()
+            .map_err(|_| anyhow::anyhow!("The DNS cache is poisoned"))?
+            .get(name)
+        {
+            if *expire_at > Instant::now() {
+                return Ok(addrs.clone());
+            }
+        }
+
+        let mut tunnel = None;
+        let result = tokio::time::timeout(self.timeout, async {
+            tunnel
+                .insert(self.tunnel().await?)
+                .query(name, self.timeout / DNS_ATTEMPTS)
+                .await
+        })
+        .await
+        .context("Timed out waiting for the answer of the DNS server")
+        .and_then(|result| result);
+
+        let answer = match result {
+            Ok(answer) => answer,
+            Err(e) => {
+                // The DNS errors are answered by the server, the tunnel is still usable then.
+                if let (Some(tunnel), None) = (tunnel, e.downcast_ref::<DnsServerError>()) {
+                    self.reset_tunnel(&tunnel).await;
+                }
+                return Err(e);
+            }
+        };
+
+        self.cache
+            .lock()
+            .map_err(|_| anyhow::anyhow!("The DNS cache is poisoned"))?
+            .insert(
+                name.to_owned(),
+                (
+                    answer.addrs.clone(),
+                    Instant::now() + Duration::from_secs(answer.ttl.into()),
+                ),
+            );
+
+        Ok(answer.addrs)
+    }
+
+    /// Returns the tunnel to the DNS server, establishing it if there is none.
+    async fn tunnel(&self) -> Result<Arc<DnsTunnel>> {
+        let mut tunnel = self.tunnel.lock().await;
+        if let Some(tunnel) = &*tunnel {
+            return Ok(tunnel.clone());
+        }
+
+        tracing::debug!(server = ?self.server, "Establishing the tunnel to the DNS server");
+        let tls_config = self
+            .tls_gen
+            .get_blocking_one_time_rustls_client_config(Alpn::RatsQuic)
+            .await?;
+        let client = QuicDatagramTunnelClient::connect(
+            &self.server,
+            Alpn::RatsQuic,
+            self.max_datagram_size,
+            tls_config,
+        )
+        .await?;
+        Ok(tunnel
+            .insert(Arc::new(DnsTunnel::new(client, &self.runtime)))
+            .clone())
+    }
+
+    /// Closes the tunnel which a query failed on, unless it is replaced already by another query.
+    async fn reset_tunnel(&self, failed: &Arc<DnsTunnel>) {
+        failed.close();
+        let mut tunnel = self.tunnel.lock().await;
+        if tunnel
+            .as_ref()
+            .is_some_and(|tunnel| Arc::ptr_eq(tunnel, failed))
+        {
+            *tunnel = None;
+        }
+    }
+}
+
+/// A tunnel to the DNS server, on which the answers are dispatched to the queries by their id.
+struct DnsTunnel {
+    client: Arc<QuicDatagramTunnelClient>,
+    /// The queries waiting for their answer, by their id.
+    pending: Arc<std::sync::Mutex<HashMap<u16, oneshot::Sender<Bytes>>>>,
+}
+
+impl DnsTunnel {
+    fn new(client: QuicDatagramTunnelClient, runtime: &TokioRuntime) -> Self {
+        let client = Arc::new(client);
+        let pending: Arc<std::sync::Mutex<HashMap<u16, oneshot::Sender<Bytes>>>> =
+            Default::default();
+        {
+            let client = client.clone();
+            let pending = pending.clone();
+            runtime.spawn_supervised_task_current_span(async move {
+                loop {
+                    let response = match client.receive_datagram().await {
+                        Ok(response) => response,
+                        Err(error) => {
+                            tracing::debug!(?error, "The tunnel to the DNS server is closed");
+                            break;
+                        }
+                    };
+                    let Some(&[high, low]) = response.get(..2) else {
+                        continue;
+                    };
+                    // The answers to the queries which are finished already, e.g. the answers
+                    // to the retransmissions, are dropped
+                    if let Some(sender) = pending
+                        .lock()
+                        .ok()
+                        .and_then(|mut pending| pending.remove(&u16::from_be_bytes([high, low])))
+                    {
+                        let _ = sender.send(response); // Ignore any error occuring during send
+                    }
+                }
+                // The queries still waiting fail at once
+                if let Ok(mut pending) = pending.lock() {
+                    pending.clear();
+                }
+            });
+        }
+        Self { client, pending }
+    }
+
+    /// Sends the query, and sends it again every `interval` until it is answered.
+    async fn query(&self, name: &str, interval: Duration) -> Result<DnsAnswer> {
+        let (id, mut receiver) = {
+            let mut pending = self
+                .pending
+                .lock()
+                .map_err(|_| anyhow!("The pending DNS queries are poisoned"))?;
+            let id = free_query_id(&pending)?;
+            let (sender, receiver) = oneshot::channel();
+            pending.insert(id, sender);
+            (id, receiver)
+        };
+        defer! {
+            if let Ok(mut pending) = self.pending.lock() {
+                pending.remove(&id);
+            }
+        }
+
+        let query = Bytes::from(encode_query(id, name)?);
+        loop {
+            self.client.send_datagram(query.clone())?;
+            match tokio::time::timeout(interval, &mut receiver).await {
+                Ok(Ok(response)) => {
+                    return decode_response(id, &response)?
+                        .context("The DNS server answered another query");
+                }
+                Ok(Err(_)) => bail!("The tunnel to the DNS server is closed"),
+                Err(_) => tracing::debug!(name, "Sending the DNS query again"),
+            }
+        }
+    }
+
+    fn close(&self) {
+        self.client.connection.close(0u32.into(), b"");
+    }
+}
+
+impl Drop for DnsTunnel {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[async_trait]
+impl Resolver for TunnelDnsResolver {
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        if !self
+            .matcher
+            .matches(&TngEndpoint::from_domain(host.to_owned(), port))
+        {
+            return self.fallback.resolve(host, port).await;
+        }
+
+        tracing::debug!(host, "Resolving through the trusted tunnel");
+        let addrs = self
+            .query(host)
+            .await
+            .with_context(|| format!("Failed to resolve '{host}' through the trusted tunnel"))?;
+        Ok(addrs
+            .into_iter()
+            .map(|addr| SocketAddr::new(addr.into(), port))
+            .collect())
+    }
+}
+
+/// An error answered by the DNS server.
+#[derive(Debug, thiserror::Error)]
+enum DnsServerError {
+    #[error("The domain name does not exist")]
+    NameNotFound,
+
+    #[error("The DNS server responded with rcode {0}")]
+    Rcode(u16),
+
+    #[error("The domain name has no IPv4 address")]
+    NoAddress,
+}
+
+/// The IPv4 addresses answered by the DNS server, and the time to live of the answer in seconds.
+#[derive(Debug, PartialEq, Eq)]
+struct DnsAnswer {
+    addrs: Vec<Ipv4Addr>,
+    ttl: u32,
+}
+
+/// Encodes a recursive query for the A records of `name`.
+/// Picks a random id which is not used by any of the pending queries.
+fn free_query_id<T>(pending: &HashMap<u16, T>) -> Result<u16> {
+    (0..DNS_ID_ATTEMPTS)
+        .map(|_| rand::random::<u16>())
+        .find(|id| !pending.contains_key(id))
+        .context("Too many DNS queries are pending on the tunnel")
+}
+
+fn encode_query(id: u16, name: &str) -> Result<Vec<u8>> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() || name.len() > 253 {
+        bail!("Invalid domain name '{name}'");
+    }
+
+    let mut query = Vec::with_capacity(DNS_HEADER_LEN + name.len() + 6);
+    query.extend_from_slice(&id.to_be_bytes());
+    query.extend_from_slice(&0x0100u16.to_be_bytes()); // Recursion desired
+    query.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+    query.extend_from_slice(&[0; 6]); // ANCOUNT, NSCOUNT, ARCOUNT
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            bail!("Invalid domain name '{name}'");
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&DNS_TYPE_A.to_be_bytes());
+    query.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Decodes the response to the query `id`. Returns `None` if it answers another query.
+fn decode_response(id: u16, response: &[u8]) -> Result<Option<DnsAnswer>> {
+    let read_u16 = |pos: usize| -> Result<u16> {
+        response
+            .get(pos..pos + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .context("Truncated DNS response")
+    };
+
+    if read_u16(0)? != id {
+        return Ok(None);
+    }
+    let flags = read_u16(2)?;
+    if flags & 0x8000 == 0 {
+        bail!("Not a DNS response");
+    }
+    match flags & 0x000f {
+        0 => {}
+        DNS_RCODE_NXDOMAIN => return Err(DnsServerError::NameNotFound.into()),
+        rcode => return Err(DnsServerError::Rcode(rcode).into()),
+    }
+    let qdcount = read_u16(4)?;
+    let ancount = read_u16(6)?;
+
+    let mut pos = DNS_HEADER_LEN;
+    for _ in 0..qdcount {
+        pos = skip_name(response, pos)? + 4;
+    }
+
+    let mut addrs = vec![];
+    let mut ttl = u32::MAX;
+    for _ in 0..ancount {
+        pos = skip_name(response, pos)?;
+        let rtype = read_u16(pos)?;
+        let class = read_u16(pos + 2)?;
+        let record_ttl = (u32::from(read_u16(pos + 4)?) << 16) | u32::from(read_u16(pos + 6)?);
+        let rdlength = usize::from(read_u16(pos + 8)?);
+        pos += 10;
+        let rdata = response
+            .get(pos..pos + rdlength)
+            .context("Truncated DNS response")?;
+        pos += rdlength;
+
+        if rtype == DNS_TYPE_A && class == DNS_CLASS_IN && rdlength == 4 {
+            addrs.push(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]));
+            ttl = ttl.min(record_ttl);
+        }
+    }
+
+    if addrs.is_empty() {
+        return Err(DnsServerError::NoAddress.into());
+    }
+    Ok(Some(DnsAnswer { addrs, ttl }))
+}
+
+/// Returns the position after the (possibly compressed) name starting at `pos`.
+fn skip_name(message: &[u8], mut pos: usize) -> Result<usize> {
+    loop {
+        let len = *message.get(pos).context("Truncated DNS response")?;
+        match len {
+            0 => return Ok(pos + 1),
+            len if len & 0xc0 == 0xc0 => return Ok(pos + 2),
+            len => pos += 1 + usize::from(len),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the response of a DNS server to `query`, with the given A records.
+    fn response(query: &[u8], rcode: u16, records: &[([u8; 4], u32)]) -> Vec<u8> {
+        let mut response = query.to_vec();
+        response[2..4].copy_from_slice(&(0x8180 | rcode).to_be_bytes());
+        response[6..8].copy_from_slice(&(records.len() as u16).to_be_bytes());
+        for (addr, ttl) in records {
+            response.extend_from_slice(&[0xc0, DNS_HEADER_LEN as u8]); // Pointer to the question
+            response.extend_from_slice(&DNS_TYPE_A.to_be_bytes());
+            response.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+            response.extend_from_slice(&ttl.to_be_bytes());
+            response.extend_from_slice(&4u16.to_be_bytes());
+            response.extend_from_slice(addr);
+        }
+        response
+    }
+
+    #[test]
+    fn test_encode_query() -> Result<()> {
+        let query = encode_query(0x1234, "svc.tee.internal.")?;
+        assert_eq!(
+            &query[..DNS_HEADER_LEN],
+            b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00"
+        );
+        assert_eq!(
+            &query[DNS_HEADER_LEN..],
+            b"\x03svc\x03tee\x08internal\x00\x00\x01\x00\x01"
+        );
+
+        assert!(encode_query(1, "").is_err());
+        assert!(encode_query(1, "a..b").is_err());
+        assert!(encode_query(1, &"a".repeat(64)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_response() -> Result<()> {
+        let query = encode_query(7, "svc.tee.internal")?;
+
+        let answer = decode_response(
+            7,
+            &response(&query, 0, &[([10, 0, 0, 1], 300), ([10, 0, 0, 2], 60)]),
+        )?;
+        assert_eq!(
+            answer,
+            Some(DnsAnswer {
+                addrs: vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)],
+                ttl: 60,
+            })
+        );
+
+        // The answer to another query
+        assert_eq!(
+            decode_response(8, &response(&query, 0, &[([10, 0, 0, 1], 300)]))?,
+            None
+        );
+
+        let error = decode_response(7, &response(&query, DNS_RCODE_NXDOMAIN, &[])).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DnsServerError>(),
+            Some(DnsServerError::NameNotFound)
+        ));
+
+        let error = decode_response(7, &response(&query, 0, &[])).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DnsServerError>(),
+            Some(DnsServerError::NoAddress)
+        ));
+
+        // The query sent back without being answered
+        assert!(decode_response(7, &query).is_err());
+        // A truncated answer
+        let response = response(&query, 0, &[([10, 0, 0, 1], 300)]);
+        assert!(decode_response(7, &response[..response.len() - 2]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_free_query_id() -> Result<()> {
+        let mut pending = HashMap::new();
+        let id = free_query_id(&pending)?;
+        pending.insert(id, ());
+        assert_ne!(free_query_id(&pending)?, id);
+
+        // All the ids are in use
+        let pending = (0..=u16::MAX).map(|id| (id, ())).collect::<HashMap<_, _>>();
+        assert!(free_query_id(&pending).is_err());
+        Ok(())
+    }
+}
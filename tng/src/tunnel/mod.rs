@@ -1,9 +1,12 @@
 pub(crate) mod access_log;
 pub(crate) mod attestation_result;
+pub(crate) mod audit_log;
+#[cfg(not(wasm))]
+pub(crate) mod connections;
 #[cfg(not(wasm))]
 pub(crate) mod datagram;
 #[cfg(feature = "__egress-common")]
-pub(crate) mod egress;
+pub mod egress;
 pub mod endpoint;
 #[cfg(feature = "__ingress-common")]
 pub mod ingress;
@@ -11,7 +14,9 @@ pub(crate) mod ohttp;
 pub(crate) mod provider;
 pub(crate) mod ra_context;
 #[cfg(not(wasm))]
-pub(crate) mod service_metrics;
+pub mod resolver;
+#[cfg(not(wasm))]
+pub mod service_metrics;
 pub(crate) mod stream;
 #[cfg(not(wasm))]
 pub(crate) mod udp;
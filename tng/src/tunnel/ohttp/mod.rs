@@ -1,2 +1,3 @@
 pub mod key_config;
+pub mod padding;
 pub mod protocol;
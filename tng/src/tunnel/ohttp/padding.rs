@@ -0,0 +1,211 @@
+//! Padding of OHTTP-encapsulated messages.
+//!
+//! RFC 9292 allows a Binary HTTP message to be followed by any number of zero-valued bytes,
+//! which the decoder ignores. We append such padding to the encoded message before it is
+//! encrypted, so the ciphertext length no longer reveals the exact size of the message.
+//!
+//! Each chunk of the message is encrypted as a separate chunk of the chunked OHTTP message, whose
+//! size is visible on the wire. The padding is therefore appended to the last chunk of the
+//! message instead of being sent in chunks of its own, which would reveal where it starts.
+
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt as _};
+use rand::{Rng as _, SeedableRng as _};
+use rand_chacha::ChaCha12Rng;
+
+use crate::config::ohttp_padding::OHttpPaddingPolicy;
+
+/// Get the number of padding bytes to append to a message of `message_len` bytes.
+fn padding_len(policy: &OHttpPaddingPolicy, message_len: usize) -> usize {
+    match policy {
+        OHttpPaddingPolicy::Block { block_size } => {
+            let block_size = block_size.get();
+            (block_size - message_len % block_size) % block_size
+        }
+        OHttpPaddingPolicy::Random { max_bytes } => {
+            ChaCha12Rng::from_os_rng().random_range(0..=*max_bytes)
+        }
+    }
+}
+
+/// Wrap a stream of encoded Binary HTTP message chunks, appending padding bytes to the last chunk
+/// of the message according to the policy. Since the last chunk is only known once the message
+/// ends, each chunk is held back until the next one is received. The stream is passed through
+/// unchanged if no policy is set.
+pub fn pad_message_stream<S, T, E>(
+    message: S,
+    policy: Option<OHttpPaddingPolicy>,
+) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<T, E>>,
+    T: Into<Bytes>,
+{
+    async_stream::try_stream! {
+        futures::pin_mut!(message);
+        let Some(policy) = policy else {
+            while let Some(chunk) = message.next().await {
+                yield chunk?.into();
+            }
+            return;
+        };
+
+        let mut message_len = 0;
+        let mut last_chunk: Option<Bytes> = None;
+        while let Some(chunk) = message.next().await {
+            let chunk: Bytes = chunk?.into();
+            message_len += chunk.len();
+            if let Some(previous) = last_chunk.replace(chunk) {
+                yield previous;
+            }
+        }
+
+        let padding_len = padding_len(&policy, message_len);
+        tracing::trace!(message_len, padding_len, "Padding OHTTP message");
+        let mut last_chunk = BytesMut::from(last_chunk.unwrap_or_default());
+        last_chunk.resize(last_chunk.len() + padding_len, 0);
+        if !last_chunk.is_empty() {
+            yield last_chunk.freeze();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_block_padding_len() -> Result<()> {
+        let policy = OHttpPaddingPolicy::Block {
+            block_size: NonZeroUsize::new(512).unwrap(),
+        };
+        assert_eq!(padding_len(&policy, 0), 0);
+        assert_eq!(padding_len(&policy, 1), 511);
+        assert_eq!(padding_len(&policy, 512), 0);
+        assert_eq!(padding_len(&policy, 600), 424);
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_padding_len() -> Result<()> {
+        let policy = OHttpPaddingPolicy::Random { max_bytes: 16 };
+        for _ in 0..100 {
+            assert!(padding_len(&policy, 100) <= 16);
+        }
+        assert_eq!(
+            padding_len(&OHttpPaddingPolicy::Random { max_bytes: 0 }, 100),
+            0
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pad_message_stream() -> Result<()> {
+        let message = futures::stream::iter(vec![
+            Ok::<_, std::io::Error>(Bytes::from_static(b"hello")),
+            Ok(Bytes::from_static(b" world")),
+        ]);
+        let policy = OHttpPaddingPolicy::Block {
+            block_size: NonZeroUsize::new(5000).unwrap(),
+        };
+
+        let chunks: Vec<Bytes> = pad_message_stream(message, Some(policy))
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+        let padded = chunks.concat();
+
+        // The padding is appended to the last chunk
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], Bytes::from_static(b"hello"));
+        assert_eq!(padded.len(), 5000);
+        assert_eq!(&padded[..11], b"hello world");
+        assert!(padded[11..].iter().all(|b| *b == 0));
+        Ok(())
+    }
+
+    /// Pads and encrypts the message chunks like the OHTTP ingress does, and returns the sizes of
+    /// the plaintext of the chunks of the encapsulated request, as seen on the wire.
+    async fn wire_chunk_sizes(
+        message: &[&'static [u8]],
+        policy: OHttpPaddingPolicy,
+    ) -> Result<Vec<usize>> {
+        use futures::{AsyncWriteExt as _, TryStreamExt as _};
+        use tokio_util::compat::{FuturesAsyncReadCompatExt as _, FuturesAsyncWriteCompatExt as _};
+
+        // The length of the header of the request with a X25519 key, and of the AEAD tag.
+        const REQUEST_HEADER_LEN: usize = 7 + 32;
+        const AEAD_TAG_LEN: usize = 16;
+
+        let message = futures::stream::iter(
+            message
+                .iter()
+                .map(|chunk| Ok::<_, std::io::Error>(Bytes::from_static(chunk)))
+                .collect::<Vec<_>>(),
+        );
+        let padded = Box::pin(pad_message_stream(message, Some(policy)));
+
+        let mut key_config = ohttp::KeyConfig::new(
+            1,
+            ohttp::hpke::Kem::X25519Sha256,
+            vec![ohttp::SymmetricSuite::new(
+                ohttp::hpke::Kdf::HkdfSha256,
+                ohttp::hpke::Aead::ChaCha20Poly1305,
+            )],
+        )?;
+        let mut wire = Vec::new();
+        {
+            let client_request = ohttp::ClientRequest::from_config(&mut key_config)?
+                .encapsulate_stream(futures::io::Cursor::new(&mut wire))?;
+            let mut client_request = client_request.compat_write();
+            tokio::io::copy(&mut padded.into_async_read().compat(), &mut client_request).await?;
+            client_request.into_inner().close().await?;
+        }
+
+        // Each chunk is prefixed with its length as a variable-length integer, and the final
+        // chunk with a length of 0 to span the rest of the request.
+        let mut chunks = &wire[REQUEST_HEADER_LEN..];
+        let mut sizes = vec![];
+        loop {
+            let prefix_len = 1 << (chunks[0] >> 6);
+            let mut len = (chunks[0] & 0x3f) as usize;
+            for byte in &chunks[1..prefix_len] {
+                len = (len << 8) | *byte as usize;
+            }
+            chunks = &chunks[prefix_len..];
+            if len == 0 {
+                sizes.push(chunks.len() - AEAD_TAG_LEN);
+                return Ok(sizes);
+            }
+            sizes.push(len - AEAD_TAG_LEN);
+            chunks = &chunks[len..];
+        }
+    }
+
+    #[tokio::test]
+    async fn test_padding_on_the_wire() -> Result<()> {
+        let policy = OHttpPaddingPolicy::Block {
+            block_size: NonZeroUsize::new(512).unwrap(),
+        };
+
+        // No chunk of padding only reveals where the message ends, so messages of different
+        // sizes have chunks of the same sizes on the wire
+        assert_eq!(
+            wire_chunk_sizes(&[b"hello", b" world"], policy).await?,
+            [5, 507, 0]
+        );
+        assert_eq!(
+            wire_chunk_sizes(&[b"hello", b" world, again"], policy).await?,
+            [5, 507, 0]
+        );
+        assert_eq!(
+            wire_chunk_sizes(&[b"hello", b" world", &[b'!'; 600]], policy).await?,
+            [5, 6, 1013, 0]
+        );
+        Ok(())
+    }
+}
@@ -2,6 +2,9 @@ pub const OHTTP_CHUNKED_REQUEST_CONTENT_TYPE: &str = "message/ohttp-chunked-req"
 
 pub const OHTTP_CHUNKED_RESPONSE_CONTENT_TYPE: &str = "message/ohttp-chunked-res";
 
+/// Media type of a list of key configurations, defined in Section 3.2 of RFC 9458.
+pub const OHTTP_KEYS_CONTENT_TYPE: &str = "application/ohttp-keys";
+
 #[derive(Debug, Clone)]
 #[allow(unused)]
 pub enum OhttpApi {
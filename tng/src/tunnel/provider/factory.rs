@@ -7,9 +7,11 @@ use rats_cert::tee::coco::converter::CocoConverter;
 use rats_cert::tee::coco::verifier::remote::CocoRemoteVerifier;
 use rats_cert::tee::coco::verifier::CocoVerifier;
 
+use crate::config::ra::{
+    AsRequestArgs, CocoConverterArgs, CocoVerifierArgs, ConverterArgs, VerifierArgs,
+};
 #[cfg(unix)]
 use crate::config::ra::{AttesterArgs, CocoAttesterArgs};
-use crate::config::ra::{CocoConverterArgs, CocoVerifierArgs, ConverterArgs, VerifierArgs};
 
 #[cfg(unix)]
 use super::attester::TngAttester;
@@ -41,15 +43,33 @@ pub fn create_converter(config: &ConverterArgs) -> Result<TngConverter> {
                 as_addr,
                 policy_ids,
                 as_headers,
+                as_request,
             } => Ok(TngConverter::Coco(CocoConverter::Restful(
-                CocoRestfulConverter::new(as_addr, policy_ids, as_headers)?,
+                CocoRestfulConverter::new(
+                    as_addr,
+                    policy_ids,
+                    as_headers,
+                    &as_request
+                        .as_ref()
+                        .map(AsRequestArgs::to_config)
+                        .unwrap_or_default(),
+                )?,
             ))),
             CocoConverterArgs::Grpc {
                 as_addr,
                 policy_ids,
                 as_headers,
+                as_request,
             } => Ok(TngConverter::Coco(CocoConverter::Grpc(
-                CocoGrpcConverter::new(as_addr, policy_ids, as_headers)?,
+                CocoGrpcConverter::new(
+                    as_addr,
+                    policy_ids,
+                    as_headers,
+                    &as_request
+                        .as_ref()
+                        .map(AsRequestArgs::to_config)
+                        .unwrap_or_default(),
+                )?,
             ))),
             #[cfg(feature = "__builtin-as")]
             CocoConverterArgs::Builtin { .. } => {
@@ -32,4 +32,4 @@ pub use factory::*;
 #[allow(unused_imports)]
 pub use provider_type::ProviderType;
 pub use token::TngToken;
-pub use verifier::TngVerifier;
+pub use verifier::{LocalChecksVerifier, TngVerifier};
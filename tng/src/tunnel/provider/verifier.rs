@@ -1,9 +1,13 @@
 use rats_cert::errors::*;
+use rats_cert::tee::claims::Claims;
 use rats_cert::tee::coco::verifier::CocoVerifier;
 use rats_cert::tee::ita::ItaVerifier;
-use rats_cert::tee::{GenericVerifier, ReportData};
+use rats_cert::tee::{GenericEvidence as _, GenericVerifier, ReportData};
+use web_time_compat::{SystemTime, SystemTimeExt as _};
 
 use super::token::TngToken;
+use crate::config::ra::{ClaimRedaction, ClaimRequirement, VerifyArgs};
+use crate::tunnel::audit_log::AttestationAudit;
 
 /// Provider-polymorphic verifier. Verifies an AS token against report data.
 pub enum TngVerifier {
@@ -36,6 +40,93 @@ impl GenericVerifier for TngVerifier {
     }
 }
 
+/// A [`TngVerifier`] which also enforces the `required_claims` and the `max_evidence_age` of
+/// `verify` on the verified tokens, locally, and records the outcome in the audit log, with the
+/// values of the claims hidden as set by `redact_claims`.
+pub struct LocalChecksVerifier {
+    inner: TngVerifier,
+    required_claims: Vec<ClaimRequirement>,
+    max_evidence_age: Option<u64>,
+    redact_claims: Vec<ClaimRedaction>,
+    audit: AttestationAudit,
+}
+
+impl LocalChecksVerifier {
+    pub fn new(inner: TngVerifier, verify_args: &VerifyArgs) -> Self {
+        let (model, verifier_args) = match verify_args {
+            VerifyArgs::Passport { verifier, .. } => ("passport", verifier),
+            VerifyArgs::BackgroundCheck { verifier, .. } => ("background_check", verifier),
+        };
+        let audit = AttestationAudit {
+            model,
+            provider: inner.provider_type(),
+            policy_ids: verifier_args.policy_ids().to_vec(),
+        };
+        Self {
+            inner,
+            required_claims: verify_args.required_claims().to_vec(),
+            max_evidence_age: verify_args.max_evidence_age(),
+            redact_claims: verify_args.redact_claims().to_vec(),
+            audit,
+        }
+    }
+
+    async fn verify_and_check(&self, token: &TngToken, report_data: &ReportData) -> Result<()> {
+        self.inner.verify_evidence(token, report_data).await?;
+
+        if self.required_claims.is_empty() && self.max_evidence_age.is_none() {
+            return Ok(());
+        }
+        let claims = token.get_claims()?;
+        if let Some(max_age) = self.max_evidence_age {
+            Self::check_evidence_age(&claims, max_age)?;
+        }
+        for requirement in &self.required_claims {
+            requirement
+                .check(&claims, &self.redact_claims)
+                .map_err(Error::ClaimRequirementNotMet)?;
+        }
+        Ok(())
+    }
+
+    /// Records in the audit log that a peer was accepted without being verified, since the
+    /// attestation service could not be reached to convert its evidence.
+    pub fn audit_degraded(&self, error: &Error) {
+        self.audit.emit_degraded(error);
+    }
+
+    /// Rejects the token if it was issued more than `max_age` seconds ago, so that a stale
+    /// passport cannot be replayed for as long as it has not expired.
+    fn check_evidence_age(claims: &Claims, max_age: u64) -> Result<()> {
+        let Some(issued_at) = claims.get("iat").and_then(|iat| iat.as_u64()) else {
+            return Err(Error::MissingTokenField {
+                detail: "token issuance time unset".to_string(),
+            });
+        };
+        let now = SystemTime::get()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or_default();
+        let age = now.saturating_sub(issued_at);
+        if age > max_age {
+            return Err(Error::EvidenceTooOld { age, max_age });
+        }
+        Ok(())
+    }
+}
+
+#[cfg_attr(wasm, async_trait::async_trait(?Send))]
+#[cfg_attr(not(wasm), async_trait::async_trait)]
+impl GenericVerifier for LocalChecksVerifier {
+    type Evidence = TngToken;
+
+    async fn verify_evidence(&self, token: &TngToken, report_data: &ReportData) -> Result<()> {
+        let result = self.verify_and_check(token, report_data).await;
+        self.audit.emit(token.get_claims().ok().as_ref(), &result);
+        result
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::provider_type::ProviderType;
@@ -55,4 +146,28 @@ mod tests {
             "ITA verifier with Coco token should fail with IncompatibleTypes"
         );
     }
+
+    #[test]
+    fn check_evidence_age_rejects_stale_token() {
+        let now = SystemTime::get()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let claims_issued_at = |iat: u64| {
+            serde_json::json!({ "iat": iat })
+                .as_object()
+                .unwrap()
+                .clone()
+        };
+
+        assert!(LocalChecksVerifier::check_evidence_age(&claims_issued_at(now - 10), 60).is_ok());
+        assert!(matches!(
+            LocalChecksVerifier::check_evidence_age(&claims_issued_at(now - 120), 60),
+            Err(Error::EvidenceTooOld { max_age: 60, .. })
+        ));
+        assert!(matches!(
+            LocalChecksVerifier::check_evidence_age(&Claims::default(), 60),
+            Err(Error::MissingTokenField { .. })
+        ));
+    }
 }
@@ -4,13 +4,15 @@
 //! components based on `RaArgs` configuration. This avoids repeated creation
 //! of attester/converter/verifier instances at each API call.
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use web_time_compat::{Instant, InstantExt as _};
 
 #[cfg(unix)]
 use crate::config::ra::AttestArgs;
-use crate::config::ra::{RaArgs, VerifyArgs};
+use crate::config::ra::{AsUnreachableArgs, RaArgs, VerifyArgs, VerifyUpdate};
 #[cfg(feature = "__builtin-as")]
 use rats_cert::tee::coco::converter::builtin::BuiltinCocoConverter;
 #[cfg(feature = "__builtin-as")]
@@ -20,6 +22,8 @@ use rats_cert::tee::coco::verifier::CocoVerifier;
 
 #[cfg(feature = "__builtin-as")]
 use crate::config::ra::{CocoConverterArgs, ConverterArgs};
+#[cfg(feature = "__builtin-as")]
+use crate::tunnel::provider::TngVerifier;
 #[cfg(unix)]
 use crate::tunnel::utils::maybe_cached::RefreshStrategy;
 
@@ -27,7 +31,9 @@ use crate::tunnel::utils::maybe_cached::RefreshStrategy;
 use crate::tunnel::provider::create_attester;
 #[cfg(unix)]
 use crate::tunnel::provider::TngAttester;
-use crate::tunnel::provider::{create_converter, create_verifier, TngConverter, TngVerifier};
+use crate::tunnel::provider::{
+    create_converter, create_verifier, LocalChecksVerifier, TngConverter,
+};
 
 /// Pre-instantiated RA context for OHTTP security
 ///
@@ -39,13 +45,13 @@ pub enum RaContext {
     AttestOnly(Arc<AttestContext>),
 
     /// Verify only mode - server verifies client
-    VerifyOnly(Arc<VerifyContext>),
+    VerifyOnly(Arc<VerifyContextCell>),
 
     /// Both attest and verify
     #[cfg(unix)]
     AttestAndVerify {
         attest: Arc<AttestContext>,
-        verify: Arc<VerifyContext>,
+        verify: Arc<VerifyContextCell>,
     },
 
     /// No remote attestation
@@ -63,7 +69,7 @@ impl RaContext {
         match ra_args {
             RaArgs::NoRa => Ok(Self::NoRa),
             RaArgs::VerifyOnly(verify_args) => Ok(Self::VerifyOnly(Arc::new(
-                VerifyContext::from_verify_args(verify_args).await?,
+                VerifyContextCell::from_verify_args(verify_args).await?,
             ))),
             #[cfg(unix)]
             RaArgs::AttestOnly(attest_args) => Ok(Self::AttestOnly(Arc::new(
@@ -72,13 +78,21 @@ impl RaContext {
             #[cfg(unix)]
             RaArgs::AttestAndVerify(attest_args, verify_args) => Ok(Self::AttestAndVerify {
                 attest: Arc::new(AttestContext::from_attest_args(attest_args).await?),
-                verify: Arc::new(VerifyContext::from_verify_args(verify_args).await?),
+                verify: Arc::new(VerifyContextCell::from_verify_args(verify_args).await?),
             }),
         }
     }
 
     /// Get verify context if available
-    pub fn verify_context(&self) -> Option<&VerifyContext> {
+    ///
+    /// This is the current verify context, which is replaced when the verification parameters are
+    /// updated.
+    pub fn verify_context(&self) -> Option<Arc<VerifyContext>> {
+        self.verify_context_cell().map(|cell| cell.load())
+    }
+
+    /// Get the cell holding the verify context if available
+    fn verify_context_cell(&self) -> Option<&Arc<VerifyContextCell>> {
         match self {
             Self::VerifyOnly(verify) => Some(verify),
             #[cfg(unix)]
@@ -87,6 +101,16 @@ impl RaContext {
         }
     }
 
+    /// Update the verification parameters, which the peers are verified with from the next
+    /// handshake on.
+    #[cfg_attr(wasm, allow(dead_code))]
+    pub async fn update_verify(&self, update: &VerifyUpdate) -> Result<()> {
+        match self.verify_context_cell() {
+            Some(cell) => cell.update(update).await,
+            None => bail!("The peers are not verified, since `verify` is not configured"),
+        }
+    }
+
     /// Get attest context if available
     #[cfg(unix)]
     pub fn attest_context(&self) -> Option<&AttestContext> {
@@ -108,12 +132,14 @@ pub enum AttestContext {
         attester: TngAttester,
         converter: TngConverter,
         refresh_strategy: RefreshStrategy,
+        cert_refresh_strategy: RefreshStrategy,
     },
 
     /// Background check mode - just attest via AA (client verifies)
     BackgroundCheck {
         attester: TngAttester,
         refresh_strategy: RefreshStrategy,
+        cert_refresh_strategy: RefreshStrategy,
     },
     // Future: PassportBuiltin, Builtin
 }
@@ -135,6 +161,7 @@ impl AttestContext {
                     attester,
                     converter,
                     refresh_strategy: attest_args.refresh_strategy(),
+                    cert_refresh_strategy: attest_args.cert_refresh_strategy(),
                 })
             }
             AttestArgs::BackgroundCheck {
@@ -152,6 +179,7 @@ impl AttestContext {
                 Ok(Self::BackgroundCheck {
                     attester,
                     refresh_strategy: attest_args.refresh_strategy(),
+                    cert_refresh_strategy: attest_args.cert_refresh_strategy(),
                 })
             }
         }
@@ -168,6 +196,20 @@ impl AttestContext {
             } => *refresh_strategy,
         }
     }
+
+    /// Get refresh strategy for the certificate of the rats-tls sessions
+    pub fn cert_refresh_strategy(&self) -> RefreshStrategy {
+        match self {
+            Self::Passport {
+                cert_refresh_strategy,
+                ..
+            }
+            | Self::BackgroundCheck {
+                cert_refresh_strategy,
+                ..
+            } => *cert_refresh_strategy,
+        }
+    }
 }
 
 /// Pre-instantiated verification context
@@ -175,14 +217,55 @@ impl AttestContext {
 /// Holds components needed for verifying client attestation.
 pub enum VerifyContext {
     /// Passport mode - verify token from remote AS
-    Passport { verifier: TngVerifier },
+    Passport { verifier: LocalChecksVerifier },
     /// Background check - convert evidence via remote AS, then verify
     BackgroundCheck {
         converter: TngConverter,
-        verifier: TngVerifier,
+        verifier: LocalChecksVerifier,
+        as_unreachable: AsUnreachablePolicy,
     },
 }
 
+/// Decides whether the peers are accepted unverified while the attestation service is
+/// unreachable, as configured with `as_unreachable`.
+pub struct AsUnreachablePolicy {
+    /// How long the peers are accepted unverified, or `None` if they are rejected.
+    grace_period: Option<Duration>,
+    /// Since when the attestation service has been unreachable, if it is.
+    unreachable_since: Mutex<Option<Instant>>,
+}
+
+impl AsUnreachablePolicy {
+    fn new(args: &AsUnreachableArgs) -> Self {
+        Self {
+            grace_period: args.grace_period(),
+            unreachable_since: Mutex::new(None),
+        }
+    }
+
+    fn unreachable_since(&self) -> MutexGuard<'_, Option<Instant>> {
+        self.unreachable_since
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Records that the attestation service answered, which ends the grace period.
+    pub fn reachable(&self) {
+        *self.unreachable_since() = None;
+    }
+
+    /// Records that the attestation service could not be reached to verify a peer, and returns
+    /// whether the peer is accepted unverified. The grace period starts with the first peer which
+    /// could not be verified.
+    pub fn accept_unverified(&self) -> bool {
+        let Some(grace_period) = self.grace_period else {
+            return false;
+        };
+        let since = *self.unreachable_since().get_or_insert_with(Instant::get);
+        since.elapsed() <= grace_period
+    }
+}
+
 impl std::fmt::Debug for VerifyContext {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -196,19 +279,74 @@ impl std::fmt::Debug for VerifyContext {
     }
 }
 
+/// The current verify context, with the verification parameters it was created from
+///
+/// The verify context is replaced as a whole when the parameters are updated, so that a
+/// verification in progress keeps using the context it started with.
+pub struct VerifyContextCell {
+    current: RwLock<(VerifyArgs, Arc<VerifyContext>)>,
+    /// Serializes the updates, so that none of them is lost
+    update_lock: tokio::sync::Mutex<()>,
+}
+
+impl VerifyContextCell {
+    pub async fn from_verify_args(verify_args: &VerifyArgs) -> Result<Self> {
+        let verify_context = VerifyContext::from_verify_args(verify_args).await?;
+        Ok(Self {
+            current: RwLock::new((verify_args.clone(), Arc::new(verify_context))),
+            update_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Get the current verify context
+    pub fn load(&self) -> Arc<VerifyContext> {
+        self.current
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .1
+            .clone()
+    }
+
+    /// Create a verify context with the parameters updated, and replace the current one with it.
+    /// The current one is kept if the new one can not be created.
+    #[cfg_attr(wasm, allow(dead_code))]
+    pub async fn update(&self, update: &VerifyUpdate) -> Result<()> {
+        let _guard = self.update_lock.lock().await;
+
+        let verify_args = self
+            .current
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .0
+            .with_update(update)?;
+        let verify_context = VerifyContext::from_verify_args(&verify_args).await?;
+
+        *self
+            .current
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            (verify_args, Arc::new(verify_context));
+        Ok(())
+    }
+}
+
 impl VerifyContext {
     /// Create verification context from VerifyArgs configuration
     pub async fn from_verify_args(verify_args: &VerifyArgs) -> Result<Self> {
         match verify_args {
             VerifyArgs::Passport {
                 verifier: verifier_args,
+                ..
             } => {
                 let verifier = create_verifier(verifier_args).await?;
-                Ok(Self::Passport { verifier })
+                Ok(Self::Passport {
+                    verifier: LocalChecksVerifier::new(verifier, verify_args),
+                })
             }
             VerifyArgs::BackgroundCheck {
                 converter: converter_args,
                 verifier: verifier_args,
+                ..
             } => {
                 #[cfg(feature = "__builtin-as")]
                 if let ConverterArgs::Coco(CocoConverterArgs::Builtin {
@@ -222,7 +360,11 @@ impl VerifyContext {
                         CocoVerifier::Builtin(builtin_converter.new_verifier().await?);
                     return Ok(Self::BackgroundCheck {
                         converter: TngConverter::Coco(CocoConverter::Builtin(builtin_converter)),
-                        verifier: TngVerifier::Coco(builtin_verifier),
+                        verifier: LocalChecksVerifier::new(
+                            TngVerifier::Coco(builtin_verifier),
+                            verify_args,
+                        ),
+                        as_unreachable: AsUnreachablePolicy::new(verify_args.as_unreachable()),
                     });
                 }
 
@@ -230,7 +372,8 @@ impl VerifyContext {
                 let verifier = create_verifier(verifier_args).await?;
                 Ok(Self::BackgroundCheck {
                     converter,
-                    verifier,
+                    verifier: LocalChecksVerifier::new(verifier, verify_args),
+                    as_unreachable: AsUnreachablePolicy::new(verify_args.as_unreachable()),
                 })
             }
         }
@@ -241,8 +384,8 @@ impl VerifyContext {
 mod tests {
     use super::*;
     use crate::config::ra::{
-        AttesterArgs, CocoAttesterArgs, CocoConverterArgs, CocoVerifierArgs, ConverterArgs,
-        VerifierArgs,
+        AttesterArgs, CertMode, CocoAttesterArgs, CocoConverterArgs, CocoVerifierArgs,
+        ConverterArgs, VerifierArgs,
     };
     use std::collections::HashMap;
 
@@ -270,6 +413,7 @@ mod tests {
             as_addr: TEST_AS_ADDR.to_string(),
             policy_ids: vec!["default".to_string()],
             as_headers: HashMap::new(),
+            as_request: None,
         })
     }
 
@@ -299,6 +443,9 @@ mod tests {
     fn make_verify_passport_args() -> VerifyArgs {
         VerifyArgs::Passport {
             verifier: make_verifier_args_with_addr(),
+            required_claims: vec![],
+            max_evidence_age: None,
+            redact_claims: vec![],
         }
     }
 
@@ -306,6 +453,10 @@ mod tests {
         VerifyArgs::BackgroundCheck {
             converter: make_converter_args(),
             verifier: make_verifier_args_certs_only(),
+            required_claims: vec![],
+            max_evidence_age: None,
+            redact_claims: vec![],
+            as_unreachable: Default::default(),
         }
     }
 
@@ -330,6 +481,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_as_unreachable_policy() {
+        let policy = AsUnreachablePolicy::new(&AsUnreachableArgs::FailClosed);
+        assert!(!policy.accept_unverified());
+
+        let policy = AsUnreachablePolicy::new(&AsUnreachableArgs::FailOpen {
+            grace_period_secs: 60,
+        });
+        assert!(policy.accept_unverified());
+        assert!(policy.accept_unverified());
+
+        // The peers are rejected once the grace period is over
+        *policy.unreachable_since() = Some(Instant::get() - Duration::from_secs(61));
+        assert!(!policy.accept_unverified());
+
+        // while the grace period of another service has its own clock
+        let other = AsUnreachablePolicy::new(&AsUnreachableArgs::FailOpen {
+            grace_period_secs: 60,
+        });
+        assert!(other.accept_unverified());
+        assert!(!policy.accept_unverified());
+
+        // and accepted again once the attestation service was reachable in between
+        policy.reachable();
+        assert!(policy.accept_unverified());
+    }
+
     // =========================================================================
     // Section 2: VerifyOnly Tests
     // =========================================================================
@@ -368,6 +546,43 @@ mod tests {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_ra_context_update_verify() {
+        let ra_args = RaArgs::VerifyOnly(make_verify_bgcheck_args());
+        let ctx = RaContext::from_ra_args(&ra_args).await.unwrap();
+        let before = ctx
+            .verify_context()
+            .expect("VerifyOnly should have verify context");
+
+        let update = VerifyUpdate {
+            policy_ids: Some(vec!["policy-v2".to_string()]),
+            required_claims: None,
+        };
+        ctx.update_verify(&update).await.unwrap();
+        let after = ctx
+            .verify_context()
+            .expect("VerifyOnly should have verify context");
+        assert!(
+            !Arc::ptr_eq(&before, &after),
+            "The verify context should be replaced"
+        );
+
+        // The verify context is kept if the update is invalid
+        let update = VerifyUpdate {
+            policy_ids: None,
+            required_claims: Some(vec![crate::config::ra::ClaimRequirement {
+                claim: "tcb.svn".to_string(),
+                min: None,
+                allowed: None,
+            }]),
+        };
+        assert!(ctx.update_verify(&update).await.is_err());
+        assert!(Arc::ptr_eq(&after, &ctx.verify_context().unwrap()));
+
+        let ctx = RaContext::from_ra_args(&RaArgs::NoRa).await.unwrap();
+        assert!(ctx.update_verify(&VerifyUpdate::default()).await.is_err());
+    }
+
     // =========================================================================
     // Section 5: Accessor Method Tests
     // =========================================================================
@@ -400,6 +615,7 @@ mod tests {
             AttestArgs::BackgroundCheck {
                 attester: make_attester_args(),
                 refresh_interval: None,
+                cert_mode: CertMode::Shared,
             }
         }
 
@@ -408,6 +624,7 @@ mod tests {
                 attester: make_attester_args(),
                 converter: make_converter_args(),
                 refresh_interval: None,
+                cert_mode: CertMode::Shared,
             }
         }
 
@@ -446,6 +663,7 @@ mod tests {
             let attest_args = AttestArgs::BackgroundCheck {
                 attester: make_attester_args(),
                 refresh_interval: Some(600),
+                cert_mode: CertMode::Shared,
             };
             let result = AttestContext::from_attest_args(&attest_args).await;
             assert!(result.is_ok(), "Failed: {:?}", result.err());
@@ -467,6 +685,7 @@ mod tests {
             let attest_args = AttestArgs::BackgroundCheck {
                 attester: make_attester_args(),
                 refresh_interval: Some(0),
+                cert_mode: CertMode::Shared,
             };
             let result = AttestContext::from_attest_args(&attest_args).await;
             assert!(result.is_ok(), "Failed: {:?}", result.err());
@@ -613,6 +832,7 @@ mod tests {
                 attester: make_attester_args(),
                 converter: make_builtin_converter_args(),
                 refresh_interval: None,
+                cert_mode: CertMode::Shared,
             }
         }
 
@@ -620,6 +840,7 @@ mod tests {
             AttestArgs::BackgroundCheck {
                 attester: make_attester_args(),
                 refresh_interval: None,
+                cert_mode: CertMode::Shared,
             }
         }
 
@@ -627,6 +848,10 @@ mod tests {
             VerifyArgs::BackgroundCheck {
                 converter: make_builtin_converter_args(),
                 verifier: make_builtin_verifier_args(),
+                required_claims: vec![],
+                max_evidence_age: None,
+                redact_claims: vec![],
+                as_unreachable: Default::default(),
             }
         }
 
@@ -692,6 +917,10 @@ mod tests {
                     reference_values,
                 }),
                 verifier: VerifierArgs::Coco(CocoVerifierArgs::Builtin),
+                required_claims: vec![],
+                max_evidence_age: None,
+                redact_claims: vec![],
+                as_unreachable: Default::default(),
             }
         }
 
@@ -777,7 +1006,7 @@ mod tests {
             let verify = ctx
                 .verify_context()
                 .expect("VerifyOnly should have verify context");
-            assert_verify_context_builtin_background_check(verify);
+            assert_verify_context_builtin_background_check(&verify);
         }
 
         #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
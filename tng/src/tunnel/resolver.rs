@@ -0,0 +1,105 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+
+/// Resolves the domain names of the upstream endpoints, when an ingress or an egress connects to
+/// them.
+///
+/// The resolver of the system is used unless another one is set as the `resolver` of an ingress
+/// or an egress. Library users can implement this trait to look the endpoints up in their own
+/// service discovery, e.g. the registry of a service mesh, instead of the DNS. The endpoints
+/// configured with an IPv4 address are connected to as they are.
+#[async_trait]
+pub trait Resolver: Send + Sync + 'static {
+    /// Returns the addresses of `host`, which are tried in order until one of them is connected.
+    ///
+    /// `port` is the port of the endpoint, which the returned addresses should carry. It is `0`
+    /// for the HTTP clients (e.g. of the `ohttp` ingresses), which set the port of the URL on the
+    /// returned addresses themselves.
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>>;
+}
+
+/// Resolves with the resolver of the system, i.e. `getaddrinfo(3)`.
+pub struct SystemResolver;
+
+#[async_trait]
+impl Resolver for SystemResolver {
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        Ok(tokio::net::lookup_host((host, port))
+            .await
+            .context("Failed to resolve via dns")?
+            .collect())
+    }
+}
+
+/// Adapts a [`Resolver`] to the reqwest clients.
+pub(crate) struct ReqwestResolver(pub Arc<dyn Resolver>);
+
+impl reqwest::dns::Resolve for ReqwestResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let addrs = resolver
+                .resolve(name.as_str(), 0)
+                .await
+                .map_err(Box::<dyn std::error::Error + Send + Sync>::from)?;
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::tunnel::endpoint::TngEndpoint;
+
+    use super::*;
+
+    /// Resolves the names in a fixed table.
+    struct StaticResolver(HashMap<String, SocketAddr>);
+
+    #[async_trait]
+    impl Resolver for StaticResolver {
+        async fn resolve(&self, host: &str, _port: u16) -> Result<Vec<SocketAddr>> {
+            self.0
+                .get(host)
+                .map(|addr| vec![*addr])
+                .with_context(|| format!("Unknown service '{host}'"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_resolver() -> Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let resolver = StaticResolver(HashMap::from([(
+            "upstream.mesh.internal".to_owned(),
+            listener.local_addr()?,
+        )]));
+
+        let endpoint = TngEndpoint::from_domain("upstream.mesh.internal".to_owned(), 80);
+        let stream = endpoint
+            .tcp_connect(
+                #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                None,
+                Some(&resolver),
+            )
+            .await?;
+        let (_, peer) = listener.accept().await?;
+        assert_eq!(stream.local_addr()?, peer);
+
+        let endpoint = TngEndpoint::from_domain("unknown.mesh.internal".to_owned(), 80);
+        let error = endpoint
+            .tcp_connect(
+                #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                None,
+                Some(&resolver),
+            )
+            .await
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("Unknown service"));
+
+        Ok(())
+    }
+}
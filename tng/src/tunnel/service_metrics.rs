@@ -1,40 +1,124 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use indexmap::IndexMap;
-use opentelemetry::metrics::{Counter, MeterProvider, UpDownCounter};
+use opentelemetry::metrics::{Counter, Histogram, Meter, MeterProvider, UpDownCounter};
+use web_time_compat::{Instant, InstantExt as _};
 
+use crate::config::observability::metric::DestinationLabelsArgs;
 use crate::observability::metric::{
     counter::{AttributedCounter, WithAttributes},
+    simple_exporter::noop::NoopMeterProvider,
     stream::{PendingCounter, StreamWithCounter},
 };
+use crate::tunnel::endpoint::TngEndpoint;
 
-pub struct ServiceMetricsCreator(Arc<dyn MeterProvider + Send + Sync>);
+/// Bucket boundaries in seconds of the latency histograms, from the time of a handshake within the
+/// same host to a slow remote attestation.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// The `dst` label of the connections to the destinations beyond `max_destinations`.
+const OTHER_DESTINATION: &str = "other";
+
+/// Bucket boundaries in seconds of the connection duration histogram.
+const DURATION_BUCKETS: &[f64] = &[
+    0.01, 0.1, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 600.0, 1800.0, 3600.0,
+];
+
+/// A phase of establishing a secure session with the peer, recorded in
+/// `handshake_phase_duration` with the `phase` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakePhase {
+    /// Connecting the transport to the peer, e.g. the TCP connection.
+    Transport,
+    /// The TLS handshake, excluding the verification of the peer's evidence.
+    Tls,
+    /// Verifying the evidence of the peer, e.g. with the attestation service.
+    Verification,
+}
+
+impl HandshakePhase {
+    const ALL: [HandshakePhase; 3] = [
+        HandshakePhase::Transport,
+        HandshakePhase::Tls,
+        HandshakePhase::Verification,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            HandshakePhase::Transport => "transport",
+            HandshakePhase::Tls => "tls",
+            HandshakePhase::Verification => "verification",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ServiceMetricsCreator {
+    meter_provider: Arc<dyn MeterProvider + Send + Sync>,
+    destination_labels: Option<DestinationLabelsArgs>,
+}
 
 impl ServiceMetricsCreator {
     pub fn new_creator(
         meter_provider: Arc<dyn MeterProvider + Send + Sync>,
+        destination_labels: Option<DestinationLabelsArgs>,
     ) -> ServiceMetricsCreator {
-        ServiceMetricsCreator(meter_provider)
+        ServiceMetricsCreator {
+            meter_provider,
+            destination_labels,
+        }
     }
 
     pub fn new_service_metrics(
         &self,
         attributes: impl Into<IndexMap<String, String>>,
     ) -> ServiceMetrics {
-        ServiceMetrics::new(self.0.clone(), attributes)
+        let mut metrics = ServiceMetrics::new(self.meter_provider.clone(), attributes);
+        if let Some(args) = &self.destination_labels {
+            metrics.destinations = Some(Arc::new(DestinationMetrics {
+                max_destinations: args.max_destinations,
+                labeled: Default::default(),
+            }));
+        }
+        metrics
     }
 }
 
+/// The metrics labeled with the destination endpoints, shared by all clones of a
+/// [`ServiceMetrics`].
+#[derive(Debug)]
+struct DestinationMetrics {
+    max_destinations: usize,
+    /// Keyed by the `dst` label, including [`OTHER_DESTINATION`] once the cap is reached.
+    labeled: Mutex<HashMap<String, ServiceMetrics>>,
+}
+
 /// ServiceMetrics is a set of metrics for a service.
 ///
-/// This struct is free be cloned and used anywhere.
+/// This struct is free to be cloned and used anywhere.
 #[derive(Debug, Clone)]
 pub struct ServiceMetrics {
+    meter: Meter,
+    attributes: Arc<IndexMap<String, String>>,
     cx_total: AttributedCounter<Counter<u64>, u64>,
     cx_active: AttributedCounter<UpDownCounter<i64>, i64>,
     cx_failed: AttributedCounter<Counter<u64>, u64>,
+    cx_rejected: AttributedCounter<Counter<u64>, u64>,
+    cx_rejected_per_source: AttributedCounter<Counter<u64>, u64>,
+    attestation_degraded_total: AttributedCounter<Counter<u64>, u64>,
     tx_bytes_total: AttributedCounter<Counter<u64>, u64>,
     rx_bytes_total: AttributedCounter<Counter<u64>, u64>,
+    handshake_duration: AttributedCounter<Histogram<f64>, f64>,
+    /// Indexed by [`HandshakePhase`].
+    handshake_phase_duration: [AttributedCounter<Histogram<f64>, f64>; 3],
+    cx_first_byte_duration: AttributedCounter<Histogram<f64>, f64>,
+    cx_duration: AttributedCounter<Histogram<f64>, f64>,
+    /// Set if the connection and bytes metrics are labeled with the destination.
+    destinations: Option<Arc<DestinationMetrics>>,
 }
 
 impl ServiceMetrics {
@@ -66,6 +150,33 @@ impl ServiceMetrics {
             .with_attributes(attributes.clone());
         cx_failed.add(0);
 
+        let cx_rejected = meter
+            .u64_counter("cx_rejected")
+            .with_description(
+                "Total number of connections rejected since the service is overloaded",
+            )
+            .build()
+            .with_attributes(attributes.clone());
+        cx_rejected.add(0);
+
+        let cx_rejected_per_source = meter
+            .u64_counter("cx_rejected_per_source")
+            .with_description(
+                "Total number of connections rejected since their source has too many connections",
+            )
+            .build()
+            .with_attributes(attributes.clone());
+        cx_rejected_per_source.add(0);
+
+        let attestation_degraded_total = meter
+            .u64_counter("attestation_degraded_total")
+            .with_description(
+                "Total peers accepted without being verified, since the attestation service was unreachable",
+            )
+            .build()
+            .with_attributes(attributes.clone());
+        attestation_degraded_total.add(0);
+
         let tx_bytes_total = meter
             .u64_counter("tx_bytes_total")
             .with_unit("bytes")
@@ -82,23 +193,167 @@ impl ServiceMetrics {
             .with_attributes(attributes.clone());
         rx_bytes_total.add(0);
 
+        let handshake_duration = meter
+            .f64_histogram("handshake_duration")
+            .with_unit("s")
+            .with_description(
+                "Time to establish a secure session with the peer, including the remote attestation",
+            )
+            .with_boundaries(LATENCY_BUCKETS.to_vec())
+            .build()
+            .with_attributes(attributes.clone());
+
+        let histogram = meter
+            .f64_histogram("handshake_phase_duration")
+            .with_unit("s")
+            .with_description("Time spent in each phase of establishing a secure session")
+            .with_boundaries(LATENCY_BUCKETS.to_vec())
+            .build();
+        let handshake_phase_duration = HandshakePhase::ALL.map(|phase| {
+            let mut attributes = (*attributes).clone();
+            attributes.insert("phase".to_owned(), phase.as_str().to_owned());
+            histogram.clone().with_attributes(Arc::new(attributes))
+        });
+
+        let cx_first_byte_duration = meter
+            .f64_histogram("cx_first_byte_duration")
+            .with_unit("s")
+            .with_description(
+                "Time from accepting a connection to sending the first byte from the upstream",
+            )
+            .with_boundaries(LATENCY_BUCKETS.to_vec())
+            .build()
+            .with_attributes(attributes.clone());
+
+        let cx_duration = meter
+            .f64_histogram("cx_duration")
+            .with_unit("s")
+            .with_description("Time from accepting a connection to closing it")
+            .with_boundaries(DURATION_BUCKETS.to_vec())
+            .build()
+            .with_attributes(attributes.clone());
+
         Self {
+            meter,
+            attributes,
             cx_total,
             cx_active,
             cx_failed,
+            cx_rejected,
+            cx_rejected_per_source,
+            attestation_degraded_total,
             tx_bytes_total,
             rx_bytes_total,
+            handshake_duration,
+            handshake_phase_duration,
+            cx_first_byte_duration,
+            cx_duration,
+            destinations: None,
         }
     }
 
+    /// Metrics which are not exported, for the stream managers used outside of a
+    /// [`TngRuntime`](crate::runtime::TngRuntime).
+    pub fn noop() -> Self {
+        Self::new(
+            Arc::new(NoopMeterProvider::new()),
+            IndexMap::<String, String>::new(),
+        )
+    }
+
+    /// The metrics of the connections to the destination. If destination labels are enabled, the
+    /// connection and bytes metrics are labeled with `dst={host}:{port}`, or `dst=other` once
+    /// `max_destinations` distinct destinations have been labeled. Otherwise the metrics are
+    /// returned unchanged.
+    pub fn for_destination(&self, dst: &TngEndpoint) -> ServiceMetrics {
+        let Some(destinations) = &self.destinations else {
+            return self.clone();
+        };
+
+        let mut labeled = match destinations.labeled.lock() {
+            Ok(labeled) => labeled,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut dst = dst.to_string();
+        if !labeled.contains_key(&dst) {
+            let distinct = labeled.len() - labeled.contains_key(OTHER_DESTINATION) as usize;
+            if distinct >= destinations.max_destinations {
+                dst = OTHER_DESTINATION.to_owned();
+            }
+        }
+        labeled
+            .entry(dst)
+            .or_insert_with_key(|dst| self.with_destination_label(dst))
+            .clone()
+    }
+
+    fn with_destination_label(&self, dst: &str) -> ServiceMetrics {
+        fn labeled<C: Clone + WithAttributes<T>, T>(
+            counter: &AttributedCounter<C, T>,
+            dst: &str,
+        ) -> AttributedCounter<C, T> {
+            let mut attributes = (*counter.attributes).clone();
+            attributes.insert("dst".to_owned(), dst.to_owned());
+            counter.inner.clone().with_attributes(Arc::new(attributes))
+        }
+
+        ServiceMetrics {
+            cx_total: labeled(&self.cx_total, dst),
+            cx_active: labeled(&self.cx_active, dst),
+            cx_failed: labeled(&self.cx_failed, dst),
+            cx_duration: labeled(&self.cx_duration, dst),
+            tx_bytes_total: labeled(&self.tx_bytes_total, dst),
+            rx_bytes_total: labeled(&self.rx_bytes_total, dst),
+            destinations: None,
+            ..self.clone()
+        }
+    }
+
+    /// The meter used by this service, for components which register their own instruments.
+    pub fn meter(&self) -> &Meter {
+        &self.meter
+    }
+
+    /// The attributes attached to every metric of this service.
+    pub fn attributes(&self) -> Arc<IndexMap<String, String>> {
+        self.attributes.clone()
+    }
+
     pub fn new_cx(&self) -> ActiveConnectionCounter {
         ActiveConnectionCounter::new(
             self.cx_total.clone(),
             self.cx_active.clone(),
             self.cx_failed.clone(),
+            self.cx_duration.clone(),
         )
     }
 
+    /// Record a connection rejected since the service is overloaded.
+    pub fn record_rejected(&self) {
+        self.cx_rejected.add(1);
+    }
+
+    /// Record a connection rejected since its source has too many connections.
+    pub fn record_rejected_per_source(&self) {
+        self.cx_rejected_per_source.add(1);
+    }
+
+    /// Record a peer accepted without being verified, since the attestation service was
+    /// unreachable.
+    pub fn record_degraded(&self) {
+        self.attestation_degraded_total.add(1);
+    }
+
+    /// Record the time taken to establish a secure session, e.g. a rats-tls handshake.
+    pub fn record_handshake(&self, duration: Duration) {
+        self.handshake_duration.record(duration.as_secs_f64());
+    }
+
+    /// Record the time spent in a phase of establishing a secure session.
+    pub fn record_handshake_phase(&self, phase: HandshakePhase, duration: Duration) {
+        self.handshake_phase_duration[phase as usize].record(duration.as_secs_f64());
+    }
+
     pub fn new_wrapped_stream<
         T: tokio::io::AsyncRead + tokio::io::AsyncWrite + std::marker::Unpin,
     >(
@@ -109,6 +364,7 @@ impl ServiceMetrics {
             inner: stream,
             tx: PendingCounter::new(self.tx_bytes_total.clone()),
             rx: PendingCounter::new(self.rx_bytes_total.clone()),
+            first_byte: Some((Instant::get(), self.cx_first_byte_duration.clone())),
         }
     }
 }
@@ -116,6 +372,8 @@ impl ServiceMetrics {
 pub struct ActiveConnectionCounter {
     cx_active: AttributedCounter<UpDownCounter<i64>, i64>,
     cx_failed: AttributedCounter<Counter<u64>, u64>,
+    cx_duration: AttributedCounter<Histogram<f64>, f64>,
+    accepted_at: Instant,
     finished_successfully: bool,
 }
 
@@ -124,6 +382,7 @@ impl ActiveConnectionCounter {
         cx_total: AttributedCounter<Counter<u64>, u64>,
         cx_active: AttributedCounter<UpDownCounter<i64>, i64>,
         cx_failed: AttributedCounter<Counter<u64>, u64>,
+        cx_duration: AttributedCounter<Histogram<f64>, f64>,
     ) -> Self {
         cx_total.add(1);
         cx_active.add(1);
@@ -131,6 +390,8 @@ impl ActiveConnectionCounter {
         Self {
             cx_active,
             cx_failed,
+            cx_duration,
+            accepted_at: Instant::get(),
             finished_successfully: false,
         }
     }
@@ -147,5 +408,61 @@ impl Drop for ActiveConnectionCounter {
             self.cx_failed.add(1);
         }
         self.cx_active.add(-1);
+        self.cx_duration
+            .record(self.accepted_at.elapsed().as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::observability::metric::simple_exporter::noop::NoopMeterProvider;
+
+    use super::*;
+
+    #[test]
+    fn test_destination_labels_cap() {
+        let dst_label = |metrics: &ServiceMetrics| metrics.cx_total.attributes.get("dst").cloned();
+
+        let creator = ServiceMetricsCreator::new_creator(Arc::new(NoopMeterProvider::new()), None);
+        let metrics = creator.new_service_metrics([("egress_id".to_owned(), "0".to_owned())]);
+        assert_eq!(
+            dst_label(&metrics.for_destination(&TngEndpoint::new("10.0.0.1", 80))),
+            None
+        );
+
+        let creator = ServiceMetricsCreator::new_creator(
+            Arc::new(NoopMeterProvider::new()),
+            Some(DestinationLabelsArgs {
+                max_destinations: 2,
+            }),
+        );
+        let metrics = creator.new_service_metrics([("egress_id".to_owned(), "0".to_owned())]);
+        let labeled = metrics.for_destination(&TngEndpoint::new("10.0.0.1", 80));
+        assert_eq!(dst_label(&labeled), Some("10.0.0.1:80".to_owned()));
+        assert_eq!(
+            labeled.tx_bytes_total.attributes.get("egress_id"),
+            Some(&"0".to_owned())
+        );
+
+        for (dst, expected) in [
+            (TngEndpoint::new("example.com", 443), "example.com:443"),
+            (TngEndpoint::new("10.0.0.2", 80), "other"),
+            (TngEndpoint::new("10.0.0.3", 80), "other"),
+            (TngEndpoint::new("10.0.0.1", 80), "10.0.0.1:80"),
+        ] {
+            assert_eq!(
+                dst_label(&metrics.for_destination(&dst)),
+                Some(expected.to_owned())
+            );
+        }
+        // The clones of the metrics share the labeled destinations
+        assert_eq!(
+            dst_label(
+                &metrics
+                    .clone()
+                    .for_destination(&TngEndpoint::new("10.0.0.4", 80))
+            ),
+            Some("other".to_owned())
+        );
     }
 }
@@ -1,4 +1,5 @@
 use std::{
+    any::Any,
     io,
     pin::Pin,
     task::{Context, Poll},
@@ -6,9 +7,21 @@ use std::{
 
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
-pub trait CommonStreamTrait: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+pub trait CommonStreamTrait: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+    /// The concrete stream, e.g. to find out whether a boxed stream is a plain TCP socket. Note
+    /// that on a `Box<dyn CommonStreamTrait>` this must be called as `(*stream).as_any()`, since
+    /// the box itself implements this trait too.
+    fn as_any(&self) -> &dyn Any;
+}
 
-impl<T> CommonStreamTrait for T where T: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+impl<T> CommonStreamTrait for T
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
 
 /// Wraps a stream and tags IO errors with a source identifier, so error logs
 /// can distinguish which component the stream came from.
@@ -0,0 +1,190 @@
+//! A pool of the buffers which the streams are forwarded with. Each forwarded stream needs a
+//! buffer of `buffer_size.forward` bytes for each direction, so with tens of thousands of short
+//! connections, allocating and freeing them for every stream puts a lot of pressure on the
+//! allocator. The buffers are returned to the pool when the stream is finished, and reused by the
+//! next stream asking for the same size.
+
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use bytes::BytesMut;
+
+/// The most bytes kept idle in the pool. The buffers returned beyond it are freed, so that a burst
+/// of connections does not hold the memory forever.
+const MAX_IDLE_BYTES: usize = 64 * 1024 * 1024;
+
+/// The counters of a [`BufferPool`], which are exported as the `buffer_pool_*` metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    /// The buffers handed out and not returned yet.
+    pub in_use_buffers: u64,
+    /// The buffers kept in the pool for reuse.
+    pub idle_buffers: u64,
+    /// The bytes of the buffers kept in the pool.
+    pub idle_bytes: u64,
+    /// Total buffers newly allocated, since the pool had none of the size.
+    pub allocated_total: u64,
+    /// Total buffers handed out from the pool.
+    pub reused_total: u64,
+}
+
+#[derive(Debug)]
+pub struct BufferPool {
+    /// The idle buffers, keyed by their size.
+    idle: Mutex<HashMap<usize, Vec<BytesMut>>>,
+    max_idle_bytes: usize,
+    idle_buffers: AtomicUsize,
+    idle_bytes: AtomicUsize,
+    in_use_buffers: AtomicUsize,
+    allocated_total: AtomicU64,
+    reused_total: AtomicU64,
+}
+
+impl BufferPool {
+    fn new(max_idle_bytes: usize) -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+            max_idle_bytes,
+            idle_buffers: AtomicUsize::new(0),
+            idle_bytes: AtomicUsize::new(0),
+            in_use_buffers: AtomicUsize::new(0),
+            allocated_total: AtomicU64::new(0),
+            reused_total: AtomicU64::new(0),
+        }
+    }
+
+    /// The pool shared by all the ingresses and egresses of the process.
+    pub fn global() -> &'static BufferPool {
+        static POOL: OnceLock<BufferPool> = OnceLock::new();
+        POOL.get_or_init(|| BufferPool::new(MAX_IDLE_BYTES))
+    }
+
+    /// Returns a buffer of `size` bytes, which goes back to the pool when dropped. The content of
+    /// a reused buffer is left from its last user.
+    pub fn acquire(&'static self, size: usize) -> PooledBuffer {
+        let reused = self
+            .idle
+            .lock()
+            .ok()
+            .and_then(|mut idle| idle.get_mut(&size).and_then(Vec::pop));
+
+        let buf = match reused {
+            Some(buf) => {
+                self.idle_buffers.fetch_sub(1, Ordering::Relaxed);
+                self.idle_bytes.fetch_sub(size, Ordering::Relaxed);
+                self.reused_total.fetch_add(1, Ordering::Relaxed);
+                buf
+            }
+            None => {
+                self.allocated_total.fetch_add(1, Ordering::Relaxed);
+                BytesMut::zeroed(size)
+            }
+        };
+        self.in_use_buffers.fetch_add(1, Ordering::Relaxed);
+        PooledBuffer { pool: self, buf }
+    }
+
+    fn release(&self, buf: BytesMut) {
+        self.in_use_buffers.fetch_sub(1, Ordering::Relaxed);
+
+        let size = buf.len();
+        let reserved = self
+            .idle_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |idle_bytes| {
+                idle_bytes
+                    .checked_add(size)
+                    .filter(|idle_bytes| *idle_bytes <= self.max_idle_bytes)
+            })
+            .is_ok();
+        if !reserved {
+            return;
+        }
+
+        match self.idle.lock() {
+            Ok(mut idle) => {
+                idle.entry(size).or_default().push(buf);
+                self.idle_buffers.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(_) => {
+                self.idle_bytes.fetch_sub(size, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        BufferPoolStats {
+            in_use_buffers: self.in_use_buffers.load(Ordering::Relaxed) as u64,
+            idle_buffers: self.idle_buffers.load(Ordering::Relaxed) as u64,
+            idle_bytes: self.idle_bytes.load(Ordering::Relaxed) as u64,
+            allocated_total: self.allocated_total.load(Ordering::Relaxed),
+            reused_total: self.reused_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A buffer borrowed from a [`BufferPool`].
+pub struct PooledBuffer {
+    pool: &'static BufferPool,
+    buf: BytesMut,
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        self.pool.release(std::mem::take(&mut self.buf));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_pool_reuse() {
+        let pool: &'static BufferPool = Box::leak(Box::new(BufferPool::new(3 * 1024)));
+
+        let first = pool.acquire(1024);
+        let second = pool.acquire(1024);
+        assert_eq!(first.len(), 1024);
+        drop(first);
+        drop(second);
+        assert_eq!(
+            pool.stats(),
+            BufferPoolStats {
+                in_use_buffers: 0,
+                idle_buffers: 2,
+                idle_bytes: 2048,
+                allocated_total: 2,
+                reused_total: 0,
+            }
+        );
+
+        let reused = pool.acquire(1024);
+        let other_size = pool.acquire(2048);
+        assert_eq!(pool.stats().reused_total, 1);
+        assert_eq!(pool.stats().allocated_total, 3);
+
+        // Only one of them fits in the idle bytes left
+        drop(other_size);
+        drop(reused);
+        let stats = pool.stats();
+        assert_eq!(stats.in_use_buffers, 0);
+        assert_eq!(stats.idle_bytes, 3072);
+        assert_eq!(stats.idle_buffers, 2);
+    }
+}
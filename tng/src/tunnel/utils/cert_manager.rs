@@ -77,7 +77,7 @@ impl rustls::client::ResolvesClientCert for DynamicCertResolver {
 
 impl CertManager {
     pub async fn new(attest_ctx: Arc<AttestContext>, runtime: TokioRuntime) -> Result<Self> {
-        let refresh_strategy = attest_ctx.refresh_strategy();
+        let refresh_strategy = attest_ctx.cert_refresh_strategy();
 
         let cert = MaybeCached::new(runtime, refresh_strategy, move || {
             let attest_ctx = attest_ctx.clone();
@@ -173,6 +173,14 @@ impl CertManager {
         Ok((certified_key, expired))
     }
 
+    /// Returns a receiver notified each time the certificate is replaced by a new one, or `None`
+    /// if a new certificate is generated for every handshake.
+    pub fn subscribe_rotation(
+        &self,
+    ) -> Option<tokio::sync::watch::Receiver<Arc<rustls::sign::CertifiedKey>>> {
+        self.cert.subscribe()
+    }
+
     #[cfg(test)]
     pub async fn get_latest_cert(&self) -> Result<Arc<rustls::sign::CertifiedKey>> {
         self.cert.get_latest().await
@@ -206,7 +214,7 @@ mod tests {
     use anyhow::bail;
 
     use crate::{
-        config::ra::{AttestArgs, AttesterArgs, CocoAttesterArgs},
+        config::ra::{AttestArgs, AttesterArgs, CertMode, CocoAttesterArgs},
         tests::run_test_with_tokio_runtime,
     };
 
@@ -222,6 +230,7 @@ mod tests {
                             .to_owned(),
                 }),
                 refresh_interval: Some(3),
+                cert_mode: CertMode::Shared,
             }).await?;
             let mut cert_manager = CertManager::new(Arc::new(attest_ctx), runtime).await?;
 
@@ -275,6 +284,7 @@ mod tests {
                             .to_owned(),
                 }),
                 refresh_interval: Some(0),
+                cert_mode: CertMode::Shared,
             }).await?;
             let cert_manager = CertManager::new(Arc::new(attest_ctx), runtime).await?;
 
@@ -0,0 +1,185 @@
+//! Circuit breaking of the connections to the upstream endpoints. After an endpoint failed to be
+//! connected `failure_threshold` times in a row, its circuit is opened: the connections to it fail
+//! immediately with [`TngError::UpstreamCircuitOpen`] for `cooldown_secs`, instead of tying up the
+//! streams until the connect attempts time out. Once the cooldown is over, a single connection is
+//! let through to probe the endpoint, and the circuit is closed again if it succeeds.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use web_time_compat::{Instant, InstantExt as _};
+
+use crate::config::circuit_breaker::CircuitBreakerArgs;
+use crate::error::TngError;
+use crate::tunnel::endpoint::TngEndpoint;
+
+#[derive(Debug, Default)]
+struct EndpointState {
+    consecutive_failures: u32,
+    /// Set while the circuit is open. Once it is passed, the circuit is opened again for another
+    /// cooldown while the probing connection is in flight, so that a probe which never completes
+    /// does not keep the circuit half-open.
+    open_until: Option<Instant>,
+}
+
+/// The circuit breaker of the upstream endpoints of an ingress or egress. The state is shared by
+/// all the clones.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    /// The connections are never failed fast if not set.
+    inner: Option<Arc<BreakerInner>>,
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    failure_threshold: u32,
+    cooldown: Duration,
+    /// Only the endpoints which failed since their last successful connection are kept.
+    endpoints: Mutex<HashMap<TngEndpoint, EndpointState>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(args: Option<&CircuitBreakerArgs>) -> Result<Self> {
+        let inner = match args {
+            Some(args) => {
+                args.validate()?;
+                Some(Arc::new(BreakerInner::new(
+                    args.failure_threshold,
+                    args.cooldown(),
+                )))
+            }
+            None => None,
+        };
+        Ok(Self { inner })
+    }
+
+    /// Connects to `endpoint` with `connect`, unless the circuit of the endpoint is open, in which
+    /// case [`TngError::UpstreamCircuitOpen`] is returned without calling it.
+    pub async fn connect<T>(
+        &self,
+        endpoint: &TngEndpoint,
+        connect: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let Some(inner) = &self.inner else {
+            return connect.await;
+        };
+
+        inner.acquire(endpoint)?;
+        let result = connect.await;
+        match &result {
+            Ok(_) => inner.record_success(endpoint),
+            Err(_) => inner.record_failure(endpoint),
+        }
+        result
+    }
+}
+
+impl BreakerInner {
+    fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            endpoints: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn acquire(&self, endpoint: &TngEndpoint) -> Result<(), TngError> {
+        let Ok(mut endpoints) = self.endpoints.lock() else {
+            return Ok(());
+        };
+        let Some(open_until) = endpoints
+            .get_mut(endpoint)
+            .and_then(|state| state.open_until.as_mut())
+        else {
+            return Ok(());
+        };
+
+        let now = Instant::get();
+        if now < *open_until {
+            return Err(TngError::UpstreamCircuitOpen(endpoint.to_string()));
+        }
+        // Let this connection probe the endpoint, while the others keep failing fast
+        *open_until = now + self.cooldown;
+        tracing::debug!(%endpoint, "Probing the upstream with an open circuit");
+        Ok(())
+    }
+
+    fn record_success(&self, endpoint: &TngEndpoint) {
+        let Ok(mut endpoints) = self.endpoints.lock() else {
+            return;
+        };
+        if endpoints
+            .remove(endpoint)
+            .is_some_and(|state| state.open_until.is_some())
+        {
+            tracing::info!(%endpoint, "The upstream is reachable again, closing its circuit");
+        }
+    }
+
+    fn record_failure(&self, endpoint: &TngEndpoint) {
+        let Ok(mut endpoints) = self.endpoints.lock() else {
+            return;
+        };
+        let state = endpoints.entry(endpoint.clone()).or_default();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.consecutive_failures < self.failure_threshold {
+            return;
+        }
+
+        if state.open_until.is_none() {
+            tracing::warn!(
+                %endpoint,
+                consecutive_failures = state.consecutive_failures,
+                cooldown = ?self.cooldown,
+                "Opening the circuit of the upstream since it failed to be connected too many times in a row"
+            );
+        }
+        state.open_until = Some(Instant::get() + self.cooldown);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn is_circuit_open<T>(result: &Result<T>) -> bool {
+        matches!(
+            result
+                .as_ref()
+                .err()
+                .and_then(|error| error.downcast_ref::<TngError>()),
+            Some(TngError::UpstreamCircuitOpen(..))
+        )
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let breaker = CircuitBreaker {
+            inner: Some(Arc::new(BreakerInner::new(2, Duration::from_millis(100)))),
+        };
+        let endpoint = TngEndpoint::from_ipv4(Ipv4Addr::LOCALHOST, 8080);
+        let other = TngEndpoint::from_ipv4(Ipv4Addr::LOCALHOST, 8081);
+
+        for _ in 0..2 {
+            let result: Result<()> = breaker
+                .connect(&endpoint, async { anyhow::bail!("connection refused") })
+                .await;
+            assert!(result.is_err() && !is_circuit_open(&result));
+        }
+
+        // The circuit is open now, so the connection fails without being tried
+        let result = breaker.connect(&endpoint, async { Ok(()) }).await;
+        assert!(is_circuit_open(&result));
+        assert!(breaker.connect(&other, async { Ok(()) }).await.is_ok());
+
+        // A probe is let through after the cooldown, and closes the circuit when it succeeds
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(breaker.connect(&endpoint, async { Ok(()) }).await.is_ok());
+        assert!(breaker.connect(&endpoint, async { Ok(()) }).await.is_ok());
+    }
+}
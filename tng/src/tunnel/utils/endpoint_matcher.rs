@@ -114,19 +114,31 @@ impl PortMatch {
 #[derive(Debug)]
 pub struct EndpointMatcher {
     items: Vec<EndpointMatcherItem>,
+    /// The rules with `exclude`, which take priority over `items` whatever their order.
+    excluded: Vec<EndpointMatcherItem>,
 }
 
 impl EndpointMatcher {
     pub fn new(dst_filters: &[EndpointMatcherConfig]) -> Result<Self> {
-        let items = dst_filters
-            .iter()
-            .map(EndpointMatcherItem::from_config)
-            .collect::<Result<Vec<_>>>()?;
+        let mut items = vec![];
+        let mut excluded = vec![];
+        for config in dst_filters {
+            let item = EndpointMatcherItem::from_config(config)?;
+            if config.exclude {
+                excluded.push(item);
+            } else {
+                items.push(item);
+            }
+        }
 
-        Ok(Self { items })
+        Ok(Self { items, excluded })
     }
 
     pub fn matches(&self, endpoint: &TngEndpoint) -> bool {
+        if self.excluded.iter().any(|item| item.matches(endpoint)) {
+            return false;
+        }
+
         if self.items.is_empty() {
             return true;
         }
@@ -164,6 +176,10 @@ impl EndpointMatcherItem {
 }
 
 /// This is a matcher that compatible with the [envoy domain matcher](https://www.envoyproxy.io/docs/envoy/latest/api-v3/config/route/v3/route_components.proto#config-route-v3-virtualhost).
+///
+/// Like domain names, the patterns are case-insensitive, and the trailing dot of a fully qualified
+/// domain name is ignored. The wildcard matches at least one character, so `*.foo.com` does not
+/// match `foo.com` nor `.foo.com`.
 #[allow(dead_code)]
 #[derive(Debug)]
 pub(crate) enum EnvoyDomainMatcher {
@@ -180,6 +196,8 @@ pub(crate) enum EnvoyDomainMatcher {
 #[allow(dead_code)]
 impl EnvoyDomainMatcher {
     pub(crate) fn new(domain: &str) -> Result<Self> {
+        let domain = domain.to_ascii_lowercase();
+        let domain = domain.strip_suffix('.').unwrap_or(&domain);
         if domain == "*" {
             Ok(EnvoyDomainMatcher::MatchAny)
         } else if let Some(stripped) = domain.strip_prefix('*') {
@@ -195,10 +213,16 @@ impl EnvoyDomainMatcher {
 
     #[inline]
     pub(crate) fn is_match(&self, haystack: &str) -> bool {
+        let haystack = haystack.strip_suffix('.').unwrap_or(haystack).as_bytes();
         match self {
-            EnvoyDomainMatcher::Exact(s) => haystack == s,
-            EnvoyDomainMatcher::Suffix(s) => haystack.ends_with(s),
-            EnvoyDomainMatcher::Prefix(s) => haystack.starts_with(s),
+            EnvoyDomainMatcher::Exact(s) => haystack.eq_ignore_ascii_case(s.as_bytes()),
+            EnvoyDomainMatcher::Suffix(s) => {
+                haystack.len() > s.len()
+                    && haystack[haystack.len() - s.len()..].eq_ignore_ascii_case(s.as_bytes())
+            }
+            EnvoyDomainMatcher::Prefix(s) => {
+                haystack.len() > s.len() && haystack[..s.len()].eq_ignore_ascii_case(s.as_bytes())
+            }
             EnvoyDomainMatcher::MatchAny => true,
         }
     }
@@ -313,6 +337,73 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_domain_match_normalization() -> Result<()> {
+        let endpoint_matcher = EndpointMatcher::new(&[serde_json::from_value(json!({
+            "domain": "*.Foo.com."
+        }))?])?;
+        assert!(endpoint_matcher.matches(&TngEndpoint::new("www.foo.com", 80)));
+        assert!(endpoint_matcher.matches(&TngEndpoint::new("WWW.FOO.COM.", 80)));
+        // The wildcard does not match the empty string
+        assert!(!endpoint_matcher.matches(&TngEndpoint::new("foo.com", 80)));
+        assert!(!endpoint_matcher.matches(&TngEndpoint::new(".foo.com", 80)));
+
+        let endpoint_matcher = EndpointMatcher::new(&[serde_json::from_value(json!({
+            "domain": "api.foo.com"
+        }))?])?;
+        assert!(endpoint_matcher.matches(&TngEndpoint::new("API.foo.com", 80)));
+        assert!(endpoint_matcher.matches(&TngEndpoint::new("api.foo.com.", 80)));
+        assert!(!endpoint_matcher.matches(&TngEndpoint::new("api.foo.co", 80)));
+
+        let endpoint_matcher = EndpointMatcher::new(&[serde_json::from_value(json!({
+            "domain": "foo-*"
+        }))?])?;
+        assert!(endpoint_matcher.matches(&TngEndpoint::new("Foo-bar.com", 80)));
+        assert!(!endpoint_matcher.matches(&TngEndpoint::new("foo-", 80)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_match() -> Result<()> {
+        // Only excluding rules: everything else is matched
+        let endpoint_matcher = EndpointMatcher::new(&[
+            serde_json::from_value(json!({ "domain": "*.internal", "exclude": true }))?,
+            serde_json::from_value(json!({ "ip_cidr": "10.0.0.0/8", "exclude": true }))?,
+        ])?;
+        assert!(!endpoint_matcher.matches(&TngEndpoint::new("db.internal", 5432)));
+        assert!(!endpoint_matcher.matches(&TngEndpoint::new("10.1.2.3", 80)));
+        assert!(endpoint_matcher.matches(&TngEndpoint::new("www.foo.com", 80)));
+        assert!(endpoint_matcher.matches(&TngEndpoint::new("192.168.1.1", 80)));
+
+        // The excluding rules take priority over the overlapping ones, whatever their order
+        let endpoint_matcher = EndpointMatcher::new(&[
+            serde_json::from_value(json!({ "domain": "*.foo.com" }))?,
+            serde_json::from_value(json!({ "domain": "admin.foo.com", "exclude": true }))?,
+            serde_json::from_value(json!({ "domain_regex": "^api[0-9]+\\.foo\\.com$" }))?,
+            serde_json::from_value(json!({
+                "domain": "*",
+                "port": 22,
+                "exclude": true
+            }))?,
+        ])?;
+        assert!(endpoint_matcher.matches(&TngEndpoint::new("www.foo.com", 80)));
+        assert!(endpoint_matcher.matches(&TngEndpoint::new("api1.foo.com", 443)));
+        assert!(!endpoint_matcher.matches(&TngEndpoint::new("admin.foo.com", 80)));
+        assert!(!endpoint_matcher.matches(&TngEndpoint::new("www.foo.com", 22)));
+        assert!(!endpoint_matcher.matches(&TngEndpoint::new("www.bar.com", 80)));
+
+        let config: EndpointMatcherConfig =
+            serde_json::from_value(json!({ "domain": "*.foo.com" }))?;
+        assert!(!config.exclude);
+        assert_eq!(
+            serde_json::to_value(&config)?,
+            json!({ "domain": "*.foo.com" })
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_port_range_match() -> Result<()> {
         // Single port (existing behavior)
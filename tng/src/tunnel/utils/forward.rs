@@ -4,6 +4,10 @@ use std::pin::Pin;
 use std::task::{Context as TaskContext, Poll};
 use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+use crate::tunnel::stream::{CommonStreamTrait, ContextualStream};
+use crate::tunnel::utils::buffer_pool::{BufferPool, PooledBuffer};
 
 // This module provides a custom bidirectional stream forwarding implementation
 // instead of using `tokio::io::copy_bidirectional`. We intentionally avoid the
@@ -30,17 +34,15 @@ pub enum ForwardError {
     WriteZero,
 }
 
-// The default buffer size used in tokio::io::copy_bidirectional is 8 KB, here we increase it to 512 KB to improve the performance.
-const FORWARD_BUF_SIZE: usize = 512 * 1024;
-
-/// Buffer used for copying data between streams.
+/// Buffer used for copying data between streams, which is taken from the global
+/// [`BufferPool`] and returned to it when the copy is finished.
 struct CopyBuffer {
     read_done: bool,
     need_flush: bool,
     pos: usize,
     cap: usize,
     amt: u64,
-    buf: Box<[u8]>,
+    buf: PooledBuffer,
 }
 
 impl CopyBuffer {
@@ -51,7 +53,7 @@ impl CopyBuffer {
             pos: 0,
             cap: 0,
             amt: 0,
-            buf: vec![0; buf_size].into_boxed_slice(),
+            buf: BufferPool::global().acquire(buf_size),
         }
     }
 
@@ -143,7 +145,7 @@ impl CopyBuffer {
         R: AsyncRead + ?Sized,
     {
         let me = &mut *self;
-        let mut buf = ReadBuf::new(&mut me.buf);
+        let mut buf = ReadBuf::new(&mut me.buf[..]);
         buf.set_filled(me.cap);
         let res = reader.poll_read(cx, &mut buf);
         if let Poll::Ready(Ok(())) = res {
@@ -330,21 +332,19 @@ where
     .await
 }
 
+/// Copies the data between the two streams in both directions, with a buffer of `buffer_size`
+/// bytes for each direction.
 pub async fn forward_stream(
     mut upstream: impl AsyncRead + AsyncWrite + Unpin,
     mut downstream: impl AsyncRead + AsyncWrite + Unpin,
+    buffer_size: usize,
 ) {
     tracing::debug!("Starting to transmit application data");
     // downstream corresponds to 'a', upstream corresponds to 'b'
     // a_to_b is downstream -> upstream (tx/from_client)
     // b_to_a is upstream -> downstream (rx/from_server)
-    let (from_client, from_server) = copy_bidirectional_impl(
-        &mut downstream,
-        &mut upstream,
-        FORWARD_BUF_SIZE,
-        FORWARD_BUF_SIZE,
-    )
-    .await;
+    let (from_client, from_server) =
+        copy_bidirectional_impl(&mut downstream, &mut upstream, buffer_size, buffer_size).await;
     tracing::debug!(
         tx_bytes = from_client,
         rx_bytes = from_server,
@@ -352,9 +352,130 @@ pub async fn forward_stream(
     );
 }
 
+/// Adds the bytes read from and written to a socket by splice(2) to the counters of a stream.
+pub type SpliceCounter = Box<dyn Fn(u64, u64) + Send + Sync>;
+
+/// A stream which may be a plain TCP socket underneath, so that the data forwarded on it can be
+/// moved with splice(2) in the kernel, instead of being copied through the stream.
+pub trait SpliceSocket {
+    /// The TCP socket under the stream, if the stream does not change the data read from or
+    /// written to it.
+    fn tcp_socket(&self) -> Option<&TcpStream>;
+
+    /// The counters of the stream, which the bytes moved by splice(2) are added to since they do
+    /// not go through the stream. It is detached from the stream, so that it can be called while
+    /// the socket is borrowed for splicing.
+    fn splice_counter(&self) -> SpliceCounter {
+        Box::new(|_, _| {})
+    }
+}
+
+impl SpliceSocket for TcpStream {
+    fn tcp_socket(&self) -> Option<&TcpStream> {
+        Some(self)
+    }
+}
+
+impl<S: SpliceSocket> SpliceSocket for ContextualStream<S> {
+    fn tcp_socket(&self) -> Option<&TcpStream> {
+        self.inner().tcp_socket()
+    }
+
+    fn splice_counter(&self) -> SpliceCounter {
+        self.inner().splice_counter()
+    }
+}
+
+impl<T: CommonStreamTrait + ?Sized> SpliceSocket for Box<T> {
+    fn tcp_socket(&self) -> Option<&TcpStream> {
+        // The streams accepted by the ingresses and egresses are wrapped with their source
+        let any = (**self).as_any();
+        any.downcast_ref::<ContextualStream<TcpStream>>()
+            .map(ContextualStream::inner)
+            .or_else(|| any.downcast_ref::<TcpStream>())
+    }
+}
+
+/// Same as [`forward_stream`], but if both streams are plain TCP sockets, the data is moved with
+/// splice(2) on Linux, which saves the CPU spent on copying it through userspace. With
+/// `io_uring`, the splice(2) calls are submitted through io_uring if it is available.
+pub async fn forward_stream_zero_copy(
+    upstream: impl AsyncRead + AsyncWrite + Unpin + SpliceSocket,
+    downstream: impl AsyncRead + AsyncWrite + Unpin + SpliceSocket,
+    buffer_size: usize,
+    io_uring: bool,
+) {
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    let _ = io_uring;
+
+    #[cfg(target_os = "linux")]
+    {
+        let sockets = (upstream.tcp_socket(), downstream.tcp_socket());
+        if let (Some(upstream_socket), Some(downstream_socket)) = sockets {
+            let upstream_counter = upstream.splice_counter();
+            let downstream_counter = downstream.splice_counter();
+            let on_downstream_to_upstream = |n| {
+                downstream_counter(n, 0);
+                upstream_counter(0, n);
+            };
+            let on_upstream_to_downstream = |n| {
+                upstream_counter(n, 0);
+                downstream_counter(0, n);
+            };
+
+            #[cfg(feature = "io-uring")]
+            if let Some(forwarder) = io_uring.then(super::uring::UringForwarder::get).flatten() {
+                tracing::debug!("Starting to transmit application data with io_uring");
+                match forwarder
+                    .splice_bidirectional(
+                        downstream_socket,
+                        upstream_socket,
+                        on_downstream_to_upstream,
+                        on_upstream_to_downstream,
+                    )
+                    .await
+                {
+                    Ok((from_client, from_server)) => {
+                        tracing::debug!(
+                            tx_bytes = from_client,
+                            rx_bytes = from_server,
+                            "Finished transmit application data",
+                        );
+                        return;
+                    }
+                    Err(error) => {
+                        tracing::warn!(
+                            ?error,
+                            "Failed to start io_uring forwarding, falling back to epoll"
+                        )
+                    }
+                }
+            }
+
+            tracing::debug!("Starting to transmit application data with splice");
+            let (from_client, from_server) = super::splice::splice_bidirectional(
+                downstream_socket,
+                upstream_socket,
+                on_downstream_to_upstream,
+                on_upstream_to_downstream,
+            )
+            .await;
+            tracing::debug!(
+                tx_bytes = from_client,
+                rx_bytes = from_server,
+                "Finished transmit application data",
+            );
+            return;
+        }
+    }
+
+    forward_stream(upstream, downstream, buffer_size).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::DEFAULT_FORWARD_BUF_SIZE;
     use std::sync::{Arc, Mutex};
 
     // =====================================================================
@@ -539,7 +660,13 @@ mod tests {
 
     /// Runs `copy_bidirectional_impl` and returns `(downstream→upstream, upstream→downstream)` byte counts.
     async fn run_copy(downstream: &mut MockStream, upstream: &mut MockStream) -> (u64, u64) {
-        copy_bidirectional_impl(downstream, upstream, FORWARD_BUF_SIZE, FORWARD_BUF_SIZE).await
+        copy_bidirectional_impl(
+            downstream,
+            upstream,
+            DEFAULT_FORWARD_BUF_SIZE,
+            DEFAULT_FORWARD_BUF_SIZE,
+        )
+        .await
     }
 
     // =====================================================================
@@ -747,8 +874,13 @@ mod tests {
         assert_eq!(&buf, b"world");
 
         // After consuming all data, both sides see EOF → forward_stream returns Ok
-        let result =
-            copy_bidirectional_impl(&mut ds_a, &mut us_a, FORWARD_BUF_SIZE, FORWARD_BUF_SIZE).await;
+        let result = copy_bidirectional_impl(
+            &mut ds_a,
+            &mut us_a,
+            DEFAULT_FORWARD_BUF_SIZE,
+            DEFAULT_FORWARD_BUF_SIZE,
+        )
+        .await;
         assert_eq!(
             result,
             (0, 0),
@@ -236,6 +236,15 @@ impl<
         }
     }
 
+    /// Returns a receiver notified each time the cached value is replaced by a new one, or `None`
+    /// if the value is not cached, since a new one is then produced on every access.
+    pub fn subscribe(&self) -> Option<tokio::sync::watch::Receiver<Arc<T>>> {
+        match self {
+            MaybeCached::UpdatePeriodically { latest, .. } => Some(latest.0.subscribe()),
+            MaybeCached::NoCache { .. } => None,
+        }
+    }
+
     pub fn invalidate(&self) {
         match self {
             MaybeCached::UpdatePeriodically { invalidator_tx, .. } => {
@@ -466,6 +475,48 @@ mod tests {
         .await
     }
 
+    #[tokio::test]
+    async fn test_maybe_cached_subscribe() -> Result<()> {
+        run_test_with_tokio_runtime(|runtime| async move {
+            let call_count = std::sync::Arc::new(std::sync::atomic::AtomicU32::new(0));
+            let call_count_clone = call_count.clone();
+
+            let maybe_cached: MaybeCached<String, anyhow::Error> = MaybeCached::new(
+                runtime.clone(),
+                RefreshStrategy::Periodically {
+                    interval: 3600,
+                    min_fallback_interval: 1,
+                },
+                move || {
+                    let call_count_clone = call_count_clone.clone();
+                    Box::pin(async move {
+                        let count =
+                            call_count_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                        Ok((format!("value{count}"), Expire::NoExpire))
+                    })
+                },
+            )
+            .await?;
+
+            let mut receiver = maybe_cached.subscribe().context("No receiver")?;
+            assert!(!receiver.has_changed()?);
+
+            // The receiver is notified once the value is refreshed
+            maybe_cached.invalidate();
+            tokio_time::timeout(tokio_time::Duration::from_secs(1), receiver.changed()).await??;
+            assert_eq!(**receiver.borrow_and_update(), "value2");
+
+            let maybe_cached: MaybeCached<String, anyhow::Error> =
+                MaybeCached::new(runtime, RefreshStrategy::Always, || {
+                    Box::pin(async { Ok(("value".to_string(), Expire::NoExpire)) })
+                })
+                .await?;
+            assert!(maybe_cached.subscribe().is_none());
+            Ok(())
+        })
+        .await
+    }
+
     #[tokio::test]
     async fn test_maybe_cached_periodically_with_short_expire() -> Result<()> {
         run_test_with_tokio_runtime(|runtime| async move {
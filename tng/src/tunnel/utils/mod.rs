@@ -1,6 +1,10 @@
+#[cfg(not(wasm))]
+pub mod buffer_pool;
 #[cfg(unix)]
 pub mod cert_manager;
 #[cfg(not(wasm))]
+pub mod circuit_breaker;
+#[cfg(not(wasm))]
 pub mod endpoint_matcher;
 #[cfg(not(wasm))]
 pub mod forward;
@@ -11,11 +15,17 @@ pub mod hyper;
 #[cfg(target_os = "linux")]
 pub mod iptables;
 pub mod maybe_cached;
-pub mod runtime;
 #[cfg(not(wasm))]
+pub mod quic_inspector;
+pub mod runtime;
 pub mod rustls;
 pub mod socket;
+pub mod source_addr;
+#[cfg(target_os = "linux")]
+pub mod splice;
 pub mod tokio;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
 
 #[cfg(not(wasm))]
 pub mod file_watcher;
@@ -0,0 +1,446 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context as _, Result};
+use rustls::quic::{Keys, Version};
+use rustls::Side;
+
+const QUIC_VERSION_1: u32 = 0x0000_0001;
+const QUIC_VERSION_2: u32 = 0x6b33_43cf;
+
+/// The largest ClientHello which is reassembled from the CRYPTO frames of the Initial packets.
+const MAX_CLIENT_HELLO_LEN: u64 = 16 * 1024;
+
+/// The TLS extension carrying the server name, see RFC 6066.
+const TLS_EXTENSION_SERVER_NAME: u16 = 0;
+
+#[derive(Debug, PartialEq)]
+pub struct ClientHelloInfo {
+    /// The host name in the server_name extension, if any.
+    pub sni: Option<String>,
+}
+
+/// Inspects the Initial packets sent by a QUIC client to get its ClientHello, which may span
+/// several packets, without taking part in the handshake.
+#[derive(Default)]
+pub struct QuicInitialInspector {
+    /// The destination connection id chosen by the client, and the Initial keys derived from it
+    keys: Option<(Vec<u8>, Keys)>,
+    /// The CRYPTO frames received so far, by offset
+    crypto: BTreeMap<u64, Vec<u8>>,
+}
+
+impl QuicInitialInspector {
+    /// Returns whether the datagram starts with a QUIC Initial packet of a supported version.
+    pub fn is_initial(datagram: &[u8]) -> bool {
+        let &[first, v0, v1, v2, v3, ..] = datagram else {
+            return false;
+        };
+        // Long header with the fixed bit set
+        if first & 0xc0 != 0xc0 {
+            return false;
+        }
+        match u32::from_be_bytes([v0, v1, v2, v3]) {
+            QUIC_VERSION_1 => (first >> 4) & 0x03 == 0b00,
+            QUIC_VERSION_2 => (first >> 4) & 0x03 == 0b01,
+            _ => false,
+        }
+    }
+
+    /// Feeds a datagram received from the client. Returns the ClientHello once all of its
+    /// fragments have been received, or `None` if more packets are needed.
+    pub fn feed(&mut self, datagram: &[u8]) -> Result<Option<ClientHelloInfo>> {
+        let plaintext = self.decrypt_initial(datagram)?;
+        self.read_frames(&plaintext)?;
+        self.client_hello()
+    }
+
+    /// Returns whether the CRYPTO frames received so far contain the beginning of the handshake.
+    /// The Initial packets without it are not the first flight of a new connection.
+    pub fn has_first_fragment(&self) -> bool {
+        self.crypto.contains_key(&0)
+    }
+
+    /// Removes the protection of the first packet in the datagram, which must be an Initial
+    /// packet, and returns its payload.
+    fn decrypt_initial(&mut self, datagram: &[u8]) -> Result<Vec<u8>> {
+        let mut reader = Reader::new(datagram);
+        let first = reader.u8()?;
+        let version = match reader.u32()? {
+            QUIC_VERSION_1 => Version::V1,
+            QUIC_VERSION_2 => Version::V2,
+            version => bail!("Unsupported QUIC version {version:#x}"),
+        };
+        if !Self::is_initial(datagram) {
+            bail!("Not a QUIC Initial packet, first byte: {first:#x}");
+        }
+
+        let dcid_len = reader.u8()? as usize;
+        if dcid_len > 20 {
+            bail!("Invalid length of destination connection id: {dcid_len}");
+        }
+        let dcid = reader.bytes(dcid_len)?;
+        let scid_len = reader.u8()? as usize;
+        reader.bytes(scid_len)?;
+        let token_len = reader.varint()? as usize;
+        reader.bytes(token_len)?;
+        let length = reader.varint()? as usize;
+        let pn_offset = reader.pos;
+
+        let keys = match self.keys.take() {
+            Some((id, keys)) if id == dcid => (id, keys),
+            _ => {
+                // A new destination connection id means a new connection attempt
+                self.crypto.clear();
+                let suite = rustls::crypto::aws_lc_rs::cipher_suite::TLS13_AES_128_GCM_SHA256
+                    .tls13()
+                    .and_then(|suite| suite.quic_suite())
+                    .context("The cipher suite of QUIC Initial packets is not supported")?;
+                (dcid.to_vec(), suite.keys(dcid, Side::Server, version))
+            }
+        };
+        let (_, keys) = self.keys.insert(keys);
+
+        // The packet number is followed by at least 16 bytes, from which the sample of the header
+        // protection is taken.
+        let sample_len = keys.remote.header.sample_len();
+        if length < 4 + sample_len || datagram.len() < pn_offset + length {
+            bail!("Truncated QUIC Initial packet");
+        }
+
+        let mut packet = datagram[..pn_offset + length].to_vec();
+        let sample = packet[pn_offset + 4..pn_offset + 4 + sample_len].to_vec();
+        let (header, rest) = packet.split_at_mut(pn_offset);
+        keys.remote
+            .header
+            .decrypt_in_place(&sample, &mut header[0], &mut rest[..4])
+            .context("Failed to remove the header protection")?;
+
+        let pn_len = (header[0] & 0x03) as usize + 1;
+        // The packet numbers of the first packets of a connection are small enough to be decoded
+        // without the largest acknowledged packet number.
+        let packet_number = rest[..pn_len]
+            .iter()
+            .fold(0u64, |pn, byte| (pn << 8) | *byte as u64);
+
+        let (header, payload) = packet.split_at_mut(pn_offset + pn_len);
+        let plaintext = keys
+            .remote
+            .packet
+            .decrypt_in_place(packet_number, header, payload)
+            .context("Failed to decrypt the QUIC Initial packet")?;
+        Ok(plaintext.to_vec())
+    }
+
+    fn read_frames(&mut self, plaintext: &[u8]) -> Result<()> {
+        let mut reader = Reader::new(plaintext);
+        while !reader.is_empty() {
+            match reader.varint()? {
+                // PADDING, PING
+                0x00 | 0x01 => {}
+                // ACK
+                frame_type @ (0x02 | 0x03) => {
+                    reader.varint()?; // Largest Acknowledged
+                    reader.varint()?; // ACK Delay
+                    let range_count = reader.varint()?;
+                    reader.varint()?; // First ACK Range
+                    for _ in 0..range_count {
+                        reader.varint()?; // Gap
+                        reader.varint()?; // ACK Range Length
+                    }
+                    if frame_type == 0x03 {
+                        // ECN Counts
+                        reader.varint()?;
+                        reader.varint()?;
+                        reader.varint()?;
+                    }
+                }
+                // CRYPTO
+                0x06 => {
+                    let offset = reader.varint()?;
+                    let len = reader.varint()?;
+                    let data = reader.bytes(len as usize)?;
+                    if offset + len > MAX_CLIENT_HELLO_LEN {
+                        bail!("The ClientHello is larger than {MAX_CLIENT_HELLO_LEN} bytes");
+                    }
+                    self.crypto.insert(offset, data.to_vec());
+                }
+                // CONNECTION_CLOSE
+                0x1c => {
+                    reader.varint()?; // Error Code
+                    reader.varint()?; // Frame Type
+                    let reason_len = reader.varint()?;
+                    reader.bytes(reason_len as usize)?;
+                }
+                frame_type => bail!("Unexpected frame type {frame_type:#x} in QUIC Initial packet"),
+            }
+        }
+        Ok(())
+    }
+
+    fn client_hello(&self) -> Result<Option<ClientHelloInfo>> {
+        // Reassemble the contiguous data from the beginning of the handshake
+        let mut data = Vec::new();
+        for (offset, fragment) in &self.crypto {
+            let offset = *offset as usize;
+            if offset > data.len() {
+                break;
+            }
+            if offset + fragment.len() > data.len() {
+                data.extend_from_slice(&fragment[data.len() - offset..]);
+            }
+        }
+
+        let mut reader = Reader::new(&data);
+        let Ok(msg_type) = reader.u8() else {
+            return Ok(None);
+        };
+        if msg_type != 0x01 {
+            bail!("The first handshake message is not a ClientHello: {msg_type}");
+        }
+        let Ok(len) = reader.u24() else {
+            return Ok(None);
+        };
+        let Ok(body) = reader.bytes(len as usize) else {
+            return Ok(None);
+        };
+
+        parse_client_hello(body).map(Some)
+    }
+}
+
+fn parse_client_hello(body: &[u8]) -> Result<ClientHelloInfo> {
+    let mut reader = Reader::new(body);
+    reader.bytes(2 + 32)?; // legacy_version, random
+    let session_id_len = reader.u8()? as usize;
+    reader.bytes(session_id_len)?;
+    let cipher_suites_len = reader.u16()? as usize;
+    reader.bytes(cipher_suites_len)?;
+    let compression_methods_len = reader.u8()? as usize;
+    reader.bytes(compression_methods_len)?;
+
+    let extensions_len = reader.u16()? as usize;
+    let mut extensions = Reader::new(reader.bytes(extensions_len)?);
+    while !extensions.is_empty() {
+        let extension_type = extensions.u16()?;
+        let extension_len = extensions.u16()? as usize;
+        let extension = extensions.bytes(extension_len)?;
+        if extension_type != TLS_EXTENSION_SERVER_NAME {
+            continue;
+        }
+
+        let mut extension = Reader::new(extension);
+        let list_len = extension.u16()? as usize;
+        let mut names = Reader::new(extension.bytes(list_len)?);
+        while !names.is_empty() {
+            let name_type = names.u8()?;
+            let name_len = names.u16()? as usize;
+            let name = names.bytes(name_len)?;
+            // host_name
+            if name_type == 0 {
+                let sni = std::str::from_utf8(name).context("The SNI is not valid UTF-8")?;
+                return Ok(ClientHelloInfo {
+                    sni: Some(sni.to_owned()),
+                });
+            }
+        }
+    }
+
+    Ok(ClientHelloInfo { sni: None })
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.buf.get(self.pos..end))
+            .context("Unexpected end of data")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&mut self) -> Result<u32> {
+        let bytes = self.bytes(3)?;
+        Ok(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a variable-length integer, see RFC 9000 section 16.
+    fn varint(&mut self) -> Result<u64> {
+        let first = self.u8()?;
+        let len = 1 << (first >> 6);
+        let mut value = (first & 0x3f) as u64;
+        for byte in self.bytes(len - 1)? {
+            value = (value << 8) | *byte as u64;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rustls::quic::{ClientConnection, DirectionalKeys};
+
+    use super::*;
+
+    const DCID: &[u8] = &[0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+
+    fn client_hello(server_name: &str) -> Vec<u8> {
+        let mut config = rustls::ClientConfig::builder()
+            .with_root_certificates(rustls::RootCertStore::empty())
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"h3".to_vec()];
+
+        let mut connection = ClientConnection::new(
+            Arc::new(config),
+            Version::V1,
+            server_name.to_owned().try_into().unwrap(),
+            vec![],
+        )
+        .unwrap();
+        let mut client_hello = vec![];
+        connection.write_hs(&mut client_hello);
+        client_hello
+    }
+
+    /// Builds a protected Initial packet, as sent by a client, carrying a CRYPTO frame.
+    fn initial_packet(
+        keys: &DirectionalKeys,
+        packet_number: u8,
+        offset: u64,
+        crypto: &[u8],
+    ) -> Vec<u8> {
+        let mut payload = vec![0x06];
+        payload.extend_from_slice(&(0x4000 | offset as u16).to_be_bytes());
+        payload.extend_from_slice(&(0x4000 | crypto.len() as u16).to_be_bytes());
+        payload.extend_from_slice(crypto);
+        // Pad the packet as QUIC clients do
+        if payload.len() < 1100 {
+            payload.resize(1100, 0x00);
+        }
+
+        let mut packet = vec![0xc0];
+        packet.extend_from_slice(&QUIC_VERSION_1.to_be_bytes());
+        packet.push(DCID.len() as u8);
+        packet.extend_from_slice(DCID);
+        packet.push(0); // scid
+        packet.push(0); // token
+        let length = 1 + payload.len() + keys.packet.tag_len();
+        packet.extend_from_slice(&(0x4000 | length as u16).to_be_bytes());
+        let pn_offset = packet.len();
+        packet.push(packet_number);
+
+        let tag = keys
+            .packet
+            .encrypt_in_place(packet_number as u64, &packet, &mut payload)
+            .unwrap();
+        packet.extend_from_slice(&payload);
+        packet.extend_from_slice(tag.as_ref());
+
+        let sample = packet[pn_offset + 4..pn_offset + 4 + keys.header.sample_len()].to_vec();
+        let (header, rest) = packet.split_at_mut(pn_offset);
+        keys.header
+            .encrypt_in_place(&sample, &mut header[0], &mut rest[..1])
+            .unwrap();
+        packet
+    }
+
+    fn client_keys() -> DirectionalKeys {
+        rustls::crypto::aws_lc_rs::cipher_suite::TLS13_AES_128_GCM_SHA256
+            .tls13()
+            .and_then(|suite| suite.quic_suite())
+            .unwrap()
+            .keys(DCID, Side::Client, Version::V1)
+            .local
+    }
+
+    #[test]
+    fn test_inspect_client_hello() -> Result<()> {
+        let packet = initial_packet(&client_keys(), 0, 0, &client_hello("www.example.com"));
+        assert!(QuicInitialInspector::is_initial(&packet));
+
+        let mut inspector = QuicInitialInspector::default();
+        assert_eq!(
+            inspector.feed(&packet)?,
+            Some(ClientHelloInfo {
+                sni: Some("www.example.com".to_owned())
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_split_client_hello() -> Result<()> {
+        let keys = client_keys();
+        let client_hello = client_hello("www.example.com");
+        let (head, tail) = client_hello.split_at(100);
+
+        let mut inspector = QuicInitialInspector::default();
+        // The fragments may arrive out of order
+        assert_eq!(
+            inspector.feed(&initial_packet(&keys, 1, head.len() as u64, tail))?,
+            None
+        );
+        assert!(!inspector.has_first_fragment());
+        assert_eq!(
+            inspector.feed(&initial_packet(&keys, 0, 0, head))?,
+            Some(ClientHelloInfo {
+                sni: Some("www.example.com".to_owned())
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_client_hello_without_sni() -> Result<()> {
+        // No SNI is sent when connecting to an IP address
+        let packet = initial_packet(&client_keys(), 0, 0, &client_hello("192.168.1.1"));
+
+        let mut inspector = QuicInitialInspector::default();
+        assert_eq!(
+            inspector.feed(&packet)?,
+            Some(ClientHelloInfo { sni: None })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_invalid_packet() {
+        let mut packet = initial_packet(&client_keys(), 0, 0, &client_hello("www.example.com"));
+        let last = packet.len() - 1;
+        packet[last] ^= 0xff;
+        assert!(QuicInitialInspector::default().feed(&packet).is_err());
+
+        let short_header = [0x40, 0x01, 0x02, 0x03, 0x04, 0x05];
+        assert!(!QuicInitialInspector::is_initial(&short_header));
+        assert!(QuicInitialInspector::default().feed(&short_header).is_err());
+    }
+}
@@ -8,6 +8,8 @@ use tokio_graceful::ShutdownGuard;
 use tokio_with_wasm::alias as tokio;
 use tracing::Instrument;
 
+#[cfg(not(wasm))]
+use crate::config::runtime::RuntimeArgs;
 use crate::tunnel::utils::runtime::future::TokioRuntimeSupportedFuture;
 
 pub mod future;
@@ -34,6 +36,19 @@ pub struct TokioRuntime {
     inner: Arc<TokioRuntimeInner>,
     #[allow(unused)]
     shutdown_guard: ShutdownGuard,
+    #[cfg(not(wasm))]
+    protocol_runtime_layout: Arc<ProtocolRuntimeLayout>,
+}
+
+/// How the runtimes of the protocol layers of the ingresses and egresses are created, see
+/// [`TokioRuntime::new_protocol_runtime`].
+#[cfg(not(wasm))]
+#[derive(Debug, Default)]
+struct ProtocolRuntimeLayout {
+    worker_threads: Option<usize>,
+    /// The runtime shared by all the protocol layers, created on first use. `None` if each of
+    /// them creates its own runtime.
+    shared: Option<std::sync::Mutex<Option<TokioRuntime>>>,
 }
 
 #[derive(Debug)]
@@ -52,10 +67,17 @@ enum TokioRuntimeInner {
 impl TokioRuntime {
     #[cfg(not(wasm))]
     #[allow(dead_code)]
-    pub fn new_multi_thread(shutdown_guard: ShutdownGuard) -> Result<Self> {
+    pub fn new_multi_thread(
+        shutdown_guard: ShutdownGuard,
+        worker_threads: Option<usize>,
+    ) -> Result<Self> {
         use anyhow::Context;
 
-        let rt = tokio::runtime::Builder::new_multi_thread()
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        if let Some(worker_threads) = worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        let rt = builder
             .enable_all()
             .build()
             .context("Failed to create tokio runtime")?;
@@ -66,6 +88,7 @@ impl TokioRuntime {
                 rt_handle,
             }),
             shutdown_guard,
+            protocol_runtime_layout: Default::default(),
         })
     }
 
@@ -76,9 +99,46 @@ impl TokioRuntime {
         Ok(Self {
             inner: Arc::new(TokioRuntimeInner::Reference { rt_handle }),
             shutdown_guard,
+            protocol_runtime_layout: Default::default(),
         })
     }
 
+    /// Returns this runtime, with the protocol runtimes created from it following `args`.
+    #[cfg(not(wasm))]
+    pub fn with_protocol_runtime_layout(mut self, args: &RuntimeArgs) -> Self {
+        self.protocol_runtime_layout = Arc::new(ProtocolRuntimeLayout {
+            worker_threads: args.protocol_worker_threads,
+            shared: args
+                .share_protocol_runtime
+                .then(|| std::sync::Mutex::new(None)),
+        });
+        self
+    }
+
+    /// Returns the runtime for the protocol layer of an ingress or egress, which is either a new
+    /// multi-threaded runtime, or the one shared by all of them if `share_protocol_runtime` is
+    /// set.
+    #[cfg(not(wasm))]
+    pub fn new_protocol_runtime(&self) -> Result<Self> {
+        let layout = &self.protocol_runtime_layout;
+        let Some(shared) = &layout.shared else {
+            return Self::new_multi_thread(self.shutdown_guard.clone(), layout.worker_threads);
+        };
+
+        let mut shared = shared
+            .lock()
+            .map_err(|_| anyhow::anyhow!("The shared protocol runtime is poisoned"))?;
+        match &*shared {
+            Some(runtime) => Ok(runtime.clone()),
+            None => {
+                let runtime =
+                    Self::new_multi_thread(self.shutdown_guard.clone(), layout.worker_threads)?;
+                *shared = Some(runtime.clone());
+                Ok(runtime)
+            }
+        }
+    }
+
     #[cfg(wasm)]
     #[allow(dead_code)]
     pub fn wasm_main_thread(shutdown_guard: ShutdownGuard) -> Result<Self> {
@@ -1,3 +1,5 @@
+use anyhow::{bail, Result};
+
 /// ALPN protocol identifier used during TLS handshake negotiation.
 ///
 /// Each variant determines how the connection is established:
@@ -28,4 +30,27 @@ impl Alpn {
             Alpn::RatsQuic => b"rats-quic",
         }
     }
+
+    /// Returns the ALPN protocols to negotiate in place of this one, which are the `configured`
+    /// ones if any.
+    pub fn or_configured(self, configured: &[String]) -> Vec<Vec<u8>> {
+        if configured.is_empty() {
+            vec![self.as_bytes().to_vec()]
+        } else {
+            configured
+                .iter()
+                .map(|protocol| protocol.as_bytes().to_vec())
+                .collect()
+        }
+    }
+
+    /// Checks the ALPN protocols set in the `alpn` field of the configuration.
+    pub fn validate_configured(configured: &[String]) -> Result<()> {
+        for protocol in configured {
+            if protocol.is_empty() || protocol.len() > 255 {
+                bail!("Invalid ALPN protocol {protocol:?} in `rats_tls.alpn`, the length must be between 1 and 255 bytes");
+            }
+        }
+        Ok(())
+    }
 }
@@ -1,21 +1,20 @@
-#[cfg(not(wasm))]
 use std::sync::Arc;
 
-#[cfg(not(wasm))]
 use anyhow::{Context as _, Result};
-#[cfg(not(wasm))]
 use rustls::RootCertStore;
+#[cfg(not(wasm))]
+use web_time_compat::{Instant, InstantExt as _};
 
+#[cfg(not(wasm))]
+use crate::tunnel::service_metrics::{HandshakePhase, ServiceMetrics};
 #[cfg(unix)]
 use crate::tunnel::utils::cert_manager::DynamicCertResolver;
-#[cfg(not(wasm))]
 use crate::tunnel::utils::rustls::{
     config::{alpn::Alpn, TlsConfigGenerator},
     dummy::verifier::DummyServerCertVerifier,
     ra::server_cert_verifier::LazyServerCertVerifier,
 };
 
-#[cfg(not(wasm))]
 impl TlsConfigGenerator {
     pub async fn get_lazy_one_time_rustls_client_config(
         &self,
@@ -45,7 +44,7 @@ impl TlsConfigGenerator {
                     .with_no_client_auth();
 
                 let verifier: Arc<LazyServerCertVerifier> =
-                    Arc::new(LazyServerCertVerifier::new(verify_ctx.clone())?);
+                    Arc::new(LazyServerCertVerifier::new(verify_ctx.load())?);
                 tls_client_config
                     .dangerous()
                     .set_certificate_verifier(verifier.clone());
@@ -80,7 +79,7 @@ impl TlsConfigGenerator {
                     ));
 
                 let verifier: Arc<LazyServerCertVerifier> =
-                    Arc::new(LazyServerCertVerifier::new(verify_ctx.clone())?);
+                    Arc::new(LazyServerCertVerifier::new(verify_ctx.load())?);
                 tls_client_config
                     .dangerous()
                     .set_certificate_verifier(verifier.clone());
@@ -95,27 +94,23 @@ impl TlsConfigGenerator {
     }
 }
 
-#[cfg(not(wasm))]
 pub struct LazyOnetimeTlsClientConfig(rustls::ClientConfig, Option<Arc<LazyServerCertVerifier>>);
 
-#[cfg(not(wasm))]
 impl LazyOnetimeTlsClientConfig {
-    /// Perform TLS handshake then verify the peer certificate if a verifier was configured.
+    /// Replaces the ALPN protocols offered in the handshake.
+    pub fn with_alpn_protocols(mut self, alpn_protocols: Vec<Vec<u8>>) -> Self {
+        self.0.alpn_protocols = alpn_protocols;
+        self
+    }
+
+    /// Builds the `ServerName` of the TLS handshake with the peer.
     ///
     /// Takes the peer as an `EndpointAddr` rather than a pre-formatted string so that
     /// IPv4 addresses become `ServerName::IpAddress` directly (no allocation) and
     /// domains become `ServerName::DnsName` from the borrowed string.
-    pub async fn handshake_with_stream<S>(
-        self,
+    fn server_name(
         server_name: &crate::tunnel::endpoint::EndpointAddr,
-        stream: S,
-    ) -> Result<(
-        tokio_rustls::client::TlsStream<S>,
-        Option<crate::tunnel::attestation_result::AttestationResult>,
-    )>
-    where
-        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send,
-    {
+    ) -> Result<rustls::pki_types::ServerName<'static>> {
         use rustls::pki_types::{DnsName, IpAddr, ServerName};
 
         // Build the borrowing `ServerName` from the structured address: IPv4 →
@@ -132,9 +127,77 @@ impl LazyOnetimeTlsClientConfig {
                     .with_context(|| format!("Invalid server name for TLS handshake ({d})"))?,
             ),
         };
+        Ok(server_name.to_owned())
+    }
+}
+
+#[cfg(not(wasm))]
+impl LazyOnetimeTlsClientConfig {
+    /// Perform TLS handshake then verify the peer certificate if a verifier was configured. The
+    /// time spent in each phase is recorded in the metrics.
+    pub async fn handshake_with_stream<S>(
+        self,
+        server_name: &crate::tunnel::endpoint::EndpointAddr,
+        stream: S,
+        metrics: &ServiceMetrics,
+    ) -> Result<(
+        tokio_rustls::client::TlsStream<S>,
+        Option<crate::tunnel::attestation_result::AttestationResult>,
+    )>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send,
+    {
+        let server_name = Self::server_name(server_name)?;
+
+        let tls_started_at = Instant::get();
+        let tls_stream = tokio_rustls::TlsConnector::from(std::sync::Arc::new(self.0))
+            .connect(server_name, stream)
+            .await
+            .context("Failed to establish TLS connection")?;
+        metrics.record_handshake_phase(HandshakePhase::Tls, tls_started_at.elapsed());
+
+        let attestation_result = match self.1 {
+            Some(verifier) => {
+                let verification_started_at = Instant::get();
+                let attestation_result = verifier
+                    .verity_pending_cert()
+                    .await
+                    .context("Failed to verify pending certificate")?;
+                metrics.record_handshake_phase(
+                    HandshakePhase::Verification,
+                    verification_started_at.elapsed(),
+                );
+                if attestation_result.is_degraded() {
+                    metrics.record_degraded();
+                }
+                Some(attestation_result)
+            }
+            None => None,
+        };
+
+        Ok((tls_stream, attestation_result))
+    }
+}
+
+#[cfg(wasm)]
+impl LazyOnetimeTlsClientConfig {
+    /// Perform TLS handshake then verify the peer certificate if a verifier was configured. Unlike
+    /// on the native targets, no handshake metrics are recorded in the browser.
+    pub async fn handshake_with_stream<S>(
+        self,
+        server_name: &crate::tunnel::endpoint::EndpointAddr,
+        stream: S,
+    ) -> Result<(
+        tokio_rustls::client::TlsStream<S>,
+        Option<crate::tunnel::attestation_result::AttestationResult>,
+    )>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let server_name = Self::server_name(server_name)?;
 
         let tls_stream = tokio_rustls::TlsConnector::from(std::sync::Arc::new(self.0))
-            .connect(server_name.to_owned(), stream)
+            .connect(server_name, stream)
             .await
             .context("Failed to establish TLS connection")?;
 
@@ -184,7 +247,7 @@ impl TlsConfigGenerator {
                     .with_no_client_auth();
 
                 let verifier: Arc<BlockingServerCertVerifier> =
-                    Arc::new(BlockingServerCertVerifier::new(verify_ctx.clone())?);
+                    Arc::new(BlockingServerCertVerifier::new(verify_ctx.load())?);
                 tls_client_config
                     .dangerous()
                     .set_certificate_verifier(verifier.clone());
@@ -219,7 +282,7 @@ impl TlsConfigGenerator {
                     ));
 
                 let verifier: Arc<BlockingServerCertVerifier> =
-                    Arc::new(BlockingServerCertVerifier::new(verify_ctx.clone())?);
+                    Arc::new(BlockingServerCertVerifier::new(verify_ctx.load())?);
                 tls_client_config
                     .dangerous()
                     .set_certificate_verifier(verifier.clone());
@@ -5,7 +5,7 @@ pub mod server;
 
 use std::sync::Arc;
 
-use crate::tunnel::ra_context::{RaContext, VerifyContext};
+use crate::tunnel::ra_context::{RaContext, VerifyContextCell};
 #[cfg(unix)]
 use crate::tunnel::utils::cert_manager::CertManager;
 use crate::tunnel::utils::runtime::TokioRuntime;
@@ -13,11 +13,11 @@ use anyhow::Result;
 
 pub enum TlsConfigGenerator {
     NoRa,
-    Verify(Arc<VerifyContext>),
+    Verify(Arc<VerifyContextCell>),
     #[cfg(unix)]
     Attest(Arc<CertManager>),
     #[cfg(unix)]
-    AttestAndVerify(Arc<CertManager>, Arc<VerifyContext>),
+    AttestAndVerify(Arc<CertManager>, Arc<VerifyContextCell>),
 }
 
 impl TlsConfigGenerator {
@@ -37,4 +37,19 @@ impl TlsConfigGenerator {
             RaContext::NoRa => Self::NoRa,
         })
     }
+
+    /// Returns a receiver notified each time the local certificate is rotated, or `None` if there
+    /// is no local certificate or a new one is generated for every handshake.
+    pub fn subscribe_cert_rotation(
+        &self,
+    ) -> Option<tokio::sync::watch::Receiver<Arc<rustls::sign::CertifiedKey>>> {
+        match self {
+            TlsConfigGenerator::NoRa | TlsConfigGenerator::Verify(_) => None,
+            #[cfg(unix)]
+            TlsConfigGenerator::Attest(cert_manager)
+            | TlsConfigGenerator::AttestAndVerify(cert_manager, _) => {
+                cert_manager.subscribe_rotation()
+            }
+        }
+    }
 }
@@ -2,7 +2,9 @@ use std::sync::Arc;
 
 use anyhow::{Context as _, Result};
 use rustls::ServerConfig;
+use web_time_compat::{Instant, InstantExt as _};
 
+use crate::tunnel::service_metrics::{HandshakePhase, ServiceMetrics};
 #[cfg(unix)]
 use crate::tunnel::utils::cert_manager::DynamicCertResolver;
 use crate::tunnel::utils::rustls::{
@@ -25,7 +27,7 @@ impl TlsConfigGenerator {
                 LazyOnetimeTlsServerConfig(tls_server_config, None)
             }
             TlsConfigGenerator::Verify(verify_ctx) => {
-                let verifier = Arc::new(LazyClientCertVerifier::new(verify_ctx.clone())?);
+                let verifier = Arc::new(LazyClientCertVerifier::new(verify_ctx.load())?);
                 let tls_server_config: ServerConfig =
                     ServerConfig::builder_with_protocol_versions(&[&rustls::version::TLS13])
                         .with_client_cert_verifier(verifier.clone())
@@ -44,7 +46,7 @@ impl TlsConfigGenerator {
             }
             #[cfg(unix)]
             TlsConfigGenerator::AttestAndVerify(cert_manager, verify_ctx) => {
-                let verifier = Arc::new(LazyClientCertVerifier::new(verify_ctx.clone())?);
+                let verifier = Arc::new(LazyClientCertVerifier::new(verify_ctx.load())?);
                 let tls_server_config: ServerConfig =
                     ServerConfig::builder_with_protocol_versions(&[&rustls::version::TLS13])
                         .with_client_cert_verifier(verifier.clone())
@@ -66,10 +68,18 @@ pub struct LazyOnetimeTlsServerConfig(
 );
 
 impl LazyOnetimeTlsServerConfig {
-    /// Perform TLS handshake then verify the peer certificate if a verifier was configured.
+    /// Replaces the ALPN protocols accepted in the handshake.
+    pub fn with_alpn_protocols(mut self, alpn_protocols: Vec<Vec<u8>>) -> Self {
+        self.0.alpn_protocols = alpn_protocols;
+        self
+    }
+
+    /// Perform TLS handshake then verify the peer certificate if a verifier was configured. The
+    /// time spent in each phase is recorded in the metrics.
     pub async fn handshake_with_stream<S>(
         self,
         stream: S,
+        metrics: &ServiceMetrics,
     ) -> Result<(
         tokio_rustls::server::TlsStream<S>,
         Option<crate::tunnel::attestation_result::AttestationResult>,
@@ -77,19 +87,30 @@ impl LazyOnetimeTlsServerConfig {
     where
         S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send,
     {
+        let tls_started_at = Instant::get();
         let tls_acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(self.0));
         let tls_stream = tls_acceptor
             .accept(stream)
             .await
             .context("Failed to accept TLS connection")?;
+        metrics.record_handshake_phase(HandshakePhase::Tls, tls_started_at.elapsed());
 
         let attestation_result = match self.1 {
-            Some(verifier) => Some(
-                verifier
+            Some(verifier) => {
+                let verification_started_at = Instant::get();
+                let attestation_result = verifier
                     .verity_pending_cert()
                     .await
-                    .context("Failed to verify pending certificate")?,
-            ),
+                    .context("Failed to verify pending certificate")?;
+                metrics.record_handshake_phase(
+                    HandshakePhase::Verification,
+                    verification_started_at.elapsed(),
+                );
+                if attestation_result.is_degraded() {
+                    metrics.record_degraded();
+                }
+                Some(attestation_result)
+            }
             None => None,
         };
 
@@ -114,7 +135,7 @@ impl TlsConfigGenerator {
                 BlockingOnetimeTlsServerConfig(tls_server_config)
             }
             TlsConfigGenerator::Verify(verify_ctx) => {
-                let verifier = Arc::new(BlockingClientCertVerifier::new(verify_ctx.clone())?);
+                let verifier = Arc::new(BlockingClientCertVerifier::new(verify_ctx.load())?);
                 let tls_server_config: ServerConfig =
                     ServerConfig::builder_with_protocol_versions(&[&rustls::version::TLS13])
                         .with_client_cert_verifier(verifier)
@@ -133,7 +154,7 @@ impl TlsConfigGenerator {
             }
             #[cfg(unix)]
             TlsConfigGenerator::AttestAndVerify(cert_manager, verify_ctx) => {
-                let verifier = Arc::new(BlockingClientCertVerifier::new(verify_ctx.clone())?);
+                let verifier = Arc::new(BlockingClientCertVerifier::new(verify_ctx.load())?);
                 let tls_server_config: ServerConfig =
                     ServerConfig::builder_with_protocol_versions(&[&rustls::version::TLS13])
                         .with_client_cert_verifier(verifier)
@@ -1,15 +1,19 @@
 pub mod verifier;
 
+#[cfg(not(wasm))]
 use anyhow::{Context as _, Result};
+#[cfg(not(wasm))]
 use std::sync::Arc;
 
 pub const TNG_DUMMY_CERT: &str = include_str!("servercert.pem");
 #[allow(dead_code)]
 pub const TNG_DUMMY_KEY: &str = include_str!("serverkey.pem");
 
+#[cfg(not(wasm))]
 #[derive(Clone, Copy)]
 pub struct RustlsDummyCert {}
 
+#[cfg(not(wasm))]
 impl RustlsDummyCert {
     pub fn new_rustls_cert() -> Result<Arc<rustls::sign::SingleCertAndKey>> {
         let cert_chain =
@@ -139,6 +139,7 @@ async fn verify_cert(verify_ctx: &VerifyContext, end_entity: Vec<u8>) -> Result<
         VerifyContext::BackgroundCheck {
             converter,
             verifier,
+            as_unreachable,
         } => {
             // BackgroundCheck: extension must parse as raw evidence (then convert via AS).
             let evidence = parse_evidence_from_dice_cert(
@@ -147,10 +148,24 @@ async fn verify_cert(verify_ctx: &VerifyContext, end_entity: Vec<u8>) -> Result<
             )?;
 
             // Convert evidence to token via remote AS
-            let token = converter
-                .convert(&evidence)
-                .await
-                .map_err(|e| anyhow!("Failed to convert evidence to token: {:?}", e))?;
+            let token = match converter.convert(&evidence).await {
+                Err(error) if error.is_attestation_service_unreachable() => {
+                    if !as_unreachable.accept_unverified() {
+                        return Err(anyhow!("Failed to convert evidence to token: {:?}", error));
+                    }
+                    tracing::warn!(
+                        ?error,
+                        as_addr = converter.as_addr(),
+                        "Accepting the peer without verifying it, since the attestation service is unreachable"
+                    );
+                    verifier.audit_degraded(&error);
+                    return Ok(AttestationResult::degraded());
+                }
+                result => {
+                    as_unreachable.reachable();
+                    result.map_err(|e| anyhow!("Failed to convert evidence to token: {:?}", e))?
+                }
+            };
 
             // Verify the token
             verifier
@@ -1,5 +1,4 @@
 #[cfg(not(wasm))]
 pub mod client_cert_verifier;
 pub mod common;
-#[cfg(not(wasm))]
 pub mod server_cert_verifier;
@@ -7,13 +7,12 @@ use anyhow::Result;
 use rustls::client::{danger::ServerCertVerified, WebPkiServerVerifier};
 use tokio_rustls::rustls::RootCertStore;
 
+#[cfg(not(wasm))]
+use crate::tunnel::utils::rustls::ra::common::BlockingCertVerifier;
 use crate::tunnel::{
     attestation_result::AttestationResult,
     ra_context::VerifyContext,
-    utils::rustls::{
-        dummy::TNG_DUMMY_CERT,
-        ra::common::{BlockingCertVerifier, LazyCertVerifier},
-    },
+    utils::rustls::{dummy::TNG_DUMMY_CERT, ra::common::LazyCertVerifier},
 };
 
 fn webpki_server_verifier() -> Result<Arc<WebPkiServerVerifier>, anyhow::Error> {
@@ -81,6 +80,7 @@ impl rustls::client::danger::ServerCertVerifier for LazyServerCertVerifier {
 #[derive(Debug)]
 pub struct BlockingServerCertVerifier(Arc<WebPkiServerVerifier>, BlockingCertVerifier);
 
+#[cfg(not(wasm))]
 impl BlockingServerCertVerifier {
     pub fn new(verify_ctx: Arc<VerifyContext>) -> Result<Self> {
         Ok(Self(
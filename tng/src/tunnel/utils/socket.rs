@@ -10,6 +10,8 @@ pub const TCP_CONNECT_SO_MARK_DEFAULT: u32 = 0x235; // 565
 #[cfg(not(wasm))]
 use anyhow::{Context, Result};
 #[cfg(not(wasm))]
+use std::net::SocketAddr;
+#[cfg(not(wasm))]
 use tokio::net::TcpStream;
 
 #[cfg(not(wasm))]
@@ -33,17 +35,7 @@ impl SetListenerSockOpts for tokio::net::TcpListener {
 
     #[cfg(windows)]
     fn set_listener_common_sock_opts(&self) -> Result<()> {
-        use std::os::windows::io::{AsRawSocket, FromRawSocket};
-
-        let raw_socket = self.as_raw_socket();
-        let socket = unsafe { socket2::Socket::from_raw_socket(raw_socket) };
-        let _ = socket.set_keepalive(true).map_err(|e| {
-            tracing::warn!(error = ?e, "set SO_KEEPALIVE failed");
-            e
-        });
-        std::mem::forget(socket);
-
-        Ok(())
+        set_tcp_common_sock_opts(&socket2::SockRef::from(self))
     }
 
     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
@@ -68,6 +60,81 @@ impl SetListenerSockOpts for tokio::net::TcpListener {
     }
 }
 
+/// Binds a TCP listener on `addr`, or takes the one passed on the same address by the service
+/// manager with socket activation, if any.
+#[cfg(not(wasm))]
+pub fn bind_tcp_listener(addr: &str) -> Result<tokio::net::TcpListener> {
+    #[cfg(unix)]
+    for socket_addr in std::net::ToSocketAddrs::to_socket_addrs(addr)? {
+        if let Some(listener) = crate::systemd::inherited_listener(socket_addr)? {
+            return Ok(tokio::net::TcpListener::from_std(listener)?);
+        }
+    }
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener
+        .set_nonblocking(true)
+        .context("Failed to set nonblocking on listener")?;
+    Ok(tokio::net::TcpListener::from_std(listener)?)
+}
+
+/// Binds `listener.acceptors` TCP listeners on `addr`, with the listen backlog of `listener`. With
+/// more than one acceptor, the listeners share the address with `SO_REUSEPORT`, and the kernel
+/// spreads the new connections across them. If the service manager passed a listener on `addr` with socket
+/// activation, the acceptors all share it instead.
+#[cfg(not(wasm))]
+pub fn bind_tcp_listeners(
+    addr: std::net::SocketAddr,
+    listener: &crate::config::listener::ListenerArgs,
+) -> Result<Vec<tokio::net::TcpListener>> {
+    let mut listeners: Vec<tokio::net::TcpListener> = Vec::with_capacity(listener.acceptors);
+    for _ in 0..listener.acceptors {
+        // The others are bound to the address of the first one, in case the port is picked by the
+        // system
+        let addr = match listeners.first() {
+            Some(first) => first.local_addr()?,
+            None => addr,
+        };
+
+        #[cfg(unix)]
+        if let Some(listener) = crate::systemd::inherited_listener(addr)? {
+            let tcp_listener = tokio::net::TcpListener::from_std(listener)?;
+            tcp_listener.set_listener_common_sock_opts()?;
+            listeners.push(tcp_listener);
+            continue;
+        }
+
+        let socket = socket2::Socket::new(
+            socket2::Domain::for_address(addr),
+            socket2::Type::STREAM,
+            Some(socket2::Protocol::TCP),
+        )
+        .context("Failed to create socket")?;
+        socket
+            .set_nonblocking(true)
+            .context("Failed to set nonblocking on socket")?;
+        // The same as tokio::net::TcpListener::bind()
+        #[cfg(unix)]
+        socket.set_reuse_address(true)?;
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if listener.acceptors > 1 {
+            nix::sys::socket::setsockopt(&socket, nix::sys::socket::sockopt::ReusePort, &true)
+                .context("Failed to set SO_REUSEPORT on socket")?;
+        }
+        socket
+            .bind(&addr.into())
+            .with_context(|| format!("Failed to bind on {addr}"))?;
+        socket
+            .listen(listener.backlog())
+            .with_context(|| format!("Failed to listen on {addr}"))?;
+
+        let tcp_listener = tokio::net::TcpListener::from_std(socket.into())?;
+        tcp_listener.set_listener_common_sock_opts()?;
+        listeners.push(tcp_listener);
+    }
+
+    Ok(listeners)
+}
+
 #[cfg(unix)]
 pub fn set_tcp_common_sock_opts(as_fs: impl std::os::fd::AsFd) -> Result<()> {
     let fd = as_fs.as_fd();
@@ -113,12 +180,14 @@ pub fn set_tcp_common_sock_opts(as_fs: impl std::os::fd::AsFd) -> Result<()> {
 
 #[cfg(windows)]
 pub fn set_tcp_common_sock_opts(socket: &socket2::Socket) -> Result<()> {
-    socket.set_keepalive(true).unwrap_or_else(|e| {
-        tracing::warn!(error = ?e, "set SO_KEEPALIVE failed");
-    });
-
-    // Note: detailed keepalive params (idle/interval/count) are not set on Windows
-    // to avoid platform-specific socket option dependencies.
+    // Enable SO_KEEPALIVE, with the same idle time as on unix. The probe interval and count are
+    // left to the system defaults.
+    let keepalive = socket2::TcpKeepalive::new().with_time(std::time::Duration::from_secs(
+        TCP_KEEPALIVE_IDLE_SECS.into(),
+    ));
+    if let Err(error) = socket.set_tcp_keepalive(&keepalive) {
+        tracing::warn!(?error, "set SO_KEEPALIVE failed")
+    }
 
     Ok(())
 }
@@ -137,6 +206,22 @@ where
         .await
         .context("Failed to resolve via dns")?;
 
+    tcp_connect_addrs(
+        addrs,
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        so_mark,
+    )
+    .await
+}
+
+/// Connects to the first of `addrs` which can be connected, in order.
+#[cfg(not(wasm))]
+pub async fn tcp_connect_addrs(
+    addrs: impl IntoIterator<Item = SocketAddr>,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    #[rustfmt::skip]
+    so_mark: Option<u32>,
+) -> Result<TcpStream> {
     let mut last_result = None;
     for addr in addrs {
         tracing::debug!(?addr, "Trying to tcp connect");
@@ -0,0 +1,34 @@
+use std::net::IpAddr;
+
+use cidr::IpCidr;
+
+/// Returns the address a peer is identified by. Peers connected to a dual-stack listener may
+/// appear as IPv4-mapped IPv6 addresses, which are turned back into the IPv4 ones.
+pub fn canonical_ip(ip: IpAddr) -> IpAddr {
+    ip.to_canonical()
+}
+
+/// Checks whether a peer at `ip` is in any of `allowed_sources`. All the sources are allowed if
+/// `allowed_sources` is empty.
+pub fn is_source_allowed(allowed_sources: &[IpCidr], ip: IpAddr) -> bool {
+    let ip = canonical_ip(ip);
+    allowed_sources.is_empty() || allowed_sources.iter().any(|cidr| cidr.contains(&ip))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_is_source_allowed() -> Result<()> {
+        let allowed: Vec<IpCidr> = vec!["127.0.0.0/8".parse()?, "fd00::/8".parse()?];
+        assert!(is_source_allowed(&allowed, "127.0.0.1".parse()?));
+        assert!(is_source_allowed(&allowed, "::ffff:127.0.0.1".parse()?));
+        assert!(is_source_allowed(&allowed, "fd00::1".parse()?));
+        assert!(!is_source_allowed(&allowed, "10.0.0.1".parse()?));
+        assert!(is_source_allowed(&[], "10.0.0.1".parse()?));
+        Ok(())
+    }
+}
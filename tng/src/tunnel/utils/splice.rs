@@ -0,0 +1,187 @@
+//! Forwarding between two TCP sockets with splice(2), which moves the data through a pipe in the
+//! kernel instead of copying it to userspace and back. It is used for the streams which are not
+//! protected by TNG, where the data does not need to be inspected.
+
+use std::io;
+use std::net::Shutdown;
+
+use nix::fcntl::{OFlag, SpliceFFlags};
+use tokio::io::Interest;
+use tokio::net::TcpStream;
+
+use super::forward::ForwardError;
+
+/// The maximum bytes moved by one splice(2) call, which is the default capacity of a pipe.
+const SPLICE_LEN: usize = 64 * 1024;
+
+/// Moves the data from `from` to `to` until `from` reaches EOF, then shuts down the write side of
+/// `to`. `on_spliced` is called with the bytes written to `to` each time.
+///
+/// Returns the bytes moved, or on error the bytes moved, the bytes left in the pipe and the
+/// error, like the userspace copy in [`super::forward`].
+async fn splice_one_direction<RE, WE>(
+    from: &TcpStream,
+    to: &TcpStream,
+    mut on_spliced: impl FnMut(u64),
+    read_err: RE,
+    write_err: WE,
+) -> Result<u64, (u64, u64, ForwardError)>
+where
+    RE: Fn(io::Error) -> ForwardError,
+    WE: Fn(io::Error) -> ForwardError,
+{
+    let (pipe_read, pipe_write) = nix::unistd::pipe2(OFlag::O_NONBLOCK | OFlag::O_CLOEXEC)
+        .map_err(|errno| (0, 0, read_err(errno.into())))?;
+    let flags = SpliceFFlags::SPLICE_F_MOVE | SpliceFFlags::SPLICE_F_NONBLOCK;
+
+    let mut sent = 0u64;
+    loop {
+        // The pipe is always drained before reading again, so it never blocks the read
+        let read = splice_when_ready(from, Interest::READABLE, || {
+            nix::fcntl::splice(from, None, &pipe_write, None, SPLICE_LEN, flags)
+        })
+        .await
+        .map_err(|error| (sent, 0, read_err(error)))?;
+        if read == 0 {
+            break;
+        }
+
+        let mut remain = read;
+        while remain > 0 {
+            let written = splice_when_ready(to, Interest::WRITABLE, || {
+                nix::fcntl::splice(&pipe_read, None, to, None, remain, flags)
+            })
+            .await
+            .map_err(|error| (sent, remain as u64, write_err(error)))?;
+            if written == 0 {
+                return Err((sent, remain as u64, ForwardError::WriteZero));
+            }
+            remain -= written;
+            sent += written as u64;
+            on_spliced(written as u64);
+        }
+    }
+
+    socket2::SockRef::from(to)
+        .shutdown(Shutdown::Write)
+        .map_err(|error| (sent, 0, write_err(error)))?;
+    Ok(sent)
+}
+
+/// Calls `splice` once the socket is ready for `interest`, until it does not block.
+async fn splice_when_ready(
+    socket: &TcpStream,
+    interest: Interest,
+    mut splice: impl FnMut() -> nix::Result<usize>,
+) -> io::Result<usize> {
+    loop {
+        socket.ready(interest).await?;
+        match socket.try_io(interest, || splice().map_err(io::Error::from)) {
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+            result => return result,
+        }
+    }
+}
+
+/// Moves the data between the two sockets in both directions, until both of them reach EOF or
+/// fail. `on_a_to_b` and `on_b_to_a` are called with the bytes moved in each direction.
+///
+/// Returns `(a_to_b_bytes, b_to_a_bytes)`, and like the userspace copy, the errors in either
+/// direction are only logged.
+pub async fn splice_bidirectional(
+    a: &TcpStream,
+    b: &TcpStream,
+    on_a_to_b: impl FnMut(u64),
+    on_b_to_a: impl FnMut(u64),
+) -> (u64, u64) {
+    let (a_to_b, b_to_a) = tokio::join!(
+        splice_one_direction(
+            a,
+            b,
+            on_a_to_b,
+            ForwardError::ReadDownstream,
+            ForwardError::WriteUpstream,
+        ),
+        splice_one_direction(
+            b,
+            a,
+            on_b_to_a,
+            ForwardError::ReadUpstream,
+            ForwardError::WriteDownstream,
+        ),
+    );
+
+    (
+        transferred_bytes(a_to_b, "downstream to upstream"),
+        transferred_bytes(b_to_a, "upstream to downstream"),
+    )
+}
+
+/// Logs the error of the transfer in one direction, if any, and returns the bytes moved.
+pub(super) fn transferred_bytes(
+    result: Result<u64, (u64, u64, ForwardError)>,
+    direction: &str,
+) -> u64 {
+    match result {
+        Ok(n) => n,
+        Err((sent, remain, error)) => {
+            if remain > 0 {
+                tracing::error!(?error, sent, remain, "{direction} transfer lost data");
+            } else {
+                tracing::debug!(?error, sent, "{direction} transfer completed with error");
+            }
+            sent
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    async fn tcp_pair() -> io::Result<(TcpStream, TcpStream)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let connect = TcpStream::connect(listener.local_addr()?);
+        let (accepted, connected) = tokio::join!(listener.accept(), connect);
+        Ok((accepted?.0, connected?))
+    }
+
+    #[tokio::test]
+    async fn test_splice_bidirectional() -> anyhow::Result<()> {
+        let (mut client, downstream) = tcp_pair().await?;
+        let (upstream, mut server) = tcp_pair().await?;
+
+        let data = (0..1024 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+        let client_task = async {
+            client.write_all(&data).await?;
+            client.shutdown().await?;
+            let mut received = vec![];
+            client.read_to_end(&mut received).await?;
+            Ok::<_, io::Error>(received)
+        };
+        let server_task = async {
+            let mut received = vec![];
+            server.read_to_end(&mut received).await?;
+            server.write_all(b"pong").await?;
+            server.shutdown().await?;
+            Ok::<_, io::Error>(received)
+        };
+
+        let mut tx = 0;
+        let mut rx = 0;
+        let (client_received, server_received, (a_to_b, b_to_a)) = tokio::join!(
+            client_task,
+            server_task,
+            splice_bidirectional(&downstream, &upstream, |n| tx += n, |n| rx += n),
+        );
+
+        assert_eq!(server_received?, data);
+        assert_eq!(client_received?, b"pong");
+        assert_eq!((a_to_b, b_to_a), (data.len() as u64, 4));
+        assert_eq!((tx, rx), (a_to_b, b_to_a));
+        Ok(())
+    }
+}
@@ -0,0 +1,497 @@
+//! Forwarding between two TCP sockets with splice(2) submitted through io_uring, which saves the
+//! syscalls spent on waiting for readiness in [`super::splice`] on egresses with a very high
+//! connection rate. The transfers of all the streams are driven by one thread, which is started
+//! on first use.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::Shutdown;
+use std::os::fd::{AsFd as _, AsRawFd as _, OwnedFd, RawFd};
+use std::sync::{mpsc as std_mpsc, Arc, OnceLock};
+
+use anyhow::Context as _;
+use io_uring::{opcode, squeue, types, IoUring};
+use nix::fcntl::{FcntlArg, OFlag};
+use nix::libc;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+use super::forward::ForwardError;
+use super::splice::transferred_bytes;
+
+/// The size of the submission queue. It bounds the operations submitted at once, not the number
+/// of transfers.
+const RING_ENTRIES: u32 = 1024;
+
+/// The maximum bytes moved by one splice(2), which is the default capacity of a pipe.
+const SPLICE_LEN: u32 = 64 * 1024;
+
+/// The user data of the read on the wake-up pipe, which is completed when transfers are queued.
+const WAKE_UP: u64 = u64::MAX;
+
+/// The bytes moved, or on error the bytes moved, the bytes left in the pipe and the error.
+type TransferResult = Result<u64, (u64, u64, ForwardError)>;
+
+enum TransferEvent {
+    /// The bytes written to the destination.
+    Spliced(u64),
+    Finished(TransferResult),
+}
+
+#[derive(Clone, Copy)]
+enum TransferState {
+    Reading,
+    Writing,
+    WaitingReadable,
+    WaitingWritable,
+}
+
+/// The transfer in one direction, from `from` to `to` through a pipe. It has at most one
+/// operation in flight.
+struct Transfer {
+    from: Arc<OwnedFd>,
+    to: Arc<OwnedFd>,
+    pipe_read: OwnedFd,
+    pipe_write: OwnedFd,
+    state: TransferState,
+    /// The bytes read into the pipe and not yet written to `to`.
+    in_pipe: u32,
+    sent: u64,
+    read_err: fn(io::Error) -> ForwardError,
+    write_err: fn(io::Error) -> ForwardError,
+    events: mpsc::UnboundedSender<TransferEvent>,
+}
+
+/// What the driver does after an operation of a transfer is completed.
+enum Step {
+    Submit(squeue::Entry),
+    Finish(TransferResult),
+}
+
+impl Transfer {
+    fn new(
+        from: Arc<OwnedFd>,
+        to: Arc<OwnedFd>,
+        read_err: fn(io::Error) -> ForwardError,
+        write_err: fn(io::Error) -> ForwardError,
+    ) -> io::Result<(Self, mpsc::UnboundedReceiver<TransferEvent>)> {
+        let (pipe_read, pipe_write) = nix::unistd::pipe2(OFlag::O_CLOEXEC)?;
+        let (events, receiver) = mpsc::unbounded_channel();
+        let transfer = Self {
+            from,
+            to,
+            pipe_read,
+            pipe_write,
+            state: TransferState::Reading,
+            in_pipe: 0,
+            sent: 0,
+            read_err,
+            write_err,
+            events,
+        };
+        Ok((transfer, receiver))
+    }
+
+    fn read(&mut self) -> Step {
+        self.state = TransferState::Reading;
+        let entry = opcode::Splice::new(
+            types::Fd(self.from.as_raw_fd()),
+            -1,
+            types::Fd(self.pipe_write.as_raw_fd()),
+            -1,
+            SPLICE_LEN,
+        )
+        .flags(libc::SPLICE_F_MOVE)
+        .build();
+        Step::Submit(entry)
+    }
+
+    fn write(&mut self) -> Step {
+        self.state = TransferState::Writing;
+        let entry = opcode::Splice::new(
+            types::Fd(self.pipe_read.as_raw_fd()),
+            -1,
+            types::Fd(self.to.as_raw_fd()),
+            -1,
+            self.in_pipe,
+        )
+        .flags(libc::SPLICE_F_MOVE)
+        .build();
+        Step::Submit(entry)
+    }
+
+    /// Waits for `fd` to be ready for `events`, since the last operation on it would block.
+    fn wait(&mut self, state: TransferState, fd: RawFd, events: libc::c_short) -> Step {
+        self.state = state;
+        Step::Submit(opcode::PollAdd::new(types::Fd(fd), events as u32).build())
+    }
+
+    fn on_completed(&mut self, result: i32) -> Step {
+        let result = if result < 0 {
+            Err(io::Error::from_raw_os_error(-result))
+        } else {
+            Ok(result as u32)
+        };
+
+        match self.state {
+            TransferState::Reading => match result {
+                Ok(0) => Step::Finish(self.shutdown_write()),
+                Ok(read) => {
+                    self.in_pipe = read;
+                    self.write()
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    let fd = self.from.as_raw_fd();
+                    self.wait(TransferState::WaitingReadable, fd, libc::POLLIN)
+                }
+                Err(error) => Step::Finish(Err((self.sent, 0, (self.read_err)(error)))),
+            },
+            TransferState::Writing => match result {
+                Ok(0) => Step::Finish(Err((
+                    self.sent,
+                    self.in_pipe as u64,
+                    ForwardError::WriteZero,
+                ))),
+                Ok(written) => {
+                    self.in_pipe -= written;
+                    self.sent += written as u64;
+                    let _ = self.events.send(TransferEvent::Spliced(written as u64));
+                    if self.in_pipe > 0 {
+                        self.write()
+                    } else {
+                        self.read()
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    let fd = self.to.as_raw_fd();
+                    self.wait(TransferState::WaitingWritable, fd, libc::POLLOUT)
+                }
+                Err(error) => Step::Finish(Err((
+                    self.sent,
+                    self.in_pipe as u64,
+                    (self.write_err)(error),
+                ))),
+            },
+            TransferState::WaitingReadable => match result {
+                Ok(_) => self.read(),
+                Err(error) => Step::Finish(Err((self.sent, 0, (self.read_err)(error)))),
+            },
+            TransferState::WaitingWritable => match result {
+                Ok(_) => self.write(),
+                Err(error) => Step::Finish(Err((
+                    self.sent,
+                    self.in_pipe as u64,
+                    (self.write_err)(error),
+                ))),
+            },
+        }
+    }
+
+    fn shutdown_write(&self) -> TransferResult {
+        socket2::SockRef::from(&*self.to)
+            .shutdown(Shutdown::Write)
+            .map_err(|error| (self.sent, 0, (self.write_err)(error)))?;
+        Ok(self.sent)
+    }
+}
+
+/// The thread which submits the operations of all the transfers and handles their completions.
+struct Driver {
+    ring: IoUring,
+    transfers: HashMap<u64, Transfer>,
+    next_id: u64,
+    queued: std_mpsc::Receiver<[Transfer; 2]>,
+    wake_read: OwnedFd,
+    wake_buf: Box<[u8; 64]>,
+}
+
+impl Driver {
+    fn run(mut self) {
+        if let Err(error) = self.run_loop() {
+            tracing::error!(?error, "The io_uring driver exited");
+        }
+        // The transfers left are failed when their event senders are dropped here
+    }
+
+    fn run_loop(&mut self) -> io::Result<()> {
+        self.wake_up_on_queued()?;
+        loop {
+            match self.ring.submit_and_wait(1) {
+                Ok(_) => {}
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+
+            let completed = self
+                .ring
+                .completion()
+                .map(|cqe| (cqe.user_data(), cqe.result()))
+                .collect::<Vec<_>>();
+            for (user_data, result) in completed {
+                if user_data != WAKE_UP {
+                    let Some(transfer) = self.transfers.get_mut(&user_data) else {
+                        continue;
+                    };
+                    let step = transfer.on_completed(result);
+                    self.step(user_data, step)?;
+                    continue;
+                }
+
+                match result {
+                    // The forwarder is dropped
+                    0 => return Ok(()),
+                    result if result < 0 => return Err(io::Error::from_raw_os_error(-result)),
+                    _ => {
+                        self.start_queued()?;
+                        self.wake_up_on_queued()?;
+                    }
+                }
+            }
+        }
+    }
+
+    fn wake_up_on_queued(&mut self) -> io::Result<()> {
+        let entry = opcode::Read::new(
+            types::Fd(self.wake_read.as_raw_fd()),
+            self.wake_buf.as_mut_ptr(),
+            self.wake_buf.len() as u32,
+        )
+        .build()
+        .user_data(WAKE_UP);
+        self.push(entry)
+    }
+
+    fn start_queued(&mut self) -> io::Result<()> {
+        while let Ok(transfers) = self.queued.try_recv() {
+            for mut transfer in transfers {
+                let id = self.next_id;
+                self.next_id += 1;
+                let step = transfer.read();
+                self.transfers.insert(id, transfer);
+                self.step(id, step)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, id: u64, step: Step) -> io::Result<()> {
+        match step {
+            Step::Submit(entry) => self.push(entry.user_data(id)),
+            Step::Finish(result) => {
+                if let Some(transfer) = self.transfers.remove(&id) {
+                    let _ = transfer.events.send(TransferEvent::Finished(result));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn push(&mut self, entry: squeue::Entry) -> io::Result<()> {
+        loop {
+            // SAFETY: the file descriptors and the buffer of the operations are owned by the
+            // driver, and a transfer is only dropped once its operation in flight is completed
+            if unsafe { self.ring.submission().push(&entry) }.is_ok() {
+                return Ok(());
+            }
+            // The submission queue is full
+            self.ring.submit()?;
+        }
+    }
+}
+
+/// Shuts down the sockets when dropped before being disarmed, so that the transfers of a
+/// cancelled forwarding are stopped by the driver too.
+struct ShutdownOnDrop<'a> {
+    sockets: [&'a TcpStream; 2],
+    armed: bool,
+}
+
+impl Drop for ShutdownOnDrop<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            for socket in self.sockets {
+                let _ = socket2::SockRef::from(socket).shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
+
+/// The handle to the io_uring driver thread.
+pub struct UringForwarder {
+    queued: std_mpsc::Sender<[Transfer; 2]>,
+    wake_write: OwnedFd,
+}
+
+static FORWARDER: OnceLock<Option<UringForwarder>> = OnceLock::new();
+
+impl UringForwarder {
+    /// Returns the forwarder, starting its driver thread on first use, or `None` if io_uring is
+    /// not available, e.g. on older kernels or when it is blocked by seccomp.
+    pub fn get() -> Option<&'static Self> {
+        FORWARDER
+            .get_or_init(|| match Self::start() {
+                Ok(forwarder) => Some(forwarder),
+                Err(error) => {
+                    tracing::warn!(?error, "io_uring is not available, falling back to epoll");
+                    None
+                }
+            })
+            .as_ref()
+    }
+
+    fn start() -> anyhow::Result<Self> {
+        let ring = IoUring::new(RING_ENTRIES).context("Failed to create the io_uring instance")?;
+        let (wake_read, wake_write) =
+            nix::unistd::pipe2(OFlag::O_CLOEXEC).context("Failed to create the wake-up pipe")?;
+        // A write to a full pipe can be dropped, since the driver is woken up by it anyway
+        nix::fcntl::fcntl(&wake_write, FcntlArg::F_SETFL(OFlag::O_NONBLOCK))
+            .context("Failed to set the wake-up pipe to non-blocking")?;
+
+        let (queued, receiver) = std_mpsc::channel();
+        let driver = Driver {
+            ring,
+            transfers: HashMap::new(),
+            next_id: 0,
+            queued: receiver,
+            wake_read,
+            wake_buf: Box::new([0; 64]),
+        };
+        std::thread::Builder::new()
+            .name("tng-io-uring".to_owned())
+            .spawn(move || driver.run())
+            .context("Failed to start the io_uring driver thread")?;
+
+        Ok(Self { queued, wake_write })
+    }
+
+    /// Moves the data between the two sockets in both directions, like
+    /// [`super::splice::splice_bidirectional`], but with the operations submitted by the driver
+    /// thread.
+    ///
+    /// Fails only if the transfers can not be started, before any data is moved.
+    pub async fn splice_bidirectional(
+        &self,
+        a: &TcpStream,
+        b: &TcpStream,
+        mut on_a_to_b: impl FnMut(u64),
+        mut on_b_to_a: impl FnMut(u64),
+    ) -> io::Result<(u64, u64)> {
+        // The driver works on duplicates of the sockets, which are kept open until the transfers
+        // are finished
+        let a_fd = Arc::new(a.as_fd().try_clone_to_owned()?);
+        let b_fd = Arc::new(b.as_fd().try_clone_to_owned()?);
+        let (a_to_b, a_to_b_events) = Transfer::new(
+            a_fd.clone(),
+            b_fd.clone(),
+            ForwardError::ReadDownstream,
+            ForwardError::WriteUpstream,
+        )?;
+        let (b_to_a, b_to_a_events) = Transfer::new(
+            b_fd,
+            a_fd,
+            ForwardError::ReadUpstream,
+            ForwardError::WriteDownstream,
+        )?;
+
+        self.queued
+            .send([a_to_b, b_to_a])
+            .map_err(|_| io::Error::other("The io_uring driver has exited"))?;
+        // The driver drains all the transfers queued once woken up, so the write is allowed to
+        // fail when the pipe is full
+        let _ = nix::unistd::write(&self.wake_write, &[0]);
+
+        let mut shutdown_on_drop = ShutdownOnDrop {
+            sockets: [a, b],
+            armed: true,
+        };
+        let (a_to_b, b_to_a) = tokio::join!(
+            wait_transfer(a_to_b_events, &mut on_a_to_b, ForwardError::ReadDownstream),
+            wait_transfer(b_to_a_events, &mut on_b_to_a, ForwardError::ReadUpstream),
+        );
+        shutdown_on_drop.armed = false;
+
+        Ok((
+            transferred_bytes(a_to_b, "downstream to upstream"),
+            transferred_bytes(b_to_a, "upstream to downstream"),
+        ))
+    }
+}
+
+async fn wait_transfer(
+    mut events: mpsc::UnboundedReceiver<TransferEvent>,
+    on_spliced: &mut impl FnMut(u64),
+    read_err: fn(io::Error) -> ForwardError,
+) -> TransferResult {
+    let mut sent = 0;
+    while let Some(event) = events.recv().await {
+        match event {
+            TransferEvent::Spliced(n) => {
+                sent += n;
+                on_spliced(n);
+            }
+            TransferEvent::Finished(result) => return result,
+        }
+    }
+    Err((
+        sent,
+        0,
+        read_err(io::Error::other("The io_uring driver has exited")),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    async fn tcp_pair() -> io::Result<(TcpStream, TcpStream)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let connect = TcpStream::connect(listener.local_addr()?);
+        let (accepted, connected) = tokio::join!(listener.accept(), connect);
+        Ok((accepted?.0, connected?))
+    }
+
+    #[tokio::test]
+    async fn test_uring_splice_bidirectional() -> anyhow::Result<()> {
+        let Some(forwarder) = UringForwarder::get() else {
+            // io_uring is not available in this environment
+            return Ok(());
+        };
+
+        let (mut client, downstream) = tcp_pair().await?;
+        let (upstream, mut server) = tcp_pair().await?;
+
+        let data = (0..1024 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+        let client_task = async {
+            client.write_all(&data).await?;
+            client.shutdown().await?;
+            let mut received = vec![];
+            client.read_to_end(&mut received).await?;
+            Ok::<_, io::Error>(received)
+        };
+        let server_task = async {
+            let mut received = vec![];
+            server.read_to_end(&mut received).await?;
+            server.write_all(b"pong").await?;
+            server.shutdown().await?;
+            Ok::<_, io::Error>(received)
+        };
+
+        let mut tx = 0;
+        let mut rx = 0;
+        let (client_received, server_received, transferred) = tokio::join!(
+            client_task,
+            server_task,
+            forwarder.splice_bidirectional(&downstream, &upstream, |n| tx += n, |n| rx += n),
+        );
+
+        assert_eq!(server_received?, data);
+        assert_eq!(client_received?, b"pong");
+        let (a_to_b, b_to_a) = transferred?;
+        assert_eq!((a_to_b, b_to_a), (data.len() as u64, 4));
+        assert_eq!((tx, rx), (a_to_b, b_to_a));
+        Ok(())
+    }
+}